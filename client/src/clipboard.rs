@@ -0,0 +1,47 @@
+#[cfg(feature = "clipboard")]
+use std::sync::Mutex;
+
+#[cfg(feature = "clipboard")]
+use arboard::Clipboard;
+use shared::LobbyId;
+
+/// opened once and kept for as long as the client runs, since on some platforms copied text is
+/// only available while the clipboard that copied it is still open.
+#[cfg(feature = "clipboard")]
+static CLIPBOARD: Mutex<Option<Clipboard>> = Mutex::new(None);
+
+/// runs `f` with the system clipboard, unless it can't be opened.
+#[cfg(feature = "clipboard")]
+fn with_clipboard<T>(f: impl FnOnce(&mut Clipboard) -> Option<T>) -> Option<T> {
+    let mut clipboard = CLIPBOARD.lock().unwrap();
+    if clipboard.is_none() {
+        *clipboard = Clipboard::new().ok();
+    }
+    clipboard.as_mut().and_then(f)
+}
+
+/// copies a lobby id to the system clipboard, so that it can be pasted to whoever will join,
+/// returning whether it was copied.
+#[cfg(feature = "clipboard")]
+pub(crate) fn copy_lobby_id(lobby_id: LobbyId) -> bool {
+    with_clipboard(|clipboard| clipboard.set_text(lobby_id.as_str()).ok()).is_some()
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub(crate) fn copy_lobby_id(_lobby_id: LobbyId) -> bool {
+    false
+}
+
+/// the lobby id on the system clipboard, if the clipboard holds one.
+#[cfg(feature = "clipboard")]
+pub(crate) fn paste_lobby_id() -> Option<LobbyId> {
+    with_clipboard(|clipboard| clipboard.get_text().ok())?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub(crate) fn paste_lobby_id() -> Option<LobbyId> {
+    None
+}
@@ -0,0 +1,221 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crossterm::event::KeyCode;
+use shared::cosmetics::{Cosmetics, PaddleColour};
+use toml::{Table, Value};
+
+/// the defaults for the client's options, read from `~/.config/pong/config.toml` if it exists,
+/// which the command line adds to. the file can also hold named profiles, e.g. `[profiles.work]`,
+/// whose settings take the place of the file's own when the profile is chosen.
+#[derive(Default)]
+pub(crate) struct ClientConfig {
+    /// the server to play on, instead of the default one. `PONG_SERVER_ADDR` takes its place if
+    /// it's set.
+    pub(crate) server_address: Option<String>,
+    pub(crate) mouse: bool,
+    pub(crate) portrait: bool,
+    pub(crate) checksums: bool,
+    pub(crate) keys: KeyBindings,
+    /// how the player's paddle is drawn, which their opponents see too.
+    pub(crate) cosmetics: Cosmetics,
+}
+
+/// the keys that control the game, which are looked for before any others.
+pub(crate) struct KeyBindings {
+    pub(crate) up: KeyCode,
+    pub(crate) down: KeyCode,
+    pub(crate) ready: KeyCode,
+    pub(crate) resign: KeyCode,
+    pub(crate) cancel: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            up: KeyCode::Up,
+            down: KeyCode::Down,
+            ready: KeyCode::Char('r'),
+            resign: KeyCode::Char('q'),
+            cancel: KeyCode::Esc,
+        }
+    }
+}
+
+impl ClientConfig {
+    /// reads the given config file, or the default one if there is one, and the defaults
+    /// otherwise, with the settings of the given profile.
+    pub(crate) fn load(config_file: Option<&Path>, profile: Option<&str>) -> Result<Self, String> {
+        let path = match config_file {
+            Some(path) => path.to_owned(),
+            None => match default_config_file() {
+                Some(path) if path.exists() => path,
+                _ => {
+                    return match profile {
+                        Some(profile) => Err(format!("no config file for profile {profile}")),
+                        None => Ok(Self::default()),
+                    }
+                }
+            },
+        };
+        let file = fs::read_to_string(&path)
+            .map_err(|err| format!("failed to read config file {path:?}: {err}"))?;
+        Self::parse(&file, profile).map_err(|err| format!("invalid config file {path:?}: {err}"))
+    }
+
+    fn parse(file: &str, profile: Option<&str>) -> Result<Self, String> {
+        let mut table: Table = file
+            .parse()
+            .map_err(|err: toml::de::Error| err.message().to_owned())?;
+        let mut profiles = match table.remove("profiles") {
+            Some(Value::Table(profiles)) => profiles,
+            Some(_) => return Err("profiles must be a table".to_owned()),
+            None => Table::new(),
+        };
+        let mut config = Self::default();
+        config.apply(table)?;
+        // every profile is checked, so that a mistake in one is found before it's needed.
+        for (name, settings) in &profiles {
+            let Value::Table(settings) = settings else {
+                return Err(format!("profile {name} must be a table"));
+            };
+            Self::default()
+                .apply(settings.clone())
+                .map_err(|err| format!("in profile {name}: {err}"))?;
+        }
+        if let Some(profile) = profile {
+            let Some(Value::Table(settings)) = profiles.remove(profile) else {
+                return Err(format!("no profile named {profile}"));
+            };
+            config.apply(settings)?;
+        }
+        Ok(config)
+    }
+
+    /// sets whatever the table has a setting for, leaving the rest as they are.
+    fn apply(&mut self, table: Table) -> Result<(), String> {
+        for (key, value) in table {
+            match key.as_str() {
+                "server_addr" => self.server_address = Some(string(&key, value)?),
+                "mouse" => self.mouse = bool(&key, value)?,
+                "portrait" => self.portrait = bool(&key, value)?,
+                "checksums" => self.checksums = bool(&key, value)?,
+                "paddle_glyph" => {
+                    let glyph = string(&key, value)?;
+                    let mut chars = glyph.chars();
+                    self.cosmetics.glyph = match (chars.next(), chars.next()) {
+                        (Some(c), None) if Cosmetics::is_valid_glyph(c) => Some(c),
+                        _ => return Err(format!("{key} must be a single printable character")),
+                    };
+                }
+                "paddle_colour" => {
+                    let colour = string(&key, value)?;
+                    self.cosmetics.colour = Some(
+                        colour
+                            .parse::<PaddleColour>()
+                            .map_err(|()| format!("unknown {key} {colour}"))?,
+                    );
+                }
+                "keys" => {
+                    let Value::Table(keys) = value else {
+                        return Err("keys must be a table".to_owned());
+                    };
+                    self.keys.apply(keys)?;
+                }
+                _ => return Err(format!("unknown setting {key}")),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl KeyBindings {
+    fn apply(&mut self, table: Table) -> Result<(), String> {
+        for (action, value) in table {
+            let key = parse_key(&string(&action, value)?)
+                .ok_or_else(|| format!("unknown key for {action}"))?;
+            match action.as_str() {
+                "up" => self.up = key,
+                "down" => self.down = key,
+                "ready" => self.ready = key,
+                "resign" => self.resign = key,
+                "cancel" => self.cancel = key,
+                _ => return Err(format!("unknown key binding {action}")),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `$XDG_CONFIG_HOME/pong/config.toml`, which is in `~/.config` unless it's set.
+fn default_config_file() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+            PathBuf::from(home).join(".config")
+        }
+    };
+    Some(config_dir.join("pong").join("config.toml"))
+}
+
+fn string(key: &str, value: Value) -> Result<String, String> {
+    match value {
+        Value::String(value) => Ok(value),
+        _ => Err(format!("{key} must be a string")),
+    }
+}
+
+fn bool(key: &str, value: Value) -> Result<bool, String> {
+    match value {
+        Value::Boolean(value) => Ok(value),
+        _ => Err(format!("{key} must be true or false")),
+    }
+}
+
+/// a key by the name it's given in the config file: a single character, or one of the named keys.
+fn parse_key(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    Some(match name {
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "space" => KeyCode::Char(' '),
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        _ => return None,
+    })
+}
+
+/// what a key is called in hints on screen, e.g. "'r'" or "enter".
+pub(crate) fn describe_key(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "space".to_owned(),
+        KeyCode::Char(c) => format!("'{c}'"),
+        KeyCode::Up => "up".to_owned(),
+        KeyCode::Down => "down".to_owned(),
+        KeyCode::Left => "left".to_owned(),
+        KeyCode::Right => "right".to_owned(),
+        KeyCode::Enter => "enter".to_owned(),
+        KeyCode::Esc => "esc".to_owned(),
+        KeyCode::Tab => "tab".to_owned(),
+        KeyCode::Backspace => "backspace".to_owned(),
+        KeyCode::PageUp => "page up".to_owned(),
+        KeyCode::PageDown => "page down".to_owned(),
+        KeyCode::Home => "home".to_owned(),
+        KeyCode::End => "end".to_owned(),
+        _ => format!("{key:?}").to_lowercase(),
+    }
+}
@@ -0,0 +1,73 @@
+use std::sync::mpsc::Sender;
+
+#[cfg(feature = "gamepad")]
+use std::thread::Builder;
+
+#[cfg(feature = "gamepad")]
+use gilrs::{Axis, Button, EventType, Gilrs};
+
+use crate::tcp_client::MoveKey;
+
+/// how far the stick has to be pushed up or down before it moves the paddle.
+#[cfg(feature = "gamepad")]
+const STICK_DEAD_ZONE: f32 = 0.5;
+
+/// listens to every connected gamepad on its own thread, holding the move keys down while the
+/// d-pad or left stick is held up or down, and pressing the ready key with the south button (A on
+/// most gamepads). does nothing if gamepads can't be read.
+#[cfg(feature = "gamepad")]
+pub(crate) fn spawn_listener(ready_key_tx: Sender<()>, move_key_tx: Sender<MoveKey>) {
+    let Ok(mut gilrs) = Gilrs::new() else {
+        return;
+    };
+    Builder::new()
+        .name("gamepad_listener".to_owned())
+        .spawn(move || {
+            // the move key held down, `true` being down.
+            let mut held = None;
+            loop {
+                let Some(event) = gilrs.next_event_blocking(None) else {
+                    continue;
+                };
+                let now_held = match event.event {
+                    EventType::ButtonPressed(Button::South, _) => {
+                        if ready_key_tx.send(()).is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                    EventType::ButtonPressed(Button::DPadUp, _) => Some(false),
+                    EventType::ButtonPressed(Button::DPadDown, _) => Some(true),
+                    EventType::ButtonReleased(Button::DPadUp | Button::DPadDown, _) => None,
+                    // the stick's y axis points up.
+                    EventType::AxisChanged(Axis::LeftStickY, value, _) => {
+                        if value > STICK_DEAD_ZONE {
+                            Some(false)
+                        } else if value < -STICK_DEAD_ZONE {
+                            Some(true)
+                        } else {
+                            None
+                        }
+                    }
+                    EventType::Disconnected => None,
+                    _ => continue,
+                };
+                if now_held == held {
+                    continue;
+                }
+                let released = held.map(MoveKey::Released);
+                held = now_held;
+                let moves = released.into_iter().chain(held.map(MoveKey::Held));
+                if moves
+                    .map(|key| move_key_tx.send(key))
+                    .any(|sent| sent.is_err())
+                {
+                    break;
+                }
+            }
+        })
+        .unwrap();
+}
+
+#[cfg(not(feature = "gamepad"))]
+pub(crate) fn spawn_listener(_ready_key_tx: Sender<()>, _move_key_tx: Sender<MoveKey>) {}
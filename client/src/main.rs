@@ -1,36 +1,268 @@
 use std::{
     io::stdout,
+    path::PathBuf,
     sync::mpsc::channel,
     thread::{spawn, Builder},
 };
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use config::{describe_key, ClientConfig};
 use crossterm::{
     cursor,
-    event::{self, Event, KeyCode, KeyModifiers},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+        KeyModifiers, KeyboardEnhancementFlags, MouseEventKind, PopKeyboardEnhancementFlags,
+        PushKeyboardEnhancementFlags,
+    },
     execute,
     terminal::{self, disable_raw_mode, enable_raw_mode},
 };
-use tcp_client::TcpClient;
+use shared::{
+    cosmetics::Cosmetics,
+    game_state::{
+        Handicap, Handicaps, Modifier, FAST_TICK_INTERVAL_MS, MAX_PADDLE_CELLS_PER_TICK,
+        MAX_TICK_INTERVAL_MS, MIN_TICK_INTERVAL_MS, NORMAL_TICK_INTERVAL_MS, SLOW_TICK_INTERVAL_MS,
+    },
+    session::ServerStatus,
+    tournament::{is_valid_league_size, is_valid_tournament_size, MAX_TOURNAMENT_PLAYERS},
+    LobbyId, MAX_U14,
+};
+use tcp_client::{BrowseKey, MoveKey, TcpClient};
 
+mod clipboard;
+mod config;
+mod gamepad;
 mod tcp_client;
 
 #[derive(Parser)]
 struct Cli {
     #[command(subcommand)]
-    command: Start,
+    command: Command,
+    /// Move your paddle by moving the mouse up and down, or scrolling, as well as with the arrow
+    /// keys
+    #[arg(long, global = true)]
+    mouse: bool,
+    /// Draw the field on its side, for terminals taller than they are wide, with your paddle moved
+    /// by the left and right arrow keys
+    #[arg(long, global = true)]
+    portrait: bool,
+    /// End every message to and from the server with a checksum, so that one corrupted on its way
+    /// is caught rather than misread
+    #[arg(long, global = true)]
+    checksums: bool,
+    /// The config file to read the defaults for these options, the server to play on and the
+    /// keys from, instead of ~/.config/pong/config.toml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// A profile from the config file, e.g. work, whose settings take the place of the file's
+    /// own
+    #[arg(long, global = true)]
+    profile: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(flatten)]
+    Start(Start),
+    /// Check whether a server is up, and how busy it is
+    Status {
+        /// The server to ask, instead of the one games are played on
+        #[arg(long)]
+        server: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
 enum Start {
     /// Start a new game
-    New,
+    New {
+        /// How fast the game runs: slow, normal, fast, or a tick interval in milliseconds
+        #[arg(long, default_value = "normal", value_parser = parse_tick_rate)]
+        tick_rate: u16,
+        /// A lobby id to use instead of a generated one, e.g. GAME
+        #[arg(long)]
+        id: Option<LobbyId>,
+        #[command(flatten)]
+        handicaps: HandicapArgs,
+        /// How the ball moves: classic, gravity (it falls back down after rising), wobble (its
+        /// path curves) or wrap (it leaves through the top or bottom wall and comes back in
+        /// through the other)
+        #[arg(long, default_value = "classic", value_parser = parse_modifier)]
+        modifier: Modifier,
+        /// The number of games a player must win to take the match, instead of the server's
+        #[arg(long, value_parser = parse_match_length)]
+        match_length: Option<u16>,
+    },
     /// Join an existing game
-    Join { lobby_id: String },
+    Join {
+        /// The id of the game's lobby, or the one on the clipboard if left out and the client was
+        /// built with clipboard support
+        lobby_id: Option<LobbyId>,
+    },
+    /// Play against whoever else is looking for a game
+    Quick,
+    /// Warm up alone against a wall that returns every ball, counting your rally
+    Practice {
+        /// How fast the game runs: slow, normal, fast, or a tick interval in milliseconds
+        #[arg(long, default_value = "normal", value_parser = parse_tick_rate)]
+        tick_rate: u16,
+    },
+    /// Start a co-op game, defending the left side with a partner against the server's paddle,
+    /// which gets faster the more you return the ball
+    Coop {
+        /// How fast the game runs: slow, normal, fast, or a tick interval in milliseconds
+        #[arg(long, default_value = "normal", value_parser = parse_tick_rate)]
+        tick_rate: u16,
+        /// A lobby id to use instead of a generated one, e.g. GAME
+        #[arg(long)]
+        id: Option<LobbyId>,
+    },
+    /// Browse the games waiting for an opponent, and join one
+    Browse,
+    /// Play a single elimination tournament or a round robin league
+    #[command(subcommand)]
+    Tournament(TournamentStart),
+}
+
+/// how each player's paddle differs from the server's, for evening out a game between players of
+/// different skill. the host is the left player.
+#[derive(Args)]
+struct HandicapArgs {
+    /// The height of the left player's paddle, instead of the server's
+    #[arg(long, value_parser = parse_paddle_height)]
+    left_paddle_height: Option<u16>,
+    /// The height of the right player's paddle, instead of the server's
+    #[arg(long, value_parser = parse_paddle_height)]
+    right_paddle_height: Option<u16>,
+    /// How many cells the left player's paddle moves each tick
+    #[arg(long, value_parser = parse_paddle_speed)]
+    left_paddle_speed: Option<u16>,
+    /// How many cells the right player's paddle moves each tick
+    #[arg(long, value_parser = parse_paddle_speed)]
+    right_paddle_speed: Option<u16>,
+}
+
+impl From<HandicapArgs> for Handicaps {
+    fn from(args: HandicapArgs) -> Self {
+        Handicaps {
+            left: Handicap {
+                paddle_height: args.left_paddle_height,
+                paddle_speed: args.left_paddle_speed,
+            },
+            right: Handicap {
+                paddle_height: args.right_paddle_height,
+                paddle_speed: args.right_paddle_speed,
+            },
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum TournamentStart {
+    /// Start a new tournament
+    New {
+        /// How many players the tournament is for: 2, 4 or 8
+        #[arg(long, default_value = "4", value_parser = parse_tournament_size)]
+        players: u8,
+    },
+    /// Start a new league, in which everyone plays everyone once
+    League {
+        /// How many players the league is for, from 2 to 8
+        #[arg(long, default_value = "4", value_parser = parse_league_size)]
+        players: u8,
+    },
+    /// Join an existing tournament or league
+    Join { tournament_id: LobbyId },
+}
+
+fn parse_tournament_size(s: &str) -> Result<u8, String> {
+    match s.parse::<u8>() {
+        Ok(players) if is_valid_tournament_size(players) => Ok(players),
+        _ => Err(format!(
+            "expected a power of two between 2 and {MAX_TOURNAMENT_PLAYERS}"
+        )),
+    }
+}
+
+fn parse_league_size(s: &str) -> Result<u8, String> {
+    match s.parse::<u8>() {
+        Ok(players) if is_valid_league_size(players) => Ok(players),
+        _ => Err(format!(
+            "expected between 2 and {MAX_TOURNAMENT_PLAYERS} players"
+        )),
+    }
+}
+
+fn parse_paddle_height(s: &str) -> Result<u16, String> {
+    match s.parse::<u16>() {
+        Ok(height) if (1..=MAX_U14).contains(&height) => Ok(height),
+        _ => Err(format!("expected a height between 1 and {MAX_U14}")),
+    }
+}
+
+fn parse_paddle_speed(s: &str) -> Result<u16, String> {
+    match s.parse::<u16>() {
+        Ok(speed) if (1..=MAX_PADDLE_CELLS_PER_TICK).contains(&speed) => Ok(speed),
+        _ => Err(format!(
+            "expected a speed between 1 and {MAX_PADDLE_CELLS_PER_TICK}"
+        )),
+    }
+}
+
+fn parse_match_length(s: &str) -> Result<u16, String> {
+    match s.parse::<u16>() {
+        Ok(games) if (1..=MAX_U14).contains(&games) => Ok(games),
+        _ => Err(format!(
+            "expected a number of games between 1 and {MAX_U14}"
+        )),
+    }
+}
+
+fn parse_modifier(s: &str) -> Result<Modifier, String> {
+    match s {
+        "classic" => Ok(Modifier::Classic),
+        "gravity" => Ok(Modifier::Gravity),
+        "wobble" => Ok(Modifier::Wobble),
+        "wrap" => Ok(Modifier::Wrap),
+        _ => Err("expected classic, gravity, wobble or wrap".to_owned()),
+    }
+}
+
+fn parse_tick_rate(s: &str) -> Result<u16, String> {
+    match s {
+        "slow" => Ok(SLOW_TICK_INTERVAL_MS),
+        "normal" => Ok(NORMAL_TICK_INTERVAL_MS),
+        "fast" => Ok(FAST_TICK_INTERVAL_MS),
+        ms => match ms.parse::<u16>() {
+            Ok(ms) if (MIN_TICK_INTERVAL_MS..=MAX_TICK_INTERVAL_MS).contains(&ms) => Ok(ms),
+            _ => Err(format!(
+                "expected slow, normal, fast, or a number of milliseconds between {MIN_TICK_INTERVAL_MS} and {MAX_TICK_INTERVAL_MS}"
+            )),
+        },
+    }
 }
 
 fn main() {
     let cli = Cli::parse();
+    let config = match ClientConfig::load(cli.config.as_deref(), cli.profile.as_deref()) {
+        Ok(config) => config,
+        Err(err) => {
+            println!("error: {err}");
+            return;
+        }
+    };
+    let server_address = server_address(config.server_address);
+    let start = match cli.command {
+        Command::Start(start) => start,
+        Command::Status { server } => {
+            let server_address = server.unwrap_or(server_address);
+            match tcp_client::server_status(&server_address) {
+                Ok(status) => print_server_status(&server_address, status),
+                Err(err) => println!("error: {err}"),
+            }
+            return;
+        }
+    };
     enable_raw_mode().unwrap();
     execute!(
         stdout(),
@@ -39,77 +271,220 @@ fn main() {
         cursor::MoveTo(0, 0)
     )
     .unwrap();
+    // the command line can only turn on what the config file leaves off.
+    let mouse = cli.mouse || config.mouse;
+    if mouse {
+        execute!(stdout(), EnableMouseCapture).unwrap();
+    }
+    // terminals that support it report when keys are released, so that a held move key keeps the
+    // paddle moving without waiting on the operating system's key repeat. windows always does.
+    let keyboard_enhanced = terminal::supports_keyboard_enhancement().unwrap_or(false);
+    if keyboard_enhanced {
+        execute!(
+            stdout(),
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+        )
+        .unwrap();
+    }
+    let key_releases = keyboard_enhanced || cfg!(windows);
+    // the paddles lie across the top and bottom of a field drawn on its side.
+    let portrait = cli.portrait || config.portrait;
+    let checksums = cli.checksums || config.checksums;
+    let keys = config.keys;
+    let ready_key = describe_key(keys.ready);
+    // players who haven't chosen how their paddle looks aren't told how their opponents' do.
+    let cosmetics = (config.cosmetics != Cosmetics::NONE).then_some(config.cosmetics);
     let (game_over_tx, game_over_rx) = channel();
     let (ready_key_tx, ready_key_rx) = channel();
     let (move_key_tx, move_key_rx) = channel();
+    let (resign_key_tx, resign_key_rx) = channel();
+    let (cancel_key_tx, cancel_key_rx) = channel();
+    let (browse_key_tx, browse_key_rx) = channel();
+    let (resize_tx, resize_rx) = channel();
+    gamepad::spawn_listener(ready_key_tx.clone(), move_key_tx.clone());
     let game_over_tx_clone = game_over_tx.clone();
     spawn(move || {
         let game_over_tx = game_over_tx_clone.clone();
-        if let Err(_) = Builder::new()
+        if Builder::new()
             .name("tcp_client".to_owned())
             .spawn(move || {
-                let server_address = std::env::var("PONG_SERVER_ADDR")
-                    .unwrap_or(include_str!("../default_server_addr").to_owned());
                 TcpClient::run(
                     &server_address,
-                    cli.command,
+                    start,
                     game_over_tx,
                     ready_key_rx,
                     move_key_rx,
+                    resign_key_rx,
+                    cancel_key_rx,
+                    browse_key_rx,
+                    resize_rx,
+                    portrait,
+                    checksums,
+                    ready_key,
+                    cosmetics,
                 )
             })
             .unwrap()
             .join()
+            .is_err()
         {
             let _ = game_over_tx_clone.send(Quit::Panic);
         }
     });
     Builder::new()
         .name("terminate_key_listener".to_owned())
-        .spawn(move || loop {
-            let event = event::read().unwrap();
-            if let Event::Key(key_event) = event {
-                if key_event.modifiers == KeyModifiers::CONTROL
-                    && key_event.code == KeyCode::Char('c')
-                {
-                    let _ = game_over_tx.send(Quit::CtrlC);
-                } else if key_event.modifiers == KeyModifiers::NONE {
-                    match key_event.code {
-                        KeyCode::Char('r') => {
-                            let _ = ready_key_tx.send(());
-                        }
-                        KeyCode::Down => {
-                            let _ = move_key_tx.send(true);
+        .spawn(move || {
+            // the row the mouse was last seen on, so that moving it up or down can be told apart.
+            let mut mouse_row = None;
+            loop {
+                match event::read().unwrap() {
+                    // mouse events only arrive when the mouse is captured. like the arrow keys of most
+                    // terminals, each one keeps the paddle moving for a moment.
+                    Event::Mouse(mouse_event) => {
+                        let down = match mouse_event.kind {
+                            MouseEventKind::ScrollDown => Some(true),
+                            MouseEventKind::ScrollUp => Some(false),
+                            MouseEventKind::Moved | MouseEventKind::Drag(_) => mouse_row
+                                .filter(|&row| row != mouse_event.row)
+                                .map(|row| mouse_event.row > row),
+                            _ => None,
+                        };
+                        mouse_row = Some(mouse_event.row);
+                        if let Some(down) = down {
+                            let _ = move_key_tx.send(MoveKey::Repeated(down));
                         }
-                        KeyCode::Up => {
-                            let _ = move_key_tx.send(false);
+                    }
+                    // in portrait mode, the left and right arrow keys move the paddle too.
+                    Event::Key(KeyEvent {
+                        code,
+                        modifiers: KeyModifiers::NONE,
+                        kind,
+                        ..
+                    }) if code == keys.up
+                        || code == keys.down
+                        || portrait && matches!(code, KeyCode::Left | KeyCode::Right) =>
+                    {
+                        let down = code == keys.down || code == KeyCode::Right;
+                        let move_key = match kind {
+                            _ if !key_releases => MoveKey::Repeated(down),
+                            KeyEventKind::Press | KeyEventKind::Repeat => MoveKey::Held(down),
+                            KeyEventKind::Release => MoveKey::Released(down),
+                        };
+                        let _ = move_key_tx.send(move_key);
+                    }
+                    // other keys act once each time they're pressed, however long they're held for.
+                    Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                        if key_event.modifiers == KeyModifiers::CONTROL
+                            && key_event.code == KeyCode::Char('c')
+                        {
+                            let _ = game_over_tx.send(Quit::CtrlC);
+                        } else if key_event.modifiers == KeyModifiers::NONE {
+                            match key_event.code {
+                                code if code == keys.ready => {
+                                    let _ = ready_key_tx.send(());
+                                }
+                                code if code == keys.resign => {
+                                    let _ = resign_key_tx.send(());
+                                }
+                                code if code == keys.cancel => {
+                                    let _ = cancel_key_tx.send(());
+                                }
+                                KeyCode::Left | KeyCode::PageUp => {
+                                    let _ = browse_key_tx.send(BrowseKey::PreviousPage);
+                                }
+                                KeyCode::Right | KeyCode::PageDown => {
+                                    let _ = browse_key_tx.send(BrowseKey::NextPage);
+                                }
+                                KeyCode::Enter => {
+                                    let _ = browse_key_tx.send(BrowseKey::Join);
+                                }
+                                _ => {}
+                            }
                         }
-                        _ => {}
                     }
+                    Event::Resize(..) => {
+                        let _ = resize_tx.send(());
+                    }
+                    _ => {}
                 }
             }
         })
         .unwrap();
     let game_over = game_over_rx.recv().unwrap();
+    if mouse {
+        execute!(stdout(), DisableMouseCapture).unwrap();
+    }
+    if keyboard_enhanced {
+        execute!(stdout(), PopKeyboardEnhancementFlags).unwrap();
+    }
     disable_raw_mode().unwrap();
     execute!(stdout(), terminal::LeaveAlternateScreen, cursor::Show).unwrap();
     match game_over {
         Quit::CtrlC => println!("^C"),
         Quit::Panic => println!("error occurred"),
+        Quit::Error(err) => println!("error: {err}"),
         Quit::LobbyFull => println!("lobby full"),
         Quit::LobbyNotFound => println!("lobby not found"),
-        Quit::YouWon => println!("you won"),
-        Quit::OpponentWon => println!("you lost"),
+        Quit::NoLobbyId => println!("no lobby id given, and none on the clipboard"),
+        Quit::LobbyIncompatible => println!("lobby requires a newer client"),
+        Quit::IdTaken(lobby_id) => println!("lobby id {lobby_id} is taken"),
+        Quit::TooManyLobbies => println!("too many lobbies created, try again later"),
         Quit::OpponentLeft => println!("opponent left"),
+        Quit::LobbyTimedOut => println!("lobby timed out"),
+        Quit::LobbyExpired => println!("lobby expired"),
+        Quit::LobbyCancelled => println!("lobby cancelled"),
+        Quit::TournamentFull => println!("tournament full"),
+        Quit::TournamentNotFound => println!("tournament not found"),
+        Quit::TournamentIncompatible => println!("tournament requires a newer client"),
+        Quit::TournamentWon { you: true, .. } => println!("you won the tournament!"),
+        Quit::TournamentWon { seat, .. } => println!("player {} won the tournament", seat + 1),
     }
 }
 
+/// the server to play on: the one set with `PONG_SERVER_ADDR`, or in the config file, or the
+/// default one.
+fn server_address(config_server_address: Option<String>) -> String {
+    std::env::var("PONG_SERVER_ADDR")
+        .ok()
+        .or(config_server_address)
+        .unwrap_or(include_str!("../default_server_addr").to_owned())
+}
+
+fn print_server_status(server_address: &str, status: ServerStatus) {
+    let uptime_mins = status.uptime_secs / 60;
+    println!(
+        "{server_address} is up (version {}, running for {}d {}h {}m)",
+        status.version,
+        uptime_mins / (60 * 24),
+        uptime_mins / 60 % 24,
+        uptime_mins % 60,
+    );
+    println!(
+        "{} players online, {} open lobbies",
+        status.players_online, status.open_lobbies
+    );
+}
+
 enum Quit {
     CtrlC,
     Panic,
+    Error(String),
     LobbyFull,
     LobbyNotFound,
-    YouWon,
-    OpponentWon,
+    /// no lobby id was given to join, and the clipboard didn't hold one either.
+    NoLobbyId,
+    LobbyIncompatible,
+    IdTaken(LobbyId),
+    TooManyLobbies,
     OpponentLeft,
+    LobbyTimedOut,
+    LobbyExpired,
+    LobbyCancelled,
+    TournamentFull,
+    TournamentNotFound,
+    TournamentIncompatible,
+    TournamentWon {
+        seat: u8,
+        you: bool,
+    },
 }
@@ -1,221 +1,1242 @@
 use std::{
-    error::Error,
-    fmt::Display,
-    io::{stdout, BufRead, BufReader, Stdout, StdoutLock, Write},
+    io::{self, stdout, ErrorKind, Stdout, StdoutLock, Write},
     net::TcpStream,
+    ops::Range,
+    str::FromStr,
     sync::{
-        atomic::{AtomicU8, Ordering},
-        mpsc::{channel, Receiver, Sender, TryRecvError},
-        Arc,
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender, TryRecvError},
+        Arc, Mutex,
     },
-    thread::Builder,
+    thread::{sleep, Builder},
+    time::{Duration, Instant},
 };
 
 use crossterm::{
-    cursor::{MoveDown, MoveLeft, MoveRight, MoveToColumn, MoveToNextLine, MoveUp},
+    cursor::{MoveDown, MoveLeft, MoveRight, MoveTo, MoveToColumn, MoveToNextLine, MoveUp},
     execute,
-    style::{Color, Print, SetForegroundColor},
-    terminal::{Clear, ClearType},
+    style::{style, Color, Print, SetForegroundColor, StyledContent, Stylize},
+    terminal::{self, Clear, ClearType},
 };
 use shared::{
-    client_msg::{AwaitingOpenClientMessage, AwaitingReadyClientMessage, PlayingClientMessage},
-    game_state::{Ball, GAME_HEIGHT, GAME_WIDTH, PADDLE_HEIGHT},
+    client_msg::{
+        AwaitingOpponentJoinClientMessage, AwaitingReadyClientMessage, PlayingClientMessage,
+    },
+    cosmetics::{Cosmetics, PaddleColour},
+    game_state::{
+        ai_speed_level, GameDimensions, GameState, Handicap, Handicaps, Modifier, MoveDirection,
+        Ruleset, Side, FAST_TICK_INTERVAL_MS, MAX_AI_SPEED_LEVEL, MAX_PADDLE_CELLS_PER_TICK,
+        MAX_SPEED_LEVEL, NORMAL_TICK_INTERVAL_MS, SLOW_TICK_INTERVAL_MS,
+    },
+    io::Keepalive,
     server_msg::{
-        AwaitingJoinLobbyServerMessage, AwaitingNewLobbyServerMessage,
-        AwaitingOpponentJoinServerMessage, AwaitingReadyServerMessage, PlayingServerMessage,
-        MAX_SERVER_MESSAGE_SIZE, SERVER_MESSAGE_DELIMITER,
+        AwaitingReadyServerMessage, AwaitingTournamentServerMessage, PlayingServerMessage,
     },
-    DeserializeMessageError,
+    session::{
+        AwaitingNewLobby, AwaitingOpen, AwaitingOpponentJoin, AwaitingReady, ClientSession,
+        JoinLobbyOutcome, LobbyPage, NewLobbyOutcome, OpponentJoinOutcome, Playing, ReceiveError,
+        Resynced, ServerStatus,
+    },
+    tournament::{league_rounds, rounds, Bracket, Standings},
+    DeserializeMessageError, LobbyId,
 };
 
-use crate::{Quit, Start};
+use crate::{clipboard, Quit, Start, TournamentStart};
+
+/// how long to wait for a repeated move key press before assuming the key has been released, if
+/// the key's release won't be reported. must be longer than the interval between the operating
+/// system's key repeats.
+const KEY_RELEASE_TIMEOUT: Duration = Duration::from_millis(150);
+
+/// how often key listeners check whether they should stop, so that their receivers can be handed
+/// back for reuse in the next game.
+const KEY_LISTENER_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// the frames of the spinner shown while waiting for an opponent, and how long each is shown for.
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+const SPINNER_FRAME_MS: u128 = 100;
+
+/// how often a host waiting for an opponent is reminded to share the lobby id, and for how long.
+const SHARE_REMINDER_INTERVAL_SECS: u64 = 30;
+const SHARE_REMINDER_SECS: u64 = 5;
+
+/// how often the connection's health is checked while no game state updates are arriving.
+const CONNECTION_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// how many ticks can pass without a game state update before the connection is shown as
+/// lagging, and how long before the game is shown as frozen. the latter is at least as long as
+/// the server's default serve delay, so that the pause before a co-op game's serve isn't mistaken
+/// for a lost connection.
+const LAGGING_TICKS: u32 = 3;
+const STALLED_TICKS: u32 = 10;
+const STALLED_MIN: Duration = Duration::from_millis(1500);
+
+/// how often the field is redrawn, moving the ball and the other paddles between the positions of
+/// the last two game states.
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// how long the top barrier stays lit, then unlit, while it flashes during a match point.
+const MATCH_POINT_FLASH_INTERVAL: Duration = Duration::from_millis(400);
+
+/// how often the terminal's size is checked while it's too small for the field, for terminals that
+/// don't report being resized.
+const TERMINAL_SIZE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// how long the server has to agree to checksums before it's assumed not to support them.
+const CHECKSUMS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// why the client quits when the server has it in a state other than the one it can carry on from.
+const DESYNCED: &str = "lost track of the game's state";
+
+type Session<S> = ClientSession<S, TcpStream, TcpStream>;
+
+/// a description of a game's result, the session returned to the ready screen, and the move and
+/// resign key receivers.
+type FinishedGame = (
+    &'static str,
+    Session<AwaitingReady>,
+    Receiver<MoveKey>,
+    Receiver<()>,
+);
+
+/// the receivers of the keys pressed during a game and its ready screen, handed back once the
+/// game is over so that they can be reused for the next one.
+struct KeyReceivers {
+    ready: Receiver<()>,
+    moves: Receiver<MoveKey>,
+    resign: Receiver<()>,
+    /// only used while waiting for an opponent, to close the lobby.
+    cancel: Receiver<()>,
+    /// told each time the terminal is resized, while waiting for it to be big enough for the field.
+    resizes: Receiver<()>,
+    /// what the ready key is called, in the hints that say to press it.
+    ready_name: String,
+}
+
+/// keys used only by the lobby browser. moving the selection up and down uses the move keys, and
+/// refreshing the page uses the ready key.
+pub(crate) enum BrowseKey {
+    PreviousPage,
+    NextPage,
+    Join,
+}
+
+/// a move key, or the mouse or a gamepad standing in for one. `true` is down.
+pub(crate) enum MoveKey {
+    /// pressed or repeated by a key whose release won't be reported, so the paddle stops once
+    /// repeats stop arriving.
+    Repeated(bool),
+    /// pressed and held until it's released.
+    Held(bool),
+    Released(bool),
+}
+
+/// how the ready screen was left.
+enum ReadyScreenExit {
+    /// the game started, with the handicaps the host chose for each player's paddle and the
+    /// interval between the game's ticks.
+    GameStarted(Session<Playing>, Handicaps, u16),
+    /// the lobby closed before the game started, which ends a tournament match.
+    LobbyClosed(&'static str, Session<AwaitingReady>),
+    /// the opponent left the lobby, or its host disconnected, and it's waiting for another
+    /// opponent with the player as its host.
+    OpponentLeftLobby(Session<AwaitingOpponentJoin>),
+}
 
 pub struct TcpClient {
-    stream: BufReader<TcpStream>,
-    server_msg_buffer: Vec<u8>,
     is_left_player: bool,
+    /// shown again if the opponent leaves, so that someone else can be invited to join.
+    lobby_id: LobbyId,
+    dimensions: GameDimensions,
+    screen: Screen,
     game_over_tx: Sender<Quit>,
+    /// whether the lobby is a match in a tournament, which is over after one game. an opponent
+    /// leaving or the lobby timing out then returns the player to the tournament, rather than
+    /// quitting.
+    in_tournament: bool,
+    /// whether the lobby is a practice lobby, whose opponent is a wall that is always ready and
+    /// returns every ball. the player's rally is counted instead.
+    practicing: bool,
+    /// whether the lobby is for a co-op survival game, in which the other player is a partner
+    /// defending the left side too, against a paddle steered by the server.
+    coop: bool,
+    /// the rules the lobby is played under, once the server has said what they are.
+    ruleset: Option<Ruleset>,
+    /// how the player chose to have their paddle drawn.
+    cosmetics: Cosmetics,
+    /// how the other player chose to have theirs drawn, once the server has said.
+    opponent_cosmetics: Cosmetics,
+    /// whether the server plays its games in lockstep, waiting for the player's direction every
+    /// tick.
+    lockstep: bool,
 }
 
 impl TcpClient {
-    fn new(stream: TcpStream, is_left_player: bool, game_over_tx: Sender<Quit>) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        is_left_player: bool,
+        lobby_id: LobbyId,
+        dimensions: GameDimensions,
+        game_over_tx: Sender<Quit>,
+        in_tournament: bool,
+        practicing: bool,
+        portrait: bool,
+        cosmetics: Cosmetics,
+    ) -> Self {
         Self {
-            stream: BufReader::with_capacity(MAX_SERVER_MESSAGE_SIZE, stream.try_clone().unwrap()),
-            server_msg_buffer: Vec::with_capacity(MAX_SERVER_MESSAGE_SIZE),
             is_left_player,
+            lobby_id,
+            dimensions,
+            screen: Screen::new(dimensions, portrait),
             game_over_tx,
+            in_tournament,
+            practicing,
+            coop: false,
+            ruleset: None,
+            cosmetics,
+            opponent_cosmetics: Cosmetics::NONE,
+            lockstep: false,
         }
     }
 
+    /// how each paddle on the field is drawn: the player's own and the other player's as they
+    /// chose, and the server's as it always is.
+    fn paddle_looks(&self) -> PaddleLooks {
+        let (own, other) = (self.cosmetics, self.opponent_cosmetics);
+        match (self.coop, self.is_left_player) {
+            (false, true) => PaddleLooks {
+                left: own,
+                right: other,
+                partner: Cosmetics::NONE,
+            },
+            (false, false) => PaddleLooks {
+                left: other,
+                right: own,
+                partner: Cosmetics::NONE,
+            },
+            (true, true) => PaddleLooks {
+                left: own,
+                right: Cosmetics::NONE,
+                partner: other,
+            },
+            (true, false) => PaddleLooks {
+                left: other,
+                right: Cosmetics::NONE,
+                partner: own,
+            },
+        }
+    }
+
+    /// what the other player in the lobby is called on screen.
+    fn other_player(&self) -> &'static str {
+        if self.coop {
+            "partner"
+        } else {
+            "opponent"
+        }
+    }
+
+    /// what the ready screen says about the rules the lobby is played under, once they're known.
+    fn rules(&self) -> Option<String> {
+        if self.practicing {
+            Some("practice against the wall".to_owned())
+        } else if self.coop {
+            Some("co-op survival against the server".to_owned())
+        } else {
+            self.ruleset
+                .map(|ruleset| describe_ruleset(ruleset, self.dimensions, self.is_left_player))
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn run(
         server_addr: &str,
         start: Start,
         game_over_tx: Sender<Quit>,
         ready_key_rx: Receiver<()>,
-        move_key_rx: Receiver<bool>,
+        move_key_rx: Receiver<MoveKey>,
+        resign_key_rx: Receiver<()>,
+        cancel_key_rx: Receiver<()>,
+        browse_key_rx: Receiver<BrowseKey>,
+        resize_rx: Receiver<()>,
+        portrait: bool,
+        checksums: bool,
+        ready_name: String,
+        cosmetics: Option<Cosmetics>,
     ) {
-        let stream = TcpStream::connect(server_addr).expect("failed to connect to server");
-        let is_left_player = match start {
-            Start::New => true,
-            Start::Join { .. } => false,
-        };
-        let mut client = Self::new(stream, is_left_player, game_over_tx.clone());
+        let stream = connect(server_addr).expect("failed to connect to server");
+        let mut session = Session::new(stream.try_clone().unwrap(), stream);
+        if let Some(cosmetics) = cosmetics {
+            session.set_cosmetics(cosmetics);
+        }
+        let cosmetics = cosmetics.unwrap_or(Cosmetics::NONE);
+        if checksums {
+            session
+                .set_receive_timeout(Some(CHECKSUMS_TIMEOUT))
+                .unwrap();
+            session = match session.enable_checksums() {
+                Ok(session) => session,
+                Err(err) => {
+                    let err = format!("the server didn't agree to checksums: {err}");
+                    game_over_tx.send(Quit::Error(err)).unwrap();
+                    return;
+                }
+            };
+            session.set_receive_timeout(None).unwrap();
+        }
         let mut stdout = stdout();
-        draw_barriers(&mut stdout);
-        execute!(stdout, MoveDown(2)).unwrap();
-        match start {
-            Start::New => {
-                let message = AwaitingOpenClientMessage::NewLobby;
-                Self::send(client.stream.get_mut(), message);
-                let lobby_id = match client.await_msg::<AwaitingNewLobbyServerMessage>().unwrap() {
-                    AwaitingNewLobbyServerMessage::NewLobbyCreated { lobby_id } => lobby_id,
+        let mut keys = KeyReceivers {
+            ready: ready_key_rx,
+            moves: move_key_rx,
+            resign: resign_key_rx,
+            cancel: cancel_key_rx,
+            resizes: resize_rx,
+            ready_name,
+        };
+        let practicing = matches!(start, Start::Practice { .. });
+        // the field is drawn once the server has said how big it is.
+        let (is_left_player, lobby_id, ready_timeout_secs, dimensions, mut session) = match start {
+            Start::New {
+                tick_rate,
+                id,
+                handicaps,
+                modifier,
+                match_length,
+            } => {
+                let ruleset = Ruleset {
+                    tick_interval_ms: tick_rate,
+                    match_length,
+                    handicaps: handicaps.into(),
+                    modifier,
                 };
-                let text = format!("lobby id: {lobby_id}");
-                execute!(
-                    stdout,
-                    MoveRight((GAME_WIDTH as u16 - text.len() as u16) / 2),
-                    Print(text),
-                    MoveToColumn(0),
-                )
-                .unwrap();
-                stdout.flush().unwrap();
-                match client
-                    .await_msg::<AwaitingOpponentJoinServerMessage>()
-                    .unwrap()
-                {
-                    AwaitingOpponentJoinServerMessage::OpponentJoined => {}
+                let session = session.new_lobby(ruleset, id).unwrap();
+                let Some((lobby_id, ready_timeout_secs, dimensions, session)) =
+                    Self::host_lobby(session, id, &game_over_tx, &keys, portrait)
+                else {
+                    return;
+                };
+                (true, lobby_id, ready_timeout_secs, dimensions, session)
+            }
+            Start::Coop { tick_rate, id } => {
+                let session = session.new_coop_lobby(tick_rate, id).unwrap();
+                let Some((lobby_id, ready_timeout_secs, dimensions, session)) =
+                    Self::host_lobby(session, id, &game_over_tx, &keys, portrait)
+                else {
+                    return;
                 };
+                (true, lobby_id, ready_timeout_secs, dimensions, session)
             }
             Start::Join { lobby_id } => {
-                let message = AwaitingOpenClientMessage::JoinLobby {
-                    lobby_id: &lobby_id,
+                let Some(lobby_id) = lobby_id.or_else(clipboard::paste_lobby_id) else {
+                    game_over_tx.send(Quit::NoLobbyId).unwrap();
+                    return;
                 };
-                Self::send(client.stream.get_mut(), message);
-                match client.await_msg().unwrap() {
-                    AwaitingJoinLobbyServerMessage::JoinedLobby => {}
-                    AwaitingJoinLobbyServerMessage::LobbyFull => {
-                        game_over_tx.send(Quit::LobbyFull).unwrap()
-                    }
-                    AwaitingJoinLobbyServerMessage::LobbyNotFound => {
-                        game_over_tx.send(Quit::LobbyNotFound).unwrap()
-                    }
+                let Some((ready_timeout_secs, dimensions, session)) =
+                    Self::join_lobby(session, lobby_id, &game_over_tx, &keys, portrait)
+                else {
+                    return;
+                };
+                (false, lobby_id, ready_timeout_secs, dimensions, session)
+            }
+            Start::Browse => {
+                let Some((lobby_id, session)) =
+                    Self::browse(session, &game_over_tx, &keys, &browse_key_rx)
+                else {
+                    return;
+                };
+                execute!(stdout, Clear(ClearType::All), MoveTo(0, 0)).unwrap();
+                let Some((ready_timeout_secs, dimensions, session)) =
+                    Self::join_lobby(session, lobby_id, &game_over_tx, &keys, portrait)
+                else {
+                    return;
+                };
+                (false, lobby_id, ready_timeout_secs, dimensions, session)
+            }
+            Start::Quick => {
+                let session = session.find_match().unwrap();
+                execute!(stdout, Print("looking for an opponent..."), MoveToColumn(0)).unwrap();
+                stdout.flush().unwrap();
+                let Some(found_match) = quit_on_error(&game_over_tx, session.await_match()) else {
+                    return;
                 };
+                let dimensions = found_match.dimensions;
+                execute!(stdout, Clear(ClearType::CurrentLine)).unwrap();
+                let screen = Screen::new(dimensions, portrait);
+                await_terminal_fits(&mut stdout, screen, &keys.resizes);
+                draw_barriers(&mut stdout, screen);
+                execute!(stdout, MoveDown(2)).unwrap();
+                (
+                    found_match.side == Side::Left,
+                    found_match.lobby_id,
+                    found_match.ready_timeout_secs,
+                    dimensions,
+                    found_match.session,
+                )
+            }
+            Start::Practice { tick_rate } => {
+                let session = session.practice(tick_rate).unwrap();
+                let Some(found_match) = quit_on_error(&game_over_tx, session.await_match()) else {
+                    return;
+                };
+                let dimensions = found_match.dimensions;
+                let screen = Screen::new(dimensions, portrait);
+                await_terminal_fits(&mut stdout, screen, &keys.resizes);
+                draw_barriers(&mut stdout, screen);
+                execute!(stdout, MoveDown(2)).unwrap();
+                (
+                    found_match.side == Side::Left,
+                    found_match.lobby_id,
+                    found_match.ready_timeout_secs,
+                    dimensions,
+                    found_match.session,
+                )
+            }
+            Start::Tournament(start) => {
+                Self::run_tournament(session, start, game_over_tx, keys, portrait, cosmetics);
+                return;
+            }
+        };
+        let mut client = Self::new(
+            is_left_player,
+            lobby_id,
+            dimensions,
+            game_over_tx.clone(),
+            false,
+            practicing,
+            portrait,
+            cosmetics,
+        );
+        // players return to the ready screen after each game, until one of them leaves.
+        loop {
+            let Some((result, ready_session, returned_keys)) =
+                client.play_round(session, ready_timeout_secs, keys)
+            else {
+                return;
+            };
+            session = ready_session;
+            keys = returned_keys;
+            // replace the game with the result, above the ready screen.
+            execute!(stdout, MoveUp(client.screen.height())).unwrap();
+            clear(&mut stdout, client.screen);
+            execute!(
+                stdout,
+                MoveRight(centre_offset(client.screen, result)),
+                Print(result),
+                MoveToColumn(0),
+                MoveDown(1),
+            )
+            .unwrap();
+        }
+    }
+
+    /// waits for the lobby the client asked for to be created, and draws its field and id until an
+    /// opponent joins. returns the lobby's id, the ready timeout, the field's dimensions and the
+    /// joined session, or nothing if the client quit.
+    fn host_lobby(
+        session: Session<AwaitingNewLobby>,
+        id: Option<LobbyId>,
+        game_over_tx: &Sender<Quit>,
+        keys: &KeyReceivers,
+        portrait: bool,
+    ) -> Option<(LobbyId, u16, GameDimensions, Session<AwaitingReady>)> {
+        let mut stdout = stdout();
+        let (lobby_id, dimensions, session) =
+            match quit_on_error(game_over_tx, session.await_lobby())? {
+                NewLobbyOutcome::Created {
+                    lobby_id,
+                    dimensions,
+                    session,
+                } => (lobby_id, dimensions, session),
+                NewLobbyOutcome::IdTaken => {
+                    // the id is only ever taken if the host chose it.
+                    game_over_tx.send(Quit::IdTaken(id.unwrap())).unwrap();
+                    return None;
+                }
+                NewLobbyOutcome::TooManyLobbies => {
+                    game_over_tx.send(Quit::TooManyLobbies).unwrap();
+                    return None;
+                }
+                NewLobbyOutcome::LobbyIncompatible => {
+                    game_over_tx.send(Quit::LobbyIncompatible).unwrap();
+                    return None;
+                }
+            };
+        let screen = Screen::new(dimensions, portrait);
+        await_terminal_fits(&mut stdout, screen, &keys.resizes);
+        draw_barriers(&mut stdout, screen);
+        execute!(stdout, MoveDown(2)).unwrap();
+        // so that the id can be pasted to whoever will join, rather than read out.
+        let text = if clipboard::copy_lobby_id(lobby_id) {
+            format!("lobby id: {lobby_id} (copied to clipboard)")
+        } else {
+            format!("lobby id: {lobby_id}")
+        };
+        execute!(
+            stdout,
+            MoveRight(centre_offset(screen, &text)),
+            Print(text),
+            MoveToColumn(0),
+        )
+        .unwrap();
+        stdout.flush().unwrap();
+        let (ready_timeout_secs, session) =
+            Self::await_opponent(session, lobby_id, screen, game_over_tx, keys)?;
+        Some((lobby_id, ready_timeout_secs, dimensions, session))
+    }
+
+    /// joins the lobby and draws its field, returning the ready timeout, the field's dimensions
+    /// and the joined session, or nothing if the lobby couldn't be joined.
+    fn join_lobby(
+        session: Session<AwaitingOpen>,
+        lobby_id: LobbyId,
+        game_over_tx: &Sender<Quit>,
+        keys: &KeyReceivers,
+        portrait: bool,
+    ) -> Option<(u16, GameDimensions, Session<AwaitingReady>)> {
+        let session = session.join_lobby(lobby_id).unwrap();
+        let quit = match quit_on_error(game_over_tx, session.await_join())? {
+            JoinLobbyOutcome::Joined {
+                ready_timeout_secs,
+                dimensions,
+                session,
+            } => {
+                let mut stdout = stdout();
+                let screen = Screen::new(dimensions, portrait);
+                await_terminal_fits(&mut stdout, screen, &keys.resizes);
+                draw_barriers(&mut stdout, screen);
+                execute!(stdout, MoveDown(2)).unwrap();
+                return Some((ready_timeout_secs, dimensions, session));
+            }
+            JoinLobbyOutcome::LobbyFull => Quit::LobbyFull,
+            JoinLobbyOutcome::LobbyNotFound => Quit::LobbyNotFound,
+            JoinLobbyOutcome::LobbyIncompatible => Quit::LobbyIncompatible,
+        };
+        game_over_tx.send(quit).unwrap();
+        None
+    }
+
+    /// shows a page of the lobbies waiting for an opponent at a time, until one is chosen to join.
+    /// returns the chosen lobby's id, or nothing if the client quit.
+    fn browse(
+        mut session: Session<AwaitingOpen>,
+        game_over_tx: &Sender<Quit>,
+        keys: &KeyReceivers,
+        browse_keys: &Receiver<BrowseKey>,
+    ) -> Option<(LobbyId, Session<AwaitingOpen>)> {
+        let mut stdout = stdout();
+        let mut page = 0;
+        let mut selected = 0;
+        loop {
+            let awaiting_page = session.browse_lobbies(page).unwrap();
+            let (lobby_page, next_session) =
+                quit_on_error(game_over_tx, awaiting_page.await_page())?;
+            session = next_session;
+            selected = selected.min(lobby_page.lobbies.len().saturating_sub(1));
+            draw_lobby_page(&mut stdout, &lobby_page, selected, &keys.ready_name);
+            // wait for a key that needs a new page, redrawing this one as the selection moves.
+            loop {
+                sleep(KEY_LISTENER_POLL_INTERVAL);
+                if let Ok(MoveKey::Repeated(down) | MoveKey::Held(down)) = keys.moves.try_recv() {
+                    selected = if down {
+                        (selected + 1).min(lobby_page.lobbies.len().saturating_sub(1))
+                    } else {
+                        selected.saturating_sub(1)
+                    };
+                    draw_lobby_page(&mut stdout, &lobby_page, selected, &keys.ready_name);
+                } else if keys.ready.try_recv().is_ok() {
+                    break;
+                } else {
+                    match browse_keys.try_recv() {
+                        Ok(BrowseKey::PreviousPage) if lobby_page.page > 0 => {
+                            page = lobby_page.page - 1;
+                            selected = 0;
+                            break;
+                        }
+                        Ok(BrowseKey::NextPage) if lobby_page.page + 1 < lobby_page.pages => {
+                            page = lobby_page.page + 1;
+                            selected = 0;
+                            break;
+                        }
+                        Ok(BrowseKey::Join) => {
+                            if let Some(lobby) = lobby_page.lobbies.get(selected) {
+                                return Some((lobby.lobby_id, session));
+                            }
+                        }
+                        Ok(_) | Err(_) => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// plays a tournament from the bracket screen, playing each of the player's matches as it
+    /// becomes ready, until somebody wins it.
+    fn run_tournament(
+        session: Session<AwaitingOpen>,
+        start: TournamentStart,
+        game_over_tx: Sender<Quit>,
+        mut keys: KeyReceivers,
+        portrait: bool,
+        cosmetics: Cosmetics,
+    ) {
+        let mut stdout = stdout();
+        let mut session = match start {
+            TournamentStart::New { players } => session.new_tournament(players),
+            TournamentStart::League { players } => session.new_league(players),
+            TournamentStart::Join { tournament_id } => session.join_tournament(tournament_id),
+        }
+        .unwrap();
+        let mut screen = TournamentScreen::default();
+        loop {
+            let Some(message) = quit_on_error(&game_over_tx, session.receive()) else {
+                return;
+            };
+            match message {
+                AwaitingTournamentServerMessage::JoinedTournament {
+                    tournament_id,
+                    seat,
+                    players,
+                } => {
+                    screen.tournament_id = Some(tournament_id);
+                    screen.seat = seat;
+                    screen.players = players;
+                    // the number of players who have joined follows straight after.
+                    continue;
+                }
+                AwaitingTournamentServerMessage::TournamentFull => {
+                    game_over_tx.send(Quit::TournamentFull).unwrap();
+                    return;
+                }
+                AwaitingTournamentServerMessage::TournamentNotFound => {
+                    game_over_tx.send(Quit::TournamentNotFound).unwrap();
+                    return;
+                }
+                AwaitingTournamentServerMessage::TournamentIncompatible => {
+                    game_over_tx.send(Quit::TournamentIncompatible).unwrap();
+                    return;
+                }
+                AwaitingTournamentServerMessage::PlayersJoined { players } => {
+                    screen.players_joined = players;
+                }
+                AwaitingTournamentServerMessage::BracketUpdated { bracket } => {
+                    screen.bracket = Some(bracket);
+                }
+                AwaitingTournamentServerMessage::StandingsUpdated { standings } => {
+                    screen.standings = Some(standings);
+                }
+                AwaitingTournamentServerMessage::MatchReady {
+                    lobby_id,
+                    side,
+                    ready_timeout_secs,
+                    dimensions,
+                } => {
+                    execute!(stdout, Clear(ClearType::All), MoveTo(0, 0)).unwrap();
+                    let field = Screen::new(dimensions, portrait);
+                    await_terminal_fits(&mut stdout, field, &keys.resizes);
+                    draw_barriers(&mut stdout, field);
+                    execute!(stdout, MoveDown(2)).unwrap();
+                    let mut client = Self::new(
+                        side == Side::Left,
+                        lobby_id,
+                        dimensions,
+                        game_over_tx.clone(),
+                        true,
+                        false,
+                        portrait,
+                        cosmetics,
+                    );
+                    let Some((result, ready_session, returned_keys)) =
+                        client.play_round(session.start_match(), ready_timeout_secs, keys)
+                    else {
+                        return;
+                    };
+                    session = ready_session.end_match();
+                    keys = returned_keys;
+                    screen.last_result = Some(result);
+                }
+                AwaitingTournamentServerMessage::TournamentWon { seat } => {
+                    let you = seat == screen.seat;
+                    game_over_tx
+                        .send(Quit::TournamentWon { seat, you })
+                        .unwrap();
+                    return;
+                }
+            }
+            screen.draw(&mut stdout);
+        }
+    }
+
+    /// plays the lobby's next game, from its ready screen until it ends, returning a description
+    /// of the result along with the session and key receivers for the next one. returns `None`
+    /// once the client has quit.
+    fn play_round(
+        &mut self,
+        session: Session<AwaitingReady>,
+        ready_timeout_secs: u16,
+        keys: KeyReceivers,
+    ) -> Option<(&'static str, Session<AwaitingReady>, KeyReceivers)> {
+        let ready_deadline = Instant::now() + Duration::from_secs(ready_timeout_secs as u64);
+        let (exit, ready) = self.await_game_start(
+            session,
+            self.game_over_tx.clone(),
+            keys.ready,
+            &keys.ready_name,
+            ready_deadline,
+        )?;
+        let (playing_session, handicaps, tick_interval_ms) = match exit {
+            ReadyScreenExit::GameStarted(session, handicaps, tick_interval_ms) => {
+                (session, handicaps, tick_interval_ms)
+            }
+            ReadyScreenExit::LobbyClosed(result, session) => {
+                let keys = KeyReceivers { ready, ..keys };
+                return Some((result, session, keys));
+            }
+            ReadyScreenExit::OpponentLeftLobby(session) => {
+                let keys = KeyReceivers { ready, ..keys };
+                let (ready_timeout_secs, session) = self.await_new_opponent(session, &keys)?;
+                return self.play_round(session, ready_timeout_secs, keys);
             }
         };
-        let client = client.await_game_start(game_over_tx.clone(), ready_key_rx);
-        if client.is_none() {
-            return;
+        let dimensions = self.dimensions;
+        let mut stdout = stdout();
+        // the ready screen is gone once the terminal has been cleared to ask for it to be enlarged,
+        // so the field is drawn again from the top.
+        if await_terminal_fits(&mut stdout, self.screen, &keys.resizes) {
+            draw_barriers(&mut stdout, self.screen);
+            execute!(stdout, MoveDown(2)).unwrap();
         }
-        let mut client = client.unwrap();
-        let local_paddle_pos = Arc::new(AtomicU8::new(0));
-        let local_paddle_pos_clone = Arc::clone(&local_paddle_pos);
         execute!(stdout, MoveUp(2)).unwrap();
-        draw_game(
+        let mut frame = Frame::new(self.screen);
+        frame.draw(
             stdout.lock(),
-            0,
-            0,
-            Ball {
-                x: GAME_WIDTH / 2,
-                y: GAME_HEIGHT / 2,
-                moving_right: true,
-                moving_down: true,
+            dimensions,
+            handicaps,
+            self.paddle_looks(),
+            Positions {
+                left_paddle: 0,
+                right_paddle: 0,
+                partner_paddle: self.coop.then_some(0),
+                ball_x: dimensions.width() / 2,
+                ball_y: dimensions.height() / 2,
             },
         );
-        let mut stream_writer_clone = client.stream.get_ref().try_clone().unwrap();
+        let (result, session, moves, resign) = self.play_game(
+            playing_session,
+            frame,
+            handicaps,
+            tick_interval_ms,
+            keys.moves,
+            keys.resign,
+        )?;
+        let keys = KeyReceivers {
+            ready,
+            moves,
+            resign,
+            cancel: keys.cancel,
+            resizes: keys.resizes,
+            ready_name: keys.ready_name,
+        };
+        Some((result, session, keys))
+    }
+
+    /// plays a game until it ends, returning a description of the result along with the key
+    /// receivers so that they can be reused for the next game. returns `None` if the opponent
+    /// left, unless the game is a tournament match. a glyph at the end of the field's bottom
+    /// barrier shows whether game state updates are arriving as often as the game ticks.
+    fn play_game(
+        &self,
+        mut session: Session<Playing>,
+        mut frame: Frame,
+        handicaps: Handicaps,
+        tick_interval_ms: u16,
+        move_key_rx: Receiver<MoveKey>,
+        resign_key_rx: Receiver<()>,
+    ) -> Option<FinishedGame> {
+        let (kill_keys_tx, kill_keys_rx) = channel::<()>();
+        let mut sender = session.try_clone_sender().unwrap();
         // drain previously buffered move key events.
-        while let Ok(_) = move_key_rx.try_recv() {}
-        Builder::new()
+        while move_key_rx.try_recv().is_ok() {}
+        let direction = Arc::new(Mutex::new(MoveDirection::Stop));
+        let listener_direction = Arc::clone(&direction);
+        // set while the server waits for this player to serve, which any move key does.
+        let serving = Arc::new(Mutex::new(false));
+        let listener_serving = Arc::clone(&serving);
+        let move_key_listener = Builder::new()
             .name("move_key_listener".to_owned())
             .spawn(move || {
-                for move_key in move_key_rx {
-                    let new_pos = local_paddle_pos_clone.fetch_update(
-                        Ordering::Relaxed,
-                        Ordering::Relaxed,
-                        |pos| {
-                            if move_key && pos < GAME_HEIGHT - PADDLE_HEIGHT {
-                                // move down.
-                                Some(pos + 1)
-                            } else if !move_key && pos > 0 {
-                                // move up.
-                                Some(pos - 1)
-                            } else {
-                                None
-                            }
-                        },
-                    );
-                    if let Ok(prev_pos) = new_pos {
-                        let new_pos = if move_key {
-                            // move down.
-                            prev_pos + 1
-                        } else {
-                            // move up.
-                            prev_pos - 1
-                        };
-                        Self::send(
-                            &mut stream_writer_clone,
-                            PlayingClientMessage::MovePaddle { pos: new_pos },
-                        )
+                // most terminals only report key presses, so a held key is detected by the
+                // operating system's key repeat, and the paddle is stopped once repeats stop
+                // arriving. keys whose releases are reported keep it moving until they're released.
+                let mut held = false;
+                loop {
+                    let current_direction = *listener_direction.lock().unwrap();
+                    let new_direction = match move_key_rx.recv_timeout(KEY_RELEASE_TIMEOUT) {
+                        Ok(MoveKey::Repeated(down)) => {
+                            held = false;
+                            move_direction(down)
+                        }
+                        Ok(MoveKey::Held(down)) => {
+                            held = true;
+                            move_direction(down)
+                        }
+                        // releasing the other key, after pressing both, leaves the paddle moving.
+                        Ok(MoveKey::Released(down))
+                            if move_direction(down) != current_direction =>
+                        {
+                            current_direction
+                        }
+                        Ok(MoveKey::Released(_)) => MoveDirection::Stop,
+                        Err(RecvTimeoutError::Timeout) if held => current_direction,
+                        Err(RecvTimeoutError::Timeout) => MoveDirection::Stop,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    };
+                    if let Ok(_) | Err(TryRecvError::Disconnected) = kill_keys_rx.try_recv() {
+                        break;
+                    }
+                    if new_direction != MoveDirection::Stop {
+                        let mut serving = listener_serving.lock().unwrap();
+                        if *serving {
+                            *serving = false;
+                            sender.send(PlayingClientMessage::Serve).unwrap();
+                        }
+                    }
+                    let mut direction = listener_direction.lock().unwrap();
+                    if new_direction != *direction {
+                        *direction = new_direction;
+                        sender
+                            .send(PlayingClientMessage::MoveIntent {
+                                direction: new_direction,
+                            })
+                            .unwrap();
                     }
                 }
+                move_key_rx
             })
             .unwrap();
-        loop {
-            let message = client.await_msg::<PlayingServerMessage>().unwrap();
-            match message {
+        let mut sender = session.try_clone_sender().unwrap();
+        let (kill_resign_tx, kill_resign_rx) = channel::<()>();
+        // drain previously buffered resign key events.
+        while resign_key_rx.try_recv().is_ok() {}
+        let resign_key_listener = Builder::new()
+            .name("resign_key_listener".to_owned())
+            .spawn(move || {
+                loop {
+                    match resign_key_rx.recv_timeout(KEY_LISTENER_POLL_INTERVAL) {
+                        Ok(()) => {
+                            sender.send(PlayingClientMessage::Resign).unwrap();
+                            break;
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                    if let Ok(_) | Err(TryRecvError::Disconnected) = kill_resign_rx.try_recv() {
+                        break;
+                    }
+                }
+                resign_key_rx
+            })
+            .unwrap();
+        let own_side = if self.is_left_player {
+            Side::Left
+        } else {
+            Side::Right
+        };
+        let mut awaiting_serve = false;
+        // shown below the field for the whole game: the stakes if it's a tiebreak, the rally so far
+        // when practicing, or the players' score in a co-op game.
+        let mut stakes = if self.practicing {
+            Some("rally 0".to_owned())
+        } else if self.coop {
+            Some(describe_survival(0))
+        } else {
+            None
+        };
+        let mut speed_level = 0;
+        // whether the ball was last seen moving right, so that the player's returns are counted.
+        let mut moving_right = None;
+        let mut rally = 0;
+        let mut survival_score = 0;
+        // nothing is sent while the ball waits to be served, so the connection isn't checked then.
+        session
+            .set_receive_timeout(Some(CONNECTION_CHECK_INTERVAL))
+            .unwrap();
+        let mut last_message = Instant::now();
+        let mut health = ConnectionHealth::Good;
+        display_connection_health(&mut stdout(), self.screen, health);
+        // when the match point was announced, and whether the top barrier is currently lit.
+        let mut match_point = None;
+        let mut barrier_lit = false;
+        // the field is drawn by its own thread, at a higher rate than the game ticks. the player's
+        // own paddle is always drawn where the server last put it.
+        let own_paddle = match (self.coop, self.is_left_player) {
+            (_, true) => Paddle::Left,
+            (true, false) => Paddle::Partner,
+            (false, false) => Paddle::Right,
+        };
+        let render_slot = Arc::new(Mutex::new(None::<RenderSlot>));
+        let (kill_renderer_tx, kill_renderer_rx) = channel::<()>();
+        let renderer_slot = Arc::clone(&render_slot);
+        let dimensions = self.dimensions;
+        let looks = self.paddle_looks();
+        let renderer = Builder::new()
+            .name("renderer".to_owned())
+            .spawn(move || {
+                let mut drawn = None;
+                while let Err(RecvTimeoutError::Timeout) =
+                    kill_renderer_rx.recv_timeout(FRAME_INTERVAL)
+                {
+                    let positions = renderer_slot
+                        .lock()
+                        .unwrap()
+                        .as_ref()
+                        .map(|slot| slot.positions(own_paddle));
+                    let Some(positions) = positions else {
+                        continue;
+                    };
+                    if drawn != Some(positions) {
+                        drawn = Some(positions);
+                        draw_positions(&mut frame, dimensions, handicaps, looks, positions);
+                    }
+                }
+                frame
+            })
+            .unwrap();
+        let result = loop {
+            if let Some(since) = match_point.map(|since: Instant| since.elapsed()) {
+                let lit =
+                    (since.as_millis() / MATCH_POINT_FLASH_INTERVAL.as_millis()).is_multiple_of(2);
+                if lit != barrier_lit {
+                    // the renderer waits while anything else is drawn.
+                    let _drawing = stdout().lock();
+                    barrier_lit = lit;
+                    display_top_barrier(&mut stdout(), self.screen, barrier_lit);
+                }
+            }
+            let message = match session.receive() {
+                Err(err) if err.is_timeout() => {
+                    let _drawing = stdout().lock();
+                    let new_health = ConnectionHealth::since(last_message, tick_interval_ms);
+                    if !awaiting_serve && new_health != health {
+                        health = new_health;
+                        display_connection_health(&mut stdout(), self.screen, health);
+                        if health == ConnectionHealth::Stalled {
+                            display_game_status(
+                                &mut stdout(),
+                                self.screen,
+                                stakes.as_deref(),
+                                Some("waiting for the game to resume"),
+                            );
+                        }
+                    }
+                    continue;
+                }
+                // a message for another state means the client lost track of the game, e.g. it
+                // ended without the client hearing about it. the server says where things stand.
+                Err(ReceiveError::Deserialize(DeserializeMessageError::InvalidState {
+                    ..
+                })) => {
+                    match quit_on_error(&self.game_over_tx, session.resync())? {
+                        Resynced::Playing(resynced) => {
+                            session = resynced;
+                            continue;
+                        }
+                        // the session is moved back to the ready state once the game is over.
+                        Resynced::AwaitingReady {
+                            session: resynced, ..
+                        } => {
+                            session = resynced.start_game();
+                            break "game over";
+                        }
+                        _ => {
+                            let _ = self.game_over_tx.send(Quit::Error(DESYNCED.to_owned()));
+                            return None;
+                        }
+                    }
+                }
+                received => quit_on_error(&self.game_over_tx, received)?,
+            };
+            let _drawing = stdout().lock();
+            last_message = Instant::now();
+            if health != ConnectionHealth::Good {
+                if health == ConnectionHealth::Stalled {
+                    display_speed_level(&mut stdout(), self.screen, speed_level, stakes.as_deref());
+                }
+                health = ConnectionHealth::Good;
+                display_connection_health(&mut stdout(), self.screen, health);
+            }
+            // the partner's paddle and the players' score come with the game state of a co-op
+            // game.
+            let (game_state, coop) = match message {
+                PlayingServerMessage::OpponentLeft if self.in_tournament => break "opponent left",
                 PlayingServerMessage::OpponentLeft => {
-                    game_over_tx.send(Quit::OpponentLeft).unwrap()
+                    let _ = self.game_over_tx.send(Quit::OpponentLeft);
+                    return None;
                 }
-                PlayingServerMessage::OpponentWon => {
-                    let _ = client.game_over_tx.send(Quit::OpponentWon);
-                    break;
+                PlayingServerMessage::OpponentWon if self.practicing => break "you missed",
+                PlayingServerMessage::OpponentWon if self.coop => {
+                    break "the server got one past you"
                 }
-                PlayingServerMessage::YouWon => {
-                    let _ = client.game_over_tx.send(Quit::YouWon);
-                    break;
+                PlayingServerMessage::OpponentWon => break "you lost",
+                PlayingServerMessage::YouWon => break "you won",
+                PlayingServerMessage::OpponentResigned if self.coop => break "partner resigned",
+                PlayingServerMessage::OpponentResigned => break "opponent resigned",
+                PlayingServerMessage::BallSpedUp {
+                    speed_level: new_speed_level,
+                } => {
+                    speed_level = new_speed_level;
+                    display_speed_level(&mut stdout(), self.screen, speed_level, stakes.as_deref());
+                    continue;
                 }
-                PlayingServerMessage::GameStateUpdated { game_state } => {
-                    local_paddle_pos.store(
-                        if client.is_left_player {
-                            game_state.left_paddle
-                        } else {
-                            game_state.right_paddle
-                        },
-                        Ordering::Relaxed,
-                    );
-                    let mut stdout = stdout.lock();
-                    execute!(stdout, MoveUp(GAME_HEIGHT as u16)).unwrap();
-                    draw_game(
-                        stdout,
-                        game_state.left_paddle,
-                        game_state.right_paddle,
-                        game_state.ball,
-                    );
+                PlayingServerMessage::Deuce => {
+                    stakes = Some("deuce, win by two".to_owned());
+                    display_serve_prompt(&mut stdout(), self.screen, None, stakes.as_deref());
+                    continue;
+                }
+                PlayingServerMessage::SuddenDeath => {
+                    stakes = Some("sudden death".to_owned());
+                    display_serve_prompt(&mut stdout(), self.screen, None, stakes.as_deref());
+                    continue;
+                }
+                PlayingServerMessage::MatchPoint {
+                    left_wins,
+                    right_wins,
+                } => {
+                    let (own_wins, opponent_wins) = if self.is_left_player {
+                        (left_wins, right_wins)
+                    } else {
+                        (right_wins, left_wins)
+                    };
+                    stakes = Some(describe_match_point(own_wins, opponent_wins));
+                    match_point = Some(Instant::now());
+                    display_serve_prompt(&mut stdout(), self.screen, None, stakes.as_deref());
+                    continue;
+                }
+                PlayingServerMessage::Serving { side } => {
+                    *serving.lock().unwrap() = side == own_side;
+                    awaiting_serve = true;
+                    let own_serve = Some(side == own_side);
+                    display_serve_prompt(&mut stdout(), self.screen, own_serve, stakes.as_deref());
+                    continue;
                 }
+                PlayingServerMessage::GameStateUpdated { game_state } => (game_state, None),
+                PlayingServerMessage::CoopStateUpdated {
+                    game_state,
+                    partner_paddle,
+                    score,
+                } => (game_state, Some((partner_paddle, score))),
+            };
+            let valid = self.dimensions.validate_with(handicaps, &game_state);
+            // the partner's paddle defends the left side, so it's checked as the left paddle.
+            let valid = valid.and_then(|_| match coop {
+                Some((partner_paddle, _)) => self.dimensions.validate(&GameState {
+                    left_paddle: partner_paddle,
+                    ..game_state.clone()
+                }),
+                None => Ok(()),
+            });
+            quit_on_error(&self.game_over_tx, valid.map_err(ReceiveError::from))?;
+            // the first update after the serve was announced means the ball is moving.
+            if awaiting_serve {
+                awaiting_serve = false;
+                *serving.lock().unwrap() = false;
+                display_serve_prompt(&mut stdout(), self.screen, None, stakes.as_deref());
+            }
+            // the ball only turns right off the player's paddle, since the wall is on the right.
+            if self.practicing && moving_right == Some(false) && game_state.ball.moving_right {
+                rally += 1;
+                stakes = Some(format!("rally {rally}"));
+                display_speed_level(&mut stdout(), self.screen, speed_level, stakes.as_deref());
+            }
+            if let Some((_, score)) = coop.filter(|&(_, score)| score != survival_score) {
+                survival_score = score;
+                stakes = Some(describe_survival(score));
+                display_speed_level(&mut stdout(), self.screen, speed_level, stakes.as_deref());
             }
+            moving_right = Some(game_state.ball.moving_right);
+            let positions = Positions {
+                left_paddle: game_state.left_paddle,
+                right_paddle: game_state.right_paddle,
+                partner_paddle: coop.map(|(partner_paddle, _)| partner_paddle),
+                ball_x: game_state.ball.x,
+                ball_y: game_state.ball.y,
+            };
+            let mut slot = render_slot.lock().unwrap();
+            *slot = Some(RenderSlot::next(slot.take(), positions, tick_interval_ms));
+            drop(slot);
+            // servers in lockstep wait for an input from both players every tick.
+            if self.lockstep {
+                let direction = *direction.lock().unwrap();
+                session
+                    .send(PlayingClientMessage::MoveIntent { direction })
+                    .unwrap();
+            }
+        };
+        let _ = kill_renderer_tx.send(());
+        let mut frame = renderer.join().unwrap();
+        // the game may have ended before the renderer caught up with its last state.
+        if let Some(slot) = render_slot.lock().unwrap().take() {
+            draw_positions(&mut frame, self.dimensions, handicaps, looks, slot.latest);
+        }
+        // the players' score is left below the field once a co-op game is over.
+        let final_stakes = if self.coop { stakes.as_deref() } else { None };
+        display_speed_level(&mut stdout(), self.screen, 0, final_stakes);
+        display_connection_health(&mut stdout(), self.screen, ConnectionHealth::Unknown);
+        if barrier_lit {
+            display_top_barrier(&mut stdout(), self.screen, false);
+        }
+        session.set_receive_timeout(None).unwrap();
+        let _ = kill_keys_tx.send(());
+        let _ = kill_resign_tx.send(());
+        let move_key_rx = move_key_listener.join().unwrap();
+        let resign_key_rx = resign_key_listener.join().unwrap();
+        Some((result, session.end_game(), move_key_rx, resign_key_rx))
+    }
+
+    /// replaces the ready screen with the lobby's id once the opponent has left, and waits for
+    /// someone else to join as the lobby's host. returns the ready timeout and the joined session,
+    /// or nothing if the client quit.
+    fn await_new_opponent(
+        &mut self,
+        session: Session<AwaitingOpponentJoin>,
+        keys: &KeyReceivers,
+    ) -> Option<(u16, Session<AwaitingReady>)> {
+        // whoever joins next takes the right side, opposite the host, and says how their paddle
+        // looks themselves.
+        self.is_left_player = true;
+        self.opponent_cosmetics = Cosmetics::NONE;
+        let mut stdout = stdout();
+        let text = format!("{} left, lobby id: {}", self.other_player(), self.lobby_id);
+        execute!(
+            stdout,
+            MoveUp(1),
+            Clear(ClearType::CurrentLine),
+            MoveDown(1),
+            Clear(ClearType::CurrentLine),
+            MoveDown(1),
+            Clear(ClearType::CurrentLine),
+            MoveUp(2),
+            MoveRight(centre_offset(self.screen, &text)),
+            Print(text),
+            MoveToColumn(0),
+        )
+        .unwrap();
+        stdout.flush().unwrap();
+        Self::await_opponent(
+            session,
+            self.lobby_id,
+            self.screen,
+            &self.game_over_tx,
+            keys,
+        )
+    }
+
+    /// waits for an opponent to join the host's lobby, showing how long the host has waited below
+    /// the lobby id. once the server warns that the lobby is about to expire, a countdown is shown
+    /// instead, and the ready key keeps the lobby open. the cancel key closes the lobby.
+    fn await_opponent(
+        mut session: Session<AwaitingOpponentJoin>,
+        lobby_id: LobbyId,
+        screen: Screen,
+        game_over_tx: &Sender<Quit>,
+        keys: &KeyReceivers,
+    ) -> Option<(u16, Session<AwaitingReady>)> {
+        let mut stdout = stdout();
+        let mut sender = session.try_clone_sender().unwrap();
+        let (warning_tx, warning_rx) = channel();
+        let msg_listener = Builder::new()
+            .name("awaiting_opponent_msg_listener".to_owned())
+            .spawn(move || loop {
+                match session.await_opponent() {
+                    Ok(OpponentJoinOutcome::ExpiringSoon {
+                        seconds,
+                        session: next,
+                    }) => {
+                        let _ = warning_tx.send(seconds);
+                        session = next;
+                    }
+                    outcome => return outcome,
+                }
+            })
+            .unwrap();
+        // drain previously buffered ready and cancel key events.
+        while keys.ready.try_recv().is_ok() {}
+        while keys.cancel.try_recv().is_ok() {}
+        let waiting_since = Instant::now();
+        let mut expires_at = None;
+        loop {
+            match warning_rx.recv_timeout(KEY_LISTENER_POLL_INTERVAL) {
+                Ok(seconds) => {
+                    expires_at = Some(Instant::now() + Duration::from_secs(seconds.into()));
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            if keys.cancel.try_recv().is_ok() {
+                // the connection closing when the client quits would close the lobby too.
+                let _ = sender.send(AwaitingOpponentJoinClientMessage::LeaveLobby);
+                game_over_tx.send(Quit::LobbyCancelled).unwrap();
+                return None;
+            }
+            if keys.ready.try_recv().is_ok() && expires_at.is_some() {
+                // the lobby may have closed in the meantime, which the listener will report.
+                let _ = sender.send(AwaitingOpponentJoinClientMessage::StillHere);
+                expires_at = None;
+            }
+            match expires_at {
+                Some(_) => {
+                    display_expiry_warning(&mut stdout, screen, expires_at, &keys.ready_name)
+                }
+                None => display_waiting(&mut stdout, screen, lobby_id, waiting_since.elapsed()),
+            }
+        }
+        display_expiry_warning(&mut stdout, screen, None, &keys.ready_name);
+        match quit_on_error(game_over_tx, msg_listener.join().unwrap())? {
+            OpponentJoinOutcome::Joined {
+                ready_timeout_secs,
+                session,
+            } => Some((ready_timeout_secs, session)),
+            OpponentJoinOutcome::Expired => {
+                game_over_tx.send(Quit::LobbyExpired).unwrap();
+                None
+            }
+            OpponentJoinOutcome::ExpiringSoon { .. } => unreachable!(),
         }
     }
 
     fn await_game_start(
-        mut self,
+        &mut self,
+        mut session: Session<AwaitingReady>,
         game_over_tx: Sender<Quit>,
         ready_key_rx: Receiver<()>,
-    ) -> Option<Self> {
+        ready_name: &str,
+        ready_deadline: Instant,
+    ) -> Option<(ReadyScreenExit, Receiver<()>)> {
         let is_left_player = self.is_left_player;
+        let screen = self.screen;
         let mut stdout = stdout();
+        let text = format!("press {ready_name} to toggle ready status");
         execute!(
             stdout,
             Clear(ClearType::CurrentLine),
-            MoveRight((GAME_WIDTH as u16 - 32) / 2),
-            Print("press 'r' to toggle ready status"),
+            MoveRight(centre_offset(screen, &text)),
+            Print(text),
             MoveToNextLine(1),
         )
         .unwrap();
         execute!(stdout, SetForegroundColor(Color::Red)).unwrap();
-        if is_left_player {
+        if self.practicing {
             execute!(
                 stdout,
                 Print("you are not ready"),
-                MoveRight(GAME_WIDTH as u16 - (21 + 17)),
+                SetForegroundColor(Color::Green),
+                MoveRight(screen.width().saturating_sub(13 + 17)),
+                Print("wall is ready"),
+            )
+            .unwrap();
+        } else if is_left_player {
+            execute!(
+                stdout,
+                Print("you are not ready"),
+                MoveRight(screen.width().saturating_sub(21 + 17)),
                 Print("opponent is not ready"),
             )
             .unwrap();
@@ -223,38 +1244,103 @@ impl TcpClient {
             execute!(
                 stdout,
                 Print("opponent is not ready"),
-                MoveRight(GAME_WIDTH as u16 - (21 + 17)),
+                MoveRight(screen.width().saturating_sub(21 + 17)),
                 Print("you are not ready"),
             )
             .unwrap();
         }
         execute!(stdout, SetForegroundColor(Color::Reset), MoveToColumn(0)).unwrap();
-        stdout.flush().unwrap();
+        let mut rules = self.rules();
+        display_countdown(&mut stdout, screen, ready_deadline, rules.as_deref());
         let (kill_keys_tx, kill_keys_rx) = channel::<()>();
         let (event_tx, event_rx) = channel();
         let event_tx_clone = event_tx.clone();
-        // drain previously buffered ready key events.
-        while let Ok(_) = ready_key_rx.try_recv() {}
+        let countdown_event_tx = event_tx.clone();
         Builder::new()
+            .name("ready_countdown".to_owned())
+            .spawn(move || loop {
+                sleep(Duration::from_secs(1));
+                // stops once the ready screen has been left and the receiver dropped.
+                if countdown_event_tx
+                    .send(AwaitingReadyEvent::CountdownTicked)
+                    .is_err()
+                {
+                    break;
+                }
+            })
+            .unwrap();
+        // drain previously buffered ready key events.
+        while ready_key_rx.try_recv().is_ok() {}
+        let ready_key_listener = Builder::new()
             .name("ready_key_listener".to_owned())
             .spawn(move || {
-                for _ in ready_key_rx {
+                loop {
+                    match ready_key_rx.recv_timeout(KEY_LISTENER_POLL_INTERVAL) {
+                        Ok(()) => {
+                            let _ = event_tx_clone.send(AwaitingReadyEvent::ReadyKeyPressed);
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
                     if let Ok(_) | Err(TryRecvError::Disconnected) = kill_keys_rx.try_recv() {
                         break;
                     }
-                    let _ = event_tx_clone.send(AwaitingReadyEvent::ReadyKeyPressed);
                 }
+                ready_key_rx
             })
             .unwrap();
-        let mut stream_writer_clone = self.stream.get_ref().try_clone().unwrap();
+        let mut sender = session.try_clone_sender().unwrap();
+        let practicing = self.practicing;
         let msg_listener = Builder::new()
             .name("awaiting_ready_msg_listener".to_owned())
             .spawn(move || {
                 loop {
-                    let msg = self.await_msg::<AwaitingReadyServerMessage>();
+                    let msg = session.receive();
                     match msg {
-                        Ok(AwaitingReadyServerMessage::GameStarted)
+                        // the client lost track of the lobby, so the server says where it stands.
+                        Err(ReceiveError::Deserialize(DeserializeMessageError::InvalidState {
+                            ..
+                        })) => match session.resync() {
+                            Ok(Resynced::AwaitingReady {
+                                you_ready,
+                                opponent_ready,
+                                session: resynced,
+                            }) => {
+                                session = resynced;
+                                let mut msgs = vec![if you_ready {
+                                    AwaitingReadyServerMessage::YouReadied
+                                } else {
+                                    AwaitingReadyServerMessage::YouUnreadied
+                                }];
+                                // the wall is always shown as ready.
+                                if !practicing {
+                                    msgs.push(if opponent_ready {
+                                        AwaitingReadyServerMessage::OpponentReadied
+                                    } else {
+                                        AwaitingReadyServerMessage::OpponentUnreadied
+                                    });
+                                }
+                                for msg in msgs {
+                                    let _ = event_tx
+                                        .send(AwaitingReadyEvent::ServerMessageReceived(Ok(msg)));
+                                }
+                            }
+                            Ok(_) => {
+                                let _ = event_tx.send(AwaitingReadyEvent::Desynced);
+                                return None;
+                            }
+                            Err(err) => {
+                                let _ = event_tx
+                                    .send(AwaitingReadyEvent::ServerMessageReceived(Err(err)));
+                                return None;
+                            }
+                        },
+                        Ok(AwaitingReadyServerMessage::GameStarted { .. })
+                        | Ok(AwaitingReadyServerMessage::HandicappedGameStarted { .. })
                         | Ok(AwaitingReadyServerMessage::OpponentLeft)
+                        | Ok(AwaitingReadyServerMessage::OpponentLeftLobby)
+                        | Ok(AwaitingReadyServerMessage::PromotedToHost { .. })
+                        | Ok(AwaitingReadyServerMessage::LobbyTimedOut)
                         | Err(_) => {
                             let _ = event_tx.send(AwaitingReadyEvent::ServerMessageReceived(msg));
                             break;
@@ -262,16 +1348,24 @@ impl TcpClient {
                         Ok(AwaitingReadyServerMessage::OpponentReadied)
                         | Ok(AwaitingReadyServerMessage::OpponentUnreadied)
                         | Ok(AwaitingReadyServerMessage::YouReadied)
-                        | Ok(AwaitingReadyServerMessage::YouUnreadied) => {
+                        | Ok(AwaitingReadyServerMessage::YouUnreadied)
+                        | Ok(AwaitingReadyServerMessage::LobbyRuleset { .. })
+                        | Ok(AwaitingReadyServerMessage::CoopLobby)
+                        | Ok(AwaitingReadyServerMessage::OpponentCosmetics { .. })
+                        | Ok(AwaitingReadyServerMessage::LockstepGame) => {
                             let _ = event_tx.send(AwaitingReadyEvent::ServerMessageReceived(msg));
                         }
                     };
                 }
-                self
+                Some(session)
             })
             .unwrap();
         let mut you_ready = false;
         let mut awaiting_you_readied_reply = false;
+        let mut lobby_closed = None;
+        let mut opponent_left_lobby = false;
+        let mut handicaps = Handicaps::NONE;
+        let mut tick_interval_ms = NORMAL_TICK_INTERVAL_MS;
         for event in event_rx.iter() {
             match event {
                 AwaitingReadyEvent::ReadyKeyPressed => {
@@ -279,32 +1373,48 @@ impl TcpClient {
                         continue;
                     }
                     awaiting_you_readied_reply = true;
-                    Self::send(
-                        &mut stream_writer_clone,
-                        if you_ready {
+                    sender
+                        .send(if you_ready {
                             AwaitingReadyClientMessage::Unready
                         } else {
                             AwaitingReadyClientMessage::Ready
-                        },
-                    );
+                        })
+                        .unwrap();
+                }
+                AwaitingReadyEvent::CountdownTicked => {
+                    display_countdown(&mut stdout, screen, ready_deadline, rules.as_deref());
+                }
+                AwaitingReadyEvent::Desynced => {
+                    let _ = self.game_over_tx.send(Quit::Error(DESYNCED.to_owned()));
+                    return None;
                 }
                 AwaitingReadyEvent::ServerMessageReceived(msg) => {
-                    match msg.unwrap() {
+                    match quit_on_error(&self.game_over_tx, msg)? {
                         AwaitingReadyServerMessage::OpponentReadied => {
+                            let other_player = self.other_player();
                             let colour = Color::Green;
                             if is_left_player {
-                                display_status_right(&mut stdout, "    opponent is ready", colour);
+                                display_status_right(
+                                    &mut stdout,
+                                    screen,
+                                    &format!("    {other_player} is ready"),
+                                    colour,
+                                );
                             } else {
-                                display_status_left(&mut stdout, "opponent is ready    ", colour);
+                                display_status_left(
+                                    &mut stdout,
+                                    &format!("{other_player} is ready    "),
+                                    colour,
+                                );
                             }
                         }
                         AwaitingReadyServerMessage::OpponentUnreadied => {
-                            let text = "opponent is not ready";
+                            let text = format!("{} is not ready", self.other_player());
                             let colour = Color::Red;
                             if is_left_player {
-                                display_status_right(&mut stdout, text, colour);
+                                display_status_right(&mut stdout, screen, &text, colour);
                             } else {
-                                display_status_left(&mut stdout, text, colour);
+                                display_status_left(&mut stdout, &text, colour);
                             }
                         }
                         AwaitingReadyServerMessage::YouReadied => {
@@ -314,7 +1424,12 @@ impl TcpClient {
                             if is_left_player {
                                 display_status_left(&mut stdout, "you are ready    ", colour);
                             } else {
-                                display_status_right(&mut stdout, "    you are ready", colour);
+                                display_status_right(
+                                    &mut stdout,
+                                    screen,
+                                    "    you are ready",
+                                    colour,
+                                );
                             }
                         }
                         AwaitingReadyServerMessage::YouUnreadied => {
@@ -325,106 +1440,636 @@ impl TcpClient {
                             if is_left_player {
                                 display_status_left(&mut stdout, text, colour);
                             } else {
-                                display_status_right(&mut stdout, text, colour);
+                                display_status_right(&mut stdout, screen, text, colour);
                             }
                         }
-                        AwaitingReadyServerMessage::GameStarted => {
+                        AwaitingReadyServerMessage::GameStarted {
+                            tick_interval_ms: game_tick_interval_ms,
+                        } => {
+                            tick_interval_ms = game_tick_interval_ms;
+                            let _ = kill_keys_tx.send(());
+                            break;
+                        }
+                        AwaitingReadyServerMessage::HandicappedGameStarted {
+                            tick_interval_ms: game_tick_interval_ms,
+                            handicaps: game_handicaps,
+                        } => {
+                            tick_interval_ms = game_tick_interval_ms;
+                            handicaps = game_handicaps;
                             let _ = kill_keys_tx.send(());
                             break;
                         }
+                        AwaitingReadyServerMessage::LobbyRuleset { ruleset } => {
+                            self.ruleset = Some(ruleset);
+                            rules = self.rules();
+                            display_countdown(
+                                &mut stdout,
+                                screen,
+                                ready_deadline,
+                                rules.as_deref(),
+                            );
+                        }
+                        AwaitingReadyServerMessage::CoopLobby => {
+                            self.coop = true;
+                            rules = self.rules();
+                            display_countdown(
+                                &mut stdout,
+                                screen,
+                                ready_deadline,
+                                rules.as_deref(),
+                            );
+                            // it's sent as soon as the partner joins, before either can ready up.
+                            let colour = Color::Red;
+                            if is_left_player {
+                                display_status_right(
+                                    &mut stdout,
+                                    screen,
+                                    " partner is not ready",
+                                    colour,
+                                );
+                            } else {
+                                display_status_left(&mut stdout, "partner is not ready ", colour);
+                            }
+                        }
+                        AwaitingReadyServerMessage::OpponentCosmetics { cosmetics } => {
+                            self.opponent_cosmetics = cosmetics;
+                        }
+                        AwaitingReadyServerMessage::LockstepGame => {
+                            self.lockstep = true;
+                        }
                         AwaitingReadyServerMessage::OpponentLeft => {
-                            game_over_tx.send(Quit::OpponentLeft).unwrap();
+                            lobby_closed = Some((Quit::OpponentLeft, "opponent left"));
+                            let _ = kill_keys_tx.send(());
+                            break;
+                        }
+                        AwaitingReadyServerMessage::LobbyTimedOut => {
+                            lobby_closed = Some((Quit::LobbyTimedOut, "lobby timed out"));
+                            let _ = kill_keys_tx.send(());
+                            break;
+                        }
+                        AwaitingReadyServerMessage::OpponentLeftLobby => {
+                            opponent_left_lobby = true;
+                            let _ = kill_keys_tx.send(());
+                            break;
+                        }
+                        AwaitingReadyServerMessage::PromotedToHost { lobby_id } => {
+                            self.lobby_id = lobby_id;
+                            opponent_left_lobby = true;
+                            let _ = kill_keys_tx.send(());
+                            break;
                         }
                     };
                 }
             }
         }
-        Some(msg_listener.join().unwrap())
+        let ready_key_rx = ready_key_listener.join().unwrap();
+        // the listener only gives up the session when the client is quitting.
+        let session = msg_listener.join().unwrap()?;
+        let exit = match lobby_closed {
+            None if opponent_left_lobby => {
+                ReadyScreenExit::OpponentLeftLobby(session.reopen_lobby())
+            }
+            None => ReadyScreenExit::GameStarted(session.start_game(), handicaps, tick_interval_ms),
+            Some((_, result)) if self.in_tournament => {
+                ReadyScreenExit::LobbyClosed(result, session)
+            }
+            Some((quit, _)) => {
+                game_over_tx.send(quit).unwrap();
+                return None;
+            }
+        };
+        Some((exit, ready_key_rx))
     }
+}
 
-    fn send<M>(stream: &mut TcpStream, message: M)
-    where
-        Vec<u8>: From<M>,
-    {
-        stream.write_all(&Vec::<u8>::from(message)).unwrap();
+/// ends the client with the error's details if the message couldn't be received.
+fn quit_on_error<T>(game_over_tx: &Sender<Quit>, result: Result<T, ReceiveError>) -> Option<T> {
+    result
+        .map_err(|err| {
+            let _ = game_over_tx.send(Quit::Error(err.to_string()));
+        })
+        .ok()
+}
+
+/// lists the page's lobbies, marking the selected one.
+fn draw_lobby_page(stdout: &mut Stdout, lobby_page: &LobbyPage, selected: usize, ready_name: &str) {
+    let mut lines = vec![format!(
+        "open lobbies, page {} of {}",
+        lobby_page.page + 1,
+        lobby_page.pages
+    )];
+    if lobby_page.lobbies.is_empty() {
+        lines.push("  no lobbies are waiting for an opponent".to_owned());
+    }
+    for (i, lobby) in lobby_page.lobbies.iter().enumerate() {
+        let marker = if i == selected { ">" } else { " " };
+        let tick_rate = tick_rate(lobby.tick_interval_ms);
+        let age = match lobby.age_secs {
+            secs if secs < 60 => format!("{secs}s"),
+            secs => format!("{}m {}s", secs / 60, secs % 60),
+        };
+        lines.push(format!(
+            "{marker} {}  {tick_rate:<6}  waiting {age}",
+            lobby.lobby_id
+        ));
+    }
+    lines.push(String::new());
+    lines.push("up/down to choose, enter to join".to_owned());
+    lines.push(format!(
+        "left/right to change page, {ready_name} to refresh"
+    ));
+    execute!(stdout, Clear(ClearType::All), MoveTo(0, 0)).unwrap();
+    for line in lines {
+        execute!(stdout, Print(line), MoveToNextLine(1)).unwrap();
     }
+    stdout.flush().unwrap();
+}
 
-    fn await_msg<'a, R>(&'a mut self) -> Result<R, AwaitMsgError>
-    where
-        R: TryFrom<&'a [u8], Error = DeserializeMessageError>,
-    {
-        let buffer = &mut self.server_msg_buffer;
-        buffer.clear();
-        let n = self
-            .stream
-            .read_until(SERVER_MESSAGE_DELIMITER, buffer)
-            .map_err(|err| AwaitMsgError::IOError(err))?;
-        if n == 0 {
-            return Err(AwaitMsgError::ServerClosedConnection);
+fn connect(server_addr: &str) -> io::Result<TcpStream> {
+    let stream = TcpStream::connect(server_addr)?;
+    // holding back messages as small as the game's to send them together only delays them.
+    stream.set_nodelay(true)?;
+    if let Some(keepalive) = keepalive_from_env()? {
+        keepalive.apply(&stream)?;
+    }
+    Ok(stream)
+}
+
+/// the keepalive set with `PONG_KEEPALIVE_SECS`, `PONG_KEEPALIVE_INTERVAL_SECS` and
+/// `PONG_KEEPALIVE_RETRIES`, if any.
+fn keepalive_from_env() -> io::Result<Option<Keepalive>> {
+    let Some(idle_secs) = optional_env_var("PONG_KEEPALIVE_SECS")? else {
+        return Ok(None);
+    };
+    Ok(Some(Keepalive {
+        idle: Duration::from_secs(idle_secs),
+        interval: optional_env_var("PONG_KEEPALIVE_INTERVAL_SECS")?.map(Duration::from_secs),
+        retries: optional_env_var("PONG_KEEPALIVE_RETRIES")?,
+    }))
+}
+
+fn optional_env_var<T: FromStr>(key: &str) -> io::Result<Option<T>> {
+    let Ok(value) = std::env::var(key) else {
+        return Ok(None);
+    };
+    value.parse().map(Some).map_err(|_| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid value for {key}: {value}"),
+        )
+    })
+}
+
+/// connects to the server just to ask for its status.
+pub(crate) fn server_status(server_addr: &str) -> Result<ServerStatus, String> {
+    let stream = connect(server_addr).map_err(|err| err.to_string())?;
+    let session = Session::new(stream.try_clone().map_err(|err| err.to_string())?, stream);
+    let session = session.server_status().map_err(|err| err.to_string())?;
+    let (status, _) = session.await_status().map_err(|err| err.to_string())?;
+    Ok(status)
+}
+
+/// the size of the field as it's drawn, which is turned on its side in portrait mode so that it
+/// fits narrow terminals. the left paddle is then drawn along the top, the right paddle along the
+/// bottom, and the ball's x and y swap places.
+#[derive(Clone, Copy)]
+struct Screen {
+    width: u16,
+    height: u16,
+    portrait: bool,
+}
+
+impl Screen {
+    fn new(dimensions: GameDimensions, portrait: bool) -> Screen {
+        let (width, height) = if portrait {
+            (dimensions.height(), dimensions.width())
+        } else {
+            (dimensions.width(), dimensions.height())
+        };
+        Screen {
+            width,
+            height,
+            portrait,
         }
-        R::try_from(&buffer[..n - 1]).map_err(|err| AwaitMsgError::DeserializeMsg(err))
     }
+
+    fn width(&self) -> u16 {
+        self.width
+    }
+
+    fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// what the barriers above and below the field are drawn with.
+    fn barrier(&self) -> char {
+        if self.portrait {
+            '.'
+        } else {
+            '-'
+        }
+    }
+}
+
+/// how far right to move for `text` to be centred in the field.
+fn centre_offset(screen: Screen, text: &str) -> u16 {
+    screen.width().saturating_sub(text.len() as u16) / 2
 }
 
-fn draw_game(mut w: StdoutLock, left_paddle: u8, right_paddle: u8, ball: Ball) {
-    clear(&mut w);
+/// what was last drawn in each cell of the field, so that each frame only redraws the cells that
+/// changed since the one before rather than clearing and redrawing the whole field.
+struct Frame {
+    screen: Screen,
+    /// row by row, or nothing until the field is first drawn, since whatever was on it before
+    /// isn't known.
+    cells: Option<Vec<Option<StyledContent<char>>>>,
+}
+
+impl Frame {
+    fn new(screen: Screen) -> Frame {
+        Frame {
+            screen,
+            cells: None,
+        }
+    }
+
+    /// draws the positions from the start of the field's top row, leaving the cursor at the start
+    /// of its bottom barrier.
+    fn draw(
+        &mut self,
+        mut w: StdoutLock,
+        dimensions: GameDimensions,
+        handicaps: Handicaps,
+        looks: PaddleLooks,
+        positions: Positions,
+    ) {
+        let cells = field_cells(self.screen, dimensions, handicaps, looks, positions);
+        let width = self.screen.width() as usize;
+        match &self.cells {
+            Some(drawn) => {
+                for (i, (&cell, &drawn)) in cells.iter().zip(drawn).enumerate() {
+                    if cell != drawn {
+                        let symbol = cell.unwrap_or(style(' '));
+                        draw_cell(&mut w, (i / width) as u16, (i % width) as u16, symbol);
+                    }
+                }
+            }
+            None => {
+                clear(&mut w, self.screen);
+                for (i, &cell) in cells.iter().enumerate() {
+                    if let Some(symbol) = cell {
+                        draw_cell(&mut w, (i / width) as u16, (i % width) as u16, symbol);
+                    }
+                }
+            }
+        }
+        execute!(w, MoveDown(self.screen.height()), MoveToColumn(0)).unwrap();
+        w.flush().unwrap();
+        self.cells = Some(cells);
+    }
+}
+
+/// what's in each cell of the field, row by row, with the paddles drawn over the ball.
+fn field_cells(
+    screen: Screen,
+    dimensions: GameDimensions,
+    handicaps: Handicaps,
+    looks: PaddleLooks,
+    positions: Positions,
+) -> Vec<Option<StyledContent<char>>> {
+    let width = screen.width() as usize;
+    let mut cells = vec![None; width * screen.height() as usize];
+    let mut set = |row: u16, column: u16, symbol: StyledContent<char>| {
+        if let Some(cell) = cells.get_mut(row as usize * width + column as usize) {
+            *cell = Some(symbol);
+        }
+    };
+    // in portrait mode the paddles lie along the top and bottom rows, and the ball's x and y swap
+    // places.
+    let (ball_row, ball_column) = if screen.portrait {
+        (positions.ball_x, positions.ball_y)
+    } else {
+        (positions.ball_y, positions.ball_x)
+    };
+    set(ball_row, ball_column, style('o'));
+    let (near, far, symbol) = if screen.portrait {
+        (0, screen.height() - 1, '=')
+    } else {
+        (0, screen.width() - 1, '|')
+    };
+    // the partner of a co-op game defends the same side as the host, who is drawn over them.
+    let paddles = [
+        (
+            near,
+            Handicap::NONE,
+            positions.partner_paddle,
+            paddle_symbol(looks.partner, ':'),
+        ),
+        (
+            near,
+            handicaps.side(Side::Left),
+            Some(positions.left_paddle),
+            paddle_symbol(looks.left, symbol),
+        ),
+        (
+            far,
+            handicaps.side(Side::Right),
+            Some(positions.right_paddle),
+            paddle_symbol(looks.right, symbol),
+        ),
+    ];
+    for (line, handicap, paddle, symbol) in paddles {
+        let Some(paddle) = paddle else {
+            continue;
+        };
+        for along in paddle..paddle + handicap.paddle_height(dimensions) {
+            if screen.portrait {
+                set(line, along, symbol);
+            } else {
+                set(along, line, symbol);
+            }
+        }
+    }
+    cells
+}
+
+/// draws a character in the field, from and back to the start of its top row.
+fn draw_cell<W: Write>(w: &mut W, row: u16, column: u16, symbol: StyledContent<char>) {
     execute!(
         w,
-        MoveRight(ball.x as u16 + 1),
+        MoveRight(column + 1),
         MoveLeft(1),
-        MoveDown(ball.y as u16 + 1),
+        MoveDown(row + 1),
         MoveUp(1),
-        Print('o'),
+        Print(symbol),
         MoveToColumn(0),
-        MoveUp(ball.y as u16 + 1),
+        MoveUp(row + 1),
         MoveDown(1),
     )
     .unwrap();
-    draw_paddle(&mut w, left_paddle);
+}
+
+fn clear<W: Write>(w: &mut W, screen: Screen) {
+    for _ in 0..screen.height() {
+        execute!(w, Clear(ClearType::CurrentLine), MoveToNextLine(1)).unwrap();
+    }
+    execute!(w, MoveUp(screen.height())).unwrap();
+}
+
+/// waits for the terminal to fit the field, its barriers and the line below them, asking for it
+/// to be enlarged until it does. the field is drawn by moving the cursor relative to where it is,
+/// which a smaller terminal would throw off. returns whether the terminal was cleared to ask, in
+/// which case the cursor is left in its top left corner.
+fn await_terminal_fits(stdout: &mut Stdout, screen: Screen, resizes: &Receiver<()>) -> bool {
+    let (width, height) = (screen.width(), screen.height() + 3);
+    let mut asked = false;
+    while terminal::size().is_ok_and(|(columns, rows)| columns < width || rows < height) {
+        if !asked {
+            execute!(
+                stdout,
+                Clear(ClearType::All),
+                MoveTo(0, 0),
+                Print(format!(
+                    "please enlarge your terminal to at least {width}x{height}"
+                )),
+            )
+            .unwrap();
+            asked = true;
+        }
+        let _ = resizes.recv_timeout(TERMINAL_SIZE_POLL_INTERVAL);
+    }
+    if asked {
+        execute!(stdout, Clear(ClearType::All), MoveTo(0, 0)).unwrap();
+    }
+    asked
+}
+
+fn draw_barriers<W: Write>(w: &mut W, screen: Screen) {
+    draw_barrier(w, screen);
+    execute!(w, MoveDown(screen.height() + 1)).unwrap();
+    draw_barrier(w, screen);
+    execute!(w, MoveUp(screen.height() + 1)).unwrap();
+}
+
+/// draws the wall along the top or bottom of the field, or in portrait mode the goal line behind
+/// a paddle.
+fn draw_barrier<W: Write>(w: &mut W, screen: Screen) {
+    for _ in 0..screen.width() {
+        execute!(w, Print(screen.barrier())).unwrap();
+    }
+    execute!(w, MoveLeft(screen.width())).unwrap();
+}
+
+/// redraws the top barrier from the bottom one, on which the cursor rests during a game, lit up
+/// in yellow or back in the terminal's colour.
+fn display_top_barrier(stdout: &mut Stdout, screen: Screen, lit: bool) {
+    let colour = if lit { Color::Yellow } else { Color::Reset };
     execute!(
-        w,
-        MoveUp(GAME_HEIGHT as u16),
-        MoveRight(GAME_WIDTH as u16 - 1),
+        stdout,
+        MoveUp(screen.height() + 1),
+        SetForegroundColor(colour)
+    )
+    .unwrap();
+    draw_barrier(stdout, screen);
+    execute!(
+        stdout,
+        SetForegroundColor(Color::Reset),
+        MoveDown(screen.height() + 1),
     )
     .unwrap();
-    draw_paddle(&mut w, right_paddle);
-    execute!(w, MoveToColumn(0)).unwrap();
-    w.flush().unwrap();
+    stdout.flush().unwrap();
 }
 
-fn clear<W: Write>(w: &mut W) {
-    for _ in 0..GAME_HEIGHT {
-        execute!(w, Clear(ClearType::CurrentLine), MoveToNextLine(1)).unwrap();
+/// how each of the paddles on the field looks.
+#[derive(Clone, Copy)]
+struct PaddleLooks {
+    left: Cosmetics,
+    right: Cosmetics,
+    partner: Cosmetics,
+}
+
+/// what a paddle is drawn with, given what it's drawn with when nothing was chosen.
+fn paddle_symbol(cosmetics: Cosmetics, default: char) -> StyledContent<char> {
+    let symbol = style(cosmetics.glyph.unwrap_or(default));
+    match cosmetics.colour {
+        Some(colour) => symbol.with(terminal_colour(colour)),
+        None => symbol,
+    }
+}
+
+fn terminal_colour(colour: PaddleColour) -> Color {
+    match colour {
+        PaddleColour::Red => Color::Red,
+        PaddleColour::Green => Color::Green,
+        PaddleColour::Yellow => Color::Yellow,
+        PaddleColour::Blue => Color::Blue,
+        PaddleColour::Magenta => Color::Magenta,
+        PaddleColour::Cyan => Color::Cyan,
+        PaddleColour::White => Color::White,
     }
-    execute!(w, MoveUp(GAME_HEIGHT as u16)).unwrap();
 }
 
-fn draw_barriers<W: Write>(w: &mut W) {
-    draw_barrier(w);
-    execute!(w, MoveDown(GAME_HEIGHT as u16 + 1)).unwrap();
-    draw_barrier(w);
-    execute!(w, MoveUp(GAME_HEIGHT as u16 + 1)).unwrap();
+/// one of the paddles on the field.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Paddle {
+    Left,
+    Right,
+    /// the joiner's paddle in a co-op game, which also defends the left side.
+    Partner,
 }
 
-fn draw_barrier<W: Write>(w: &mut W) {
-    for _ in 0..GAME_WIDTH {
-        execute!(w, Print("-")).unwrap();
+/// where everything on the field is drawn.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Positions {
+    left_paddle: u16,
+    right_paddle: u16,
+    partner_paddle: Option<u16>,
+    ball_x: u16,
+    ball_y: u16,
+}
+
+/// the last two game states received, shared with the thread that draws the field.
+struct RenderSlot {
+    previous: Option<Positions>,
+    latest: Positions,
+    received_at: Instant,
+    /// how long the game took to get from the previous state to the latest, which the renderer
+    /// takes to catch up with the latest.
+    interval: Duration,
+}
+
+impl RenderSlot {
+    /// the slot after a new game state arrives. the game ticks faster as the ball speeds up, so
+    /// the interval is measured rather than assumed, but it's no longer than the lobby's tick
+    /// interval so that a pause, such as before a serve, isn't drawn in slow motion.
+    fn next(slot: Option<RenderSlot>, latest: Positions, tick_interval_ms: u16) -> RenderSlot {
+        let tick_interval = Duration::from_millis(tick_interval_ms as u64);
+        let interval = slot
+            .as_ref()
+            .map_or(tick_interval, |slot| slot.received_at.elapsed())
+            .min(tick_interval);
+        RenderSlot {
+            previous: slot.map(|slot| slot.latest),
+            latest,
+            received_at: Instant::now(),
+            interval,
+        }
+    }
+
+    /// where to draw everything now, part way from the previous state to the latest.
+    fn positions(&self, own_paddle: Paddle) -> Positions {
+        let Some(previous) = self.previous else {
+            return self.latest;
+        };
+        let progress = (self.received_at.elapsed().as_secs_f32()
+            / self.interval.as_secs_f32().max(f32::EPSILON))
+        .min(1.0);
+        let paddle = |paddle: Paddle, from: u16, to: u16| {
+            if paddle == own_paddle {
+                to
+            } else {
+                interpolate(from, to, MAX_PADDLE_CELLS_PER_TICK, progress)
+            }
+        };
+        Positions {
+            left_paddle: paddle(Paddle::Left, previous.left_paddle, self.latest.left_paddle),
+            right_paddle: paddle(
+                Paddle::Right,
+                previous.right_paddle,
+                self.latest.right_paddle,
+            ),
+            partner_paddle: match (previous.partner_paddle, self.latest.partner_paddle) {
+                (Some(from), Some(to)) => Some(paddle(Paddle::Partner, from, to)),
+                (_, latest) => latest,
+            },
+            // the ball moves a cell each tick along each axis.
+            ball_x: interpolate(previous.ball_x, self.latest.ball_x, 1, progress),
+            ball_y: interpolate(previous.ball_y, self.latest.ball_y, 1, progress),
+        }
     }
-    execute!(w, MoveLeft(GAME_WIDTH as u16)).unwrap();
 }
 
-fn draw_paddle<W: Write>(w: &mut W, paddle: u8) {
-    for _ in 0..paddle {
-        execute!(w, MoveDown(1)).unwrap();
+/// a position `progress` of the way from `from` to `to`, rounded to a cell. anything further
+/// apart than a tick's move, like a ball that wrapped around or was served again, jumps straight
+/// to `to`.
+fn interpolate(from: u16, to: u16, max_move: u16, progress: f32) -> u16 {
+    if from.abs_diff(to) > max_move {
+        return to;
     }
-    for _ in 0..PADDLE_HEIGHT {
-        execute!(w, Print('|'), MoveLeft(1), MoveDown(1)).unwrap();
+    (from as f32 + (to as f32 - from as f32) * progress).round() as u16
+}
+
+/// redraws the field from the bottom barrier, on which the cursor rests during a game.
+fn draw_positions(
+    frame: &mut Frame,
+    dimensions: GameDimensions,
+    handicaps: Handicaps,
+    looks: PaddleLooks,
+    positions: Positions,
+) {
+    let mut stdout = stdout().lock();
+    execute!(stdout, MoveUp(frame.screen.height())).unwrap();
+    frame.draw(stdout, dimensions, handicaps, looks, positions);
+}
+
+/// the direction a move key moves the paddle in.
+fn move_direction(down: bool) -> MoveDirection {
+    if down {
+        MoveDirection::Down
+    } else {
+        MoveDirection::Up
     }
-    for _ in 0..GAME_HEIGHT - PADDLE_HEIGHT - paddle {
-        execute!(w, MoveDown(1)).unwrap();
+}
+
+/// how well game state updates are arriving during a game.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ConnectionHealth {
+    /// updates are arriving every tick.
+    Good,
+    /// a few ticks have passed without an update.
+    Lagging,
+    /// the game looks frozen, as either player's connection has stopped delivering updates.
+    Stalled,
+    /// no game is being played.
+    Unknown,
+}
+
+impl ConnectionHealth {
+    /// the health of the connection, given when the last message arrived.
+    fn since(last_message: Instant, tick_interval_ms: u16) -> ConnectionHealth {
+        let tick_interval = Duration::from_millis(tick_interval_ms as u64);
+        let elapsed = last_message.elapsed();
+        if elapsed > (tick_interval * STALLED_TICKS).max(STALLED_MIN) {
+            ConnectionHealth::Stalled
+        } else if elapsed > tick_interval * LAGGING_TICKS {
+            ConnectionHealth::Lagging
+        } else {
+            ConnectionHealth::Good
+        }
     }
 }
 
+/// draws the connection's health at the right end of the field's bottom barrier, on which the
+/// cursor rests during a game.
+fn display_connection_health(stdout: &mut Stdout, screen: Screen, health: ConnectionHealth) {
+    let (glyph, colour) = match health {
+        ConnectionHealth::Good => ('*', Color::Green),
+        ConnectionHealth::Lagging => ('*', Color::Yellow),
+        ConnectionHealth::Stalled => ('*', Color::Red),
+        ConnectionHealth::Unknown => (screen.barrier(), Color::Reset),
+    };
+    execute!(
+        stdout,
+        MoveRight(screen.width() - 1),
+        SetForegroundColor(colour),
+        Print(glyph),
+        SetForegroundColor(Color::Reset),
+        MoveToColumn(0),
+    )
+    .unwrap();
+    stdout.flush().unwrap();
+}
+
 fn display_status_left(stdout: &mut Stdout, text: &str, colour: Color) {
     execute!(
         stdout,
@@ -437,10 +2082,10 @@ fn display_status_left(stdout: &mut Stdout, text: &str, colour: Color) {
     stdout.flush().unwrap();
 }
 
-fn display_status_right(stdout: &mut Stdout, text: &str, colour: Color) {
+fn display_status_right(stdout: &mut Stdout, screen: Screen, text: &str, colour: Color) {
     execute!(
         stdout,
-        MoveRight(GAME_WIDTH as u16 - text.len() as u16),
+        MoveRight(screen.width().saturating_sub(text.len() as u16)),
         SetForegroundColor(colour),
         Print(text),
         SetForegroundColor(Color::Reset),
@@ -450,34 +2095,315 @@ fn display_status_right(stdout: &mut Stdout, text: &str, colour: Color) {
     stdout.flush().unwrap();
 }
 
-enum AwaitingReadyEvent {
-    ReadyKeyPressed,
-    ServerMessageReceived(Result<AwaitingReadyServerMessage, AwaitMsgError>),
+/// the name of a tick interval, as the host would have chosen it.
+fn tick_rate(tick_interval_ms: u16) -> String {
+    match tick_interval_ms {
+        SLOW_TICK_INTERVAL_MS => "slow".to_owned(),
+        NORMAL_TICK_INTERVAL_MS => "normal".to_owned(),
+        FAST_TICK_INTERVAL_MS => "fast".to_owned(),
+        ms => format!("{ms}ms"),
+    }
 }
 
-#[derive(Debug)]
-enum AwaitMsgError {
-    ServerClosedConnection,
-    DeserializeMsg(DeserializeMessageError),
-    IOError(std::io::Error),
+/// describes the rules a lobby is played under for its ready screen, leaving out any that are
+/// the same as in a classic game.
+fn describe_ruleset(ruleset: Ruleset, dimensions: GameDimensions, is_left_player: bool) -> String {
+    let mut rules = Vec::new();
+    if let Some(match_length) = ruleset.match_length {
+        rules.push(format!("first to {match_length}"));
+    }
+    if ruleset.tick_interval_ms != NORMAL_TICK_INTERVAL_MS {
+        rules.push(format!("{} speed", tick_rate(ruleset.tick_interval_ms)));
+    }
+    match ruleset.modifier {
+        Modifier::Classic => {}
+        Modifier::Gravity => rules.push("gravity".to_owned()),
+        Modifier::Wobble => rules.push("wobble".to_owned()),
+        Modifier::Wrap => rules.push("wrap".to_owned()),
+    }
+    let (own, opponent) = match is_left_player {
+        true => (ruleset.handicaps.left, ruleset.handicaps.right),
+        false => (ruleset.handicaps.right, ruleset.handicaps.left),
+    };
+    for (whose, handicap) in [("your", own), ("opponent's", opponent)] {
+        if handicap != Handicap::NONE {
+            rules.push(format!(
+                "{whose} paddle {} tall, speed {}",
+                handicap.paddle_height(dimensions),
+                handicap.paddle_speed(),
+            ));
+        }
+    }
+    if rules.is_empty() {
+        "classic rules".to_owned()
+    } else {
+        rules.join(", ")
+    }
 }
 
-impl Display for AwaitMsgError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            AwaitMsgError::ServerClosedConnection => Display::fmt("server closed connection", f),
-            AwaitMsgError::DeserializeMsg(err) => Display::fmt(err, f),
-            AwaitMsgError::IOError(err) => Display::fmt(err, f),
-        }
+/// displays the time left before the lobby is closed, on the line below the ready statuses, after
+/// the lobby's rules once they're known.
+fn display_countdown(
+    stdout: &mut Stdout,
+    screen: Screen,
+    ready_deadline: Instant,
+    rules: Option<&str>,
+) {
+    let secs_left = ready_deadline
+        .saturating_duration_since(Instant::now())
+        .as_secs();
+    let countdown = format!("lobby closes in {}:{:02}", secs_left / 60, secs_left % 60);
+    let text = match rules {
+        Some(rules) => format!("{rules} - {countdown}"),
+        None => countdown,
+    };
+    execute!(
+        stdout,
+        MoveDown(1),
+        Clear(ClearType::CurrentLine),
+        MoveRight(centre_offset(screen, &text)),
+        Print(text),
+        MoveUp(1),
+        MoveToColumn(0),
+    )
+    .unwrap();
+    stdout.flush().unwrap();
+}
+
+/// displays how fast the ball is going on the line below the field, after the stakes of the game
+/// if it's a tiebreak, or clears it once the ball is back to its starting speed.
+fn display_speed_level(stdout: &mut Stdout, screen: Screen, speed_level: u8, stakes: Option<&str>) {
+    let speed = (speed_level > 0).then(|| format!("speed {speed_level}/{MAX_SPEED_LEVEL}"));
+    display_game_status(stdout, screen, stakes, speed.as_deref());
+}
+
+/// describes whose match point a game is, with the score from the player's point of view, e.g.
+/// "match point to you, 2-1".
+fn describe_match_point(own_wins: u16, opponent_wins: u16) -> String {
+    let leader = if own_wins > opponent_wins {
+        "you"
+    } else {
+        "opponent"
+    };
+    format!("match point to {leader}, {own_wins}-{opponent_wins}")
+}
+
+/// describes how a co-op game is going, e.g. "score 12, server speed 1/3".
+fn describe_survival(score: u16) -> String {
+    format!(
+        "score {score}, server speed {}/{MAX_AI_SPEED_LEVEL}",
+        ai_speed_level(score)
+    )
+}
+
+/// displays who is serving on the line below the field, after the stakes of the game if it's a
+/// tiebreak, or clears it once the ball is served.
+fn display_serve_prompt(
+    stdout: &mut Stdout,
+    screen: Screen,
+    own_serve: Option<bool>,
+    stakes: Option<&str>,
+) {
+    let prompt = match own_serve {
+        Some(true) => Some("your serve, press a move key"),
+        Some(false) => Some("opponent's serve"),
+        None => None,
+    };
+    display_game_status(stdout, screen, stakes, prompt);
+}
+
+/// replaces the line below the field with the given parts, separated by dashes.
+fn display_game_status(
+    stdout: &mut Stdout,
+    screen: Screen,
+    stakes: Option<&str>,
+    status: Option<&str>,
+) {
+    execute!(stdout, MoveDown(1), Clear(ClearType::CurrentLine)).unwrap();
+    let text = [stakes, status].into_iter().flatten().collect::<Vec<_>>();
+    if !text.is_empty() {
+        let text = text.join(" - ");
+        execute!(stdout, MoveRight(centre_offset(screen, &text)), Print(text),).unwrap();
+    }
+    execute!(stdout, MoveUp(1), MoveToColumn(0)).unwrap();
+    stdout.flush().unwrap();
+}
+
+/// displays a spinner and how long the host has waited for an opponent on the line below the
+/// lobby id, swapped for a reminder to share the id every so often.
+fn display_waiting(stdout: &mut Stdout, screen: Screen, lobby_id: LobbyId, waited: Duration) {
+    let secs = waited.as_secs();
+    let text = if secs >= SHARE_REMINDER_INTERVAL_SECS
+        && secs % SHARE_REMINDER_INTERVAL_SECS < SHARE_REMINDER_SECS
+    {
+        format!("still waiting... share code {lobby_id}")
+    } else {
+        let frame = SPINNER[(waited.as_millis() / SPINNER_FRAME_MS) as usize % SPINNER.len()];
+        format!(
+            "{frame} waiting for an opponent {}:{:02}",
+            secs / 60,
+            secs % 60
+        )
+    };
+    execute!(
+        stdout,
+        MoveDown(1),
+        Clear(ClearType::CurrentLine),
+        MoveRight(centre_offset(screen, &text)),
+        Print(text),
+        MoveUp(1),
+        MoveToColumn(0),
+    )
+    .unwrap();
+    stdout.flush().unwrap();
+}
+
+/// displays the time left before an unjoined lobby expires, on the line below the lobby id, or
+/// clears it if the lobby isn't about to expire.
+fn display_expiry_warning(
+    stdout: &mut Stdout,
+    screen: Screen,
+    expires_at: Option<Instant>,
+    ready_name: &str,
+) {
+    let text = expires_at.map(|expires_at| {
+        let secs_left = expires_at
+            .saturating_duration_since(Instant::now())
+            .as_secs();
+        format!("lobby closes in {secs_left}s, press {ready_name} to keep it open")
+    });
+    execute!(stdout, MoveDown(1), Clear(ClearType::CurrentLine)).unwrap();
+    if let Some(text) = text {
+        execute!(stdout, MoveRight(centre_offset(screen, &text)), Print(text),).unwrap();
     }
+    execute!(stdout, MoveUp(1), MoveToColumn(0)).unwrap();
+    stdout.flush().unwrap();
+}
+
+/// what the client knows about the tournament it's in, redrawn whenever that changes.
+#[derive(Default)]
+struct TournamentScreen {
+    tournament_id: Option<LobbyId>,
+    players: u8,
+    seat: u8,
+    players_joined: u16,
+    /// only known once the tournament has started.
+    bracket: Option<Bracket>,
+    /// only known once the league has started.
+    standings: Option<Standings>,
+    /// the result of the player's last match.
+    last_result: Option<&'static str>,
 }
 
-impl Error for AwaitMsgError {
-    fn cause(&self) -> Option<&dyn Error> {
-        match self {
-            AwaitMsgError::ServerClosedConnection => None,
-            AwaitMsgError::DeserializeMsg(err) => Some(err),
-            AwaitMsgError::IOError(err) => Some(err),
+impl TournamentScreen {
+    fn draw(&self, stdout: &mut Stdout) {
+        let mut lines = Vec::new();
+        if let Some(tournament_id) = self.tournament_id {
+            lines.push(format!("tournament id: {tournament_id}"));
+        }
+        match (&self.bracket, &self.standings) {
+            (_, Some(standings)) => self.standings_table(standings, &mut lines),
+            (None, None) => lines.push(format!(
+                "{}/{} players joined",
+                self.players_joined, self.players
+            )),
+            (Some(bracket), None) => {
+                let rounds = rounds(self.players);
+                for round in 1..=rounds {
+                    lines.push(if round == rounds {
+                        "final".to_owned()
+                    } else {
+                        format!("round {round}")
+                    });
+                    let size = 1 << round;
+                    for start in (0..self.players).step_by(size as usize) {
+                        let middle = start + size / 2;
+                        lines.push(format!(
+                            "  {}",
+                            self.bracket_match(bracket, start..middle, middle..start + size, round)
+                        ));
+                    }
+                }
+                if bracket.seats[self.seat as usize].eliminated {
+                    lines.push("you're out, but can watch the rest of the tournament".to_owned());
+                }
+            }
+        }
+        if let Some(result) = self.last_result {
+            lines.push(format!("last match: {result}"));
+        }
+        execute!(stdout, Clear(ClearType::All), MoveTo(0, 0)).unwrap();
+        for line in lines {
+            execute!(stdout, Print(line), MoveToNextLine(1)).unwrap();
+        }
+        stdout.flush().unwrap();
+    }
+
+    /// lists the league's players from first to last, e.g. "2. you  1-1".
+    fn standings_table(&self, standings: &Standings, lines: &mut Vec<String>) {
+        let rounds = league_rounds(self.players);
+        lines.push(if standings.round == rounds {
+            "final table".to_owned()
+        } else {
+            format!("round {} of {rounds}", standings.round + 1)
+        });
+        let table = standings.table(self.players);
+        for (rank, &seat) in table[..self.players as usize].iter().enumerate() {
+            let standing = standings.seats[seat as usize];
+            let player = if seat == self.seat {
+                "you".to_owned()
+            } else {
+                format!("player {}", seat + 1)
+            };
+            lines.push(format!(
+                "  {}. {player:<8}  {}-{}{}",
+                rank + 1,
+                standing.wins,
+                standing.losses,
+                if standing.left { "  (left)" } else { "" }
+            ));
+        }
+    }
+
+    /// describes the match in the given round between the winners of two neighbouring parts of
+    /// the bracket, e.g. "player 1 beat you".
+    fn bracket_match(
+        &self,
+        bracket: &Bracket,
+        left: Range<u8>,
+        right: Range<u8>,
+        round: u8,
+    ) -> String {
+        let left_player = self.bracket_player(bracket, left.clone(), round - 1);
+        let right_player = self.bracket_player(bracket, right.clone(), round - 1);
+        match bracket.winner(left.start..right.end, round) {
+            Some(winner) if left.contains(&winner) => format!("{left_player} beat {right_player}"),
+            Some(_) => format!("{right_player} beat {left_player}"),
+            None => format!("{left_player} v {right_player}"),
         }
     }
+
+    /// names the player that won the given part of the bracket, "?" if it's still being decided,
+    /// or "-" if nobody from it is left.
+    fn bracket_player(&self, bracket: &Bracket, seats: Range<u8>, wins: u8) -> String {
+        match bracket.winner(seats.clone(), wins) {
+            Some(seat) if seat == self.seat => "you".to_owned(),
+            Some(seat) => format!("player {}", seat + 1),
+            None if seats
+                .into_iter()
+                .all(|seat| bracket.seats[seat as usize].eliminated) =>
+            {
+                "-".to_owned()
+            }
+            None => "?".to_owned(),
+        }
+    }
+}
+
+enum AwaitingReadyEvent {
+    ReadyKeyPressed,
+    CountdownTicked,
+    ServerMessageReceived(Result<AwaitingReadyServerMessage, ReceiveError>),
+    /// the server has the client in a state the ready screen can't carry on from.
+    Desynced,
 }
@@ -0,0 +1,56 @@
+use std::{
+    io,
+    net::{TcpListener, TcpStream},
+    thread::spawn,
+};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use server::{
+    client_conn::{ClientConn, Delivery},
+    tcp_stream_handler::TcpStreamHandler,
+};
+use shared::{
+    game_state::{Ball, GameState},
+    server_msg::PlayingServerMessage,
+};
+
+fn game_state_updated() -> PlayingServerMessage {
+    PlayingServerMessage::GameStateUpdated {
+        game_state: GameState {
+            left_paddle: 3,
+            right_paddle: 5,
+            ball: Ball {
+                x: 25,
+                y: 7,
+                moving_right: true,
+                moving_down: false,
+            },
+        },
+    }
+}
+
+/// a connection to a client that reads everything it's sent, as fast as it can.
+fn draining_conn(listener: &TcpListener) -> ClientConn {
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+    spawn(move || io::copy(&mut client, &mut io::sink()));
+    ClientConn::new(stream)
+}
+
+fn broadcast_game_state(c: &mut Criterion) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let left = draining_conn(&listener);
+    let right = draining_conn(&listener);
+    c.bench_function("broadcast game state to both players", |b| {
+        b.iter(|| {
+            TcpStreamHandler::broadcast(
+                &black_box(game_state_updated()),
+                [&left, &right],
+                Delivery::Superseding,
+            )
+        })
+    });
+}
+
+criterion_group!(benches, broadcast_game_state);
+criterion_main!(benches);
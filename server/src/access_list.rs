@@ -0,0 +1,243 @@
+use std::{
+    fs, io,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::SystemTime,
+};
+
+/// a range of ip addresses, written as an address and how many of its leading bits the range
+/// shares, e.g. `192.168.0.0/16`. a lone address is a range of just itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, address: IpAddr) -> bool {
+        // ipv4 clients of a dual-stack listener connect from ipv4-mapped ipv6 addresses.
+        match (self.network, address.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                self.prefix_matches(network.to_bits().into(), address.to_bits().into(), 32)
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                self.prefix_matches(network.to_bits(), address.to_bits(), 128)
+            }
+            _ => false,
+        }
+    }
+
+    /// whether the leading bits of two addresses `bits` long are the same.
+    fn prefix_matches(&self, network: u128, address: u128, bits: u32) -> bool {
+        self.prefix_len == 0 || (network ^ address) >> (bits - self.prefix_len as u32) == 0
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix_len) = match s.split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (s, None),
+        };
+        let network = address.parse::<IpAddr>().map_err(|_| ())?.to_canonical();
+        let max_prefix_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len.parse().map_err(|_| ())?,
+            None => max_prefix_len,
+        };
+        if prefix_len > max_prefix_len {
+            return Err(());
+        }
+        Ok(Self {
+            network,
+            prefix_len,
+        })
+    }
+}
+
+/// which addresses can connect to the server. written one rule a line, each either `allow` or
+/// `deny` followed by a range of addresses, with `#` starting a comment. an address in a denied
+/// range can't connect, and if any ranges are allowed, neither can an address outside of them.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct AccessList {
+    allowed: Vec<Cidr>,
+    denied: Vec<Cidr>,
+}
+
+impl AccessList {
+    pub fn permits(&self, address: IpAddr) -> bool {
+        (self.allowed.is_empty() || self.allowed.iter().any(|range| range.contains(address)))
+            && !self.denied.iter().any(|range| range.contains(address))
+    }
+}
+
+impl FromStr for AccessList {
+    /// describes the first line that isn't a rule.
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut list = AccessList::default();
+        for (i, line) in s.lines().enumerate() {
+            let rule = line.split('#').next().unwrap().trim();
+            if rule.is_empty() {
+                continue;
+            }
+            let (ranges, range) = match rule.split_once(char::is_whitespace) {
+                Some(("allow", range)) => (&mut list.allowed, range),
+                Some(("deny", range)) => (&mut list.denied, range),
+                _ => return Err(format!("line {} isn't an allow or deny rule", i + 1)),
+            };
+            let range = range
+                .trim()
+                .parse()
+                .map_err(|_| format!("line {} has an invalid address range", i + 1))?;
+            ranges.push(range);
+        }
+        Ok(list)
+    }
+}
+
+/// an access list read from a file, which is read again whenever the file changes, so that
+/// addresses can be banned without restarting the server.
+pub struct AccessListFile {
+    path: PathBuf,
+    list: AccessList,
+    /// when the file was last changed as of when it was read.
+    modified: Option<SystemTime>,
+}
+
+impl AccessListFile {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let modified = fs::metadata(path)?.modified().ok();
+        let list = read_access_list(path)?;
+        Ok(Self {
+            path: path.to_owned(),
+            list,
+            modified,
+        })
+    }
+
+    /// whether the address can connect, as of the latest version of the file. a file that can't be
+    /// read any more leaves the last list that could be in place.
+    pub fn permits(&mut self, address: IpAddr) -> bool {
+        self.reload_if_changed();
+        self.list.permits(address)
+    }
+
+    fn reload_if_changed(&mut self) {
+        let modified = fs::metadata(&self.path).and_then(|metadata| metadata.modified());
+        match modified {
+            Ok(modified) if Some(modified) == self.modified => {}
+            Ok(modified) => {
+                // only read once, so that a broken file isn't complained about on every connection.
+                self.modified = Some(modified);
+                match read_access_list(&self.path) {
+                    Ok(list) => {
+                        println!("reloaded access list {:?}", self.path);
+                        self.list = list;
+                    }
+                    Err(err) => eprintln!("failed to reload access list {:?}: {err}", self.path),
+                }
+            }
+            Err(err) => eprintln!("failed to check access list {:?}: {err}", self.path),
+        }
+    }
+}
+
+fn read_access_list(path: &Path) -> io::Result<AccessList> {
+    fs::read_to_string(path)?
+        .parse()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        net::IpAddr,
+        time::{Duration, SystemTime},
+    };
+
+    use crate::access_list::{AccessList, AccessListFile, Cidr};
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn cidr_contains() {
+        let lan: Cidr = "192.168.0.0/16".parse().unwrap();
+        assert!(lan.contains(ip("192.168.4.20")));
+        assert!(!lan.contains(ip("192.169.0.1")));
+        assert!(lan.contains(ip("::ffff:192.168.0.1")));
+        let host: Cidr = "10.0.0.7".parse().unwrap();
+        assert!(host.contains(ip("10.0.0.7")));
+        assert!(!host.contains(ip("10.0.0.8")));
+        let everything: Cidr = "::/0".parse().unwrap();
+        assert!(everything.contains(ip("2001:db8::1")));
+        assert!(!everything.contains(ip("10.0.0.7")));
+        let docs: Cidr = "2001:db8::/32".parse().unwrap();
+        assert!(docs.contains(ip("2001:db8:ffff::1")));
+        assert!(!docs.contains(ip("2001:db9::1")));
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("10.0.0/8".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn denied_ranges_win() {
+        let list: AccessList = "
+            # the lan, apart from one machine.
+            allow 192.168.0.0/16
+            deny 192.168.1.13 # too good at pong
+        "
+        .parse()
+        .unwrap();
+        assert!(list.permits(ip("192.168.1.12")));
+        assert!(!list.permits(ip("192.168.1.13")));
+        assert!(!list.permits(ip("8.8.8.8")));
+        let list: AccessList = "deny 8.8.0.0/16".parse().unwrap();
+        assert!(list.permits(ip("192.168.1.12")));
+        assert!(!list.permits(ip("8.8.8.8")));
+        assert_eq!(
+            "allow 10.0.0.0/8\nban 8.8.8.8".parse::<AccessList>(),
+            Err("line 2 isn't an allow or deny rule".to_owned())
+        );
+        assert_eq!(
+            "allow 10.0.0.0/40".parse::<AccessList>(),
+            Err("line 1 has an invalid address range".to_owned())
+        );
+    }
+
+    #[test]
+    fn reloads_once_changed() {
+        let path = std::env::temp_dir().join(format!("pong_access_list_{}", std::process::id()));
+        fs::write(&path, "deny 10.0.0.1").unwrap();
+        let mut file = AccessListFile::load(&path).unwrap();
+        assert!(!file.permits(ip("10.0.0.1")));
+        assert!(file.permits(ip("10.0.0.2")));
+        fs::write(&path, "deny 10.0.0.2").unwrap();
+        // the file may be written within the same tick of its clock.
+        let modified = SystemTime::now() + Duration::from_secs(1);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert!(file.permits(ip("10.0.0.1")));
+        assert!(!file.permits(ip("10.0.0.2")));
+        // a broken file leaves the last list in place.
+        fs::write(&path, "deny everyone").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified + Duration::from_secs(1))
+            .unwrap();
+        assert!(!file.permits(ip("10.0.0.2")));
+        fs::remove_file(&path).unwrap();
+    }
+}
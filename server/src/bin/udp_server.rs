@@ -15,11 +15,11 @@ fn main() {
 }
 
 fn deserialize_u64(u: &[u8]) -> u64 {
-    u.into_iter()
+    u.iter()
         .enumerate()
         .fold(0, |acc, (idx, n)| acc | ((*n as u64) << (8 * idx)))
 }
 
 fn serialize_u64(u: u64) -> [u8; 8] {
-    std::array::from_fn(|idx| (u >> 8 * idx) as u8)
+    std::array::from_fn(|idx| (u >> (8 * idx)) as u8)
 }
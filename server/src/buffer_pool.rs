@@ -0,0 +1,116 @@
+//! buffers for the frames queued for clients that can't take them yet, handed back once they've
+//! been written or dropped, so that a game with a slow player doesn't allocate on every tick.
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+
+use shared::{checksum::CHECKSUM_SIZE, server_msg::MAX_SERVER_MESSAGE_SIZE};
+
+/// how many buffers are kept for reuse at most. any more handed back are freed.
+const MAX_POOLED_BUFFERS: usize = 1024;
+/// the capacity of a new buffer, which fits any message with its checksum and delimiter.
+const BUFFER_CAPACITY: usize = MAX_SERVER_MESSAGE_SIZE + CHECKSUM_SIZE + 1;
+/// buffers that have grown bigger than this are freed rather than kept, so that the odd big frame
+/// doesn't leave the pool holding on to memory it won't need again.
+const MAX_POOLED_CAPACITY: usize = 4 * BUFFER_CAPACITY;
+
+/// a pool of byte buffers shared by every connection, counting how often a buffer could be reused
+/// rather than allocated.
+#[derive(Default)]
+pub struct BufferPool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl BufferPool {
+    /// a buffer holding a copy of the bytes, reused from the pool if there's one to spare.
+    pub fn take(&self, bytes: &[u8]) -> Vec<u8> {
+        let mut buffer = match self.buffers.lock().unwrap().pop() {
+            Some(buffer) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                buffer
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                Vec::with_capacity(BUFFER_CAPACITY.max(bytes.len()))
+            }
+        };
+        buffer.extend_from_slice(bytes);
+        buffer
+    }
+
+    /// returns a buffer to the pool once whatever was in it is done with.
+    pub fn give_back(&self, mut buffer: Vec<u8>) {
+        if buffer.capacity() > MAX_POOLED_CAPACITY {
+            return;
+        }
+        let mut buffers = self.buffers.lock().unwrap();
+        if buffers.len() < MAX_POOLED_BUFFERS {
+            buffer.clear();
+            buffers.push(buffer);
+        }
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            pooled: self.buffers.lock().unwrap().len(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolStats {
+    /// how many buffers were reused from the pool.
+    pub hits: u64,
+    /// how many buffers had to be allocated, since the pool had none to spare.
+    pub misses: u64,
+    /// how many buffers the pool is holding on to.
+    pub pooled: usize,
+}
+
+impl PoolStats {
+    /// the share of buffers that were reused, if any have been taken at all.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let taken = self.hits + self.misses;
+        (taken > 0).then(|| self.hits as f64 / taken as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer_pool::{BufferPool, PoolStats, MAX_POOLED_CAPACITY};
+
+    #[test]
+    fn reuses_buffers() {
+        let pool = BufferPool::default();
+        let first = pool.take(&[1, 2, 3]);
+        assert_eq!(first, [1, 2, 3]);
+        let second = pool.take(&[4]);
+        pool.give_back(first);
+        pool.give_back(second);
+        // handed back empty, whatever was in them.
+        assert_eq!(pool.take(&[5]), [5]);
+        assert_eq!(
+            pool.stats(),
+            PoolStats {
+                hits: 1,
+                misses: 2,
+                pooled: 1,
+            }
+        );
+        assert_eq!(pool.stats().hit_rate(), Some(1.0 / 3.0));
+        assert_eq!(BufferPool::default().stats().hit_rate(), None);
+    }
+
+    #[test]
+    fn frees_big_buffers() {
+        let pool = BufferPool::default();
+        pool.give_back(Vec::with_capacity(MAX_POOLED_CAPACITY + 1));
+        assert_eq!(pool.stats().pooled, 0);
+    }
+}
@@ -0,0 +1,479 @@
+use std::{
+    collections::VecDeque,
+    io::{self, ErrorKind, IoSlice, Write},
+    net::{Shutdown, SocketAddr, TcpStream},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use shared::{
+    checksum::{checksum, CHECKSUM_SIZE},
+    cosmetics::Cosmetics,
+};
+
+use crate::buffer_pool::BufferPool;
+
+/// how many frames can be queued for a client before stale game states start being dropped.
+const OUTBOUND_QUEUE_CAPACITY: usize = 16;
+
+/// how many buffers are written in one syscall at most, which is enough for a batch of frames
+/// each split into its message, checksum and delimiter.
+const MAX_WRITE_SLICES: usize = 16;
+
+/// how long a client that isn't reading has to make room for its queued frames, before it's
+/// disconnected.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// how long a write to a socket that blocks waits for its client to make room, before what's left
+/// is queued as if the socket didn't block.
+const BLOCKING_WRITE_LIMIT: Duration = Duration::from_millis(5);
+
+/// whether a frame has to reach its client, or can be dropped for a newer one.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Reliable,
+    /// made useless by the next frame like it, such as a game state.
+    Superseding,
+}
+
+struct QueuedFrame {
+    bytes: Vec<u8>,
+    delivery: Delivery,
+}
+
+/// the frames waiting for their client to make room for them.
+struct Outbound {
+    frames: VecDeque<QueuedFrame>,
+    /// how much of the front frame has already been written.
+    written: usize,
+    /// when the client last made room for a frame, or had nothing queued.
+    last_progress: Instant,
+    /// whether the client has agreed to have every frame end with a checksum.
+    checksums: bool,
+    /// where frames sent together are joined, and have their checksums added, when there are too
+    /// many to write from where they are. kept so that it's only allocated once.
+    scratch: Vec<u8>,
+}
+
+/// a client's connection, which every frame sent to the client is written through. frames are
+/// queued while the connection can't take any more, and written once it can, so that a slow client
+/// doesn't hold up whoever is sending to it. the event loop's connections don't block, and those
+/// served by a thread each only block for as long as [`ClientConn::limit_blocking`] lets them.
+pub struct ClientConn {
+    stream: TcpStream,
+    /// taken when the connection is made, since it can't be once the connection has been reset.
+    peer_addr: Option<SocketAddr>,
+    outbound: Mutex<Outbound>,
+    /// how the client would like its paddle drawn, if it's said, which its opponents are told.
+    cosmetics: Mutex<Option<Cosmetics>>,
+    /// where the buffers of queued frames come from, and go back to once they're written.
+    pool: Arc<BufferPool>,
+}
+
+impl ClientConn {
+    /// a connection with a pool of its own, for one that's only sent the odd frame.
+    pub fn new(stream: TcpStream) -> Self {
+        Self::with_pool(stream, Arc::default())
+    }
+
+    pub fn with_pool(stream: TcpStream, pool: Arc<BufferPool>) -> Self {
+        Self {
+            peer_addr: stream.peer_addr().ok(),
+            stream,
+            outbound: Mutex::new(Outbound {
+                frames: VecDeque::new(),
+                written: 0,
+                last_progress: Instant::now(),
+                checksums: false,
+                scratch: Vec::new(),
+            }),
+            cosmetics: Mutex::new(None),
+            pool,
+        }
+    }
+
+    pub fn stream(&self) -> &TcpStream {
+        &self.stream
+    }
+
+    /// stops writes to a socket that blocks, such as one read from on a thread of its own, from
+    /// waiting on the client for more than a moment, so that a client that's stopped reading can't
+    /// hold up a thread other clients' games are sent from. this affects every handle on the
+    /// socket.
+    pub fn limit_blocking(&self) -> io::Result<()> {
+        self.stream.set_write_timeout(Some(BLOCKING_WRITE_LIMIT))
+    }
+
+    pub fn cosmetics(&self) -> Option<Cosmetics> {
+        *self.cosmetics.lock().unwrap()
+    }
+
+    /// replaces whatever the client said before, since it says it again with everything it joins.
+    pub fn set_cosmetics(&self, cosmetics: Option<Cosmetics>) {
+        *self.cosmetics.lock().unwrap() = cosmetics;
+    }
+
+    /// queues a frame behind any others waiting for the client, and writes as many of them as the
+    /// connection takes. once the queue is full, superseding frames replace any queued before them.
+    /// a frame with nothing queued ahead of it is written as it is, and only copied into the queue
+    /// if the connection doesn't take all of it, so that sending to a client that keeps up doesn't
+    /// allocate.
+    pub fn send(&self, frame: &[u8], delivery: Delivery) {
+        self.send_all(&[frame], delivery);
+    }
+
+    /// sends the frames together, in a single write if the connection takes them, so that they
+    /// cost one syscall and can't have another frame sent between them. they're queued as one,
+    /// and so are only dropped together.
+    pub fn send_all(&self, frames: &[&[u8]], delivery: Delivery) {
+        let mut guard = self.outbound.lock().unwrap();
+        let outbound = &mut *guard;
+        // each frame's message, checksum and delimiter are written from wherever they are, rather
+        // than copied together first.
+        let mut checksums = [[0; CHECKSUM_SIZE]; MAX_WRITE_SLICES];
+        let mut slices: [&[u8]; MAX_WRITE_SLICES] = [&[]; MAX_WRITE_SLICES];
+        let joined;
+        let slices_per_frame = if outbound.checksums { 3 } else { 1 };
+        let slices: &[&[u8]] = if frames.len() * slices_per_frame <= MAX_WRITE_SLICES {
+            if outbound.checksums {
+                for (frame, frame_checksum) in frames.iter().zip(&mut checksums) {
+                    *frame_checksum = checksum(&frame[..frame.len() - 1]);
+                }
+            }
+            for (i, (frame, frame_checksum)) in frames.iter().zip(&checksums).enumerate() {
+                if outbound.checksums {
+                    let (message, delimiter) = frame.split_at(frame.len() - 1);
+                    slices[i * 3..i * 3 + 3].copy_from_slice(&[message, frame_checksum, delimiter]);
+                } else {
+                    slices[i] = frame;
+                }
+            }
+            &slices[..frames.len() * slices_per_frame]
+        } else {
+            // too many to write from where they are, so they're joined first.
+            outbound.scratch.clear();
+            for frame in frames {
+                if outbound.checksums {
+                    let (message, delimiter) = frame.split_at(frame.len() - 1);
+                    outbound.scratch.extend_from_slice(message);
+                    outbound.scratch.extend_from_slice(&checksum(message));
+                    outbound.scratch.extend_from_slice(delimiter);
+                } else {
+                    outbound.scratch.extend_from_slice(frame);
+                }
+            }
+            joined = outbound.scratch.as_slice();
+            std::slice::from_ref(&joined)
+        };
+        if outbound.frames.is_empty() {
+            let written = write_available(&self.stream, slices);
+            let len = slices.iter().map(|slice| slice.len()).sum();
+            if written == len {
+                outbound.last_progress = Instant::now();
+                return;
+            }
+            // the rest is written, or its error handled, along with any frames queued after it.
+            outbound.frames.push_back(QueuedFrame {
+                bytes: self.take_buffer(slices),
+                delivery,
+            });
+            outbound.written = written;
+            self.write_queued(guard);
+            return;
+        }
+        let bytes = self.take_buffer(slices);
+        if outbound.frames.len() >= OUTBOUND_QUEUE_CAPACITY && delivery == Delivery::Superseding {
+            // the front frame has to be finished once it's been started.
+            let started = outbound.written > 0;
+            for index in 0..outbound.frames.len() {
+                let frame = outbound.frames.pop_front().unwrap();
+                if frame.delivery == Delivery::Reliable || (started && index == 0) {
+                    outbound.frames.push_back(frame);
+                } else {
+                    self.pool.give_back(frame.bytes);
+                }
+            }
+        }
+        outbound.frames.push_back(QueuedFrame { bytes, delivery });
+        self.write_queued(guard);
+    }
+
+    /// sends the frame agreeing to checksums as it is, and ends every frame sent after it with a
+    /// checksum, with nothing sent in between.
+    pub fn enable_checksums(&self, ack: &[u8]) {
+        let mut outbound = self.outbound.lock().unwrap();
+        outbound.frames.push_back(QueuedFrame {
+            bytes: self.pool.take(ack),
+            delivery: Delivery::Reliable,
+        });
+        outbound.checksums = true;
+        self.write_queued(outbound);
+    }
+
+    /// writes as many queued frames as the connection takes, such as once it's writable again.
+    pub fn flush(&self) {
+        self.write_queued(self.outbound.lock().unwrap());
+    }
+
+    pub fn shutdown(&self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+
+    fn write_queued(&self, mut outbound: MutexGuard<Outbound>) {
+        let result = self.write_frames(&mut outbound);
+        let now = Instant::now();
+        match result {
+            Ok(()) => outbound.last_progress = now,
+            // a write that timed out would have blocked for longer.
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                if now.duration_since(outbound.last_progress) < WRITE_TIMEOUT {
+                    return;
+                }
+                eprintln!(
+                    "disconnecting client {:?}, which has {} frames waiting for it",
+                    self.peer_addr,
+                    outbound.frames.len()
+                );
+                self.discard_queued(&mut outbound);
+                drop(outbound);
+                self.shutdown();
+            }
+            Err(err) => {
+                eprintln!(
+                    "failed to write message {:?} to client {:?}: {err}",
+                    outbound.frames.front().map(|frame| &frame.bytes),
+                    self.peer_addr
+                );
+                // part of the frame may have been written, so nothing more can be.
+                self.discard_queued(&mut outbound);
+            }
+        }
+    }
+
+    fn discard_queued(&self, outbound: &mut Outbound) {
+        for frame in outbound.frames.drain(..) {
+            self.pool.give_back(frame.bytes);
+        }
+        outbound.written = 0;
+    }
+
+    /// writes the queued frames, as many at a time as fit in a vectored write.
+    fn write_frames(&self, outbound: &mut Outbound) -> io::Result<()> {
+        while !outbound.frames.is_empty() {
+            let mut slices = [IoSlice::new(&[]); MAX_WRITE_SLICES];
+            let mut count = 0;
+            for (slice, frame) in slices.iter_mut().zip(&outbound.frames) {
+                let offset = if count == 0 { outbound.written } else { 0 };
+                *slice = IoSlice::new(&frame.bytes[offset..]);
+                count += 1;
+            }
+            match (&self.stream).write_vectored(&slices[..count]) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    outbound.written += n;
+                    outbound.last_progress = Instant::now();
+                    // the write may have ended part way through any of the frames.
+                    while let Some(frame) = outbound.frames.front() {
+                        if outbound.written < frame.bytes.len() {
+                            break;
+                        }
+                        outbound.written -= frame.bytes.len();
+                        let frame = outbound.frames.pop_front().unwrap();
+                        self.pool.give_back(frame.bytes);
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// a buffer from the pool holding the slices one after another, to be queued.
+    fn take_buffer(&self, slices: &[&[u8]]) -> Vec<u8> {
+        let mut buffer = self.pool.take(&[]);
+        for slice in slices {
+            buffer.extend_from_slice(slice);
+        }
+        buffer
+    }
+
+    #[cfg(test)]
+    fn queued(&self) -> usize {
+        self.outbound.lock().unwrap().frames.len()
+    }
+}
+
+/// writes as much of the slices as the stream takes without blocking for long, or until it fails,
+/// returning how much was written. they're written together, and a write that ends part way
+/// through one carries on from there.
+fn write_available(mut stream: &TcpStream, slices: &[&[u8]]) -> usize {
+    let mut io_slices = [IoSlice::new(&[]); MAX_WRITE_SLICES];
+    for (io_slice, slice) in io_slices.iter_mut().zip(slices) {
+        *io_slice = IoSlice::new(slice);
+    }
+    let mut remaining = &mut io_slices[..slices.len()];
+    let mut written = 0;
+    while !remaining.is_empty() {
+        match stream.write_vectored(remaining) {
+            Ok(0) => break,
+            Ok(n) => {
+                written += n;
+                IoSlice::advance_slices(&mut remaining, n);
+            }
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(_) => break,
+        }
+    }
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Read,
+        net::{TcpListener, TcpStream},
+        sync::Arc,
+    };
+
+    use shared::checksum::checksum;
+
+    use crate::{
+        buffer_pool::BufferPool,
+        client_conn::{ClientConn, Delivery, OUTBOUND_QUEUE_CAPACITY},
+    };
+
+    #[test]
+    fn writes_without_queueing_while_keeping_up() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut client, _) = listener.accept().unwrap();
+        let conn = ClientConn::new(stream);
+        conn.send(&[1, 2, 0], Delivery::Superseding);
+        assert_eq!(conn.queued(), 0);
+        conn.enable_checksums(&[3, 0]);
+        conn.send(&[4, 5, 0], Delivery::Reliable);
+        assert_eq!(conn.queued(), 0);
+        drop(conn);
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).unwrap();
+        assert_eq!(
+            received,
+            [&[1, 2, 0, 3, 0, 4, 5][..], &checksum(&[4, 5]), &[0]].concat()
+        );
+    }
+
+    #[test]
+    fn sends_frames_together() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut client, _) = listener.accept().unwrap();
+        let conn = ClientConn::new(stream);
+        conn.send_all(&[&[1, 0], &[2, 3, 0]], Delivery::Reliable);
+        conn.enable_checksums(&[4, 0]);
+        // each frame gets its own checksum.
+        conn.send_all(&[&[5, 0], &[6, 0]], Delivery::Reliable);
+        drop(conn);
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).unwrap();
+        assert_eq!(
+            received,
+            [
+                &[1, 0, 2, 3, 0, 4, 0, 5][..],
+                &checksum(&[5]),
+                &[0, 6],
+                &checksum(&[6]),
+                &[0],
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn carries_on_from_partial_writes() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut client, _) = listener.accept().unwrap();
+        stream.set_nonblocking(true).unwrap();
+        let conn = ClientConn::new(stream);
+        conn.enable_checksums(&[0]);
+        let mut expected = vec![0];
+        // frames of odd sizes, so that writes end part way through messages and checksums.
+        let mut sent = 0;
+        while conn.queued() < 4 {
+            let message = vec![sent as u8 % 100 + 1; 997 + sent % 13];
+            let frame = [&message[..], &[0]].concat();
+            conn.send_all(&[&frame, &[200, 0]], Delivery::Reliable);
+            expected.extend_from_slice(&message);
+            expected.extend_from_slice(&checksum(&message));
+            expected.extend_from_slice(&[0, 200]);
+            expected.extend_from_slice(&checksum(&[200]));
+            expected.push(0);
+            sent += 1;
+        }
+        let mut received = Vec::new();
+        let mut buf = [0; 4096];
+        while received.len() < expected.len() {
+            conn.flush();
+            let n = client.read(&mut buf).unwrap();
+            received.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(received, expected);
+    }
+
+    #[test]
+    fn drops_stale_frames_once_full() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut client, _) = listener.accept().unwrap();
+        stream.set_nonblocking(true).unwrap();
+        let pool = Arc::new(BufferPool::default());
+        let conn = ClientConn::with_pool(stream, Arc::clone(&pool));
+        // fill the connection until frames start being queued.
+        let filler = [0; 1024];
+        let mut fillers = 0;
+        while conn.queued() == 0 {
+            conn.send(&filler, Delivery::Reliable);
+            fillers += 1;
+        }
+        for state in 1..=OUTBOUND_QUEUE_CAPACITY as u8 * 2 {
+            conn.send(&[state], Delivery::Superseding);
+            if state % 4 == 0 {
+                conn.send(&[100 + state], Delivery::Reliable);
+            }
+        }
+        assert!(conn.queued() <= OUTBOUND_QUEUE_CAPACITY + 1);
+        conn.send(&[255], Delivery::Superseding);
+        let mut received = Vec::new();
+        let mut buf = [0; 4096];
+        while received.len() < fillers * filler.len() || conn.queued() > 0 {
+            conn.flush();
+            let n = client.read(&mut buf).unwrap();
+            received.extend_from_slice(&buf[..n]);
+        }
+        drop(conn);
+        client.read_to_end(&mut received).unwrap();
+        let after_fillers: Vec<u8> = received[fillers * filler.len()..]
+            .iter()
+            .copied()
+            .filter(|&byte| byte != 0)
+            .collect();
+        // every reliable frame arrives, in order, but only the newest states do.
+        let reliable: Vec<u8> = after_fillers
+            .iter()
+            .copied()
+            .filter(|&b| b > 100 && b != 255)
+            .collect();
+        assert_eq!(
+            reliable,
+            (4..=32)
+                .step_by(4)
+                .map(|state| 100 + state)
+                .collect::<Vec<u8>>()
+        );
+        assert_eq!(after_fillers.last(), Some(&255));
+        assert!(after_fillers.len() < 32 + 8 + 1);
+        // the buffers of dropped and written frames were reused for those queued after them.
+        assert!(pool.stats().hits > 0);
+    }
+}
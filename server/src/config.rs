@@ -0,0 +1,592 @@
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    fs, io,
+    net::TcpStream,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use socket2::SockRef;
+use toml::{Table, Value};
+
+use shared::{
+    client_msg::Capabilities, game_state::GameDimensions, io::Keepalive, LOBBY_ID_LEN,
+    MAX_LOBBY_ID_LEN, MAX_U14,
+};
+
+use crate::{lobby_id_generator::parse_key, replay::ReplayArchive, telemetry::Endpoint};
+
+/// a config file with every setting, commented, at its default.
+pub const DEFAULT_CONFIG: &str = include_str!("default_config.toml");
+
+const DEFAULT_ADDRESS: &str = "0.0.0.0:8080";
+const DEFAULT_READY_TIMEOUT_SECS: u16 = 300;
+const DEFAULT_RECORD_RETENTION: usize = 1000;
+const DEFAULT_LOBBY_TTL_SECS: u64 = 30 * 60;
+const DEFAULT_SERVE_DELAY_MS: u64 = 1000;
+const DEFAULT_MAX_CONNECTIONS: usize = 1024;
+const DEFAULT_CONNECTION_QUOTA: u32 = 120;
+const DEFAULT_LOBBY_QUOTA: u32 = 30;
+const DEFAULT_OPEN_READ_TIMEOUT_SECS: u64 = 120;
+const DEFAULT_PLAYING_READ_TIMEOUT_SECS: u64 = 30;
+
+/// server settings, read from a config file and environment variables at startup.
+pub struct ServerConfig {
+    /// the address to listen for connections on. set with `SERVER_ADDR`.
+    pub address: String,
+    /// how long players have to ready up once both have joined a lobby, before the lobby is
+    /// closed. set with `READY_TIMEOUT_SECS`.
+    pub ready_timeout_secs: u16,
+    /// how long a lobby can wait for an opponent without its host being active, before it is
+    /// closed. set with `LOBBY_TTL_SECS`.
+    pub lobby_ttl_secs: u64,
+    /// the size of the field and paddles in every lobby. set with `GAME_WIDTH`, `GAME_HEIGHT` and
+    /// `PADDLE_HEIGHT`.
+    pub dimensions: GameDimensions,
+    /// the address to serve the web dashboard on, if it should be served at all. set with
+    /// `DASHBOARD_ADDR`.
+    pub dashboard_address: Option<String>,
+    /// the directory to write a log of each lobby's events to, if they should be logged at all.
+    /// set with `EVENT_LOG_DIR`.
+    pub event_log_dir: Option<PathBuf>,
+    /// the sqlite database the result of every finished match is kept in, if they should outlast
+    /// the server. they're kept in memory otherwise. needs the `sqlite` feature. set with
+    /// `STATS_DB_FILE`.
+    pub stats_db_file: Option<PathBuf>,
+    /// the file the result of every finished match is appended to as a line of json, if they
+    /// should be exported at all. set with `MATCH_RESULTS_FILE`.
+    pub match_results_file: Option<PathBuf>,
+    /// where anonymous counts of the server's connections, games and protocol errors are sent
+    /// every hour, if they should be sent at all. needs the `telemetry` feature. set with
+    /// `TELEMETRY_ENDPOINT`.
+    pub telemetry_endpoint: Option<Endpoint>,
+    /// where to save a replay of every finished game, if they should be saved at all. set with
+    /// `RECORD_DIR`, and how many to keep with `RECORD_RETENTION`.
+    pub replays: Option<ReplayArchive>,
+    /// whether games should be paused, only advancing when an admin steps them from stdin. set
+    /// with `DEBUG_STEP`.
+    pub debug_step: bool,
+    /// how long a game waits for both players' inputs before ticking anyway, if games should be
+    /// played in lockstep at all. set with `LOCKSTEP_TIMEOUT_MS`.
+    pub lockstep_timeout_ms: Option<u64>,
+    /// the seed every lobby's rng is created with, instead of a random one. set with `SEED`.
+    pub seed: Option<u64>,
+    /// the key lobby ids are generated with, instead of a random one, so that they stay the same
+    /// across restarts. set with `LOBBY_ID_KEY`, or read from the file at `LOBBY_ID_KEY_FILE`.
+    pub lobby_id_key: Option<[u8; 32]>,
+    /// the file the number of lobby ids generated is kept in, so that ids aren't handed out again
+    /// after a restart. set with `LOBBY_ID_COUNTER_FILE`.
+    pub lobby_id_counter_file: Option<PathBuf>,
+    /// how many characters generated lobby ids have. ids longer than the default can't be used by
+    /// clients from before ids could be longer. set with `LOBBY_ID_LENGTH`.
+    pub lobby_id_len: usize,
+    /// which player serves each game after the first, which is served by a random one. set with
+    /// `SERVE_RULE`.
+    pub serve_rule: ServeRule,
+    /// how long the ball waits to be served before launching anyway. set with `SERVE_DELAY_MS`.
+    pub serve_delay_ms: u64,
+    /// how many games a player has to win in a lobby to win the match, after which the next game
+    /// starts a new one. matches aren't kept score of if unset. set with `MATCH_LENGTH`.
+    pub match_length: Option<u16>,
+    /// how a match tied at match point is decided. set with `TIEBREAK`.
+    pub tiebreak: Tiebreak,
+    /// how many connections are served at once. any more wait to be accepted until one closes. set
+    /// with `MAX_CONNECTIONS`.
+    pub max_connections: usize,
+    /// whether connections are served by a thread each, or all by one event loop. set with
+    /// `SERVER_MODE`.
+    pub server_mode: ServerMode,
+    /// how many threads games and lobby timers share when connections are served by a thread
+    /// each, rather than one per cpu core. set with `TASK_WORKERS`.
+    pub task_workers: Option<usize>,
+    /// the options set on every connection the server accepts.
+    pub socket_options: SocketOptions,
+    /// how many connections one ip address can open a minute, if there's a limit at all. set with
+    /// `CONNECTION_QUOTA`, where 0 is no limit.
+    pub connection_quota: Option<u32>,
+    /// how many lobbies one ip address can create a minute, if there's a limit at all. set with
+    /// `LOBBY_QUOTA`, where 0 is no limit.
+    pub lobby_quota: Option<u32>,
+    /// the file listing which addresses can connect, if not every one can. it's read again
+    /// whenever it changes. set with `ACCESS_LIST_FILE`.
+    pub access_list_file: Option<PathBuf>,
+    /// how long a connection that isn't in a lobby, the match queue or a tournament can go without
+    /// sending anything before it's closed, if there's a limit at all. this includes time spent
+    /// browsing lobbies. set with `OPEN_READ_TIMEOUT_SECS`, where 0 is no limit.
+    pub open_read_timeout_secs: Option<u64>,
+    /// how long a player in the middle of a game in lockstep can go without sending anything before
+    /// their connection is closed, if there's a limit at all. clients answer every game state of
+    /// such a game with their paddle's direction, so only one that's gone stops. set with
+    /// `PLAYING_READ_TIMEOUT_SECS`, where 0 is no limit.
+    pub playing_read_timeout_secs: Option<u64>,
+}
+
+/// options for the sockets of accepted connections, left to the os where they're unset.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SocketOptions {
+    /// whether messages are sent as soon as they're written, rather than held back to be sent
+    /// together, which only delays messages as small as the game's. set with `TCP_NODELAY`.
+    pub nodelay: bool,
+    /// the size of the buffer frames are written into. set with `SEND_BUFFER_SIZE`.
+    pub send_buffer_size: Option<usize>,
+    /// the size of the buffer messages are received into. set with `RECV_BUFFER_SIZE`.
+    pub recv_buffer_size: Option<usize>,
+    /// how the os checks that the other end of an idle connection is still there, if it should at
+    /// all. set with `TCP_KEEPALIVE_SECS`, `TCP_KEEPALIVE_INTERVAL_SECS` and
+    /// `TCP_KEEPALIVE_RETRIES`.
+    pub keepalive: Option<Keepalive>,
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self {
+            nodelay: true,
+            send_buffer_size: None,
+            recv_buffer_size: None,
+            keepalive: None,
+        }
+    }
+}
+
+impl SocketOptions {
+    fn from_settings(settings: &Settings) -> Self {
+        let defaults = Self::default();
+        Self {
+            nodelay: settings.get_or("TCP_NODELAY", defaults.nodelay),
+            send_buffer_size: settings.get("SEND_BUFFER_SIZE"),
+            recv_buffer_size: settings.get("RECV_BUFFER_SIZE"),
+            keepalive: keepalive_from_settings(settings),
+        }
+    }
+
+    pub fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        stream.set_nodelay(self.nodelay)?;
+        let socket = SockRef::from(stream);
+        if let Some(size) = self.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+        if let Some(keepalive) = &self.keepalive {
+            keepalive.apply(stream)?;
+        }
+        Ok(())
+    }
+}
+
+/// how connections, and the games and timers of their lobbies, are run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServerMode {
+    /// each connection is read on a thread of its own, while games and timers share a small pool
+    /// of threads.
+    Threads,
+    /// every connection is read, and every game and timer run, on a single thread. games can't
+    /// be debug stepped, and `MAX_CONNECTIONS` doesn't apply.
+    EventLoop,
+}
+
+impl FromStr for ServerMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "threads" => Ok(ServerMode::Threads),
+            "event_loop" => Ok(ServerMode::EventLoop),
+            _ => Err(()),
+        }
+    }
+}
+
+/// who serves the next game, based on who won the last.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServeRule {
+    Winner,
+    Loser,
+}
+
+impl FromStr for ServeRule {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "winner" => Ok(ServeRule::Winner),
+            "loser" => Ok(ServeRule::Loser),
+            _ => Err(()),
+        }
+    }
+}
+
+/// how a match is decided once both players are a game away from winning it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tiebreak {
+    /// the match goes on until one player is two games ahead.
+    WinByTwo,
+    /// the next game decides the match.
+    SuddenDeath,
+}
+
+impl FromStr for Tiebreak {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "win_by_two" => Ok(Tiebreak::WinByTwo),
+            "sudden_death" => Ok(Tiebreak::SuddenDeath),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// reads the settings in the config file at the given path, if there is one, with those set
+    /// by environment variables taking their place.
+    pub fn load(config_file: Option<&Path>) -> Self {
+        let settings = Settings::read(config_file);
+        let config = Self::from_settings(&settings);
+        settings.check_all_read();
+        config
+    }
+
+    fn from_settings(settings: &Settings) -> Self {
+        let ready_timeout_secs = settings.get_or("READY_TIMEOUT_SECS", DEFAULT_READY_TIMEOUT_SECS);
+        assert!(
+            ready_timeout_secs <= MAX_U14,
+            "READY_TIMEOUT_SECS must be no greater than {MAX_U14}"
+        );
+        let lobby_ttl_secs = settings.get_or("LOBBY_TTL_SECS", DEFAULT_LOBBY_TTL_SECS);
+        assert!(lobby_ttl_secs > 0, "LOBBY_TTL_SECS must be greater than 0");
+        let default_dimensions = GameDimensions::DEFAULT;
+        let dimensions = GameDimensions::new(
+            settings.get_or("GAME_WIDTH", default_dimensions.width()),
+            settings.get_or("GAME_HEIGHT", default_dimensions.height()),
+            settings.get_or("PADDLE_HEIGHT", default_dimensions.paddle_height()),
+        )
+        .expect("GAME_WIDTH, GAME_HEIGHT and PADDLE_HEIGHT must describe a playable game");
+        let lobby_id_len = settings.get_or("LOBBY_ID_LENGTH", LOBBY_ID_LEN);
+        assert!(
+            (LOBBY_ID_LEN..=MAX_LOBBY_ID_LEN).contains(&lobby_id_len),
+            "LOBBY_ID_LENGTH must be from {LOBBY_ID_LEN} to {MAX_LOBBY_ID_LEN}"
+        );
+        let match_length = settings.get("MATCH_LENGTH");
+        assert!(
+            match_length != Some(0),
+            "MATCH_LENGTH must be greater than 0"
+        );
+        let max_connections = settings.get_or("MAX_CONNECTIONS", DEFAULT_MAX_CONNECTIONS);
+        assert!(
+            max_connections > 0,
+            "MAX_CONNECTIONS must be greater than 0"
+        );
+        let serve_delay_ms = settings.get_or("SERVE_DELAY_MS", DEFAULT_SERVE_DELAY_MS);
+        let playing_read_timeout_secs = read_timeout_from_settings(
+            settings,
+            "PLAYING_READ_TIMEOUT_SECS",
+            DEFAULT_PLAYING_READ_TIMEOUT_SECS,
+        );
+        // no game states are sent while the ball waits to be served, so nor are any inputs.
+        assert!(
+            playing_read_timeout_secs.is_none_or(|secs| secs * 1000 > serve_delay_ms),
+            "PLAYING_READ_TIMEOUT_SECS must be longer than SERVE_DELAY_MS"
+        );
+        let task_workers = settings.get("TASK_WORKERS");
+        assert!(
+            task_workers != Some(0),
+            "TASK_WORKERS must be greater than 0"
+        );
+        let debug_step = settings.get_or("DEBUG_STEP", false);
+        let server_mode = settings.get_or("SERVER_MODE", ServerMode::Threads);
+        assert!(
+            !debug_step || server_mode == ServerMode::Threads,
+            "DEBUG_STEP can't be used with SERVER_MODE=event_loop"
+        );
+        let telemetry_endpoint = settings.get("TELEMETRY_ENDPOINT");
+        assert!(
+            telemetry_endpoint.is_none() || cfg!(feature = "telemetry"),
+            "TELEMETRY_ENDPOINT needs the server to be built with the `telemetry` feature"
+        );
+        // looked up even without a directory, so that it isn't taken for an unknown setting.
+        let record_retention = settings.get_or("RECORD_RETENTION", DEFAULT_RECORD_RETENTION);
+        Self {
+            address: settings
+                .string("SERVER_ADDR")
+                .unwrap_or(DEFAULT_ADDRESS.to_owned()),
+            ready_timeout_secs,
+            lobby_ttl_secs,
+            dimensions,
+            dashboard_address: settings.string("DASHBOARD_ADDR"),
+            event_log_dir: settings.path("EVENT_LOG_DIR"),
+            stats_db_file: settings.path("STATS_DB_FILE"),
+            match_results_file: settings.path("MATCH_RESULTS_FILE"),
+            telemetry_endpoint,
+            replays: settings.path("RECORD_DIR").map(|dir| ReplayArchive {
+                dir,
+                retention: record_retention,
+            }),
+            debug_step,
+            lockstep_timeout_ms: settings.get("LOCKSTEP_TIMEOUT_MS"),
+            seed: settings.get("SEED"),
+            lobby_id_key: lobby_id_key_from_settings(settings),
+            lobby_id_counter_file: settings.path("LOBBY_ID_COUNTER_FILE"),
+            lobby_id_len,
+            serve_rule: settings.get_or("SERVE_RULE", ServeRule::Loser),
+            serve_delay_ms,
+            match_length,
+            tiebreak: settings.get_or("TIEBREAK", Tiebreak::WinByTwo),
+            max_connections,
+            server_mode,
+            task_workers,
+            socket_options: SocketOptions::from_settings(settings),
+            connection_quota: quota_from_settings(
+                settings,
+                "CONNECTION_QUOTA",
+                DEFAULT_CONNECTION_QUOTA,
+            ),
+            lobby_quota: quota_from_settings(settings, "LOBBY_QUOTA", DEFAULT_LOBBY_QUOTA),
+            access_list_file: settings.path("ACCESS_LIST_FILE"),
+            open_read_timeout_secs: read_timeout_from_settings(
+                settings,
+                "OPEN_READ_TIMEOUT_SECS",
+                DEFAULT_OPEN_READ_TIMEOUT_SECS,
+            ),
+            playing_read_timeout_secs,
+        }
+    }
+
+    pub fn ready_timeout(&self) -> Duration {
+        Duration::from_secs(self.ready_timeout_secs as u64)
+    }
+
+    pub fn lobby_ttl(&self) -> Duration {
+        Duration::from_secs(self.lobby_ttl_secs)
+    }
+
+    pub fn serve_delay(&self) -> Duration {
+        Duration::from_millis(self.serve_delay_ms)
+    }
+
+    pub fn lockstep_timeout(&self) -> Option<Duration> {
+        self.lockstep_timeout_ms.map(Duration::from_millis)
+    }
+
+    /// whether a client with the given capabilities can play the server's games at all, whatever
+    /// lobby it's in. older clients don't answer every game state, so can't play in lockstep.
+    pub fn can_serve(&self, capabilities: Capabilities) -> bool {
+        self.lockstep_timeout_ms.is_none() || capabilities.contains(Capabilities::LOCKSTEP)
+    }
+
+    pub fn open_read_timeout(&self) -> Option<Duration> {
+        self.open_read_timeout_secs.map(Duration::from_secs)
+    }
+
+    /// outside lockstep, clients only send their paddle's direction when it changes, so a player
+    /// can go quiet for as long as they like.
+    pub fn playing_read_timeout(&self) -> Option<Duration> {
+        self.lockstep_timeout_ms?;
+        self.playing_read_timeout_secs.map(Duration::from_secs)
+    }
+}
+
+fn lobby_id_key_from_settings(settings: &Settings) -> Option<[u8; 32]> {
+    let key = match settings.path("LOBBY_ID_KEY_FILE") {
+        Some(path) => fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("failed to read LOBBY_ID_KEY_FILE {path:?}: {err}")),
+        None => settings.string("LOBBY_ID_KEY")?,
+    };
+    Some(parse_key(key.trim()).expect("the lobby id key must be 64 hexadecimal digits"))
+}
+
+fn quota_from_settings(settings: &Settings, key: &str, default: u32) -> Option<u32> {
+    Some(settings.get_or(key, default)).filter(|&quota| quota > 0)
+}
+
+fn read_timeout_from_settings(settings: &Settings, key: &str, default: u64) -> Option<u64> {
+    Some(settings.get_or(key, default)).filter(|&secs| secs > 0)
+}
+
+fn keepalive_from_settings(settings: &Settings) -> Option<Keepalive> {
+    let interval = settings
+        .get("TCP_KEEPALIVE_INTERVAL_SECS")
+        .map(Duration::from_secs);
+    let retries = settings.get("TCP_KEEPALIVE_RETRIES");
+    let Some(idle_secs) = settings.get("TCP_KEEPALIVE_SECS") else {
+        assert!(
+            interval.is_none() && retries.is_none(),
+            "TCP_KEEPALIVE_INTERVAL_SECS and TCP_KEEPALIVE_RETRIES need TCP_KEEPALIVE_SECS"
+        );
+        return None;
+    };
+    Some(Keepalive {
+        idle: Duration::from_secs(idle_secs),
+        interval,
+        retries,
+    })
+}
+
+/// where settings are read from: each one's environment variable if it's set, or otherwise its
+/// key in the config file, which is the variable's name in lowercase.
+struct Settings {
+    file: Table,
+    /// the keys looked up so far, so that any others in the file can be reported as unknown.
+    read: RefCell<HashSet<String>>,
+}
+
+impl Settings {
+    fn read(config_file: Option<&Path>) -> Self {
+        let file = match config_file {
+            Some(path) => fs::read_to_string(path)
+                .unwrap_or_else(|err| panic!("failed to read config file {path:?}: {err}"))
+                .parse()
+                .unwrap_or_else(|err| panic!("invalid config file {path:?}: {err}")),
+            None => Table::new(),
+        };
+        Self {
+            file,
+            read: RefCell::default(),
+        }
+    }
+
+    fn string(&self, key: &str) -> Option<String> {
+        let file_key = key.to_lowercase();
+        let value = std::env::var(key).ok().or_else(|| {
+            self.file.get(&file_key).map(|value| match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            })
+        });
+        self.read.borrow_mut().insert(file_key);
+        value
+    }
+
+    fn path(&self, key: &str) -> Option<PathBuf> {
+        match std::env::var_os(key) {
+            Some(path) => {
+                self.read.borrow_mut().insert(key.to_lowercase());
+                Some(PathBuf::from(path))
+            }
+            None => self.string(key).map(PathBuf::from),
+        }
+    }
+
+    fn get<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.string(key).map(|value| {
+            value
+                .parse()
+                .unwrap_or_else(|_| panic!("invalid value for {key}: {value}"))
+        })
+    }
+
+    fn get_or<T: FromStr>(&self, key: &str, default: T) -> T {
+        self.get(key).unwrap_or(default)
+    }
+
+    /// panics if the config file has a setting that was never looked up, which is most likely a
+    /// typo.
+    fn check_all_read(&self) {
+        let read = self.read.borrow();
+        for key in self.file.keys() {
+            assert!(read.contains(key), "unknown setting {key} in config file");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::HashSet};
+
+    use toml::Table;
+
+    use crate::config::{ServeRule, ServerConfig, Settings, DEFAULT_CONFIG};
+
+    fn settings(file: &str) -> Settings {
+        Settings {
+            file: file.parse().unwrap(),
+            read: RefCell::default(),
+        }
+    }
+
+    #[test]
+    fn default_config_lists_every_setting() {
+        let uncommented = DEFAULT_CONFIG
+            .lines()
+            .map(|line| match line.strip_prefix("# ") {
+                Some(setting) if setting.contains(" = ") => setting,
+                _ => line,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let listed: HashSet<String> = uncommented
+            .parse::<Table>()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        let settings = settings("");
+        ServerConfig::from_settings(&settings);
+        assert_eq!(listed, settings.read.into_inner());
+    }
+
+    #[test]
+    fn default_config_is_default() {
+        let settings = settings(DEFAULT_CONFIG);
+        let config = ServerConfig::from_settings(&settings);
+        settings.check_all_read();
+        let default = ServerConfig::from_settings(&self::settings(""));
+        assert_eq!(config.address, default.address);
+        assert_eq!(config.ready_timeout_secs, default.ready_timeout_secs);
+        assert_eq!(config.lobby_ttl_secs, default.lobby_ttl_secs);
+        assert_eq!(config.dimensions, default.dimensions);
+        assert_eq!(config.serve_rule, default.serve_rule);
+        assert_eq!(config.serve_delay_ms, default.serve_delay_ms);
+        assert_eq!(config.match_length, default.match_length);
+        assert_eq!(config.tiebreak, default.tiebreak);
+        assert_eq!(config.max_connections, default.max_connections);
+        assert_eq!(config.server_mode, default.server_mode);
+        assert_eq!(config.task_workers, default.task_workers);
+        assert_eq!(config.socket_options, default.socket_options);
+        assert_eq!(config.connection_quota, default.connection_quota);
+        assert_eq!(config.lobby_quota, default.lobby_quota);
+        assert_eq!(config.lobby_id_len, default.lobby_id_len);
+        assert_eq!(
+            config.open_read_timeout_secs,
+            default.open_read_timeout_secs
+        );
+        assert_eq!(
+            config.playing_read_timeout_secs,
+            default.playing_read_timeout_secs
+        );
+        assert_eq!(config.debug_step, default.debug_step);
+    }
+
+    #[test]
+    fn config_file_settings() {
+        let settings = settings(
+            "server_addr = \"127.0.0.1:9000\"\n\
+             game_width = 31\n\
+             serve_rule = \"winner\"\n\
+             match_length = 5\n\
+             connection_quota = 0\n\
+             tcp_nodelay = false\n\
+             stats_db_file = \"stats.db\"",
+        );
+        let config = ServerConfig::from_settings(&settings);
+        settings.check_all_read();
+        assert_eq!(config.address, "127.0.0.1:9000");
+        assert_eq!(config.dimensions.width(), 31);
+        assert_eq!(config.serve_rule, ServeRule::Winner);
+        assert_eq!(config.match_length, Some(5));
+        assert_eq!(config.connection_quota, None);
+        assert!(!config.socket_options.nodelay);
+        assert_eq!(config.stats_db_file, Some("stats.db".into()));
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown setting game_widht in config file")]
+    fn unknown_setting() {
+        let settings = settings("game_widht = 31");
+        ServerConfig::from_settings(&settings);
+        settings.check_all_read();
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    thread::JoinHandle,
+};
+
+/// how big the log file grows before it's rotated, leaving the one before it as `<path>.1`.
+const LOG_FILE_MAX_LEN: u64 = 10 * 1024 * 1024;
+
+/// detaches the server from its terminal, leaving it running in the background once the command
+/// that started it exits. has to be called before any threads are started, as only the calling
+/// thread carries on in the background.
+#[cfg(unix)]
+pub fn daemonize() -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: the process is still single threaded, so nothing is left in an inconsistent state in
+    // the child.
+    match unsafe { libc::fork() } {
+        -1 => return Err(io::Error::last_os_error()),
+        0 => {}
+        _ => process::exit(0),
+    }
+    // a new session leaves the terminal behind, so that closing it doesn't hang up the server.
+    // SAFETY: setsid only affects the calling process.
+    if unsafe { libc::setsid() } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let null = File::options().read(true).write(true).open("/dev/null")?;
+    for fd in [libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+        redirect(null.as_raw_fd(), fd)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn daemonize() -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// records the server's process id in the file, so that it can be stopped once detached.
+pub fn write_pidfile(path: &Path) -> io::Result<()> {
+    fs::write(path, format!("{}\n", process::id()))
+}
+
+/// stops SIGINT and SIGTERM from killing the server, so that it can shut down by itself once
+/// [`await_shutdown_signal`] returns. has to be called before any threads are started, as they
+/// only block the signals if the thread that started them did.
+#[cfg(unix)]
+pub fn block_shutdown_signals() -> io::Result<()> {
+    let set = shutdown_signals();
+    // SAFETY: the set was initialised by sigemptyset, and the old mask isn't asked for.
+    match unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut()) } {
+        0 => Ok(()),
+        err => Err(io::Error::from_raw_os_error(err)),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn block_shutdown_signals() -> io::Result<()> {
+    Ok(())
+}
+
+/// waits for the server to be sent SIGINT or SIGTERM, returning which it was. the signals have to
+/// have been blocked by [`block_shutdown_signals`], or they kill the server first.
+#[cfg(unix)]
+pub fn await_shutdown_signal() -> io::Result<i32> {
+    let set = shutdown_signals();
+    let mut signal = 0;
+    // SAFETY: the set was initialised by sigemptyset, and the signal is written to a valid i32.
+    match unsafe { libc::sigwait(&set, &mut signal) } {
+        0 => Ok(signal),
+        err => Err(io::Error::from_raw_os_error(err)),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn await_shutdown_signal() -> io::Result<i32> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(unix)]
+fn shutdown_signals() -> libc::sigset_t {
+    // SAFETY: sigset_t is plain data, which sigemptyset initialises before it's used.
+    unsafe {
+        let mut set = std::mem::zeroed();
+        libc::sigemptyset(&mut set);
+        libc::sigaddset(&mut set, libc::SIGINT);
+        libc::sigaddset(&mut set, libc::SIGTERM);
+        set
+    }
+}
+
+/// the thread writing everything the server prints to its log file.
+pub struct LogFile {
+    writer: JoinHandle<()>,
+}
+
+impl LogFile {
+    /// waits for everything printed so far to be written to the log, after which nothing else
+    /// printed is. for when the server is about to exit.
+    #[cfg(unix)]
+    pub fn close(self) {
+        if let Ok(null) = File::options().write(true).open("/dev/null") {
+            use std::os::fd::AsRawFd;
+
+            // the log's pipe is closed once nothing refers to it any more, which the writer reads
+            // to the end of.
+            let _ = redirect(null.as_raw_fd(), libc::STDOUT_FILENO);
+            let _ = redirect(null.as_raw_fd(), libc::STDERR_FILENO);
+            let _ = self.writer.join();
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn close(self) {}
+}
+
+/// sends everything the server prints to the log file instead, which is rotated once it grows too
+/// big.
+#[cfg(unix)]
+pub fn log_to_file(path: &Path) -> io::Result<LogFile> {
+    use std::{io::Read, os::fd::AsRawFd, thread::Builder};
+
+    let mut log = RotatingFile::open(path.to_owned(), LOG_FILE_MAX_LEN)?;
+    let (mut reader, writer) = io::pipe()?;
+    redirect(writer.as_raw_fd(), libc::STDOUT_FILENO)?;
+    redirect(writer.as_raw_fd(), libc::STDERR_FILENO)?;
+    let writer = Builder::new().name("log_file".to_owned()).spawn(move || {
+        let mut buf = [0; 8192];
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => return,
+                Ok(n) => n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                // stderr goes to the log too, so there's nowhere left to report this.
+                Err(_) => return,
+            };
+            let _ = log.write_all(&buf[..n]);
+        }
+    })?;
+    Ok(LogFile { writer })
+}
+
+#[cfg(not(unix))]
+pub fn log_to_file(_path: &Path) -> io::Result<LogFile> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// makes `fd` refer to whatever `to` does.
+#[cfg(unix)]
+fn redirect(to: i32, fd: i32) -> io::Result<()> {
+    // SAFETY: dup2 only replaces `fd`, closing whatever it referred to.
+    if unsafe { libc::dup2(to, fd) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// a file that's appended to until it reaches its max length, when it's moved to `<path>.1`,
+/// replacing the one moved before it, and started again.
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    len: u64,
+    max_len: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_len: u64) -> io::Result<Self> {
+        let file = File::options().create(true).append(true).open(&path)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            len,
+            max_len,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        fs::rename(&self.path, rotated)?;
+        self.file = File::options().create(true).append(true).open(&self.path)?;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.len > 0 && self.len + buf.len() as u64 > self.max_len {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.len += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write};
+
+    use crate::daemon::RotatingFile;
+
+    #[test]
+    fn rotates_once_full() {
+        let dir = std::env::temp_dir().join(format!("pong_log_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("server.log");
+        let mut log = RotatingFile::open(path.clone(), 10).unwrap();
+        log.write_all(b"first\n").unwrap();
+        log.write_all(b"second\n").unwrap();
+        log.write_all(b"third\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("server.log.1")).unwrap(),
+            "second\n"
+        );
+        // an existing log is appended to.
+        drop(log);
+        let mut log = RotatingFile::open(path.clone(), 10).unwrap();
+        log.write_all(b"4th\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third\n4th\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
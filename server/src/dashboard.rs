@@ -0,0 +1,445 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+    thread::{sleep, Builder},
+    time::Duration,
+};
+
+use shared::{
+    game_state::{sped_up_tick_interval_ms, speed_level},
+    LobbyId,
+};
+
+use crate::{
+    buffer_pool::{BufferPool, PoolStats},
+    json,
+    lobby::{Lobbies, Lobby, LobbyState},
+    tick_timings::{TickStats, TickTimings},
+};
+
+const PAGE: &str = include_str!("dashboard.html");
+/// how often a watched lobby is checked for changes while no game is being played in it.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// how long a viewer has to send their request before their connection is dropped.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// the most of a request that's read, its request line and headers together. every request the
+/// dashboard serves fits in far less.
+const MAX_REQUEST_SIZE: u64 = 8 * 1024;
+
+/// serves a read-only web page listing the open lobbies, from which the game being played in any
+/// of them can be watched live.
+///
+/// - `GET /` serves the page itself.
+/// - `GET /lobbies` lists every lobby as json.
+/// - `GET /lobbies/<LOBBY_ID>/events` streams a lobby as server-sent events, each one a json
+///   snapshot of the lobby, until the lobby is closed.
+/// - `GET /metrics` reports how well the server is reusing its buffers, and how punctually its
+///   games tick, as json.
+pub fn serve(
+    listener: TcpListener,
+    lobbies: Arc<Lobbies>,
+    buffer_pool: Arc<BufferPool>,
+    tick_timings: Arc<TickTimings>,
+) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let lobbies = Arc::clone(&lobbies);
+                let buffer_pool = Arc::clone(&buffer_pool);
+                let tick_timings = Arc::clone(&tick_timings);
+                Builder::new()
+                    .name("dashboard_handler".to_owned())
+                    .spawn(move || {
+                        if let Err(err) =
+                            handle_connection(&stream, &lobbies, &buffer_pool, &tick_timings)
+                        {
+                            eprintln!("failed to respond to dashboard request: {err}");
+                        }
+                    })
+                    .unwrap();
+            }
+            Err(err) => eprintln!("incoming dashboard connection failure: {err}"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Route {
+    Page,
+    Lobbies,
+    LobbyEvents(LobbyId),
+    Metrics,
+    NotFound,
+}
+
+fn route(request_line: &str) -> Route {
+    let mut parts = request_line.split_whitespace();
+    let (Some("GET"), Some(path)) = (parts.next(), parts.next()) else {
+        return Route::NotFound;
+    };
+    match path {
+        "/" => Route::Page,
+        "/lobbies" => Route::Lobbies,
+        "/metrics" => Route::Metrics,
+        path => path
+            .strip_prefix("/lobbies/")
+            .and_then(|path| path.strip_suffix("/events"))
+            .and_then(|lobby_id| lobby_id.parse().ok())
+            .map_or(Route::NotFound, Route::LobbyEvents),
+    }
+}
+
+fn handle_connection(
+    stream: &TcpStream,
+    lobbies: &Lobbies,
+    buffer_pool: &BufferPool,
+    tick_timings: &TickTimings,
+) -> io::Result<()> {
+    // a viewer that never finishes their request would otherwise hold on to its thread forever.
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let Some(request_line) = read_request(stream)? else {
+        return respond(
+            stream,
+            "431 Request Header Fields Too Large",
+            "text/plain",
+            "request too large",
+        );
+    };
+    match route(&request_line) {
+        Route::Page => respond(stream, "200 OK", "text/html; charset=utf-8", PAGE),
+        Route::Lobbies => respond(stream, "200 OK", "application/json", &lobbies_json(lobbies)),
+        Route::LobbyEvents(lobby_id) => {
+            // the viewer leaving is the usual way for the stream to end, so isn't an error.
+            let _ = stream_lobby_events(stream, lobbies, lobby_id);
+            Ok(())
+        }
+        Route::Metrics => respond(
+            stream,
+            "200 OK",
+            "application/json",
+            &metrics_json(buffer_pool.stats(), tick_timings.stats()),
+        ),
+        Route::NotFound => respond(stream, "404 Not Found", "text/plain", "not found"),
+    }
+}
+
+/// reads a request up to the blank line ending its headers, returning its request line, or
+/// nothing if it's longer than `MAX_REQUEST_SIZE`.
+fn read_request(stream: impl Read) -> io::Result<Option<String>> {
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_SIZE));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // none of the headers are needed, but they must be read before responding.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+        header.clear();
+    }
+    Ok((reader.get_ref().limit() > 0).then_some(request_line))
+}
+
+fn respond(mut stream: &TcpStream, status: &str, content_type: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// sends a snapshot of the lobby whenever it changes, polling it as often as its game ticks.
+fn stream_lobby_events(
+    mut stream: &TcpStream,
+    lobbies: &Lobbies,
+    lobby_id: LobbyId,
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\r\n"
+    )?;
+    let Some(shared) = lobbies.get(&lobby_id) else {
+        return write!(stream, "event: closed\ndata: {{}}\n\n");
+    };
+    let mut last_snapshot = String::new();
+    loop {
+        // the lobby is released before writing, so that a slow viewer can't hold up the game.
+        let Some((snapshot, poll_interval)) = shared.lock().as_ref().map(|lobby| {
+            let poll_interval = match lobby {
+                Lobby::Joined {
+                    ruleset,
+                    state: LobbyState::Playing { hits, .. },
+                    ..
+                } => Duration::from_millis(sped_up_tick_interval_ms(
+                    ruleset.tick_interval_ms,
+                    speed_level(*hits),
+                ) as u64),
+                Lobby::AwaitingJoin { .. } | Lobby::Joined { .. } => IDLE_POLL_INTERVAL,
+            };
+            (lobby_json(lobby_id, lobby), poll_interval)
+        }) else {
+            return write!(stream, "event: closed\ndata: {{}}\n\n");
+        };
+        if snapshot != last_snapshot {
+            write!(stream, "data: {snapshot}\n\n")?;
+            last_snapshot = snapshot;
+        } else if poll_interval == IDLE_POLL_INTERVAL {
+            // a comment, so that viewers who have left are noticed even if nothing is happening.
+            write!(stream, ":\n\n")?;
+        }
+        sleep(poll_interval);
+    }
+}
+
+fn metrics_json(buffer_pool: PoolStats, ticks: TickStats) -> String {
+    let millis = |duration: Duration| format!("{:.3}", duration.as_secs_f64() * 1000.0);
+    format!(
+        r#"{{"buffer_pool":{{"hits":{},"misses":{},"pooled":{},"hit_rate":{}}},"ticks":{{"count":{},"overruns":{},"mean_lateness_ms":{},"max_lateness_ms":{},"mean_duration_ms":{},"max_duration_ms":{}}}}}"#,
+        buffer_pool.hits,
+        buffer_pool.misses,
+        buffer_pool.pooled,
+        buffer_pool
+            .hit_rate()
+            .map_or("null".to_owned(), |rate| format!("{rate:.3}")),
+        ticks.ticks,
+        ticks.overruns,
+        ticks.mean_lateness().map_or("null".to_owned(), millis),
+        millis(ticks.max_lateness),
+        ticks.mean_duration().map_or("null".to_owned(), millis),
+        millis(ticks.max_duration),
+    )
+}
+
+fn lobbies_json(lobbies: &Lobbies) -> String {
+    let mut lobbies: Vec<_> = lobbies
+        .all()
+        .into_iter()
+        .filter_map(|(lobby_id, lobby)| {
+            let json = lobby_json(lobby_id, lobby.lock().as_ref()?);
+            Some((lobby_id.to_string(), json))
+        })
+        .collect();
+    lobbies.sort();
+    let lobbies: Vec<_> = lobbies.into_iter().map(|(_, json)| json).collect();
+    format!("[{}]", lobbies.join(","))
+}
+
+fn lobby_json(lobby_id: LobbyId, lobby: &Lobby) -> String {
+    let (state, players, dimensions, ruleset, game_state) = match lobby {
+        Lobby::AwaitingJoin {
+            dimensions,
+            ruleset,
+            ..
+        } => ("awaiting_join", 1, dimensions, ruleset, None),
+        Lobby::Joined {
+            right_player_conn,
+            dimensions,
+            ruleset,
+            state,
+            ..
+        } => {
+            // the wall of a practice lobby isn't a player.
+            let players = if right_player_conn.is_some() { 2 } else { 1 };
+            match state {
+                LobbyState::AwaitingReadies { .. } => {
+                    ("awaiting_readies", players, dimensions, ruleset, None)
+                }
+                LobbyState::Playing { game_state, .. } => {
+                    ("playing", players, dimensions, ruleset, Some(game_state))
+                }
+            }
+        }
+    };
+    format!(
+        r#"{{"id":"{lobby_id}","state":"{state}","players":{players},{},{},"modifier":{},"game_state":{}}}"#,
+        json::dimensions_fields(*dimensions),
+        json::paddles_fields(*dimensions, ruleset.handicaps),
+        json::modifier(ruleset.modifier),
+        game_state.map_or("null".to_owned(), json::game_state),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use rand::{rngs::StdRng, SeedableRng};
+    use shared::{
+        client_msg::Capabilities,
+        game_state::{
+            Ball, GameDimensions, GameState, Handicap, Handicaps, Modifier, MoveDirection, Ruleset,
+            Side,
+        },
+        LobbyId,
+    };
+
+    use crate::{
+        buffer_pool::PoolStats,
+        client_conn::ClientConn,
+        dashboard::{lobby_json, metrics_json, read_request, route, Route, MAX_REQUEST_SIZE},
+        event_log::EventLog,
+        lobby::{Lobby, LobbyState, MatchScore, PlayerId},
+        tick_timings::TickStats,
+    };
+
+    #[test]
+    fn routes() {
+        let lobby_id: LobbyId = "ABCD".parse().unwrap();
+        assert_eq!(route("GET / HTTP/1.1\r\n"), Route::Page);
+        assert_eq!(route("GET /lobbies HTTP/1.1\r\n"), Route::Lobbies);
+        assert_eq!(
+            route("GET /lobbies/abcd/events HTTP/1.1\r\n"),
+            Route::LobbyEvents(lobby_id)
+        );
+        assert_eq!(
+            route("GET /lobbies/ABC/events HTTP/1.1\r\n"),
+            Route::NotFound
+        );
+        assert_eq!(route("GET /metrics HTTP/1.1\r\n"), Route::Metrics);
+        assert_eq!(route("POST /lobbies HTTP/1.1\r\n"), Route::NotFound);
+        assert_eq!(route("GET /favicon.ico HTTP/1.1\r\n"), Route::NotFound);
+        assert_eq!(route(""), Route::NotFound);
+    }
+
+    #[test]
+    fn caps_requests() {
+        let request = "GET /lobbies HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        assert_eq!(
+            read_request(request.as_bytes()).unwrap().as_deref(),
+            Some("GET /lobbies HTTP/1.1\r\n")
+        );
+        // an endless header is only read up to the cap.
+        let header = "X".repeat(MAX_REQUEST_SIZE as usize * 2);
+        let request = format!("GET /lobbies HTTP/1.1\r\nHost: {header}\r\n\r\n");
+        assert_eq!(read_request(request.as_bytes()).unwrap(), None);
+    }
+
+    #[test]
+    fn metrics() {
+        let stats = PoolStats {
+            hits: 3,
+            misses: 1,
+            pooled: 2,
+        };
+        let ticks = TickStats {
+            ticks: 4,
+            overruns: 1,
+            total_lateness: Duration::from_micros(2_500),
+            max_lateness: Duration::from_micros(1_800),
+            total_duration: Duration::from_micros(400),
+            max_duration: Duration::from_micros(250),
+        };
+        assert_eq!(
+            metrics_json(stats, ticks),
+            r#"{"buffer_pool":{"hits":3,"misses":1,"pooled":2,"hit_rate":0.750},"ticks":{"count":4,"overruns":1,"mean_lateness_ms":0.625,"max_lateness_ms":1.800,"mean_duration_ms":0.100,"max_duration_ms":0.250}}"#
+        );
+        let unused = PoolStats {
+            hits: 0,
+            misses: 0,
+            pooled: 0,
+        };
+        assert_eq!(
+            metrics_json(unused, TickStats::default()),
+            r#"{"buffer_pool":{"hits":0,"misses":0,"pooled":0,"hit_rate":null},"ticks":{"count":0,"overruns":0,"mean_lateness_ms":null,"max_lateness_ms":0.000,"mean_duration_ms":null,"max_duration_ms":0.000}}"#
+        );
+    }
+
+    #[test]
+    fn lobby_snapshots() {
+        let lobby_id: LobbyId = "ABCD".parse().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let conn = || {
+            Arc::new(ClientConn::new(
+                TcpStream::connect(listener.local_addr().unwrap()).unwrap(),
+            ))
+        };
+        let dimensions = GameDimensions::new(20, 10, 3).unwrap();
+        let awaiting_join = Lobby::AwaitingJoin {
+            host_player_id: PlayerId(0),
+            host_player_conn: conn(),
+            host_capabilities: Capabilities::ALL,
+            dimensions,
+            ruleset: Ruleset {
+                tick_interval_ms: 100,
+                match_length: None,
+                handicaps: Handicaps::NONE,
+                modifier: Modifier::Classic,
+            },
+            coop: false,
+            rng: StdRng::seed_from_u64(0),
+            event_log: EventLog::DISABLED,
+            created_at: Instant::now(),
+            expires_at: Instant::now(),
+        };
+        assert_eq!(
+            lobby_json(lobby_id, &awaiting_join),
+            r#"{"id":"ABCD","state":"awaiting_join","players":1,"width":20,"height":10,"paddle_height":3,"left_paddle_height":3,"left_paddle_speed":1,"right_paddle_height":3,"right_paddle_speed":1,"modifier":"classic","game_state":null}"#
+        );
+        let joined = |state| Lobby::Joined {
+            left_player_id: PlayerId(0),
+            left_player_conn: conn(),
+            right_player_id: PlayerId(1),
+            right_player_conn: Some(conn()),
+            left_player_capabilities: Capabilities::ALL,
+            right_player_capabilities: Capabilities::ALL,
+            dimensions,
+            ruleset: Ruleset {
+                tick_interval_ms: 100,
+                match_length: Some(3),
+                handicaps: Handicaps {
+                    left: Handicap::NONE,
+                    right: Handicap {
+                        paddle_height: Some(2),
+                        paddle_speed: None,
+                    },
+                },
+                modifier: Modifier::Wobble,
+            },
+            coop: false,
+            rng: StdRng::seed_from_u64(0),
+            games_played: 0,
+            last_winner: None,
+            score: MatchScore::default(),
+            match_started_at: None,
+            state,
+            event_log: EventLog::DISABLED,
+            tournament_id: None,
+        };
+        assert_eq!(
+            lobby_json(
+                lobby_id,
+                &joined(LobbyState::awaiting_readies(Instant::now()))
+            ),
+            r#"{"id":"ABCD","state":"awaiting_readies","players":2,"width":20,"height":10,"paddle_height":3,"left_paddle_height":3,"left_paddle_speed":1,"right_paddle_height":2,"right_paddle_speed":1,"modifier":"wobble","game_state":null}"#
+        );
+        let playing = LobbyState::Playing {
+            game_state: GameState {
+                left_paddle: 1,
+                right_paddle: 7,
+                ball: Ball {
+                    x: 12,
+                    y: 4,
+                    moving_right: true,
+                    moving_down: false,
+                },
+            },
+            ticks: 0,
+            hits: 0,
+            serving: Side::Left,
+            served: true,
+            replay: None,
+            left_paddle_direction: MoveDirection::Stop,
+            right_paddle_direction: MoveDirection::Up,
+            left_input_received: false,
+            right_input_received: false,
+            game_loop: None,
+            coop: None,
+        };
+        assert_eq!(
+            lobby_json(lobby_id, &joined(playing)),
+            r#"{"id":"ABCD","state":"playing","players":2,"width":20,"height":10,"paddle_height":3,"left_paddle_height":3,"left_paddle_speed":1,"right_paddle_height":2,"right_paddle_speed":1,"modifier":"wobble","game_state":{"left_paddle":1,"right_paddle":7,"ball":{"x":12,"y":4}}}"#
+        );
+    }
+}
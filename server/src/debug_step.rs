@@ -0,0 +1,100 @@
+use std::{collections::HashMap, io::BufRead, sync::Mutex};
+
+use shared::LobbyId;
+
+const USAGE: &str = "expected `step <LOBBY_ID> [TICKS]`";
+
+/// pauses every game, letting an admin advance each one a tick at a time by typing
+/// `step <LOBBY_ID> [TICKS]` into the server's stdin. enabled with `DEBUG_STEP=true`, for
+/// reproducing physics bugs one tick at a time.
+#[derive(Default)]
+pub struct DebugStepper {
+    /// how many more ticks each lobby's game may make.
+    pending_steps: Mutex<HashMap<LobbyId, u32>>,
+}
+
+impl DebugStepper {
+    /// reads admin commands until the input is closed.
+    pub fn run_console(&self, input: impl BufRead) {
+        for line in input.lines() {
+            let Ok(line) = line else {
+                return;
+            };
+            match parse_command(&line) {
+                Some((lobby_id, ticks)) => self.step(lobby_id, ticks),
+                None => println!("{USAGE}"),
+            }
+        }
+    }
+
+    pub fn step(&self, lobby_id: LobbyId, ticks: u32) {
+        let mut pending_steps = self.pending_steps.lock().unwrap();
+        let steps = pending_steps.entry(lobby_id).or_default();
+        *steps = steps.saturating_add(ticks);
+    }
+
+    /// uses up one of the lobby's steps, returning whether its game may make another tick. a game
+    /// that ends while waiting for a step isn't noticed until the lobby is stepped again.
+    pub fn take_step(&self, lobby_id: LobbyId) -> bool {
+        let mut pending_steps = self.pending_steps.lock().unwrap();
+        let Some(steps) = pending_steps.get_mut(&lobby_id) else {
+            return false;
+        };
+        *steps -= 1;
+        if *steps == 0 {
+            pending_steps.remove(&lobby_id);
+        }
+        true
+    }
+}
+
+fn parse_command(line: &str) -> Option<(LobbyId, u32)> {
+    let mut words = line.split_whitespace();
+    if words.next()? != "step" {
+        return None;
+    }
+    let lobby_id = words.next()?.parse().ok()?;
+    let ticks = match words.next() {
+        Some(ticks) => ticks.parse().ok().filter(|ticks| *ticks > 0)?,
+        None => 1,
+    };
+    words.next().is_none().then_some((lobby_id, ticks))
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::LobbyId;
+
+    use crate::debug_step::{parse_command, DebugStepper};
+
+    #[test]
+    fn parse_commands() {
+        let lobby_id: LobbyId = "ABCD".parse().unwrap();
+        assert_eq!(parse_command("step abcd"), Some((lobby_id, 1)));
+        assert_eq!(parse_command("  step ABCD 10 "), Some((lobby_id, 10)));
+        assert_eq!(parse_command("step ABCD 0"), None);
+        assert_eq!(parse_command("step ABCD -1"), None);
+        assert_eq!(parse_command("step ABCD 1 2"), None);
+        assert_eq!(parse_command("step"), None);
+        assert_eq!(parse_command("stop ABCD"), None);
+        assert_eq!(parse_command(""), None);
+    }
+
+    #[test]
+    fn takes_each_step() {
+        let lobby_id: LobbyId = "ABCD".parse().unwrap();
+        let other_lobby_id: LobbyId = "EFGH".parse().unwrap();
+        let stepper = DebugStepper::default();
+        assert!(!stepper.take_step(lobby_id));
+        stepper.step(other_lobby_id, 1);
+        assert!(!stepper.take_step(lobby_id));
+        stepper.step(lobby_id, 2);
+        assert!(stepper.take_step(lobby_id));
+        assert!(stepper.take_step(lobby_id));
+        assert!(!stepper.take_step(lobby_id));
+        stepper.run_console("step ABCD\nnonsense\n".as_bytes());
+        assert!(stepper.take_step(lobby_id));
+        assert!(!stepper.take_step(lobby_id));
+        assert!(stepper.take_step(other_lobby_id));
+    }
+}
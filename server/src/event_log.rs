@@ -0,0 +1,326 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use shared::{
+    game_state::{GameDimensions, GameState, MoveDirection, Ruleset, Side},
+    LobbyId,
+};
+
+use crate::{json, lobby::PlayerId};
+
+/// something that happened in a lobby, worth recording for later analysis or replay.
+pub enum LobbyEvent<'a> {
+    Created {
+        host: PlayerId,
+        dimensions: GameDimensions,
+        ruleset: Ruleset,
+        seed: u64,
+    },
+    Joined {
+        player: PlayerId,
+    },
+    Readied {
+        player: PlayerId,
+    },
+    Unreadied {
+        player: PlayerId,
+    },
+    GameStarted {
+        game: u32,
+        game_state: &'a GameState,
+    },
+    /// a player changed the direction their paddle moves in, after the given number of ticks of
+    /// the game.
+    Moved {
+        game: u32,
+        tick: u32,
+        player: PlayerId,
+        direction: MoveDirection,
+    },
+    /// the ball got past a paddle on the given tick of the game.
+    GameWon {
+        game: u32,
+        tick: u32,
+        side: Side,
+        player: PlayerId,
+    },
+    /// the ball got past both players of a co-op survival game on the given tick of the game,
+    /// after they'd returned it the given number of times.
+    Survived {
+        game: u32,
+        tick: u32,
+        score: u16,
+    },
+    Resigned {
+        game: u32,
+        tick: u32,
+        player: PlayerId,
+    },
+    Left {
+        player: PlayerId,
+    },
+    TimedOut,
+    /// the lobby was closed after waiting too long for an opponent without its host being active.
+    Expired,
+}
+
+impl LobbyEvent<'_> {
+    fn to_json(&self) -> String {
+        match self {
+            Self::Created {
+                host,
+                dimensions,
+                ruleset,
+                seed,
+            } => format!(
+                r#""event":"created","player":{host},"tick_interval_ms":{},{},{},"modifier":{},"match_length":{},"seed":{seed}"#,
+                ruleset.tick_interval_ms,
+                json::dimensions_fields(*dimensions),
+                json::paddles_fields(*dimensions, ruleset.handicaps),
+                json::modifier(ruleset.modifier),
+                ruleset
+                    .match_length
+                    .map_or("null".to_owned(), |match_length| match_length.to_string()),
+            ),
+            Self::Joined { player } => format!(r#""event":"joined","player":{player}"#),
+            Self::Readied { player } => format!(r#""event":"readied","player":{player}"#),
+            Self::Unreadied { player } => format!(r#""event":"unreadied","player":{player}"#),
+            Self::GameStarted { game, game_state } => format!(
+                r#""event":"game_started","game":{game},"game_state":{}"#,
+                json::game_state(game_state),
+            ),
+            Self::Moved {
+                game,
+                tick,
+                player,
+                direction,
+            } => format!(
+                r#""event":"moved","game":{game},"tick":{tick},"player":{player},"direction":{}"#,
+                json::move_direction(*direction),
+            ),
+            Self::GameWon {
+                game,
+                tick,
+                side,
+                player,
+            } => format!(
+                r#""event":"game_won","game":{game},"tick":{tick},"side":{},"player":{player}"#,
+                json::side(*side),
+            ),
+            Self::Survived { game, tick, score } => {
+                format!(r#""event":"survived","game":{game},"tick":{tick},"score":{score}"#)
+            }
+            Self::Resigned { game, tick, player } => {
+                format!(r#""event":"resigned","game":{game},"tick":{tick},"player":{player}"#)
+            }
+            Self::Left { player } => format!(r#""event":"left","player":{player}"#),
+            Self::TimedOut => r#""event":"timed_out""#.to_owned(),
+            Self::Expired => r#""event":"expired""#.to_owned(),
+        }
+    }
+}
+
+/// where the events of a single lobby are recorded, one json object per line. does nothing if
+/// event logging is disabled.
+#[derive(Clone)]
+pub struct EventLog {
+    file: Option<Arc<File>>,
+}
+
+impl EventLog {
+    pub const DISABLED: EventLog = EventLog { file: None };
+
+    /// creates a new log file for the lobby in the given directory, if there is one. failing to
+    /// do so disables the lobby's log rather than its game.
+    pub fn create(dir: Option<&Path>, lobby_id: LobbyId) -> Self {
+        let Some(dir) = dir else {
+            return Self::DISABLED;
+        };
+        // lobby ids are reused, so the creation time is included to keep each lobby's log apart.
+        let path = dir.join(format!("{lobby_id}-{}.jsonl", unix_time_ms()));
+        match fs::create_dir_all(dir).and_then(|_| File::create(&path)) {
+            Ok(file) => Self {
+                file: Some(Arc::new(file)),
+            },
+            Err(err) => {
+                eprintln!("failed to create event log {}: {err}", path.display());
+                Self::DISABLED
+            }
+        }
+    }
+
+    pub fn record(&self, event: LobbyEvent) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        // each event is written with a single call, so that events recorded by different threads
+        // don't interleave.
+        let line = format!("{{\"time_ms\":{},{}}}\n", unix_time_ms(), event.to_json());
+        if let Err(err) = (&**file).write_all(line.as_bytes()) {
+            eprintln!("failed to record lobby event: {err}");
+        }
+    }
+}
+
+pub(crate) fn unix_time_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use shared::{
+        game_state::{
+            Ball, GameDimensions, GameState, Handicap, Handicaps, Modifier, MoveDirection, Ruleset,
+            Side,
+        },
+        LobbyId,
+    };
+
+    use crate::{
+        event_log::{EventLog, LobbyEvent},
+        lobby::PlayerId,
+    };
+
+    #[test]
+    fn events_to_json() {
+        let game_state = GameState {
+            left_paddle: 0,
+            right_paddle: 3,
+            ball: Ball {
+                x: 25,
+                y: 6,
+                moving_right: false,
+                moving_down: true,
+            },
+        };
+        let events = [
+            (
+                LobbyEvent::Created {
+                    host: PlayerId(4),
+                    dimensions: GameDimensions::DEFAULT,
+                    ruleset: Ruleset {
+                        tick_interval_ms: 100,
+                        match_length: Some(3),
+                        handicaps: Handicaps {
+                            left: Handicap::NONE,
+                            right: Handicap {
+                                paddle_height: Some(3),
+                                paddle_speed: Some(2),
+                            },
+                        },
+                        modifier: Modifier::Gravity,
+                    },
+                    seed: 42,
+                },
+                r#""event":"created","player":4,"tick_interval_ms":100,"width":51,"height":11,"paddle_height":5,"left_paddle_height":5,"left_paddle_speed":1,"right_paddle_height":3,"right_paddle_speed":2,"modifier":"gravity","match_length":3,"seed":42"#,
+            ),
+            (
+                LobbyEvent::Joined {
+                    player: PlayerId(5),
+                },
+                r#""event":"joined","player":5"#,
+            ),
+            (
+                LobbyEvent::Readied {
+                    player: PlayerId(5),
+                },
+                r#""event":"readied","player":5"#,
+            ),
+            (
+                LobbyEvent::Unreadied {
+                    player: PlayerId(5),
+                },
+                r#""event":"unreadied","player":5"#,
+            ),
+            (
+                LobbyEvent::GameStarted {
+                    game: 0,
+                    game_state: &game_state,
+                },
+                r#""event":"game_started","game":0,"game_state":{"left_paddle":0,"right_paddle":3,"ball":{"x":25,"y":6}}"#,
+            ),
+            (
+                LobbyEvent::Moved {
+                    game: 0,
+                    tick: 7,
+                    player: PlayerId(4),
+                    direction: MoveDirection::Down,
+                },
+                r#""event":"moved","game":0,"tick":7,"player":4,"direction":"down""#,
+            ),
+            (
+                LobbyEvent::GameWon {
+                    game: 0,
+                    tick: 31,
+                    side: Side::Right,
+                    player: PlayerId(5),
+                },
+                r#""event":"game_won","game":0,"tick":31,"side":"right","player":5"#,
+            ),
+            (
+                LobbyEvent::Survived {
+                    game: 0,
+                    tick: 480,
+                    score: 23,
+                },
+                r#""event":"survived","game":0,"tick":480,"score":23"#,
+            ),
+            (
+                LobbyEvent::Resigned {
+                    game: 1,
+                    tick: 2,
+                    player: PlayerId(4),
+                },
+                r#""event":"resigned","game":1,"tick":2,"player":4"#,
+            ),
+            (
+                LobbyEvent::Left {
+                    player: PlayerId(4),
+                },
+                r#""event":"left","player":4"#,
+            ),
+            (LobbyEvent::TimedOut, r#""event":"timed_out""#),
+            (LobbyEvent::Expired, r#""event":"expired""#),
+        ];
+        for (event, json) in events {
+            assert_eq!(event.to_json(), json);
+        }
+    }
+
+    #[test]
+    fn records_one_event_per_line() {
+        let dir = std::env::temp_dir().join(format!("pong_event_log_{}", std::process::id()));
+        let lobby_id: LobbyId = "ABCD".parse().unwrap();
+        let log = EventLog::create(Some(&dir), lobby_id);
+        log.record(LobbyEvent::Joined {
+            player: PlayerId(1),
+        });
+        log.clone().record(LobbyEvent::TimedOut);
+        let entries: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+        let path = entries[0].as_ref().unwrap().path();
+        assert!(path
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("ABCD-"));
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let lines: Vec<_> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with(r#"{"time_ms":"#));
+        assert!(lines[0].ends_with(r#","event":"joined","player":1}"#));
+        assert!(lines[1].ends_with(r#","event":"timed_out"}"#));
+    }
+}
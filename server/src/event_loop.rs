@@ -0,0 +1,232 @@
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
+    time::Instant,
+};
+
+use mio::{Events, Interest, Poll, Token, Waker};
+use shared::{
+    checksum::CHECKSUM_SIZE,
+    client_msg::{CLIENT_MESSAGE_DELIMITER, MAX_CLIENT_MESSAGE_SIZE},
+    io::{MessageReader, ReadMessageError},
+};
+
+use crate::{
+    scheduler::{Scheduler, TaskEvent, TaskTimers},
+    tcp_stream_handler::{TcpStreamHandler, READ_TIMEOUT_CHECK_INTERVAL},
+};
+
+const LISTENER: Token = Token(0);
+/// woken when a task is handed to the event loop, or woken itself.
+const WAKER: Token = Token(1);
+/// the token given to the first connection, each one after it getting the next.
+const FIRST_CONNECTION: usize = 2;
+
+const EVENT_CAPACITY: usize = 1024;
+
+/// a connection being served by the event loop.
+struct Connection {
+    /// registered for the connection's readiness. it shares its socket with the reader and the
+    /// handler's stream, none of which block. frames queued for the client are written once it's
+    /// writable again.
+    source: mio::net::TcpStream,
+    reader: MessageReader<TcpStream>,
+    handler: TcpStreamHandler,
+    /// when the last message arrived, or the connection was accepted, for closing it once it's
+    /// gone quiet for too long.
+    last_message: Instant,
+}
+
+/// serves every connection, and runs every task, on a single thread. connections are only read
+/// once they're readable, and tasks are only polled once they're due, so an idle connection costs
+/// nothing but its socket.
+pub struct EventLoop {
+    poll: Poll,
+    waker: Arc<Waker>,
+    task_tx: Sender<TaskEvent>,
+    task_rx: Receiver<TaskEvent>,
+}
+
+impl EventLoop {
+    pub fn new() -> io::Result<Self> {
+        let poll = Poll::new()?;
+        let waker = Arc::new(Waker::new(poll.registry(), WAKER)?);
+        let (task_tx, task_rx) = channel();
+        Ok(Self {
+            poll,
+            waker,
+            task_tx,
+            task_rx,
+        })
+    }
+
+    /// a scheduler that hands tasks to this event loop.
+    pub fn scheduler(&self) -> Scheduler {
+        Scheduler::EventLoop {
+            tasks: self.task_tx.clone(),
+            waker: Arc::clone(&self.waker),
+        }
+    }
+
+    /// serves connections accepted by the listener until the process exits, creating a handler
+    /// for each one.
+    pub fn run(
+        mut self,
+        listener: TcpListener,
+        mut new_handler: impl FnMut(TcpStream, SocketAddr) -> Option<TcpStreamHandler>,
+    ) -> io::Result<()> {
+        listener.set_nonblocking(true)?;
+        let mut listener_source = mio::net::TcpListener::from_std(listener.try_clone()?);
+        self.poll
+            .registry()
+            .register(&mut listener_source, LISTENER, Interest::READABLE)?;
+        let mut connections = HashMap::<Token, Connection>::new();
+        let mut next_connection = FIRST_CONNECTION;
+        let mut tasks = TaskTimers::new();
+        let mut events = Events::with_capacity(EVENT_CAPACITY);
+        let mut next_read_timeout_check = Instant::now() + READ_TIMEOUT_CHECK_INTERVAL;
+        loop {
+            // tasks are first polled as soon as they're handed over, as they may already be due.
+            while let Ok(event) = self.task_rx.try_recv() {
+                match event {
+                    TaskEvent::Spawned(id, task) => tasks.insert(id, task),
+                    TaskEvent::Woken(id) => tasks.wake(id),
+                }
+            }
+            for (id, mut task) in tasks.take_due(Instant::now()) {
+                let next_poll = task.poll(Instant::now());
+                tasks.put_back(id, task, next_poll);
+            }
+            let now = Instant::now();
+            if now >= next_read_timeout_check {
+                next_read_timeout_check = now + READ_TIMEOUT_CHECK_INTERVAL;
+                let timed_out = connections
+                    .iter()
+                    .filter(|(_, connection)| {
+                        connection
+                            .handler
+                            .read_timed_out(connection.last_message, now)
+                    })
+                    .map(|(&token, _)| token)
+                    .collect::<Vec<_>>();
+                for token in timed_out {
+                    let mut connection = connections.remove(&token).unwrap();
+                    self.poll.registry().deregister(&mut connection.source)?;
+                    connection.handler.handle_read_timeout();
+                }
+            }
+            let timeout = tasks
+                .next_expiry()
+                .unwrap_or(next_read_timeout_check)
+                .min(next_read_timeout_check)
+                .saturating_duration_since(Instant::now());
+            if let Err(err) = self.poll.poll(&mut events, Some(timeout)) {
+                if err.kind() == ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err);
+            }
+            for event in &events {
+                match event.token() {
+                    LISTENER => loop {
+                        let (stream, peer_addr) = match listener.accept() {
+                            Ok(accepted) => accepted,
+                            Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                            Err(err) => {
+                                eprintln!("incoming connection failure: {err}");
+                                break;
+                            }
+                        };
+                        let token = Token(next_connection);
+                        match self.connection(stream, peer_addr, token, &mut new_handler) {
+                            Ok(Some(connection)) => {
+                                connections.insert(token, connection);
+                                next_connection += 1;
+                            }
+                            Ok(None) => {}
+                            Err(err) => eprintln!("failed to serve connection: {err}"),
+                        }
+                    },
+                    // any tasks handed over are picked up at the top of the loop.
+                    WAKER => {}
+                    token => {
+                        let Some(connection) = connections.get_mut(&token) else {
+                            continue;
+                        };
+                        if event.is_writable() {
+                            connection.handler.conn().flush();
+                        }
+                        if event.is_readable() && !Self::read(connection) {
+                            let mut connection = connections.remove(&token).unwrap();
+                            self.poll.registry().deregister(&mut connection.source)?;
+                            connection.handler.handle_close();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// sets up a newly accepted connection to be served, or nothing if it can't be.
+    fn connection(
+        &self,
+        stream: TcpStream,
+        peer_addr: SocketAddr,
+        token: Token,
+        new_handler: &mut impl FnMut(TcpStream, SocketAddr) -> Option<TcpStreamHandler>,
+    ) -> io::Result<Option<Connection>> {
+        // this affects every handle on the socket, so the handler's writes can't block either.
+        stream.set_nonblocking(true)?;
+        let mut source = mio::net::TcpStream::from_std(stream.try_clone()?);
+        let reader = MessageReader::new(
+            stream.try_clone()?,
+            CLIENT_MESSAGE_DELIMITER,
+            MAX_CLIENT_MESSAGE_SIZE + CHECKSUM_SIZE,
+        );
+        let Some(handler) = new_handler(stream, peer_addr) else {
+            return Ok(None);
+        };
+        self.poll.registry().register(
+            &mut source,
+            token,
+            Interest::READABLE | Interest::WRITABLE,
+        )?;
+        Ok(Some(Connection {
+            source,
+            reader,
+            handler,
+            last_message: Instant::now(),
+        }))
+    }
+
+    /// handles every message that has arrived on the connection, returning false once it's closed.
+    fn read(connection: &mut Connection) -> bool {
+        loop {
+            match connection.reader.read_message() {
+                Ok(message) => {
+                    connection.last_message = Instant::now();
+                    connection.handler.handle_message(message);
+                }
+                Err(ReadMessageError::Io(err)) if err.kind() == ErrorKind::WouldBlock => {
+                    return true;
+                }
+                Err(ReadMessageError::Closed) => return false,
+                Err(ReadMessageError::OversizedMessage) => {
+                    connection
+                        .handler
+                        .handle_read_error(ReadMessageError::OversizedMessage);
+                }
+                // a connection that can't be read from won't become readable again.
+                Err(err) => {
+                    connection.handler.handle_read_error(err);
+                    return false;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use shared::game_state::{GameDimensions, GameState, Handicaps, Modifier, MoveDirection, Side};
+
+/// the fields of a json object describing the dimensions of a game, without the enclosing braces.
+pub fn dimensions_fields(dimensions: GameDimensions) -> String {
+    format!(
+        r#""width":{},"height":{},"paddle_height":{}"#,
+        dimensions.width(),
+        dimensions.height(),
+        dimensions.paddle_height(),
+    )
+}
+
+/// the fields of a json object describing each player's paddle, after their handicaps, without
+/// the enclosing braces.
+pub fn paddles_fields(dimensions: GameDimensions, handicaps: Handicaps) -> String {
+    format!(
+        r#""left_paddle_height":{},"left_paddle_speed":{},"right_paddle_height":{},"right_paddle_speed":{}"#,
+        handicaps.left.paddle_height(dimensions),
+        handicaps.left.paddle_speed(),
+        handicaps.right.paddle_height(dimensions),
+        handicaps.right.paddle_speed(),
+    )
+}
+
+pub fn game_state(game_state: &GameState) -> String {
+    format!(
+        r#"{{"left_paddle":{},"right_paddle":{},"ball":{{"x":{},"y":{}}}}}"#,
+        game_state.left_paddle, game_state.right_paddle, game_state.ball.x, game_state.ball.y,
+    )
+}
+
+pub fn move_direction(direction: MoveDirection) -> &'static str {
+    match direction {
+        MoveDirection::Stop => r#""stop""#,
+        MoveDirection::Up => r#""up""#,
+        MoveDirection::Down => r#""down""#,
+    }
+}
+
+pub fn modifier(modifier: Modifier) -> &'static str {
+    match modifier {
+        Modifier::Classic => r#""classic""#,
+        Modifier::Gravity => r#""gravity""#,
+        Modifier::Wobble => r#""wobble""#,
+        Modifier::Wrap => r#""wrap""#,
+    }
+}
+
+pub fn side(side: Side) -> &'static str {
+    match side {
+        Side::Left => r#""left""#,
+        Side::Right => r#""right""#,
+    }
+}
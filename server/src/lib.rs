@@ -1,4 +1,28 @@
+pub mod access_list;
+pub mod buffer_pool;
+pub mod client_conn;
+pub mod config;
+pub mod daemon;
+pub mod dashboard;
+pub mod debug_step;
+pub mod event_log;
+pub mod event_loop;
+pub mod json;
 pub mod lobby;
 pub mod lobby_id_generator;
+pub mod match_queue;
+pub mod quota;
+pub mod replay;
+pub mod scheduler;
+pub mod simulation;
+pub mod stats;
+pub mod summary;
+pub mod systemd;
+pub mod task_pool;
 pub mod tcp_server;
 pub mod tcp_stream_handler;
+pub mod telemetry;
+pub mod tick_timings;
+pub mod timer_wheel;
+pub mod tournament;
+pub mod worker_pool;
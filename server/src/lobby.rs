@@ -1,24 +1,798 @@
-use std::net::TcpStream;
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    mem,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Instant,
+};
 
-use shared::game_state::GameState;
+use dashmap::{mapref::entry::Entry, DashMap};
+use rand::{rngs::StdRng, Rng};
+use shared::{
+    client_msg::Capabilities,
+    game_state::{
+        Ball, GameDimensions, GameState, Handicap, Handicaps, Modifier, MoveDirection, Ruleset,
+        Side,
+    },
+    server_msg::LOBBY_PAGE_SIZE,
+    session::ListedLobby,
+    LobbyId,
+};
+
+use crate::{
+    client_conn::ClientConn,
+    config::{ServeRule, Tiebreak},
+    event_log::EventLog,
+    lobby_id_generator::LobbyIdGenerator,
+    replay::ReplayRecorder,
+    scheduler::TaskId,
+};
+
+/// a server-side identifier assigned to each connection when it is accepted.
+/// used to determine which seat in a lobby a connection occupies, since peer addresses are not
+/// guaranteed to be unique (e.g. clients behind the same NAT or proxy).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PlayerId(pub u64);
+
+impl PlayerId {
+    /// sits in the right seat of a practice lobby. never assigned to a connection.
+    pub const WALL: PlayerId = PlayerId(u64::MAX);
+}
+
+impl Display for PlayerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
 
 pub enum Lobby {
     AwaitingJoin {
-        host_player_conn: TcpStream,
+        host_player_id: PlayerId,
+        host_player_conn: Arc<ClientConn>,
+        host_capabilities: Capabilities,
+        dimensions: GameDimensions,
+        /// the rules the host chose, as settled by the server.
+        ruleset: Ruleset,
+        /// whether the lobby is for a co-op survival game, in which both players defend the left
+        /// side against a paddle steered by the server.
+        coop: bool,
+        rng: StdRng,
+        event_log: EventLog,
+        created_at: Instant,
+        /// when the lobby will be closed, unless its host is active before then.
+        expires_at: Instant,
     },
     Joined {
-        left_player_conn: TcpStream,
-        right_player_conn: TcpStream,
+        left_player_id: PlayerId,
+        left_player_conn: Arc<ClientConn>,
+        right_player_id: PlayerId,
+        /// `None` in a practice lobby, where the right seat is a wall.
+        right_player_conn: Option<Arc<ClientConn>>,
+        /// what each player's client supports, so that they're only sent messages they understand.
+        left_player_capabilities: Capabilities,
+        right_player_capabilities: Capabilities,
+        dimensions: GameDimensions,
+        ruleset: Ruleset,
+        coop: bool,
+        rng: StdRng,
+        /// incremented whenever a game ends, so that the game loop of a finished game can tell
+        /// that it should stop even if the next game has already started.
+        games_played: u32,
+        /// the side that won the last game, which decides who serves the next.
+        last_winner: Option<Side>,
+        /// the games each player has won in the current match, if the server keeps score.
+        score: MatchScore,
+        /// when the first game of the current match started, so that how long it took can be
+        /// reported once it's over.
+        match_started_at: Option<Instant>,
         state: LobbyState,
+        event_log: EventLog,
+        /// the tournament the lobby is a match in, if any. such lobbies are closed after one game.
+        tournament_id: Option<LobbyId>,
     },
 }
 
+impl Lobby {
+    pub fn has_player(&self, player_id: PlayerId) -> bool {
+        match self {
+            Lobby::AwaitingJoin { host_player_id, .. } => *host_player_id == player_id,
+            Lobby::Joined {
+                left_player_id,
+                right_player_id,
+                ..
+            } => *left_player_id == player_id || *right_player_id == player_id,
+        }
+    }
+}
+
+/// every open lobby on the server, by id. the map is only used to find a lobby: each lobby is
+/// behind a lock of its own, so that a game loop ticking one lobby never blocks a client or loop
+/// working on another.
+///
+/// a lobby must never be locked while an entry of the map is held, since the map is locked while
+/// holding a lobby to close it. nor should two lobbies ever be locked at once.
+#[derive(Default)]
+pub struct Lobbies {
+    lobbies: DashMap<LobbyId, Arc<SharedLobby>>,
+}
+
+/// a lobby, as shared by the clients in it, its game loop and its timers, each of which holds on
+/// to it directly rather than looking it up by id.
+pub struct SharedLobby {
+    /// `None` once the lobby has been closed, which is how anything still holding on to it can
+    /// tell.
+    lobby: Mutex<Option<Lobby>>,
+}
+
+impl SharedLobby {
+    fn new(lobby: Lobby) -> Arc<Self> {
+        Arc::new(Self {
+            lobby: Mutex::new(Some(lobby)),
+        })
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, Option<Lobby>> {
+        self.lobby.lock().unwrap()
+    }
+}
+
+impl Lobbies {
+    pub fn get(&self, lobby_id: &LobbyId) -> Option<Arc<SharedLobby>> {
+        self.lobbies.get(lobby_id).map(|lobby| Arc::clone(&lobby))
+    }
+
+    pub fn contains(&self, lobby_id: &LobbyId) -> bool {
+        self.lobbies.contains_key(lobby_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lobbies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lobbies.is_empty()
+    }
+
+    /// opens the lobby made by `lobby` under the given id, unless it's already taken, in which
+    /// case the lobby isn't made at all.
+    pub fn open(
+        &self,
+        lobby_id: LobbyId,
+        lobby: impl FnOnce() -> Lobby,
+    ) -> Option<Arc<SharedLobby>> {
+        match self.lobbies.entry(lobby_id) {
+            Entry::Occupied(_) => None,
+            Entry::Vacant(entry) => Some(Arc::clone(&entry.insert(SharedLobby::new(lobby())))),
+        }
+    }
+
+    /// opens a lobby under the given id, replacing any lobby that already has it.
+    pub fn insert(&self, lobby_id: LobbyId, lobby: Lobby) -> Arc<SharedLobby> {
+        let shared = SharedLobby::new(lobby);
+        if let Some(replaced) = self.lobbies.insert(lobby_id, Arc::clone(&shared)) {
+            replaced.lock().take();
+        }
+        shared
+    }
+
+    /// closes a lobby, given its lock, returning what was in it. the lobby is only removed from
+    /// the map if it's still the one under its id.
+    pub fn close(
+        &self,
+        lobby_id: &LobbyId,
+        shared: &Arc<SharedLobby>,
+        lobby: &mut Option<Lobby>,
+    ) -> Option<Lobby> {
+        self.lobbies
+            .remove_if(lobby_id, |_, listed| Arc::ptr_eq(listed, shared));
+        lobby.take()
+    }
+
+    /// closes a lobby if what's in it passes the check, returning what was.
+    pub fn close_if(
+        &self,
+        lobby_id: &LobbyId,
+        shared: &Arc<SharedLobby>,
+        f: impl FnOnce(&Lobby) -> bool,
+    ) -> Option<Lobby> {
+        let mut lobby = shared.lock();
+        if !lobby.as_ref().is_some_and(f) {
+            return None;
+        }
+        self.close(lobby_id, shared, &mut lobby)
+    }
+
+    /// every lobby open at the moment, for going through them one at a time without holding the
+    /// map.
+    pub fn all(&self) -> Vec<(LobbyId, Arc<SharedLobby>)> {
+        self.lobbies
+            .iter()
+            .map(|entry| (*entry.key(), Arc::clone(entry.value())))
+            .collect()
+    }
+}
+
+/// the games each player has won in a lobby's current match.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct MatchScore {
+    pub left: u16,
+    pub right: u16,
+}
+
+impl MatchScore {
+    /// counts a game won by `winner`, starting a new match if it won this one, in which case the
+    /// final score of the match is returned.
+    pub fn record_win(
+        &mut self,
+        winner: Side,
+        match_length: u16,
+        tiebreak: Tiebreak,
+    ) -> Option<MatchScore> {
+        let (wins, other_wins) = match winner {
+            Side::Left => (&mut self.left, self.right),
+            Side::Right => (&mut self.right, self.left),
+        };
+        *wins += 1;
+        let lead_needed = match tiebreak {
+            Tiebreak::WinByTwo => 2,
+            Tiebreak::SuddenDeath => 1,
+        };
+        if *wins >= match_length && *wins >= other_wins + lead_needed {
+            return Some(mem::take(self));
+        }
+        None
+    }
+
+    /// the score of a match of a single game, as every game is in a lobby that doesn't keep score.
+    pub fn single_game(winner: Side) -> Self {
+        match winner {
+            Side::Left => MatchScore { left: 1, right: 0 },
+            Side::Right => MatchScore { left: 0, right: 1 },
+        }
+    }
+
+    /// the tiebreak the next game is played under, if both players are a game away from winning
+    /// the match.
+    pub fn tiebreak(self, match_length: u16, tiebreak: Tiebreak) -> Option<Tiebreak> {
+        (self.left == self.right && self.left + 1 >= match_length).then_some(tiebreak)
+    }
+
+    /// the side that would win the match by winning the next game, unless the match is tied, in
+    /// which case [`MatchScore::tiebreak`] describes the game instead.
+    pub fn match_point(self, match_length: u16) -> Option<Side> {
+        let (leader, leader_wins) = match self.left.cmp(&self.right) {
+            Ordering::Greater => (Side::Left, self.left),
+            Ordering::Less => (Side::Right, self.right),
+            Ordering::Equal => return None,
+        };
+        // whoever is behind is at least two games short of a win-by-two tiebreak's lead.
+        (leader_wins + 1 >= match_length).then_some(leader)
+    }
+}
+
 pub enum LobbyState {
     AwaitingReadies {
         left_player_ready: bool,
         right_player_ready: bool,
+        /// when the lobby will be closed if the game still hasn't started.
+        ready_deadline: Instant,
     },
     Playing {
         game_state: GameState,
+        /// the number of times the game has ticked so far.
+        ticks: u32,
+        /// the number of times a paddle has hit the ball this game, which speeds the ball up.
+        hits: u16,
+        /// the side the ball was served from, and whether they've served it yet. the game doesn't
+        /// tick until they have, or the serve delay has passed.
+        serving: Side,
+        served: bool,
+        /// the moves made so far, if finished games are being saved.
+        replay: Option<ReplayRecorder>,
+        /// the direction each player currently intends to move their paddle in. paddles are moved
+        /// by the game loop on each tick.
+        left_paddle_direction: MoveDirection,
+        right_paddle_direction: MoveDirection,
+        /// whether each player has sent an input since the last tick. in lockstep mode, the game
+        /// only ticks once both have.
+        left_input_received: bool,
+        right_input_received: bool,
+        /// the task ticking the game, once it's been spawned, for waking it as soon as both
+        /// players' inputs are in.
+        game_loop: Option<TaskId>,
+        /// the state only a co-op survival game has, in which the right player's paddle is the
+        /// partner's, and the right paddle of the game state is the server's.
+        coop: Option<CoopGame>,
     },
 }
+
+/// the state of a co-op survival game beyond that of a regular game.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct CoopGame {
+    /// the position of the partner's paddle, which defends the left side alongside the host's.
+    pub partner_paddle: u16,
+    /// the number of times the players have returned the ball between them.
+    pub score: u16,
+}
+
+impl LobbyState {
+    /// the state of a lobby waiting for both players to ready up for the next game.
+    pub fn awaiting_readies(ready_deadline: Instant) -> Self {
+        Self::AwaitingReadies {
+            left_player_ready: false,
+            right_player_ready: false,
+            ready_deadline,
+        }
+    }
+}
+
+/// whether a client with the given capabilities can play on the given field, between players
+/// with the given handicaps, and in a co-op survival game if the lobby is for one.
+pub fn can_play(
+    capabilities: Capabilities,
+    dimensions: GameDimensions,
+    handicaps: Handicaps,
+    coop: bool,
+) -> bool {
+    (!dimensions.requires_wide_encoding_with(handicaps)
+        || capabilities.contains(Capabilities::WIDE_GAME_STATE))
+        && (handicaps.is_none() || capabilities.contains(Capabilities::HANDICAPS))
+        && (!coop || capabilities.contains(Capabilities::COOP))
+}
+
+/// the ruleset a lobby on the given field is played under, given the one its host asked for.
+/// handicaps are fitted to the field, and dropped if the host couldn't be sent the field's game
+/// states with them, and matches are as long as the server's unless the host chose a length.
+pub fn settle_ruleset(
+    requested: Ruleset,
+    dimensions: GameDimensions,
+    host_capabilities: Capabilities,
+    match_length: Option<u16>,
+) -> Ruleset {
+    let mut handicaps = requested.handicaps.fit(dimensions);
+    if dimensions.requires_wide_encoding_with(handicaps)
+        && !host_capabilities.contains(Capabilities::WIDE_GAME_STATE)
+    {
+        handicaps = Handicaps::NONE;
+    }
+    Ruleset {
+        match_length: requested.match_length.or(match_length),
+        handicaps,
+        ..requested
+    }
+}
+
+/// the ruleset of a practice lobby on the given field, whose wall is a right paddle as tall as the
+/// field, so that it returns every ball.
+pub fn practice_ruleset(tick_interval_ms: u16, dimensions: GameDimensions) -> Ruleset {
+    Ruleset {
+        tick_interval_ms,
+        match_length: None,
+        handicaps: Handicaps {
+            left: Handicap::default(),
+            right: Handicap {
+                paddle_height: Some(dimensions.height()),
+                paddle_speed: None,
+            },
+        },
+        modifier: Modifier::Classic,
+    }
+}
+
+/// lists a page of the lobbies waiting for an opponent that a client with the given capabilities
+/// could join, oldest first, along with how many pages there are.
+pub fn open_lobby_page(
+    lobbies: &Lobbies,
+    page: u16,
+    capabilities: Capabilities,
+) -> (Vec<ListedLobby>, u16) {
+    let mut open: Vec<_> = lobbies
+        .all()
+        .into_iter()
+        .filter_map(|(lobby_id, lobby)| match lobby.lock().as_ref()? {
+            Lobby::AwaitingJoin {
+                dimensions,
+                ruleset,
+                coop,
+                created_at,
+                ..
+            } if can_play(capabilities, *dimensions, ruleset.handicaps, *coop) => {
+                Some((*created_at, lobby_id, ruleset.tick_interval_ms))
+            }
+            Lobby::AwaitingJoin { .. } | Lobby::Joined { .. } => None,
+        })
+        .collect();
+    // lobbies created in the same instant are ordered by id, so that pages don't overlap.
+    open.sort_by(|a, b| (a.0, a.1.as_str()).cmp(&(b.0, b.1.as_str())));
+    let pages = open.len().div_ceil(LOBBY_PAGE_SIZE).max(1);
+    let listed = open
+        .into_iter()
+        .skip(page as usize * LOBBY_PAGE_SIZE)
+        .take(LOBBY_PAGE_SIZE)
+        .map(|(created_at, lobby_id, tick_interval_ms)| ListedLobby {
+            lobby_id,
+            tick_interval_ms,
+            age_secs: u32::try_from(created_at.elapsed().as_secs()).unwrap_or(u32::MAX),
+        })
+        .collect();
+    (listed, u16::try_from(pages).unwrap_or(u16::MAX))
+}
+
+/// generates ids until one isn't already taken by a lobby, which it could be if a host chose it.
+pub fn unused_lobby_id(lobbies: &Lobbies, lobby_id_generator: &LobbyIdGenerator) -> LobbyId {
+    loop {
+        let lobby_id = lobby_id_generator.next_id();
+        if !lobbies.contains(&lobby_id) {
+            return lobby_id;
+        }
+    }
+}
+
+/// creates a ball in the centre column of the game, in a random row and moving in a random
+/// direction, so that neither player is given a predictable first return.
+pub fn serve_ball<R: Rng>(rng: &mut R, dimensions: GameDimensions) -> Ball {
+    Ball {
+        x: dimensions.width() / 2,
+        y: rng.gen_range(0..dimensions.height()),
+        moving_right: rng.gen(),
+        moving_down: rng.gen(),
+    }
+}
+
+/// picks the side to serve the next game, at random if no game has been won yet.
+pub fn serving_side<R: Rng>(rng: &mut R, last_winner: Option<Side>, serve_rule: ServeRule) -> Side {
+    match (last_winner, serve_rule) {
+        (Some(winner), ServeRule::Winner) => winner,
+        (Some(Side::Left), ServeRule::Loser) => Side::Right,
+        (Some(Side::Right), ServeRule::Loser) => Side::Left,
+        (None, _) if rng.gen() => Side::Left,
+        (None, _) => Side::Right,
+    }
+}
+
+/// creates a ball on the serving side of the game, in a random row and moving towards the other
+/// side. it's kept clear of the column in front of the paddle, which would turn it straight back.
+pub fn serve_ball_from<R: Rng>(rng: &mut R, dimensions: GameDimensions, side: Side) -> Ball {
+    let offset = (dimensions.width() / 4).max(2);
+    Ball {
+        x: match side {
+            Side::Left => offset,
+            Side::Right => dimensions.width() - 1 - offset,
+        },
+        y: rng.gen_range(0..dimensions.height()),
+        moving_right: side == Side::Left,
+        moving_down: rng.gen(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use rand::{rngs::StdRng, SeedableRng};
+    use shared::{
+        client_msg::Capabilities,
+        game_state::{
+            GameDimensions, GameState, Handicap, Handicaps, Modifier, MoveDirection, Ruleset, Side,
+        },
+        server_msg::LOBBY_PAGE_SIZE,
+        LobbyId,
+    };
+
+    use crate::{
+        client_conn::ClientConn,
+        config::{ServeRule, Tiebreak},
+        event_log::EventLog,
+        lobby::{
+            open_lobby_page, practice_ruleset, serve_ball, serve_ball_from, serving_side,
+            settle_ruleset, Lobbies, Lobby, MatchScore, PlayerId,
+        },
+    };
+
+    #[test]
+    fn serve_is_reproducible_from_seed() {
+        let serves = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..16)
+                .map(|_| serve_ball(&mut rng, GameDimensions::DEFAULT))
+                .map(|ball| (ball.x, ball.y, ball.moving_right, ball.moving_down))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(serves(42), serves(42));
+        assert_ne!(serves(42), serves(43));
+    }
+
+    #[test]
+    fn serve_within_bounds() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for dimensions in [
+            GameDimensions::DEFAULT,
+            GameDimensions::new(20, 40, 30).unwrap(),
+        ] {
+            for _ in 0..1000 {
+                let ball = serve_ball(&mut rng, dimensions);
+                assert_eq!(ball.x, dimensions.width() / 2);
+                assert!(ball.y < dimensions.height());
+            }
+        }
+    }
+
+    #[test]
+    fn serve_from_either_side() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for dimensions in [
+            GameDimensions::DEFAULT,
+            GameDimensions::new(5, 2, 1).unwrap(),
+        ] {
+            for _ in 0..100 {
+                let ball = serve_ball_from(&mut rng, dimensions, Side::Left);
+                assert!(ball.x > 1 && ball.x <= dimensions.width() / 2);
+                assert!(ball.moving_right);
+                let ball = serve_ball_from(&mut rng, dimensions, Side::Right);
+                assert!(ball.x >= dimensions.width() / 2 && ball.x < dimensions.width() - 2);
+                assert!(!ball.moving_right);
+                assert!(ball.y < dimensions.height());
+            }
+        }
+    }
+
+    #[test]
+    fn serving_side_follows_rule() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for side in [Side::Left, Side::Right] {
+            assert!(serving_side(&mut rng, Some(side), ServeRule::Winner) == side);
+        }
+        assert!(serving_side(&mut rng, Some(Side::Left), ServeRule::Loser) == Side::Right);
+        assert!(serving_side(&mut rng, Some(Side::Right), ServeRule::Loser) == Side::Left);
+    }
+
+    #[test]
+    fn match_won_by_two() {
+        let mut score = MatchScore::default();
+        for _ in 0..2 {
+            score.record_win(Side::Left, 3, Tiebreak::WinByTwo);
+            score.record_win(Side::Right, 3, Tiebreak::WinByTwo);
+        }
+        assert_eq!(score, MatchScore { left: 2, right: 2 });
+        assert_eq!(
+            score.tiebreak(3, Tiebreak::WinByTwo),
+            Some(Tiebreak::WinByTwo)
+        );
+        score.record_win(Side::Right, 3, Tiebreak::WinByTwo);
+        assert_eq!(score, MatchScore { left: 2, right: 3 });
+        assert_eq!(score.tiebreak(3, Tiebreak::WinByTwo), None);
+        score.record_win(Side::Left, 3, Tiebreak::WinByTwo);
+        assert_eq!(
+            score.tiebreak(3, Tiebreak::WinByTwo),
+            Some(Tiebreak::WinByTwo)
+        );
+        assert!(score.match_point(3).is_none());
+        score.record_win(Side::Left, 3, Tiebreak::WinByTwo);
+        assert!(score.match_point(3) == Some(Side::Left));
+        assert_eq!(
+            score.record_win(Side::Left, 3, Tiebreak::WinByTwo),
+            Some(MatchScore { left: 5, right: 3 })
+        );
+        // the match is over, so a new one starts.
+        assert_eq!(score, MatchScore::default());
+    }
+
+    #[test]
+    fn match_points() {
+        let mut score = MatchScore::default();
+        assert!(score.match_point(3).is_none());
+        score.record_win(Side::Right, 3, Tiebreak::SuddenDeath);
+        assert!(score.match_point(3).is_none());
+        score.record_win(Side::Right, 3, Tiebreak::SuddenDeath);
+        assert!(score.match_point(3) == Some(Side::Right));
+        score.record_win(Side::Left, 3, Tiebreak::SuddenDeath);
+        assert!(score.match_point(3) == Some(Side::Right));
+        score.record_win(Side::Left, 3, Tiebreak::SuddenDeath);
+        // a tied match is a tiebreak instead.
+        assert!(score.match_point(3).is_none());
+    }
+
+    #[test]
+    fn match_decided_by_sudden_death() {
+        let mut score = MatchScore::default();
+        score.record_win(Side::Left, 2, Tiebreak::SuddenDeath);
+        assert_eq!(score.tiebreak(2, Tiebreak::SuddenDeath), None);
+        score.record_win(Side::Right, 2, Tiebreak::SuddenDeath);
+        assert_eq!(
+            score.tiebreak(2, Tiebreak::SuddenDeath),
+            Some(Tiebreak::SuddenDeath)
+        );
+        score.record_win(Side::Right, 2, Tiebreak::SuddenDeath);
+        assert_eq!(score, MatchScore::default());
+        // a match of one game is always decided by the next one.
+        assert_eq!(
+            score.tiebreak(1, Tiebreak::SuddenDeath),
+            Some(Tiebreak::SuddenDeath)
+        );
+    }
+
+    #[test]
+    fn rulesets_are_settled() {
+        let dimensions = GameDimensions::new(51, 20, 6).unwrap();
+        let requested = Ruleset {
+            tick_interval_ms: 80,
+            match_length: None,
+            handicaps: Handicaps {
+                // a paddle this short can move further down than the narrow encoding allows.
+                left: Handicap {
+                    paddle_height: Some(1),
+                    paddle_speed: Some(2),
+                },
+                right: Handicap {
+                    paddle_height: Some(50),
+                    paddle_speed: None,
+                },
+            },
+            modifier: Modifier::Gravity,
+        };
+        let settled = settle_ruleset(requested, dimensions, Capabilities::ALL, Some(3));
+        assert_eq!(
+            settled,
+            Ruleset {
+                tick_interval_ms: 80,
+                match_length: Some(3),
+                handicaps: Handicaps {
+                    left: requested.handicaps.left,
+                    right: Handicap {
+                        paddle_height: Some(20),
+                        paddle_speed: None,
+                    },
+                },
+                modifier: Modifier::Gravity,
+            }
+        );
+        let settled = settle_ruleset(
+            Ruleset {
+                match_length: Some(5),
+                ..requested
+            },
+            dimensions,
+            Capabilities::HANDICAPS,
+            Some(3),
+        );
+        assert_eq!(settled.match_length, Some(5));
+        assert_eq!(settled.handicaps, Handicaps::NONE);
+    }
+
+    #[test]
+    fn practice_wall_returns_every_ball() {
+        let dimensions = GameDimensions::new(20, 11, 3).unwrap();
+        let ruleset = practice_ruleset(60, dimensions);
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut game_state = GameState {
+            left_paddle: 0,
+            right_paddle: 0,
+            ball: serve_ball_from(&mut rng, dimensions, Side::Left),
+        };
+        let mut returns = 0;
+        let winner = loop {
+            let was_moving_right = game_state.ball.moving_right;
+            let winner = game_state.tick(
+                dimensions,
+                ruleset.handicaps,
+                ruleset.modifier,
+                MoveDirection::Stop,
+                MoveDirection::Stop,
+            );
+            if let Some(winner) = winner {
+                break winner;
+            }
+            if was_moving_right && !game_state.ball.moving_right {
+                returns += 1;
+            }
+        };
+        // the player's paddle never moved, so they missed eventually. the wall never does.
+        assert!(winner == Side::Right);
+        assert!(returns > 0);
+    }
+
+    #[test]
+    fn open_lobby_pages() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let conn = || {
+            Arc::new(ClientConn::new(
+                TcpStream::connect(listener.local_addr().unwrap()).unwrap(),
+            ))
+        };
+        let lobbies = Lobbies::default();
+        let created_at = Instant::now();
+        let wide = GameDimensions::new(200, 11, 5).unwrap();
+        for n in 0..LOBBY_PAGE_SIZE as u64 + 3 {
+            let lobby_id: LobbyId = format!("AA{:02}", n).parse().unwrap();
+            let lobby = Lobby::AwaitingJoin {
+                host_player_id: PlayerId(n),
+                host_player_conn: conn(),
+                host_capabilities: Capabilities::ALL,
+                // the last lobby is too wide for older clients.
+                dimensions: if n == LOBBY_PAGE_SIZE as u64 + 2 {
+                    wide
+                } else {
+                    GameDimensions::DEFAULT
+                },
+                ruleset: Ruleset {
+                    tick_interval_ms: 100,
+                    match_length: None,
+                    // and the one before it needs clients that support handicaps.
+                    handicaps: if n == LOBBY_PAGE_SIZE as u64 + 1 {
+                        Handicaps {
+                            left: Handicap {
+                                paddle_height: Some(2),
+                                paddle_speed: None,
+                            },
+                            right: Handicap::NONE,
+                        }
+                    } else {
+                        Handicaps::NONE
+                    },
+                    modifier: Modifier::Classic,
+                },
+                // as is the one before that, which is for a co-op game.
+                coop: n == LOBBY_PAGE_SIZE as u64,
+                rng: StdRng::seed_from_u64(n),
+                event_log: EventLog::DISABLED,
+                created_at: created_at - Duration::from_secs(n),
+                expires_at: created_at,
+            };
+            lobbies.insert(lobby_id, lobby);
+        }
+        let (page, pages) = open_lobby_page(&lobbies, 0, Capabilities::ALL);
+        assert_eq!((page.len(), pages), (LOBBY_PAGE_SIZE, 2));
+        // oldest first.
+        assert_eq!(page[0].lobby_id, "AA12".parse().unwrap());
+        assert!(page[0].age_secs >= 12);
+        let (page, _) = open_lobby_page(&lobbies, 1, Capabilities::ALL);
+        assert_eq!(page.len(), 3);
+        let (page, _) = open_lobby_page(&lobbies, 1, Capabilities::WIDE_GAME_STATE);
+        assert_eq!(page.len(), 1);
+        let (page, pages) = open_lobby_page(&lobbies, 0, Capabilities::NONE);
+        assert_eq!((page.len(), pages), (LOBBY_PAGE_SIZE, 1));
+        assert_eq!(page[0].lobby_id, "AA09".parse().unwrap());
+        let (page, _) = open_lobby_page(&lobbies, 0, Capabilities::COOP);
+        assert_eq!(page[0].lobby_id, "AA10".parse().unwrap());
+        assert_eq!(
+            open_lobby_page(&Lobbies::default(), 3, Capabilities::ALL),
+            (Vec::new(), 1)
+        );
+    }
+    #[test]
+    fn closing_stale_lobbies() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let lobby = |host| Lobby::AwaitingJoin {
+            host_player_id: PlayerId(host),
+            host_player_conn: Arc::new(ClientConn::new(
+                TcpStream::connect(listener.local_addr().unwrap()).unwrap(),
+            )),
+            host_capabilities: Capabilities::ALL,
+            dimensions: GameDimensions::DEFAULT,
+            ruleset: practice_ruleset(100, GameDimensions::DEFAULT),
+            coop: false,
+            rng: StdRng::seed_from_u64(host),
+            event_log: EventLog::DISABLED,
+            created_at: Instant::now(),
+            expires_at: Instant::now(),
+        };
+        let lobbies = Lobbies::default();
+        let lobby_id: LobbyId = "AAAA".parse().unwrap();
+        let first = lobbies.open(lobby_id, || lobby(0)).unwrap();
+        // the id is taken, so the lobby isn't made.
+        assert!(lobbies.open(lobby_id, || unreachable!()).is_none());
+        let second = lobbies.insert(lobby_id, lobby(1));
+        // a lobby replaced under its id is closed for anything still holding on to it.
+        assert!(first.lock().is_none());
+        assert!(lobbies.close_if(&lobby_id, &first, |_| true).is_none());
+        assert!(lobbies.contains(&lobby_id));
+        let closed = lobbies.close_if(&lobby_id, &second, |lobby| lobby.has_player(PlayerId(1)));
+        assert!(closed.is_some_and(|lobby| lobby.has_player(PlayerId(1))));
+        assert!(second.lock().is_none());
+        assert!(lobbies.is_empty());
+    }
+}
@@ -1,61 +1,198 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
 use aes::Aes256;
 use fpe::ff1::{FlexibleNumeralString, FF1};
-use shared::LOBBY_ID_LEN;
+use shared::{LobbyId, MAX_LOBBY_ID_LEN};
 
 const LOBBY_ID_RADIX: u32 = 32;
+/// how many ids are reserved in the counter file at a time. a restart skips whatever's left of the
+/// reservation, rather than the file being written for every id.
+const RESERVED_IDS: u32 = 64;
 
+/// generates lobby ids, which can be done by several threads at once: only counting off the next
+/// id takes a lock, and not encrypting it.
 pub struct LobbyIdGenerator {
-    id_count: u32,
+    count: Mutex<IdCount>,
+    /// how many characters each id has.
+    id_len: usize,
     ff1: FF1<Aes256>,
 }
 
+struct IdCount {
+    /// how many ids have been generated.
+    generated: u32,
+    counter_file: Option<CounterFile>,
+}
+
+/// a file the id count is persisted to, so that a server restarted with the same key carries on
+/// where it left off instead of handing out ids again.
+struct CounterFile {
+    path: PathBuf,
+    /// ids below this count have been reserved in the file.
+    reserved: u32,
+}
+
 impl LobbyIdGenerator {
-    pub fn new(key: &[u8; 32]) -> Self {
+    pub fn new(key: &[u8; 32], id_len: usize) -> Self {
         Self {
-            id_count: 0,
-            ff1: FF1::<Aes256>::new(key, LOBBY_ID_RADIX as u32).unwrap(),
+            count: Mutex::new(IdCount {
+                generated: 0,
+                counter_file: None,
+            }),
+            id_len,
+            ff1: FF1::<Aes256>::new(key, LOBBY_ID_RADIX).unwrap(),
         }
     }
 
-    pub fn next_id(&mut self) -> String {
+    /// a generator that starts from the count persisted in the given file, if it exists yet, and
+    /// keeps it up to date.
+    pub fn with_counter_file(key: &[u8; 32], id_len: usize, path: &Path) -> io::Result<Self> {
+        let generated = match fs::read_to_string(path) {
+            Ok(count) => count
+                .trim()
+                .parse()
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+            Err(err) => return Err(err),
+        };
+        Ok(Self {
+            count: Mutex::new(IdCount {
+                generated,
+                counter_file: Some(CounterFile {
+                    path: path.to_owned(),
+                    reserved: generated,
+                }),
+            }),
+            ..Self::new(key, id_len)
+        })
+    }
+
+    pub fn next_id(&self) -> LobbyId {
+        let id_count = {
+            let mut count = self.count.lock().unwrap();
+            let id_count = count.generated;
+            if let Some(counter_file) = &mut count.counter_file {
+                if id_count == counter_file.reserved {
+                    counter_file.reserved = id_count.wrapping_add(RESERVED_IDS);
+                    if let Err(err) = counter_file.save() {
+                        eprintln!("failed to save lobby id count: {err}");
+                    }
+                }
+            }
+            count.generated = id_count.wrapping_add(1);
+            id_count
+        };
         let num_str = FlexibleNumeralString::from(
-            (0..LOBBY_ID_LEN)
-                .map(|idx| ((self.id_count >> 5 * idx) as u16) & 0b11111)
+            (0..self.id_len)
+                .map(|idx| ((id_count.checked_shr(5 * idx as u32).unwrap_or(0)) as u16) & 0b11111)
                 .collect::<Vec<_>>(),
         );
-        self.id_count = self.id_count.wrapping_add(1);
         let lobby_id = self.ff1.encrypt(&[], &num_str).unwrap();
-        String::from_utf8(
-            Vec::<u16>::from(lobby_id)
-                .into_iter()
-                .map(|n| match n as u8 {
-                    n if n < 8 => n + b'2', // don't use 0 and 1 in IDs because they could be mixed up with O and I, plus it allows the use of the entire alphabet (A..Z).
-                    n => n + b'A' - 8,
-                })
-                .collect(),
-        )
-        .unwrap()
+        let mut digits = [0; MAX_LOBBY_ID_LEN];
+        for (digit, n) in digits.iter_mut().zip(Vec::<u16>::from(lobby_id)) {
+            *digit = n as u8;
+        }
+        LobbyId::from_digits(&digits[..self.id_len])
     }
 }
 
+impl CounterFile {
+    fn save(&self) -> io::Result<()> {
+        // written to a temporary file first, so that a crash mid-write can't lose the count.
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, self.reserved.to_string())?;
+        fs::rename(tmp_path, &self.path)
+    }
+}
+
+/// parses a key written as 64 hexadecimal digits.
+pub fn parse_key(hex: &str) -> Option<[u8; 32]> {
+    // checked up front, since parsing each byte would also accept a sign.
+    if hex.len() != 64 || !hex.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut key = [0; 32];
+    for (byte, digits) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()?;
+    }
+    Some(key)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::lobby_id_generator::LobbyIdGenerator;
+    use std::{collections::HashSet, fs};
+
+    use shared::{LOBBY_ID_LEN, MAX_LOBBY_ID_LEN};
+
+    use crate::lobby_id_generator::{parse_key, LobbyIdGenerator, RESERVED_IDS};
 
     #[test]
     fn distinct_by_key() {
         let key = [0; 32];
-        let mut lobby_id_generator = LobbyIdGenerator::new(&key);
-        assert_eq!(lobby_id_generator.next_id(), "H5MS");
-        assert_eq!(lobby_id_generator.next_id(), "EK9F");
-        assert_eq!(lobby_id_generator.next_id(), "FWSI");
-        assert_eq!(lobby_id_generator.next_id(), "5B4M");
+        let lobby_id_generator = LobbyIdGenerator::new(&key, LOBBY_ID_LEN);
+        assert_eq!(lobby_id_generator.next_id().as_str(), "H5MS");
+        assert_eq!(lobby_id_generator.next_id().as_str(), "EK9F");
+        assert_eq!(lobby_id_generator.next_id().as_str(), "FWSI");
+        assert_eq!(lobby_id_generator.next_id().as_str(), "5B4M");
         let key = [1; 32];
-        let mut lobby_id_generator = LobbyIdGenerator::new(&key);
-        assert_eq!(lobby_id_generator.next_id(), "B4RL");
-        assert_eq!(lobby_id_generator.next_id(), "X9UE");
-        assert_eq!(lobby_id_generator.next_id(), "2E9J");
-        assert_eq!(lobby_id_generator.next_id(), "ELIX");
+        let lobby_id_generator = LobbyIdGenerator::new(&key, LOBBY_ID_LEN);
+        assert_eq!(lobby_id_generator.next_id().as_str(), "B4RL");
+        assert_eq!(lobby_id_generator.next_id().as_str(), "X9UE");
+        assert_eq!(lobby_id_generator.next_id().as_str(), "2E9J");
+        assert_eq!(lobby_id_generator.next_id().as_str(), "ELIX");
+    }
+
+    #[test]
+    fn longer_ids() {
+        let key = [0; 32];
+        let lobby_id_generator = LobbyIdGenerator::new(&key, MAX_LOBBY_ID_LEN);
+        let ids: HashSet<_> = (0..100).map(|_| lobby_id_generator.next_id()).collect();
+        assert_eq!(ids.len(), 100);
+        assert!(ids.iter().all(|id| id.as_str().len() == MAX_LOBBY_ID_LEN));
+    }
+
+    #[test]
+    fn keys() {
+        let key = parse_key(&"0f".repeat(32)).unwrap();
+        assert_eq!(key, [0x0f; 32]);
+        assert_eq!(parse_key(&"0F".repeat(32)), Some(key));
+        assert_eq!(parse_key(&"0f".repeat(31)), None);
+        assert_eq!(parse_key(&"0g".repeat(32)), None);
+        assert_eq!(parse_key(&"+f".repeat(32)), None);
+        assert_eq!(parse_key(&"é".repeat(32)), None);
+    }
+
+    #[test]
+    fn count_survives_restarts() {
+        let dir = std::env::temp_dir().join(format!("pong-ids-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("lobby_id_count");
+        let _ = fs::remove_file(&path);
+        let key = [0; 32];
+        let lobby_id_generator =
+            LobbyIdGenerator::with_counter_file(&key, LOBBY_ID_LEN, &path).unwrap();
+        assert_eq!(lobby_id_generator.next_id().as_str(), "H5MS");
+        assert_eq!(lobby_id_generator.next_id().as_str(), "EK9F");
+        assert_eq!(fs::read_to_string(&path).unwrap(), RESERVED_IDS.to_string());
+        // a restart carries on from the end of the reservation, so never repeats an id.
+        let lobby_id_generator =
+            LobbyIdGenerator::with_counter_file(&key, LOBBY_ID_LEN, &path).unwrap();
+        let from_start = LobbyIdGenerator::new(&key, LOBBY_ID_LEN);
+        for _ in 0..RESERVED_IDS {
+            from_start.next_id();
+        }
+        assert_eq!(lobby_id_generator.next_id(), from_start.next_id());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            (2 * RESERVED_IDS).to_string()
+        );
+        fs::write(&path, "not a count").unwrap();
+        assert!(LobbyIdGenerator::with_counter_file(&key, LOBBY_ID_LEN, &path).is_err());
+        fs::remove_dir_all(dir).unwrap();
     }
 }
 
@@ -65,12 +202,14 @@ mod tests {
 mod release_tests {
     use std::collections::HashSet;
 
-    use crate::lobby_id_generator::{LobbyIdGenerator, LOBBY_ID_LEN, LOBBY_ID_RADIX};
+    use shared::LOBBY_ID_LEN;
+
+    use crate::lobby_id_generator::{LobbyIdGenerator, LOBBY_ID_RADIX};
 
     #[test]
     fn uniqueness() {
         let key = [0; 32];
-        let mut lobby_id_generator = LobbyIdGenerator::new(&key);
+        let lobby_id_generator = LobbyIdGenerator::new(&key, LOBBY_ID_LEN);
         let count = LOBBY_ID_RADIX.pow(LOBBY_ID_LEN as u32);
         let set = (0..count)
             .map(|_| lobby_id_generator.next_id())
@@ -81,7 +220,7 @@ mod release_tests {
     #[test]
     fn wrap_around() {
         let key = [0; 32];
-        let mut lobby_id_generator = LobbyIdGenerator::new(&key);
+        let lobby_id_generator = LobbyIdGenerator::new(&key, LOBBY_ID_LEN);
         let first_id = lobby_id_generator.next_id();
         for _ in 0..LOBBY_ID_RADIX.pow(LOBBY_ID_LEN as u32) - 1 {
             lobby_id_generator.next_id();
@@ -1,5 +1,169 @@
-use server::tcp_server::start;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process,
+    sync::Arc,
+    thread::Builder,
+    time::{Instant, SystemTime},
+};
+
+use clap::{Parser, Subcommand};
+use server::{
+    config::{ServerConfig, DEFAULT_CONFIG},
+    daemon, json,
+    simulation::simulate,
+    stats::{self, parse_date, ExportFormat},
+    summary::DailySummary,
+    tcp_server::start,
+};
+
+/// the config file read when no other is given, if it exists.
+const DEFAULT_CONFIG_FILE: &str = "pong-server.toml";
+
+#[derive(Parser)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Run in the background, detached from the terminal
+    #[arg(long)]
+    daemon: bool,
+    /// The file to write the server's process id to
+    #[arg(long)]
+    pidfile: Option<PathBuf>,
+    /// The file to log to instead of the terminal, rotated once it reaches 10MiB
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+    /// The config file to read settings from, instead of pong-server.toml if it exists. Settings
+    /// set by environment variables take the place of the file's
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Print a config file with every setting and its default, then exit
+    #[arg(long)]
+    print_default_config: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Play games between two bots without any networking, using the configured game dimensions
+    Simulate {
+        /// How many ticks to simulate
+        #[arg(long)]
+        ticks: u32,
+        /// The seed to serve the ball and move the bots with
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Print the state of the game after every tick
+        #[arg(long)]
+        trace: bool,
+    },
+    /// Work with the results of finished matches kept in the STATS_DB_FILE database
+    #[command(subcommand)]
+    Stats(StatsCommand),
+}
+
+#[derive(Subcommand)]
+enum StatsCommand {
+    /// Print every match recorded, for analysis elsewhere
+    Export {
+        /// How to print the matches: csv, or json with an object per line
+        #[arg(long, default_value = "csv")]
+        format: ExportFormat,
+        /// Only print matches finished on or after this date, in UTC, e.g. 2024-06-01
+        #[arg(long, value_parser = parse_date)]
+        since: Option<u64>,
+    },
+}
 
 fn main() {
-    start();
+    let cli = Cli::parse();
+    if cli.print_default_config {
+        print!("{DEFAULT_CONFIG}");
+        return;
+    }
+    let config_file = cli.config.as_deref().or_else(|| {
+        let path = Path::new(DEFAULT_CONFIG_FILE);
+        path.exists().then_some(path)
+    });
+    // read before daemonizing, so that a bad setting is reported to the terminal.
+    let config = ServerConfig::load(config_file);
+    // before anything else, as only the thread that daemonizes carries on.
+    if cli.daemon {
+        daemon::daemonize().expect("failed to daemonize");
+    }
+    let serving = cli.command.is_none();
+    // before any threads are started, as they'd be killed by the signals otherwise.
+    if serving {
+        daemon::block_shutdown_signals().expect("failed to block shutdown signals");
+    }
+    if let Some(path) = &cli.pidfile {
+        daemon::write_pidfile(path)
+            .unwrap_or_else(|err| panic!("failed to write pidfile {path:?}: {err}"));
+    }
+    let log_file = cli.log_file.as_ref().map(|path| {
+        daemon::log_to_file(path)
+            .unwrap_or_else(|err| panic!("failed to open log file {path:?}: {err}"))
+    });
+    match cli.command {
+        None => {
+            let summary = Arc::new(DailySummary::default());
+            spawn_shutdown(Arc::clone(&summary), log_file);
+            start(config, summary)
+        }
+        Some(Command::Simulate { ticks, seed, trace }) => {
+            let dimensions = config.dimensions;
+            let started_at = Instant::now();
+            let result = simulate(dimensions, ticks, seed, |game, game_state| {
+                if trace {
+                    println!("game {game}: {}", json::game_state(game_state));
+                }
+            });
+            let elapsed = started_at.elapsed();
+            println!(
+                "{} games played in {ticks} ticks: left won {}, right won {}",
+                result.left_wins + result.right_wins,
+                result.left_wins,
+                result.right_wins,
+            );
+            println!(
+                "simulated in {elapsed:?} ({:.0} ticks per second)",
+                ticks as f64 / elapsed.as_secs_f64()
+            );
+        }
+        Some(Command::Stats(StatsCommand::Export { format, since })) => {
+            // stats kept in memory are gone with the server that kept them.
+            let path = config
+                .stats_db_file
+                .expect("STATS_DB_FILE must be set to export stats");
+            let store = stats::open(Some(&path))
+                .unwrap_or_else(|err| panic!("failed to open stats database {path:?}: {err}"));
+            stats::export(
+                &*store,
+                format,
+                since.unwrap_or(0),
+                &mut io::stdout().lock(),
+            )
+            .expect("failed to export stats");
+        }
+    }
+}
+
+/// logs the summary of the day so far once the server is told to shut down, then exits.
+fn spawn_shutdown(summary: Arc<DailySummary>, log_file: Option<daemon::LogFile>) {
+    Builder::new()
+        .name("shutdown".to_owned())
+        .spawn(move || {
+            // without the signals to wait for, they kill the server as they always have.
+            let Ok(signal) = daemon::await_shutdown_signal() else {
+                return;
+            };
+            println!(
+                "shutting down on signal {signal}, summary {}",
+                summary.take(SystemTime::now())
+            );
+            if let Some(log_file) = log_file {
+                log_file.close();
+            }
+            process::exit(0);
+        })
+        .unwrap();
 }
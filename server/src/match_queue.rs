@@ -0,0 +1,101 @@
+use std::sync::{mpsc::Sender, Arc, Mutex};
+
+use shared::{client_msg::Capabilities, LobbyId};
+
+use crate::{client_conn::ClientConn, lobby::PlayerId};
+
+/// a player looking for an opponent.
+pub struct QueuedPlayer {
+    pub player_id: PlayerId,
+    pub conn: Arc<ClientConn>,
+    pub capabilities: Capabilities,
+    /// sent the id of the lobby the player is seated in once an opponent is found, since it's the
+    /// opponent's connection handler that creates the lobby.
+    pub matched: Sender<LobbyId>,
+}
+
+/// players asking for a quick match. players are paired as soon as they're queued, so at most one
+/// is ever left waiting.
+#[derive(Default)]
+pub struct MatchQueue {
+    waiting: Mutex<Option<QueuedPlayer>>,
+}
+
+impl MatchQueue {
+    /// pairs the player with the one already waiting, returning the waiting player first, or
+    /// leaves the player waiting if nobody else is.
+    pub fn join(&self, player: QueuedPlayer) -> Option<(QueuedPlayer, QueuedPlayer)> {
+        let mut waiting = self.waiting.lock().unwrap();
+        match waiting.take() {
+            Some(opponent) => Some((opponent, player)),
+            None => {
+                *waiting = Some(player);
+                None
+            }
+        }
+    }
+
+    pub fn is_waiting(&self, player_id: PlayerId) -> bool {
+        let waiting = self.waiting.lock().unwrap();
+        waiting
+            .as_ref()
+            .is_some_and(|player| player.player_id == player_id)
+    }
+
+    /// takes the player out of the queue, returning whether they were still waiting. a player
+    /// that wasn't has already been paired.
+    pub fn leave(&self, player_id: PlayerId) -> bool {
+        let mut waiting = self.waiting.lock().unwrap();
+        let is_waiting = waiting
+            .as_ref()
+            .is_some_and(|player| player.player_id == player_id);
+        if is_waiting {
+            *waiting = None;
+        }
+        is_waiting
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        sync::{mpsc::channel, Arc},
+    };
+
+    use shared::client_msg::Capabilities;
+
+    use crate::{
+        client_conn::ClientConn,
+        lobby::PlayerId,
+        match_queue::{MatchQueue, QueuedPlayer},
+    };
+
+    #[test]
+    fn pairs_players_in_turn() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let player = |id| QueuedPlayer {
+            player_id: PlayerId(id),
+            conn: Arc::new(ClientConn::new(
+                TcpStream::connect(listener.local_addr().unwrap()).unwrap(),
+            )),
+            capabilities: Capabilities::ALL,
+            matched: channel().0,
+        };
+        let queue = MatchQueue::default();
+        assert!(queue.join(player(0)).is_none());
+        assert!(queue.is_waiting(PlayerId(0)));
+        let (first, second) = queue.join(player(1)).unwrap();
+        assert_eq!(
+            (first.player_id, second.player_id),
+            (PlayerId(0), PlayerId(1))
+        );
+        assert!(!queue.is_waiting(PlayerId(0)));
+        assert!(!queue.leave(PlayerId(0)));
+        assert!(queue.join(player(2)).is_none());
+        assert!(!queue.leave(PlayerId(3)));
+        assert!(queue.leave(PlayerId(2)));
+        assert!(queue.join(player(3)).is_none());
+        assert!(queue.is_waiting(PlayerId(3)));
+    }
+}
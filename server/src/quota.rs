@@ -0,0 +1,126 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// the period quotas are given over.
+const QUOTA_PERIOD: Duration = Duration::from_secs(60);
+
+/// how much of an address' quota is used up, as of when it last decayed.
+struct Used {
+    count: u32,
+    decayed_at: Instant,
+}
+
+impl Used {
+    /// forgets everything counted long enough ago.
+    fn decay(&mut self, decay: Duration, now: Instant) {
+        let steps = now.saturating_duration_since(self.decayed_at).as_nanos() / decay.as_nanos();
+        if steps >= self.count as u128 {
+            self.count = 0;
+            self.decayed_at = now;
+        } else {
+            self.count -= steps as u32;
+            self.decayed_at += decay * steps as u32;
+        }
+    }
+}
+
+struct Usage {
+    by_address: HashMap<IpAddr, Used>,
+    /// when addresses whose usage had decayed away were last forgotten.
+    pruned_at: Instant,
+}
+
+/// limits how often each ip address can do something. every time an address does it counts
+/// against its quota, and is forgotten again at the rate that lets it do so `limit` times a
+/// minute, so that an address can do it in bursts as long as it's not too often overall.
+pub struct Quota {
+    limit: u32,
+    /// how long it takes for one time to be forgotten.
+    decay: Duration,
+    usage: Mutex<Usage>,
+}
+
+impl Quota {
+    pub fn new(limit: u32) -> Self {
+        assert!(limit > 0, "a quota must allow something");
+        Self {
+            limit,
+            decay: QUOTA_PERIOD / limit,
+            usage: Mutex::new(Usage {
+                by_address: HashMap::new(),
+                pruned_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// counts one more time against the address' quota, returning false instead if the quota has
+    /// been used up.
+    pub fn try_use(&self, address: IpAddr, now: Instant) -> bool {
+        let mut usage = self.usage.lock().unwrap();
+        if now.saturating_duration_since(usage.pruned_at) >= QUOTA_PERIOD {
+            // an address that hasn't been seen for a whole period has nothing left to forget.
+            usage.by_address.retain(|_, used| {
+                used.decay(self.decay, now);
+                used.count > 0
+            });
+            usage.pruned_at = now;
+        }
+        let used = usage.by_address.entry(address).or_insert(Used {
+            count: 0,
+            decayed_at: now,
+        });
+        used.decay(self.decay, now);
+        if used.count >= self.limit {
+            return false;
+        }
+        used.count += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        time::{Duration, Instant},
+    };
+
+    use crate::quota::Quota;
+
+    const FIRST: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    const SECOND: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+    #[test]
+    fn limits_each_address() {
+        let quota = Quota::new(3);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert!(quota.try_use(FIRST, now));
+        }
+        assert!(!quota.try_use(FIRST, now));
+        assert!(quota.try_use(SECOND, now));
+    }
+
+    #[test]
+    fn decays_over_the_period() {
+        // one use is forgotten every 20 seconds.
+        let quota = Quota::new(3);
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(quota.try_use(FIRST, start));
+        }
+        assert!(!quota.try_use(FIRST, start + Duration::from_secs(19)));
+        assert!(quota.try_use(FIRST, start + Duration::from_secs(20)));
+        assert!(!quota.try_use(FIRST, start + Duration::from_secs(39)));
+        // a long enough wait forgets everything, and the quota can be used up again all at once.
+        let later = start + Duration::from_secs(600);
+        for _ in 0..3 {
+            assert!(quota.try_use(FIRST, later));
+        }
+        assert!(!quota.try_use(FIRST, later));
+    }
+}
@@ -0,0 +1,567 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use shared::{
+    game_state::{
+        Ball, GameDimensions, GameState, Handicap, Handicaps, Modifier, MoveDirection, Side,
+    },
+    LobbyId,
+};
+
+const MAGIC: &[u8; 4] = b"PONG";
+const FORMAT_VERSION: u8 = 3;
+/// replays saved before the host could choose a modifier, which are read as classic games.
+const CLASSIC_FORMAT_VERSION: u8 = 2;
+/// replays saved before games could be handicapped, which are read as having no handicaps.
+const UNHANDICAPPED_FORMAT_VERSION: u8 = 1;
+const EXTENSION: &str = "replay";
+
+/// a change in the direction a paddle moves in, after the given number of ticks of the game.
+#[derive(Clone, Copy)]
+pub struct ReplayMove {
+    pub tick: u32,
+    pub side: Side,
+    pub direction: MoveDirection,
+}
+
+/// how a game ended, and on which tick.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ReplayResult {
+    Won { tick: u32, side: Side },
+    Resigned { tick: u32, side: Side },
+}
+
+/// everything needed to play a finished game back: its settings, the state it started in, and
+/// every move either player made. the game is deterministic once the ball has been served, so
+/// ticking the initial state with the recorded moves reproduces it exactly.
+pub struct Replay {
+    pub dimensions: GameDimensions,
+    pub handicaps: Handicaps,
+    pub modifier: Modifier,
+    pub tick_interval_ms: u16,
+    pub initial_state: GameState,
+    pub moves: Vec<ReplayMove>,
+    pub result: ReplayResult,
+}
+
+/// records the moves of a game as it's played, until it finishes.
+pub struct ReplayRecorder {
+    dimensions: GameDimensions,
+    handicaps: Handicaps,
+    modifier: Modifier,
+    tick_interval_ms: u16,
+    initial_state: GameState,
+    moves: Vec<ReplayMove>,
+}
+
+impl ReplayRecorder {
+    pub fn new(
+        dimensions: GameDimensions,
+        handicaps: Handicaps,
+        modifier: Modifier,
+        tick_interval_ms: u16,
+        initial_state: GameState,
+    ) -> Self {
+        Self {
+            dimensions,
+            handicaps,
+            modifier,
+            tick_interval_ms,
+            initial_state,
+            moves: Vec::new(),
+        }
+    }
+
+    pub fn record_move(&mut self, tick: u32, side: Side, direction: MoveDirection) {
+        self.moves.push(ReplayMove {
+            tick,
+            side,
+            direction,
+        });
+    }
+
+    pub fn finish(self, result: ReplayResult) -> Replay {
+        Replay {
+            dimensions: self.dimensions,
+            handicaps: self.handicaps,
+            modifier: self.modifier,
+            tick_interval_ms: self.tick_interval_ms,
+            initial_state: self.initial_state,
+            moves: self.moves,
+            result,
+        }
+    }
+}
+
+impl Replay {
+    /// the replay file format is, with every number little-endian:
+    ///
+    /// - the bytes `PONG`, then the format version as a byte.
+    /// - the width, height and paddle height of the game, then its tick interval, as u16s.
+    /// - the left paddle's height and speed, then the right's, as u16s, each 0 if that paddle
+    ///   isn't handicapped. left out of replays of the first version.
+    /// - the modifier as a byte (0 if classic, 1 if gravity, 2 if wobble, 3 if wrap). left out of replays of
+    ///   the first two versions.
+    /// - the left and right paddle positions, then the ball's x and y, as u16s, then the ball's
+    ///   direction as a byte (1 if moving right, plus 2 if moving down).
+    /// - the number of moves as a u32, then each move as its tick (u32), side and direction
+    ///   (bytes).
+    /// - the result as a byte (0 if won, 1 if resigned), its tick (u32) and the winning or
+    ///   resigning side (byte).
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(46 + 6 * self.moves.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        for n in [
+            self.dimensions.width(),
+            self.dimensions.height(),
+            self.dimensions.paddle_height(),
+            self.tick_interval_ms,
+            self.handicaps.left.paddle_height.unwrap_or(0),
+            self.handicaps.left.paddle_speed.unwrap_or(0),
+            self.handicaps.right.paddle_height.unwrap_or(0),
+            self.handicaps.right.paddle_speed.unwrap_or(0),
+        ] {
+            bytes.extend_from_slice(&n.to_le_bytes());
+        }
+        bytes.push(match self.modifier {
+            Modifier::Classic => 0,
+            Modifier::Gravity => 1,
+            Modifier::Wobble => 2,
+            Modifier::Wrap => 3,
+        });
+        for n in [
+            self.initial_state.left_paddle,
+            self.initial_state.right_paddle,
+            self.initial_state.ball.x,
+            self.initial_state.ball.y,
+        ] {
+            bytes.extend_from_slice(&n.to_le_bytes());
+        }
+        let ball = &self.initial_state.ball;
+        bytes.push(ball.moving_right as u8 | (ball.moving_down as u8) << 1);
+        bytes.extend_from_slice(&(self.moves.len() as u32).to_le_bytes());
+        for replay_move in &self.moves {
+            bytes.extend_from_slice(&replay_move.tick.to_le_bytes());
+            bytes.push(encode_side(replay_move.side));
+            bytes.push(match replay_move.direction {
+                MoveDirection::Stop => 0,
+                MoveDirection::Up => 1,
+                MoveDirection::Down => 2,
+            });
+        }
+        let (kind, tick, side) = match self.result {
+            ReplayResult::Won { tick, side } => (0, tick, side),
+            ReplayResult::Resigned { tick, side } => (1, tick, side),
+        };
+        bytes.push(kind);
+        bytes.extend_from_slice(&tick.to_le_bytes());
+        bytes.push(encode_side(side));
+        bytes
+    }
+
+    /// reads a replay written by [`Replay::encode`], returning nothing if it's malformed.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ByteReader(bytes);
+        if reader.take(4)? != MAGIC {
+            return None;
+        }
+        let version = reader.u8()?;
+        if !(UNHANDICAPPED_FORMAT_VERSION..=FORMAT_VERSION).contains(&version) {
+            return None;
+        }
+        let dimensions = GameDimensions::new(reader.u16()?, reader.u16()?, reader.u16()?)?;
+        let tick_interval_ms = reader.u16()?;
+        let handicaps = if version >= CLASSIC_FORMAT_VERSION {
+            Handicaps {
+                left: reader.handicap()?,
+                right: reader.handicap()?,
+            }
+        } else {
+            Handicaps::NONE
+        };
+        let modifier = if version == FORMAT_VERSION {
+            match reader.u8()? {
+                0 => Modifier::Classic,
+                1 => Modifier::Gravity,
+                2 => Modifier::Wobble,
+                3 => Modifier::Wrap,
+                _ => return None,
+            }
+        } else {
+            Modifier::Classic
+        };
+        let (left_paddle, right_paddle, x, y) =
+            (reader.u16()?, reader.u16()?, reader.u16()?, reader.u16()?);
+        let ball_direction = reader.u8()?;
+        let initial_state = GameState {
+            left_paddle,
+            right_paddle,
+            ball: Ball {
+                x,
+                y,
+                moving_right: ball_direction & 1 != 0,
+                moving_down: ball_direction & 2 != 0,
+            },
+        };
+        dimensions.validate_with(handicaps, &initial_state).ok()?;
+        let move_count = reader.u32()?;
+        let mut moves = Vec::new();
+        for _ in 0..move_count {
+            moves.push(ReplayMove {
+                tick: reader.u32()?,
+                side: decode_side(reader.u8()?)?,
+                direction: match reader.u8()? {
+                    0 => MoveDirection::Stop,
+                    1 => MoveDirection::Up,
+                    2 => MoveDirection::Down,
+                    _ => return None,
+                },
+            });
+        }
+        let result = match (reader.u8()?, reader.u32()?, decode_side(reader.u8()?)?) {
+            (0, tick, side) => ReplayResult::Won { tick, side },
+            (1, tick, side) => ReplayResult::Resigned { tick, side },
+            _ => return None,
+        };
+        if !reader.0.is_empty() {
+            return None;
+        }
+        Some(Self {
+            dimensions,
+            handicaps,
+            modifier,
+            tick_interval_ms,
+            initial_state,
+            moves,
+            result,
+        })
+    }
+}
+
+fn encode_side(side: Side) -> u8 {
+    match side {
+        Side::Left => 0,
+        Side::Right => 1,
+    }
+}
+
+fn decode_side(byte: u8) -> Option<Side> {
+    match byte {
+        0 => Some(Side::Left),
+        1 => Some(Side::Right),
+        _ => None,
+    }
+}
+
+struct ByteReader<'a>(&'a [u8]);
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(taken)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn handicap(&mut self) -> Option<Handicap> {
+        let non_zero = |n| (n != 0).then_some(n);
+        Some(Handicap {
+            paddle_height: non_zero(self.u16()?),
+            paddle_speed: non_zero(self.u16()?),
+        })
+    }
+}
+
+/// the directory finished games are saved to, which only ever keeps the most recent ones.
+pub struct ReplayArchive {
+    pub dir: PathBuf,
+    /// how many replays to keep. the oldest are deleted once there are more.
+    pub retention: usize,
+}
+
+impl ReplayArchive {
+    pub fn save(&self, lobby_id: LobbyId, game: u32, replay: &Replay) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let saved_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        // the save time comes first, and is padded, so that replays sort from oldest to newest.
+        let file_name = format!("{saved_at:015}-{lobby_id}-{game}.{EXTENSION}");
+        fs::write(self.dir.join(file_name), replay.encode())?;
+        self.remove_oldest()
+    }
+
+    fn remove_oldest(&self) -> io::Result<()> {
+        let mut replays = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == EXTENSION)
+            {
+                replays.push(path);
+            }
+        }
+        if replays.len() > self.retention {
+            replays.sort();
+            for path in &replays[..replays.len() - self.retention] {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use rand::{rngs::StdRng, SeedableRng};
+    use shared::{
+        game_state::{
+            GameDimensions, GameState, Handicap, Handicaps, Modifier, MoveDirection, Side,
+        },
+        LobbyId,
+    };
+
+    use crate::{
+        lobby::serve_ball,
+        replay::{Replay, ReplayArchive, ReplayRecorder, ReplayResult},
+    };
+
+    const HANDICAPS: Handicaps = Handicaps {
+        left: Handicap {
+            paddle_height: Some(3),
+            paddle_speed: Some(2),
+        },
+        right: Handicap {
+            paddle_height: None,
+            paddle_speed: Some(3),
+        },
+    };
+
+    fn initial_state(dimensions: GameDimensions, seed: u64) -> GameState {
+        GameState {
+            left_paddle: 0,
+            right_paddle: 0,
+            ball: serve_ball(&mut StdRng::seed_from_u64(seed), dimensions),
+        }
+    }
+
+    /// plays a game where the left player chases the ball and the right player never moves,
+    /// recording it as it goes.
+    fn play_and_record(
+        dimensions: GameDimensions,
+        handicaps: Handicaps,
+        modifier: Modifier,
+        seed: u64,
+    ) -> Replay {
+        let mut game_state = initial_state(dimensions, seed);
+        let mut recorder =
+            ReplayRecorder::new(dimensions, handicaps, modifier, 100, game_state.clone());
+        let mut left_direction = MoveDirection::Stop;
+        let mut tick = 0;
+        loop {
+            let paddle_centre =
+                game_state.left_paddle + handicaps.left.paddle_height(dimensions) / 2;
+            let direction = match game_state.ball.y.cmp(&paddle_centre) {
+                std::cmp::Ordering::Less => MoveDirection::Up,
+                std::cmp::Ordering::Equal => MoveDirection::Stop,
+                std::cmp::Ordering::Greater => MoveDirection::Down,
+            };
+            if direction != left_direction {
+                recorder.record_move(tick, Side::Left, direction);
+                left_direction = direction;
+            }
+            let winner = game_state.tick(
+                dimensions,
+                handicaps,
+                modifier,
+                left_direction,
+                MoveDirection::Stop,
+            );
+            tick += 1;
+            if let Some(side) = winner {
+                return recorder.finish(ReplayResult::Won { tick, side });
+            }
+        }
+    }
+
+    /// ticks the replay's initial state with its moves until the ball gets past a paddle.
+    fn play_back(replay: &Replay) -> ReplayResult {
+        let mut game_state = replay.initial_state.clone();
+        let mut directions = [MoveDirection::Stop; 2];
+        let mut moves = replay.moves.iter().peekable();
+        let mut tick = 0;
+        loop {
+            while let Some(replay_move) = moves.next_if(|replay_move| replay_move.tick == tick) {
+                directions[replay_move.side as usize] = replay_move.direction;
+            }
+            let winner = game_state.tick(
+                replay.dimensions,
+                replay.handicaps,
+                replay.modifier,
+                directions[0],
+                directions[1],
+            );
+            tick += 1;
+            if let Some(side) = winner {
+                return ReplayResult::Won { tick, side };
+            }
+        }
+    }
+
+    #[test]
+    fn encode_and_back() {
+        for seed in 0..8 {
+            let bytes = play_and_record(
+                GameDimensions::DEFAULT,
+                Handicaps::NONE,
+                Modifier::Classic,
+                seed,
+            )
+            .encode();
+            assert_eq!(
+                Replay::decode(&bytes).map(|replay| replay.encode()),
+                Some(bytes)
+            );
+        }
+        let resigned = ReplayRecorder::new(
+            GameDimensions::new(300, 40, 6).unwrap(),
+            HANDICAPS,
+            Modifier::Wobble,
+            16,
+            initial_state(GameDimensions::new(300, 40, 6).unwrap(), 0),
+        )
+        .finish(ReplayResult::Resigned {
+            tick: 0,
+            side: Side::Right,
+        });
+        let bytes = resigned.encode();
+        assert_eq!(
+            Replay::decode(&bytes).map(|replay| replay.encode()),
+            Some(bytes)
+        );
+    }
+
+    #[test]
+    fn decode_rejects_malformed_replays() {
+        let bytes = play_and_record(
+            GameDimensions::DEFAULT,
+            Handicaps::NONE,
+            Modifier::Classic,
+            0,
+        )
+        .encode();
+        assert!(Replay::decode(&[]).is_none());
+        assert!(Replay::decode(&bytes[..bytes.len() - 1]).is_none());
+        assert!(Replay::decode(&[&bytes[..], &[0]].concat()).is_none());
+        let mut wrong_version = bytes.clone();
+        wrong_version[4] = 4;
+        assert!(Replay::decode(&wrong_version).is_none());
+        let mut unknown_modifier = bytes.clone();
+        unknown_modifier[21] = 4;
+        assert!(Replay::decode(&unknown_modifier).is_none());
+        let mut unplayable = bytes;
+        unplayable[5..7].copy_from_slice(&1u16.to_le_bytes());
+        assert!(Replay::decode(&unplayable).is_none());
+    }
+
+    #[test]
+    fn decode_replays_from_before_handicaps() {
+        let replay = play_and_record(
+            GameDimensions::DEFAULT,
+            Handicaps::NONE,
+            Modifier::Classic,
+            0,
+        );
+        let bytes = replay.encode();
+        // the first version is the same, but without the handicaps and modifier after the tick
+        // interval.
+        let first_version = [&bytes[..4], &[1], &bytes[5..13], &bytes[22..]].concat();
+        let decoded = Replay::decode(&first_version).unwrap();
+        assert!(decoded.handicaps.is_none());
+        assert_eq!(decoded.modifier, Modifier::Classic);
+        assert_eq!(decoded.encode(), bytes);
+    }
+
+    #[test]
+    fn decode_replays_from_before_modifiers() {
+        let replay = play_and_record(GameDimensions::DEFAULT, HANDICAPS, Modifier::Classic, 0);
+        let bytes = replay.encode();
+        // the second version is the same, but without the modifier after the handicaps.
+        let second_version = [&bytes[..4], &[2], &bytes[5..21], &bytes[22..]].concat();
+        let decoded = Replay::decode(&second_version).unwrap();
+        assert_eq!(decoded.handicaps, HANDICAPS);
+        assert_eq!(decoded.modifier, Modifier::Classic);
+        assert_eq!(decoded.encode(), bytes);
+    }
+
+    #[test]
+    fn replays_reproduce_the_game() {
+        for handicaps in [Handicaps::NONE, HANDICAPS] {
+            for modifier in [
+                Modifier::Classic,
+                Modifier::Gravity,
+                Modifier::Wobble,
+                Modifier::Wrap,
+            ] {
+                for seed in 0..8 {
+                    let replay =
+                        play_and_record(GameDimensions::DEFAULT, handicaps, modifier, seed);
+                    assert!(play_back(&replay) == replay.result);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn archive_keeps_most_recent_replays() {
+        let dir = std::env::temp_dir().join(format!("pong_replays_{}", std::process::id()));
+        let archive = ReplayArchive {
+            dir: dir.clone(),
+            retention: 3,
+        };
+        let lobby_id: LobbyId = "ABCD".parse().unwrap();
+        let replay = play_and_record(
+            GameDimensions::DEFAULT,
+            Handicaps::NONE,
+            Modifier::Classic,
+            0,
+        );
+        for game in 0..5 {
+            archive.save(lobby_id, game, &replay).unwrap();
+            // replays saved within the same millisecond would have no order between them.
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        let mut names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        fs::remove_dir_all(&dir).unwrap();
+        names.sort();
+        assert_eq!(names.len(), 3);
+        for (name, game) in names.iter().zip(2..) {
+            assert!(name.ends_with(&format!("-ABCD-{game}.replay")));
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::Sender,
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use mio::Waker;
+
+use crate::{task_pool::TaskPool, timer_wheel::TimerWheel};
+
+/// how precisely tasks are polled when they ask to be.
+const TIMER_RESOLUTION: Duration = Duration::from_millis(1);
+/// a few seconds' worth of slots, so that most tasks are polled again within a turn of the wheel.
+const TIMER_SLOTS: usize = 4096;
+
+/// work that waits on the clock, such as a game's ticks or a lobby's expiry.
+pub trait Task: Send {
+    /// does whatever is due by `now`, returning when the task next needs polling, or nothing once
+    /// it's finished. it mustn't block, as other tasks share its thread. it may be polled before
+    /// it asked to be, if it was woken.
+    fn poll(&mut self, now: Instant) -> Option<Instant>;
+}
+
+/// identifies a spawned task, so that it can be woken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TaskId(u64);
+
+impl TaskId {
+    pub(crate) fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        TaskId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// what's handed to the event loop.
+pub enum TaskEvent {
+    Spawned(TaskId, Box<dyn Task>),
+    Woken(TaskId),
+}
+
+/// runs tasks until they finish.
+#[derive(Clone)]
+pub enum Scheduler {
+    /// tasks are polled on a small pool of threads whenever they're due, so that a sleeping game
+    /// doesn't hold on to a thread.
+    Threads { tasks: Arc<TaskPool> },
+    /// tasks are handed to the event loop, which polls them between reading from connections.
+    EventLoop {
+        tasks: Sender<TaskEvent>,
+        waker: Arc<Waker>,
+    },
+}
+
+impl Scheduler {
+    /// runs a task, such as a game's loop or a lobby's expiry.
+    pub fn spawn(&self, task: impl Task + 'static) -> TaskId {
+        let id = TaskId::next();
+        match self {
+            Scheduler::Threads { tasks } => tasks.spawn(id, Box::new(task)),
+            Scheduler::EventLoop { tasks, waker } => {
+                hand_over(tasks, waker, TaskEvent::Spawned(id, Box::new(task)));
+            }
+        }
+        id
+    }
+
+    /// polls the task as soon as possible, rather than waiting until it asked to be. does nothing
+    /// if it's already finished.
+    pub fn wake(&self, id: TaskId) {
+        match self {
+            Scheduler::Threads { tasks } => tasks.wake(id),
+            Scheduler::EventLoop { tasks, waker } => hand_over(tasks, waker, TaskEvent::Woken(id)),
+        }
+    }
+}
+
+fn hand_over(tasks: &Sender<TaskEvent>, waker: &Waker, event: TaskEvent) {
+    // the event loop only stops when the process exits.
+    tasks.send(event).unwrap();
+    waker.wake().unwrap();
+}
+
+/// a task waiting to be polled, or being polled.
+struct TaskEntry {
+    /// taken while the task is being polled.
+    task: Option<Box<dyn Task>>,
+    /// bumped whenever the task's timer is replaced, so that the timers it replaced are ignored
+    /// when they go off.
+    generation: u64,
+    /// whether the task was woken while it was being polled, so needs polling again straight
+    /// away.
+    woken: bool,
+}
+
+/// tasks waiting on a timer wheel until they're due, or woken.
+pub(crate) struct TaskTimers {
+    timers: TimerWheel<(TaskId, u64)>,
+    tasks: HashMap<TaskId, TaskEntry>,
+}
+
+impl TaskTimers {
+    pub(crate) fn new() -> Self {
+        Self {
+            timers: TimerWheel::new(TIMER_RESOLUTION, TIMER_SLOTS),
+            tasks: HashMap::new(),
+        }
+    }
+
+    /// adds a task, which is due straight away.
+    pub(crate) fn insert(&mut self, id: TaskId, task: Box<dyn Task>) {
+        self.tasks.insert(
+            id,
+            TaskEntry {
+                task: Some(task),
+                generation: 0,
+                woken: false,
+            },
+        );
+        self.timers.schedule(Instant::now(), (id, 0));
+    }
+
+    /// makes the task due straight away, or once it's put back if it's being polled.
+    pub(crate) fn wake(&mut self, id: TaskId) {
+        let Some(entry) = self.tasks.get_mut(&id) else {
+            return;
+        };
+        if entry.task.is_some() {
+            entry.generation += 1;
+            self.timers.schedule(Instant::now(), (id, entry.generation));
+        } else {
+            entry.woken = true;
+        }
+    }
+
+    /// takes every task that's due by `now`, each to be polled and then put back.
+    pub(crate) fn take_due(&mut self, now: Instant) -> Vec<(TaskId, Box<dyn Task>)> {
+        self.timers
+            .expire(now)
+            .into_iter()
+            .filter_map(|(id, generation)| {
+                let entry = self.tasks.get_mut(&id)?;
+                if entry.generation != generation {
+                    return None;
+                }
+                Some((id, entry.task.take()?))
+            })
+            .collect()
+    }
+
+    /// puts back a task that's been polled, to be polled again at `next_poll`, or drops it if it's
+    /// finished.
+    pub(crate) fn put_back(&mut self, id: TaskId, task: Box<dyn Task>, next_poll: Option<Instant>) {
+        let Some(next_poll) = next_poll else {
+            self.tasks.remove(&id);
+            return;
+        };
+        let entry = self.tasks.get_mut(&id).unwrap();
+        entry.generation += 1;
+        let at = if entry.woken {
+            entry.woken = false;
+            Instant::now()
+        } else {
+            next_poll
+        };
+        self.timers.schedule(at, (id, entry.generation));
+        entry.task = Some(task);
+    }
+
+    pub(crate) fn next_expiry(&self) -> Option<Instant> {
+        self.timers.next_expiry()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::scheduler::{Task, TaskId, TaskTimers};
+
+    struct Idle;
+
+    impl Task for Idle {
+        fn poll(&mut self, now: Instant) -> Option<Instant> {
+            Some(now + Duration::from_secs(60))
+        }
+    }
+
+    #[test]
+    fn wakes_tasks_once() {
+        let mut timers = TaskTimers::new();
+        let id = TaskId::next();
+        timers.insert(id, Box::new(Idle));
+        // each check looks further ahead than the last, as the wheel is expired up to it.
+        let start = Instant::now();
+        let mut checks = 0;
+        let mut later = || {
+            checks += 1;
+            start + Duration::from_millis(100 * checks)
+        };
+        let (_, task) = timers.take_due(later()).pop().unwrap();
+        timers.put_back(id, task, Some(Instant::now() + Duration::from_secs(60)));
+        assert!(timers.take_due(later()).is_empty());
+        // woken, and so due straight away, rather than when it asked to be polled.
+        timers.wake(id);
+        timers.wake(id);
+        let due = timers.take_due(later());
+        assert_eq!(due.len(), 1);
+        let (_, task) = due.into_iter().next().unwrap();
+        // woken again while it's being polled, so it's due again as soon as it's put back.
+        timers.wake(id);
+        timers.put_back(id, task, Some(Instant::now() + Duration::from_secs(60)));
+        let (_, task) = timers.take_due(later()).pop().unwrap();
+        timers.put_back(id, task, None);
+        timers.wake(id);
+        assert!(timers.take_due(later()).is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use shared::game_state::{GameDimensions, GameState, Handicaps, Modifier, MoveDirection, Side};
+
+use crate::lobby::serve_ball;
+
+/// how often a bot misses its chance to change direction, so that games between bots end.
+const BOT_HESITATION_CHANCE: f64 = 0.2;
+
+/// the outcome of a simulation. a game still in progress when the simulation stops isn't counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SimulationResult {
+    pub left_wins: u32,
+    pub right_wins: u32,
+}
+
+/// plays games between two bots for the given number of ticks, with no networking or terminal
+/// involved. a new game is served as soon as one is won. every tick's state is passed to `trace`
+/// along with the number of the game it belongs to. the same seed always plays the same games.
+pub fn simulate(
+    dimensions: GameDimensions,
+    ticks: u32,
+    seed: u64,
+    mut trace: impl FnMut(u32, &GameState),
+) -> SimulationResult {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut result = SimulationResult::default();
+    let mut game_state = serve(&mut rng, dimensions);
+    let mut directions = [MoveDirection::Stop; 2];
+    for _ in 0..ticks {
+        for (side, direction) in [Side::Left, Side::Right].into_iter().zip(&mut directions) {
+            if !rng.gen_bool(BOT_HESITATION_CHANCE) {
+                *direction = bot_direction(dimensions, &game_state, side);
+            }
+        }
+        let winner = game_state.tick(
+            dimensions,
+            Handicaps::NONE,
+            Modifier::Classic,
+            directions[0],
+            directions[1],
+        );
+        trace(result.left_wins + result.right_wins, &game_state);
+        match winner {
+            Some(Side::Left) => result.left_wins += 1,
+            Some(Side::Right) => result.right_wins += 1,
+            None => continue,
+        }
+        game_state = serve(&mut rng, dimensions);
+        directions = [MoveDirection::Stop; 2];
+    }
+    result
+}
+
+fn serve(rng: &mut StdRng, dimensions: GameDimensions) -> GameState {
+    GameState {
+        left_paddle: 0,
+        right_paddle: 0,
+        ball: serve_ball(rng, dimensions),
+    }
+}
+
+/// moves the bot's paddle to line its centre up with the ball.
+fn bot_direction(dimensions: GameDimensions, game_state: &GameState, side: Side) -> MoveDirection {
+    let paddle = match side {
+        Side::Left => game_state.left_paddle,
+        Side::Right => game_state.right_paddle,
+    };
+    let paddle_centre = paddle + dimensions.paddle_height() / 2;
+    match game_state.ball.y.cmp(&paddle_centre) {
+        std::cmp::Ordering::Less => MoveDirection::Up,
+        std::cmp::Ordering::Equal => MoveDirection::Stop,
+        std::cmp::Ordering::Greater => MoveDirection::Down,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::game_state::GameDimensions;
+
+    use crate::{json, simulation::simulate};
+
+    #[test]
+    fn simulation_is_reproducible_from_seed() {
+        let trace = |seed| {
+            let mut states = Vec::new();
+            let result = simulate(GameDimensions::DEFAULT, 1000, seed, |game, game_state| {
+                states.push((game, json::game_state(game_state)))
+            });
+            (result, states)
+        };
+        assert_eq!(trace(42), trace(42));
+        assert_ne!(trace(42), trace(43));
+    }
+
+    #[test]
+    fn simulated_games_are_played_to_the_end() {
+        for dimensions in [
+            GameDimensions::DEFAULT,
+            GameDimensions::new(300, 40, 6).unwrap(),
+        ] {
+            let mut ticks = 0;
+            let mut last_game = 0;
+            let result = simulate(dimensions, 100_000, 0, |game, game_state| {
+                assert!(game == last_game || game == last_game + 1);
+                assert!(dimensions.validate(game_state).is_ok());
+                last_game = game;
+                ticks += 1;
+            });
+            assert_eq!(ticks, 100_000);
+            assert!(result.left_wins > 0 && result.right_wins > 0);
+            assert!(result.left_wins + result.right_wins >= last_game);
+        }
+    }
+}
@@ -0,0 +1,728 @@
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::{self, Write},
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use shared::{
+    game_state::{GameDimensions, Ruleset, Side},
+    LobbyId,
+};
+
+use crate::{
+    event_log::unix_time_ms,
+    json,
+    lobby::{MatchScore, PlayerId},
+};
+
+/// a match two players finished, with the games each of them won. in a lobby that doesn't keep
+/// score, every game is a match of its own.
+#[derive(Clone, Copy)]
+pub struct MatchResult {
+    pub lobby_id: LobbyId,
+    pub winner: PlayerId,
+    pub loser: PlayerId,
+    pub winner_games: u16,
+    pub loser_games: u16,
+    /// the seat the winner played from, which tells whose paddle the ruleset's handicaps were on.
+    pub winner_side: Side,
+    pub dimensions: GameDimensions,
+    pub ruleset: Ruleset,
+    /// from the start of the match's first game to the end of its last.
+    pub duration: Duration,
+}
+
+impl MatchResult {
+    /// the result of a match in the lobby that ended with the given score, unless it was played
+    /// against the wall of a practice lobby.
+    pub fn new(
+        lobby_id: LobbyId,
+        left_player_id: PlayerId,
+        right_player_id: PlayerId,
+        score: MatchScore,
+        dimensions: GameDimensions,
+        ruleset: Ruleset,
+        duration: Duration,
+    ) -> Option<Self> {
+        if right_player_id == PlayerId::WALL {
+            return None;
+        }
+        let (winner, loser, winner_games, loser_games, winner_side) = if score.left > score.right {
+            (
+                left_player_id,
+                right_player_id,
+                score.left,
+                score.right,
+                Side::Left,
+            )
+        } else {
+            (
+                right_player_id,
+                left_player_id,
+                score.right,
+                score.left,
+                Side::Right,
+            )
+        };
+        Some(Self {
+            lobby_id,
+            winner,
+            loser,
+            winner_games,
+            loser_games,
+            winner_side,
+            dimensions,
+            ruleset,
+            duration,
+        })
+    }
+
+    /// the result as a json object, for tools that don't speak the game's protocol.
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"lobby_id":"{}","winner":{},"loser":{},"winner_side":{},"winner_games":{},"loser_games":{},"duration_ms":{},"tick_interval_ms":{},{},{},"modifier":{},"match_length":{}}}"#,
+            self.lobby_id,
+            self.winner,
+            self.loser,
+            json::side(self.winner_side),
+            self.winner_games,
+            self.loser_games,
+            self.duration.as_millis(),
+            self.ruleset.tick_interval_ms,
+            json::dimensions_fields(self.dimensions),
+            json::paddles_fields(self.dimensions, self.ruleset.handicaps),
+            json::modifier(self.ruleset.modifier),
+            self.ruleset
+                .match_length
+                .map_or("null".to_owned(), |match_length| match_length.to_string()),
+        )
+    }
+
+    /// appends the result to the file as a line of json, with the time it was appended, creating
+    /// the file if it doesn't exist.
+    pub fn append_json(&self, path: &Path) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        // written with a single call, so that results appended by different threads don't
+        // interleave.
+        let line = format!(
+            "{{\"time_ms\":{},{}\n",
+            unix_time_ms(),
+            &self.to_json()[1..]
+        );
+        file.write_all(line.as_bytes())
+    }
+}
+
+/// a match as a stats store keeps it, for exporting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecordedMatch {
+    /// when the match was recorded, in seconds since the unix epoch.
+    pub finished_at: u64,
+    pub lobby_id: LobbyId,
+    pub winner: PlayerId,
+    pub loser: PlayerId,
+    pub winner_games: u16,
+    pub loser_games: u16,
+}
+
+impl RecordedMatch {
+    /// the first row of a csv export, naming the columns of the rest.
+    pub const CSV_HEADER: &'static str =
+        "finished_at,lobby_id,winner,loser,winner_games,loser_games";
+
+    fn new(result: &MatchResult, finished_at: u64) -> Self {
+        Self {
+            finished_at,
+            lobby_id: result.lobby_id,
+            winner: result.winner,
+            loser: result.loser,
+            winner_games: result.winner_games,
+            loser_games: result.loser_games,
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{}",
+            format_date_time(self.finished_at),
+            self.lobby_id,
+            self.winner,
+            self.loser,
+            self.winner_games,
+            self.loser_games,
+        )
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"finished_at":"{}","lobby_id":"{}","winner":{},"loser":{},"winner_games":{},"loser_games":{}}}"#,
+            format_date_time(self.finished_at),
+            self.lobby_id,
+            self.winner,
+            self.loser,
+            self.winner_games,
+            self.loser_games,
+        )
+    }
+}
+
+/// how `stats export` writes matches out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// with a header row naming the columns.
+    Csv,
+    /// an object per line.
+    Json,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            _ => Err("expected csv or json".to_owned()),
+        }
+    }
+}
+
+/// writes every match the store has recorded since the given unix time, oldest first.
+pub fn export(
+    store: &dyn StatsStore,
+    format: ExportFormat,
+    since: u64,
+    w: &mut impl Write,
+) -> io::Result<()> {
+    if format == ExportFormat::Csv {
+        writeln!(w, "{}", RecordedMatch::CSV_HEADER)?;
+    }
+    for recorded in store.matches_since(since)? {
+        match format {
+            ExportFormat::Csv => writeln!(w, "{}", recorded.to_csv())?,
+            ExportFormat::Json => writeln!(w, "{}", recorded.to_json())?,
+        }
+    }
+    Ok(())
+}
+
+/// the unix time at the start of a day given as `YYYY-MM-DD`, in utc.
+pub fn parse_date(s: &str) -> Result<u64, String> {
+    let invalid = || format!("expected a date like 2024-06-01, not {s}");
+    let mut parts = s.splitn(3, '-');
+    let mut part = || -> Result<u32, String> {
+        parts
+            .next()
+            .and_then(|part| part.parse().ok())
+            .ok_or_else(invalid)
+    };
+    let (year, month, day) = (part()?, part()?, part()?);
+    if year < 1970 || !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day)
+    {
+        return Err(invalid());
+    }
+    Ok(days_from_civil(year, month, day) * SECS_PER_DAY)
+}
+
+pub(crate) const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// the days from the unix epoch to the date, using howard hinnant's algorithm, which counts years
+/// from march so that leap days fall at the end of them.
+fn days_from_civil(year: u32, month: u32, day: u32) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year } as u64;
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let month_from_march = (month as u64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as u64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// the inverse of `days_from_civil`.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = (month_from_march + 2) % 12 + 1;
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// the unix time as an iso 8601 date and time in utc, e.g. `2024-06-01T12:30:00Z`.
+pub(crate) fn format_date_time(secs: u64) -> String {
+    let (year, month, day) = civil_from_days(secs / SECS_PER_DAY);
+    let secs_of_day = secs % SECS_PER_DAY;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+    )
+}
+
+/// a player's record across every match they've finished.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlayerStats {
+    pub player_id: PlayerId,
+    pub matches_won: u32,
+    pub matches_lost: u32,
+    pub games_won: u32,
+    pub games_lost: u32,
+}
+
+impl PlayerStats {
+    fn new(player_id: PlayerId) -> Self {
+        Self {
+            player_id,
+            matches_won: 0,
+            matches_lost: 0,
+            games_won: 0,
+            games_lost: 0,
+        }
+    }
+}
+
+/// where the results of finished matches are kept, and each player's record is worked out from.
+pub trait StatsStore: Send + Sync {
+    fn record_match(&self, result: &MatchResult) -> io::Result<()>;
+
+    /// the player's record, if they've finished a match.
+    fn get_player(&self, player_id: PlayerId) -> io::Result<Option<PlayerStats>>;
+
+    /// the records of the players who have won the most matches, best first. players who have won
+    /// as many are ranked by who lost fewest.
+    fn leaderboard(&self, limit: usize) -> io::Result<Vec<PlayerStats>>;
+
+    /// the highest player id with a match recorded, so that the ids given to players after a
+    /// restart can follow on from it rather than be mixed up with theirs.
+    fn last_player_id(&self) -> io::Result<Option<PlayerId>>;
+
+    /// every match recorded at or after the given unix time, oldest first.
+    fn matches_since(&self, since: u64) -> io::Result<Vec<RecordedMatch>>;
+}
+
+/// opens the sqlite database at the given path, creating it if it doesn't exist, or keeps stats
+/// in memory if there's no path.
+pub fn open(path: Option<&Path>) -> io::Result<Arc<dyn StatsStore>> {
+    match path {
+        #[cfg(feature = "sqlite")]
+        Some(path) => Ok(Arc::new(SqliteStatsStore::open(path)?)),
+        #[cfg(not(feature = "sqlite"))]
+        Some(_) => Err(io::Error::other(
+            "the server wasn't built with the `sqlite` feature",
+        )),
+        None => Ok(Arc::new(MemoryStatsStore::default())),
+    }
+}
+
+/// keeps each player's record, and every match, in memory until the server stops.
+#[derive(Default)]
+pub struct MemoryStatsStore {
+    players: Mutex<HashMap<PlayerId, PlayerStats>>,
+    matches: Mutex<Vec<RecordedMatch>>,
+}
+
+impl StatsStore for MemoryStatsStore {
+    fn record_match(&self, result: &MatchResult) -> io::Result<()> {
+        let finished_at = (unix_time_ms() / 1000) as u64;
+        self.matches
+            .lock()
+            .unwrap()
+            .push(RecordedMatch::new(result, finished_at));
+        let mut players = self.players.lock().unwrap();
+        let winner = players
+            .entry(result.winner)
+            .or_insert_with(|| PlayerStats::new(result.winner));
+        winner.matches_won += 1;
+        winner.games_won += result.winner_games as u32;
+        winner.games_lost += result.loser_games as u32;
+        let loser = players
+            .entry(result.loser)
+            .or_insert_with(|| PlayerStats::new(result.loser));
+        loser.matches_lost += 1;
+        loser.games_won += result.loser_games as u32;
+        loser.games_lost += result.winner_games as u32;
+        Ok(())
+    }
+
+    fn get_player(&self, player_id: PlayerId) -> io::Result<Option<PlayerStats>> {
+        Ok(self.players.lock().unwrap().get(&player_id).copied())
+    }
+
+    fn leaderboard(&self, limit: usize) -> io::Result<Vec<PlayerStats>> {
+        let mut players: Vec<PlayerStats> =
+            self.players.lock().unwrap().values().copied().collect();
+        players.sort_by_key(|player| {
+            (
+                u32::MAX - player.matches_won,
+                player.matches_lost,
+                player.player_id.0,
+            )
+        });
+        players.truncate(limit);
+        Ok(players)
+    }
+
+    fn last_player_id(&self) -> io::Result<Option<PlayerId>> {
+        Ok(self
+            .players
+            .lock()
+            .unwrap()
+            .keys()
+            .copied()
+            .max_by_key(|id| id.0))
+    }
+
+    fn matches_since(&self, since: u64) -> io::Result<Vec<RecordedMatch>> {
+        let matches = self.matches.lock().unwrap();
+        Ok(matches
+            .iter()
+            .filter(|recorded| recorded.finished_at >= since)
+            .copied()
+            .collect())
+    }
+}
+
+/// keeps every finished match in an sqlite database, working out players' records from them when
+/// they're asked for.
+#[cfg(feature = "sqlite")]
+pub struct SqliteStatsStore {
+    conn: Mutex<rusqlite::Connection>,
+}
+
+/// every match from the point of view of each of its players, one row each.
+#[cfg(feature = "sqlite")]
+const PLAYER_STATS_QUERY: &str = "
+    SELECT player, SUM(won), SUM(lost), SUM(games_won), SUM(games_lost) FROM (
+        SELECT winner AS player, 1 AS won, 0 AS lost, winner_games AS games_won,
+            loser_games AS games_lost FROM matches
+        UNION ALL
+        SELECT loser, 0, 1, loser_games, winner_games FROM matches
+    )";
+
+#[cfg(feature = "sqlite")]
+impl SqliteStatsStore {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let conn = rusqlite::Connection::open(path).map_err(io::Error::other)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS matches (
+                id INTEGER PRIMARY KEY,
+                lobby_id TEXT NOT NULL,
+                winner INTEGER NOT NULL,
+                loser INTEGER NOT NULL,
+                winner_games INTEGER NOT NULL,
+                loser_games INTEGER NOT NULL,
+                finished_at INTEGER NOT NULL DEFAULT (unixepoch())
+            );
+            CREATE INDEX IF NOT EXISTS matches_winner ON matches (winner);
+            CREATE INDEX IF NOT EXISTS matches_loser ON matches (loser);",
+        )
+        .map_err(io::Error::other)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn player_stats(row: &rusqlite::Row) -> rusqlite::Result<PlayerStats> {
+        Ok(PlayerStats {
+            // sqlite's integers are signed, so ids are stored as their bits.
+            player_id: PlayerId(row.get::<_, i64>(0)? as u64),
+            matches_won: row.get(1)?,
+            matches_lost: row.get(2)?,
+            games_won: row.get(3)?,
+            games_lost: row.get(4)?,
+        })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl StatsStore for SqliteStatsStore {
+    fn record_match(&self, result: &MatchResult) -> io::Result<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO matches (lobby_id, winner, loser, winner_games, loser_games)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+                (
+                    result.lobby_id.to_string(),
+                    result.winner.0 as i64,
+                    result.loser.0 as i64,
+                    result.winner_games,
+                    result.loser_games,
+                ),
+            )
+            .map(|_| ())
+            .map_err(io::Error::other)
+    }
+
+    fn get_player(&self, player_id: PlayerId) -> io::Result<Option<PlayerStats>> {
+        use rusqlite::OptionalExtension;
+
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                &format!("{PLAYER_STATS_QUERY} WHERE player = ?1 GROUP BY player"),
+                [player_id.0 as i64],
+                Self::player_stats,
+            )
+            .optional()
+            .map_err(io::Error::other)
+    }
+
+    fn leaderboard(&self, limit: usize) -> io::Result<Vec<PlayerStats>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn
+            .prepare(&format!(
+                "{PLAYER_STATS_QUERY} GROUP BY player
+                ORDER BY SUM(won) DESC, SUM(lost) ASC, player ASC LIMIT ?1"
+            ))
+            .map_err(io::Error::other)?;
+        let players = statement
+            .query_map([limit as i64], Self::player_stats)
+            .map_err(io::Error::other)?;
+        players
+            .collect::<rusqlite::Result<_>>()
+            .map_err(io::Error::other)
+    }
+
+    fn last_player_id(&self) -> io::Result<Option<PlayerId>> {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT MAX(MAX(winner), MAX(loser)) FROM matches",
+                [],
+                |row| row.get::<_, Option<i64>>(0),
+            )
+            .map(|id| id.map(|id| PlayerId(id as u64)))
+            .map_err(io::Error::other)
+    }
+
+    fn matches_since(&self, since: u64) -> io::Result<Vec<RecordedMatch>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn
+            .prepare(
+                "SELECT finished_at, lobby_id, winner, loser, winner_games, loser_games
+                FROM matches WHERE finished_at >= ?1 ORDER BY id",
+            )
+            .map_err(io::Error::other)?;
+        let matches = statement
+            .query_map([since.min(i64::MAX as u64) as i64], |row| {
+                let lobby_id = row.get::<_, String>(1)?.parse().map_err(|_| {
+                    rusqlite::Error::FromSqlConversionFailure(
+                        1,
+                        rusqlite::types::Type::Text,
+                        "invalid lobby id".into(),
+                    )
+                })?;
+                Ok(RecordedMatch {
+                    finished_at: row.get::<_, i64>(0)? as u64,
+                    lobby_id,
+                    winner: PlayerId(row.get::<_, i64>(2)? as u64),
+                    loser: PlayerId(row.get::<_, i64>(3)? as u64),
+                    winner_games: row.get(4)?,
+                    loser_games: row.get(5)?,
+                })
+            })
+            .map_err(io::Error::other)?;
+        matches
+            .collect::<rusqlite::Result<_>>()
+            .map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use shared::game_state::{GameDimensions, Handicaps, Modifier, Ruleset, Side};
+
+    use crate::{
+        lobby::{MatchScore, PlayerId},
+        stats::{
+            export, format_date_time, parse_date, ExportFormat, MatchResult, MemoryStatsStore,
+            PlayerStats, RecordedMatch, StatsStore,
+        },
+    };
+
+    const RULESET: Ruleset = Ruleset {
+        tick_interval_ms: 100,
+        match_length: Some(3),
+        handicaps: Handicaps::NONE,
+        modifier: Modifier::Classic,
+    };
+
+    /// a result of a match between the players seated on the left and right.
+    fn match_result(left: u64, right: u64, left_games: u16, right_games: u16) -> MatchResult {
+        MatchResult::new(
+            "ABCD".parse().unwrap(),
+            PlayerId(left),
+            PlayerId(right),
+            MatchScore {
+                left: left_games,
+                right: right_games,
+            },
+            GameDimensions::DEFAULT,
+            RULESET,
+            Duration::from_millis(61500),
+        )
+        .unwrap()
+    }
+
+    fn record_matches(store: &dyn StatsStore) {
+        for result in [
+            match_result(1, 2, 3, 1),
+            match_result(3, 1, 0, 1),
+            match_result(3, 2, 5, 3),
+        ] {
+            store.record_match(&result).unwrap();
+        }
+    }
+
+    fn assert_stats(store: &dyn StatsStore) {
+        assert_eq!(store.last_player_id().unwrap(), None);
+        assert_eq!(store.get_player(PlayerId(1)).unwrap(), None);
+        record_matches(store);
+        assert_eq!(
+            store.get_player(PlayerId(2)).unwrap(),
+            Some(PlayerStats {
+                player_id: PlayerId(2),
+                matches_won: 0,
+                matches_lost: 2,
+                games_won: 4,
+                games_lost: 8,
+            })
+        );
+        let leaderboard: Vec<(u64, u32, u32)> = store
+            .leaderboard(10)
+            .unwrap()
+            .into_iter()
+            .map(|player| (player.player_id.0, player.matches_won, player.matches_lost))
+            .collect();
+        assert_eq!(leaderboard, [(1, 2, 0), (3, 1, 1), (2, 0, 2)]);
+        assert_eq!(store.leaderboard(1).unwrap().len(), 1);
+        assert_eq!(store.last_player_id().unwrap(), Some(PlayerId(3)));
+        let matches: Vec<(u64, u64, u16, u16)> = store
+            .matches_since(0)
+            .unwrap()
+            .into_iter()
+            .map(|recorded| {
+                (
+                    recorded.winner.0,
+                    recorded.loser.0,
+                    recorded.winner_games,
+                    recorded.loser_games,
+                )
+            })
+            .collect();
+        assert_eq!(matches, [(1, 2, 3, 1), (1, 3, 1, 0), (3, 2, 5, 3)]);
+        assert!(store.matches_since(u64::MAX).unwrap().is_empty());
+    }
+
+    #[test]
+    fn match_result_to_json() {
+        assert_eq!(
+            match_result(1, 2, 1, 3).to_json(),
+            r#"{"lobby_id":"ABCD","winner":2,"loser":1,"winner_side":"right","winner_games":3,"loser_games":1,"duration_ms":61500,"tick_interval_ms":100,"width":51,"height":11,"paddle_height":5,"left_paddle_height":5,"left_paddle_speed":1,"right_paddle_height":5,"right_paddle_speed":1,"modifier":"classic","match_length":3}"#
+        );
+        let practice = MatchResult::new(
+            "ABCD".parse().unwrap(),
+            PlayerId(1),
+            PlayerId::WALL,
+            MatchScore::single_game(Side::Left),
+            GameDimensions::DEFAULT,
+            RULESET,
+            Duration::ZERO,
+        );
+        assert!(practice.is_none());
+    }
+
+    #[test]
+    fn export_csv() {
+        let store = MemoryStatsStore::default();
+        store.record_match(&match_result(1, 2, 3, 1)).unwrap();
+        let mut csv = Vec::new();
+        export(&store, ExportFormat::Csv, 0, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], RecordedMatch::CSV_HEADER);
+        assert_eq!(lines[1].split_once(',').unwrap().1, "ABCD,1,2,3,1");
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn recorded_match_to_json() {
+        let recorded = RecordedMatch {
+            finished_at: 1709164800 + 3661,
+            lobby_id: "ABCD".parse().unwrap(),
+            winner: PlayerId(2),
+            loser: PlayerId(1),
+            winner_games: 3,
+            loser_games: 1,
+        };
+        assert_eq!(
+            recorded.to_json(),
+            r#"{"finished_at":"2024-02-29T01:01:01Z","lobby_id":"ABCD","winner":2,"loser":1,"winner_games":3,"loser_games":1}"#
+        );
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(parse_date("1970-01-01"), Ok(0));
+        assert_eq!(parse_date("2024-02-29"), Ok(1709164800));
+        assert_eq!(parse_date("2000-03-01"), Ok(951868800));
+        assert!(parse_date("2023-02-29").is_err());
+        assert!(parse_date("2024-13-01").is_err());
+        assert!(parse_date("1969-12-31").is_err());
+        assert!(parse_date("yesterday").is_err());
+        assert_eq!(format_date_time(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_date_time(951868800 - 1), "2000-02-29T23:59:59Z");
+    }
+
+    #[test]
+    fn memory_store() {
+        assert_stats(&MemoryStatsStore::default());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_store_outlasts_reopening() {
+        use crate::stats::SqliteStatsStore;
+
+        let path = std::env::temp_dir().join(format!("pong_stats_{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        assert_stats(&SqliteStatsStore::open(&path).unwrap());
+        let reopened = SqliteStatsStore::open(&path).unwrap();
+        assert_eq!(reopened.last_player_id().unwrap(), Some(PlayerId(3)));
+        assert_eq!(
+            reopened
+                .get_player(PlayerId(1))
+                .unwrap()
+                .map(|player| player.matches_won),
+            Some(2)
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,224 @@
+//! a summary of how busy the server has been, logged at midnight utc each day and again when the
+//! server is shut down, so that its use can be followed from its log alone.
+
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    sync::{Arc, Mutex},
+    thread::{sleep, Builder},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    lobby::{Lobbies, PlayerId},
+    stats::{format_date_time, SECS_PER_DAY},
+};
+
+/// tallies what happens on the server since the last summary was taken. each lobby and game reports
+/// to it as it opens or ends, which only takes a lock for as long as it takes to count.
+pub struct DailySummary {
+    tally: Mutex<Tally>,
+}
+
+struct Tally {
+    /// the unix time the tally was started at.
+    since: u64,
+    games: u64,
+    /// everyone who has played a game, not counting the wall of practice lobbies.
+    players: HashSet<PlayerId>,
+    matches: u32,
+    /// the time every finished match took altogether.
+    match_time: Duration,
+    peak_lobbies: usize,
+}
+
+impl Tally {
+    fn new(since: u64) -> Self {
+        Self {
+            since,
+            games: 0,
+            players: HashSet::new(),
+            matches: 0,
+            match_time: Duration::ZERO,
+            peak_lobbies: 0,
+        }
+    }
+}
+
+impl Default for DailySummary {
+    fn default() -> Self {
+        Self {
+            tally: Mutex::new(Tally::new(unix_secs(SystemTime::now()))),
+        }
+    }
+}
+
+impl DailySummary {
+    /// counts a game that's ended, however it ended, between the lobby's two players.
+    pub fn game_played(&self, left_player_id: PlayerId, right_player_id: PlayerId) {
+        let mut tally = self.tally.lock().unwrap();
+        tally.games += 1;
+        for player_id in [left_player_id, right_player_id] {
+            if player_id != PlayerId::WALL {
+                tally.players.insert(player_id);
+            }
+        }
+    }
+
+    pub fn match_finished(&self, duration: Duration) {
+        let mut tally = self.tally.lock().unwrap();
+        tally.matches += 1;
+        tally.match_time += duration;
+    }
+
+    /// notes how many lobbies are open, once another has been opened.
+    pub fn lobbies_open(&self, lobbies: usize) {
+        let mut tally = self.tally.lock().unwrap();
+        tally.peak_lobbies = tally.peak_lobbies.max(lobbies);
+    }
+
+    /// the summary of everything since the last one was taken, starting the next from `now`.
+    pub fn take(&self, now: SystemTime) -> Summary {
+        let tally = std::mem::replace(&mut *self.tally.lock().unwrap(), Tally::new(unix_secs(now)));
+        Summary {
+            since: tally.since,
+            games: tally.games,
+            players: tally.players.len(),
+            average_match: (tally.matches > 0).then(|| tally.match_time / tally.matches),
+            peak_lobbies: tally.peak_lobbies,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Summary {
+    /// the unix time the summary starts from.
+    pub since: u64,
+    pub games: u64,
+    pub players: usize,
+    /// how long finished matches took on average, if any were.
+    pub average_match: Option<Duration>,
+    /// the most lobbies that were open at once.
+    pub peak_lobbies: usize,
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "since {}: {} games played by {} players, ",
+            format_date_time(self.since),
+            self.games,
+            self.players,
+        )?;
+        match self.average_match {
+            Some(average) => {
+                let secs = average.as_secs();
+                write!(
+                    f,
+                    "matches lasted {}m{:02}s on average, ",
+                    secs / 60,
+                    secs % 60
+                )?
+            }
+            None => write!(f, "no matches finished, ")?,
+        }
+        write!(f, "at most {} lobbies open at once", self.peak_lobbies)
+    }
+}
+
+/// logs the day's summary at every midnight utc, for as long as the server runs.
+pub fn spawn_daily_log(summary: Arc<DailySummary>, lobbies: Arc<Lobbies>) {
+    Builder::new()
+        .name("daily_summary".to_owned())
+        .spawn(move || loop {
+            sleep(until_midnight(SystemTime::now()));
+            println!("daily summary {}", summary.take(SystemTime::now()));
+            // the lobbies still open carry over into the next day.
+            summary.lobbies_open(lobbies.len());
+        })
+        .unwrap();
+}
+
+/// how long until the next midnight utc.
+fn until_midnight(now: SystemTime) -> Duration {
+    let since_midnight =
+        now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() % SECS_PER_DAY;
+    Duration::from_secs(SECS_PER_DAY - since_midnight)
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::{
+        lobby::PlayerId,
+        summary::{until_midnight, DailySummary, Summary},
+    };
+
+    #[test]
+    fn summary_of_the_day() {
+        let summary = DailySummary::default();
+        summary.lobbies_open(1);
+        summary.lobbies_open(3);
+        summary.lobbies_open(2);
+        summary.game_played(PlayerId(1), PlayerId(2));
+        summary.game_played(PlayerId(1), PlayerId(2));
+        summary.match_finished(Duration::from_secs(100));
+        summary.game_played(PlayerId(3), PlayerId::WALL);
+        summary.game_played(PlayerId(1), PlayerId(4));
+        summary.match_finished(Duration::from_secs(141));
+        let midnight = UNIX_EPOCH + Duration::from_secs(1_717_200_000);
+        let taken = summary.take(midnight);
+        assert_eq!(taken.games, 4);
+        assert_eq!(taken.players, 4);
+        assert_eq!(taken.average_match, Some(Duration::from_millis(120_500)));
+        assert_eq!(taken.peak_lobbies, 3);
+        // the next summary starts from nothing.
+        assert_eq!(
+            summary.take(midnight),
+            Summary {
+                since: 1_717_200_000,
+                games: 0,
+                players: 0,
+                average_match: None,
+                peak_lobbies: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn summary_display() {
+        let summary = Summary {
+            since: 1_717_200_000,
+            games: 12,
+            players: 5,
+            average_match: Some(Duration::from_secs(200)),
+            peak_lobbies: 4,
+        };
+        assert_eq!(
+            summary.to_string(),
+            "since 2024-06-01T00:00:00Z: 12 games played by 5 players, matches lasted 3m20s on \
+             average, at most 4 lobbies open at once"
+        );
+    }
+
+    #[test]
+    fn midnight() {
+        let day = 1_717_200_000;
+        assert_eq!(
+            until_midnight(UNIX_EPOCH + Duration::from_secs(day)),
+            Duration::from_secs(24 * 60 * 60)
+        );
+        assert_eq!(
+            until_midnight(UNIX_EPOCH + Duration::from_secs(day + 23 * 60 * 60 + 59 * 60)),
+            Duration::from_secs(60)
+        );
+    }
+}
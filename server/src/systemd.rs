@@ -0,0 +1,135 @@
+use std::{
+    env,
+    io::{self, ErrorKind},
+    net::TcpListener,
+    process,
+    thread::{sleep, Builder},
+    time::Duration,
+};
+
+/// the first descriptor passed by socket activation, the rest following it.
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+
+/// the listener systemd bound for the server, if it was socket activated. only the first socket
+/// passed is served.
+#[cfg(unix)]
+pub fn listener_from_env() -> Option<TcpListener> {
+    use std::os::fd::FromRawFd;
+
+    // the variables could be inherited from a parent that was socket activated itself.
+    let listen_pid = env::var("LISTEN_PID").ok()?;
+    if listen_pid.parse() != Ok(process::id()) {
+        return None;
+    }
+    let fds: u32 = env::var("LISTEN_FDS").ok()?.parse().ok()?;
+    if fds == 0 {
+        return None;
+    }
+    if fds > 1 {
+        eprintln!("{fds} sockets passed by systemd, only serving the first");
+    }
+    // so that nothing started by the server takes them for its own.
+    for key in ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+        env::remove_var(key);
+    }
+    // SAFETY: systemd passes the process its sockets from `LISTEN_FDS_START`, and nothing else
+    // owns them.
+    Some(unsafe { TcpListener::from_raw_fd(LISTEN_FDS_START) })
+}
+
+#[cfg(not(unix))]
+pub fn listener_from_env() -> Option<TcpListener> {
+    None
+}
+
+/// tells systemd the server is ready, and keeps its watchdog fed for as long as the process runs,
+/// if it's watching. does nothing when the server isn't run by systemd.
+pub fn notify_ready() {
+    match notify("READY=1") {
+        Ok(true) => println!("notified systemd that the server is ready"),
+        Ok(false) => return,
+        Err(err) => {
+            eprintln!("failed to notify systemd: {err}");
+            return;
+        }
+    }
+    let Some(interval) = watchdog_interval() else {
+        return;
+    };
+    Builder::new()
+        .name("watchdog".to_owned())
+        .spawn(move || loop {
+            // pinged twice each interval, so that a late ping isn't mistaken for a hang.
+            sleep(interval / 2);
+            if let Err(err) = notify("WATCHDOG=1") {
+                eprintln!("failed to ping systemd's watchdog: {err}");
+            }
+        })
+        .unwrap();
+}
+
+/// how often systemd expects to hear from the server, if it's watching.
+fn watchdog_interval() -> Option<Duration> {
+    if let Ok(watchdog_pid) = env::var("WATCHDOG_PID") {
+        if watchdog_pid.parse() != Ok(process::id()) {
+            return None;
+        }
+    }
+    let usec: u64 = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec))
+}
+
+/// sends the state to systemd's notification socket, returning false if there isn't one.
+pub fn notify(state: &str) -> io::Result<bool> {
+    match env::var("NOTIFY_SOCKET") {
+        Ok(socket) => send_notification(&socket, state).map(|()| true),
+        Err(_) => Ok(false),
+    }
+}
+
+#[cfg(unix)]
+fn send_notification(socket: &str, state: &str) -> io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let datagram = UnixDatagram::unbound()?;
+    let sent = match socket.strip_prefix('@') {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        Some(name) => {
+            use std::os::{linux::net::SocketAddrExt, unix::net::SocketAddr};
+
+            datagram.send_to_addr(state.as_bytes(), &SocketAddr::from_abstract_name(name)?)?
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        Some(_) => return Err(ErrorKind::Unsupported.into()),
+        None => datagram.send_to(state.as_bytes(), socket)?,
+    };
+    if sent < state.len() {
+        return Err(ErrorKind::WriteZero.into());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send_notification(_socket: &str, _state: &str) -> io::Result<()> {
+    Err(ErrorKind::Unsupported.into())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::net::UnixDatagram;
+
+    use crate::systemd::send_notification;
+
+    #[test]
+    fn sends_notifications() {
+        let path = std::env::temp_dir().join(format!("pong_notify_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let socket = UnixDatagram::bind(&path).unwrap();
+        send_notification(path.to_str().unwrap(), "READY=1").unwrap();
+        let mut buf = [0; 64];
+        let n = socket.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"READY=1");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    thread::Builder,
+    time::Instant,
+};
+
+use crate::{
+    scheduler::{Task, TaskId, TaskTimers},
+    worker_pool::WorkerPool,
+};
+
+/// runs tasks on a small pool of workers, each task waiting on a shared timer wheel between polls
+/// rather than on a sleeping thread of its own. tasks must not block, as they share their workers
+/// with every other task.
+pub struct TaskPool {
+    timers: Mutex<TaskTimers>,
+    /// notified whenever a task is scheduled, in case it's due before the timer thread would next
+    /// wake.
+    scheduled: Condvar,
+    workers: WorkerPool,
+}
+
+impl TaskPool {
+    /// starts the thread that hands due tasks to the pool's workers, which runs until the process
+    /// exits.
+    pub fn start(workers: usize) -> Arc<Self> {
+        let pool = Arc::new(Self {
+            timers: Mutex::new(TaskTimers::new()),
+            scheduled: Condvar::new(),
+            workers: WorkerPool::new("task_worker", workers),
+        });
+        let timer_pool = Arc::clone(&pool);
+        Builder::new()
+            .name("task_timer".to_string())
+            .spawn(move || timer_pool.run_timers())
+            .unwrap();
+        pool
+    }
+
+    /// runs a task until it finishes, first polling it as soon as a worker is free.
+    pub fn spawn(&self, id: TaskId, task: Box<dyn Task>) {
+        self.timers.lock().unwrap().insert(id, task);
+        self.scheduled.notify_one();
+    }
+
+    /// polls the task as soon as a worker is free, if it hasn't finished.
+    pub fn wake(&self, id: TaskId) {
+        self.timers.lock().unwrap().wake(id);
+        self.scheduled.notify_one();
+    }
+
+    fn run_timers(self: Arc<Self>) {
+        let mut timers = self.timers.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            let due = timers.take_due(now);
+            if due.is_empty() {
+                timers = match timers.next_expiry() {
+                    Some(next_expiry) => {
+                        let timeout = next_expiry.saturating_duration_since(now);
+                        self.scheduled.wait_timeout(timers, timeout).unwrap().0
+                    }
+                    None => self.scheduled.wait(timers).unwrap(),
+                };
+                continue;
+            }
+            // workers put their tasks back once they're polled, so can't be waited on while the
+            // timers are locked.
+            drop(timers);
+            for (id, task) in due {
+                let pool = Arc::clone(&self);
+                self.workers.execute(move || pool.poll(id, task));
+            }
+            timers = self.timers.lock().unwrap();
+        }
+    }
+
+    fn poll(&self, id: TaskId, mut task: Box<dyn Task>) {
+        let next_poll = task.poll(Instant::now());
+        self.timers.lock().unwrap().put_back(id, task, next_poll);
+        self.scheduled.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        sync::mpsc::{channel, Sender},
+        thread::{current, ThreadId},
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        scheduler::{Task, TaskId},
+        task_pool::TaskPool,
+    };
+
+    /// polled a few times, a tick apart, reporting which thread polled it each time.
+    struct Ticker {
+        ticks_left: u32,
+        polled_on: Sender<ThreadId>,
+    }
+
+    impl Task for Ticker {
+        fn poll(&mut self, now: Instant) -> Option<Instant> {
+            self.polled_on.send(current().id()).unwrap();
+            self.ticks_left -= 1;
+            (self.ticks_left > 0).then(|| now + Duration::from_millis(5))
+        }
+    }
+
+    #[test]
+    fn runs_many_tasks_on_few_threads() {
+        let pool = TaskPool::start(2);
+        let (polled_on_tx, polled_on_rx) = channel();
+        for _ in 0..100 {
+            pool.spawn(
+                TaskId::next(),
+                Box::new(Ticker {
+                    ticks_left: 3,
+                    polled_on: polled_on_tx.clone(),
+                }),
+            );
+        }
+        drop(polled_on_tx);
+        let threads = polled_on_rx.iter().collect::<Vec<_>>();
+        assert_eq!(threads.len(), 300);
+        assert!(threads.iter().collect::<HashSet<_>>().len() <= 2);
+    }
+}
@@ -1,70 +1,289 @@
 use std::{
-    net::TcpListener,
-    sync::{Arc, Mutex},
-    thread::Builder,
+    io::stdin,
+    net::{SocketAddr, TcpListener, TcpStream},
+    num::NonZeroUsize,
+    sync::Arc,
+    thread::{available_parallelism, Builder},
+    time::Instant,
 };
 
-use dashmap::DashMap;
 use rand::RngCore;
-use shared::LobbyId;
+use shared::server_msg::RejectedServerMessage;
 
 use crate::{
-    lobby::Lobby, lobby_id_generator::LobbyIdGenerator, tcp_stream_handler::TcpStreamHandler,
+    access_list::AccessListFile,
+    buffer_pool::BufferPool,
+    client_conn::ClientConn,
+    config::{ServerConfig, ServerMode},
+    dashboard,
+    debug_step::DebugStepper,
+    event_loop::EventLoop,
+    lobby::{Lobbies, PlayerId},
+    lobby_id_generator::LobbyIdGenerator,
+    match_queue::MatchQueue,
+    quota::Quota,
+    scheduler::Scheduler,
+    stats::{self, StatsStore},
+    summary::{self, DailySummary},
+    systemd,
+    task_pool::TaskPool,
+    tcp_stream_handler::TcpStreamHandler,
+    telemetry::Telemetry,
+    tick_timings::TickTimings,
+    tournament::Tournaments,
+    worker_pool::WorkerPool,
 };
 
 struct TcpServer {
     inner: TcpListener,
-    lobbies: Arc<DashMap<LobbyId, Lobby>>,
-    lobby_id_generator: Arc<Mutex<LobbyIdGenerator>>,
+    config: Arc<ServerConfig>,
+    /// when the server started, so that its uptime can be reported.
+    started_at: Instant,
+    lobbies: Arc<Lobbies>,
+    lobby_id_generator: Arc<LobbyIdGenerator>,
+    match_queue: Arc<MatchQueue>,
+    tournaments: Arc<Tournaments>,
+    debug_stepper: Option<Arc<DebugStepper>>,
+    /// where the result of every finished match is recorded.
+    stats: Arc<dyn StatsStore>,
+    /// tallies what happens on the server, for the summary logged each day.
+    summary: Arc<DailySummary>,
+    /// counts what happens on the server, to be sent to `TELEMETRY_ENDPOINT` if it's set.
+    telemetry: Arc<Telemetry>,
+    /// the buffers of frames queued for clients that are behind, shared by every connection.
+    buffer_pool: Arc<BufferPool>,
+    /// times every game's ticks.
+    tick_timings: Arc<TickTimings>,
+    /// runs each connection's handler, unless they're served by the event loop.
+    connections: WorkerPool,
+    /// runs each lobby's games and timers.
+    scheduler: Scheduler,
+    /// serves every connection, if the server is in event loop mode.
+    event_loop: Option<EventLoop>,
+    /// which addresses can connect, if not every one can.
+    access_list: Option<AccessListFile>,
+    /// limits how many connections each address opens, if they're limited at all.
+    connection_quota: Option<Quota>,
+    /// limits how many lobbies each address creates, if they're limited at all.
+    lobby_quota: Option<Arc<Quota>>,
+    next_player_id: u64,
 }
 
-pub fn start() {
-    let address = std::env::var("SERVER_ADDR").unwrap_or("0.0.0.0:8080".to_owned());
-    let server = TcpListener::bind(address).expect("failed to start server");
-    println!("server started");
-    TcpServer::new(server).handle_incoming();
+pub fn start(config: ServerConfig, summary: Arc<DailySummary>) {
+    let listener = match systemd::listener_from_env() {
+        Some(listener) => {
+            println!("server started on a socket passed by systemd");
+            listener
+        }
+        None => {
+            let listener = TcpListener::bind(&config.address).expect("failed to start server");
+            println!("server started");
+            listener
+        }
+    };
+    serve(listener, config, summary);
+}
+
+/// serves connections accepted by the listener until the process exits, tallying what happens
+/// in the summary.
+pub fn serve(listener: TcpListener, config: ServerConfig, summary: Arc<DailySummary>) {
+    let mut server = TcpServer::new(listener, config, summary);
+    if let Some(dashboard_address) = &server.config.dashboard_address {
+        let dashboard = TcpListener::bind(dashboard_address).expect("failed to start dashboard");
+        println!("dashboard started on http://{dashboard_address}");
+        let lobbies = Arc::clone(&server.lobbies);
+        let buffer_pool = Arc::clone(&server.buffer_pool);
+        let tick_timings = Arc::clone(&server.tick_timings);
+        Builder::new()
+            .name("dashboard".to_owned())
+            .spawn(move || dashboard::serve(dashboard, lobbies, buffer_pool, tick_timings))
+            .unwrap();
+    }
+    if let Some(debug_stepper) = &server.debug_stepper {
+        println!("debug step mode enabled, games advance with `step <LOBBY_ID> [TICKS]`");
+        let debug_stepper = Arc::clone(debug_stepper);
+        Builder::new()
+            .name("debug_console".to_owned())
+            .spawn(move || debug_stepper.run_console(stdin().lock()))
+            .unwrap();
+    }
+    summary::spawn_daily_log(Arc::clone(&server.summary), Arc::clone(&server.lobbies));
+    #[cfg(feature = "telemetry")]
+    if let Some(endpoint) = &server.config.telemetry_endpoint {
+        println!("sending anonymous telemetry to {}", endpoint.host);
+        crate::telemetry::spawn_reporter(Arc::clone(&server.telemetry), endpoint.clone());
+    }
+    systemd::notify_ready();
+    server.handle_incoming();
 }
 
 impl TcpServer {
-    pub fn new(inner: TcpListener) -> Self {
-        let lobbies = Arc::new(DashMap::new());
-        // no data within the application is persisted or distributed outside the application, so
-        // randomly generating a new key on each startup is acceptable.
-        let mut key = [0; 32];
-        rand::thread_rng().fill_bytes(&mut key);
-        let lobby_id_generator = Arc::new(Mutex::new(LobbyIdGenerator::new(&key)));
+    pub fn new(inner: TcpListener, config: ServerConfig, summary: Arc<DailySummary>) -> Self {
+        let lobbies = Arc::new(Lobbies::default());
+        // without a configured key, ids only need to be unique while the server is running, so a
+        // new key is generated on each startup.
+        let key = config.lobby_id_key.unwrap_or_else(|| {
+            let mut key = [0; 32];
+            rand::thread_rng().fill_bytes(&mut key);
+            key
+        });
+        let lobby_id_generator = match &config.lobby_id_counter_file {
+            Some(path) => LobbyIdGenerator::with_counter_file(&key, config.lobby_id_len, path)
+                .unwrap_or_else(|err| {
+                    panic!("failed to read LOBBY_ID_COUNTER_FILE {path:?}: {err}")
+                }),
+            None => LobbyIdGenerator::new(&key, config.lobby_id_len),
+        };
+        let lobby_id_generator = Arc::new(lobby_id_generator);
+        let debug_stepper = config.debug_step.then(|| Arc::new(DebugStepper::default()));
+        let connections = WorkerPool::new("handler", config.max_connections);
+        let (scheduler, event_loop) = match config.server_mode {
+            ServerMode::Threads => {
+                // games spend nearly all their time waiting for their next tick, so a worker per
+                // core keeps up with many more games than there are cores.
+                let workers = config
+                    .task_workers
+                    .unwrap_or_else(|| available_parallelism().map_or(1, NonZeroUsize::get));
+                (
+                    Scheduler::Threads {
+                        tasks: TaskPool::start(workers),
+                    },
+                    None,
+                )
+            }
+            ServerMode::EventLoop => {
+                let event_loop = EventLoop::new().expect("failed to start event loop");
+                (event_loop.scheduler(), Some(event_loop))
+            }
+        };
+        let access_list = config.access_list_file.as_ref().map(|path| {
+            AccessListFile::load(path)
+                .unwrap_or_else(|err| panic!("failed to read ACCESS_LIST_FILE {path:?}: {err}"))
+        });
+        let stats = stats::open(config.stats_db_file.as_deref()).unwrap_or_else(|err| {
+            panic!(
+                "failed to open STATS_DB_FILE {:?}: {err}",
+                config.stats_db_file
+            )
+        });
+        // players' records are kept by their ids, so ids carry on from those already recorded.
+        let next_player_id = stats
+            .last_player_id()
+            .expect("failed to read the last recorded player id")
+            .map_or(0, |player_id| player_id.0 + 1);
+        let connection_quota = config.connection_quota.map(Quota::new);
+        let lobby_quota = config.lobby_quota.map(|quota| Arc::new(Quota::new(quota)));
+        let config = Arc::new(config);
+        let tournaments = Arc::new(Tournaments::new(
+            Arc::clone(&lobbies),
+            Arc::clone(&lobby_id_generator),
+            Arc::clone(&config),
+            scheduler.clone(),
+            Arc::clone(&summary),
+        ));
         Self {
             inner,
+            config,
+            started_at: Instant::now(),
             lobbies,
             lobby_id_generator,
+            match_queue: Arc::new(MatchQueue::default()),
+            tournaments,
+            debug_stepper,
+            stats,
+            summary,
+            telemetry: Arc::default(),
+            buffer_pool: Arc::default(),
+            tick_timings: Arc::default(),
+            connections,
+            scheduler,
+            event_loop,
+            access_list,
+            connection_quota,
+            lobby_quota,
+            next_player_id,
         }
     }
 
-    fn handle_incoming(&self) {
+    fn handle_incoming(&mut self) {
         println!("listening for incoming connections!");
-        for stream in self.inner.incoming() {
-            match stream {
-                Ok(stream) => {
-                    let peer_addr = match stream.peer_addr() {
-                        Ok(peer_addr) => peer_addr,
-                        Err(err) => {
-                            eprintln!("failed to retrieve peer address of connection: {err}");
-                            continue;
-                        }
+        let listener = self.inner.try_clone().unwrap();
+        if let Some(event_loop) = self.event_loop.take() {
+            event_loop
+                .run(listener, |stream, peer_addr| {
+                    self.new_handler(stream, peer_addr)
+                })
+                .expect("event loop failed");
+            return;
+        }
+        loop {
+            match listener.accept() {
+                Ok((stream, peer_addr)) => {
+                    let Some(mut handler) = self.new_handler(stream, peer_addr) else {
+                        continue;
                     };
-                    println!("connection established from {:?}", peer_addr);
-                    let lobbies = self.lobbies.clone();
-                    let lobby_id_generator = self.lobby_id_generator.clone();
-                    Builder::new()
-                        .name(format!("handler_{peer_addr}"))
-                        .spawn(move || {
-                            TcpStreamHandler::new(stream, lobbies, lobby_id_generator)
-                                .handle_stream()
-                        })
-                        .unwrap();
+                    // blocks while every handler is busy, leaving further connections to wait
+                    // in the listener's backlog.
+                    self.connections.execute(move || handler.handle_stream());
                 }
                 Err(err) => eprintln!("incoming connection failure: {err}"),
             }
         }
     }
+
+    /// creates a handler for a new connection from the address it was accepted from, or nothing if
+    /// it's been rejected.
+    fn new_handler(
+        &mut self,
+        stream: TcpStream,
+        peer_addr: SocketAddr,
+    ) -> Option<TcpStreamHandler> {
+        let rejection = if self
+            .access_list
+            .as_mut()
+            .is_some_and(|access_list| !access_list.permits(peer_addr.ip()))
+        {
+            println!("rejecting connection from {peer_addr:?}, which isn't allowed to connect");
+            Some(RejectedServerMessage::AddressDenied)
+        } else if self
+            .connection_quota
+            .as_ref()
+            .is_some_and(|quota| !quota.try_use(peer_addr.ip(), Instant::now()))
+        {
+            println!("rejecting connection from {peer_addr:?}, which has opened too many");
+            Some(RejectedServerMessage::TooManyConnections)
+        } else {
+            None
+        };
+        if let Some(rejection) = rejection {
+            TcpStreamHandler::write_to_client(rejection, &ClientConn::new(stream));
+            return None;
+        }
+        if let Err(err) = self.config.socket_options.apply(&stream) {
+            eprintln!("failed to set socket options of connection from {peer_addr:?}: {err}");
+        }
+        let player_id = PlayerId(self.next_player_id);
+        self.next_player_id += 1;
+        println!("connection established from {peer_addr:?} as player {player_id}");
+        self.telemetry.connection_opened();
+        Some(TcpStreamHandler::new(
+            stream,
+            peer_addr,
+            player_id,
+            Arc::clone(&self.config),
+            self.started_at,
+            self.lobbies.clone(),
+            self.lobby_id_generator.clone(),
+            Arc::clone(&self.match_queue),
+            Arc::clone(&self.tournaments),
+            self.debug_stepper.clone(),
+            Arc::clone(&self.stats),
+            Arc::clone(&self.summary),
+            Arc::clone(&self.telemetry),
+            Arc::clone(&self.tick_timings),
+            Arc::clone(&self.buffer_pool),
+            self.scheduler.clone(),
+            self.lobby_quota.clone(),
+        ))
+    }
 }
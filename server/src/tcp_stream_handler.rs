@@ -1,295 +1,844 @@
 use std::{
-    io::{Read, Write},
-    net::TcpStream,
-    sync::{Arc, Mutex},
-    thread::{sleep, Builder},
-    time::Duration,
+    io::ErrorKind,
+    net::{SocketAddr, TcpStream},
+    sync::{
+        mpsc::{channel, Receiver},
+        Arc,
+    },
+    thread::Builder,
+    time::{Duration, Instant},
 };
 
-use dashmap::{mapref::entry::Entry, DashMap};
+use rand::{rngs::StdRng, SeedableRng};
 
 use shared::{
+    checksum::{self, CHECKSUM_SIZE},
     client_msg::{
-        AwaitingOpenClientMessage, AwaitingReadyClientMessage, PlayingClientMessage,
-        MAX_CLIENT_MESSAGE_SIZE,
+        AwaitingOpenClientMessage, AwaitingOpponentJoinClientMessage, AwaitingReadyClientMessage,
+        Capabilities, ChecksumClientMessage, PlayingClientMessage, ResyncClientMessage,
+        CLIENT_MESSAGE_DELIMITER, MAX_CLIENT_MESSAGE_SIZE,
+    },
+    cosmetics::Cosmetics,
+    game_state::{
+        ai_direction, sped_up_tick_interval_ms, speed_level, GameDimensions, GameState, Handicaps,
+        Modifier, MoveDirection, Ruleset, Side, NORMAL_TICK_INTERVAL_MS,
     },
-    game_state::{Ball, GameState, GAME_HEIGHT, GAME_WIDTH, PADDLE_HEIGHT},
+    io::{MessageReader, ReadMessageError},
     server_msg::{
-        AwaitingJoinLobbyServerMessage, AwaitingNewLobbyServerMessage,
-        AwaitingOpponentJoinServerMessage, AwaitingReadyServerMessage, PlayingServerMessage,
-        SERVER_MESSAGE_DELIMITER,
+        AwaitingJoinLobbyServerMessage, AwaitingLobbyPageServerMessage, AwaitingMatchServerMessage,
+        AwaitingNewLobbyServerMessage, AwaitingOpponentJoinServerMessage,
+        AwaitingReadyServerMessage, AwaitingServerStatusServerMessage, ChecksumServerMessage,
+        PlayingServerMessage, ProtocolState, ResyncServerMessage, ServerVersion,
+        MAX_SERVER_MESSAGE_SIZE, SERVER_MESSAGE_DELIMITER,
     },
-    LobbyId,
+    LobbyId, WriteInto, MAX_U14,
 };
 
 use crate::{
-    lobby::{Lobby, LobbyState},
+    buffer_pool::BufferPool,
+    client_conn::{ClientConn, Delivery},
+    config::{ServerConfig, Tiebreak},
+    debug_step::DebugStepper,
+    event_log::{EventLog, LobbyEvent},
+    json,
+    lobby::{
+        can_play, open_lobby_page, practice_ruleset, serve_ball_from, serving_side, settle_ruleset,
+        unused_lobby_id, CoopGame, Lobbies, Lobby, LobbyState, MatchScore, PlayerId, SharedLobby,
+    },
     lobby_id_generator::LobbyIdGenerator,
+    match_queue::{MatchQueue, QueuedPlayer},
+    quota::Quota,
+    replay::{Replay, ReplayRecorder, ReplayResult},
+    scheduler::{Scheduler, Task},
+    stats::{MatchResult, StatsStore},
+    summary::DailySummary,
+    telemetry::Telemetry,
+    tick_timings::TickTimings,
+    tournament::{Format, Tournaments},
 };
 
+/// the version of this build of the server, reported to clients asking for its status.
+const SERVER_VERSION: ServerVersion = ServerVersion {
+    major: parse_version_number(env!("CARGO_PKG_VERSION_MAJOR")),
+    minor: parse_version_number(env!("CARGO_PKG_VERSION_MINOR")),
+    patch: parse_version_number(env!("CARGO_PKG_VERSION_PATCH")),
+};
+
+const fn parse_version_number(number: &str) -> u16 {
+    match u16::from_str_radix(number, 10) {
+        Ok(number) => number,
+        Err(_) => panic!("version numbers must fit in a u16"),
+    }
+}
+
+/// how long before a lobby expires its host is warned, so that they can keep it open.
+const LOBBY_EXPIRY_WARNING: Duration = Duration::from_secs(60);
+
+/// how often a game waiting for its serve checks whether the ball has been served.
+const SERVE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// how often a game being stepped by an admin checks whether it's been stepped.
+const DEBUG_STEP_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// how fast games between players paired by the match queue run.
+const MATCH_TICK_INTERVAL_MS: u16 = NORMAL_TICK_INTERVAL_MS;
+
+/// how often a connection is checked for having gone quiet for longer than its read timeout, which
+/// can change while it's quiet.
+pub const READ_TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// how often a connection served by a thread of its own writes the frames queued while its client
+/// wasn't reading, when nothing else is sent to the client in the meantime.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(50);
+
 pub struct TcpStreamHandler {
-    stream: TcpStream,
-    lobbies: Arc<DashMap<LobbyId, Lobby>>,
-    lobby_id_generator: Arc<Mutex<LobbyIdGenerator>>,
-    lobby_id: Option<String>,
+    conn: Arc<ClientConn>,
+    /// looked up once, since it can't be once the connection has been reset.
+    peer_addr: SocketAddr,
+    player_id: PlayerId,
+    config: Arc<ServerConfig>,
+    started_at: Instant,
+    lobbies: Arc<Lobbies>,
+    lobby_id_generator: Arc<LobbyIdGenerator>,
+    match_queue: Arc<MatchQueue>,
+    tournaments: Arc<Tournaments>,
+    debug_stepper: Option<Arc<DebugStepper>>,
+    /// where the result of each match the player finishes is recorded.
+    stats: Arc<dyn StatsStore>,
+    /// counts the player's games and lobbies into the summary logged each day.
+    summary: Arc<DailySummary>,
+    /// counts how the player's connection and games fare, anonymously.
+    telemetry: Arc<Telemetry>,
+    /// times the ticks of the games the player starts.
+    tick_timings: Arc<TickTimings>,
+    /// runs the loop of each game the player starts, and the timers of their lobbies.
+    scheduler: Scheduler,
+    /// limits how many lobbies the player's address creates, if they're limited at all.
+    lobby_quota: Option<Arc<Quota>>,
+    lobby_id: Option<LobbyId>,
+    /// receives the id of the lobby the player is seated in, while they're queued for a match.
+    matched: Option<Receiver<LobbyId>>,
+    /// the tournament the player has a seat in. the lobbies of its matches are opened by whichever
+    /// thread advanced the tournament, so the player's current lobby is looked up in it.
+    tournament_id: Option<LobbyId>,
+    /// whether the client has agreed to have every frame end with a checksum.
+    checksums: bool,
 }
 
 impl TcpStreamHandler {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         stream: TcpStream,
-        lobbies: Arc<DashMap<LobbyId, Lobby>>,
-        lobby_id_generator: Arc<Mutex<LobbyIdGenerator>>,
+        peer_addr: SocketAddr,
+        player_id: PlayerId,
+        config: Arc<ServerConfig>,
+        started_at: Instant,
+        lobbies: Arc<Lobbies>,
+        lobby_id_generator: Arc<LobbyIdGenerator>,
+        match_queue: Arc<MatchQueue>,
+        tournaments: Arc<Tournaments>,
+        debug_stepper: Option<Arc<DebugStepper>>,
+        stats: Arc<dyn StatsStore>,
+        summary: Arc<DailySummary>,
+        telemetry: Arc<Telemetry>,
+        tick_timings: Arc<TickTimings>,
+        buffer_pool: Arc<BufferPool>,
+        scheduler: Scheduler,
+        lobby_quota: Option<Arc<Quota>>,
     ) -> Self {
         Self {
-            stream,
+            peer_addr,
+            conn: Arc::new(ClientConn::with_pool(stream, buffer_pool)),
+            player_id,
+            config,
+            started_at,
             lobbies,
             lobby_id_generator,
+            match_queue,
+            tournaments,
+            debug_stepper,
+            stats,
+            summary,
+            telemetry,
+            tick_timings,
+            scheduler,
+            lobby_quota,
             lobby_id: None,
+            matched: None,
+            tournament_id: None,
+            checksums: false,
         }
     }
 
     pub fn handle_stream(&mut self) {
-        let mut buffer = [0; MAX_CLIENT_MESSAGE_SIZE];
+        let mut reader = MessageReader::new(
+            self.conn.stream().try_clone().unwrap(),
+            CLIENT_MESSAGE_DELIMITER,
+            MAX_CLIENT_MESSAGE_SIZE + CHECKSUM_SIZE,
+        );
+        // game loops on the shared task pool write to the socket too.
+        if let Err(err) = self.conn.limit_blocking() {
+            eprintln!("failed to set write timeout of {:?}: {err}", self.peer_addr);
+        }
+        // reads give up every so often, to write whatever's been queued for the client since.
+        if let Err(err) = reader.get_ref().set_read_timeout(Some(FLUSH_INTERVAL)) {
+            eprintln!("failed to set read timeout of {:?}: {err}", self.peer_addr);
+        }
+        let checks_read_timeouts = self.config.open_read_timeout().is_some()
+            || self.config.playing_read_timeout().is_some();
+        let mut last_message = Instant::now();
+        let mut next_read_timeout_check = last_message + READ_TIMEOUT_CHECK_INTERVAL;
         loop {
-            match self.stream.read(&mut buffer) {
-                Ok(n) => {
-                    if n == 0 {
-                        println!("connection {:?} closed", self.stream.peer_addr().unwrap());
-                        if let Some(lobby_id) = &self.lobby_id {
-                            let lobby = self.lobbies.remove(lobby_id);
-                            if let Some((_, lobby)) = lobby {
-                                match lobby {
-                                    Lobby::AwaitingJoin { .. } => {}
-                                    Lobby::Joined {
-                                        left_player_conn,
-                                        right_player_conn,
-                                        state,
-                                    } => {
-                                        let is_left_player = self.stream.peer_addr().unwrap()
-                                            == left_player_conn.peer_addr().unwrap();
-                                        let mut opponent_conn = if is_left_player {
-                                            right_player_conn
-                                        } else {
-                                            left_player_conn
-                                        };
-                                        match state {
-                                            LobbyState::AwaitingReadies { .. } => {
-                                                Self::write_to_client(
-                                                    AwaitingReadyServerMessage::OpponentLeft,
-                                                    &mut opponent_conn,
-                                                );
-                                            }
-                                            LobbyState::Playing { .. } => {
-                                                Self::write_to_client(
-                                                    PlayingServerMessage::OpponentLeft,
-                                                    &mut opponent_conn,
-                                                );
-                                            }
-                                        }
-                                    }
-                                }
-                            }
+            match reader.read_message() {
+                Ok(message) => {
+                    last_message = Instant::now();
+                    self.handle_message(message);
+                }
+                Err(ReadMessageError::Closed) => {
+                    self.handle_close();
+                    break;
+                }
+                Err(ReadMessageError::Io(err))
+                    if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+                {
+                    self.conn.flush();
+                    let now = Instant::now();
+                    if checks_read_timeouts && now >= next_read_timeout_check {
+                        next_read_timeout_check = now + READ_TIMEOUT_CHECK_INTERVAL;
+                        if self.read_timed_out(last_message, now) {
+                            self.handle_read_timeout();
+                            break;
                         }
-                        break;
                     }
-                    println!(
-                        "received msg from client {}: {:?}",
-                        self.stream.peer_addr().unwrap(),
-                        &buffer[..n]
-                    );
-                    self.handle_client_message(&buffer[..n]);
                 }
-                Err(err) => eprintln!(
-                    "failed to read from {:?}: {err}",
-                    self.stream.peer_addr().unwrap(),
-                ),
+                Err(err) => self.handle_read_error(err),
+            };
+        }
+    }
+
+    /// how long the connection can go without a message before it's closed, which depends on what
+    /// the player is doing. players waiting on someone else, such as for an opponent to join or a
+    /// tournament to fill, can be quiet for as long as that takes, leaving a connection that's
+    /// gone in the meantime to be found by keepalive probes.
+    fn read_timeout(&self) -> Option<Duration> {
+        if self.matched.is_some() || self.tournament_id.is_some() {
+            return None;
+        }
+        let Some(lobby_id) = self.lobby_id else {
+            return self.config.open_read_timeout();
+        };
+        // a lobby that's gone, such as one that expired, or whose id was taken by another since,
+        // leaves the player waiting on nothing.
+        let Some(lobby) = self.lobbies.get(&lobby_id) else {
+            return self.config.open_read_timeout();
+        };
+        let lobby = lobby.lock();
+        match lobby.as_ref() {
+            Some(lobby) if lobby.has_player(self.player_id) => match lobby {
+                // games being stepped by an admin are paused for as long as the admin likes.
+                Lobby::Joined {
+                    state: LobbyState::Playing { .. },
+                    ..
+                } if !self.config.debug_step => self.config.playing_read_timeout(),
+                _ => None,
+            },
+            _ => self.config.open_read_timeout(),
+        }
+    }
+
+    /// whether the connection has gone longer than its read timeout without a message.
+    pub fn read_timed_out(&self, last_message: Instant, now: Instant) -> bool {
+        self.read_timeout()
+            .is_some_and(|timeout| now.duration_since(last_message) >= timeout)
+    }
+
+    /// closes a connection that's gone quiet for longer than its read timeout, such as one whose
+    /// client vanished without it being closed.
+    pub fn handle_read_timeout(&mut self) {
+        println!(
+            "closing connection {:?}, which went quiet for too long",
+            self.peer_addr
+        );
+        self.telemetry.read_timed_out();
+        self.conn.shutdown();
+        self.handle_close();
+    }
+
+    /// the connection every frame sent to the player is written through.
+    pub fn conn(&self) -> &ClientConn {
+        &self.conn
+    }
+
+    pub fn handle_message(&mut self, frame: &[u8]) {
+        let message = if self.checksums {
+            let Some(message) = checksum::verify(frame) else {
+                eprintln!(
+                    "discarding msg from client {} that failed its checksum",
+                    self.peer_addr
+                );
+                self.telemetry.protocol_error("failed_checksum");
+                return;
             };
+            message
+        } else {
+            frame
+        };
+        // paddle moves arrive every tick in lockstep, so would drown out everything else.
+        if !is_move_intent(message) {
+            println!("received msg from client {}: {:?}", self.peer_addr, message);
+        }
+        self.handle_client_message(message);
+    }
+
+    /// takes the player out of whatever they were waiting for or playing once their connection
+    /// has closed.
+    pub fn handle_close(&mut self) {
+        println!("connection {:?} closed", self.peer_addr);
+        if let Some(matched) = self.matched.take() {
+            // an opponent may have been found just before the connection closed, in
+            // which case they're told that the player left.
+            if !self.match_queue.leave(self.player_id) {
+                self.lobby_id = matched.recv().ok();
+            }
+        }
+        if let Some(tournament_id) = self.tournament_id {
+            self.lobby_id = self.tournaments.lobby_of(tournament_id, self.player_id);
+        }
+        if self.is_host_awaiting_readies() {
+            // the joiner takes over the lobby rather than it being closed on them.
+            self.leave_lobby();
+        }
+        if let Some(lobby_id) = &self.lobby_id {
+            // the lobby may have expired, and its id been taken by another since.
+            let lobby = self.lobbies.get(lobby_id).and_then(|shared| {
+                self.lobbies
+                    .close_if(lobby_id, &shared, |lobby| lobby.has_player(self.player_id))
+            });
+            if let Some(lobby) = lobby {
+                match lobby {
+                    Lobby::AwaitingJoin { event_log, .. } => {
+                        event_log.record(LobbyEvent::Left {
+                            player: self.player_id,
+                        });
+                    }
+                    Lobby::Joined {
+                        left_player_id,
+                        left_player_conn,
+                        right_player_conn,
+                        state,
+                        event_log,
+                        ..
+                    } => {
+                        event_log.record(LobbyEvent::Left {
+                            player: self.player_id,
+                        });
+                        let is_left_player = self.player_id == left_player_id;
+                        let opponent_conn = if is_left_player {
+                            right_player_conn
+                        } else {
+                            Some(left_player_conn)
+                        };
+                        match (state, opponent_conn) {
+                            // a practice lobby has nobody to tell.
+                            (_, None) => {}
+                            (LobbyState::AwaitingReadies { .. }, Some(opponent_conn)) => {
+                                Self::write_to_client(
+                                    AwaitingReadyServerMessage::OpponentLeft,
+                                    &opponent_conn,
+                                );
+                            }
+                            (LobbyState::Playing { .. }, Some(opponent_conn)) => {
+                                self.telemetry.dropped_mid_game();
+                                Self::write_to_client(
+                                    PlayingServerMessage::OpponentLeft,
+                                    &opponent_conn,
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        // this also gives the player's opponent the match, if they were in one.
+        if let Some(tournament_id) = self.tournament_id {
+            self.tournaments.leave(tournament_id, self.player_id);
+        }
+    }
+
+    pub fn handle_read_error(&self, err: ReadMessageError) {
+        match err {
+            // the connection is taken care of by `handle_close`.
+            ReadMessageError::Closed => {}
+            ReadMessageError::OversizedMessage => {
+                eprintln!("discarded oversized message from {:?}", self.peer_addr);
+                self.telemetry.protocol_error("oversized_message");
+            }
+            ReadMessageError::Io(err) => {
+                eprintln!("failed to read from {:?}: {err}", self.peer_addr)
+            }
         }
     }
 
     fn handle_client_message(&mut self, message: &[u8]) {
-        match self
+        // a client that lost track of its state can ask for it in any state.
+        if let Ok(ResyncClientMessage::ResyncRequest) = ResyncClientMessage::try_from(message) {
+            let state = self.protocol_state();
+            println!("resyncing client {} to {state:?}", self.peer_addr);
+            Self::write_to_client(ResyncServerMessage::FullState { state }, &self.conn);
+            return;
+        }
+        // as can one that wants every frame to end with a checksum.
+        if let Ok(ChecksumClientMessage::EnableChecksums) = ChecksumClientMessage::try_from(message)
+        {
+            println!("enabling checksums for client {}", self.peer_addr);
+            if self.checksums {
+                Self::write_to_client(ChecksumServerMessage::ChecksumsEnabled, &self.conn);
+            } else {
+                let mut frame = [0; MAX_SERVER_MESSAGE_SIZE + 1];
+                let n = Self::frame_message(&ChecksumServerMessage::ChecksumsEnabled, &mut frame);
+                self.conn.enable_checksums(&frame[..n]);
+                self.checksums = true;
+            }
+            return;
+        }
+        if let Some(matched) = self.matched.take() {
+            if self.match_queue.is_waiting(self.player_id) {
+                eprintln!("received message from client while queued for a match");
+                self.matched = Some(matched);
+                return;
+            }
+            // the opponent's handler sends the lobby's id once it has told both players about it,
+            // so it can only be moments away.
+            self.lobby_id = matched.recv().ok();
+        }
+        if let Some(tournament_id) = self.tournament_id {
+            self.lobby_id = self.tournaments.lobby_of(tournament_id, self.player_id);
+            if self.lobby_id.is_none() {
+                eprintln!("received message from client between tournament matches");
+                return;
+            }
+        }
+        if is_leave_lobby(message) {
+            self.leave_lobby();
+            return;
+        }
+        // a tournament match is over after one game. its result is recorded once the lobby has
+        // been released, since that advances the rest of the tournament.
+        let mut tournament_match_over = None;
+        let shared = self
             .lobby_id
             .as_ref()
-            .and_then(|lobby_id| self.lobbies.get_mut(lobby_id))
-        {
-            Some(mut lobby) => {
-                match lobby.value_mut() {
-                    Lobby::AwaitingJoin { .. } => {
-                        eprintln!("received message from client during invalid state")
+            .and_then(|lobby_id| self.lobbies.get(lobby_id));
+        // a lobby closed since it was looked up is released straight away.
+        let mut guard = shared
+            .as_ref()
+            .map(|shared| shared.lock())
+            .filter(|lobby| lobby.is_some());
+        match guard.as_deref_mut().and_then(Option::as_mut) {
+            Some(lobby) => {
+                match lobby {
+                    Lobby::AwaitingJoin { expires_at, .. } => {
+                        match AwaitingOpponentJoinClientMessage::try_from(message) {
+                            Ok(AwaitingOpponentJoinClientMessage::StillHere) => {
+                                *expires_at = Instant::now() + self.config.lobby_ttl();
+                            }
+                            // handled before the lobby was looked up.
+                            Ok(AwaitingOpponentJoinClientMessage::LeaveLobby) => {}
+                            Err(err) => {
+                                eprintln!(
+                                    "failed to deserialise client message {message:?}: {err}"
+                                );
+                                self.telemetry.invalid_message(&err);
+                            }
+                        }
                     }
                     Lobby::Joined {
+                        left_player_id,
                         left_player_conn,
+                        right_player_id,
                         right_player_conn,
+                        left_player_capabilities,
+                        right_player_capabilities,
+                        dimensions,
+                        ruleset,
+                        coop,
+                        rng,
+                        games_played,
+                        last_winner,
+                        score,
+                        match_started_at,
                         state,
+                        event_log,
+                        tournament_id,
                     } => {
-                        let is_left_player = self.stream.peer_addr().unwrap()
-                            == left_player_conn.peer_addr().unwrap();
+                        let is_left_player = self.player_id == *left_player_id;
+                        let dimensions = *dimensions;
+                        let Ruleset {
+                            tick_interval_ms,
+                            match_length,
+                            handicaps,
+                            modifier,
+                        } = *ruleset;
                         match state {
                             LobbyState::AwaitingReadies {
                                 left_player_ready,
                                 right_player_ready,
+                                ..
                             } => {
                                 let message = match AwaitingReadyClientMessage::try_from(message) {
                                     Ok(message) => message,
                                     Err(err) => {
-                                        eprintln!("failed to deserialise client message: {err}");
+                                        eprintln!(
+                                            "failed to deserialise client message {message:?}: {err}"
+                                        );
+                                        self.telemetry.invalid_message(&err);
                                         return;
                                     }
                                 };
                                 let is_ready = match message {
                                     AwaitingReadyClientMessage::Ready => true,
                                     AwaitingReadyClientMessage::Unready => false,
+                                    // handled before the lobby was looked up.
+                                    AwaitingReadyClientMessage::LeaveLobby => return,
                                 };
                                 if is_left_player {
                                     *left_player_ready = is_ready;
                                 } else {
                                     *right_player_ready = is_ready;
                                 }
+                                let player = self.player_id;
+                                event_log.record(if is_ready {
+                                    LobbyEvent::Readied { player }
+                                } else {
+                                    LobbyEvent::Unreadied { player }
+                                });
                                 Self::write_to_client(
                                     if is_ready {
                                         AwaitingReadyServerMessage::YouReadied
                                     } else {
                                         AwaitingReadyServerMessage::YouUnreadied
                                     },
-                                    &mut self.stream,
+                                    &self.conn,
                                 );
-                                if !(*left_player_ready && *right_player_ready) {
-                                    let opponent_conn = if is_left_player {
-                                        right_player_conn
-                                    } else {
-                                        left_player_conn
-                                    };
-                                    Self::write_to_client(
-                                        if is_ready {
-                                            AwaitingReadyServerMessage::OpponentReadied
-                                        } else {
-                                            AwaitingReadyServerMessage::OpponentUnreadied
-                                        },
-                                        opponent_conn,
-                                    );
+                                let opponent_conn = if is_left_player {
+                                    right_player_conn.as_ref()
+                                } else {
+                                    Some(&*left_player_conn)
+                                };
+                                // the wall of a practice lobby is always ready.
+                                let right_player_ready =
+                                    *right_player_ready || right_player_conn.is_none();
+                                if !(*left_player_ready && right_player_ready) {
+                                    if let Some(opponent_conn) = opponent_conn {
+                                        Self::write_to_client(
+                                            if is_ready {
+                                                AwaitingReadyServerMessage::OpponentReadied
+                                            } else {
+                                                AwaitingReadyServerMessage::OpponentUnreadied
+                                            },
+                                            opponent_conn,
+                                        );
+                                    }
                                 } else {
                                     // both players are ready. start the game.
                                     let paddle_starting_position = 0;
-                                    // GAME_HEIGHT / 2 - PADDLE_HEIGHT / 2;
+                                    // the wall can't serve, so the player always does. in a
+                                    // co-op game, the server serves to the players.
+                                    let serving = if *coop {
+                                        Side::Right
+                                    } else if right_player_conn.is_none() {
+                                        Side::Left
+                                    } else {
+                                        serving_side(rng, *last_winner, self.config.serve_rule)
+                                    };
                                     let game_state = GameState {
                                         left_paddle: paddle_starting_position,
                                         right_paddle: paddle_starting_position,
-                                        ball: Ball {
-                                            x: GAME_WIDTH / 2,
-                                            y: GAME_HEIGHT / 2,
-                                            moving_right: true,
-                                            moving_down: true,
-                                        },
+                                        ball: serve_ball_from(rng, dimensions, serving),
                                     };
+                                    event_log.record(LobbyEvent::GameStarted {
+                                        game: *games_played,
+                                        game_state: &game_state,
+                                    });
+                                    // replays can't show the partner's paddle, so co-op games
+                                    // aren't saved.
+                                    let replay =
+                                        self.config.replays.as_ref().filter(|_| !*coop).map(|_| {
+                                            ReplayRecorder::new(
+                                                dimensions,
+                                                handicaps,
+                                                modifier,
+                                                tick_interval_ms,
+                                                game_state.clone(),
+                                            )
+                                        });
+                                    match_started_at.get_or_insert(Instant::now());
                                     *state = LobbyState::Playing {
                                         game_state: game_state.clone(),
+                                        ticks: 0,
+                                        hits: 0,
+                                        serving,
+                                        served: false,
+                                        replay,
+                                        left_paddle_direction: MoveDirection::Stop,
+                                        right_paddle_direction: MoveDirection::Stop,
+                                        left_input_received: false,
+                                        right_input_received: false,
+                                        game_loop: None,
+                                        coop: coop.then(CoopGame::default),
                                     };
-                                    Self::write_to_client(
-                                        AwaitingReadyServerMessage::GameStarted,
-                                        &mut self.stream,
-                                    );
-                                    let opponent_conn = if is_left_player {
-                                        right_player_conn
+                                    // clients that predate handicaps can't join a handicapped
+                                    // lobby, so only they are sent the plain message.
+                                    let game_started_msg = if handicaps.is_none() {
+                                        AwaitingReadyServerMessage::GameStarted { tick_interval_ms }
                                     } else {
-                                        left_player_conn
+                                        AwaitingReadyServerMessage::HandicappedGameStarted {
+                                            tick_interval_ms,
+                                            handicaps,
+                                        }
                                     };
-                                    Self::write_to_client(
-                                        AwaitingReadyServerMessage::GameStarted,
-                                        opponent_conn,
-                                    );
-                                    let game_state_msg =
-                                        PlayingServerMessage::GameStateUpdated { game_state };
-                                    Self::write_to_client(game_state_msg.clone(), &mut self.stream);
-                                    Self::write_to_client(game_state_msg, opponent_conn);
-                                    let lobby_id = self.lobby_id.clone().unwrap();
-                                    let lobbies_clone = Arc::clone(&self.lobbies);
-                                    Builder::new()
-                                        .name(format!("ball_handler_{lobby_id}"))
-                                        .spawn(move || {
-                                            loop {
-                                                sleep(Duration::from_millis(100));
-                                                match lobbies_clone.get_mut(&lobby_id) {
-                                                    Some(mut entry) => match entry.value_mut() {
-                                                        Lobby::AwaitingJoin { .. } | Lobby::Joined { state: LobbyState::AwaitingReadies { .. }, .. } => {
-                                                            eprintln!("lobby is in the incorrect state to update game state");
-                                                            return;
-                                                        },
-                                                        Lobby::Joined { left_player_conn, right_player_conn, state: LobbyState::Playing { game_state } } => {
-                                                            let left_paddle = game_state.left_paddle;
-                                                            let right_paddle = game_state.right_paddle;
-                                                            let ball = &mut game_state.ball;
-                                                            if ball.x == 1 {
-                                                                if left_paddle > ball.y || left_paddle + PADDLE_HEIGHT <= ball.y {
-                                                                    Self::write_to_client(PlayingServerMessage::OpponentWon, left_player_conn);
-                                                                    Self::write_to_client(PlayingServerMessage::YouWon, right_player_conn);
-                                                                } else {
-                                                                    ball.moving_right = !ball.moving_right;
-                                                                }
-                                                            }
-                                                            if ball.x == GAME_WIDTH - 2 {
-                                                                if right_paddle > ball.y || right_paddle + PADDLE_HEIGHT <= ball.y {
-                                                                    Self::write_to_client(PlayingServerMessage::YouWon, left_player_conn);
-                                                                    Self::write_to_client(PlayingServerMessage::OpponentWon, right_player_conn);
-                                                                } else {
-                                                                    ball.moving_right = !ball.moving_right;
-                                                                }
-                                                            }
-                                                            if ball.y == 0 || ball.y == GAME_HEIGHT - 1 {
-                                                                ball.moving_down = !ball.moving_down;
-                                                            }
-                                                            if ball.moving_right {
-                                                                ball.x += 1;
-                                                            } else {
-                                                                ball.x -= 1;
-                                                            }
-                                                            if ball.moving_down {
-                                                                ball.y += 1;
-                                                            } else {
-                                                                ball.y -= 1;
-                                                            }
-                                                            let msg = PlayingServerMessage::GameStateUpdated { game_state: game_state.clone() };
-                                                            Self::write_to_client(msg.clone(), left_player_conn);
-                                                            Self::write_to_client(msg, right_player_conn);
-                                                        },
-                                                    },
-                                                    None => {
-                                                        println!("closing ball handler for lobby {lobby_id}");
-                                                        return;
-                                                    },
+                                    let game_state_msg = if *coop {
+                                        PlayingServerMessage::CoopStateUpdated {
+                                            game_state,
+                                            partner_paddle: 0,
+                                            score: 0,
+                                        }
+                                    } else {
+                                        PlayingServerMessage::GameStateUpdated { game_state }
+                                    };
+                                    let (own_capabilities, opponent_capabilities) =
+                                        if is_left_player {
+                                            (left_player_capabilities, right_player_capabilities)
+                                        } else {
+                                            (right_player_capabilities, left_player_capabilities)
+                                        };
+                                    // a game that could decide the match is either a
+                                    // tiebreak or one player's match point.
+                                    let stakes_msg = match_length.and_then(|match_length| {
+                                        match score.tiebreak(match_length, self.config.tiebreak) {
+                                            Some(Tiebreak::WinByTwo) => {
+                                                Some(PlayingServerMessage::Deuce)
+                                            }
+                                            Some(Tiebreak::SuddenDeath) => {
+                                                Some(PlayingServerMessage::SuddenDeath)
+                                            }
+                                            None => score.match_point(match_length).map(|_| {
+                                                PlayingServerMessage::MatchPoint {
+                                                    left_wins: score.left,
+                                                    right_wins: score.right,
                                                 }
+                                            }),
+                                        }
+                                    });
+                                    for (conn, capabilities) in [
+                                        (Some(&self.conn), own_capabilities),
+                                        (opponent_conn, opponent_capabilities),
+                                    ] {
+                                        let Some(conn) = conn else {
+                                            continue;
+                                        };
+                                        // each player is sent everything about the new game in
+                                        // one write, so that nothing can come between its
+                                        // messages.
+                                        let mut batch = FrameBatch::default();
+                                        if self.config.lockstep_timeout().is_some()
+                                            && capabilities.contains(Capabilities::LOCKSTEP)
+                                        {
+                                            batch.push(&AwaitingReadyServerMessage::LockstepGame);
+                                        }
+                                        batch.push(&game_started_msg);
+                                        batch.push(&game_state_msg);
+                                        if let Some(stakes_msg) = &stakes_msg {
+                                            if capabilities.contains(Capabilities::TIEBREAK) {
+                                                batch.push(stakes_msg);
                                             }
-                                        })
-                                        .unwrap();
+                                        }
+                                        // neither player of a co-op game serves.
+                                        if capabilities.contains(Capabilities::SERVE) && !*coop {
+                                            batch.push(&PlayingServerMessage::Serving {
+                                                side: serving,
+                                            });
+                                        }
+                                        batch.send(conn, Delivery::Reliable);
+                                    }
+                                    let game_loop = self.scheduler.spawn(GameLoop::new(
+                                        Arc::clone(shared.as_ref().unwrap()),
+                                        Arc::clone(&self.lobbies),
+                                        Arc::clone(&self.tournaments),
+                                        self.lobby_id.unwrap(),
+                                        *games_played,
+                                        tick_interval_ms,
+                                        dimensions,
+                                        Arc::clone(&self.config),
+                                        self.debug_stepper.clone(),
+                                        Arc::clone(&self.stats),
+                                        Arc::clone(&self.summary),
+                                        Arc::clone(&self.telemetry),
+                                        Arc::clone(&self.tick_timings),
+                                        self.scheduler.clone(),
+                                    ));
+                                    if let LobbyState::Playing { game_loop: id, .. } = state {
+                                        *id = Some(game_loop);
+                                    }
                                 }
                             }
-                            LobbyState::Playing { game_state } => {
+                            LobbyState::Playing {
+                                ticks,
+                                serving,
+                                served,
+                                replay,
+                                left_paddle_direction,
+                                right_paddle_direction,
+                                left_input_received,
+                                right_input_received,
+                                game_loop,
+                                ..
+                            } => {
+                                let side = if is_left_player {
+                                    Side::Left
+                                } else {
+                                    Side::Right
+                                };
                                 let message = match PlayingClientMessage::try_from(message) {
                                     Ok(message) => message,
                                     Err(err) => {
-                                        eprintln!("failed to deserialise client message: {err}");
+                                        eprintln!(
+                                            "failed to deserialise client message {message:?}: {err}"
+                                        );
+                                        self.telemetry.invalid_message(&err);
                                         return;
                                     }
                                 };
+                                let (opponent_id, opponent_conn) = if is_left_player {
+                                    (*right_player_id, right_player_conn.as_ref())
+                                } else {
+                                    (*left_player_id, Some(&*left_player_conn))
+                                };
                                 match message {
-                                    PlayingClientMessage::MovePaddle { pos } => {
+                                    PlayingClientMessage::MoveIntent { direction } => {
+                                        // the paddle is moved on the next tick of the game loop,
+                                        // in whichever direction the player sent last, however
+                                        // many they sent since the last tick.
                                         if is_left_player {
-                                            game_state.left_paddle = pos;
+                                            *left_paddle_direction = direction;
+                                            *left_input_received = true;
                                         } else {
-                                            game_state.right_paddle = pos;
+                                            *right_paddle_direction = direction;
+                                            *right_input_received = true;
+                                        }
+                                        // a game in lockstep ticks as soon as both inputs are in,
+                                        // rather than polling for them.
+                                        let inputs_received = *left_input_received
+                                            && (*right_input_received
+                                                || right_player_conn.is_none());
+                                        if inputs_received
+                                            && self.config.lockstep_timeout().is_some()
+                                        {
+                                            if let Some(game_loop) = game_loop {
+                                                self.scheduler.wake(*game_loop);
+                                            }
+                                        }
+                                    }
+                                    PlayingClientMessage::Serve => {
+                                        // only the serving player can launch the ball, which in a
+                                        // co-op game is the server.
+                                        if *serving == side && !*coop {
+                                            *served = true;
+                                        }
+                                    }
+                                    PlayingClientMessage::Resign => {
+                                        println!(
+                                            "player {} resigned in lobby {}",
+                                            self.player_id,
+                                            self.lobby_id.as_ref().unwrap(),
+                                        );
+                                        event_log.record(LobbyEvent::Resigned {
+                                            game: *games_played,
+                                            tick: *ticks,
+                                            player: self.player_id,
+                                        });
+                                        if let Some(replay) = replay.take() {
+                                            Self::spawn_save_replay(
+                                                Arc::clone(&self.config),
+                                                self.lobby_id.unwrap(),
+                                                *games_played,
+                                                replay.finish(ReplayResult::Resigned {
+                                                    tick: *ticks,
+                                                    side,
+                                                }),
+                                            );
+                                        }
+                                        Self::write_to_client(
+                                            PlayingServerMessage::OpponentWon,
+                                            &self.conn,
+                                        );
+                                        if let Some(opponent_conn) = opponent_conn {
+                                            Self::write_to_client(
+                                                PlayingServerMessage::OpponentResigned,
+                                                opponent_conn,
+                                            );
+                                        }
+                                        // return both players to the ready screen.
+                                        let ready_deadline =
+                                            Instant::now() + self.config.ready_timeout();
+                                        *state = LobbyState::awaiting_readies(ready_deadline);
+                                        *games_played += 1;
+                                        self.summary.game_played(*left_player_id, *right_player_id);
+                                        self.telemetry.game_played();
+                                        let winner = match side {
+                                            Side::Left => Side::Right,
+                                            Side::Right => Side::Left,
+                                        };
+                                        *last_winner = Some(winner);
+                                        let match_score = match match_length {
+                                            Some(match_length) => score.record_win(
+                                                winner,
+                                                match_length,
+                                                self.config.tiebreak,
+                                            ),
+                                            None => Some(MatchScore::single_game(winner)),
+                                        };
+                                        if let Some(match_score) = match_score {
+                                            let duration = match_started_at
+                                                .take()
+                                                .map_or(Duration::ZERO, |started_at| {
+                                                    started_at.elapsed()
+                                                });
+                                            let result = MatchResult::new(
+                                                self.lobby_id.unwrap(),
+                                                *left_player_id,
+                                                *right_player_id,
+                                                match_score,
+                                                dimensions,
+                                                *ruleset,
+                                                duration,
+                                            );
+                                            if let (Some(result), false) = (result, *coop) {
+                                                self.summary.match_finished(result.duration);
+                                                self.telemetry.match_finished(result.duration);
+                                                Self::spawn_record_match(
+                                                    Arc::clone(&self.stats),
+                                                    Arc::clone(&self.config),
+                                                    result,
+                                                );
+                                            }
+                                        }
+                                        match tournament_id {
+                                            Some(tournament_id) => {
+                                                tournament_match_over =
+                                                    Some((*tournament_id, opponent_id));
+                                            }
+                                            None => Self::spawn_ready_timeout(
+                                                &self.scheduler,
+                                                Arc::clone(shared.as_ref().unwrap()),
+                                                Arc::clone(&self.lobbies),
+                                                Arc::clone(&self.tournaments),
+                                                self.lobby_id.unwrap(),
+                                                ready_deadline,
+                                            ),
                                         }
                                     }
                                 }
-                                let reply = PlayingServerMessage::GameStateUpdated {
-                                    game_state: game_state.clone(),
-                                };
-                                Self::write_to_client(reply.clone(), &mut self.stream);
-                                let opponent_conn = if is_left_player {
-                                    right_player_conn
-                                } else {
-                                    left_player_conn
-                                };
-                                Self::write_to_client(reply, opponent_conn);
                             }
                         }
                     }
@@ -297,79 +846,1675 @@ impl TcpStreamHandler {
             }
             None => {
                 match AwaitingOpenClientMessage::try_from(message) {
-                    Ok(AwaitingOpenClientMessage::NewLobby) => {
-                        // create a new lobby.
-                        let lobby_id = self.lobby_id_generator.lock().unwrap().next_id();
-                        let mut stream = self.stream.try_clone().unwrap();
-                        let lobby = Lobby::AwaitingJoin {
-                            host_player_conn: stream.try_clone().unwrap(),
+                    Ok(AwaitingOpenClientMessage::NewLobby {
+                        tick_interval_ms,
+                        capabilities,
+                        custom_id,
+                        handicaps,
+                        modifier,
+                    }) => {
+                        // older clients can't choose the match length, so play the server's.
+                        let ruleset = Ruleset {
+                            tick_interval_ms,
+                            match_length: None,
+                            handicaps,
+                            modifier,
                         };
-                        // TODO: handle if a lobby already exists with this id (probably close any connections to the old lobby, or keep generating ids until one works).
-                        self.lobbies.insert(lobby_id.to_owned(), lobby);
-                        self.lobby_id = Some(lobby_id.to_owned());
-                        let reply = AwaitingNewLobbyServerMessage::NewLobbyCreated {
-                            lobby_id: &lobby_id,
+                        if let Some(lobby_id) =
+                            self.new_lobby(ruleset, capabilities, custom_id, false)
+                        {
+                            self.lobby_id = Some(lobby_id);
+                        }
+                    }
+                    Ok(AwaitingOpenClientMessage::NewLobbyWithRuleset {
+                        ruleset,
+                        capabilities,
+                        custom_id,
+                        cosmetics,
+                    }) => {
+                        self.conn.set_cosmetics(cosmetics);
+                        if let Some(lobby_id) =
+                            self.new_lobby(ruleset, capabilities, custom_id, false)
+                        {
+                            self.lobby_id = Some(lobby_id);
+                        }
+                    }
+                    Ok(AwaitingOpenClientMessage::NewCoopLobby {
+                        tick_interval_ms,
+                        capabilities,
+                        custom_id,
+                        cosmetics,
+                    }) => {
+                        self.conn.set_cosmetics(cosmetics);
+                        let ruleset = Ruleset {
+                            tick_interval_ms,
+                            match_length: None,
+                            handicaps: Handicaps::NONE,
+                            modifier: Modifier::Classic,
                         };
-                        Self::write_to_client(reply, &mut stream);
+                        if let Some(lobby_id) =
+                            self.new_lobby(ruleset, capabilities, custom_id, true)
+                        {
+                            self.lobby_id = Some(lobby_id);
+                        }
                     }
-                    Ok(AwaitingOpenClientMessage::JoinLobby { lobby_id }) => {
-                        match self.lobbies.entry(lobby_id.to_owned()) {
-                            Entry::Occupied(entry) => match entry.get() {
-                                Lobby::AwaitingJoin { host_player_conn } => {
-                                    let host_player_conn = host_player_conn.try_clone().unwrap();
-                                    let mut stream = self.stream.try_clone().unwrap();
+                    Ok(AwaitingOpenClientMessage::FindMatch {
+                        capabilities,
+                        cosmetics,
+                    }) => {
+                        self.conn.set_cosmetics(cosmetics);
+                        let (matched_tx, matched_rx) = channel();
+                        let player = QueuedPlayer {
+                            player_id: self.player_id,
+                            conn: Arc::clone(&self.conn),
+                            capabilities,
+                            matched: matched_tx,
+                        };
+                        match self.match_queue.join(player) {
+                            Some((host, joiner)) => {
+                                self.lobby_id = Some(self.seat_match(host, joiner));
+                            }
+                            None => {
+                                println!("player {} queued for a match", self.player_id);
+                                self.matched = Some(matched_rx);
+                            }
+                        }
+                    }
+                    Ok(AwaitingOpenClientMessage::Practice {
+                        tick_interval_ms,
+                        capabilities,
+                    }) => {
+                        self.lobby_id = Some(self.practice(tick_interval_ms, capabilities));
+                    }
+                    Ok(AwaitingOpenClientMessage::GetServerStatus) => {
+                        let (players_online, open_lobbies) = self.lobbies.all().into_iter().fold(
+                            (0, 0),
+                            |(players, open), (_, lobby)| match lobby.lock().as_ref() {
+                                Some(Lobby::AwaitingJoin { .. }) => (players + 1, open + 1),
+                                Some(Lobby::Joined {
+                                    right_player_conn, ..
+                                }) => (players + 1 + right_player_conn.is_some() as usize, open),
+                                None => (players, open),
+                            },
+                        );
+                        let reply = AwaitingServerStatusServerMessage::ServerStatus {
+                            players_online: u16::try_from(players_online).unwrap_or(u16::MAX),
+                            open_lobbies: u16::try_from(open_lobbies).unwrap_or(u16::MAX),
+                            version: SERVER_VERSION,
+                            uptime_secs: u32::try_from(self.started_at.elapsed().as_secs())
+                                .unwrap_or(u32::MAX),
+                        };
+                        Self::write_to_client(reply, &self.conn);
+                    }
+                    Ok(AwaitingOpenClientMessage::BrowseLobbies { page, capabilities }) => {
+                        let (lobbies, pages) = open_lobby_page(&self.lobbies, page, capabilities);
+                        for lobby in lobbies {
+                            let listed = AwaitingLobbyPageServerMessage::LobbyListed {
+                                lobby_id: lobby.lobby_id,
+                                tick_interval_ms: lobby.tick_interval_ms,
+                                age_secs: lobby.age_secs,
+                            };
+                            Self::write_to_client(listed, &self.conn);
+                        }
+                        Self::write_to_client(
+                            AwaitingLobbyPageServerMessage::LobbyPageEnd { page, pages },
+                            &self.conn,
+                        );
+                    }
+                    Ok(AwaitingOpenClientMessage::JoinLobby {
+                        lobby_id,
+                        capabilities,
+                        cosmetics,
+                    }) => {
+                        self.conn.set_cosmetics(cosmetics);
+                        let shared = self.lobbies.get(&lobby_id);
+                        let mut lobby = shared.as_ref().map(|shared| shared.lock());
+                        match lobby.as_deref_mut() {
+                            Some(Some(current)) => match current {
+                                Lobby::AwaitingJoin {
+                                    dimensions,
+                                    ruleset,
+                                    coop,
+                                    ..
+                                } if !self.config.can_serve(capabilities)
+                                    || !can_play(
+                                        capabilities,
+                                        *dimensions,
+                                        ruleset.handicaps,
+                                        *coop,
+                                    ) =>
+                                {
+                                    Self::write_to_client(
+                                        AwaitingJoinLobbyServerMessage::LobbyIncompatible,
+                                        &self.conn,
+                                    );
+                                    // TODO: shutdown connection
+                                }
+                                Lobby::AwaitingJoin {
+                                    host_player_id,
+                                    host_player_conn,
+                                    host_capabilities,
+                                    dimensions,
+                                    ruleset,
+                                    coop,
+                                    rng,
+                                    event_log,
+                                    ..
+                                } => {
+                                    let host_player_id = *host_player_id;
+                                    let host_capabilities = *host_capabilities;
+                                    let dimensions = *dimensions;
+                                    let ruleset = *ruleset;
+                                    let coop = *coop;
+                                    let rng = rng.clone();
+                                    let event_log = event_log.clone();
+                                    event_log.record(LobbyEvent::Joined {
+                                        player: self.player_id,
+                                    });
+                                    let host_player_conn = Arc::clone(host_player_conn);
+                                    let ready_deadline =
+                                        Instant::now() + self.config.ready_timeout();
                                     let lobby = Lobby::Joined {
-                                        left_player_conn: host_player_conn.try_clone().unwrap(),
-                                        right_player_conn: stream.try_clone().unwrap(),
-                                        state: LobbyState::AwaitingReadies {
-                                            left_player_ready: false,
-                                            right_player_ready: false,
-                                        },
+                                        left_player_id: host_player_id,
+                                        left_player_conn: Arc::clone(&host_player_conn),
+                                        right_player_id: self.player_id,
+                                        right_player_conn: Some(Arc::clone(&self.conn)),
+                                        left_player_capabilities: host_capabilities,
+                                        right_player_capabilities: capabilities,
+                                        dimensions,
+                                        ruleset,
+                                        coop,
+                                        rng,
+                                        games_played: 0,
+                                        last_winner: None,
+                                        score: MatchScore::default(),
+                                        match_started_at: None,
+                                        state: LobbyState::awaiting_readies(ready_deadline),
+                                        event_log,
+                                        tournament_id: None,
                                     };
-                                    self.lobby_id = Some(lobby_id.to_owned());
-                                    entry.replace_entry(lobby);
+                                    self.lobby_id = Some(lobby_id);
+                                    *current = lobby;
+                                    let ready_timeout_secs = self.config.ready_timeout_secs;
                                     Self::write_to_client(
-                                        AwaitingJoinLobbyServerMessage::JoinedLobby,
-                                        &mut stream,
+                                        AwaitingJoinLobbyServerMessage::JoinedLobby {
+                                            ready_timeout_secs,
+                                            dimensions,
+                                        },
+                                        &self.conn,
                                     );
-                                    let mut opponent_conn = host_player_conn;
                                     Self::write_to_client(
-                                        AwaitingOpponentJoinServerMessage::OpponentJoined,
-                                        &mut opponent_conn,
+                                        AwaitingOpponentJoinServerMessage::OpponentJoined {
+                                            ready_timeout_secs,
+                                        },
+                                        &host_player_conn,
+                                    );
+                                    // both players are told the rules before either can ready up.
+                                    for (conn, capabilities) in [
+                                        (&self.conn, capabilities),
+                                        (&host_player_conn, host_capabilities),
+                                    ] {
+                                        if capabilities.contains(Capabilities::RULESET) {
+                                            Self::write_to_client(
+                                                AwaitingReadyServerMessage::LobbyRuleset {
+                                                    ruleset,
+                                                },
+                                                conn,
+                                            );
+                                        }
+                                        if coop {
+                                            Self::write_to_client(
+                                                AwaitingReadyServerMessage::CoopLobby,
+                                                conn,
+                                            );
+                                        }
+                                    }
+                                    Self::exchange_cosmetics(&self.conn, &host_player_conn);
+                                    Self::spawn_ready_timeout(
+                                        &self.scheduler,
+                                        Arc::clone(shared.as_ref().unwrap()),
+                                        Arc::clone(&self.lobbies),
+                                        Arc::clone(&self.tournaments),
+                                        lobby_id,
+                                        ready_deadline,
                                     );
                                 }
                                 Lobby::Joined { .. } => {
                                     Self::write_to_client(
                                         AwaitingJoinLobbyServerMessage::LobbyFull,
-                                        &mut self.stream,
+                                        &self.conn,
                                     );
                                     // TODO: shutdown connection
                                 }
                             },
-                            Entry::Vacant(_) => {
+                            Some(None) | None => {
                                 Self::write_to_client(
                                     AwaitingJoinLobbyServerMessage::LobbyNotFound,
-                                    &mut self.stream,
+                                    &self.conn,
                                 );
                                 // TODO: shutdown connection
                             }
                         }
                     }
-                    Err(err) => eprintln!("failed to deserialize client message: {err}"),
+                    Ok(AwaitingOpenClientMessage::NewTournament {
+                        players,
+                        capabilities,
+                        cosmetics,
+                    }) => {
+                        self.conn.set_cosmetics(cosmetics);
+                        let conn = Arc::clone(&self.conn);
+                        self.tournament_id = Some(self.tournaments.create(
+                            Format::SingleElimination,
+                            players,
+                            self.player_id,
+                            conn,
+                            capabilities,
+                        ));
+                    }
+                    Ok(AwaitingOpenClientMessage::NewLeague {
+                        players,
+                        capabilities,
+                        cosmetics,
+                    }) => {
+                        self.conn.set_cosmetics(cosmetics);
+                        let conn = Arc::clone(&self.conn);
+                        self.tournament_id = Some(self.tournaments.create(
+                            Format::League,
+                            players,
+                            self.player_id,
+                            conn,
+                            capabilities,
+                        ));
+                    }
+                    Ok(AwaitingOpenClientMessage::JoinTournament {
+                        tournament_id,
+                        capabilities,
+                        cosmetics,
+                    }) => {
+                        self.conn.set_cosmetics(cosmetics);
+                        let conn = Arc::clone(&self.conn);
+                        if self
+                            .tournaments
+                            .join(tournament_id, self.player_id, conn, capabilities)
+                        {
+                            self.tournament_id = Some(tournament_id);
+                        }
+                    }
+                    Err(err) => {
+                        eprintln!("failed to deserialize client message {message:?}: {err}");
+                        self.telemetry.invalid_message(&err);
+                    }
                 }
             }
         };
+        if let Some((tournament_id, winner)) = tournament_match_over {
+            let lobby_id = self.lobby_id.take().unwrap();
+            self.lobbies
+                .close(&lobby_id, shared.as_ref().unwrap(), guard.as_mut().unwrap());
+            drop(guard);
+            self.tournaments
+                .finish_match(tournament_id, lobby_id, winner);
+        }
+    }
+
+    /// whether the player hosts a lobby that's been joined by another player, whose game hasn't
+    /// started yet.
+    fn is_host_awaiting_readies(&self) -> bool {
+        let Some(lobby_id) = self.lobby_id else {
+            return false;
+        };
+        self.lobbies.get(&lobby_id).is_some_and(|lobby| {
+            matches!(
+                lobby.lock().as_ref(),
+                Some(Lobby::Joined {
+                    left_player_id,
+                    right_player_conn: Some(_),
+                    state: LobbyState::AwaitingReadies { .. },
+                    tournament_id: None,
+                    ..
+                }) if *left_player_id == self.player_id
+            )
+        })
     }
 
-    fn write_to_client<T: Into<Vec<u8>>>(message: T, stream: &mut TcpStream) {
-        let mut message: Vec<u8> = message.into();
-        message.push(SERVER_MESSAGE_DELIMITER);
-        if let Some(err) = stream.write_all(message.as_slice()).err() {
+    /// takes the player out of their lobby before its game has started, returning them to the open
+    /// state. a lobby they were waiting in alone is closed, and one they'd been joined in is opened
+    /// again, hosted by whoever stayed. tournament matches can't be left.
+    fn leave_lobby(&mut self) {
+        let Some(lobby_id) = self.lobby_id else {
             eprintln!(
-                "failed to write message {:?} to client {}: {err}",
-                message,
-                stream.peer_addr().unwrap()
+                "player {} asked to leave a lobby outside of one",
+                self.player_id
+            );
+            return;
+        };
+        // the lobby closed before the player left it.
+        let Some(shared) = self.lobbies.get(&lobby_id) else {
+            self.lobby_id = None;
+            return;
+        };
+        let mut lobby = shared.lock();
+        let Some(current) = lobby
+            .as_ref()
+            .filter(|lobby| lobby.has_player(self.player_id))
+        else {
+            self.lobby_id = None;
+            return;
+        };
+        let reopened = match current {
+            Lobby::AwaitingJoin { event_log, .. } => {
+                event_log.record(LobbyEvent::Left {
+                    player: self.player_id,
+                });
+                None
+            }
+            Lobby::Joined {
+                left_player_id,
+                left_player_conn,
+                right_player_id,
+                right_player_conn,
+                left_player_capabilities,
+                right_player_capabilities,
+                dimensions,
+                ruleset,
+                coop,
+                rng,
+                event_log,
+                state: LobbyState::AwaitingReadies { .. },
+                tournament_id: None,
+                ..
+            } => {
+                event_log.record(LobbyEvent::Left {
+                    player: self.player_id,
+                });
+                let was_host = self.player_id == *left_player_id;
+                let (host_player_id, host_player_conn, host_capabilities) = if was_host {
+                    (
+                        *right_player_id,
+                        right_player_conn.clone(),
+                        *right_player_capabilities,
+                    )
+                } else {
+                    (
+                        *left_player_id,
+                        Some(Arc::clone(left_player_conn)),
+                        *left_player_capabilities,
+                    )
+                };
+                // a practice lobby has nobody left in it.
+                host_player_conn.map(|host_player_conn| {
+                    let now = Instant::now();
+                    let lobby = Lobby::AwaitingJoin {
+                        host_player_id,
+                        host_player_conn: Arc::clone(&host_player_conn),
+                        host_capabilities,
+                        dimensions: *dimensions,
+                        ruleset: *ruleset,
+                        coop: *coop,
+                        rng: rng.clone(),
+                        event_log: event_log.clone(),
+                        created_at: now,
+                        expires_at: now + self.config.lobby_ttl(),
+                    };
+                    (lobby, host_player_id, host_player_conn, was_host)
+                })
+            }
+            Lobby::Joined { .. } => {
+                eprintln!(
+                    "player {} can't leave lobby {lobby_id} mid game or tournament",
+                    self.player_id
+                );
+                return;
+            }
+        };
+        println!("player {} left lobby {lobby_id}", self.player_id);
+        self.lobby_id = None;
+        match reopened {
+            Some((awaiting_join, host_player_id, host_player_conn, promoted)) => {
+                *lobby = Some(awaiting_join);
+                drop(lobby);
+                let message = if promoted {
+                    AwaitingReadyServerMessage::PromotedToHost { lobby_id }
+                } else {
+                    AwaitingReadyServerMessage::OpponentLeftLobby
+                };
+                Self::write_to_client(message, &host_player_conn);
+                Self::spawn_lobby_expiry(
+                    &self.scheduler,
+                    shared,
+                    Arc::clone(&self.lobbies),
+                    lobby_id,
+                    host_player_id,
+                );
+            }
+            None => {
+                self.lobbies.close(&lobby_id, &shared, &mut lobby);
+            }
+        }
+    }
+
+    /// the protocol state the player is in, as far as their client needs to know to carry on from
+    /// it.
+    fn protocol_state(&mut self) -> ProtocolState {
+        if let Some(matched) = self.matched.take() {
+            if self.match_queue.is_waiting(self.player_id) {
+                self.matched = Some(matched);
+                return ProtocolState::AwaitingMatch;
+            }
+            self.lobby_id = matched.recv().ok();
+        }
+        if let Some(tournament_id) = self.tournament_id {
+            self.lobby_id = self.tournaments.lobby_of(tournament_id, self.player_id);
+            if self.lobby_id.is_none() {
+                return ProtocolState::AwaitingTournament;
+            }
+        }
+        let Some(shared) = self
+            .lobby_id
+            .as_ref()
+            .and_then(|lobby_id| self.lobbies.get(lobby_id))
+        else {
+            return ProtocolState::Open;
+        };
+        let lobby = shared.lock();
+        let Some(lobby) = lobby.as_ref() else {
+            return ProtocolState::Open;
+        };
+        match lobby {
+            Lobby::AwaitingJoin { .. } => ProtocolState::AwaitingOpponentJoin,
+            Lobby::Joined {
+                left_player_id,
+                right_player_conn,
+                state:
+                    LobbyState::AwaitingReadies {
+                        left_player_ready,
+                        right_player_ready,
+                        ..
+                    },
+                ..
+            } => {
+                // the wall of a practice lobby is always ready.
+                let right_player_ready = *right_player_ready || right_player_conn.is_none();
+                let (you_ready, opponent_ready) = if self.player_id == *left_player_id {
+                    (*left_player_ready, right_player_ready)
+                } else {
+                    (right_player_ready, *left_player_ready)
+                };
+                ProtocolState::AwaitingReady {
+                    you_ready,
+                    opponent_ready,
+                }
+            }
+            Lobby::Joined {
+                state: LobbyState::Playing { .. },
+                ..
+            } => ProtocolState::Playing,
+        }
+    }
+
+    /// creates a lobby hosted by this handler's client, played under the ruleset it asked for as
+    /// settled by the server, and for a co-op survival game if it asked for one. returns the
+    /// lobby's id, unless the client can't play the server's games, the id it asked for is taken,
+    /// or its address has created too many lobbies lately.
+    fn new_lobby(
+        &self,
+        requested: Ruleset,
+        capabilities: Capabilities,
+        custom_id: Option<LobbyId>,
+        coop: bool,
+    ) -> Option<LobbyId> {
+        if !self.config.can_serve(capabilities) {
+            println!(
+                "player {} asked for a lobby, but their client can't play in lockstep",
+                self.player_id
+            );
+            Self::write_to_client(AwaitingNewLobbyServerMessage::LobbyIncompatible, &self.conn);
+            return None;
+        }
+        if let Some(quota) = &self.lobby_quota {
+            if !quota.try_use(self.peer_addr.ip(), Instant::now()) {
+                println!(
+                    "player {} asked for a lobby, but their address has created too many",
+                    self.player_id
+                );
+                Self::write_to_client(AwaitingNewLobbyServerMessage::TooManyLobbies, &self.conn);
+                return None;
+            }
+        }
+        let lobby_id =
+            custom_id.unwrap_or_else(|| unused_lobby_id(&self.lobbies, &self.lobby_id_generator));
+        let mut dimensions = self.config.dimensions;
+        if dimensions.requires_wide_encoding()
+            && !capabilities.contains(Capabilities::WIDE_GAME_STATE)
+        {
+            // older clients can only play on fields that fit the narrow encoding.
+            println!("host of lobby {lobby_id} can't play wide games, using default dimensions");
+            dimensions = GameDimensions::DEFAULT;
+        }
+        let mut ruleset = settle_ruleset(
+            requested,
+            dimensions,
+            capabilities,
+            self.config.match_length,
+        );
+        if coop {
+            // co-op games are survived rather than won, so there's no match to keep score of.
+            ruleset.match_length = None;
+        }
+        if ruleset.handicaps != requested.handicaps.fit(dimensions) {
+            println!("host of lobby {lobby_id} can't play wide games, ignoring handicaps");
+        }
+        // the lobby is only made once its id is known to be free, so that a taken id doesn't
+        // leave an event log behind.
+        let opened = self.lobbies.open(lobby_id, || {
+            let seed = self.config.seed.unwrap_or_else(rand::random);
+            println!("creating lobby {lobby_id} with seed {seed}");
+            let event_log = EventLog::create(self.config.event_log_dir.as_deref(), lobby_id);
+            event_log.record(LobbyEvent::Created {
+                host: self.player_id,
+                dimensions,
+                ruleset,
+                seed,
+            });
+            Lobby::AwaitingJoin {
+                host_player_id: self.player_id,
+                host_player_conn: Arc::clone(&self.conn),
+                host_capabilities: capabilities,
+                dimensions,
+                ruleset,
+                coop,
+                rng: StdRng::seed_from_u64(seed),
+                event_log,
+                created_at: Instant::now(),
+                expires_at: Instant::now() + self.config.lobby_ttl(),
+            }
+        });
+        let Some(shared) = opened else {
+            println!(
+                "player {} asked for lobby id {lobby_id}, which is taken",
+                self.player_id
             );
+            Self::write_to_client(AwaitingNewLobbyServerMessage::IdTaken, &self.conn);
+            return None;
+        };
+        self.summary.lobbies_open(self.lobbies.len());
+        let reply = AwaitingNewLobbyServerMessage::NewLobbyCreated {
+            lobby_id,
+            dimensions,
+        };
+        Self::write_to_client(reply, &self.conn);
+        // the host can be warned straight away, which mustn't beat them to the lobby id.
+        Self::spawn_lobby_expiry(
+            &self.scheduler,
+            shared,
+            Arc::clone(&self.lobbies),
+            lobby_id,
+            self.player_id,
+        );
+        Some(lobby_id)
+    }
+
+    /// seats this handler's client in a new practice lobby, across the field from a wall, returning
+    /// the lobby's id. the client is told about it as if it had found a match.
+    fn practice(&self, tick_interval_ms: u16, capabilities: Capabilities) -> LobbyId {
+        let lobby_id = unused_lobby_id(&self.lobbies, &self.lobby_id_generator);
+        let seed = self.config.seed.unwrap_or_else(rand::random);
+        println!(
+            "player {} practicing in lobby {lobby_id} with seed {seed}",
+            self.player_id
+        );
+        let mut dimensions = self.config.dimensions;
+        if dimensions.requires_wide_encoding()
+            && !capabilities.contains(Capabilities::WIDE_GAME_STATE)
+        {
+            // older clients can only play on fields that fit the narrow encoding.
+            dimensions = GameDimensions::DEFAULT;
+        }
+        let ruleset = practice_ruleset(tick_interval_ms, dimensions);
+        let event_log = EventLog::create(self.config.event_log_dir.as_deref(), lobby_id);
+        event_log.record(LobbyEvent::Created {
+            host: self.player_id,
+            dimensions,
+            ruleset,
+            seed,
+        });
+        let ready_deadline = Instant::now() + self.config.ready_timeout();
+        let lobby = Lobby::Joined {
+            left_player_id: self.player_id,
+            left_player_conn: Arc::clone(&self.conn),
+            right_player_id: PlayerId::WALL,
+            right_player_conn: None,
+            left_player_capabilities: capabilities,
+            right_player_capabilities: Capabilities::NONE,
+            dimensions,
+            ruleset,
+            coop: false,
+            rng: StdRng::seed_from_u64(seed),
+            games_played: 0,
+            last_winner: None,
+            score: MatchScore::default(),
+            match_started_at: None,
+            state: LobbyState::awaiting_readies(ready_deadline),
+            event_log,
+            tournament_id: None,
+        };
+        let shared = self.lobbies.insert(lobby_id, lobby);
+        self.summary.lobbies_open(self.lobbies.len());
+        Self::write_to_client(
+            AwaitingMatchServerMessage::MatchFound {
+                lobby_id,
+                side: Side::Left,
+                ready_timeout_secs: self.config.ready_timeout_secs,
+                dimensions,
+            },
+            &self.conn,
+        );
+        Self::spawn_ready_timeout(
+            &self.scheduler,
+            shared,
+            Arc::clone(&self.lobbies),
+            Arc::clone(&self.tournaments),
+            lobby_id,
+            ready_deadline,
+        );
+        lobby_id
+    }
+
+    /// seats two players paired by the match queue in a new lobby, with the one who waited longer
+    /// as its host, returning the lobby's id. called by the handler of the player who joined the
+    /// queue last.
+    fn seat_match(&self, host: QueuedPlayer, joiner: QueuedPlayer) -> LobbyId {
+        let lobby_id = unused_lobby_id(&self.lobbies, &self.lobby_id_generator);
+        let mut dimensions = self.config.dimensions;
+        if dimensions.requires_wide_encoding()
+            && !(host.capabilities.contains(Capabilities::WIDE_GAME_STATE)
+                && joiner.capabilities.contains(Capabilities::WIDE_GAME_STATE))
+        {
+            // older clients can only play on fields that fit the narrow encoding.
+            dimensions = GameDimensions::DEFAULT;
+        }
+        let (shared, ready_deadline) = Self::open_match_lobby(
+            &self.lobbies,
+            &self.config,
+            lobby_id,
+            [
+                (host.player_id, &host.conn, host.capabilities),
+                (joiner.player_id, &joiner.conn, joiner.capabilities),
+            ],
+            dimensions,
+            None,
+            &self.summary,
+        );
+        for (player, side) in [(&host, Side::Left), (&joiner, Side::Right)] {
+            Self::write_to_client(
+                AwaitingMatchServerMessage::MatchFound {
+                    lobby_id,
+                    side,
+                    ready_timeout_secs: self.config.ready_timeout_secs,
+                    dimensions,
+                },
+                &player.conn,
+            );
+        }
+        Self::exchange_cosmetics(&host.conn, &joiner.conn);
+        // only now that the host has been told about the lobby, so that nothing else is sent to
+        // either player before it.
+        let _ = host.matched.send(lobby_id);
+        Self::spawn_ready_timeout(
+            &self.scheduler,
+            shared,
+            Arc::clone(&self.lobbies),
+            Arc::clone(&self.tournaments),
+            lobby_id,
+            ready_deadline,
+        );
+        lobby_id
+    }
+
+    /// opens a lobby with both of its players already in it, hosted by the first, for a match
+    /// arranged by the server rather than by the players. returns the lobby, along with the
+    /// deadline for the players to ready up by.
+    pub(crate) fn open_match_lobby(
+        lobbies: &Lobbies,
+        config: &ServerConfig,
+        lobby_id: LobbyId,
+        [(host_id, host_conn, host_capabilities), (joiner_id, joiner_conn, joiner_capabilities)]: [(
+            PlayerId,
+            &Arc<ClientConn>,
+            Capabilities,
+        ); 2],
+        dimensions: GameDimensions,
+        tournament_id: Option<LobbyId>,
+        summary: &DailySummary,
+    ) -> (Arc<SharedLobby>, Instant) {
+        let seed = config.seed.unwrap_or_else(rand::random);
+        println!("matched players {host_id} and {joiner_id} in lobby {lobby_id} with seed {seed}");
+        let event_log = EventLog::create(config.event_log_dir.as_deref(), lobby_id);
+        // players arranged into a match play by the server's rules.
+        let ruleset = Ruleset {
+            tick_interval_ms: MATCH_TICK_INTERVAL_MS,
+            match_length: config.match_length,
+            handicaps: Handicaps::NONE,
+            modifier: Modifier::Classic,
+        };
+        event_log.record(LobbyEvent::Created {
+            host: host_id,
+            dimensions,
+            ruleset,
+            seed,
+        });
+        event_log.record(LobbyEvent::Joined { player: joiner_id });
+        let ready_deadline = Instant::now() + config.ready_timeout();
+        let lobby = Lobby::Joined {
+            left_player_id: host_id,
+            left_player_conn: Arc::clone(host_conn),
+            right_player_id: joiner_id,
+            right_player_conn: Some(Arc::clone(joiner_conn)),
+            left_player_capabilities: host_capabilities,
+            right_player_capabilities: joiner_capabilities,
+            dimensions,
+            ruleset,
+            coop: false,
+            rng: StdRng::seed_from_u64(seed),
+            games_played: 0,
+            last_winner: None,
+            score: MatchScore::default(),
+            match_started_at: None,
+            state: LobbyState::awaiting_readies(ready_deadline),
+            event_log,
+            tournament_id,
+        };
+        let shared = lobbies.insert(lobby_id, lobby);
+        summary.lobbies_open(lobbies.len());
+        (shared, ready_deadline)
+    }
+
+    pub(crate) fn spawn_ready_timeout(
+        scheduler: &Scheduler,
+        lobby: Arc<SharedLobby>,
+        lobbies: Arc<Lobbies>,
+        tournaments: Arc<Tournaments>,
+        lobby_id: LobbyId,
+        ready_deadline: Instant,
+    ) {
+        scheduler.spawn(ReadyTimeout {
+            lobby,
+            lobbies,
+            tournaments,
+            lobby_id,
+            ready_deadline,
+        });
+    }
+
+    /// closes the lobby if the game hasn't started by the given deadline, which has passed,
+    /// notifying both players. a tournament match goes to the player who readied up, or to its
+    /// host if neither did.
+    fn close_lobby_after_ready_deadline(
+        lobby: &Arc<SharedLobby>,
+        lobbies: &Lobbies,
+        tournaments: &Arc<Tournaments>,
+        lobby_id: LobbyId,
+        ready_deadline: Instant,
+    ) {
+        // only close the lobby if it is still waiting for its players to ready up by this deadline.
+        let removed = lobbies.close_if(&lobby_id, lobby, |lobby| {
+            matches!(
+                lobby,
+                Lobby::Joined {
+                    state: LobbyState::AwaitingReadies {
+                        ready_deadline: deadline,
+                        ..
+                    },
+                    ..
+                } if *deadline == ready_deadline
+            )
+        });
+        if let Some(Lobby::Joined {
+            left_player_id,
+            left_player_conn,
+            right_player_id,
+            right_player_conn,
+            state,
+            event_log,
+            tournament_id,
+            ..
+        }) = removed
+        {
+            event_log.record(LobbyEvent::TimedOut);
+            println!("lobby {lobby_id} timed out waiting for players to ready up");
+            Self::write_to_client(AwaitingReadyServerMessage::LobbyTimedOut, &left_player_conn);
+            if let Some(right_player_conn) = right_player_conn {
+                Self::write_to_client(
+                    AwaitingReadyServerMessage::LobbyTimedOut,
+                    &right_player_conn,
+                );
+            }
+            if let Some(tournament_id) = tournament_id {
+                let winner = match state {
+                    LobbyState::AwaitingReadies {
+                        left_player_ready: false,
+                        right_player_ready: true,
+                        ..
+                    } => right_player_id,
+                    _ => left_player_id,
+                };
+                tournaments.finish_match(tournament_id, lobby_id, winner);
+            }
+        }
+    }
+
+    fn spawn_lobby_expiry(
+        scheduler: &Scheduler,
+        lobby: Arc<SharedLobby>,
+        lobbies: Arc<Lobbies>,
+        lobby_id: LobbyId,
+        host_player_id: PlayerId,
+    ) {
+        scheduler.spawn(LobbyExpiry {
+            lobby,
+            lobbies,
+            lobby_id,
+            host_player_id,
+            warned_expiry: None,
+        });
+    }
+
+    /// closes the lobby once it has gone without an opponent or any activity from its host for too
+    /// long, warning the host shortly beforehand. returns when to check again, or nothing once the
+    /// lobby has closed, an opponent has joined or the host has left. `warned_expiry` is the expiry
+    /// the host was last warned about, so that they're warned once per expiry.
+    fn close_lobby_once_expired(
+        lobby: &Arc<SharedLobby>,
+        lobbies: &Lobbies,
+        lobby_id: LobbyId,
+        host_player_id: PlayerId,
+        warned_expiry: &mut Option<Instant>,
+        now: Instant,
+    ) -> Option<Instant> {
+        loop {
+            let (expires_at, host_player_conn) = match lobby.lock().as_ref() {
+                Some(Lobby::AwaitingJoin {
+                    host_player_id: host,
+                    host_player_conn,
+                    expires_at,
+                    ..
+                }) if *host == host_player_id => (*expires_at, Arc::clone(host_player_conn)),
+                _ => return None,
+            };
+            let warn_at = expires_at.checked_sub(LOBBY_EXPIRY_WARNING).unwrap_or(now);
+            if now < warn_at {
+                return Some(warn_at);
+            }
+            if *warned_expiry != Some(expires_at) {
+                let remaining = expires_at.saturating_duration_since(now);
+                // rounded up, so that the host is never told there's less time than there is.
+                let seconds = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+                Self::write_to_client(
+                    AwaitingOpponentJoinServerMessage::LobbyExpiringSoon {
+                        seconds: u16::try_from(seconds).unwrap_or(MAX_U14).min(MAX_U14),
+                    },
+                    &host_player_conn,
+                );
+                *warned_expiry = Some(expires_at);
+            }
+            if now < expires_at {
+                return Some(expires_at);
+            }
+            // only close the lobby if its host hasn't been active since it was looked up.
+            let removed = lobbies.close_if(&lobby_id, lobby, |lobby| {
+                matches!(
+                    lobby,
+                    Lobby::AwaitingJoin {
+                        host_player_id: host,
+                        expires_at,
+                        ..
+                    } if *host == host_player_id && *expires_at <= now
+                )
+            });
+            if let Some(Lobby::AwaitingJoin {
+                host_player_conn,
+                event_log,
+                ..
+            }) = removed
+            {
+                event_log.record(LobbyEvent::Expired);
+                println!("lobby {lobby_id} expired waiting for an opponent");
+                Self::write_to_client(
+                    AwaitingOpponentJoinServerMessage::LobbyExpired,
+                    &host_player_conn,
+                );
+                // the host has nothing left to do on this connection.
+                host_player_conn.shutdown();
+                return None;
+            }
+        }
+    }
+
+    /// saves the replay of a finished game on another thread, so that neither the lobby nor its
+    /// game loop wait on the file system.
+    fn spawn_save_replay(config: Arc<ServerConfig>, lobby_id: LobbyId, game: u32, replay: Replay) {
+        Builder::new()
+            .name(format!("save_replay_{lobby_id}"))
+            .spawn(move || {
+                if let Some(replays) = &config.replays {
+                    if let Err(err) = replays.save(lobby_id, game, &replay) {
+                        eprintln!(
+                            "failed to save replay of game {game} in lobby {lobby_id}: {err}"
+                        );
+                    }
+                }
+            })
+            .unwrap();
+    }
+
+    /// records the result of a finished match off the thread playing the game, since it may be
+    /// written to a database, and exports it too if it should be.
+    fn spawn_record_match(
+        stats: Arc<dyn StatsStore>,
+        config: Arc<ServerConfig>,
+        result: MatchResult,
+    ) {
+        let lobby_id = result.lobby_id;
+        Builder::new()
+            .name(format!("record_match_{lobby_id}"))
+            .spawn(move || {
+                if let Err(err) = stats.record_match(&result) {
+                    eprintln!("failed to record match in lobby {lobby_id}: {err}");
+                }
+                if let Some(path) = &config.match_results_file {
+                    if let Err(err) = result.append_json(path) {
+                        eprintln!(
+                            "failed to export match in lobby {lobby_id} to {}: {err}",
+                            path.display()
+                        );
+                    }
+                }
+            })
+            .unwrap();
+    }
+
+    /// tells each of two players who have just been seated together how the other would like their
+    /// paddle drawn, if they said how they'd like their own drawn. a player who didn't say is
+    /// told as much, so that nothing is kept from whoever the other played before.
+    pub(crate) fn exchange_cosmetics(a: &ClientConn, b: &ClientConn) {
+        let (a_cosmetics, b_cosmetics) = (a.cosmetics(), b.cosmetics());
+        for (conn, own, opponent) in [(a, a_cosmetics, b_cosmetics), (b, b_cosmetics, a_cosmetics)]
+        {
+            if own.is_some() {
+                Self::write_to_client(
+                    AwaitingReadyServerMessage::OpponentCosmetics {
+                        cosmetics: opponent.unwrap_or(Cosmetics::NONE),
+                    },
+                    conn,
+                );
+            }
+        }
+    }
+
+    pub(crate) fn write_to_client<T: WriteInto>(message: T, conn: &ClientConn) {
+        let mut frame = [0; MAX_SERVER_MESSAGE_SIZE + 1];
+        let n = Self::frame_message(&message, &mut frame);
+        conn.send(&frame[..n], Delivery::Reliable);
+    }
+
+    /// sends every connection the same message, which is only serialized once, into a buffer on
+    /// the stack, so that nothing is allocated however many it's sent to.
+    pub fn broadcast<'a, T: WriteInto>(
+        message: &T,
+        conns: impl IntoIterator<Item = &'a ClientConn>,
+        delivery: Delivery,
+    ) {
+        let mut batch = FrameBatch::default();
+        batch.push(message);
+        for conn in conns {
+            batch.send(conn, delivery);
+        }
+    }
+
+    /// serializes a message followed by its delimiter, returning the length of the frame.
+    fn frame_message<T: WriteInto>(
+        message: &T,
+        frame: &mut [u8; MAX_SERVER_MESSAGE_SIZE + 1],
+    ) -> usize {
+        let n = message.write_into(frame);
+        frame[n] = SERVER_MESSAGE_DELIMITER;
+        n + 1
+    }
+}
+
+/// the most messages a client is sent at once, which is when a game starts.
+const MAX_BATCH_FRAMES: usize = 4;
+
+/// messages framed one after another in a buffer on the stack, to be sent to a client together in
+/// a single write. it can be cloned to send several clients the same messages, followed by any of
+/// their own, while serializing the shared ones only once.
+#[derive(Clone)]
+pub(crate) struct FrameBatch {
+    buf: [u8; MAX_BATCH_FRAMES * (MAX_SERVER_MESSAGE_SIZE + 1)],
+    /// where each frame ends in the buffer.
+    ends: [usize; MAX_BATCH_FRAMES],
+    frames: usize,
+}
+
+impl Default for FrameBatch {
+    fn default() -> Self {
+        Self {
+            buf: [0; MAX_BATCH_FRAMES * (MAX_SERVER_MESSAGE_SIZE + 1)],
+            ends: [0; MAX_BATCH_FRAMES],
+            frames: 0,
+        }
+    }
+}
+
+impl FrameBatch {
+    /// frames the message after those already in the batch. panics if the batch is full.
+    pub(crate) fn push<T: WriteInto>(&mut self, message: &T) {
+        let start = self.frames.checked_sub(1).map_or(0, |last| self.ends[last]);
+        let n = message.write_into(&mut self.buf[start..]);
+        self.buf[start + n] = SERVER_MESSAGE_DELIMITER;
+        self.ends[self.frames] = start + n + 1;
+        self.frames += 1;
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.frames
+    }
+
+    pub(crate) fn send(&self, conn: &ClientConn, delivery: Delivery) {
+        let mut frames: [&[u8]; MAX_BATCH_FRAMES] = [&[]; MAX_BATCH_FRAMES];
+        let mut start = 0;
+        for (frame, &end) in frames.iter_mut().zip(&self.ends[..self.frames]) {
+            *frame = &self.buf[start..end];
+            start = end;
+        }
+        conn.send_all(&frames[..self.frames], delivery);
+    }
+}
+
+/// whether the message asks to leave the player's lobby. it's accepted in either state before a
+/// game, since the host may not have heard that an opponent joined yet.
+fn is_leave_lobby(message: &[u8]) -> bool {
+    matches!(
+        AwaitingOpponentJoinClientMessage::try_from(message),
+        Ok(AwaitingOpponentJoinClientMessage::LeaveLobby)
+    ) || matches!(
+        AwaitingReadyClientMessage::try_from(message),
+        Ok(AwaitingReadyClientMessage::LeaveLobby)
+    )
+}
+
+fn is_move_intent(message: &[u8]) -> bool {
+    matches!(
+        PlayingClientMessage::try_from(message),
+        Ok(PlayingClientMessage::MoveIntent { .. })
+    )
+}
+
+/// when the tick after one due at `due` is due: a tick interval after it, however late it ran, so
+/// that late wake-ups don't push every tick after them back. a game that's fallen a whole tick
+/// behind carries on from `now` instead of rushing through the ticks it missed, which would jump the
+/// ball across the field. returns whether it had fallen behind.
+fn next_tick_at(due: Instant, tick_interval: Duration, now: Instant) -> (Instant, bool) {
+    let at = due + tick_interval;
+    if at <= now {
+        (now + tick_interval, true)
+    } else {
+        (at, false)
+    }
+}
+
+/// a game being played in a lobby, ticked until it's over.
+struct GameLoop {
+    /// the lobby the game is played in, which is all the game loop locks on each tick.
+    lobby: Arc<SharedLobby>,
+    lobbies: Arc<Lobbies>,
+    tournaments: Arc<Tournaments>,
+    lobby_id: LobbyId,
+    game_number: u32,
+    tick_interval_ms: u16,
+    dimensions: GameDimensions,
+    config: Arc<ServerConfig>,
+    debug_stepper: Option<Arc<DebugStepper>>,
+    stats: Arc<dyn StatsStore>,
+    summary: Arc<DailySummary>,
+    telemetry: Arc<Telemetry>,
+    tick_timings: Arc<TickTimings>,
+    scheduler: Scheduler,
+    /// each paddle hit speeds the ball up by shortening the ticks, until it reaches the highest
+    /// speed level.
+    speed_level: u8,
+    /// the direction each paddle was moved in on the last tick, so that a change is only recorded
+    /// once it's applied, rather than for every input a player sent in between.
+    applied_directions: [MoveDirection; 2],
+    waiting_for: GameWait,
+}
+
+/// what a game is waiting for before it next ticks.
+enum GameWait {
+    /// the serving player to serve, or the deadline to pass.
+    Serve { deadline: Instant },
+    /// the next tick to be due.
+    Tick { at: Instant },
+    /// both players to send an input since the last tick, or the deadline to pass, in lockstep
+    /// mode.
+    Inputs { deadline: Instant },
+}
+
+impl GameLoop {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        lobby: Arc<SharedLobby>,
+        lobbies: Arc<Lobbies>,
+        tournaments: Arc<Tournaments>,
+        lobby_id: LobbyId,
+        game_number: u32,
+        tick_interval_ms: u16,
+        dimensions: GameDimensions,
+        config: Arc<ServerConfig>,
+        debug_stepper: Option<Arc<DebugStepper>>,
+        stats: Arc<dyn StatsStore>,
+        summary: Arc<DailySummary>,
+        telemetry: Arc<Telemetry>,
+        tick_timings: Arc<TickTimings>,
+        scheduler: Scheduler,
+    ) -> Self {
+        let now = Instant::now();
+        // games being stepped by an admin are already paused until the first step.
+        let waiting_for = match debug_stepper {
+            Some(_) => GameWait::Tick { at: now },
+            None => GameWait::Serve {
+                deadline: now + config.serve_delay(),
+            },
+        };
+        Self {
+            lobby,
+            lobbies,
+            tournaments,
+            lobby_id,
+            game_number,
+            tick_interval_ms,
+            dimensions,
+            config,
+            debug_stepper,
+            stats,
+            summary,
+            telemetry,
+            tick_timings,
+            scheduler,
+            speed_level: 0,
+            applied_directions: [MoveDirection::Stop; 2],
+            waiting_for,
+        }
+    }
+
+    fn tick_interval(&self) -> Duration {
+        Duration::from_millis(
+            sped_up_tick_interval_ms(self.tick_interval_ms, self.speed_level).into(),
+        )
+    }
+
+    /// whether the ball has been served. returns true if the game is no longer being played,
+    /// leaving the next tick to notice.
+    fn served(&self) -> bool {
+        match self.lobby.lock().as_ref() {
+            Some(Lobby::Joined {
+                state: LobbyState::Playing { served, .. },
+                ..
+            }) => *served,
+            _ => true,
+        }
+    }
+
+    /// whether both players have sent an input since the last tick. returns true if the game is no
+    /// longer being played, leaving the next tick to notice.
+    fn inputs_received(&self) -> bool {
+        match self.lobby.lock().as_ref() {
+            // the wall of a practice lobby never sends inputs.
+            Some(Lobby::Joined {
+                right_player_conn,
+                state:
+                    LobbyState::Playing {
+                        left_input_received,
+                        right_input_received,
+                        ..
+                    },
+                ..
+            }) => *left_input_received && (*right_input_received || right_player_conn.is_none()),
+            _ => true,
+        }
+    }
+
+    /// advances the game by a tick that was due at `due`, telling both players about it. returns
+    /// false once the game is over, or no longer being played.
+    fn tick(&mut self, now: Instant, due: Instant) -> bool {
+        let lobbies = &self.lobbies;
+        let tournaments = &self.tournaments;
+        let lobby_id = self.lobby_id;
+        let game_number = self.game_number;
+        let dimensions = self.dimensions;
+        let config = &self.config;
+        let debug_stepper = &self.debug_stepper;
+        // the game state is updated while holding the lobby, but the lobby must be released
+        // before writing to either client. otherwise a slow client would hold up its opponent's
+        // messages, and anyone looking the lobby up.
+        let mut game_state_update = None;
+        let mut sped_up = None;
+        // whether the server's paddle of a co-op game missed, in which case it serves again.
+        let mut reserved = false;
+        let mut game_over = None;
+        let mut tournament_match_over = None;
+        let (left_player_conn, right_player_conn) = match self.lobby.lock().as_mut() {
+            Some(lobby) => match lobby {
+                Lobby::Joined {
+                    left_player_id,
+                    left_player_conn,
+                    right_player_id,
+                    right_player_conn,
+                    left_player_capabilities,
+                    right_player_capabilities,
+                    ruleset,
+                    rng,
+                    games_played,
+                    last_winner,
+                    score,
+                    match_started_at,
+                    state,
+                    event_log,
+                    tournament_id,
+                    ..
+                } if *games_played == game_number => {
+                    let (
+                        game_state,
+                        ticks,
+                        hits,
+                        served,
+                        replay,
+                        left_paddle_direction,
+                        right_paddle_direction,
+                        coop,
+                    ) = match state {
+                        LobbyState::Playing {
+                            game_state,
+                            ticks,
+                            hits,
+                            served,
+                            replay,
+                            left_paddle_direction,
+                            right_paddle_direction,
+                            left_input_received,
+                            right_input_received,
+                            coop,
+                            ..
+                        } => {
+                            *left_input_received = false;
+                            *right_input_received = false;
+                            (
+                                game_state,
+                                ticks,
+                                hits,
+                                served,
+                                replay,
+                                left_paddle_direction,
+                                right_paddle_direction,
+                                coop,
+                            )
+                        }
+                        LobbyState::AwaitingReadies { .. } => {
+                            eprintln!("lobby is in the incorrect state to update game state");
+                            return false;
+                        }
+                    };
+                    let directions = [
+                        (Side::Left, *left_player_id, *left_paddle_direction),
+                        (Side::Right, *right_player_id, *right_paddle_direction),
+                    ];
+                    for ((side, player, direction), applied) in
+                        directions.into_iter().zip(&mut self.applied_directions)
+                    {
+                        if *applied != direction {
+                            *applied = direction;
+                            event_log.record(LobbyEvent::Moved {
+                                game: game_number,
+                                tick: *ticks,
+                                player,
+                                direction,
+                            });
+                            if let Some(replay) = replay.as_mut() {
+                                replay.record_move(*ticks, side, direction);
+                            }
+                        }
+                    }
+                    let was_moving_right = game_state.ball.moving_right;
+                    let winner = match coop {
+                        // the right player's paddle is the partner's, and the server steers
+                        // the right paddle.
+                        Some(coop) => game_state.tick_coop(
+                            dimensions,
+                            &mut coop.partner_paddle,
+                            *left_paddle_direction,
+                            *right_paddle_direction,
+                            ai_direction(game_state, dimensions, *ticks, coop.score),
+                        ),
+                        None => game_state.tick(
+                            dimensions,
+                            ruleset.handicaps,
+                            ruleset.modifier,
+                            *left_paddle_direction,
+                            *right_paddle_direction,
+                        ),
+                    };
+                    *ticks += 1;
+                    // only a paddle can turn the ball around horizontally.
+                    if winner.is_none() && game_state.ball.moving_right != was_moving_right {
+                        if let (Some(coop), false) = (coop.as_mut(), was_moving_right) {
+                            coop.score = (coop.score + 1).min(MAX_U14);
+                        }
+                        *hits = hits.saturating_add(1);
+                        let speed_level = speed_level(*hits);
+                        if speed_level > self.speed_level {
+                            self.speed_level = speed_level;
+                            sped_up = Some((
+                                speed_level,
+                                left_player_capabilities.contains(Capabilities::SPEED_LEVEL),
+                                right_player_capabilities.contains(Capabilities::SPEED_LEVEL),
+                            ));
+                        }
+                    }
+                    if debug_stepper.is_some() {
+                        println!(
+                            r#"lobby {lobby_id} tick {ticks}: {{"left_paddle_direction":{},"right_paddle_direction":{},"game_state":{}}}"#,
+                            json::move_direction(*left_paddle_direction),
+                            json::move_direction(*right_paddle_direction),
+                            json::game_state(game_state),
+                        );
+                    }
+                    match (winner, coop) {
+                        // the server's paddle missed, so it serves the ball again once the
+                        // serve delay has passed.
+                        (Some(Side::Left), Some(coop)) => {
+                            game_state.ball = serve_ball_from(rng, dimensions, Side::Right);
+                            *served = false;
+                            reserved = true;
+                            game_state_update = Some(PlayingServerMessage::CoopStateUpdated {
+                                game_state: game_state.clone(),
+                                partner_paddle: coop.partner_paddle,
+                                score: coop.score,
+                            });
+                        }
+                        // the ball got past both players, who lose together.
+                        (Some(winner), Some(coop)) => {
+                            event_log.record(LobbyEvent::Survived {
+                                game: game_number,
+                                tick: *ticks,
+                                score: coop.score,
+                            });
+                            let ready_deadline = Instant::now() + config.ready_timeout();
+                            *state = LobbyState::awaiting_readies(ready_deadline);
+                            *games_played += 1;
+                            self.summary.game_played(*left_player_id, *right_player_id);
+                            self.telemetry.game_played();
+                            game_over = Some((winner, true, ready_deadline));
+                        }
+                        (Some(winner), None) => {
+                            event_log.record(LobbyEvent::GameWon {
+                                game: game_number,
+                                tick: *ticks,
+                                side: winner,
+                                player: match winner {
+                                    Side::Left => *left_player_id,
+                                    Side::Right => *right_player_id,
+                                },
+                            });
+                            if let Some(replay) = replay.take() {
+                                TcpStreamHandler::spawn_save_replay(
+                                    Arc::clone(config),
+                                    lobby_id,
+                                    game_number,
+                                    replay.finish(ReplayResult::Won {
+                                        tick: *ticks,
+                                        side: winner,
+                                    }),
+                                );
+                            }
+                            // return both players to the ready screen.
+                            let ready_deadline = Instant::now() + config.ready_timeout();
+                            *state = LobbyState::awaiting_readies(ready_deadline);
+                            *games_played += 1;
+                            self.summary.game_played(*left_player_id, *right_player_id);
+                            self.telemetry.game_played();
+                            *last_winner = Some(winner);
+                            let match_score = match ruleset.match_length {
+                                Some(match_length) => {
+                                    score.record_win(winner, match_length, config.tiebreak)
+                                }
+                                None => Some(MatchScore::single_game(winner)),
+                            };
+                            if let Some(match_score) = match_score {
+                                let duration = match_started_at
+                                    .take()
+                                    .map_or(Duration::ZERO, |started_at| started_at.elapsed());
+                                if let Some(result) = MatchResult::new(
+                                    lobby_id,
+                                    *left_player_id,
+                                    *right_player_id,
+                                    match_score,
+                                    self.dimensions,
+                                    *ruleset,
+                                    duration,
+                                ) {
+                                    self.summary.match_finished(result.duration);
+                                    self.telemetry.match_finished(result.duration);
+                                    TcpStreamHandler::spawn_record_match(
+                                        Arc::clone(&self.stats),
+                                        Arc::clone(config),
+                                        result,
+                                    );
+                                }
+                            }
+                            let winner_id = match winner {
+                                Side::Left => *left_player_id,
+                                Side::Right => *right_player_id,
+                            };
+                            game_over = Some((winner, false, ready_deadline));
+                            tournament_match_over =
+                                tournament_id.map(|tournament_id| (tournament_id, winner_id));
+                        }
+                        (None, Some(coop)) => {
+                            game_state_update = Some(PlayingServerMessage::CoopStateUpdated {
+                                game_state: game_state.clone(),
+                                partner_paddle: coop.partner_paddle,
+                                score: coop.score,
+                            })
+                        }
+                        (None, None) => {
+                            game_state_update = Some(PlayingServerMessage::GameStateUpdated {
+                                game_state: game_state.clone(),
+                            })
+                        }
+                    }
+                    (Arc::clone(left_player_conn), right_player_conn.clone())
+                }
+                // the game has ended (and possibly a new one started with its own game loop).
+                Lobby::AwaitingJoin { .. } | Lobby::Joined { .. } => {
+                    println!("closing ball handler for lobby {lobby_id}");
+                    return false;
+                }
+            },
+            None => {
+                println!("closing ball handler for lobby {lobby_id}");
+                return false;
+            }
+        };
+        // everything each player is sent this tick goes in one write, with the state serialized
+        // only once for both of them.
+        let mut batch = FrameBatch::default();
+        if let Some(message) = &game_state_update {
+            batch.push(message);
+        }
+        for (conn, side) in [
+            (Some(&*left_player_conn), Side::Left),
+            (right_player_conn.as_deref(), Side::Right),
+        ] {
+            let Some(conn) = conn else {
+                continue;
+            };
+            let mut batch = batch.clone();
+            if let Some((speed_level, tell_left_player, tell_right_player)) = sped_up {
+                let tell = match side {
+                    Side::Left => tell_left_player,
+                    Side::Right => tell_right_player,
+                };
+                if tell {
+                    batch.push(&PlayingServerMessage::BallSpedUp { speed_level });
+                }
+            }
+            if let Some((winner, coop, _)) = game_over {
+                batch.push(&if winner == side && !coop {
+                    PlayingServerMessage::YouWon
+                } else {
+                    PlayingServerMessage::OpponentWon
+                });
+            }
+            // a newer state makes one sent by itself useless to a client that's fallen behind.
+            let delivery = match (&game_state_update, batch.len()) {
+                (Some(_), 1) => Delivery::Superseding,
+                _ => Delivery::Reliable,
+            };
+            if !batch.is_empty() {
+                batch.send(conn, delivery);
+            }
+        }
+        let mut overran = false;
+        self.waiting_for = if reserved && debug_stepper.is_none() {
+            GameWait::Serve {
+                deadline: now + config.serve_delay(),
+            }
+        } else {
+            let (at, behind) = next_tick_at(due, self.tick_interval(), now);
+            overran = behind;
+            GameWait::Tick { at }
+        };
+        self.tick_timings
+            .record(now.duration_since(due), now.elapsed(), overran);
+        if let Some((_, _, ready_deadline)) = game_over {
+            println!("game {game_number} over in lobby {lobby_id}");
+            match tournament_match_over {
+                // the match is over, so the lobby is closed rather than played again.
+                Some((tournament_id, winner)) => {
+                    lobbies.close_if(&lobby_id, &self.lobby, |_| true);
+                    tournaments.finish_match(tournament_id, lobby_id, winner);
+                }
+                None => TcpStreamHandler::spawn_ready_timeout(
+                    &self.scheduler,
+                    Arc::clone(&self.lobby),
+                    Arc::clone(lobbies),
+                    Arc::clone(tournaments),
+                    lobby_id,
+                    ready_deadline,
+                ),
+            }
+            return false;
+        }
+        true
+    }
+}
+
+/// closes a lobby whose players haven't readied up by the deadline.
+struct ReadyTimeout {
+    lobby: Arc<SharedLobby>,
+    lobbies: Arc<Lobbies>,
+    tournaments: Arc<Tournaments>,
+    lobby_id: LobbyId,
+    ready_deadline: Instant,
+}
+
+impl Task for ReadyTimeout {
+    fn poll(&mut self, now: Instant) -> Option<Instant> {
+        if now < self.ready_deadline {
+            return Some(self.ready_deadline);
+        }
+        TcpStreamHandler::close_lobby_after_ready_deadline(
+            &self.lobby,
+            &self.lobbies,
+            &self.tournaments,
+            self.lobby_id,
+            self.ready_deadline,
+        );
+        None
+    }
+}
+
+/// closes a lobby nobody has joined once its host has been inactive for too long.
+struct LobbyExpiry {
+    lobby: Arc<SharedLobby>,
+    lobbies: Arc<Lobbies>,
+    lobby_id: LobbyId,
+    host_player_id: PlayerId,
+    warned_expiry: Option<Instant>,
+}
+
+impl Task for LobbyExpiry {
+    fn poll(&mut self, now: Instant) -> Option<Instant> {
+        TcpStreamHandler::close_lobby_once_expired(
+            &self.lobby,
+            &self.lobbies,
+            self.lobby_id,
+            self.host_player_id,
+            &mut self.warned_expiry,
+            now,
+        )
+    }
+}
+
+impl Task for GameLoop {
+    fn poll(&mut self, now: Instant) -> Option<Instant> {
+        loop {
+            match self.waiting_for {
+                GameWait::Serve { deadline } => {
+                    if now < deadline && !self.served() {
+                        return Some(deadline.min(now + SERVE_POLL_INTERVAL));
+                    }
+                    self.waiting_for = GameWait::Tick {
+                        at: now + self.tick_interval(),
+                    };
+                }
+                GameWait::Tick { at } => {
+                    // a game being stepped by an admin is due whenever they step it.
+                    let due = if let Some(debug_stepper) = &self.debug_stepper {
+                        if !debug_stepper.take_step(self.lobby_id) {
+                            return Some(now + DEBUG_STEP_POLL_INTERVAL);
+                        }
+                        now
+                    } else if now < at {
+                        return Some(at);
+                    } else {
+                        at
+                    };
+                    match self.config.lockstep_timeout() {
+                        Some(lockstep_timeout) => {
+                            self.waiting_for = GameWait::Inputs {
+                                deadline: now + lockstep_timeout,
+                            };
+                        }
+                        None if self.tick(now, due) => {}
+                        None => return None,
+                    }
+                }
+                GameWait::Inputs { deadline } => {
+                    // the game is woken once both inputs are in.
+                    if now < deadline && !self.inputs_received() {
+                        return Some(deadline);
+                    }
+                    // in lockstep, a tick is due once both inputs are in, whenever that is.
+                    if !self.tick(now, now) {
+                        return None;
+                    }
+                }
+            }
         }
     }
 }
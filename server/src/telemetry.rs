@@ -0,0 +1,348 @@
+//! anonymous counts of how the server's connections and games fare, which can be sent to the
+//! maintainers to show them where the netcode most needs work. nothing is sent unless the server
+//! was built with the `telemetry` feature and `TELEMETRY_ENDPOINT` is set, and nothing that could
+//! tell players apart is counted: no ids, addresses or lobbies, only how often things happen.
+
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use shared::DeserializeMessageError;
+
+/// how often the counts are sent, each time starting again from nothing.
+pub const REPORT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// counts what happens on the server until the counts are next taken. counting is cheap enough to
+/// be done whether or not the counts are ever sent.
+#[derive(Default)]
+pub struct Telemetry {
+    connections: AtomicU64,
+    /// connections that closed in the middle of a game, leaving their opponent without one.
+    dropped_mid_game: AtomicU64,
+    read_timeouts: AtomicU64,
+    games: AtomicU64,
+    matches: AtomicU64,
+    match_millis: AtomicU64,
+    /// how many of each kind of malformed message clients have sent.
+    protocol_errors: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Telemetry {
+    pub fn connection_opened(&self) {
+        self.connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dropped_mid_game(&self) {
+        self.dropped_mid_game.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn read_timed_out(&self) {
+        self.read_timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn game_played(&self) {
+        self.games.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn match_finished(&self, duration: Duration) {
+        self.matches.fetch_add(1, Ordering::Relaxed);
+        self.match_millis
+            .fetch_add(duration.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// counts a message that was too long to read, or failed its checksum.
+    pub fn protocol_error(&self, kind: &str) {
+        *self
+            .protocol_errors
+            .lock()
+            .unwrap()
+            .entry(kind.to_owned())
+            .or_default() += 1;
+    }
+
+    /// counts a message that couldn't be deserialised, by what was wrong with it.
+    pub fn invalid_message(&self, err: &DeserializeMessageError) {
+        // only the variant's name, leaving out whatever was in the message.
+        let debug = format!("{err:?}");
+        let variant = debug.split(['(', ' ']).next().unwrap_or_default();
+        self.protocol_error(&snake_case(variant));
+    }
+
+    /// the counts since they were last taken, starting them again from nothing.
+    pub fn take(&self) -> Counts {
+        Counts {
+            connections: self.connections.swap(0, Ordering::Relaxed),
+            dropped_mid_game: self.dropped_mid_game.swap(0, Ordering::Relaxed),
+            read_timeouts: self.read_timeouts.swap(0, Ordering::Relaxed),
+            games: self.games.swap(0, Ordering::Relaxed),
+            matches: self.matches.swap(0, Ordering::Relaxed),
+            match_millis: self.match_millis.swap(0, Ordering::Relaxed),
+            protocol_errors: std::mem::take(&mut *self.protocol_errors.lock().unwrap()),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub connections: u64,
+    pub dropped_mid_game: u64,
+    pub read_timeouts: u64,
+    pub games: u64,
+    pub matches: u64,
+    pub match_millis: u64,
+    pub protocol_errors: BTreeMap<String, u64>,
+}
+
+impl Counts {
+    /// the counts as the json object they're sent as, along with the server's version and how
+    /// long they were counted for.
+    pub fn to_json(&self, period: Duration) -> String {
+        let protocol_errors = self
+            .protocol_errors
+            .iter()
+            .map(|(kind, count)| format!(r#""{kind}":{count}"#))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            r#"{{"version":"{}","period_secs":{},"connections":{},"dropped_mid_game":{},"read_timeouts":{},"games":{},"matches":{},"match_millis":{},"protocol_errors":{{{protocol_errors}}}}}"#,
+            env!("CARGO_PKG_VERSION"),
+            period.as_secs(),
+            self.connections,
+            self.dropped_mid_game,
+            self.read_timeouts,
+            self.games,
+            self.matches,
+            self.match_millis,
+        )
+    }
+}
+
+/// where the counts are posted to, as json: an `http://` url, since the server has no tls.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Endpoint {
+    /// the host as given in the url, for the request's `Host` header.
+    pub host: String,
+    /// the host with its port, which is 80 unless the url gives another.
+    pub address: String,
+    pub path: String,
+}
+
+impl FromStr for Endpoint {
+    type Err = ();
+
+    fn from_str(url: &str) -> Result<Self, Self::Err> {
+        let rest = url.strip_prefix("http://").ok_or(())?;
+        let (host, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, "/"),
+        };
+        if host.is_empty() {
+            return Err(());
+        }
+        let has_port = host
+            .rsplit_once(':')
+            .is_some_and(|(_, port)| port.parse::<u16>().is_ok());
+        Ok(Self {
+            host: host.to_owned(),
+            address: if has_port {
+                host.to_owned()
+            } else {
+                format!("{host}:80")
+            },
+            path: path.to_owned(),
+        })
+    }
+}
+
+/// sends the counts to the endpoint every [`REPORT_INTERVAL`], for as long as the server runs.
+/// counts that fail to be sent are dropped rather than sent again with the next.
+#[cfg(feature = "telemetry")]
+pub fn spawn_reporter(telemetry: std::sync::Arc<Telemetry>, endpoint: Endpoint) {
+    std::thread::Builder::new()
+        .name("telemetry".to_owned())
+        .spawn(move || loop {
+            std::thread::sleep(REPORT_INTERVAL);
+            let body = telemetry.take().to_json(REPORT_INTERVAL);
+            if let Err(err) = post(&endpoint, &body) {
+                eprintln!("failed to send telemetry to {}: {err}", endpoint.host);
+            }
+        })
+        .unwrap();
+}
+
+/// posts the json body to the endpoint, succeeding if the endpoint responds with a 2xx status.
+#[cfg(feature = "telemetry")]
+fn post(endpoint: &Endpoint, body: &str) -> std::io::Result<()> {
+    use std::{
+        io::{self, BufRead, BufReader, Write},
+        net::{TcpStream, ToSocketAddrs},
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    let address = endpoint
+        .address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::other("no address found"))?;
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        endpoint.path,
+        endpoint.host,
+        body.len(),
+    )?;
+    let mut status_line = String::new();
+    BufReader::new(stream).read_line(&mut status_line)?;
+    match status_line.split_whitespace().nth(1) {
+        Some(status) if status.starts_with('2') => Ok(()),
+        _ => Err(io::Error::other(format!(
+            "unexpected response {:?}",
+            status_line.trim_end()
+        ))),
+    }
+}
+
+/// e.g. `invalid_byte_count` for `InvalidByteCount`.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, time::Duration};
+
+    use shared::{lobby_id::ParseLobbyIdError, DeserializeMessageError};
+
+    use crate::telemetry::{Counts, Endpoint, Telemetry};
+
+    #[test]
+    fn counts() {
+        let telemetry = Telemetry::default();
+        telemetry.connection_opened();
+        telemetry.connection_opened();
+        telemetry.game_played();
+        telemetry.dropped_mid_game();
+        telemetry.match_finished(Duration::from_millis(1500));
+        telemetry.protocol_error("oversized_message");
+        telemetry.invalid_message(&DeserializeMessageError::InvalidByteCount {
+            state_id: 1,
+            message_id: 2,
+            expected: 3,
+            actual: 4,
+        });
+        telemetry.invalid_message(&DeserializeMessageError::InvalidLobbyId(
+            ParseLobbyIdError::InvalidLength { actual: 0 },
+        ));
+        telemetry.invalid_message(&DeserializeMessageError::EmptyMessage);
+        telemetry.invalid_message(&DeserializeMessageError::EmptyMessage);
+        let counts = telemetry.take();
+        assert_eq!(
+            counts,
+            Counts {
+                connections: 2,
+                dropped_mid_game: 1,
+                read_timeouts: 0,
+                games: 1,
+                matches: 1,
+                match_millis: 1500,
+                protocol_errors: BTreeMap::from([
+                    ("empty_message".to_owned(), 2),
+                    ("invalid_byte_count".to_owned(), 1),
+                    ("invalid_lobby_id".to_owned(), 1),
+                    ("oversized_message".to_owned(), 1),
+                ]),
+            }
+        );
+        assert_eq!(
+            counts.to_json(Duration::from_secs(3600)),
+            format!(
+                r#"{{"version":"{}","period_secs":3600,"connections":2,"dropped_mid_game":1,"read_timeouts":0,"games":1,"matches":1,"match_millis":1500,"protocol_errors":{{"empty_message":2,"invalid_byte_count":1,"invalid_lobby_id":1,"oversized_message":1}}}}"#,
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+        // the next counts start from nothing.
+        assert_eq!(telemetry.take(), Counts::default());
+    }
+
+    #[test]
+    fn endpoints() {
+        assert_eq!(
+            "http://example.com/pong".parse(),
+            Ok(Endpoint {
+                host: "example.com".to_owned(),
+                address: "example.com:80".to_owned(),
+                path: "/pong".to_owned(),
+            })
+        );
+        assert_eq!(
+            "http://127.0.0.1:9000".parse(),
+            Ok(Endpoint {
+                host: "127.0.0.1:9000".to_owned(),
+                address: "127.0.0.1:9000".to_owned(),
+                path: "/".to_owned(),
+            })
+        );
+        assert_eq!("https://example.com/pong".parse::<Endpoint>(), Err(()));
+        assert_eq!("http:///pong".parse::<Endpoint>(), Err(()));
+    }
+
+    #[cfg(feature = "telemetry")]
+    #[test]
+    fn posts_counts() {
+        use std::{
+            io::{BufRead, BufReader, Read, Write},
+            net::TcpListener,
+            thread::spawn,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint: Endpoint = format!("http://{}/counts", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        let server = spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = Vec::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(len) = line.strip_prefix("Content-Length: ") {
+                    content_length = len.trim().parse().unwrap();
+                }
+                head.push(line);
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .unwrap();
+            (head, String::from_utf8(body).unwrap())
+        });
+        super::post(&endpoint, r#"{"games":1}"#).unwrap();
+        let (head, body) = server.join().unwrap();
+        assert_eq!(head[0], "POST /counts HTTP/1.1\r\n");
+        assert_eq!(body, r#"{"games":1}"#);
+    }
+}
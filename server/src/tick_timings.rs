@@ -0,0 +1,112 @@
+//! how punctually games tick, so that a server falling behind under load shows up in its metrics
+//! before its players see the ball stutter.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// the timings of every tick of every game, shared by their game loops.
+#[derive(Default)]
+pub struct TickTimings {
+    ticks: AtomicU64,
+    /// ticks after which their game had fallen a whole tick behind, and so skipped the ticks it
+    /// missed.
+    overruns: AtomicU64,
+    total_lateness_us: AtomicU64,
+    max_lateness_us: AtomicU64,
+    total_duration_us: AtomicU64,
+    max_duration_us: AtomicU64,
+}
+
+impl TickTimings {
+    /// counts a tick that ran `lateness` after it was due and took `duration` to run.
+    pub fn record(&self, lateness: Duration, duration: Duration, overran: bool) {
+        let lateness_us = u64::try_from(lateness.as_micros()).unwrap_or(u64::MAX);
+        let duration_us = u64::try_from(duration.as_micros()).unwrap_or(u64::MAX);
+        self.ticks.fetch_add(1, Ordering::Relaxed);
+        if overran {
+            self.overruns.fetch_add(1, Ordering::Relaxed);
+        }
+        self.total_lateness_us
+            .fetch_add(lateness_us, Ordering::Relaxed);
+        self.max_lateness_us
+            .fetch_max(lateness_us, Ordering::Relaxed);
+        self.total_duration_us
+            .fetch_add(duration_us, Ordering::Relaxed);
+        self.max_duration_us
+            .fetch_max(duration_us, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> TickStats {
+        TickStats {
+            ticks: self.ticks.load(Ordering::Relaxed),
+            overruns: self.overruns.load(Ordering::Relaxed),
+            total_lateness: Duration::from_micros(self.total_lateness_us.load(Ordering::Relaxed)),
+            max_lateness: Duration::from_micros(self.max_lateness_us.load(Ordering::Relaxed)),
+            total_duration: Duration::from_micros(self.total_duration_us.load(Ordering::Relaxed)),
+            max_duration: Duration::from_micros(self.max_duration_us.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TickStats {
+    /// how many ticks have been run.
+    pub ticks: u64,
+    /// how many ticks left their game a whole tick or more behind.
+    pub overruns: u64,
+    /// how long after they were due the ticks ran, all told.
+    pub total_lateness: Duration,
+    pub max_lateness: Duration,
+    /// how long the ticks took to run, including writing to their players, all told.
+    pub total_duration: Duration,
+    pub max_duration: Duration,
+}
+
+impl TickStats {
+    /// how late a tick ran on average, if any have run at all.
+    pub fn mean_lateness(&self) -> Option<Duration> {
+        self.mean(self.total_lateness)
+    }
+
+    /// how long a tick took to run on average, if any have run at all.
+    pub fn mean_duration(&self) -> Option<Duration> {
+        self.mean(self.total_duration)
+    }
+
+    fn mean(&self, total: Duration) -> Option<Duration> {
+        (self.ticks > 0)
+            .then(|| Duration::from_micros((total.as_micros() / self.ticks as u128) as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::tick_timings::{TickStats, TickTimings};
+
+    #[test]
+    fn tallies_ticks() {
+        let timings = TickTimings::default();
+        assert_eq!(timings.stats(), TickStats::default());
+        assert_eq!(timings.stats().mean_lateness(), None);
+        timings.record(Duration::from_micros(300), Duration::from_micros(40), false);
+        timings.record(Duration::from_millis(120), Duration::from_micros(80), true);
+        let stats = timings.stats();
+        assert_eq!(
+            stats,
+            TickStats {
+                ticks: 2,
+                overruns: 1,
+                total_lateness: Duration::from_micros(120_300),
+                max_lateness: Duration::from_millis(120),
+                total_duration: Duration::from_micros(120),
+                max_duration: Duration::from_micros(80),
+            }
+        );
+        assert_eq!(stats.mean_lateness(), Some(Duration::from_micros(60_150)));
+        assert_eq!(stats.mean_duration(), Some(Duration::from_micros(60)));
+    }
+}
@@ -0,0 +1,140 @@
+use std::time::{Duration, Instant};
+
+/// timers kept in a ring of slots, one per `resolution` of time. a timer further away than one
+/// turn of the ring shares its slot with nearer ones, and is skipped until its turn comes around.
+pub struct TimerWheel<T> {
+    started_at: Instant,
+    resolution: Duration,
+    /// the last slot whose timers have all been expired, counted from when the wheel started.
+    expired_up_to: u64,
+    slots: Vec<Vec<(u64, T)>>,
+    len: usize,
+}
+
+impl<T> TimerWheel<T> {
+    pub fn new(resolution: Duration, slots: usize) -> Self {
+        assert!(slots > 0, "a timer wheel needs at least one slot");
+        Self {
+            started_at: Instant::now(),
+            resolution,
+            expired_up_to: 0,
+            slots: (0..slots).map(|_| Vec::new()).collect(),
+            len: 0,
+        }
+    }
+
+    /// the slot that `at` falls in, counted from when the wheel started.
+    fn slot_of(&self, at: Instant) -> u64 {
+        (at.saturating_duration_since(self.started_at).as_nanos() / self.resolution.as_nanos())
+            as u64
+    }
+
+    /// the instant that the slot is done with.
+    fn end_of(&self, slot: u64) -> Instant {
+        let nanos = self.resolution.as_nanos() * (slot as u128 + 1);
+        self.started_at + Duration::from_nanos(nanos as u64)
+    }
+
+    /// sets a timer to go off at `at`, or as soon as possible if it's already passed.
+    pub fn schedule(&mut self, at: Instant, timer: T) {
+        let slot = self.slot_of(at).max(self.expired_up_to + 1);
+        let index = (slot % self.slots.len() as u64) as usize;
+        self.slots[index].push((slot, timer));
+        self.len += 1;
+    }
+
+    /// removes and returns every timer that has gone off by `now`. timers go off once the slot
+    /// they fall in is over, so never early.
+    pub fn expire(&mut self, now: Instant) -> Vec<T> {
+        let mut expired = Vec::new();
+        // the slot `now` is in isn't over yet.
+        let Some(up_to) = self.slot_of(now).checked_sub(1) else {
+            return expired;
+        };
+        // a long enough gap passes over every slot, and some more than once.
+        let first = (self.expired_up_to + 1).max(up_to.saturating_sub(self.slots.len() as u64 - 1));
+        for slot in first..=up_to {
+            if self.len == 0 {
+                break;
+            }
+            let index = (slot % self.slots.len() as u64) as usize;
+            let timers = &mut self.slots[index];
+            let mut i = 0;
+            while i < timers.len() {
+                if timers[i].0 <= up_to {
+                    expired.push(timers.swap_remove(i).1);
+                    self.len -= 1;
+                } else {
+                    i += 1;
+                }
+            }
+        }
+        self.expired_up_to = self.expired_up_to.max(up_to);
+        expired
+    }
+
+    /// when the next timer goes off, or at least when to check again for timers more than a turn
+    /// of the ring away. nothing if there are no timers.
+    pub fn next_expiry(&self) -> Option<Instant> {
+        if self.len == 0 {
+            return None;
+        }
+        let turn = self.expired_up_to + 1..=self.expired_up_to + self.slots.len() as u64;
+        let next = turn
+            .clone()
+            .find(|&slot| {
+                let index = (slot % self.slots.len() as u64) as usize;
+                self.slots[index].iter().any(|&(due, _)| due <= slot)
+            })
+            .unwrap_or(*turn.end());
+        Some(self.end_of(next))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::timer_wheel::TimerWheel;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn expires_timers_once_due() {
+        let mut wheel = TimerWheel::new(MS, 8);
+        let start = wheel.started_at;
+        wheel.schedule(start + 5 * MS, 'b');
+        wheel.schedule(start + 2 * MS, 'a');
+        // further away than a turn of the ring, in the same slot as the first timer.
+        wheel.schedule(start + 13 * MS, 'c');
+        assert_eq!(wheel.next_expiry(), Some(start + 3 * MS));
+        assert!(wheel.expire(start + 2 * MS).is_empty());
+        assert_eq!(wheel.expire(start + 3 * MS), ['a']);
+        assert_eq!(wheel.expire(start + 6 * MS), ['b']);
+        assert_eq!(wheel.next_expiry(), Some(start + 14 * MS));
+        assert!(wheel.expire(start + 13 * MS).is_empty());
+        assert_eq!(wheel.expire(start + 14 * MS), ['c']);
+        assert!(wheel.is_empty());
+        assert_eq!(wheel.next_expiry(), None);
+    }
+
+    #[test]
+    fn expires_late_timers_after_a_long_gap() {
+        let mut wheel = TimerWheel::new(MS, 4);
+        let start = wheel.started_at;
+        for (ms, timer) in [(1, 1), (6, 2), (9, 3), (30, 4)] {
+            wheel.schedule(start + ms * MS, timer);
+        }
+        let mut expired = wheel.expire(start + 20 * MS);
+        expired.sort();
+        assert_eq!(expired, [1, 2, 3]);
+        // timers that are already due go off as soon as the wheel is next checked.
+        wheel.schedule(start, 5);
+        assert_eq!(wheel.expire(start + 22 * MS), [5]);
+        assert_eq!(wheel.expire(start + 31 * MS), [4]);
+    }
+}
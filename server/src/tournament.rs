@@ -0,0 +1,782 @@
+use std::sync::Arc;
+
+use dashmap::{
+    mapref::entry::{Entry, OccupiedEntry},
+    DashMap,
+};
+use shared::{
+    client_msg::Capabilities,
+    game_state::{GameDimensions, Side},
+    server_msg::AwaitingTournamentServerMessage,
+    tournament::{
+        league_opponent, league_rounds, opponent_seats, rounds, Bracket, BracketSeat, Standing,
+        Standings,
+    },
+    LobbyId,
+};
+
+use crate::{
+    client_conn::ClientConn,
+    config::ServerConfig,
+    lobby::{unused_lobby_id, Lobbies, PlayerId},
+    lobby_id_generator::LobbyIdGenerator,
+    scheduler::Scheduler,
+    summary::DailySummary,
+    tcp_stream_handler::TcpStreamHandler,
+};
+
+pub struct TournamentPlayer {
+    pub player_id: PlayerId,
+    pub conn: Arc<ClientConn>,
+    pub capabilities: Capabilities,
+    pub result: BracketSeat,
+    /// the player's record, if the tournament is a league.
+    pub standing: Standing,
+    /// the lobby of the match the player is currently playing, if any.
+    pub lobby_id: Option<LobbyId>,
+    /// the player's connection closed after the tournament started. their seat is kept, so that
+    /// the bracket still shows how far they got.
+    pub left: bool,
+}
+
+impl TournamentPlayer {
+    fn new(player_id: PlayerId, conn: Arc<ClientConn>, capabilities: Capabilities) -> Self {
+        Self {
+            player_id,
+            conn,
+            capabilities,
+            result: BracketSeat::default(),
+            standing: Standing::default(),
+            lobby_id: None,
+            left: false,
+        }
+    }
+
+    /// whether the player can be sent tournament messages, i.e. they're connected and not in the
+    /// middle of a match.
+    fn is_listening(&self) -> bool {
+        !self.left && self.lobby_id.is_none()
+    }
+
+    /// whether the player is waiting to be paired for their next match.
+    fn is_waiting(&self) -> bool {
+        !self.result.eliminated && self.lobby_id.is_none()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    SingleElimination,
+    /// a round robin, in which every player plays every other player once.
+    League,
+}
+
+/// a single elimination tournament or a league. in either, seats are paired in a fixed order, so
+/// every match is known once every seat is taken, which is when the tournament starts.
+pub struct Tournament {
+    format: Format,
+    players: u8,
+    dimensions: GameDimensions,
+    /// only vacated by players leaving before the tournament starts.
+    seats: Vec<Option<TournamentPlayer>>,
+    /// the league round being played, or the number of rounds played once it's over.
+    round: u8,
+    /// whether the matches of the current league round have been opened.
+    round_started: bool,
+}
+
+impl Tournament {
+    pub fn new(format: Format, players: u8, dimensions: GameDimensions) -> Self {
+        Self {
+            format,
+            players,
+            dimensions,
+            seats: (0..players).map(|_| None).collect(),
+            round: 0,
+            round_started: false,
+        }
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.seats.iter().all(Option::is_some)
+    }
+
+    pub fn players_joined(&self) -> u16 {
+        self.seats.iter().flatten().count() as u16
+    }
+
+    /// seats the player in the first vacant seat, returning it, or nothing if the tournament has
+    /// started.
+    pub fn join(
+        &mut self,
+        player_id: PlayerId,
+        conn: Arc<ClientConn>,
+        capabilities: Capabilities,
+    ) -> Option<u8> {
+        let seat = self.seats.iter().position(Option::is_none)?;
+        self.seats[seat] = Some(TournamentPlayer::new(player_id, conn, capabilities));
+        Some(seat as u8)
+    }
+
+    /// vacates the player's seat if the tournament hasn't started, or eliminates them if it has.
+    /// returns whether that changed the bracket, which it doesn't for a player already
+    /// eliminated. in a league, the player forfeits their remaining matches instead, which always
+    /// changes the standings. a player leaving in the middle of a match loses it.
+    pub fn leave(&mut self, player_id: PlayerId) -> bool {
+        let is_started = self.is_started();
+        let is_league = self.format == Format::League;
+        let Some(seat) = self
+            .seats
+            .iter_mut()
+            .find(|seat| seat.as_ref().is_some_and(|p| p.player_id == player_id))
+        else {
+            return false;
+        };
+        match seat {
+            Some(player) if is_started => {
+                player.left = true;
+                player.standing.left = true;
+                let changed = !std::mem::replace(&mut player.result.eliminated, true) || is_league;
+                // the match must be over before the next is chosen, or a league could pair the
+                // player again.
+                if let Some(lobby_id) = player.lobby_id {
+                    let opponent = self
+                        .seats
+                        .iter()
+                        .flatten()
+                        .find(|p| p.lobby_id == Some(lobby_id) && p.player_id != player_id)
+                        .map(|opponent| opponent.player_id);
+                    return self.finish_match(lobby_id, opponent.unwrap_or(player_id)) || changed;
+                }
+                changed
+            }
+            _ => {
+                *seat = None;
+                true
+            }
+        }
+    }
+
+    /// whether every player has left, so that there's nobody left to play or watch it.
+    pub fn is_abandoned(&self) -> bool {
+        self.seats.iter().flatten().all(|player| player.left)
+    }
+
+    pub fn player(&self, player_id: PlayerId) -> Option<&TournamentPlayer> {
+        self.seats
+            .iter()
+            .flatten()
+            .find(|player| player.player_id == player_id)
+    }
+
+    fn player_mut(&mut self, seat: u8) -> &mut TournamentPlayer {
+        self.seats[seat as usize].as_mut().unwrap()
+    }
+
+    pub fn bracket(&self) -> Bracket {
+        let mut bracket = Bracket::default();
+        for (bracket_seat, seat) in bracket.seats.iter_mut().zip(&self.seats) {
+            if let Some(player) = seat {
+                *bracket_seat = player.result;
+            }
+        }
+        bracket
+    }
+
+    pub fn standings(&self) -> Standings {
+        let mut standings = Standings {
+            round: self.round,
+            ..Standings::default()
+        };
+        for (standing, seat) in standings.seats.iter_mut().zip(&self.seats) {
+            if let Some(player) = seat {
+                *standing = player.standing;
+            }
+        }
+        standings
+    }
+
+    /// how the tournament stands, for the players not in a match.
+    pub fn update(&self) -> AwaitingTournamentServerMessage {
+        match self.format {
+            Format::SingleElimination => AwaitingTournamentServerMessage::BracketUpdated {
+                bracket: self.bracket(),
+            },
+            Format::League => AwaitingTournamentServerMessage::StandingsUpdated {
+                standings: self.standings(),
+            },
+        }
+    }
+
+    /// the seat that won every round, or topped the league's table, once the tournament is over.
+    pub fn champion(&self) -> Option<u8> {
+        if self.format == Format::League {
+            if self.round < league_rounds(self.players) {
+                return None;
+            }
+            // a player who left can't win, however well they did before leaving.
+            return self.standings().table(self.players)[..self.players as usize]
+                .iter()
+                .copied()
+                .find(|&seat| !self.seats[seat as usize].as_ref().unwrap().left);
+        }
+        let rounds = rounds(self.players);
+        (0..self.players).find(|&seat| {
+            self.seats[seat as usize]
+                .as_ref()
+                .is_some_and(|player| player.result.wins == rounds)
+        })
+    }
+
+    /// records the result of the match played in the given lobby, returning whether it was still
+    /// being played. a match can end in several ways at once (e.g. the game is won as a player
+    /// leaves), and only the first counts.
+    pub fn finish_match(&mut self, lobby_id: LobbyId, winner: PlayerId) -> bool {
+        let mut finished = false;
+        for player in self.seats.iter_mut().flatten() {
+            if player.lobby_id == Some(lobby_id) {
+                player.lobby_id = None;
+                if player.player_id == winner {
+                    player.result.wins += 1;
+                    player.standing.wins += 1;
+                } else {
+                    player.result.eliminated = true;
+                    player.standing.losses += 1;
+                }
+                finished = true;
+            }
+        }
+        finished
+    }
+
+    /// gives a walkover to every player whose next opponent can no longer come, having been
+    /// eliminated along with the rest of their part of the bracket, then returns the seats of each
+    /// pair of players that should play their next match now.
+    pub fn advance(&mut self) -> Vec<(u8, u8)> {
+        if !self.is_started() {
+            return Vec::new();
+        }
+        if self.format == Format::League {
+            return self.advance_league();
+        }
+        let rounds = rounds(self.players);
+        loop {
+            let bracket = self.bracket();
+            let walkover = (0..self.players).find(|&seat| {
+                let result = bracket.seats[seat as usize];
+                self.seats[seat as usize].as_ref().unwrap().is_waiting()
+                    && result.wins < rounds
+                    && opponent_seats(seat, result.wins)
+                        .all(|opponent| bracket.seats[opponent as usize].eliminated)
+            });
+            match walkover {
+                Some(seat) => self.player_mut(seat).result.wins += 1,
+                None => break,
+            }
+        }
+        let mut pairs = Vec::new();
+        for seat in 0..self.players {
+            let player = self.seats[seat as usize].as_ref().unwrap();
+            let wins = player.result.wins;
+            if !player.is_waiting() || wins >= rounds {
+                continue;
+            }
+            let opponent = opponent_seats(seat, wins).find(|&opponent| {
+                let opponent = self.seats[opponent as usize].as_ref().unwrap();
+                opponent.is_waiting() && opponent.result.wins == wins
+            });
+            if let Some(opponent) = opponent.filter(|&opponent| seat < opponent) {
+                pairs.push((seat, opponent));
+            }
+        }
+        pairs
+    }
+
+    /// once every match of the current round is over, starts the next round, giving a walkover to
+    /// everyone whose opponent has left, then returns the seats of each pair of players that
+    /// should play in it.
+    fn advance_league(&mut self) -> Vec<(u8, u8)> {
+        if self.seats.iter().flatten().any(|p| p.lobby_id.is_some()) {
+            return Vec::new();
+        }
+        loop {
+            if std::mem::take(&mut self.round_started) {
+                self.round += 1;
+            }
+            if self.round >= league_rounds(self.players) {
+                return Vec::new();
+            }
+            self.round_started = true;
+            let mut pairs = Vec::new();
+            for seat in 0..self.players {
+                let Some(opponent) = league_opponent(seat, self.round, self.players)
+                    .filter(|&opponent| seat < opponent)
+                else {
+                    continue;
+                };
+                match (self.player_mut(seat).left, self.player_mut(opponent).left) {
+                    (false, false) => pairs.push((seat, opponent)),
+                    (false, true) => self.walkover(seat, opponent),
+                    (true, false) => self.walkover(opponent, seat),
+                    // a match between two players who have both left counts for neither.
+                    (true, true) => {}
+                }
+            }
+            if !pairs.is_empty() {
+                return pairs;
+            }
+        }
+    }
+
+    fn walkover(&mut self, winner: u8, loser: u8) {
+        self.player_mut(winner).standing.wins += 1;
+        self.player_mut(loser).standing.losses += 1;
+    }
+}
+
+/// the tournaments being played on the server, along with what's needed to open lobbies for their
+/// matches.
+/// unlike lobbies, a tournament's messages are written while holding its entry, since several
+/// threads can advance the same tournament at once (e.g. two of its matches finishing together),
+/// and its players must receive every update in the order it happened. only the tournaments
+/// sharing its shard of the map wait on a slow client.
+pub struct Tournaments {
+    tournaments: DashMap<LobbyId, Tournament>,
+    lobbies: Arc<Lobbies>,
+    lobby_id_generator: Arc<LobbyIdGenerator>,
+    config: Arc<ServerConfig>,
+    /// runs the ready timeouts of the lobbies opened for matches.
+    scheduler: Scheduler,
+    /// counts the lobbies opened for matches, for the summary logged each day.
+    summary: Arc<DailySummary>,
+}
+
+impl Tournaments {
+    pub fn new(
+        lobbies: Arc<Lobbies>,
+        lobby_id_generator: Arc<LobbyIdGenerator>,
+        config: Arc<ServerConfig>,
+        scheduler: Scheduler,
+        summary: Arc<DailySummary>,
+    ) -> Self {
+        Self {
+            tournaments: DashMap::new(),
+            lobbies,
+            lobby_id_generator,
+            config,
+            scheduler,
+            summary,
+        }
+    }
+
+    /// creates a tournament with its creator in the first seat, returning its id.
+    pub fn create(
+        &self,
+        format: Format,
+        players: u8,
+        player_id: PlayerId,
+        conn: Arc<ClientConn>,
+        capabilities: Capabilities,
+    ) -> LobbyId {
+        let (tournament_id, entry) = loop {
+            // ids are reused once the generator's count wraps around, by which time a tournament
+            // may still be holding one.
+            let tournament_id = self.lobby_id_generator.next_id();
+            if let Entry::Vacant(entry) = self.tournaments.entry(tournament_id) {
+                break (tournament_id, entry);
+            }
+        };
+        println!("creating tournament {tournament_id} ({format:?}) for {players} players");
+        let mut dimensions = self.config.dimensions;
+        if dimensions.requires_wide_encoding()
+            && !capabilities.contains(Capabilities::WIDE_GAME_STATE)
+        {
+            // older clients can only play on fields that fit the narrow encoding.
+            dimensions = GameDimensions::DEFAULT;
+        }
+        let mut tournament = Tournament::new(format, players, dimensions);
+        let seat = tournament
+            .join(player_id, Arc::clone(&conn), capabilities)
+            .unwrap();
+        let tournament = entry.insert(tournament);
+        let reply = AwaitingTournamentServerMessage::JoinedTournament {
+            tournament_id,
+            seat,
+            players,
+        };
+        TcpStreamHandler::write_to_client(reply, &conn);
+        Self::players_joined(&tournament);
+        tournament_id
+    }
+
+    /// seats the player in the tournament, returning whether they could be.
+    pub fn join(
+        self: &Arc<Self>,
+        tournament_id: LobbyId,
+        player_id: PlayerId,
+        conn: Arc<ClientConn>,
+        capabilities: Capabilities,
+    ) -> bool {
+        let Entry::Occupied(mut entry) = self.tournaments.entry(tournament_id) else {
+            TcpStreamHandler::write_to_client(
+                AwaitingTournamentServerMessage::TournamentNotFound,
+                &conn,
+            );
+            return false;
+        };
+        let tournament = entry.get_mut();
+        if tournament.dimensions.requires_wide_encoding()
+            && !capabilities.contains(Capabilities::WIDE_GAME_STATE)
+        {
+            TcpStreamHandler::write_to_client(
+                AwaitingTournamentServerMessage::TournamentIncompatible,
+                &conn,
+            );
+            return false;
+        }
+        let Some(seat) = tournament.join(player_id, Arc::clone(&conn), capabilities) else {
+            TcpStreamHandler::write_to_client(
+                AwaitingTournamentServerMessage::TournamentFull,
+                &conn,
+            );
+            return false;
+        };
+        println!("player {player_id} joined tournament {tournament_id} in seat {seat}");
+        let reply = AwaitingTournamentServerMessage::JoinedTournament {
+            tournament_id,
+            seat,
+            players: tournament.players,
+        };
+        TcpStreamHandler::write_to_client(reply, &conn);
+        Self::players_joined(tournament);
+        self.advance(tournament_id, entry);
+        true
+    }
+
+    /// the lobby of the match the player is playing in the tournament, if any.
+    pub fn lobby_of(&self, tournament_id: LobbyId, player_id: PlayerId) -> Option<LobbyId> {
+        self.tournaments
+            .get(&tournament_id)?
+            .player(player_id)?
+            .lobby_id
+    }
+
+    /// takes the player out of the tournament, giving the match they'd have played next to their
+    /// opponent.
+    pub fn leave(self: &Arc<Self>, tournament_id: LobbyId, player_id: PlayerId) {
+        let Entry::Occupied(mut entry) = self.tournaments.entry(tournament_id) else {
+            return;
+        };
+        let tournament = entry.get_mut();
+        let was_started = tournament.is_started();
+        println!("player {player_id} left tournament {tournament_id}");
+        if !tournament.leave(player_id) {
+            return;
+        }
+        if tournament.is_abandoned() {
+            println!("closing abandoned tournament {tournament_id}");
+            entry.remove();
+        } else if was_started {
+            self.advance(tournament_id, entry);
+        } else {
+            Self::players_joined(tournament);
+        }
+    }
+
+    /// records the winner of the match played in the given lobby, which has been closed.
+    pub fn finish_match(
+        self: &Arc<Self>,
+        tournament_id: LobbyId,
+        lobby_id: LobbyId,
+        winner: PlayerId,
+    ) {
+        let Entry::Occupied(mut entry) = self.tournaments.entry(tournament_id) else {
+            return;
+        };
+        if entry.get_mut().finish_match(lobby_id, winner) {
+            println!("player {winner} won their match in tournament {tournament_id}");
+            self.advance(tournament_id, entry);
+        }
+    }
+
+    /// opens the lobbies of every match that can now be played, telling everyone not in a match
+    /// how the bracket or standings stand, or who won once it's over.
+    fn advance(
+        self: &Arc<Self>,
+        tournament_id: LobbyId,
+        mut entry: OccupiedEntry<LobbyId, Tournament>,
+    ) {
+        let tournament = entry.get_mut();
+        if !tournament.is_started() {
+            return;
+        }
+        let pairs = tournament.advance();
+        let champion = tournament.champion();
+        // a league's final table is worth seeing, whereas the final bracket just shows the winner.
+        if champion.is_none() || tournament.format == Format::League {
+            let update = tournament.update();
+            for player in tournament.seats.iter().flatten() {
+                if player.is_listening() {
+                    TcpStreamHandler::write_to_client(update.clone(), &player.conn);
+                }
+            }
+        }
+        if let Some(seat) = champion {
+            println!("tournament {tournament_id} won by seat {seat}");
+            for player in tournament.seats.iter().flatten() {
+                if player.is_listening() {
+                    TcpStreamHandler::write_to_client(
+                        AwaitingTournamentServerMessage::TournamentWon { seat },
+                        &player.conn,
+                    );
+                }
+            }
+            entry.remove();
+            return;
+        }
+        for (host_seat, joiner_seat) in pairs {
+            self.open_match(tournament_id, tournament, host_seat, joiner_seat);
+        }
+    }
+
+    /// seats two players in a new lobby for their match, with the lower seat as its host.
+    fn open_match(
+        self: &Arc<Self>,
+        tournament_id: LobbyId,
+        tournament: &mut Tournament,
+        host_seat: u8,
+        joiner_seat: u8,
+    ) {
+        let lobby_id = unused_lobby_id(&self.lobbies, &self.lobby_id_generator);
+        let dimensions = tournament.dimensions;
+        let host = tournament.player_mut(host_seat);
+        host.lobby_id = Some(lobby_id);
+        let host = (host.player_id, Arc::clone(&host.conn), host.capabilities);
+        let joiner = tournament.player_mut(joiner_seat);
+        joiner.lobby_id = Some(lobby_id);
+        let joiner = (
+            joiner.player_id,
+            Arc::clone(&joiner.conn),
+            joiner.capabilities,
+        );
+        let (lobby, ready_deadline) = TcpStreamHandler::open_match_lobby(
+            &self.lobbies,
+            &self.config,
+            lobby_id,
+            [(host.0, &host.1, host.2), (joiner.0, &joiner.1, joiner.2)],
+            dimensions,
+            Some(tournament_id),
+            &self.summary,
+        );
+        for ((_, conn, _), side) in [(&host, Side::Left), (&joiner, Side::Right)] {
+            let message = AwaitingTournamentServerMessage::MatchReady {
+                lobby_id,
+                side,
+                ready_timeout_secs: self.config.ready_timeout_secs,
+                dimensions,
+            };
+            TcpStreamHandler::write_to_client(message, conn);
+        }
+        TcpStreamHandler::exchange_cosmetics(&host.1, &joiner.1);
+        TcpStreamHandler::spawn_ready_timeout(
+            &self.scheduler,
+            lobby,
+            Arc::clone(&self.lobbies),
+            Arc::clone(self),
+            lobby_id,
+            ready_deadline,
+        );
+    }
+
+    fn players_joined(tournament: &Tournament) {
+        let players = tournament.players_joined();
+        for player in tournament.seats.iter().flatten() {
+            TcpStreamHandler::write_to_client(
+                AwaitingTournamentServerMessage::PlayersJoined { players },
+                &player.conn,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        sync::Arc,
+    };
+
+    use shared::{client_msg::Capabilities, game_state::GameDimensions, LobbyId};
+
+    use crate::{
+        client_conn::ClientConn,
+        lobby::PlayerId,
+        tournament::{Format, Tournament},
+    };
+
+    /// a full tournament, with player n in seat n.
+    fn started_tournament(format: Format, players: u8) -> Tournament {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut tournament = Tournament::new(format, players, GameDimensions::DEFAULT);
+        for player in 0..players {
+            let conn = Arc::new(ClientConn::new(
+                TcpStream::connect(listener.local_addr().unwrap()).unwrap(),
+            ));
+            assert_eq!(
+                tournament.join(PlayerId(player as u64), conn, Capabilities::ALL),
+                Some(player)
+            );
+        }
+        assert!(tournament.is_started());
+        tournament
+    }
+
+    /// opens every match that can be played, returning the lobby ids given to them.
+    fn open_matches(tournament: &mut Tournament) -> Vec<(u8, u8, LobbyId)> {
+        let lobby_ids = ["AAAA", "BBBB", "CCCC", "DDDD"].map(|id| id.parse().unwrap());
+        let pairs = tournament.advance();
+        pairs
+            .into_iter()
+            .zip(lobby_ids)
+            .map(|((host, joiner), lobby_id)| {
+                tournament.player_mut(host).lobby_id = Some(lobby_id);
+                tournament.player_mut(joiner).lobby_id = Some(lobby_id);
+                (host, joiner, lobby_id)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn seats_and_leaves_before_start() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let conn = || {
+            Arc::new(ClientConn::new(
+                TcpStream::connect(listener.local_addr().unwrap()).unwrap(),
+            ))
+        };
+        let mut tournament = Tournament::new(Format::SingleElimination, 2, GameDimensions::DEFAULT);
+        assert_eq!(
+            tournament.join(PlayerId(0), conn(), Capabilities::ALL),
+            Some(0)
+        );
+        assert!(tournament.advance().is_empty());
+        assert!(tournament.leave(PlayerId(0)));
+        assert!(tournament.is_abandoned());
+        assert_eq!(
+            tournament.join(PlayerId(1), conn(), Capabilities::ALL),
+            Some(0)
+        );
+        assert_eq!(
+            tournament.join(PlayerId(2), conn(), Capabilities::ALL),
+            Some(1)
+        );
+        assert_eq!(
+            tournament.join(PlayerId(3), conn(), Capabilities::ALL),
+            None
+        );
+        assert_eq!(tournament.players_joined(), 2);
+    }
+
+    #[test]
+    fn plays_each_round_in_turn() {
+        let mut tournament = started_tournament(Format::SingleElimination, 4);
+        let matches = open_matches(&mut tournament);
+        assert_eq!(
+            matches.iter().map(|m| (m.0, m.1)).collect::<Vec<_>>(),
+            [(0, 1), (2, 3)]
+        );
+        assert!(tournament.finish_match(matches[0].2, PlayerId(1)));
+        // only the first result of a match counts.
+        assert!(!tournament.finish_match(matches[0].2, PlayerId(0)));
+        // the winner waits for the other match to finish.
+        assert!(open_matches(&mut tournament).is_empty());
+        assert!(tournament.finish_match(matches[1].2, PlayerId(2)));
+        let matches = open_matches(&mut tournament);
+        assert_eq!((matches[0].0, matches[0].1), (1, 2));
+        assert_eq!(tournament.champion(), None);
+        assert!(tournament.finish_match(matches[0].2, PlayerId(2)));
+        assert!(open_matches(&mut tournament).is_empty());
+        assert_eq!(tournament.champion(), Some(2));
+        let bracket = tournament.bracket();
+        assert_eq!(bracket.seats.map(|seat| seat.wins)[..4], [0, 1, 2, 0]);
+        assert_eq!(
+            bracket.seats.map(|seat| seat.eliminated)[..4],
+            [true, true, false, true]
+        );
+    }
+
+    #[test]
+    fn walkovers() {
+        let mut tournament = started_tournament(Format::SingleElimination, 8);
+        let matches = open_matches(&mut tournament);
+        assert_eq!(matches.len(), 4);
+        // seats 2 and 3 both leave, so the winner of seats 0 and 1 goes through to the final.
+        assert!(tournament.finish_match(matches[1].2, PlayerId(2)));
+        assert!(tournament.leave(PlayerId(2)));
+        assert!(!tournament.leave(PlayerId(3)));
+        assert!(open_matches(&mut tournament).is_empty());
+        assert!(tournament.finish_match(matches[0].2, PlayerId(0)));
+        assert!(open_matches(&mut tournament).is_empty());
+        assert_eq!(tournament.bracket().seats[0].wins, 2);
+        assert!(tournament.finish_match(matches[2].2, PlayerId(4)));
+        assert!(tournament.finish_match(matches[3].2, PlayerId(7)));
+        let matches = open_matches(&mut tournament);
+        assert_eq!((matches[0].0, matches[0].1), (4, 7));
+        // seat 4 leaving mid-match gives it to seat 7.
+        assert!(tournament.leave(PlayerId(4)));
+        assert!(!tournament.finish_match(matches[0].2, PlayerId(7)));
+        assert_eq!(tournament.bracket().seats[7].wins, 2);
+        let matches = open_matches(&mut tournament);
+        assert_eq!((matches[0].0, matches[0].1), (0, 7));
+        assert!(!tournament.is_abandoned());
+    }
+
+    #[test]
+    fn league_rounds_and_table() {
+        let mut tournament = started_tournament(Format::League, 3);
+        // with three players, one sits out each round.
+        let matches = open_matches(&mut tournament);
+        assert_eq!((matches.len(), matches[0].0, matches[0].1), (1, 1, 2));
+        assert!(tournament.finish_match(matches[0].2, PlayerId(2)));
+        let matches = open_matches(&mut tournament);
+        assert_eq!((matches.len(), matches[0].0, matches[0].1), (1, 0, 2));
+        assert_eq!(tournament.standings().round, 1);
+        assert!(tournament.finish_match(matches[0].2, PlayerId(0)));
+        let matches = open_matches(&mut tournament);
+        assert_eq!((matches.len(), matches[0].0, matches[0].1), (1, 0, 1));
+        assert!(tournament.finish_match(matches[0].2, PlayerId(0)));
+        assert!(open_matches(&mut tournament).is_empty());
+        assert_eq!(tournament.champion(), Some(0));
+        let standings = tournament.standings();
+        assert_eq!(standings.round, 3);
+        assert_eq!(
+            standings.seats.map(|seat| (seat.wins, seat.losses))[..3],
+            [(2, 0), (0, 2), (1, 1)]
+        );
+    }
+
+    #[test]
+    fn league_forfeits() {
+        let mut tournament = started_tournament(Format::League, 4);
+        let matches = open_matches(&mut tournament);
+        assert_eq!(matches.len(), 2);
+        // seat 3 leaves after losing its first match, forfeiting every match after it.
+        assert!(tournament.finish_match(matches[0].2, PlayerId(0)));
+        assert!(tournament.finish_match(matches[1].2, PlayerId(1)));
+        assert!(tournament.leave(PlayerId(3)));
+        loop {
+            let matches = open_matches(&mut tournament);
+            if matches.is_empty() {
+                break;
+            }
+            for (host, _, lobby_id) in matches {
+                assert!(tournament.finish_match(lobby_id, PlayerId(host as u64)));
+            }
+        }
+        let standings = tournament.standings();
+        assert_eq!(standings.round, 3);
+        assert!(standings.seats[3].left);
+        assert_eq!(standings.seats[3].wins + standings.seats[3].losses, 3);
+        assert_eq!(tournament.champion(), Some(0));
+    }
+}
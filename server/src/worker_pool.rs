@@ -0,0 +1,138 @@
+use std::{
+    sync::{
+        mpsc::{sync_channel, Receiver, SyncSender},
+        Arc, Condvar, Mutex,
+    },
+    thread::Builder,
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// how many of a pool's workers have been started, and how many of them are waiting for a job.
+#[derive(Default)]
+struct Workers {
+    started: usize,
+    idle: usize,
+}
+
+/// a bounded set of threads that jobs are run on. workers are only started once every other one is
+/// busy, and are reused once their job is done, so that a quiet server doesn't hold on to a thread
+/// per possible job.
+pub struct WorkerPool {
+    name: &'static str,
+    size: usize,
+    job_tx: SyncSender<Job>,
+    job_rx: Arc<Mutex<Receiver<Job>>>,
+    workers: Arc<(Mutex<Workers>, Condvar)>,
+}
+
+impl WorkerPool {
+    pub fn new(name: &'static str, size: usize) -> Self {
+        assert!(size > 0, "a worker pool needs at least one worker");
+        // jobs are handed straight to an idle worker, rather than queued.
+        let (job_tx, job_rx) = sync_channel(0);
+        Self {
+            name,
+            size,
+            job_tx,
+            job_rx: Arc::new(Mutex::new(job_rx)),
+            workers: Arc::default(),
+        }
+    }
+
+    /// runs the job on an idle worker, starting a new one if none are idle. blocks until a worker
+    /// is free if every one the pool can have is busy.
+    pub fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        let (workers, freed) = &*self.workers;
+        let mut workers = workers.lock().unwrap();
+        while workers.idle == 0 && workers.started == self.size {
+            workers = freed.wait(workers).unwrap();
+        }
+        if workers.idle > 0 {
+            // the idle worker is waiting for this job, or is about to.
+            workers.idle -= 1;
+            drop(workers);
+            self.job_tx.send(Box::new(job)).unwrap();
+        } else {
+            workers.started += 1;
+            let worker = workers.started;
+            drop(workers);
+            let job_rx = Arc::clone(&self.job_rx);
+            let workers = Arc::clone(&self.workers);
+            Builder::new()
+                .name(format!("{}_{worker}", self.name))
+                .spawn(move || Self::work(Box::new(job), job_rx, workers))
+                .unwrap();
+        }
+    }
+
+    /// runs the job, then every one it's handed after it, until the pool is dropped.
+    fn work(
+        mut job: Job,
+        job_rx: Arc<Mutex<Receiver<Job>>>,
+        workers: Arc<(Mutex<Workers>, Condvar)>,
+    ) {
+        loop {
+            job();
+            let (idle, freed) = &*workers;
+            idle.lock().unwrap().idle += 1;
+            freed.notify_one();
+            // receiving fails once the pool is dropped, as nothing can hand out jobs anymore.
+            let Ok(next) = job_rx.lock().unwrap().recv() else {
+                return;
+            };
+            job = next;
+        }
+    }
+
+    /// how many workers have been started so far.
+    pub fn started(&self) -> usize {
+        self.workers.0.lock().unwrap().started
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{mpsc::channel, Arc, Mutex},
+        thread::{sleep, spawn},
+        time::Duration,
+    };
+
+    use crate::worker_pool::WorkerPool;
+
+    #[test]
+    fn reuses_idle_workers() {
+        let pool = WorkerPool::new("test", 4);
+        let (done_tx, done_rx) = channel();
+        for job in 0..10 {
+            let done_tx = done_tx.clone();
+            pool.execute(move || done_tx.send(job).unwrap());
+            done_rx.recv().unwrap();
+            // give the worker a moment to go back to waiting for a job.
+            sleep(Duration::from_millis(10));
+        }
+        assert_eq!(pool.started(), 1);
+    }
+
+    #[test]
+    fn waits_for_a_free_worker() {
+        let pool = WorkerPool::new("test", 2);
+        let (release_tx, release_rx) = channel::<()>();
+        let release_rx = Arc::new(Mutex::new(release_rx));
+        for _ in 0..2 {
+            let release_rx = Arc::clone(&release_rx);
+            pool.execute(move || release_rx.lock().unwrap().recv().unwrap());
+        }
+        assert_eq!(pool.started(), 2);
+        let (ran_tx, ran_rx) = channel();
+        let waiting = spawn(move || {
+            pool.execute(move || ran_tx.send(()).unwrap());
+            pool
+        });
+        assert!(ran_rx.recv_timeout(Duration::from_millis(100)).is_err());
+        release_tx.send(()).unwrap();
+        ran_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(waiting.join().unwrap().started(), 2);
+    }
+}
@@ -0,0 +1,1264 @@
+use std::{
+    io::Write,
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::Arc,
+    thread::spawn,
+    time::{Duration, Instant},
+};
+
+use server::{
+    config::{ServeRule, ServerConfig, ServerMode, SocketOptions, Tiebreak},
+    tcp_server::serve,
+};
+use shared::{
+    checksum::checksum,
+    game_state::{GameDimensions, MAX_SPEED_LEVEL},
+    io::{MessageReader, ReadMessageError},
+    server_msg::MAX_SERVER_MESSAGE_SIZE,
+    server_msg::SERVER_MESSAGE_DELIMITER,
+    LOBBY_ID_LEN,
+};
+use socket2::{Domain, Socket, Type};
+
+const READY_TIMEOUT_SECS: u16 = 300;
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(5);
+/// a narrow field with tiny paddles, so that games are won within a few ticks.
+const DIMENSIONS: [u8; 6] = [0, 5, 0, 11, 0, 1];
+const GAME_STATE_UPDATED: u8 = 0x43;
+/// for games that should be won quickly.
+const FAST_TICK_INTERVAL_MS: u16 = 20;
+/// for games that shouldn't be won before the test is done with them.
+const SLOW_TICK_INTERVAL_MS: u16 = 1000;
+/// games between players paired by the match queue run at normal speed.
+const MATCH_TICK_INTERVAL_MS: u16 = 100;
+/// the capabilities sent by the test clients, which can receive wide game states.
+const WIDE_GAME_STATE: [u8; 1] = [1];
+/// the capabilities of clients that can play in lockstep too, which take a second byte.
+const LOCKSTEP: [u8; 2] = [0x81, 1];
+
+fn u14(n: u16) -> [u8; 2] {
+    [(n >> 7) as u8, n as u8 & 0x7F]
+}
+
+/// starts a server on an ephemeral port, returning its address.
+fn start_server() -> SocketAddr {
+    start_server_with(|_| {})
+}
+
+/// starts a server with its default test config adjusted by `configure`.
+fn start_server_with(configure: impl FnOnce(&mut ServerConfig)) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let mut config = ServerConfig {
+        address: address.to_string(),
+        ready_timeout_secs: READY_TIMEOUT_SECS,
+        lobby_ttl_secs: 30 * 60,
+        dimensions: GameDimensions::new(5, 11, 1).unwrap(),
+        dashboard_address: None,
+        event_log_dir: None,
+        stats_db_file: None,
+        match_results_file: None,
+        telemetry_endpoint: None,
+        replays: None,
+        debug_step: false,
+        lockstep_timeout_ms: None,
+        seed: None,
+        lobby_id_key: None,
+        lobby_id_counter_file: None,
+        lobby_id_len: LOBBY_ID_LEN,
+        serve_rule: ServeRule::Loser,
+        // tests that play games don't wait for a serve.
+        serve_delay_ms: 0,
+        match_length: None,
+        tiebreak: Tiebreak::WinByTwo,
+        max_connections: 64,
+        server_mode: ServerMode::Threads,
+        task_workers: None,
+        socket_options: SocketOptions::default(),
+        connection_quota: None,
+        lobby_quota: None,
+        access_list_file: None,
+        open_read_timeout_secs: None,
+        playing_read_timeout_secs: None,
+    };
+    configure(&mut config);
+    spawn(move || serve(listener, config, Arc::default()));
+    address
+}
+
+/// a client that sends and expects raw messages, so that the server's encoding is tested too.
+struct TestClient {
+    reader: MessageReader<TcpStream>,
+}
+
+impl TestClient {
+    fn connect(address: SocketAddr) -> Self {
+        Self::from_stream(TcpStream::connect(address).unwrap())
+    }
+
+    /// connects with a receive buffer of about the given size, which a client that stops reading
+    /// fills sooner.
+    fn connect_with_recv_buffer(address: SocketAddr, size: usize) -> Self {
+        let socket = Socket::new(Domain::for_address(address), Type::STREAM, None).unwrap();
+        socket.set_recv_buffer_size(size).unwrap();
+        socket.connect(&address.into()).unwrap();
+        Self::from_stream(socket.into())
+    }
+
+    fn from_stream(stream: TcpStream) -> Self {
+        // a server that never replies fails the test rather than hanging it.
+        stream.set_read_timeout(Some(RECEIVE_TIMEOUT)).unwrap();
+        Self {
+            reader: MessageReader::new(stream, SERVER_MESSAGE_DELIMITER, MAX_SERVER_MESSAGE_SIZE),
+        }
+    }
+
+    fn send(&mut self, message: &[u8]) {
+        let stream = self.reader.get_mut();
+        stream.write_all(message).unwrap();
+        stream.write_all(&[0xFF]).unwrap();
+    }
+
+    fn receive(&mut self) -> Vec<u8> {
+        self.reader.read_message().unwrap().to_vec()
+    }
+
+    fn expect(&mut self, message: &[u8]) {
+        assert_eq!(self.receive(), message);
+    }
+
+    /// asserts that no message arrives for the given time.
+    fn expect_nothing(&mut self, duration: Duration) {
+        let stream = self.reader.get_ref();
+        stream.set_read_timeout(Some(duration)).unwrap();
+        assert!(self.reader.read_message().is_err());
+        let stream = self.reader.get_ref();
+        stream.set_read_timeout(Some(RECEIVE_TIMEOUT)).unwrap();
+    }
+
+    /// receives the next message that isn't a game state update.
+    fn receive_skipping_game_states(&mut self) -> Vec<u8> {
+        loop {
+            let message = self.receive();
+            if message[0] != GAME_STATE_UPDATED {
+                return message;
+            }
+        }
+    }
+
+    /// creates a new lobby, returning its id.
+    fn new_lobby(&mut self, tick_interval_ms: u16) -> [u8; 4] {
+        self.new_lobby_with(tick_interval_ms, &WIDE_GAME_STATE)
+    }
+
+    /// creates a new lobby as a client with the given capabilities, returning its id.
+    fn new_lobby_with(&mut self, tick_interval_ms: u16, capabilities: &[u8]) -> [u8; 4] {
+        self.send(&[&[0x00], &u14(tick_interval_ms)[..], capabilities].concat());
+        let reply = self.receive();
+        assert_eq!(reply[0], 0x00);
+        assert_eq!(reply[5..], DIMENSIONS);
+        reply[1..5].try_into().unwrap()
+    }
+
+    fn join_lobby(&mut self, lobby_id: [u8; 4]) {
+        self.join_lobby_with(lobby_id, &WIDE_GAME_STATE);
+    }
+
+    fn join_lobby_with(&mut self, lobby_id: [u8; 4], capabilities: &[u8]) {
+        self.send(&[&[0x01], &lobby_id[..], capabilities].concat());
+    }
+}
+
+/// creates a lobby and has another client join it, returning the host and the joiner.
+fn joined_lobby(address: SocketAddr, tick_interval_ms: u16) -> (TestClient, TestClient) {
+    joined_lobby_with(address, tick_interval_ms, &WIDE_GAME_STATE)
+}
+
+/// creates a lobby and has another client join it, both with the given capabilities, returning
+/// the host and the joiner.
+fn joined_lobby_with(
+    address: SocketAddr,
+    tick_interval_ms: u16,
+    capabilities: &[u8],
+) -> (TestClient, TestClient) {
+    let mut host = TestClient::connect(address);
+    let mut joiner = TestClient::connect(address);
+    let lobby_id = host.new_lobby_with(tick_interval_ms, capabilities);
+    joiner.join_lobby_with(lobby_id, capabilities);
+    let [timeout_high, timeout_low] = u14(READY_TIMEOUT_SECS);
+    joiner.expect(&[&[0x10, timeout_high, timeout_low], &DIMENSIONS[..]].concat());
+    host.expect(&[0x20, timeout_high, timeout_low]);
+    (host, joiner)
+}
+
+/// queues a client for a match.
+fn find_match(client: &mut TestClient) {
+    client.send(&[0x03, 1]);
+}
+
+/// receives a match from the match queue (0x60) or a tournament (0x76), returning the lobby id and
+/// side it was seated with.
+fn expect_match(client: &mut TestClient, message: u8) -> ([u8; 4], u8) {
+    let reply = client.receive();
+    assert_eq!(reply[0], message);
+    let [timeout_high, timeout_low] = u14(READY_TIMEOUT_SECS);
+    assert_eq!(
+        reply[6..],
+        [&[timeout_high, timeout_low], &DIMENSIONS[..]].concat()
+    );
+    (reply[1..5].try_into().unwrap(), reply[5])
+}
+
+/// creates a tournament for the given number of players, returning its id.
+fn new_tournament(client: &mut TestClient, players: u8) -> [u8; 4] {
+    client.send(&[0x04, players, 1]);
+    let reply = client.receive();
+    assert_eq!(reply[0], 0x70);
+    assert_eq!(reply[5..], [0, players]);
+    reply[1..5].try_into().unwrap()
+}
+
+/// has each client join the tournament in turn, after the one that created it, then receives
+/// everything sent to them up to their first match. returns the side each client was seated
+/// with.
+fn fill_tournament(clients: &mut [TestClient], tournament_id: [u8; 4]) -> Vec<u8> {
+    let players = clients.len() as u8;
+    for (seat, client) in clients.iter_mut().enumerate().skip(1) {
+        client.send(&[&[0x05], &tournament_id[..], &[1]].concat());
+        client.expect(&[&[0x70], &tournament_id[..], &[seat as u8, players]].concat());
+    }
+    let mut sides = Vec::new();
+    for (seat, client) in clients.iter_mut().enumerate() {
+        for joined in seat as u16 + 1..=players as u16 {
+            client.expect(&[&[0x74], &u14(joined)[..]].concat());
+        }
+        client.expect(&[0x75, 0, 0, 0, 0, 0, 0, 0, 0]);
+        sides.push(expect_match(client, 0x76).1);
+    }
+    sides
+}
+
+/// readies both players up, returning once the game has started.
+fn start_game(host: &mut TestClient, joiner: &mut TestClient, tick_interval_ms: u16) {
+    ready_up(host, joiner);
+    for client in [host, joiner] {
+        client.expect(&[&[0x35], &u14(tick_interval_ms)[..]].concat());
+        assert_eq!(client.receive()[0], GAME_STATE_UPDATED);
+    }
+}
+
+/// has the host create a lobby and the joiner join it, and starts a game, on a server whose paddles
+/// fill the sides of its field, so that the game lasts until someone leaves.
+fn start_endless_game(host: &mut TestClient, joiner: &mut TestClient, tick_interval_ms: u16) {
+    host.send(&[&[0x00], &u14(tick_interval_ms)[..], &WIDE_GAME_STATE].concat());
+    let lobby_id = host.receive()[1..5].try_into().unwrap();
+    joiner.join_lobby(lobby_id);
+    assert_eq!(joiner.receive()[0], 0x10);
+    assert_eq!(host.receive()[0], 0x20);
+    start_game(host, joiner, tick_interval_ms);
+}
+
+/// starts a game on a server in lockstep, which says so just before the game starts.
+fn start_lockstep_game(host: &mut TestClient, joiner: &mut TestClient, tick_interval_ms: u16) {
+    ready_up(host, joiner);
+    for client in [host, joiner] {
+        client.expect(&[0x3D]);
+        client.expect(&[&[0x35], &u14(tick_interval_ms)[..]].concat());
+        assert_eq!(client.receive()[0], GAME_STATE_UPDATED);
+    }
+}
+
+fn ready_up(host: &mut TestClient, joiner: &mut TestClient) {
+    host.send(&[0x10]);
+    host.expect(&[0x33]);
+    joiner.expect(&[0x31]);
+    joiner.send(&[0x10]);
+    joiner.expect(&[0x33]);
+}
+
+#[test]
+fn play_to_a_win_and_again() {
+    let address = start_server();
+    let (mut host, mut joiner) = joined_lobby(address, FAST_TICK_INTERVAL_MS);
+    for _ in 0..2 {
+        start_game(&mut host, &mut joiner, FAST_TICK_INTERVAL_MS);
+        let host_result = host.receive_skipping_game_states();
+        let joiner_result = joiner.receive_skipping_game_states();
+        // one player is told they won (0x42), and the other that their opponent did (0x41).
+        assert!(
+            (host_result == [0x42] && joiner_result == [0x41])
+                || (host_result == [0x41] && joiner_result == [0x42]),
+            "{host_result:?} {joiner_result:?}"
+        );
+    }
+}
+
+#[test]
+fn ball_speeds_up_after_hits() {
+    // the paddles fill the sides of the field, so the ball is never missed.
+    let address = start_server_with(|config| {
+        config.dimensions = GameDimensions::new(7, 5, 5).unwrap();
+    });
+    let mut host = TestClient::connect(address);
+    // only the host supports being told how fast the ball is going.
+    host.send(&[&[0x00], &u14(MATCH_TICK_INTERVAL_MS)[..], &[3]].concat());
+    let lobby_id = host.receive()[1..5].try_into().unwrap();
+    let mut joiner = TestClient::connect(address);
+    joiner.join_lobby(lobby_id);
+    assert_eq!(joiner.receive()[0], 0x10);
+    assert_eq!(host.receive()[0], 0x20);
+    start_game(&mut host, &mut joiner, MATCH_TICK_INTERVAL_MS);
+    for speed_level in 1..=MAX_SPEED_LEVEL {
+        assert_eq!(host.receive_skipping_game_states(), [0x46, speed_level]);
+    }
+    for _ in 0..4 * MAX_SPEED_LEVEL {
+        assert_eq!(joiner.receive()[0], GAME_STATE_UPDATED);
+    }
+}
+
+#[test]
+fn resign() {
+    let address = start_server();
+    let (mut host, mut joiner) = joined_lobby(address, SLOW_TICK_INTERVAL_MS);
+    start_game(&mut host, &mut joiner, SLOW_TICK_INTERVAL_MS);
+    host.send(&[0x21]);
+    assert_eq!(host.receive_skipping_game_states(), [0x41]);
+    assert_eq!(joiner.receive_skipping_game_states(), [0x44]);
+    // both players are returned to the ready screen, and can play again.
+    start_game(&mut host, &mut joiner, SLOW_TICK_INTERVAL_MS);
+}
+
+#[test]
+fn match_results_exported() {
+    let path = std::env::temp_dir().join(format!("pong_results_{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let address = start_server_with(|config| config.match_results_file = Some(path.clone()));
+    let (mut host, mut joiner) = joined_lobby(address, SLOW_TICK_INTERVAL_MS);
+    start_game(&mut host, &mut joiner, SLOW_TICK_INTERVAL_MS);
+    host.send(&[0x21]);
+    assert_eq!(host.receive_skipping_game_states(), [0x41]);
+    // the result is exported off the game's thread.
+    let mut results = String::new();
+    for _ in 0..50 {
+        results = std::fs::read_to_string(&path).unwrap_or_default();
+        if !results.is_empty() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(results.lines().count(), 1);
+    // the joiner sits on the right, and won by the host resigning.
+    assert!(
+        results.contains(r#""winner_side":"right","winner_games":1,"loser_games":0"#),
+        "{results}"
+    );
+}
+
+#[test]
+fn moves_recorded_once_per_tick() {
+    let dir = std::env::temp_dir().join(format!("pong_moves_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let address = start_server_with(|config| config.event_log_dir = Some(dir.clone()));
+    let (mut host, mut joiner) = joined_lobby(address, SLOW_TICK_INTERVAL_MS);
+    start_game(&mut host, &mut joiner, SLOW_TICK_INTERVAL_MS);
+    // a burst of moves between ticks, ending up, of which only the last is applied.
+    for direction in [1, 2, 0, 2, 1] {
+        host.send(&[0x20, direction]);
+    }
+    assert_eq!(host.receive()[0], GAME_STATE_UPDATED);
+    host.send(&[0x21]);
+    assert_eq!(host.receive_skipping_game_states(), [0x41]);
+    let path = std::fs::read_dir(&dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let log = std::fs::read_to_string(path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let moves: Vec<_> = log
+        .lines()
+        .filter(|line| line.contains(r#""event":"moved""#))
+        .collect();
+    assert_eq!(moves.len(), 1, "{log}");
+    assert!(moves[0].contains(r#""direction":"up""#), "{log}");
+}
+
+#[test]
+fn unready() {
+    let address = start_server();
+    let (mut host, mut joiner) = joined_lobby(address, SLOW_TICK_INTERVAL_MS);
+    host.send(&[0x10]);
+    host.expect(&[0x33]);
+    joiner.expect(&[0x31]);
+    host.send(&[0x11]);
+    host.expect(&[0x34]);
+    joiner.expect(&[0x32]);
+    joiner.send(&[0x10]);
+    joiner.expect(&[0x33]);
+    host.expect(&[0x31]);
+}
+
+#[test]
+fn resync() {
+    let address = start_server();
+    let mut client = TestClient::connect(address);
+    // a resync request (0x40) is answered with the client's state (0xA0), whatever it is.
+    client.send(&[0x40]);
+    client.expect(&[0xA0, 0]);
+    let (mut host, mut joiner) = joined_lobby(address, SLOW_TICK_INTERVAL_MS);
+    host.send(&[0x10]);
+    host.expect(&[0x33]);
+    joiner.expect(&[0x31]);
+    host.send(&[0x40]);
+    host.expect(&[0xA0, 0b101]);
+    joiner.send(&[0x40]);
+    joiner.expect(&[0xA0, 0b110]);
+    joiner.send(&[0x10]);
+    joiner.expect(&[0x33]);
+    joiner.expect(&[&[0x35], &u14(SLOW_TICK_INTERVAL_MS)[..]].concat());
+    joiner.send(&[0x40]);
+    assert_eq!(joiner.receive_skipping_game_states(), [0xA0, 8]);
+}
+
+#[test]
+fn checksums() {
+    let address = start_server();
+    let mut client = TestClient::connect(address);
+    // once checksums are enabled (0x50, 0xB0), every frame either way ends with one.
+    client.send(&[0x50]);
+    client.expect(&[0xB0]);
+    client.send(&[&[0x40][..], &checksum(&[0x40])].concat());
+    client.expect(&[&[0xA0, 0][..], &checksum(&[0xA0, 0])].concat());
+    // a frame that fails its checksum is discarded.
+    client.send(&[&[0x40][..], &checksum(&[0x41])].concat());
+    client.expect_nothing(Duration::from_millis(200));
+    client.send(&[0x40]);
+    client.expect_nothing(Duration::from_millis(200));
+}
+
+#[test]
+fn leave_lobby() {
+    let address = start_server();
+    let mut host = TestClient::connect(address);
+    let mut joiner = TestClient::connect(address);
+    let lobby_id = host.new_lobby(SLOW_TICK_INTERVAL_MS);
+    joiner.join_lobby(lobby_id);
+    assert_eq!(joiner.receive()[0], 0x10);
+    assert_eq!(host.receive()[0], 0x20);
+    // the joiner leaves (0x12), and the host is told the lobby is open again (0x3A).
+    joiner.send(&[0x12]);
+    host.expect(&[0x3A]);
+    // the joiner is back in the open state, so can host a lobby of their own, and close it (0x31).
+    joiner.new_lobby(SLOW_TICK_INTERVAL_MS);
+    joiner.send(&[0x31]);
+    joiner.send(&[0x40]);
+    joiner.expect(&[0xA0, 0]);
+    // the first lobby can be joined by someone else.
+    let mut other = TestClient::connect(address);
+    other.join_lobby(lobby_id);
+    assert_eq!(other.receive()[0], 0x10);
+    assert_eq!(host.receive()[0], 0x20);
+}
+
+#[test]
+fn host_disconnects_before_game() {
+    let address = start_server();
+    let mut host = TestClient::connect(address);
+    let mut joiner = TestClient::connect(address);
+    let lobby_id = host.new_lobby(SLOW_TICK_INTERVAL_MS);
+    joiner.join_lobby(lobby_id);
+    assert_eq!(joiner.receive()[0], 0x10);
+    assert_eq!(host.receive()[0], 0x20);
+    // the joiner is promoted to host of the same lobby (0x3B).
+    drop(host);
+    joiner.expect(&[&[0x3B], &lobby_id[..]].concat());
+    let mut other = TestClient::connect(address);
+    other.join_lobby(lobby_id);
+    assert_eq!(other.receive()[0], 0x10);
+    assert_eq!(joiner.receive()[0], 0x20);
+}
+
+#[test]
+fn opponent_leaves() {
+    let address = start_server();
+    let (mut host, joiner) = joined_lobby(address, SLOW_TICK_INTERVAL_MS);
+    drop(joiner);
+    host.expect(&[0x30]);
+    let (mut host, mut joiner) = joined_lobby(address, SLOW_TICK_INTERVAL_MS);
+    start_game(&mut host, &mut joiner, SLOW_TICK_INTERVAL_MS);
+    drop(joiner);
+    assert_eq!(host.receive_skipping_game_states(), [0x40]);
+}
+
+#[test]
+fn join_missing_or_full_lobby() {
+    let address = start_server();
+    let mut client = TestClient::connect(address);
+    let mut host = TestClient::connect(address);
+    let lobby_id = host.new_lobby(SLOW_TICK_INTERVAL_MS);
+    let missing_lobby_id = if lobby_id == *b"AAAA" {
+        *b"BBBB"
+    } else {
+        *b"AAAA"
+    };
+    client.join_lobby(missing_lobby_id);
+    client.expect(&[0x12]);
+    let mut joiner = TestClient::connect(address);
+    joiner.join_lobby(lobby_id);
+    assert_eq!(joiner.receive()[0], 0x10);
+    let mut client = TestClient::connect(address);
+    client.join_lobby(lobby_id);
+    client.expect(&[0x11]);
+}
+
+#[test]
+fn custom_lobby_id() {
+    let address = start_server();
+    let mut host = TestClient::connect(address);
+    let custom_id = [&[0x00], &u14(SLOW_TICK_INTERVAL_MS)[..], &[1], b"GAME"].concat();
+    host.send(&custom_id);
+    host.expect(&[&[0x00], &b"GAME"[..], &DIMENSIONS[..]].concat());
+    let mut other_host = TestClient::connect(address);
+    other_host.send(&custom_id);
+    other_host.expect(&[0x01]);
+    let mut joiner = TestClient::connect(address);
+    joiner.join_lobby(*b"GAME");
+    assert_eq!(joiner.receive()[0], 0x10);
+}
+
+#[test]
+fn longer_lobby_ids() {
+    let address = start_server_with(|config| config.lobby_id_len = 6);
+    let mut host = TestClient::connect(address);
+    host.send(&[&[0x00], &u14(SLOW_TICK_INTERVAL_MS)[..], &[1]].concat());
+    let reply = host.receive();
+    // ids longer than the default are prefixed with their length.
+    assert_eq!(reply[..2], [0x00, 6]);
+    assert_eq!(reply[8..], DIMENSIONS);
+    let mut joiner = TestClient::connect(address);
+    joiner.send(&[&[0x01], &reply[1..8], &[1]].concat());
+    assert_eq!(joiner.receive()[0], 0x10);
+}
+
+#[test]
+fn handicapped_lobby() {
+    let address = start_server();
+    let mut host = TestClient::connect(address);
+    // the host's paddle is 3 cells tall, and the joiner's moves 2 cells a tick.
+    let handicaps = [0, 3, 0, 0, 0, 0, 0, 2];
+    host.send(
+        &[
+            &[0x00],
+            &u14(SLOW_TICK_INTERVAL_MS)[..],
+            &[17, 0],
+            &handicaps[..],
+        ]
+        .concat(),
+    );
+    let lobby_id: [u8; 4] = host.receive()[1..5].try_into().unwrap();
+    // clients that don't support handicaps can't join.
+    let mut old_client = TestClient::connect(address);
+    old_client.join_lobby(lobby_id);
+    old_client.expect(&[0x13]);
+    let mut joiner = TestClient::connect(address);
+    joiner.send(&[&[0x01], &lobby_id[..], &[17]].concat());
+    assert_eq!(joiner.receive()[0], 0x10);
+    assert_eq!(host.receive()[0], 0x20);
+    host.send(&[0x10]);
+    host.expect(&[0x33]);
+    joiner.expect(&[0x31]);
+    joiner.send(&[0x10]);
+    joiner.expect(&[0x33]);
+    for client in [&mut host, &mut joiner] {
+        client.expect(&[&[0x37], &u14(SLOW_TICK_INTERVAL_MS)[..], &handicaps[..]].concat());
+        assert_eq!(client.receive()[0], GAME_STATE_UPDATED);
+    }
+}
+
+#[test]
+fn lobby_ruleset_agreed() {
+    let address = start_server_with(|config| config.match_length = Some(5));
+    let mut host = TestClient::connect(address);
+    // the joiner's paddle is taller than the field, and the host leaves the match length to the
+    // server.
+    host.send(
+        &[
+            &[0x08],
+            &u14(SLOW_TICK_INTERVAL_MS)[..],
+            &u14(0)[..],
+            &[0, 0, 0, 0, 0, 50, 0, 0],
+            &[1, 48],
+        ]
+        .concat(),
+    );
+    let reply = host.receive();
+    assert_eq!(reply[0], 0x00);
+    let lobby_id: [u8; 4] = reply[1..5].try_into().unwrap();
+    let mut joiner = TestClient::connect(address);
+    joiner.send(&[&[0x01], &lobby_id[..], &[48]].concat());
+    assert_eq!(joiner.receive()[0], 0x10);
+    assert_eq!(host.receive()[0], 0x20);
+    // both players are told the settled ruleset before either readies up.
+    let ruleset = [
+        &[0x38],
+        &u14(SLOW_TICK_INTERVAL_MS)[..],
+        &u14(5)[..],
+        &[0, 0, 0, 0, 0, 11, 0, 0],
+        &[1],
+    ]
+    .concat();
+    joiner.expect(&ruleset);
+    host.expect(&ruleset);
+}
+
+#[test]
+fn match_point_announced() {
+    let address = start_server_with(|config| config.match_length = Some(2));
+    let mut host = TestClient::connect(address);
+    // only the host supports being told the stakes of a game.
+    host.send(&[&[0x00], &u14(FAST_TICK_INTERVAL_MS)[..], &[9]].concat());
+    let lobby_id = host.receive()[1..5].try_into().unwrap();
+    let mut joiner = TestClient::connect(address);
+    joiner.join_lobby(lobby_id);
+    assert_eq!(joiner.receive()[0], 0x10);
+    assert_eq!(host.receive()[0], 0x20);
+    start_game(&mut host, &mut joiner, FAST_TICK_INTERVAL_MS);
+    let host_won = host.receive_skipping_game_states() == [0x42];
+    joiner.receive_skipping_game_states();
+    start_game(&mut host, &mut joiner, FAST_TICK_INTERVAL_MS);
+    // the host plays on the left, and whoever won the first game is a game from the match.
+    let (left_wins, right_wins) = if host_won { (1, 0) } else { (0, 1) };
+    host.expect(&[&[0x4B], &u14(left_wins)[..], &u14(right_wins)[..]].concat());
+    assert_eq!(joiner.receive()[0], GAME_STATE_UPDATED);
+}
+
+#[test]
+fn unjoined_lobby_expires() {
+    let address = start_server_with(|config| config.lobby_ttl_secs = 2);
+    let mut host = TestClient::connect(address);
+    let lobby_id = host.new_lobby(SLOW_TICK_INTERVAL_MS);
+    // the lobby is younger than the warning period, so the host is warned straight away.
+    host.expect(&[&[0x21], &u14(2)[..]].concat());
+    host.expect(&[0x22]);
+    let mut joiner = TestClient::connect(address);
+    joiner.join_lobby(lobby_id);
+    joiner.expect(&[0x12]);
+}
+
+#[test]
+fn host_keeps_lobby_open() {
+    let address = start_server_with(|config| config.lobby_ttl_secs = 2);
+    let mut host = TestClient::connect(address);
+    host.new_lobby(SLOW_TICK_INTERVAL_MS);
+    host.expect(&[&[0x21], &u14(2)[..]].concat());
+    host.send(&[0x30]);
+    // the lobby's expiry is pushed back, so the host is warned again before it closes.
+    assert_eq!(host.receive()[0], 0x21);
+    host.expect(&[0x22]);
+}
+
+#[test]
+fn connections_beyond_limit_wait() {
+    let address = start_server_with(|config| config.max_connections = 1);
+    let mut first = TestClient::connect(address);
+    first.send(&[0x02]);
+    assert_eq!(first.receive()[0], 0x50);
+    // the listener's backlog lets the second client connect, but nothing serves it yet.
+    let mut second = TestClient::connect(address);
+    second.send(&[0x02]);
+    second.expect_nothing(Duration::from_millis(200));
+    drop(first);
+    assert_eq!(second.receive()[0], 0x50);
+}
+
+#[test]
+fn connections_beyond_quota_rejected() {
+    let address = start_server_with(|config| config.connection_quota = Some(2));
+    for _ in 0..2 {
+        let mut client = TestClient::connect(address);
+        client.send(&[0x02]);
+        assert_eq!(client.receive()[0], 0x50);
+    }
+    let mut rejected = TestClient::connect(address);
+    rejected.expect(&[0x90]);
+}
+
+#[test]
+fn lobbies_beyond_quota_rejected() {
+    let address = start_server_with(|config| config.lobby_quota = Some(1));
+    let mut host = TestClient::connect(address);
+    host.new_lobby(SLOW_TICK_INTERVAL_MS);
+    let mut other_host = TestClient::connect(address);
+    other_host.send(&[&[0x00], &u14(SLOW_TICK_INTERVAL_MS)[..], &[1]].concat());
+    other_host.expect(&[0x02]);
+}
+
+#[test]
+fn denied_addresses_rejected() {
+    let path = std::env::temp_dir().join(format!("pong_denied_{}", std::process::id()));
+    std::fs::write(&path, "deny 127.0.0.0/8\n").unwrap();
+    let address = start_server_with(|config| config.access_list_file = Some(path.clone()));
+    let mut rejected = TestClient::connect(address);
+    rejected.expect(&[0x91]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn event_loop_plays_games() {
+    let address = start_server_with(|config| config.server_mode = ServerMode::EventLoop);
+    let (mut host, mut joiner) = joined_lobby(address, FAST_TICK_INTERVAL_MS);
+    start_game(&mut host, &mut joiner, FAST_TICK_INTERVAL_MS);
+    let host_result = host.receive_skipping_game_states();
+    let joiner_result = joiner.receive_skipping_game_states();
+    assert!(
+        (host_result == [0x42] && joiner_result == [0x41])
+            || (host_result == [0x41] && joiner_result == [0x42]),
+        "{host_result:?} {joiner_result:?}"
+    );
+    let (mut host, mut joiner) = joined_lobby(address, SLOW_TICK_INTERVAL_MS);
+    start_game(&mut host, &mut joiner, SLOW_TICK_INTERVAL_MS);
+    drop(joiner);
+    assert_eq!(host.receive_skipping_game_states(), [0x40]);
+}
+
+#[test]
+fn event_loop_expires_lobbies() {
+    let address = start_server_with(|config| {
+        config.server_mode = ServerMode::EventLoop;
+        config.lobby_ttl_secs = 2;
+    });
+    // idle connections don't hold up the rest.
+    let _idle: Vec<_> = (0..100).map(|_| TestClient::connect(address)).collect();
+    let mut host = TestClient::connect(address);
+    let lobby_id = host.new_lobby(SLOW_TICK_INTERVAL_MS);
+    host.expect(&[&[0x21], &u14(2)[..]].concat());
+    host.expect(&[0x22]);
+    let mut joiner = TestClient::connect(address);
+    joiner.join_lobby(lobby_id);
+    joiner.expect(&[0x12]);
+}
+
+#[test]
+fn quiet_connections_closed() {
+    // the paddles fill the sides of the field, so the game lasts until someone leaves.
+    let address = start_server_with(|config| {
+        config.dimensions = GameDimensions::new(7, 5, 5).unwrap();
+        config.open_read_timeout_secs = Some(1);
+        config.playing_read_timeout_secs = Some(1);
+        config.lockstep_timeout_ms = Some(50);
+    });
+    // a connection that never says anything is closed.
+    let mut silent = TestClient::connect(address);
+    assert!(matches!(
+        silent.reader.read_message(),
+        Err(ReadMessageError::Closed)
+    ));
+    let mut host = TestClient::connect(address);
+    host.send(&[&[0x00], &u14(MATCH_TICK_INTERVAL_MS)[..], &LOCKSTEP].concat());
+    let lobby_id = host.receive()[1..5].try_into().unwrap();
+    // but a host can wait for an opponent for as long as the lobby is open.
+    host.expect_nothing(Duration::from_secs(2));
+    let mut joiner = TestClient::connect(address);
+    joiner.join_lobby_with(lobby_id, &LOCKSTEP);
+    assert_eq!(joiner.receive()[0], 0x10);
+    assert_eq!(host.receive()[0], 0x20);
+    start_lockstep_game(&mut host, &mut joiner, MATCH_TICK_INTERVAL_MS);
+    // the host answers every game state, but the joiner has gone quiet, so is disconnected.
+    loop {
+        let message = host.receive();
+        if message[0] != GAME_STATE_UPDATED {
+            assert_eq!(message, [0x40]);
+            break;
+        }
+        host.send(&[0x20, 0]);
+    }
+}
+
+#[test]
+fn quiet_players_kept_outside_lockstep() {
+    // the paddles fill the sides of the field, so the game lasts until someone leaves.
+    let address = start_server_with(|config| {
+        config.dimensions = GameDimensions::new(7, 5, 5).unwrap();
+        config.playing_read_timeout_secs = Some(1);
+    });
+    let mut host = TestClient::connect(address);
+    let mut joiner = TestClient::connect(address);
+    start_endless_game(&mut host, &mut joiner, MATCH_TICK_INTERVAL_MS);
+    // neither player touches their paddle, as clients only send their direction when it changes.
+    let quiet_until = Instant::now() + Duration::from_secs(3);
+    while Instant::now() < quiet_until {
+        assert_eq!(host.receive()[0], GAME_STATE_UPDATED);
+        assert_eq!(joiner.receive()[0], GAME_STATE_UPDATED);
+    }
+}
+
+#[test]
+fn client_not_reading_holds_up_no_other_games() {
+    let address = start_server_with(|config| {
+        config.dimensions = GameDimensions::new(7, 5, 5).unwrap();
+        // every game is ticked on the one thread, which a write that blocked would hold up.
+        config.task_workers = Some(1);
+        config.socket_options.send_buffer_size = Some(4096);
+    });
+    let mut host = TestClient::connect(address);
+    let mut stalled = TestClient::connect_with_recv_buffer(address, 4096);
+    start_endless_game(&mut host, &mut stalled, FAST_TICK_INTERVAL_MS);
+    let mut other_host = TestClient::connect(address);
+    let mut other_joiner = TestClient::connect(address);
+    start_endless_game(&mut other_host, &mut other_joiner, FAST_TICK_INTERVAL_MS);
+    // far more resync requests (0x40) than their answers fit in the sockets' buffers, none of
+    // which are read.
+    let requests = [0x40, 0xFF].repeat(20_000);
+    stalled.reader.get_mut().write_all(&requests).unwrap();
+    let until = Instant::now() + Duration::from_secs(3);
+    while Instant::now() < until {
+        assert_eq!(other_host.receive()[0], GAME_STATE_UPDATED);
+        assert_eq!(other_joiner.receive()[0], GAME_STATE_UPDATED);
+    }
+    // the client made no room for its frames for too long, so was disconnected.
+    assert_eq!(host.receive_skipping_game_states(), [0x40]);
+}
+
+#[test]
+fn stale_game_states_dropped_for_slow_clients() {
+    let address = start_server_with(|config| {
+        config.dimensions = GameDimensions::new(7, 5, 5).unwrap();
+        // the smallest buffers the os allows. frames are left to be sent together, since the os
+        // makes room in buffers full of small segments by merging them, which keeps letting frames
+        // through.
+        config.socket_options.send_buffer_size = Some(1);
+        config.socket_options.nodelay = false;
+    });
+    let mut host = TestClient::connect(address);
+    let mut slow = TestClient::connect_with_recv_buffer(address, 1);
+    start_endless_game(&mut host, &mut slow, FAST_TICK_INTERVAL_MS);
+    // the slow client fills the sockets' buffers with answers to resync requests (0x40), and reads
+    // nothing for a while, though not for so long that it's disconnected.
+    let requests = [0x40, 0xFF].repeat(20_000);
+    slow.reader.get_mut().write_all(&requests).unwrap();
+    let mut host_states = 0;
+    let until = Instant::now() + Duration::from_millis(700);
+    while Instant::now() < until {
+        assert_eq!(host.receive()[0], GAME_STATE_UPDATED);
+        host_states += 1;
+    }
+    drop(host);
+    // every answer reaches the slow client, but most of the game states sent while it wasn't
+    // reading were replaced by newer ones before it could be sent them.
+    let mut slow_states = 0;
+    loop {
+        match slow.receive()[0] {
+            GAME_STATE_UPDATED => slow_states += 1,
+            0xA0 => {}
+            message => {
+                assert_eq!(message, 0x40);
+                break;
+            }
+        }
+    }
+    assert!(
+        slow_states < host_states / 2,
+        "{slow_states} of {host_states} game states"
+    );
+}
+
+#[test]
+fn event_loop_closes_quiet_connections() {
+    let address = start_server_with(|config| {
+        config.server_mode = ServerMode::EventLoop;
+        config.open_read_timeout_secs = Some(1);
+    });
+    let mut silent = TestClient::connect(address);
+    let mut host = TestClient::connect(address);
+    host.new_lobby(SLOW_TICK_INTERVAL_MS);
+    assert!(matches!(
+        silent.reader.read_message(),
+        Err(ReadMessageError::Closed)
+    ));
+    host.expect_nothing(Duration::from_secs(2));
+}
+
+#[test]
+fn quiet_connections_closed_once_lobby_gone() {
+    let address = start_server_with(|config| {
+        config.lobby_ttl_secs = 2;
+        config.open_read_timeout_secs = Some(1);
+    });
+    // the host waits quietly until the lobby expires, and then has nothing left to wait on.
+    let mut host = TestClient::connect(address);
+    host.new_lobby(SLOW_TICK_INTERVAL_MS);
+    host.expect(&[&[0x21], &u14(2)[..]].concat());
+    host.expect(&[0x22]);
+    assert!(matches!(
+        host.reader.read_message(),
+        Err(ReadMessageError::Closed)
+    ));
+    // as does a player whose opponent left mid game.
+    let (mut host, mut joiner) = joined_lobby(address, SLOW_TICK_INTERVAL_MS);
+    start_game(&mut host, &mut joiner, SLOW_TICK_INTERVAL_MS);
+    drop(joiner);
+    assert_eq!(host.receive_skipping_game_states(), [0x40]);
+    assert!(matches!(
+        host.reader.read_message(),
+        Err(ReadMessageError::Closed)
+    ));
+}
+
+#[test]
+fn server_status() {
+    let address = start_server();
+    let mut client = TestClient::connect(address);
+    client.send(&[0x02]);
+    let status = client.receive();
+    assert_eq!(status[..5], [0x50, 0, 0, 0, 0]);
+    let mut host = TestClient::connect(address);
+    host.new_lobby(SLOW_TICK_INTERVAL_MS);
+    client.send(&[0x02]);
+    let status = client.receive();
+    // one player waiting in one open lobby.
+    assert_eq!(status[..5], [0x50, 0, 1, 0, 1]);
+    assert_eq!(status.len(), 15);
+    let (_host, _joiner) = joined_lobby(address, SLOW_TICK_INTERVAL_MS);
+    client.send(&[0x02]);
+    // a full lobby is no longer open.
+    assert_eq!(client.receive()[..5], [0x50, 0, 3, 0, 1]);
+}
+
+#[test]
+fn browse_then_join() {
+    let address = start_server();
+    let mut client = TestClient::connect(address);
+    client.send(&[0x07, 0, 0, 1]);
+    client.expect(&[0x81, 0, 0, 0, 1]);
+    let mut hosts = [TestClient::connect(address), TestClient::connect(address)];
+    let lobby_ids = [
+        hosts[0].new_lobby(SLOW_TICK_INTERVAL_MS),
+        hosts[1].new_lobby(FAST_TICK_INTERVAL_MS),
+    ];
+    let (_host, _joiner) = joined_lobby(address, SLOW_TICK_INTERVAL_MS);
+    client.send(&[0x07, 0, 0, 1]);
+    // only the open lobbies are listed, oldest first.
+    for (lobby_id, tick_interval_ms) in lobby_ids
+        .iter()
+        .zip([SLOW_TICK_INTERVAL_MS, FAST_TICK_INTERVAL_MS])
+    {
+        let listed = client.receive();
+        assert_eq!(listed[..5], [&[0x80], &lobby_id[..]].concat());
+        assert_eq!(listed[5..7], u14(tick_interval_ms));
+        assert_eq!(listed.len(), 11);
+    }
+    client.expect(&[0x81, 0, 0, 0, 1]);
+    // pages past the last are empty.
+    client.send(&[0x07, 0, 1, 1]);
+    client.expect(&[0x81, 0, 1, 0, 1]);
+    // the connection can still join a lobby after browsing.
+    client.join_lobby(lobby_ids[1]);
+    assert_eq!(client.receive()[0], 0x10);
+    hosts[1].expect(&[&[0x20], &u14(READY_TIMEOUT_SECS)[..]].concat());
+}
+
+#[test]
+fn lockstep_waits_for_both_inputs() {
+    let address = start_server_with(|config| config.lockstep_timeout_ms = Some(60_000));
+    let (mut host, mut joiner) = joined_lobby_with(address, FAST_TICK_INTERVAL_MS, &LOCKSTEP);
+    start_lockstep_game(&mut host, &mut joiner, FAST_TICK_INTERVAL_MS);
+    host.send(&[0x20, 0]);
+    host.expect_nothing(Duration::from_millis(200));
+    joiner.send(&[0x20, 0]);
+    assert_eq!(host.receive()[0], GAME_STATE_UPDATED);
+    assert_eq!(joiner.receive()[0], GAME_STATE_UPDATED);
+}
+
+#[test]
+fn lockstep_ticks_anyway_after_timeout() {
+    let address = start_server_with(|config| config.lockstep_timeout_ms = Some(50));
+    let (mut host, mut joiner) = joined_lobby_with(address, FAST_TICK_INTERVAL_MS, &LOCKSTEP);
+    start_lockstep_game(&mut host, &mut joiner, FAST_TICK_INTERVAL_MS);
+    assert_eq!(host.receive()[0], GAME_STATE_UPDATED);
+}
+
+#[test]
+fn lockstep_refuses_older_clients() {
+    let address = start_server_with(|config| config.lockstep_timeout_ms = Some(50));
+    // a client that can't play in lockstep can neither create a lobby,
+    let mut older = TestClient::connect(address);
+    older.send(&[&[0x00], &u14(SLOW_TICK_INTERVAL_MS)[..], &WIDE_GAME_STATE].concat());
+    older.expect(&[0x03]);
+    // nor join one.
+    let mut host = TestClient::connect(address);
+    let lobby_id = host.new_lobby_with(SLOW_TICK_INTERVAL_MS, &LOCKSTEP);
+    let mut older = TestClient::connect(address);
+    older.join_lobby(lobby_id);
+    older.expect(&[0x13]);
+    // which leaves the lobby open for one that can.
+    let mut joiner = TestClient::connect(address);
+    joiner.join_lobby_with(lobby_id, &LOCKSTEP);
+    assert_eq!(joiner.receive()[0], 0x10);
+    assert_eq!(host.receive()[0], 0x20);
+}
+
+#[test]
+fn quick_match() {
+    let address = start_server();
+    let mut first = TestClient::connect(address);
+    find_match(&mut first);
+    first.expect_nothing(Duration::from_millis(200));
+    let mut second = TestClient::connect(address);
+    find_match(&mut second);
+    let (first_lobby_id, first_side) = expect_match(&mut first, 0x60);
+    let (second_lobby_id, second_side) = expect_match(&mut second, 0x60);
+    assert_eq!(first_lobby_id, second_lobby_id);
+    // the player who waited longer hosts, on the left.
+    assert_eq!((first_side, second_side), (0, 1));
+    start_game(&mut first, &mut second, MATCH_TICK_INTERVAL_MS);
+}
+
+#[test]
+fn opponent_cosmetics_exchanged() {
+    let address = start_server();
+    let mut host = TestClient::connect(address);
+    // a lobby with no custom id, whose host would like a '#' paddle in cyan.
+    host.send(
+        &[
+            &[0x08],
+            &u14(SLOW_TICK_INTERVAL_MS)[..],
+            &u14(0)[..],
+            &[0; 9],
+            &[1, 0, b'#', 6],
+        ]
+        .concat(),
+    );
+    let reply = host.receive();
+    assert_eq!(reply[0], 0x00);
+    let lobby_id: [u8; 4] = reply[1..5].try_into().unwrap();
+    // a joiner that says nothing of its paddle isn't told of the host's.
+    let mut joiner = TestClient::connect(address);
+    joiner.join_lobby(lobby_id);
+    assert_eq!(joiner.receive()[0], 0x10);
+    assert_eq!(host.receive()[0], 0x20);
+    host.expect(&[0x3C, 0, 0]);
+    joiner.expect_nothing(Duration::from_millis(200));
+
+    let mut first = TestClient::connect(address);
+    first.send(&[0x03, 1, b'@', 3]);
+    let mut second = TestClient::connect(address);
+    second.send(&[0x03, 1, 0, 2]);
+    expect_match(&mut first, 0x60);
+    expect_match(&mut second, 0x60);
+    first.expect(&[0x3C, 0, 2]);
+    second.expect(&[0x3C, b'@', 3]);
+    start_game(&mut first, &mut second, MATCH_TICK_INTERVAL_MS);
+}
+
+#[test]
+fn practice_against_the_wall() {
+    let address = start_server_with(|config| config.seed = Some(5));
+    let mut player = TestClient::connect(address);
+    player.send(&[&[0x09], &u14(FAST_TICK_INTERVAL_MS)[..], &[17]].concat());
+    // the player is seated on the left, as if they'd found a match.
+    assert_eq!(expect_match(&mut player, 0x60).1, 0);
+    for _ in 0..2 {
+        // the wall is always ready, so the game starts as soon as the player is.
+        player.send(&[0x10]);
+        player.expect(&[0x33]);
+        // the wall is a right paddle as tall as the field.
+        player.expect(
+            &[
+                &[0x37],
+                &u14(FAST_TICK_INTERVAL_MS)[..],
+                &[0, 0, 0, 0, 0, 11, 0, 0],
+            ]
+            .concat(),
+        );
+        // the player never moves, so misses eventually, but the wall never does.
+        assert_eq!(player.receive_skipping_game_states(), [0x41]);
+    }
+}
+
+#[test]
+fn coop_survival() {
+    // co-op games are never part of a match, whatever the server's match length.
+    let address = start_server_with(|config| config.match_length = Some(3));
+    let mut host = TestClient::connect(address);
+    host.send(&[&[0x0A], &u14(FAST_TICK_INTERVAL_MS)[..], &[64]].concat());
+    let reply = host.receive();
+    assert_eq!(reply[0], 0x00);
+    assert_eq!(reply[5..], DIMENSIONS);
+    let lobby_id: [u8; 4] = reply[1..5].try_into().unwrap();
+    // clients that can't play co-op can't join.
+    let mut old_client = TestClient::connect(address);
+    old_client.join_lobby(lobby_id);
+    old_client.expect(&[0x13]);
+    let mut partner = TestClient::connect(address);
+    partner.send(&[&[0x01], &lobby_id[..], &[64 | 32]].concat());
+    assert_eq!(partner.receive()[0], 0x10);
+    assert_eq!(host.receive()[0], 0x20);
+    partner.expect(
+        &[
+            &[0x38],
+            &u14(FAST_TICK_INTERVAL_MS)[..],
+            &u14(0)[..],
+            &[0, 0, 0, 0, 0, 0, 0, 0],
+            &[0],
+        ]
+        .concat(),
+    );
+    for client in [&mut partner, &mut host] {
+        client.expect(&[0x39]);
+    }
+    for _ in 0..2 {
+        host.send(&[0x10]);
+        host.expect(&[0x33]);
+        partner.expect(&[0x31]);
+        partner.send(&[0x10]);
+        partner.expect(&[0x33]);
+        for client in [&mut host, &mut partner] {
+            client.expect(&[&[0x35], &u14(FAST_TICK_INTERVAL_MS)[..]].concat());
+            // neither player serves, so the game starts straight away.
+            let result = loop {
+                let message = client.receive();
+                if message[0] != 0x4A {
+                    break message;
+                }
+                assert_eq!(message.len(), 14);
+            };
+            // the players never move, so the ball gets past them eventually, and they lose
+            // together.
+            assert_eq!(result, [0x41]);
+        }
+    }
+}
+
+#[test]
+fn leaving_the_match_queue() {
+    let address = start_server();
+    let mut leaver = TestClient::connect(address);
+    find_match(&mut leaver);
+    drop(leaver);
+    // give the server a moment to notice the connection closing.
+    std::thread::sleep(Duration::from_millis(200));
+    let mut first = TestClient::connect(address);
+    find_match(&mut first);
+    first.expect_nothing(Duration::from_millis(200));
+    let mut second = TestClient::connect(address);
+    find_match(&mut second);
+    assert_eq!(expect_match(&mut first, 0x60).1, 0);
+    assert_eq!(expect_match(&mut second, 0x60).1, 1);
+}
+
+#[test]
+fn tournament_final() {
+    let address = start_server();
+    let mut client = TestClient::connect(address);
+    client.send(&[&[0x05], &b"AAAA"[..], &[1]].concat());
+    client.expect(&[0x72]);
+    let mut players = [TestClient::connect(address), TestClient::connect(address)];
+    let tournament_id = new_tournament(&mut players[0], 2);
+    assert_eq!(fill_tournament(&mut players, tournament_id), [0, 1]);
+    let mut client = TestClient::connect(address);
+    client.send(&[&[0x05], &tournament_id[..], &[1]].concat());
+    client.expect(&[0x71]);
+    let [first, second] = &mut players;
+    start_game(first, second, MATCH_TICK_INTERVAL_MS);
+    let first_result = first.receive_skipping_game_states();
+    let second_result = second.receive_skipping_game_states();
+    let winner = if first_result == [0x42] { 0 } else { 1 };
+    assert_eq!(
+        [first_result, second_result][1 - winner],
+        [0x41],
+        "exactly one player wins"
+    );
+    // the match is over after one game, and with it the tournament.
+    for player in &mut players {
+        player.expect(&[0x77, winner as u8]);
+    }
+}
+
+#[test]
+fn tournament_walkover() {
+    let address = start_server();
+    let mut players: Vec<_> = (0..4).map(|_| TestClient::connect(address)).collect();
+    let tournament_id = new_tournament(&mut players[0], 4);
+    assert_eq!(fill_tournament(&mut players, tournament_id), [0, 1, 0, 1]);
+    // seat 1 leaves before their match, so seat 0 wins it.
+    let mut players: Vec<_> = players.into_iter().map(Some).collect();
+    players[1] = None;
+    let mut player = |seat: usize| players[seat].take().unwrap();
+    let (mut seat_0, mut seat_2, mut seat_3) = (player(0), player(2), player(3));
+    seat_0.expect(&[0x30]);
+    seat_0.expect(&[0x75, 1, 8, 0, 0, 0, 0, 0, 0]);
+    start_game(&mut seat_2, &mut seat_3, MATCH_TICK_INTERVAL_MS);
+    let (mut winner, mut loser) = if seat_2.receive_skipping_game_states() == [0x42] {
+        assert_eq!(seat_3.receive_skipping_game_states(), [0x41]);
+        (seat_2, seat_3)
+    } else {
+        assert_eq!(seat_3.receive_skipping_game_states(), [0x42]);
+        (seat_3, seat_2)
+    };
+    let bracket = loser.receive();
+    assert_eq!(bracket[..3], [0x75, 1, 8]);
+    for client in [&mut seat_0, &mut winner] {
+        assert_eq!(client.receive(), bracket);
+    }
+    assert_eq!(expect_match(&mut seat_0, 0x76).1, 0);
+    assert_eq!(expect_match(&mut winner, 0x76).1, 1);
+    // the other finalist leaves too, so seat 0 wins the tournament without playing.
+    drop(winner);
+    seat_0.expect(&[0x30]);
+    seat_0.expect(&[0x77, 0]);
+    loser.expect(&[0x77, 0]);
+}
+
+#[test]
+fn league_table() {
+    let address = start_server();
+    let mut players: Vec<_> = (0..3).map(|_| TestClient::connect(address)).collect();
+    players[0].send(&[0x06, 3, 1]);
+    let reply = players[0].receive();
+    assert_eq!((reply[0], &reply[5..]), (0x70, &[0, 3][..]));
+    let tournament_id: [u8; 4] = reply[1..5].try_into().unwrap();
+    for (seat, client) in players.iter_mut().enumerate() {
+        if seat > 0 {
+            client.send(&[&[0x05], &tournament_id[..], &[1]].concat());
+            client.expect(&[&[0x70], &tournament_id[..], &[seat as u8, 3]].concat());
+        }
+    }
+    for (seat, client) in players.iter_mut().enumerate() {
+        for joined in seat as u16 + 1..=3 {
+            client.expect(&[&[0x74], &u14(joined)[..]].concat());
+        }
+        client.expect(&[0x78, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+    // seat 0 sits out the first round.
+    let mut seat_2 = players.pop().unwrap();
+    let mut seat_1 = players.pop().unwrap();
+    let mut seat_0 = players.pop().unwrap();
+    assert_eq!(expect_match(&mut seat_1, 0x76).1, 0);
+    assert_eq!(expect_match(&mut seat_2, 0x76).1, 1);
+    // seat 2 leaves, losing their first match and forfeiting their second, against seat 0.
+    drop(seat_2);
+    seat_1.expect(&[0x30]);
+    let left = 2 << 3 | 1 << 6;
+    for client in [&mut seat_0, &mut seat_1] {
+        client.expect(&[0x78, 2, 1, 1, left, 0, 0, 0, 0, 0]);
+    }
+    assert_eq!(expect_match(&mut seat_0, 0x76).1, 0);
+    assert_eq!(expect_match(&mut seat_1, 0x76).1, 1);
+    start_game(&mut seat_0, &mut seat_1, MATCH_TICK_INTERVAL_MS);
+    let winner = if seat_0.receive_skipping_game_states() == [0x42] {
+        assert_eq!(seat_1.receive_skipping_game_states(), [0x41]);
+        0
+    } else {
+        assert_eq!(seat_1.receive_skipping_game_states(), [0x42]);
+        1
+    };
+    // the winner tops the table, having won both their matches.
+    let mut table = [1 << 3 | 1; 2];
+    table[winner] = 2;
+    for client in [&mut seat_0, &mut seat_1] {
+        client.expect(&[0x78, 3, table[0], table[1], left, 0, 0, 0, 0, 0]);
+        client.expect(&[0x77, winner as u8]);
+    }
+}
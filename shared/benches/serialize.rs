@@ -0,0 +1,36 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use shared::{
+    game_state::{Ball, GameState},
+    server_msg::{PlayingServerMessage, MAX_SERVER_MESSAGE_SIZE},
+    WriteInto,
+};
+
+fn game_state_updated() -> PlayingServerMessage {
+    PlayingServerMessage::GameStateUpdated {
+        game_state: GameState {
+            left_paddle: 3,
+            right_paddle: 5,
+            ball: Ball {
+                x: 25,
+                y: 7,
+                moving_right: true,
+                moving_down: false,
+            },
+        },
+    }
+}
+
+fn serialize_game_state(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialize game state");
+    group.bench_function("into vec", |b| {
+        b.iter(|| Vec::<u8>::from(black_box(game_state_updated())))
+    });
+    group.bench_function("write into buffer", |b| {
+        let mut buf = [0; MAX_SERVER_MESSAGE_SIZE];
+        b.iter(|| black_box(game_state_updated()).write_into(black_box(&mut buf)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, serialize_game_state);
+criterion_main!(benches);
@@ -0,0 +1,640 @@
+use proptest::{collection::vec, prelude::*};
+
+use crate::{
+    client_msg::{
+        AwaitingOpenClientMessage, AwaitingReadyClientMessage, Capabilities, PlayingClientMessage,
+    },
+    cosmetics::{Cosmetics, PaddleColour},
+    game_state::{
+        Ball, GameDimensions, GameState, Handicap, Handicaps, Modifier, MoveDirection, Ruleset,
+        Side, MAX_GAME_HEIGHT, MAX_GAME_WIDTH, MAX_PADDLE_CELLS_PER_TICK, MAX_SPEED_LEVEL,
+        MAX_TICK_INTERVAL_MS, MIN_TICK_INTERVAL_MS, NARROW_MAX_GAME_WIDTH,
+    },
+    server_msg::{
+        AwaitingJoinLobbyServerMessage, AwaitingLobbyPageServerMessage, AwaitingMatchServerMessage,
+        AwaitingNewLobbyServerMessage, AwaitingOpponentJoinServerMessage,
+        AwaitingReadyServerMessage, AwaitingServerStatusServerMessage,
+        AwaitingTournamentServerMessage, PlayingServerMessage, ProtocolState, ResyncServerMessage,
+        ServerVersion,
+    },
+    tournament::{
+        league_rounds, rounds, Bracket, BracketSeat, Standing, Standings, MAX_TOURNAMENT_PLAYERS,
+    },
+    LobbyId, LOBBY_ID_LEN, MAX_LOBBY_ID_LEN, MAX_U14, MAX_U28,
+};
+
+/// playable game dimensions. small fields are generated as often as large ones, so that both game
+/// state encodings are exercised.
+pub fn game_dimensions() -> impl Strategy<Value = GameDimensions> {
+    prop_oneof![
+        (5..=NARROW_MAX_GAME_WIDTH, 2..=16u16),
+        (5..=MAX_GAME_WIDTH, 2..=MAX_GAME_HEIGHT),
+    ]
+    .prop_flat_map(|(width, height)| (Just(width), Just(height), 1..=height))
+    .prop_map(|(width, height, paddle_height)| {
+        GameDimensions::new(width, height, paddle_height).unwrap()
+    })
+}
+
+/// a game state that is valid for a game of the given dimensions.
+pub fn game_state(dimensions: GameDimensions) -> impl Strategy<Value = GameState> {
+    let max_paddle_position = dimensions.max_paddle_position();
+    (
+        0..=max_paddle_position,
+        0..=max_paddle_position,
+        0..dimensions.width(),
+        0..dimensions.height(),
+        any::<bool>(),
+        any::<bool>(),
+    )
+        .prop_map(
+            |(left_paddle, right_paddle, x, y, moving_right, moving_down)| GameState {
+                left_paddle,
+                right_paddle,
+                ball: Ball {
+                    x,
+                    y,
+                    moving_right,
+                    moving_down,
+                },
+            },
+        )
+}
+
+/// a game state that is valid for a game of any dimensions.
+pub fn any_game_state() -> impl Strategy<Value = GameState> {
+    game_dimensions().prop_flat_map(game_state)
+}
+
+pub fn lobby_id() -> impl Strategy<Value = LobbyId> {
+    vec(0..32u8, LOBBY_ID_LEN..=MAX_LOBBY_ID_LEN).prop_map(|digits| LobbyId::from_digits(&digits))
+}
+
+pub fn capabilities() -> impl Strategy<Value = Capabilities> {
+    prop_oneof![
+        Just(Capabilities::NONE),
+        Just(Capabilities::WIDE_GAME_STATE),
+        Just(Capabilities::SPEED_LEVEL),
+        Just(Capabilities::LOCKSTEP),
+        Just(Capabilities::ALL),
+    ]
+}
+
+pub fn cosmetics() -> impl Strategy<Value = Cosmetics> {
+    let colour = prop_oneof![
+        Just(PaddleColour::Red),
+        Just(PaddleColour::Green),
+        Just(PaddleColour::Yellow),
+        Just(PaddleColour::Blue),
+        Just(PaddleColour::Magenta),
+        Just(PaddleColour::Cyan),
+        Just(PaddleColour::White),
+    ];
+    (
+        proptest::option::of(proptest::char::range('!', '~')),
+        proptest::option::of(colour),
+    )
+        .prop_map(|(glyph, colour)| Cosmetics { glyph, colour })
+}
+
+pub fn move_direction() -> impl Strategy<Value = MoveDirection> {
+    prop_oneof![
+        Just(MoveDirection::Stop),
+        Just(MoveDirection::Up),
+        Just(MoveDirection::Down),
+    ]
+}
+
+/// handicaps of any size, since the server fits them to its field.
+pub fn handicaps() -> impl Strategy<Value = Handicaps> {
+    let handicap = (
+        proptest::option::of(1..=MAX_U14),
+        proptest::option::of(1..=MAX_PADDLE_CELLS_PER_TICK),
+    )
+        .prop_map(|(paddle_height, paddle_speed)| Handicap {
+            paddle_height,
+            paddle_speed,
+        });
+    (handicap.clone(), handicap).prop_map(|(left, right)| Handicaps { left, right })
+}
+
+pub fn modifier() -> impl Strategy<Value = Modifier> {
+    prop_oneof![
+        Just(Modifier::Classic),
+        Just(Modifier::Gravity),
+        Just(Modifier::Wobble),
+        Just(Modifier::Wrap),
+    ]
+}
+
+pub fn ruleset() -> impl Strategy<Value = Ruleset> {
+    (
+        tick_interval_ms(),
+        proptest::option::of(1..=MAX_U14),
+        handicaps(),
+        modifier(),
+    )
+        .prop_map(
+            |(tick_interval_ms, match_length, handicaps, modifier)| Ruleset {
+                tick_interval_ms,
+                match_length,
+                handicaps,
+                modifier,
+            },
+        )
+}
+
+pub fn side() -> impl Strategy<Value = Side> {
+    prop_oneof![Just(Side::Left), Just(Side::Right)]
+}
+
+pub fn tournament_size() -> impl Strategy<Value = u8> {
+    prop_oneof![Just(2), Just(4), Just(8)]
+}
+
+pub fn league_size() -> impl Strategy<Value = u8> {
+    2..=MAX_TOURNAMENT_PLAYERS
+}
+
+pub fn seat() -> impl Strategy<Value = u8> {
+    0..MAX_TOURNAMENT_PLAYERS
+}
+
+pub fn bracket() -> impl Strategy<Value = Bracket> {
+    proptest::array::uniform8((0..=rounds(MAX_TOURNAMENT_PLAYERS), any::<bool>())).prop_map(
+        |seats| Bracket {
+            seats: seats.map(|(wins, eliminated)| BracketSeat { wins, eliminated }),
+        },
+    )
+}
+
+pub fn standings() -> impl Strategy<Value = Standings> {
+    let max_rounds = league_rounds(MAX_TOURNAMENT_PLAYERS);
+    let standing = (0..=max_rounds, any::<bool>())
+        .prop_flat_map(move |(played, left)| (0..=played, Just(played), Just(left)))
+        .prop_map(|(wins, played, left)| Standing {
+            wins,
+            losses: played - wins,
+            left,
+        });
+    (0..=max_rounds, proptest::array::uniform8(standing))
+        .prop_map(|(round, seats)| Standings { round, seats })
+}
+
+fn tick_interval_ms() -> impl Strategy<Value = u16> {
+    MIN_TICK_INTERVAL_MS..=MAX_TICK_INTERVAL_MS
+}
+
+fn timeout_secs() -> impl Strategy<Value = u16> {
+    0..=MAX_U14
+}
+
+pub fn awaiting_open_client_message() -> impl Strategy<Value = AwaitingOpenClientMessage> {
+    prop_oneof![
+        (
+            tick_interval_ms(),
+            capabilities(),
+            proptest::option::of(lobby_id()),
+            handicaps(),
+            modifier(),
+        )
+            .prop_map(
+                |(tick_interval_ms, capabilities, custom_id, handicaps, modifier)| {
+                    AwaitingOpenClientMessage::NewLobby {
+                        tick_interval_ms,
+                        capabilities,
+                        custom_id,
+                        handicaps,
+                        modifier,
+                    }
+                },
+            ),
+        (
+            lobby_id(),
+            capabilities(),
+            proptest::option::of(cosmetics())
+        )
+            .prop_map(|(lobby_id, capabilities, cosmetics)| {
+                AwaitingOpenClientMessage::JoinLobby {
+                    lobby_id,
+                    capabilities,
+                    cosmetics,
+                }
+            }),
+        Just(AwaitingOpenClientMessage::GetServerStatus),
+        (capabilities(), proptest::option::of(cosmetics())).prop_map(
+            |(capabilities, cosmetics)| AwaitingOpenClientMessage::FindMatch {
+                capabilities,
+                cosmetics,
+            }
+        ),
+        (
+            tournament_size(),
+            capabilities(),
+            proptest::option::of(cosmetics())
+        )
+            .prop_map(|(players, capabilities, cosmetics)| {
+                AwaitingOpenClientMessage::NewTournament {
+                    players,
+                    capabilities,
+                    cosmetics,
+                }
+            }),
+        (
+            lobby_id(),
+            capabilities(),
+            proptest::option::of(cosmetics())
+        )
+            .prop_map(|(tournament_id, capabilities, cosmetics)| {
+                AwaitingOpenClientMessage::JoinTournament {
+                    tournament_id,
+                    capabilities,
+                    cosmetics,
+                }
+            }),
+        (
+            league_size(),
+            capabilities(),
+            proptest::option::of(cosmetics())
+        )
+            .prop_map(|(players, capabilities, cosmetics)| {
+                AwaitingOpenClientMessage::NewLeague {
+                    players,
+                    capabilities,
+                    cosmetics,
+                }
+            }),
+        (0..=MAX_U14, capabilities()).prop_map(|(page, capabilities)| {
+            AwaitingOpenClientMessage::BrowseLobbies { page, capabilities }
+        }),
+        (
+            ruleset(),
+            capabilities(),
+            proptest::option::of(lobby_id()),
+            proptest::option::of(cosmetics())
+        )
+            .prop_map(|(ruleset, capabilities, custom_id, cosmetics)| {
+                AwaitingOpenClientMessage::NewLobbyWithRuleset {
+                    ruleset,
+                    capabilities,
+                    custom_id,
+                    cosmetics,
+                }
+            }),
+        (tick_interval_ms(), capabilities()).prop_map(|(tick_interval_ms, capabilities)| {
+            AwaitingOpenClientMessage::Practice {
+                tick_interval_ms,
+                capabilities,
+            }
+        }),
+        (
+            tick_interval_ms(),
+            capabilities(),
+            proptest::option::of(lobby_id()),
+            proptest::option::of(cosmetics())
+        )
+            .prop_map(|(tick_interval_ms, capabilities, custom_id, cosmetics)| {
+                AwaitingOpenClientMessage::NewCoopLobby {
+                    tick_interval_ms,
+                    capabilities,
+                    custom_id,
+                    cosmetics,
+                }
+            }),
+    ]
+}
+
+pub fn awaiting_ready_client_message() -> impl Strategy<Value = AwaitingReadyClientMessage> {
+    prop_oneof![
+        Just(AwaitingReadyClientMessage::Ready),
+        Just(AwaitingReadyClientMessage::Unready),
+        Just(AwaitingReadyClientMessage::LeaveLobby),
+    ]
+}
+
+pub fn playing_client_message() -> impl Strategy<Value = PlayingClientMessage> {
+    prop_oneof![
+        move_direction().prop_map(|direction| PlayingClientMessage::MoveIntent { direction }),
+        Just(PlayingClientMessage::Resign),
+        Just(PlayingClientMessage::Serve),
+    ]
+}
+
+pub fn awaiting_new_lobby_server_message() -> impl Strategy<Value = AwaitingNewLobbyServerMessage> {
+    prop_oneof![
+        (lobby_id(), game_dimensions()).prop_map(|(lobby_id, dimensions)| {
+            AwaitingNewLobbyServerMessage::NewLobbyCreated {
+                lobby_id,
+                dimensions,
+            }
+        }),
+        Just(AwaitingNewLobbyServerMessage::IdTaken),
+        Just(AwaitingNewLobbyServerMessage::TooManyLobbies),
+        Just(AwaitingNewLobbyServerMessage::LobbyIncompatible),
+    ]
+}
+
+pub fn awaiting_server_status_server_message(
+) -> impl Strategy<Value = AwaitingServerStatusServerMessage> {
+    (
+        0..=MAX_U14,
+        0..=MAX_U14,
+        (0..=MAX_U14, 0..=MAX_U14, 0..=MAX_U14),
+        0..=MAX_U28,
+    )
+        .prop_map(
+            |(players_online, open_lobbies, (major, minor, patch), uptime_secs)| {
+                AwaitingServerStatusServerMessage::ServerStatus {
+                    players_online,
+                    open_lobbies,
+                    version: ServerVersion {
+                        major,
+                        minor,
+                        patch,
+                    },
+                    uptime_secs,
+                }
+            },
+        )
+}
+
+pub fn awaiting_lobby_page_server_message() -> impl Strategy<Value = AwaitingLobbyPageServerMessage>
+{
+    prop_oneof![
+        (lobby_id(), tick_interval_ms(), 0..=MAX_U28).prop_map(
+            |(lobby_id, tick_interval_ms, age_secs)| AwaitingLobbyPageServerMessage::LobbyListed {
+                lobby_id,
+                tick_interval_ms,
+                age_secs,
+            }
+        ),
+        (0..=MAX_U14, 1..=MAX_U14)
+            .prop_map(|(page, pages)| AwaitingLobbyPageServerMessage::LobbyPageEnd { page, pages }),
+    ]
+}
+
+pub fn awaiting_join_lobby_server_message() -> impl Strategy<Value = AwaitingJoinLobbyServerMessage>
+{
+    prop_oneof![
+        (timeout_secs(), game_dimensions()).prop_map(|(ready_timeout_secs, dimensions)| {
+            AwaitingJoinLobbyServerMessage::JoinedLobby {
+                ready_timeout_secs,
+                dimensions,
+            }
+        }),
+        Just(AwaitingJoinLobbyServerMessage::LobbyFull),
+        Just(AwaitingJoinLobbyServerMessage::LobbyNotFound),
+        Just(AwaitingJoinLobbyServerMessage::LobbyIncompatible),
+    ]
+}
+
+pub fn awaiting_opponent_join_server_message(
+) -> impl Strategy<Value = AwaitingOpponentJoinServerMessage> {
+    prop_oneof![
+        timeout_secs().prop_map(|ready_timeout_secs| {
+            AwaitingOpponentJoinServerMessage::OpponentJoined { ready_timeout_secs }
+        }),
+        timeout_secs()
+            .prop_map(|seconds| AwaitingOpponentJoinServerMessage::LobbyExpiringSoon { seconds }),
+        Just(AwaitingOpponentJoinServerMessage::LobbyExpired),
+    ]
+}
+
+pub fn awaiting_match_server_message() -> impl Strategy<Value = AwaitingMatchServerMessage> {
+    (lobby_id(), side(), timeout_secs(), game_dimensions()).prop_map(
+        |(lobby_id, side, ready_timeout_secs, dimensions)| AwaitingMatchServerMessage::MatchFound {
+            lobby_id,
+            side,
+            ready_timeout_secs,
+            dimensions,
+        },
+    )
+}
+
+pub fn awaiting_tournament_server_message() -> impl Strategy<Value = AwaitingTournamentServerMessage>
+{
+    prop_oneof![
+        (lobby_id(), seat(), league_size()).prop_map(|(tournament_id, seat, players)| {
+            AwaitingTournamentServerMessage::JoinedTournament {
+                tournament_id,
+                seat,
+                players,
+            }
+        }),
+        Just(AwaitingTournamentServerMessage::TournamentFull),
+        Just(AwaitingTournamentServerMessage::TournamentNotFound),
+        Just(AwaitingTournamentServerMessage::TournamentIncompatible),
+        (0..=MAX_TOURNAMENT_PLAYERS as u16)
+            .prop_map(|players| AwaitingTournamentServerMessage::PlayersJoined { players }),
+        bracket().prop_map(|bracket| AwaitingTournamentServerMessage::BracketUpdated { bracket }),
+        (lobby_id(), side(), timeout_secs(), game_dimensions()).prop_map(
+            |(lobby_id, side, ready_timeout_secs, dimensions)| {
+                AwaitingTournamentServerMessage::MatchReady {
+                    lobby_id,
+                    side,
+                    ready_timeout_secs,
+                    dimensions,
+                }
+            }
+        ),
+        seat().prop_map(|seat| AwaitingTournamentServerMessage::TournamentWon { seat }),
+        standings()
+            .prop_map(|standings| AwaitingTournamentServerMessage::StandingsUpdated { standings }),
+    ]
+}
+
+pub fn awaiting_ready_server_message() -> impl Strategy<Value = AwaitingReadyServerMessage> {
+    prop_oneof![
+        Just(AwaitingReadyServerMessage::OpponentLeft),
+        Just(AwaitingReadyServerMessage::OpponentReadied),
+        Just(AwaitingReadyServerMessage::OpponentUnreadied),
+        Just(AwaitingReadyServerMessage::YouReadied),
+        Just(AwaitingReadyServerMessage::YouUnreadied),
+        tick_interval_ms().prop_map(|tick_interval_ms| AwaitingReadyServerMessage::GameStarted {
+            tick_interval_ms
+        }),
+        Just(AwaitingReadyServerMessage::LobbyTimedOut),
+        (tick_interval_ms(), handicaps()).prop_map(|(tick_interval_ms, handicaps)| {
+            AwaitingReadyServerMessage::HandicappedGameStarted {
+                tick_interval_ms,
+                handicaps,
+            }
+        }),
+        ruleset().prop_map(|ruleset| AwaitingReadyServerMessage::LobbyRuleset { ruleset }),
+        Just(AwaitingReadyServerMessage::CoopLobby),
+        Just(AwaitingReadyServerMessage::OpponentLeftLobby),
+        lobby_id().prop_map(|lobby_id| AwaitingReadyServerMessage::PromotedToHost { lobby_id }),
+        cosmetics()
+            .prop_map(|cosmetics| AwaitingReadyServerMessage::OpponentCosmetics { cosmetics }),
+        Just(AwaitingReadyServerMessage::LockstepGame),
+    ]
+}
+
+pub fn playing_server_message() -> impl Strategy<Value = PlayingServerMessage> {
+    prop_oneof![
+        Just(PlayingServerMessage::OpponentLeft),
+        Just(PlayingServerMessage::OpponentWon),
+        Just(PlayingServerMessage::YouWon),
+        any_game_state()
+            .prop_map(|game_state| PlayingServerMessage::GameStateUpdated { game_state }),
+        Just(PlayingServerMessage::OpponentResigned),
+        (1..=MAX_SPEED_LEVEL)
+            .prop_map(|speed_level| PlayingServerMessage::BallSpedUp { speed_level }),
+        side().prop_map(|side| PlayingServerMessage::Serving { side }),
+        Just(PlayingServerMessage::Deuce),
+        Just(PlayingServerMessage::SuddenDeath),
+        (any_game_state(), 0..MAX_GAME_HEIGHT, 0..=MAX_U14).prop_map(
+            |(game_state, partner_paddle, score)| PlayingServerMessage::CoopStateUpdated {
+                game_state,
+                partner_paddle,
+                score,
+            }
+        ),
+        (0..=MAX_U14, 0..=MAX_U14)
+            .prop_filter("a tied match has no match point", |(left, right)| left
+                != right)
+            .prop_map(|(left_wins, right_wins)| PlayingServerMessage::MatchPoint {
+                left_wins,
+                right_wins,
+            }),
+    ]
+}
+
+pub fn protocol_state() -> impl Strategy<Value = ProtocolState> {
+    prop_oneof![
+        Just(ProtocolState::Open),
+        Just(ProtocolState::AwaitingOpponentJoin),
+        Just(ProtocolState::AwaitingMatch),
+        Just(ProtocolState::AwaitingTournament),
+        (any::<bool>(), any::<bool>()).prop_map(|(you_ready, opponent_ready)| {
+            ProtocolState::AwaitingReady {
+                you_ready,
+                opponent_ready,
+            }
+        }),
+        Just(ProtocolState::Playing),
+    ]
+}
+
+pub fn resync_server_message() -> impl Strategy<Value = ResyncServerMessage> {
+    protocol_state().prop_map(|state| ResyncServerMessage::FullState { state })
+}
+
+/// any sequence of bytes a peer could send as a single message, delimiter aside.
+pub fn message_bytes() -> impl Strategy<Value = Vec<u8>> {
+    vec(any::<u8>(), 0..=2 * MAX_LOBBY_ID_LEN + 16)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::{
+        arbitrary::{
+            any_game_state, awaiting_join_lobby_server_message, awaiting_lobby_page_server_message,
+            awaiting_match_server_message, awaiting_new_lobby_server_message,
+            awaiting_open_client_message, awaiting_opponent_join_server_message,
+            awaiting_ready_client_message, awaiting_ready_server_message,
+            awaiting_server_status_server_message, awaiting_tournament_server_message,
+            game_dimensions, game_state, message_bytes, playing_client_message,
+            playing_server_message, resync_server_message,
+        },
+        client_msg::{
+            AwaitingOpenClientMessage, AwaitingOpponentJoinClientMessage,
+            AwaitingReadyClientMessage, ChecksumClientMessage, PlayingClientMessage,
+            ResyncClientMessage,
+        },
+        server_msg::{
+            AwaitingJoinLobbyServerMessage, AwaitingLobbyPageServerMessage,
+            AwaitingMatchServerMessage, AwaitingNewLobbyServerMessage,
+            AwaitingOpponentJoinServerMessage, AwaitingReadyServerMessage,
+            AwaitingServerStatusServerMessage, AwaitingTournamentServerMessage,
+            ChecksumServerMessage, PlayingServerMessage, RejectedServerMessage,
+            ResyncServerMessage,
+        },
+    };
+
+    /// asserts that a message deserializes back into itself once serialized.
+    macro_rules! prop_assert_serialize_and_back {
+        ($message:expr $(,)?) => {
+            prop_assert_eq!(
+                Vec::<u8>::from($message.clone()).as_slice().try_into(),
+                Ok($message)
+            )
+        };
+    }
+
+    proptest! {
+        #[test]
+        fn client_messages_serialize_and_back(
+            awaiting_open in awaiting_open_client_message(),
+            awaiting_ready in awaiting_ready_client_message(),
+            playing in playing_client_message(),
+        ) {
+            prop_assert_serialize_and_back!(awaiting_open);
+            prop_assert_serialize_and_back!(awaiting_ready);
+            prop_assert_serialize_and_back!(playing);
+        }
+
+        #[test]
+        fn server_messages_serialize_and_back(
+            awaiting_server_status in awaiting_server_status_server_message(),
+            awaiting_lobby_page in awaiting_lobby_page_server_message(),
+            awaiting_new_lobby in awaiting_new_lobby_server_message(),
+            awaiting_join_lobby in awaiting_join_lobby_server_message(),
+            awaiting_opponent_join in awaiting_opponent_join_server_message(),
+            awaiting_match in awaiting_match_server_message(),
+            awaiting_tournament in awaiting_tournament_server_message(),
+            awaiting_ready in awaiting_ready_server_message(),
+            playing in playing_server_message(),
+            resync in resync_server_message(),
+        ) {
+            prop_assert_serialize_and_back!(awaiting_server_status);
+            prop_assert_serialize_and_back!(awaiting_lobby_page);
+            prop_assert_serialize_and_back!(awaiting_new_lobby);
+            prop_assert_serialize_and_back!(awaiting_join_lobby);
+            prop_assert_serialize_and_back!(awaiting_opponent_join);
+            prop_assert_serialize_and_back!(awaiting_match);
+            prop_assert_serialize_and_back!(awaiting_tournament);
+            prop_assert_serialize_and_back!(awaiting_ready);
+            prop_assert_serialize_and_back!(playing);
+            prop_assert_serialize_and_back!(resync);
+        }
+
+        #[test]
+        fn game_states_never_serialize_to_delimiter(game_state in any_game_state()) {
+            let bytes = Vec::<u8>::from(PlayingServerMessage::GameStateUpdated { game_state });
+            prop_assert!(!bytes.contains(&crate::server_msg::SERVER_MESSAGE_DELIMITER));
+        }
+
+        #[test]
+        fn generated_game_states_are_valid(
+            (dimensions, game_state) in game_dimensions()
+                .prop_flat_map(|dimensions| (Just(dimensions), game_state(dimensions))),
+        ) {
+            prop_assert_eq!(dimensions.validate(&game_state), Ok(()));
+        }
+
+        #[test]
+        fn deserialize_never_panics(bytes in message_bytes()) {
+            let _ = AwaitingOpenClientMessage::try_from(bytes.as_slice());
+            let _ = AwaitingReadyClientMessage::try_from(bytes.as_slice());
+            let _ = PlayingClientMessage::try_from(bytes.as_slice());
+            let _ = AwaitingOpponentJoinClientMessage::try_from(bytes.as_slice());
+            let _ = ResyncClientMessage::try_from(bytes.as_slice());
+            let _ = ChecksumClientMessage::try_from(bytes.as_slice());
+            let _ = AwaitingServerStatusServerMessage::try_from(bytes.as_slice());
+            let _ = AwaitingLobbyPageServerMessage::try_from(bytes.as_slice());
+            let _ = AwaitingNewLobbyServerMessage::try_from(bytes.as_slice());
+            let _ = AwaitingJoinLobbyServerMessage::try_from(bytes.as_slice());
+            let _ = AwaitingOpponentJoinServerMessage::try_from(bytes.as_slice());
+            let _ = AwaitingMatchServerMessage::try_from(bytes.as_slice());
+            let _ = AwaitingTournamentServerMessage::try_from(bytes.as_slice());
+            let _ = AwaitingReadyServerMessage::try_from(bytes.as_slice());
+            let _ = PlayingServerMessage::try_from(bytes.as_slice());
+            let _ = RejectedServerMessage::try_from(bytes.as_slice());
+            let _ = ResyncServerMessage::try_from(bytes.as_slice());
+            let _ = ChecksumServerMessage::try_from(bytes.as_slice());
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! the checksum a connection can agree to end every frame with, over the message before it, so that
+//! a message that was corrupted on its way, or cut short by a framing bug, is caught as such rather
+//! than being read as some other message.
+
+use crate::{serialize_u14, MAX_U14};
+
+/// the number of bytes the checksum adds to each frame, between its message and its delimiter.
+pub const CHECKSUM_SIZE: usize = 2;
+
+/// the crc-16/ccitt-false of the message, cut down to 14 bits so that it can be written as two 7
+/// bit bytes, neither of which can be mistaken for a delimiter.
+pub fn checksum(message: &[u8]) -> [u8; CHECKSUM_SIZE] {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in message {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x1021
+            };
+        }
+    }
+    serialize_u14(crc & MAX_U14)
+}
+
+/// the message a frame carries ahead of its checksum, or nothing if the checksum doesn't match it.
+pub fn verify(frame: &[u8]) -> Option<&[u8]> {
+    let (message, frame_checksum) = frame.split_at(frame.len().checked_sub(CHECKSUM_SIZE)?);
+    (checksum(message) == frame_checksum).then_some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checksum::{checksum, verify};
+
+    #[test]
+    fn checksum_matches_crc() {
+        // the crc-16/ccitt-false check value, 0x29B1, split into 7 bit bytes.
+        assert_eq!(checksum(b"123456789"), [0x53, 0x31]);
+        assert_eq!(checksum(&[]), [0x7F, 0x7F]);
+    }
+
+    #[test]
+    fn verify_strips_checksum() {
+        let frame = [&[0x43, 1, 2, 3][..], &checksum(&[0x43, 1, 2, 3])].concat();
+        assert_eq!(verify(&frame), Some([0x43, 1, 2, 3].as_slice()));
+        assert_eq!(verify(&checksum(&[])), Some([].as_slice()));
+    }
+
+    #[test]
+    fn verify_catches_corruption() {
+        let frame = [&[0x43, 1, 2, 3][..], &checksum(&[0x43, 1, 2, 3])].concat();
+        for i in 0..frame.len() {
+            for bit in 0..7 {
+                let mut corrupted = frame.clone();
+                corrupted[i] ^= 1 << bit;
+                assert_eq!(verify(&corrupted), None, "byte {i} bit {bit}");
+            }
+        }
+        // a frame cut short loses part of its message or checksum.
+        assert_eq!(verify(&frame[1..]), None);
+        assert_eq!(verify(&frame[..frame.len() - 1]), None);
+        assert_eq!(verify(&[0x7F]), None);
+    }
+}
@@ -1,110 +1,281 @@
+use shared_derive::PongMessage;
+
 use super::{
-    validate_byte_count, validate_state_and_get_message_id, DeserializeMessageError, LOBBY_ID_LEN,
+    cosmetics::Cosmetics,
+    game_state::{Handicaps, Modifier, MoveDirection, Ruleset},
+    tournament::{LeagueSize, TournamentSize},
+    Count, DeserializeMessageError, FieldCodec, LobbyId, TickInterval, MAX_LOBBY_ID_LEN,
 };
 
 /// the largest number of bytes a serialized client message could take up.
-/// [`AwaitingOpenClientMessage::JoinLobby`] is the largest client message when serialized (one byte for the identifier + lobby id length).
-pub const MAX_CLIENT_MESSAGE_SIZE: usize = 1 + LOBBY_ID_LEN;
+/// [`AwaitingOpenClientMessage::NewLobbyWithRuleset`] is the largest client message when serialized (one byte for the identifier + thirteen bytes for the ruleset + two bytes for the capabilities + one byte for the length of the longest custom id + the id itself + two bytes for the cosmetics).
+pub const MAX_CLIENT_MESSAGE_SIZE: usize = 1 + 13 + 2 + 1 + MAX_LOBBY_ID_LEN + 2;
 
-#[cfg_attr(test, derive(Clone, Debug, PartialEq))]
-pub enum AwaitingOpenClientMessage<'a> {
-    NewLobby,
-    JoinLobby { lobby_id: &'a str },
-}
+/// this byte is appended to the end of every client message to indicate termination.
+/// we must therefore ensure that no other bytes in a message must serialize to this value.
+pub const CLIENT_MESSAGE_DELIMITER: u8 = u8::MAX;
 
-#[cfg_attr(test, derive(Clone, Debug, PartialEq))]
-pub enum AwaitingReadyClientMessage {
-    Ready,
-    Unready,
-}
+/// optional protocol features a client supports, sent when it creates or joins a lobby.
+/// older clients don't send their capabilities at all, so are treated as supporting none.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities(u16);
 
-#[cfg_attr(test, derive(Clone, Debug, PartialEq))]
-pub enum PlayingClientMessage {
-    MovePaddle { pos: u8 },
+impl Capabilities {
+    pub const NONE: Capabilities = Capabilities(0);
+    /// the client can receive game states of fields too large for the narrow encoding.
+    pub const WIDE_GAME_STATE: Capabilities = Capabilities(1);
+    /// the client can be told how fast the ball is going.
+    pub const SPEED_LEVEL: Capabilities = Capabilities(2);
+    /// the client can be told who is serving.
+    pub const SERVE: Capabilities = Capabilities(4);
+    /// the client can be told when a game could decide a match, whether it's tied or not.
+    pub const TIEBREAK: Capabilities = Capabilities(8);
+    /// the client can play games in which the players' paddles differ.
+    pub const HANDICAPS: Capabilities = Capabilities(16);
+    /// the client can be told the ruleset of the lobby it's in.
+    pub const RULESET: Capabilities = Capabilities(32);
+    /// the client can play co-op survival lobbies, against a paddle steered by the server.
+    pub const COOP: Capabilities = Capabilities(64);
+    /// the client can play games in lockstep, answering every game state with its paddle's
+    /// direction once told the game is in lockstep.
+    pub const LOCKSTEP: Capabilities = Capabilities(128);
+    /// every capability this version of the protocol has.
+    pub const ALL: Capabilities = Capabilities(
+        Capabilities::WIDE_GAME_STATE.0
+            | Capabilities::SPEED_LEVEL.0
+            | Capabilities::SERVE.0
+            | Capabilities::TIEBREAK.0
+            | Capabilities::HANDICAPS.0
+            | Capabilities::RULESET.0
+            | Capabilities::COOP.0
+            | Capabilities::LOCKSTEP.0,
+    );
+
+    pub fn contains(self, other: Capabilities) -> bool {
+        self.0 & other.0 == other.0
+    }
 }
 
-impl From<AwaitingOpenClientMessage<'_>> for Vec<u8> {
-    fn from(value: AwaitingOpenClientMessage) -> Self {
-        match value {
-            AwaitingOpenClientMessage::NewLobby => vec![0],
-            AwaitingOpenClientMessage::JoinLobby { lobby_id } => {
-                [&[1], lobby_id.as_bytes()].concat()
-            }
+/// the first seven capabilities fit in one byte, as older clients sent them. any more are sent in
+/// a byte before those seven, whose top bit is set to say so, and whose next bit is never set, so
+/// that it can't equal the message delimiter. that leaves room for thirteen capabilities.
+impl FieldCodec<Capabilities> for Capabilities {
+    const SIZE: usize = 2;
+
+    fn size(value: &Capabilities) -> usize {
+        if value.0 >> 7 == 0 {
+            1
+        } else {
+            2
         }
     }
-}
 
-impl<'a> TryFrom<&'a [u8]> for AwaitingOpenClientMessage<'a> {
-    type Error = DeserializeMessageError;
+    fn read_size(bytes: &[u8]) -> usize {
+        match bytes.first() {
+            Some(byte) if byte >> 7 == 1 => 2,
+            _ => 1,
+        }
+    }
 
-    fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
-        match validate_state_and_get_message_id(value, 0)? {
-            0 => {
-                validate_byte_count(value, 1)?;
-                Ok(Self::NewLobby)
+    fn write(value: &Capabilities, buf: &mut [u8]) {
+        match buf {
+            [low] => *low = value.0 as u8,
+            [high, low] => {
+                *high = 0b10000000 | (value.0 >> 7) as u8 & 0b111111;
+                *low = value.0 as u8 & 0b1111111;
             }
-            1 => {
-                validate_byte_count(value, LOBBY_ID_LEN + 1)?;
-                let lobby_id = std::str::from_utf8(&value[1..])
-                    .map_err(|err| DeserializeMessageError::Utf8Error(err))?;
-                Ok(Self::JoinLobby { lobby_id })
+            _ => unreachable!(),
+        }
+    }
+
+    fn read(bytes: &[u8]) -> Result<Capabilities, DeserializeMessageError> {
+        // unknown capabilities are kept, but no byte can equal the message delimiter.
+        match *bytes {
+            [low] => Ok(Capabilities(low as u16)),
+            [high, low] if high >> 6 == 0b10 && low >> 7 == 0 => {
+                Ok(Capabilities((high as u16 & 0b111111) << 7 | low as u16))
             }
-            _ => Err(DeserializeMessageError::UnrecognisedMessageVariant),
+            _ => Err(DeserializeMessageError::InvalidCapabilities),
         }
     }
 }
 
-impl From<AwaitingReadyClientMessage> for Vec<u8> {
-    fn from(value: AwaitingReadyClientMessage) -> Self {
-        let mut bytes = match value {
-            AwaitingReadyClientMessage::Ready => vec![0],
-            AwaitingReadyClientMessage::Unready => vec![1],
-        };
-        bytes[0] |= 1 << 4;
-        bytes
-    }
+#[derive(PongMessage)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Clone, Debug, PartialEq))]
+#[pong(state = 0, max_size = MAX_CLIENT_MESSAGE_SIZE)]
+pub enum AwaitingOpenClientMessage {
+    #[pong(id = 0)]
+    NewLobby {
+        #[pong(codec = TickInterval)]
+        tick_interval_ms: u16,
+        #[pong(optional)]
+        capabilities: Capabilities,
+        /// the id the host would like the lobby to have, rather than a generated one.
+        #[pong(codec = LobbyId, optional)]
+        custom_id: Option<LobbyId>,
+        /// how each player's paddle should differ from the field's.
+        #[pong(optional)]
+        handicaps: Handicaps,
+        /// how the ball should move, for a livelier game than the classic one.
+        #[pong(optional)]
+        modifier: Modifier,
+    },
+    #[pong(id = 1)]
+    JoinLobby {
+        lobby_id: LobbyId,
+        #[pong(optional)]
+        capabilities: Capabilities,
+        /// how the player would like their paddle drawn for their opponents, which clients that
+        /// leave it out aren't told about their opponents' choices either.
+        #[pong(codec = Cosmetics, optional)]
+        cosmetics: Option<Cosmetics>,
+    },
+    /// asks about the server, without creating or joining a lobby.
+    #[pong(id = 2)]
+    GetServerStatus,
+    /// joins the queue of players looking for an opponent, rather than a particular lobby.
+    #[pong(id = 3)]
+    FindMatch {
+        #[pong(optional)]
+        capabilities: Capabilities,
+        #[pong(codec = Cosmetics, optional)]
+        cosmetics: Option<Cosmetics>,
+    },
+    /// creates a single elimination tournament, which starts once all of its players have joined.
+    #[pong(id = 4)]
+    NewTournament {
+        #[pong(codec = TournamentSize)]
+        players: u8,
+        #[pong(optional)]
+        capabilities: Capabilities,
+        #[pong(codec = Cosmetics, optional)]
+        cosmetics: Option<Cosmetics>,
+    },
+    /// joins a tournament or a league, which are joined the same way.
+    #[pong(id = 5)]
+    JoinTournament {
+        tournament_id: LobbyId,
+        #[pong(optional)]
+        capabilities: Capabilities,
+        #[pong(codec = Cosmetics, optional)]
+        cosmetics: Option<Cosmetics>,
+    },
+    /// creates a round robin league, in which every player plays every other player once.
+    #[pong(id = 6)]
+    NewLeague {
+        #[pong(codec = LeagueSize)]
+        players: u8,
+        #[pong(optional)]
+        capabilities: Capabilities,
+        #[pong(codec = Cosmetics, optional)]
+        cosmetics: Option<Cosmetics>,
+    },
+    /// lists a page of the lobbies waiting for an opponent, leaving out any the client couldn't
+    /// join.
+    #[pong(id = 7)]
+    BrowseLobbies {
+        #[pong(codec = Count)]
+        page: u16,
+        #[pong(optional)]
+        capabilities: Capabilities,
+    },
+    /// creates a lobby played under the given ruleset, which supersedes
+    /// [`AwaitingOpenClientMessage::NewLobby`]. the server may adjust the ruleset to suit the
+    /// field, and tells both players what it settled on once the lobby has been joined.
+    #[pong(id = 8)]
+    NewLobbyWithRuleset {
+        ruleset: Ruleset,
+        capabilities: Capabilities,
+        /// the id the host would like the lobby to have, rather than a generated one.
+        #[pong(codec = LobbyId, optional)]
+        custom_id: Option<LobbyId>,
+        #[pong(codec = Cosmetics, optional)]
+        cosmetics: Option<Cosmetics>,
+    },
+    /// starts a practice lobby, in which the client plays alone against a wall that returns every
+    /// ball. the server seats the client on the left, as if it had found a match.
+    #[pong(id = 9)]
+    Practice {
+        #[pong(codec = TickInterval)]
+        tick_interval_ms: u16,
+        capabilities: Capabilities,
+    },
+    /// creates a co-op survival lobby, in which the host and whoever joins both defend the left
+    /// side of the field against a paddle steered by the server. only clients with
+    /// [`Capabilities::COOP`] can join it.
+    #[pong(id = 10)]
+    NewCoopLobby {
+        #[pong(codec = TickInterval)]
+        tick_interval_ms: u16,
+        capabilities: Capabilities,
+        /// the id the host would like the lobby to have, rather than a generated one.
+        #[pong(codec = LobbyId, optional)]
+        custom_id: Option<LobbyId>,
+        #[pong(codec = Cosmetics, optional)]
+        cosmetics: Option<Cosmetics>,
+    },
 }
 
-impl TryFrom<&[u8]> for AwaitingReadyClientMessage {
-    type Error = DeserializeMessageError;
+#[derive(PongMessage)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Clone, Debug, PartialEq))]
+#[pong(state = 1, max_size = MAX_CLIENT_MESSAGE_SIZE)]
+pub enum AwaitingReadyClientMessage {
+    #[pong(id = 0)]
+    Ready,
+    #[pong(id = 1)]
+    Unready,
+    /// leaves the lobby before the game has started, returning to the open state. the opponent
+    /// hosts the lobby from then on, waiting for someone else to join it.
+    #[pong(id = 2)]
+    LeaveLobby,
+}
 
-    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        match validate_state_and_get_message_id(value, 1)? {
-            0 => {
-                validate_byte_count(value, 1)?;
-                Ok(Self::Ready)
-            }
-            1 => {
-                validate_byte_count(value, 1)?;
-                Ok(Self::Unready)
-            }
-            _ => Err(DeserializeMessageError::UnrecognisedMessageVariant),
-        }
-    }
+#[derive(PongMessage)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Clone, Debug, PartialEq))]
+#[pong(state = 2, max_size = MAX_CLIENT_MESSAGE_SIZE)]
+pub enum PlayingClientMessage {
+    #[pong(id = 0)]
+    MoveIntent { direction: MoveDirection },
+    #[pong(id = 1)]
+    Resign,
+    /// launches the ball, if the player is serving and it hasn't been launched already.
+    #[pong(id = 2)]
+    Serve,
 }
 
-impl From<PlayingClientMessage> for Vec<u8> {
-    fn from(value: PlayingClientMessage) -> Self {
-        let mut bytes = match value {
-            PlayingClientMessage::MovePaddle { pos } => vec![0, pos],
-        };
-        bytes[0] |= 2 << 4;
-        bytes
-    }
+/// sent by the host of a lobby that is still waiting for an opponent.
+#[derive(PongMessage)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Clone, Debug, PartialEq))]
+#[pong(state = 3, max_size = MAX_CLIENT_MESSAGE_SIZE)]
+pub enum AwaitingOpponentJoinClientMessage {
+    /// counts as activity, so keeps the lobby open for longer.
+    #[pong(id = 0)]
+    StillHere,
+    /// closes the lobby, returning to the open state.
+    #[pong(id = 1)]
+    LeaveLobby,
 }
 
-impl TryFrom<&[u8]> for PlayingClientMessage {
-    type Error = DeserializeMessageError;
+/// asks the server which state it has the client in, after the client received a message it
+/// wasn't expecting in its own. valid in every state, and answered with
+/// [`ResyncServerMessage::FullState`](crate::server_msg::ResyncServerMessage::FullState).
+#[derive(PongMessage)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Clone, Debug, PartialEq))]
+#[pong(state = 4, max_size = MAX_CLIENT_MESSAGE_SIZE)]
+pub enum ResyncClientMessage {
+    #[pong(id = 0)]
+    ResyncRequest,
+}
 
-    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        match validate_state_and_get_message_id(value, 2)? {
-            0 => {
-                validate_byte_count(value, 2)?;
-                Ok(Self::MovePaddle { pos: value[1] })
-            }
-            _ => Err(DeserializeMessageError::UnrecognisedMessageVariant),
-        }
-    }
+/// asks the server to end every frame after its reply with a [checksum](crate::checksum), as every
+/// frame the client sends after this one will be. valid in every state, and answered with
+/// [`ChecksumServerMessage::ChecksumsEnabled`](crate::server_msg::ChecksumServerMessage::ChecksumsEnabled).
+#[derive(PongMessage)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Clone, Debug, PartialEq))]
+#[pong(state = 5, max_size = MAX_CLIENT_MESSAGE_SIZE)]
+pub enum ChecksumClientMessage {
+    #[pong(id = 0)]
+    EnableChecksums,
 }
 
 #[cfg(test)]
@@ -112,18 +283,167 @@ mod tests {
     use crate::{
         assert_deserialize, assert_serialize, assert_serialize_and_back,
         client_msg::{
-            AwaitingOpenClientMessage, AwaitingReadyClientMessage, DeserializeMessageError,
-            PlayingClientMessage,
+            AwaitingOpenClientMessage, AwaitingOpponentJoinClientMessage,
+            AwaitingReadyClientMessage, Capabilities, ChecksumClientMessage,
+            DeserializeMessageError, PlayingClientMessage, ResyncClientMessage,
         },
+        cosmetics::{Cosmetics, PaddleColour},
+        game_state::{Handicap, Handicaps, Modifier, MoveDirection, Ruleset},
+        lobby_id::ParseLobbyIdError,
+        LobbyId,
     };
 
     #[test]
     fn awaiting_open_serialize() {
-        assert_serialize!(AwaitingOpenClientMessage::NewLobby, vec![0]);
-        let lobby_id = "F7BW";
         assert_serialize!(
-            AwaitingOpenClientMessage::JoinLobby { lobby_id },
-            [&[1], lobby_id.as_bytes()].concat(),
+            AwaitingOpenClientMessage::NewLobby {
+                tick_interval_ms: 100,
+                capabilities: Capabilities::NONE,
+                custom_id: None,
+                handicaps: Handicaps::NONE,
+                modifier: Modifier::Classic,
+            },
+            vec![0, 0, 100, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        );
+        assert_serialize!(
+            AwaitingOpenClientMessage::NewLobby {
+                tick_interval_ms: 0b1111101000, // 1000
+                capabilities: Capabilities::WIDE_GAME_STATE,
+                custom_id: None,
+                handicaps: Handicaps::NONE,
+                modifier: Modifier::Classic,
+            },
+            vec![0, 0b0000111, 0b1101000, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        );
+        let lobby_id: LobbyId = "F7BW".parse().unwrap();
+        assert_serialize!(
+            AwaitingOpenClientMessage::NewLobby {
+                tick_interval_ms: 100,
+                capabilities: Capabilities::NONE,
+                custom_id: Some(lobby_id),
+                handicaps: Handicaps::NONE,
+                modifier: Modifier::Classic,
+            },
+            [&[0, 0, 100, 0], lobby_id.as_bytes(), &[0; 9]].concat(),
+        );
+        assert_serialize!(
+            AwaitingOpenClientMessage::JoinLobby {
+                lobby_id,
+                capabilities: Capabilities::WIDE_GAME_STATE,
+                cosmetics: None,
+            },
+            [&[1], lobby_id.as_bytes(), &[1]].concat(),
+        );
+        // ids longer than older peers know of are prefixed with their length.
+        assert_serialize!(
+            AwaitingOpenClientMessage::JoinLobby {
+                lobby_id: "F7BW23".parse().unwrap(),
+                capabilities: Capabilities::WIDE_GAME_STATE,
+                cosmetics: None,
+            },
+            [&[1, 6], b"F7BW23".as_slice(), &[1]].concat(),
+        );
+        assert_serialize!(AwaitingOpenClientMessage::GetServerStatus, vec![2]);
+        assert_serialize!(
+            AwaitingOpenClientMessage::FindMatch {
+                capabilities: Capabilities::WIDE_GAME_STATE,
+                cosmetics: None,
+            },
+            vec![3, 1],
+        );
+        assert_serialize!(
+            AwaitingOpenClientMessage::FindMatch {
+                capabilities: Capabilities::WIDE_GAME_STATE,
+                cosmetics: Some(Cosmetics {
+                    glyph: Some('#'),
+                    colour: Some(PaddleColour::Red),
+                }),
+            },
+            vec![3, 1, b'#', 1],
+        );
+        // a lobby without a custom id still leaves its byte ahead of the cosmetics.
+        assert_serialize!(
+            AwaitingOpenClientMessage::NewCoopLobby {
+                tick_interval_ms: 60,
+                capabilities: Capabilities::COOP,
+                custom_id: None,
+                cosmetics: Some(Cosmetics {
+                    glyph: None,
+                    colour: Some(PaddleColour::White),
+                }),
+            },
+            vec![10, 0, 60, 64, 0, 0, 7],
+        );
+        assert_serialize!(
+            AwaitingOpenClientMessage::NewTournament {
+                players: 8,
+                capabilities: Capabilities::WIDE_GAME_STATE,
+                cosmetics: None,
+            },
+            vec![4, 8, 1],
+        );
+        assert_serialize!(
+            AwaitingOpenClientMessage::JoinTournament {
+                tournament_id: lobby_id,
+                capabilities: Capabilities::NONE,
+                cosmetics: None,
+            },
+            [&[5], lobby_id.as_bytes(), &[0]].concat(),
+        );
+        assert_serialize!(
+            AwaitingOpenClientMessage::NewLeague {
+                players: 5,
+                capabilities: Capabilities::WIDE_GAME_STATE,
+                cosmetics: None,
+            },
+            vec![6, 5, 1],
+        );
+        assert_serialize!(
+            AwaitingOpenClientMessage::BrowseLobbies {
+                page: 130,
+                capabilities: Capabilities::WIDE_GAME_STATE,
+            },
+            vec![7, 1, 2, 1],
+        );
+        assert_serialize!(
+            AwaitingOpenClientMessage::NewLobbyWithRuleset {
+                ruleset: Ruleset {
+                    tick_interval_ms: 100,
+                    match_length: Some(3),
+                    handicaps: Handicaps {
+                        left: Handicap::default(),
+                        right: Handicap {
+                            paddle_height: Some(2),
+                            paddle_speed: None,
+                        },
+                    },
+                    modifier: Modifier::Wrap,
+                },
+                capabilities: Capabilities::ALL,
+                custom_id: Some(lobby_id),
+                cosmetics: None,
+            },
+            [
+                &[8, 0, 100, 0, 3, 0, 0, 0, 0, 0, 2, 0, 0, 3, 129, 127],
+                lobby_id.as_bytes(),
+            ]
+            .concat(),
+        );
+        assert_serialize!(
+            AwaitingOpenClientMessage::Practice {
+                tick_interval_ms: 80,
+                capabilities: Capabilities::ALL,
+            },
+            vec![9, 0, 80, 129, 127],
+        );
+        assert_serialize!(
+            AwaitingOpenClientMessage::NewCoopLobby {
+                tick_interval_ms: 60,
+                capabilities: Capabilities::COOP,
+                custom_id: Some(lobby_id),
+                cosmetics: None,
+            },
+            [&[10, 0, 60, 64], lobby_id.as_bytes()].concat(),
         );
     }
 
@@ -131,14 +451,225 @@ mod tests {
     fn awaiting_open_deserialize_ok() {
         assert_deserialize!(
             AwaitingOpenClientMessage,
-            [0],
-            Ok(AwaitingOpenClientMessage::NewLobby),
+            [0, 0, 60, 1],
+            Ok(AwaitingOpenClientMessage::NewLobby {
+                tick_interval_ms: 60,
+                capabilities: Capabilities::WIDE_GAME_STATE,
+                custom_id: None,
+                handicaps: Handicaps::NONE,
+                modifier: Modifier::Classic,
+            }),
+        );
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [&[0, 0, 60, 1], b"GAME".as_slice()].concat(),
+            Ok(AwaitingOpenClientMessage::NewLobby {
+                tick_interval_ms: 60,
+                capabilities: Capabilities::WIDE_GAME_STATE,
+                custom_id: Some("GAME".parse().unwrap()),
+                handicaps: Handicaps::NONE,
+                modifier: Modifier::Classic,
+            }),
+        );
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [0, 0, 60, 1, 0],
+            Ok(AwaitingOpenClientMessage::NewLobby {
+                tick_interval_ms: 60,
+                capabilities: Capabilities::WIDE_GAME_STATE,
+                custom_id: None,
+                handicaps: Handicaps::NONE,
+                modifier: Modifier::Classic,
+            }),
+        );
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [0, 0, 60, 1, 0, 0, 3, 0, 0, 0, 0, 0, 2],
+            Ok(AwaitingOpenClientMessage::NewLobby {
+                tick_interval_ms: 60,
+                capabilities: Capabilities::WIDE_GAME_STATE,
+                custom_id: None,
+                handicaps: Handicaps {
+                    left: Handicap {
+                        paddle_height: Some(3),
+                        paddle_speed: None,
+                    },
+                    right: Handicap {
+                        paddle_height: None,
+                        paddle_speed: Some(2),
+                    },
+                },
+                modifier: Modifier::Classic,
+            }),
+        );
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [0, 0, 60, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2],
+            Ok(AwaitingOpenClientMessage::NewLobby {
+                tick_interval_ms: 60,
+                capabilities: Capabilities::WIDE_GAME_STATE,
+                custom_id: None,
+                handicaps: Handicaps::NONE,
+                modifier: Modifier::Wobble,
+            }),
+        );
+        // clients from before capabilities existed don't send them.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [0, 0b0000001, 0b0010110],
+            Ok(AwaitingOpenClientMessage::NewLobby {
+                tick_interval_ms: 0b10010110, // 150
+                capabilities: Capabilities::NONE,
+                custom_id: None,
+                handicaps: Handicaps::NONE,
+                modifier: Modifier::Classic,
+            }),
+        );
+        let lobby_id: LobbyId = "A5EX".parse().unwrap();
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [&[1], lobby_id.as_bytes(), &[1]].concat(),
+            Ok(AwaitingOpenClientMessage::JoinLobby {
+                lobby_id,
+                capabilities: Capabilities::WIDE_GAME_STATE,
+                cosmetics: None,
+            }),
         );
-        let lobby_id = "A5EZ";
         assert_deserialize!(
             AwaitingOpenClientMessage,
             [&[1], lobby_id.as_bytes()].concat(),
-            Ok(AwaitingOpenClientMessage::JoinLobby { lobby_id }),
+            Ok(AwaitingOpenClientMessage::JoinLobby {
+                lobby_id,
+                capabilities: Capabilities::NONE,
+                cosmetics: None,
+            }),
+        );
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [&[1, 8], b"A5EXA5EX".as_slice()].concat(),
+            Ok(AwaitingOpenClientMessage::JoinLobby {
+                lobby_id: "A5EXA5EX".parse().unwrap(),
+                capabilities: Capabilities::NONE,
+                cosmetics: None,
+            }),
+        );
+        // a prefixed id can be of the length older peers know of too.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [&[1, 4], lobby_id.as_bytes(), &[1]].concat(),
+            Ok(AwaitingOpenClientMessage::JoinLobby {
+                lobby_id,
+                capabilities: Capabilities::WIDE_GAME_STATE,
+                cosmetics: None,
+            }),
+        );
+        // capabilities this version doesn't know of are kept.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [0, 0, 60, 0b1111111],
+            Ok(AwaitingOpenClientMessage::NewLobby {
+                tick_interval_ms: 60,
+                capabilities: Capabilities(0b1111111),
+                custom_id: None,
+                handicaps: Handicaps::NONE,
+                modifier: Modifier::Classic,
+            }),
+        );
+        // as are those sent in a second byte.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [0, 0, 60, 0b10111111, 0b1111111],
+            Ok(AwaitingOpenClientMessage::NewLobby {
+                tick_interval_ms: 60,
+                capabilities: Capabilities(0b1111111111111),
+                custom_id: None,
+                handicaps: Handicaps::NONE,
+                modifier: Modifier::Classic,
+            }),
+        );
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [2],
+            Ok(AwaitingOpenClientMessage::GetServerStatus),
+        );
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [3, 1],
+            Ok(AwaitingOpenClientMessage::FindMatch {
+                capabilities: Capabilities::WIDE_GAME_STATE,
+                cosmetics: None,
+            }),
+        );
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [3],
+            Ok(AwaitingOpenClientMessage::FindMatch {
+                capabilities: Capabilities::NONE,
+                cosmetics: None,
+            }),
+        );
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [&[1], lobby_id.as_bytes(), &[1, b'=', 0]].concat(),
+            Ok(AwaitingOpenClientMessage::JoinLobby {
+                lobby_id,
+                capabilities: Capabilities::WIDE_GAME_STATE,
+                cosmetics: Some(Cosmetics {
+                    glyph: Some('='),
+                    colour: None,
+                }),
+            }),
+        );
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [4, 2],
+            Ok(AwaitingOpenClientMessage::NewTournament {
+                players: 2,
+                capabilities: Capabilities::NONE,
+                cosmetics: None,
+            }),
+        );
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [&[5], lobby_id.as_bytes(), &[1]].concat(),
+            Ok(AwaitingOpenClientMessage::JoinTournament {
+                tournament_id: lobby_id,
+                capabilities: Capabilities::WIDE_GAME_STATE,
+                cosmetics: None,
+            }),
+        );
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [6, 3],
+            Ok(AwaitingOpenClientMessage::NewLeague {
+                players: 3,
+                capabilities: Capabilities::NONE,
+                cosmetics: None,
+            }),
+        );
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [7, 0, 2],
+            Ok(AwaitingOpenClientMessage::BrowseLobbies {
+                page: 2,
+                capabilities: Capabilities::NONE,
+            }),
+        );
+        // a match length of zero leaves it to the server.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [8, 0, 60, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 33],
+            Ok(AwaitingOpenClientMessage::NewLobbyWithRuleset {
+                ruleset: Ruleset {
+                    tick_interval_ms: 60,
+                    match_length: None,
+                    handicaps: Handicaps::NONE,
+                    modifier: Modifier::Gravity,
+                },
+                capabilities: Capabilities(33),
+                custom_id: None,
+                cosmetics: None,
+            }),
         );
     }
 
@@ -150,46 +681,288 @@ mod tests {
             [],
             Err(DeserializeMessageError::EmptyMessage),
         );
+        // new lobby message with missing bytes.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [0],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 0,
+                message_id: 0,
+                expected: 3,
+                actual: 1
+            }),
+        );
         // new lobby message with extra bytes.
         assert_deserialize!(
             AwaitingOpenClientMessage,
             [&[0], "A5EZ".as_bytes()].concat(),
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 0,
+                message_id: 0,
+                expected: 3,
+                actual: 5
+            }),
+        );
+        // new lobby message with only part of a custom id.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [&[0, 0, 100, 0], "GAM".as_bytes()].concat(),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 0,
+                message_id: 0,
+                expected: 3,
+                actual: 7
+            }),
+        );
+        // new lobby message with a custom id that isn't a valid lobby id.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [&[0, 0, 100, 0], "GAM1".as_bytes()].concat(),
+            Err(DeserializeMessageError::InvalidLobbyId(
+                ParseLobbyIdError::InvalidCharacter('1')
+            )),
+        );
+        // new lobby message with a paddle speed that is too fast.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [0, 0, 100, 0, 0, 0, 0, 0, 5, 0, 0, 0, 0],
+            Err(DeserializeMessageError::InvalidHandicap),
+        );
+        // new lobby message with a handicap byte using more than 7 bits.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [0, 0, 100, 0, 0, 0, 0, 0, 0, 0b10000000, 0, 0, 0],
+            Err(DeserializeMessageError::InvalidHandicap),
+        );
+        // new lobby message with an unknown modifier.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [0, 0, 100, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4],
+            Err(DeserializeMessageError::InvalidModifier),
+        );
+        // new lobby message with only the first byte of two of capabilities.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [0, 0, 100, 0b10000001],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 0,
+                message_id: 0,
+                expected: 3,
+                actual: 4
+            }),
+        );
+        // new lobby message with a first capabilities byte that could equal the delimiter.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [0, 0, 100, 0b11000001, 1],
+            Err(DeserializeMessageError::InvalidCapabilities),
+        );
+        // new lobby message with a second capabilities byte using more than 7 bits.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [0, 0, 100, 0b10000001, 0b10000001],
+            Err(DeserializeMessageError::InvalidCapabilities),
+        );
+        // new lobby message with a tick interval that is too short.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [0, 0, 19],
+            Err(DeserializeMessageError::InvalidTickInterval),
+        );
+        // new lobby message with a tick interval that is too long.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [0, 0b0000111, 0b1101001],
+            Err(DeserializeMessageError::InvalidTickInterval),
+        );
+        // new lobby message with a tick interval byte using more than 7 bits.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [0, 0, 0b10000000 | 100],
+            Err(DeserializeMessageError::InvalidTickInterval),
         );
         // join lobby message with no lobby id bytes.
         assert_deserialize!(
             AwaitingOpenClientMessage,
             [1],
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 0,
+                message_id: 1,
+                expected: 5,
+                actual: 1
+            }),
         );
         // join lobby message with not enough bytes.
         assert_deserialize!(
             AwaitingOpenClientMessage,
             [&[1], "A5E".as_bytes()].concat(),
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 0,
+                message_id: 1,
+                expected: 5,
+                actual: 4
+            }),
         );
         // join lobby message with too many bytes.
         assert_deserialize!(
             AwaitingOpenClientMessage,
-            [&[1], "A5EZ8".as_bytes()].concat(),
-            Err(DeserializeMessageError::InvalidByteCount),
+            [&[1], "A5EX".as_bytes(), &[1, 1]].concat(),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 0,
+                message_id: 1,
+                expected: 5,
+                actual: 7
+            }),
+        );
+        // join lobby message with fewer id bytes than its length prefix.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [&[1, 6], "A5EXA".as_bytes()].concat(),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 0,
+                message_id: 1,
+                expected: 8,
+                actual: 7
+            }),
+        );
+        // join lobby message with a length prefix too short for an id.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [&[1, 3], "A5E".as_bytes()].concat(),
+            Err(DeserializeMessageError::InvalidLobbyId(
+                ParseLobbyIdError::InvalidLength { actual: 3 }
+            )),
+        );
+        // lobby id with a character that isn't used in ids.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [&[1], "A0EX".as_bytes()].concat(),
+            Err(DeserializeMessageError::InvalidLobbyId(
+                ParseLobbyIdError::InvalidCharacter('0')
+            )),
+        );
+        // lobby id that isn't normalized.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [&[1], "a5ex".as_bytes()].concat(),
+            Err(DeserializeMessageError::InvalidLobbyId(
+                ParseLobbyIdError::InvalidCharacter('a')
+            )),
         );
-        // join lobby message with invalid utf-8.
-        assert!(matches!(
-            AwaitingOpenClientMessage::try_from([1, 255, 255, 255, 255].as_slice()),
-            Err(DeserializeMessageError::Utf8Error(_))
-        ));
         // invalid state variant.
         assert_deserialize!(
             AwaitingOpenClientMessage,
             [1 << 4],
-            Err(DeserializeMessageError::InvalidState),
+            Err(DeserializeMessageError::InvalidState {
+                expected: 0,
+                actual: 1
+            }),
+        );
+        // server status request with extra bytes.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [2, 0],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 0,
+                message_id: 2,
+                expected: 1,
+                actual: 2
+            }),
+        );
+        // find match message with extra bytes.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [3, 1, 1],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 0,
+                message_id: 3,
+                expected: 1,
+                actual: 3
+            }),
+        );
+        // new tournament message with a number of players that can't be halved every round.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [4, 6, 1],
+            Err(DeserializeMessageError::InvalidTournamentSize),
+        );
+        // new tournament message with more players than a bracket can hold.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [4, 16, 1],
+            Err(DeserializeMessageError::InvalidTournamentSize),
+        );
+        // join tournament message with no tournament id bytes.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [5],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 0,
+                message_id: 5,
+                expected: 5,
+                actual: 1
+            }),
+        );
+        // new league message with too many players.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [6, 9],
+            Err(DeserializeMessageError::InvalidTournamentSize),
+        );
+        // browse lobbies message with a page number that isn't two 7-bit bytes.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [7, 0, 0b10000000],
+            Err(DeserializeMessageError::InvalidCount),
+        );
+        // new lobby with ruleset message with a match length byte using more than 7 bits.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [8, 0, 60, 0b10000000, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            Err(DeserializeMessageError::InvalidMatchLength),
+        );
+        // new lobby with ruleset message with a tick interval that is too short.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [8, 0, 19, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            Err(DeserializeMessageError::InvalidTickInterval),
+        );
+        // new lobby with ruleset message without its capabilities.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [8, 0, 60, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 0,
+                message_id: 8,
+                expected: 15,
+                actual: 14
+            }),
+        );
+        // practice message without its capabilities.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [9, 0, 80],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 0,
+                message_id: 9,
+                expected: 4,
+                actual: 3
+            }),
+        );
+        // new co-op lobby message with a tick interval that is too long.
+        assert_deserialize!(
+            AwaitingOpenClientMessage,
+            [10, 15, 105, 64],
+            Err(DeserializeMessageError::InvalidTickInterval),
         );
         // unrecognised message variant.
         assert_deserialize!(
             AwaitingOpenClientMessage,
-            [2],
-            Err(DeserializeMessageError::UnrecognisedMessageVariant),
+            [11],
+            Err(DeserializeMessageError::UnrecognisedMessageVariant {
+                state_id: 0,
+                message_id: 11
+            }),
         );
     }
 
@@ -203,6 +976,10 @@ mod tests {
             Vec::<u8>::from(AwaitingReadyClientMessage::Unready),
             vec![1 << 4 | 1]
         );
+        assert_serialize!(
+            Vec::<u8>::from(AwaitingReadyClientMessage::LeaveLobby),
+            vec![1 << 4 | 2]
+        );
     }
 
     #[test]
@@ -217,6 +994,11 @@ mod tests {
             [1 << 4 | 1],
             Ok(AwaitingReadyClientMessage::Unready),
         );
+        assert_deserialize!(
+            AwaitingReadyClientMessage,
+            [1 << 4 | 2],
+            Ok(AwaitingReadyClientMessage::LeaveLobby),
+        );
     }
 
     #[test]
@@ -231,47 +1013,100 @@ mod tests {
         assert_deserialize!(
             AwaitingReadyClientMessage,
             [1 << 4, 0],
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 1,
+                message_id: 0,
+                expected: 1,
+                actual: 2
+            }),
         );
         // unready message with extra bytes.
         assert_deserialize!(
             AwaitingReadyClientMessage,
             [1 << 4 | 1, 0],
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 1,
+                message_id: 1,
+                expected: 1,
+                actual: 2
+            }),
         );
         // invalid state variant.
         assert_deserialize!(
             AwaitingReadyClientMessage,
             [0],
-            Err(DeserializeMessageError::InvalidState),
+            Err(DeserializeMessageError::InvalidState {
+                expected: 1,
+                actual: 0
+            }),
         );
         // unrecognised message variant.
         assert_deserialize!(
             AwaitingReadyClientMessage,
-            [1 << 4 | 2],
-            Err(DeserializeMessageError::UnrecognisedMessageVariant),
+            [1 << 4 | 3],
+            Err(DeserializeMessageError::UnrecognisedMessageVariant {
+                state_id: 1,
+                message_id: 3
+            }),
         );
     }
 
     #[test]
     fn playing_serialize() {
-        let pos = 6;
-        assert_serialize!(PlayingClientMessage::MovePaddle { pos }, vec![2 << 4, pos]);
-        let pos = 154;
-        assert_serialize!(PlayingClientMessage::MovePaddle { pos }, vec![2 << 4, pos]);
+        assert_serialize!(
+            PlayingClientMessage::MoveIntent {
+                direction: MoveDirection::Stop
+            },
+            vec![2 << 4, 0]
+        );
+        assert_serialize!(
+            PlayingClientMessage::MoveIntent {
+                direction: MoveDirection::Up
+            },
+            vec![2 << 4, 1]
+        );
+        assert_serialize!(
+            PlayingClientMessage::MoveIntent {
+                direction: MoveDirection::Down
+            },
+            vec![2 << 4, 2]
+        );
+        assert_serialize!(PlayingClientMessage::Resign, vec![2 << 4 | 1]);
+        assert_serialize!(PlayingClientMessage::Serve, vec![2 << 4 | 2]);
     }
 
     #[test]
     fn playing_deserialize_ok() {
         assert_deserialize!(
             PlayingClientMessage,
-            [2 << 4, 5],
-            Ok(PlayingClientMessage::MovePaddle { pos: 5 }),
+            [2 << 4, 0],
+            Ok(PlayingClientMessage::MoveIntent {
+                direction: MoveDirection::Stop
+            }),
+        );
+        assert_deserialize!(
+            PlayingClientMessage,
+            [2 << 4, 1],
+            Ok(PlayingClientMessage::MoveIntent {
+                direction: MoveDirection::Up
+            }),
+        );
+        assert_deserialize!(
+            PlayingClientMessage,
+            [2 << 4, 2],
+            Ok(PlayingClientMessage::MoveIntent {
+                direction: MoveDirection::Down
+            }),
+        );
+        assert_deserialize!(
+            PlayingClientMessage,
+            [2 << 4 | 1],
+            Ok(PlayingClientMessage::Resign),
         );
         assert_deserialize!(
             PlayingClientMessage,
-            [2 << 4, 76],
-            Ok(PlayingClientMessage::MovePaddle { pos: 76 }),
+            [2 << 4 | 2],
+            Ok(PlayingClientMessage::Serve),
         );
     }
 
@@ -283,38 +1118,208 @@ mod tests {
             [],
             Err(DeserializeMessageError::EmptyMessage),
         );
-        // move paddle message with missing byte.
+        // move intent message with missing byte.
         assert_deserialize!(
             PlayingClientMessage,
             [2 << 4],
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 2,
+                message_id: 0,
+                expected: 2,
+                actual: 1
+            }),
+        );
+        // move intent message with extra bytes.
+        assert_deserialize!(
+            PlayingClientMessage,
+            [2 << 4, 1, 1],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 2,
+                message_id: 0,
+                expected: 2,
+                actual: 3
+            }),
         );
-        // move paddle message with extra bytes.
+        // move intent message with an unrecognised direction.
         assert_deserialize!(
             PlayingClientMessage,
-            [2 << 4, 5, 5],
-            Err(DeserializeMessageError::InvalidByteCount),
+            [2 << 4, 3],
+            Err(DeserializeMessageError::InvalidMoveDirection),
+        );
+        // resign message with extra bytes.
+        assert_deserialize!(
+            PlayingClientMessage,
+            [2 << 4 | 1, 0],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 2,
+                message_id: 1,
+                expected: 1,
+                actual: 2
+            }),
         );
         // invalid state variant.
         assert_deserialize!(
             PlayingClientMessage,
             [0],
-            Err(DeserializeMessageError::InvalidState),
+            Err(DeserializeMessageError::InvalidState {
+                expected: 2,
+                actual: 0
+            }),
         );
         // unrecognised message variant.
         assert_deserialize!(
             PlayingClientMessage,
+            [2 << 4 | 3],
+            Err(DeserializeMessageError::UnrecognisedMessageVariant {
+                state_id: 2,
+                message_id: 3
+            }),
+        );
+    }
+
+    #[test]
+    fn awaiting_opponent_join_serialize() {
+        assert_serialize!(AwaitingOpponentJoinClientMessage::StillHere, vec![3 << 4]);
+        assert_serialize!(
+            AwaitingOpponentJoinClientMessage::LeaveLobby,
+            vec![3 << 4 | 1]
+        );
+    }
+
+    #[test]
+    fn awaiting_opponent_join_deserialize() {
+        assert_deserialize!(
+            AwaitingOpponentJoinClientMessage,
+            [3 << 4],
+            Ok(AwaitingOpponentJoinClientMessage::StillHere),
+        );
+        assert_deserialize!(
+            AwaitingOpponentJoinClientMessage,
+            [3 << 4 | 1],
+            Ok(AwaitingOpponentJoinClientMessage::LeaveLobby),
+        );
+        // still here message with extra bytes.
+        assert_deserialize!(
+            AwaitingOpponentJoinClientMessage,
+            [3 << 4, 0],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 3,
+                message_id: 0,
+                expected: 1,
+                actual: 2
+            }),
+        );
+        // unrecognised message variant.
+        assert_deserialize!(
+            AwaitingOpponentJoinClientMessage,
+            [3 << 4 | 2],
+            Err(DeserializeMessageError::UnrecognisedMessageVariant {
+                state_id: 3,
+                message_id: 2
+            }),
+        );
+    }
+
+    #[test]
+    fn checksum_serialize_and_deserialize() {
+        assert_serialize!(ChecksumClientMessage::EnableChecksums, vec![5 << 4]);
+        assert_deserialize!(
+            ChecksumClientMessage,
+            [5 << 4],
+            Ok(ChecksumClientMessage::EnableChecksums),
+        );
+    }
+
+    #[test]
+    fn resync_serialize_and_deserialize() {
+        assert_serialize!(ResyncClientMessage::ResyncRequest, vec![4 << 4]);
+        assert_deserialize!(
+            ResyncClientMessage,
+            [4 << 4],
+            Ok(ResyncClientMessage::ResyncRequest),
+        );
+        // a message from another state isn't mistaken for a resync request.
+        assert_deserialize!(
+            ResyncClientMessage,
             [2 << 4 | 1],
-            Err(DeserializeMessageError::UnrecognisedMessageVariant),
+            Err(DeserializeMessageError::InvalidState {
+                expected: 4,
+                actual: 2
+            }),
         );
     }
 
     #[test]
     fn serialize_and_back() {
-        assert_serialize_and_back!(AwaitingOpenClientMessage::NewLobby);
-        assert_serialize_and_back!(AwaitingOpenClientMessage::JoinLobby { lobby_id: "AOP4" });
+        assert_serialize_and_back!(AwaitingOpenClientMessage::NewLobby {
+            tick_interval_ms: 100,
+            capabilities: Capabilities::ALL,
+            custom_id: Some("GAME".parse().unwrap()),
+            handicaps: Handicaps::NONE,
+            modifier: Modifier::Classic,
+        });
+        assert_serialize_and_back!(AwaitingOpenClientMessage::NewLobby {
+            tick_interval_ms: 100,
+            capabilities: Capabilities::ALL,
+            custom_id: None,
+            handicaps: Handicaps {
+                left: Handicap {
+                    paddle_height: Some(200),
+                    paddle_speed: None,
+                },
+                right: Handicap {
+                    paddle_height: None,
+                    paddle_speed: Some(4),
+                },
+            },
+            modifier: Modifier::Gravity,
+        });
+        assert_serialize_and_back!(AwaitingOpenClientMessage::NewLobbyWithRuleset {
+            ruleset: Ruleset {
+                tick_interval_ms: 250,
+                match_length: Some(11),
+                handicaps: Handicaps::NONE,
+                modifier: Modifier::Wobble,
+            },
+            capabilities: Capabilities::RULESET,
+            custom_id: None,
+            cosmetics: Some(Cosmetics {
+                glyph: Some('@'),
+                colour: None,
+            }),
+        });
+        assert_serialize_and_back!(AwaitingOpenClientMessage::Practice {
+            tick_interval_ms: 40,
+            capabilities: Capabilities::HANDICAPS,
+        });
+        assert_serialize_and_back!(AwaitingOpenClientMessage::NewCoopLobby {
+            tick_interval_ms: 150,
+            capabilities: Capabilities::ALL,
+            custom_id: None,
+            cosmetics: None,
+        });
+        assert_serialize_and_back!(AwaitingOpenClientMessage::JoinLobby {
+            lobby_id: "AOP4".parse().unwrap(),
+            capabilities: Capabilities::NONE,
+            cosmetics: Some(Cosmetics {
+                glyph: None,
+                colour: Some(PaddleColour::Magenta),
+            }),
+        });
+        assert_serialize_and_back!(AwaitingOpenClientMessage::GetServerStatus);
         assert_serialize_and_back!(AwaitingReadyClientMessage::Ready);
         assert_serialize_and_back!(AwaitingReadyClientMessage::Unready);
-        assert_serialize_and_back!(PlayingClientMessage::MovePaddle { pos: 42 });
+        assert_serialize_and_back!(AwaitingReadyClientMessage::LeaveLobby);
+        assert_serialize_and_back!(PlayingClientMessage::MoveIntent {
+            direction: MoveDirection::Stop
+        });
+        assert_serialize_and_back!(PlayingClientMessage::MoveIntent {
+            direction: MoveDirection::Up
+        });
+        assert_serialize_and_back!(PlayingClientMessage::MoveIntent {
+            direction: MoveDirection::Down
+        });
+        assert_serialize_and_back!(PlayingClientMessage::Resign);
+        assert_serialize_and_back!(PlayingClientMessage::Serve);
     }
 }
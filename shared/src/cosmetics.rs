@@ -0,0 +1,165 @@
+//! how a player chose to have their paddle drawn, which their opponent is told so that each of them
+//! sees the other's choice. it changes nothing about how the game is played.
+
+use core::str::FromStr;
+
+use crate::{DeserializeMessageError, FieldCodec};
+
+/// a colour a paddle can be drawn in, from the ones every terminal has. black is left out, as it's
+/// the background of most terminals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaddleColour {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl PaddleColour {
+    const ALL: [PaddleColour; 7] = [
+        PaddleColour::Red,
+        PaddleColour::Green,
+        PaddleColour::Yellow,
+        PaddleColour::Blue,
+        PaddleColour::Magenta,
+        PaddleColour::Cyan,
+        PaddleColour::White,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PaddleColour::Red => "red",
+            PaddleColour::Green => "green",
+            PaddleColour::Yellow => "yellow",
+            PaddleColour::Blue => "blue",
+            PaddleColour::Magenta => "magenta",
+            PaddleColour::Cyan => "cyan",
+            PaddleColour::White => "white",
+        }
+    }
+}
+
+impl FromStr for PaddleColour {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PaddleColour::ALL
+            .into_iter()
+            .find(|colour| colour.name() == s)
+            .ok_or(())
+    }
+}
+
+/// how a paddle is drawn. whatever is left unset is drawn as it always has been.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cosmetics {
+    /// the character every cell of the paddle is drawn with.
+    pub glyph: Option<char>,
+    pub colour: Option<PaddleColour>,
+}
+
+impl Cosmetics {
+    pub const NONE: Cosmetics = Cosmetics {
+        glyph: None,
+        colour: None,
+    };
+
+    /// whether a paddle can be drawn with the character: any printable ascii character other than
+    /// a space, so that it takes up one cell on every terminal.
+    pub fn is_valid_glyph(glyph: char) -> bool {
+        glyph.is_ascii_graphic()
+    }
+}
+
+/// two bytes: the glyph's ascii code, and one more than the colour's index, either of which is 0
+/// if it's unset.
+impl FieldCodec<Cosmetics> for Cosmetics {
+    const SIZE: usize = 2;
+
+    fn write(value: &Cosmetics, buf: &mut [u8]) {
+        buf[0] = value.glyph.map_or(0, |glyph| glyph as u8);
+        buf[1] = value.colour.map_or(0, |colour| colour as u8 + 1);
+    }
+
+    fn read(bytes: &[u8]) -> Result<Cosmetics, DeserializeMessageError> {
+        let glyph = match bytes[0] {
+            0 => None,
+            byte if Cosmetics::is_valid_glyph(byte as char) => Some(byte as char),
+            _ => return Err(DeserializeMessageError::InvalidCosmetics),
+        };
+        let colour = match bytes[1] {
+            0 => None,
+            byte => Some(
+                *PaddleColour::ALL
+                    .get(byte as usize - 1)
+                    .ok_or(DeserializeMessageError::InvalidCosmetics)?,
+            ),
+        };
+        Ok(Cosmetics { glyph, colour })
+    }
+}
+
+/// cosmetics that may be left out, in which case nothing is written. only ever the last field of
+/// a message, where older peers leave it out anyway, so that it can be told apart from one that's
+/// present.
+impl FieldCodec<Option<Cosmetics>> for Cosmetics {
+    const SIZE: usize = <Cosmetics as FieldCodec<Cosmetics>>::SIZE;
+
+    fn size(value: &Option<Cosmetics>) -> usize {
+        value.map_or(0, |_| <Cosmetics as FieldCodec<Cosmetics>>::SIZE)
+    }
+
+    fn write(value: &Option<Cosmetics>, buf: &mut [u8]) {
+        if let Some(cosmetics) = value {
+            <Cosmetics as FieldCodec<Cosmetics>>::write(cosmetics, buf);
+        }
+    }
+
+    fn read(bytes: &[u8]) -> Result<Option<Cosmetics>, DeserializeMessageError> {
+        <Cosmetics as FieldCodec<Cosmetics>>::read(bytes).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cosmetics::{Cosmetics, PaddleColour},
+        DeserializeMessageError, FieldCodec,
+    };
+
+    #[test]
+    fn cosmetics_codec() {
+        let cosmetics = Cosmetics {
+            glyph: Some('#'),
+            colour: Some(PaddleColour::Cyan),
+        };
+        let mut buf = [0; 2];
+        <Cosmetics as FieldCodec<Cosmetics>>::write(&cosmetics, &mut buf);
+        assert_eq!(buf, [b'#', 6]);
+        assert_eq!(
+            <Cosmetics as FieldCodec<Cosmetics>>::read(&buf),
+            Ok(cosmetics)
+        );
+        assert_eq!(
+            <Cosmetics as FieldCodec<Cosmetics>>::read(&[0, 0]),
+            Ok(Cosmetics::NONE)
+        );
+        for invalid in [[b' ', 0], [0x7F, 0], [0, 8]] {
+            assert_eq!(
+                <Cosmetics as FieldCodec<Cosmetics>>::read(&invalid),
+                Err(DeserializeMessageError::InvalidCosmetics)
+            );
+        }
+    }
+
+    #[test]
+    fn colour_names() {
+        for colour in PaddleColour::ALL {
+            assert_eq!(colour.name().parse(), Ok(colour));
+        }
+        assert_eq!("black".parse::<PaddleColour>(), Err(()));
+    }
+}
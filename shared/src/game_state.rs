@@ -1,20 +1,1050 @@
-pub const GAME_HEIGHT: u8 = 11;
-pub const GAME_WIDTH: u8 = 51;
-pub const PADDLE_HEIGHT: u8 = 5;
+use crate::{DeserializeMessageError, MAX_U14};
+
+/// the largest width and height of any game, since every dimension and position is serialized
+/// as a u14 in the wide game state encoding.
+pub const MAX_GAME_WIDTH: u16 = MAX_U14;
+pub const MAX_GAME_HEIGHT: u16 = MAX_U14;
+/// the largest width and height the narrow game state encoding can describe. the ball's position
+/// on each axis is serialized alongside its direction in a single byte, which must never equal
+/// the message delimiter.
+pub const NARROW_MAX_GAME_WIDTH: u16 = 126;
+pub const NARROW_MAX_GAME_HEIGHT: u16 = 126;
+/// the largest paddle position the narrow game state encoding can describe. both paddle
+/// positions are serialized into a single byte, which must never equal the message delimiter.
+pub const NARROW_MAX_PADDLE_POSITION: u16 = 14;
+/// the number of cells a paddle moves each tick while its player intends to move it.
+pub const PADDLE_CELLS_PER_TICK: u16 = 1;
+/// the most cells a host can make a handicapped paddle move each tick.
+pub const MAX_PADDLE_CELLS_PER_TICK: u16 = 4;
+
+/// the tick intervals, in milliseconds, a host can choose from when creating a lobby.
+pub const SLOW_TICK_INTERVAL_MS: u16 = 150;
+pub const NORMAL_TICK_INTERVAL_MS: u16 = 100;
+pub const FAST_TICK_INTERVAL_MS: u16 = 60;
+/// the bounds on custom tick intervals, in milliseconds.
+pub const MIN_TICK_INTERVAL_MS: u16 = 20;
+pub const MAX_TICK_INTERVAL_MS: u16 = 1000;
+/// the ball speeds up each time a paddle hits it, by shortening each tick by this percentage of
+/// the lobby's tick interval, until it has been hit [`MAX_SPEED_LEVEL`] times in a game.
+pub const SPEED_UP_PERCENT_PER_HIT: u16 = 10;
+pub const MAX_SPEED_LEVEL: u8 = 5;
+/// under [`Modifier::Gravity`], a rising ball starts to fall each time it reaches a column that is
+/// a multiple of this.
+pub const GRAVITY_PERIOD: u16 = 6;
+/// under [`Modifier::Wobble`], the ball alternates between moving diagonally and flying level
+/// every this many columns.
+pub const WOBBLE_PERIOD: u16 = 4;
+/// in a co-op survival game, the server's paddle speeds up each time the players return the ball
+/// this many times, until it reaches [`MAX_AI_SPEED_LEVEL`].
+pub const RETURNS_PER_AI_SPEED_UP: u16 = 10;
+pub const MAX_AI_SPEED_LEVEL: u8 = 3;
+/// the server's paddle moves on one more tick in every this many for each speed level, so that it
+/// moves every tick at its top speed.
+const AI_MOVE_PERIOD: u32 = MAX_AI_SPEED_LEVEL as u32 + 1;
+
+/// the size of the field and paddles of a game. chosen by the server and sent to clients when
+/// they enter a lobby, so that clients needn't be rebuilt to play on a different field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GameDimensions {
+    width: u16,
+    height: u16,
+    paddle_height: u16,
+}
+
+/// how a player's paddle differs from the field's, so that players of different skill can have an
+/// even game. chosen by the host when creating a lobby.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Handicap {
+    /// the height of the paddle, if not the field's.
+    pub paddle_height: Option<u16>,
+    /// the cells the paddle moves each tick, if not [`PADDLE_CELLS_PER_TICK`].
+    pub paddle_speed: Option<u16>,
+}
+
+/// the handicaps of both players in a lobby.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Handicaps {
+    pub left: Handicap,
+    pub right: Handicap,
+}
+
+/// how the ball moves between the paddles, chosen by the host when creating a lobby to spice up
+/// long rallies. the ball still moves one cell a tick on each axis, but its vertical direction
+/// drifts as it crosses the field.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Modifier {
+    /// the ball only turns when it hits a wall or a paddle.
+    #[default]
+    Classic,
+    /// the ball falls back down after rising for a while, so that it bounces along the bottom of
+    /// the field.
+    Gravity,
+    /// the ball flies level through every other few columns, so that its path curves.
+    Wobble,
+    /// the top and bottom walls wrap around rather than the ball bouncing off them, so that a
+    /// ball leaving through the top comes back in through the bottom.
+    Wrap,
+}
+
+/// every option a host chooses when creating a lobby, which both players are told about before
+/// they ready up so that they agree on how the game will be played.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ruleset {
+    pub tick_interval_ms: u16,
+    /// the number of games a player must win to take the match, or `None` if games aren't played
+    /// as matches. hosts leave it as `None` to play matches of the server's length.
+    pub match_length: Option<u16>,
+    pub handicaps: Handicaps,
+    pub modifier: Modifier,
+}
 
 #[derive(Clone)]
-#[cfg_attr(test, derive(Debug, PartialEq))]
+#[cfg_attr(any(test, feature = "proptest"), derive(Debug, PartialEq))]
 pub struct GameState {
-    pub left_paddle: u8,
-    pub right_paddle: u8,
+    pub left_paddle: u16,
+    pub right_paddle: u16,
     pub ball: Ball,
 }
 
 #[derive(Clone)]
-#[cfg_attr(test, derive(Debug, PartialEq))]
+#[cfg_attr(any(test, feature = "proptest"), derive(Debug, PartialEq))]
 pub struct Ball {
-    pub x: u8,
-    pub y: u8,
+    pub x: u16,
+    pub y: u16,
     pub moving_right: bool,
     pub moving_down: bool,
 }
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Debug))]
+pub enum MoveDirection {
+    Stop,
+    Up,
+    Down,
+}
+
+/// a side of the game, and so the player whose paddle is on it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Debug))]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl GameDimensions {
+    pub const DEFAULT: GameDimensions = GameDimensions {
+        width: 51,
+        height: 11,
+        paddle_height: 5,
+    };
+
+    /// returns [`None`] if a game couldn't be played on the given dimensions, or if they're too
+    /// large for the wire format.
+    pub const fn new(width: u16, height: u16, paddle_height: u16) -> Option<Self> {
+        if width < 5
+            || width > MAX_GAME_WIDTH
+            || height < 2
+            || height > MAX_GAME_HEIGHT
+            || paddle_height == 0
+            || paddle_height > height
+        {
+            return None;
+        }
+        Some(Self {
+            width,
+            height,
+            paddle_height,
+        })
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn paddle_height(&self) -> u16 {
+        self.paddle_height
+    }
+
+    /// the position of a paddle at the bottom of the field.
+    pub fn max_paddle_position(&self) -> u16 {
+        self.height - self.paddle_height
+    }
+
+    /// whether games on this field can only be sent to clients that support the wide game state
+    /// encoding.
+    pub fn requires_wide_encoding(&self) -> bool {
+        self.requires_wide_encoding_with(Handicaps::NONE)
+    }
+
+    /// whether games on this field between players with the given handicaps can only be sent to
+    /// clients that support the wide game state encoding. a shorter paddle can move further down.
+    pub fn requires_wide_encoding_with(&self, handicaps: Handicaps) -> bool {
+        self.width > NARROW_MAX_GAME_WIDTH
+            || self.height > NARROW_MAX_GAME_HEIGHT
+            || [handicaps.left, handicaps.right]
+                .iter()
+                .any(|handicap| handicap.max_paddle_position(*self) > NARROW_MAX_PADDLE_POSITION)
+    }
+
+    /// checks that a game state received from the server fits within these dimensions.
+    pub fn validate(&self, game_state: &GameState) -> Result<(), DeserializeMessageError> {
+        self.validate_with(Handicaps::NONE, game_state)
+    }
+
+    /// checks that a game state received from the server fits within these dimensions, for
+    /// players with the given handicaps.
+    pub fn validate_with(
+        &self,
+        handicaps: Handicaps,
+        game_state: &GameState,
+    ) -> Result<(), DeserializeMessageError> {
+        if game_state.left_paddle > handicaps.left.max_paddle_position(*self)
+            || game_state.right_paddle > handicaps.right.max_paddle_position(*self)
+        {
+            return Err(DeserializeMessageError::InvalidPaddlePosition);
+        }
+        if game_state.ball.x >= self.width || game_state.ball.y >= self.height {
+            return Err(DeserializeMessageError::InvalidBallPosition);
+        }
+        Ok(())
+    }
+}
+
+const _CHECKS: () = assert!(
+    GameDimensions::new(
+        GameDimensions::DEFAULT.width,
+        GameDimensions::DEFAULT.height,
+        GameDimensions::DEFAULT.paddle_height,
+    )
+    .is_some(),
+    "the default game dimensions are invalid"
+);
+
+impl Handicap {
+    pub const NONE: Handicap = Handicap {
+        paddle_height: None,
+        paddle_speed: None,
+    };
+
+    /// the height of the paddle on the given field. never taller than the field.
+    pub fn paddle_height(&self, dimensions: GameDimensions) -> u16 {
+        self.paddle_height
+            .unwrap_or(dimensions.paddle_height)
+            .min(dimensions.height)
+    }
+
+    /// the cells the paddle moves each tick.
+    pub fn paddle_speed(&self) -> u16 {
+        self.paddle_speed.unwrap_or(PADDLE_CELLS_PER_TICK)
+    }
+
+    /// the position of the paddle at the bottom of the given field.
+    pub fn max_paddle_position(&self, dimensions: GameDimensions) -> u16 {
+        dimensions.height - self.paddle_height(dimensions)
+    }
+}
+
+impl Handicaps {
+    pub const NONE: Handicaps = Handicaps {
+        left: Handicap::NONE,
+        right: Handicap::NONE,
+    };
+
+    pub fn is_none(&self) -> bool {
+        *self == Handicaps::NONE
+    }
+
+    pub fn side(&self, side: Side) -> Handicap {
+        match side {
+            Side::Left => self.left,
+            Side::Right => self.right,
+        }
+    }
+
+    /// shortens any paddle taller than the given field to fit it.
+    pub fn fit(self, dimensions: GameDimensions) -> Self {
+        let fit = |handicap: Handicap| Handicap {
+            paddle_height: handicap
+                .paddle_height
+                .map(|paddle_height| paddle_height.min(dimensions.height)),
+            ..handicap
+        };
+        Handicaps {
+            left: fit(self.left),
+            right: fit(self.right),
+        }
+    }
+}
+
+impl GameState {
+    /// advances the game by one tick, moving each paddle in the given direction and then the
+    /// ball. returns the winning side if the ball got past a paddle, in which case the ball is
+    /// left where it is.
+    pub fn tick(
+        &mut self,
+        dimensions: GameDimensions,
+        handicaps: Handicaps,
+        modifier: Modifier,
+        left_paddle_direction: MoveDirection,
+        right_paddle_direction: MoveDirection,
+    ) -> Option<Side> {
+        self.left_paddle = move_paddle(
+            dimensions,
+            handicaps.left,
+            self.left_paddle,
+            left_paddle_direction,
+        );
+        self.right_paddle = move_paddle(
+            dimensions,
+            handicaps.right,
+            self.right_paddle,
+            right_paddle_direction,
+        );
+        let left_paddle_height = handicaps.left.paddle_height(dimensions);
+        let right_paddle_height = handicaps.right.paddle_height(dimensions);
+        let y = self.ball.y;
+        self.move_ball(
+            dimensions,
+            modifier,
+            covers(self.left_paddle, left_paddle_height, y),
+            covers(self.right_paddle, right_paddle_height, y),
+        )
+    }
+
+    /// advances a co-op survival game by one tick, in which the left paddle and the partner's
+    /// paddle both defend the left side, and the right paddle is steered by the server. returns
+    /// the winning side if the ball got past either side, in which case the ball is left where it
+    /// is.
+    pub fn tick_coop(
+        &mut self,
+        dimensions: GameDimensions,
+        partner_paddle: &mut u16,
+        left_paddle_direction: MoveDirection,
+        partner_paddle_direction: MoveDirection,
+        ai_direction: MoveDirection,
+    ) -> Option<Side> {
+        self.left_paddle = move_paddle(
+            dimensions,
+            Handicap::NONE,
+            self.left_paddle,
+            left_paddle_direction,
+        );
+        *partner_paddle = move_paddle(
+            dimensions,
+            Handicap::NONE,
+            *partner_paddle,
+            partner_paddle_direction,
+        );
+        self.right_paddle =
+            move_paddle(dimensions, Handicap::NONE, self.right_paddle, ai_direction);
+        let y = self.ball.y;
+        self.move_ball(
+            dimensions,
+            Modifier::Classic,
+            [self.left_paddle, *partner_paddle]
+                .into_iter()
+                .any(|paddle| covers(paddle, dimensions.paddle_height, y)),
+            covers(self.right_paddle, dimensions.paddle_height, y),
+        )
+    }
+
+    /// moves the ball once the paddles have moved, given whether a paddle is in the ball's row on
+    /// each side.
+    fn move_ball(
+        &mut self,
+        dimensions: GameDimensions,
+        modifier: Modifier,
+        left_covered: bool,
+        right_covered: bool,
+    ) -> Option<Side> {
+        let ball = &mut self.ball;
+        if ball.x == 1 {
+            if !left_covered {
+                return Some(Side::Right);
+            }
+            ball.moving_right = !ball.moving_right;
+        }
+        if ball.x == dimensions.width - 2 {
+            if !right_covered {
+                return Some(Side::Left);
+            }
+            ball.moving_right = !ball.moving_right;
+        }
+        // gravity pulls the ball down before it bounces off a wall, so that it can't be pulled into
+        // the bottom one.
+        if modifier == Modifier::Gravity && ball.x.is_multiple_of(GRAVITY_PERIOD) {
+            ball.moving_down = true;
+        }
+        // only bounce off a wall the ball is moving towards, since it can be served in the top or
+        // bottom row moving away from it.
+        if modifier != Modifier::Wrap
+            && ((ball.y == 0 && !ball.moving_down)
+                || (ball.y == dimensions.height - 1 && ball.moving_down))
+        {
+            ball.moving_down = !ball.moving_down;
+        }
+        // a wobbling ball flies level through every other stretch of columns, so that its path
+        // curves.
+        let level = modifier == Modifier::Wobble && (ball.x / WOBBLE_PERIOD) % 2 == 1;
+        if ball.moving_right {
+            ball.x += 1;
+        } else {
+            ball.x -= 1;
+        }
+        // only a ball on a field whose walls wrap can still be moving into one.
+        if !level {
+            if ball.moving_down {
+                ball.y = (ball.y + 1) % dimensions.height;
+            } else {
+                ball.y = ball.y.checked_sub(1).unwrap_or(dimensions.height - 1);
+            }
+        }
+        None
+    }
+}
+
+/// whether a paddle of the given height at the given position is in the given row.
+fn covers(paddle: u16, paddle_height: u16, y: u16) -> bool {
+    paddle <= y && y < paddle + paddle_height
+}
+
+/// how fast the server's paddle moves in a co-op survival game, once the players have returned the
+/// ball the given number of times.
+pub fn ai_speed_level(score: u16) -> u8 {
+    (score / RETURNS_PER_AI_SPEED_UP).min(MAX_AI_SPEED_LEVEL.into()) as u8
+}
+
+/// the direction the server moves its paddle in a co-op survival game on the given tick, towards
+/// the ball's row. the paddle moves on more ticks the higher the players' score.
+pub fn ai_direction(
+    game_state: &GameState,
+    dimensions: GameDimensions,
+    tick: u32,
+    score: u16,
+) -> MoveDirection {
+    if tick % AI_MOVE_PERIOD > u32::from(ai_speed_level(score)) {
+        return MoveDirection::Stop;
+    }
+    let paddle = game_state.right_paddle;
+    if game_state.ball.y < paddle {
+        MoveDirection::Up
+    } else if game_state.ball.y >= paddle + dimensions.paddle_height {
+        MoveDirection::Down
+    } else {
+        MoveDirection::Stop
+    }
+}
+
+/// how fast the ball is going after being hit by a paddle the given number of times in a game.
+pub fn speed_level(hits: u16) -> u8 {
+    hits.min(MAX_SPEED_LEVEL.into()) as u8
+}
+
+/// the interval between ticks of a game at the given speed level. never shorter than
+/// [`MIN_TICK_INTERVAL_MS`], unless the lobby's own tick interval is.
+pub fn sped_up_tick_interval_ms(tick_interval_ms: u16, speed_level: u8) -> u16 {
+    let speed_level = u32::from(speed_level.min(MAX_SPEED_LEVEL));
+    let percent = 100 - u32::from(SPEED_UP_PERCENT_PER_HIT) * speed_level;
+    let sped_up = (u32::from(tick_interval_ms) * percent / 100) as u16;
+    sped_up.max(MIN_TICK_INTERVAL_MS.min(tick_interval_ms))
+}
+
+/// moves a paddle by its speed in the given direction, stopping at the edges of the game.
+pub fn move_paddle(
+    dimensions: GameDimensions,
+    handicap: Handicap,
+    pos: u16,
+    direction: MoveDirection,
+) -> u16 {
+    match direction {
+        MoveDirection::Stop => pos,
+        MoveDirection::Up => pos.saturating_sub(handicap.paddle_speed()),
+        MoveDirection::Down => pos
+            .saturating_add(handicap.paddle_speed())
+            .min(handicap.max_paddle_position(dimensions)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game_state::{
+            ai_direction, ai_speed_level, move_paddle, sped_up_tick_interval_ms, speed_level, Ball,
+            GameDimensions, GameState, Handicap, Handicaps, Modifier, MoveDirection, Side,
+            GRAVITY_PERIOD, MAX_AI_SPEED_LEVEL, MAX_SPEED_LEVEL, MIN_TICK_INTERVAL_MS,
+            NORMAL_TICK_INTERVAL_MS, PADDLE_CELLS_PER_TICK, RETURNS_PER_AI_SPEED_UP, WOBBLE_PERIOD,
+        },
+        DeserializeMessageError, MAX_U14,
+    };
+
+    const DIMENSIONS: GameDimensions = GameDimensions::DEFAULT;
+
+    fn game_state(x: u16, y: u16, moving_right: bool, moving_down: bool) -> GameState {
+        GameState {
+            left_paddle: 0,
+            right_paddle: 0,
+            ball: Ball {
+                x,
+                y,
+                moving_right,
+                moving_down,
+            },
+        }
+    }
+
+    #[test]
+    fn paddle_moves_in_direction() {
+        assert_eq!(
+            move_paddle(DIMENSIONS, Handicap::NONE, 3, MoveDirection::Stop),
+            3
+        );
+        assert_eq!(
+            move_paddle(DIMENSIONS, Handicap::NONE, 3, MoveDirection::Up),
+            3 - PADDLE_CELLS_PER_TICK
+        );
+        assert_eq!(
+            move_paddle(DIMENSIONS, Handicap::NONE, 3, MoveDirection::Down),
+            3 + PADDLE_CELLS_PER_TICK
+        );
+    }
+
+    #[test]
+    fn paddle_stops_at_edges() {
+        assert_eq!(
+            move_paddle(DIMENSIONS, Handicap::NONE, 0, MoveDirection::Up),
+            0
+        );
+        assert_eq!(
+            move_paddle(
+                DIMENSIONS,
+                Handicap::NONE,
+                DIMENSIONS.max_paddle_position(),
+                MoveDirection::Down
+            ),
+            DIMENSIONS.max_paddle_position(),
+        );
+    }
+
+    #[test]
+    fn tick_moves_paddles_and_ball() {
+        let mut state = game_state(10, 5, true, false);
+        assert_eq!(
+            state.tick(
+                DIMENSIONS,
+                Handicaps::NONE,
+                Modifier::Classic,
+                MoveDirection::Down,
+                MoveDirection::Stop
+            ),
+            None
+        );
+        assert_eq!(state.left_paddle, PADDLE_CELLS_PER_TICK);
+        assert_eq!(state.right_paddle, 0);
+        assert_eq!(state.ball, game_state(11, 4, true, false).ball);
+    }
+
+    #[test]
+    fn ball_bounces_off_walls() {
+        let mut state = game_state(10, 0, true, false);
+        state.tick(
+            DIMENSIONS,
+            Handicaps::NONE,
+            Modifier::Classic,
+            MoveDirection::Stop,
+            MoveDirection::Stop,
+        );
+        assert_eq!(state.ball, game_state(11, 1, true, true).ball);
+        let mut state = game_state(10, DIMENSIONS.height() - 1, true, true);
+        state.tick(
+            DIMENSIONS,
+            Handicaps::NONE,
+            Modifier::Classic,
+            MoveDirection::Stop,
+            MoveDirection::Stop,
+        );
+        assert_eq!(
+            state.ball,
+            game_state(11, DIMENSIONS.height() - 2, true, false).ball
+        );
+    }
+
+    #[test]
+    fn ball_moving_away_from_wall_does_not_bounce() {
+        let mut state = game_state(10, 0, true, true);
+        state.tick(
+            DIMENSIONS,
+            Handicaps::NONE,
+            Modifier::Classic,
+            MoveDirection::Stop,
+            MoveDirection::Stop,
+        );
+        assert_eq!(state.ball, game_state(11, 1, true, true).ball);
+    }
+
+    fn tick_ball(modifier: Modifier, state: &mut GameState) {
+        state.tick(
+            DIMENSIONS,
+            Handicaps::NONE,
+            modifier,
+            MoveDirection::Stop,
+            MoveDirection::Stop,
+        );
+    }
+
+    #[test]
+    fn gravity_pulls_rising_ball_down() {
+        let mut state = game_state(GRAVITY_PERIOD * 2, 5, true, false);
+        tick_ball(Modifier::Gravity, &mut state);
+        assert_eq!(
+            state.ball,
+            game_state(GRAVITY_PERIOD * 2 + 1, 6, true, true).ball
+        );
+        // the ball keeps rising between the columns gravity acts on.
+        let mut state = game_state(GRAVITY_PERIOD * 2 + 1, 5, true, false);
+        tick_ball(Modifier::Gravity, &mut state);
+        assert_eq!(
+            state.ball,
+            game_state(GRAVITY_PERIOD * 2 + 2, 4, true, false).ball
+        );
+        // and still bounces off the bottom of the field.
+        let mut state = game_state(GRAVITY_PERIOD * 2, DIMENSIONS.height() - 1, false, true);
+        tick_ball(Modifier::Gravity, &mut state);
+        assert_eq!(
+            state.ball,
+            game_state(
+                GRAVITY_PERIOD * 2 - 1,
+                DIMENSIONS.height() - 2,
+                false,
+                false
+            )
+            .ball
+        );
+    }
+
+    #[test]
+    fn wobbling_ball_flies_level_every_other_stretch() {
+        let mut state = game_state(WOBBLE_PERIOD * 2, 5, true, true);
+        tick_ball(Modifier::Wobble, &mut state);
+        assert_eq!(
+            state.ball,
+            game_state(WOBBLE_PERIOD * 2 + 1, 6, true, true).ball
+        );
+        let mut state = game_state(WOBBLE_PERIOD * 3, 5, true, true);
+        tick_ball(Modifier::Wobble, &mut state);
+        assert_eq!(
+            state.ball,
+            game_state(WOBBLE_PERIOD * 3 + 1, 5, true, true).ball
+        );
+        // a level ball still bounces off a wall, moving away from it once it's out of the stretch.
+        let mut state = game_state(WOBBLE_PERIOD * 4 - 1, 0, false, false);
+        tick_ball(Modifier::Wobble, &mut state);
+        assert_eq!(
+            state.ball,
+            game_state(WOBBLE_PERIOD * 4 - 2, 0, false, true).ball
+        );
+    }
+
+    #[test]
+    fn ball_wraps_around_walls() {
+        let bottom = DIMENSIONS.height() - 1;
+        let mut state = game_state(10, 0, true, false);
+        tick_ball(Modifier::Wrap, &mut state);
+        assert_eq!(state.ball, game_state(11, bottom, true, false).ball);
+        let mut state = game_state(10, bottom, false, true);
+        tick_ball(Modifier::Wrap, &mut state);
+        assert_eq!(state.ball, game_state(9, 0, false, true).ball);
+        // a ball in the top or bottom row moving away from it doesn't wrap.
+        let mut state = game_state(10, 0, true, true);
+        tick_ball(Modifier::Wrap, &mut state);
+        assert_eq!(state.ball, game_state(11, 1, true, true).ball);
+        let mut state = game_state(10, bottom, true, false);
+        tick_ball(Modifier::Wrap, &mut state);
+        assert_eq!(state.ball, game_state(11, bottom - 1, true, false).ball);
+    }
+
+    #[test]
+    fn ball_wraps_around_walls_at_paddles() {
+        let bottom = DIMENSIONS.height() - 1;
+        // the paddle is hit in the top row, and the ball then wraps around to the bottom one.
+        let mut state = game_state(1, 0, false, false);
+        tick_ball(Modifier::Wrap, &mut state);
+        assert_eq!(state.ball, game_state(2, bottom, true, false).ball);
+        // the paddle at the top doesn't reach the bottom row, so the ball gets past it before it
+        // would wrap around.
+        let mut state = game_state(1, bottom, false, true);
+        assert_eq!(
+            state.tick(
+                DIMENSIONS,
+                Handicaps::NONE,
+                Modifier::Wrap,
+                MoveDirection::Stop,
+                MoveDirection::Stop
+            ),
+            Some(Side::Right)
+        );
+        assert_eq!(state.ball, game_state(1, bottom, false, true).ball);
+    }
+
+    #[test]
+    fn ball_wraps_around_shortest_field() {
+        let dimensions = GameDimensions::new(5, 2, 1).unwrap();
+        let mut state = game_state(2, 1, true, true);
+        state.tick(
+            dimensions,
+            Handicaps::NONE,
+            Modifier::Wrap,
+            MoveDirection::Stop,
+            MoveDirection::Stop,
+        );
+        assert_eq!(state.ball, game_state(3, 0, true, true).ball);
+        assert!(dimensions.validate(&state).is_ok());
+    }
+
+    #[test]
+    fn ball_bounces_off_paddles() {
+        let mut state = game_state(1, 2, false, true);
+        assert_eq!(
+            state.tick(
+                DIMENSIONS,
+                Handicaps::NONE,
+                Modifier::Classic,
+                MoveDirection::Stop,
+                MoveDirection::Stop
+            ),
+            None
+        );
+        assert_eq!(state.ball, game_state(2, 3, true, true).ball);
+        let mut state = game_state(DIMENSIONS.width() - 2, 2, true, true);
+        assert_eq!(
+            state.tick(
+                DIMENSIONS,
+                Handicaps::NONE,
+                Modifier::Classic,
+                MoveDirection::Stop,
+                MoveDirection::Stop
+            ),
+            None
+        );
+        assert_eq!(
+            state.ball,
+            game_state(DIMENSIONS.width() - 3, 3, false, true).ball
+        );
+    }
+
+    #[test]
+    fn missed_ball_wins_for_other_side() {
+        let mut state = game_state(1, DIMENSIONS.paddle_height(), false, true);
+        assert_eq!(
+            state.tick(
+                DIMENSIONS,
+                Handicaps::NONE,
+                Modifier::Classic,
+                MoveDirection::Stop,
+                MoveDirection::Stop
+            ),
+            Some(Side::Right)
+        );
+        let mut state = game_state(
+            DIMENSIONS.width() - 2,
+            DIMENSIONS.paddle_height(),
+            true,
+            true,
+        );
+        assert_eq!(
+            state.tick(
+                DIMENSIONS,
+                Handicaps::NONE,
+                Modifier::Classic,
+                MoveDirection::Stop,
+                MoveDirection::Stop
+            ),
+            Some(Side::Left)
+        );
+    }
+
+    #[test]
+    fn coop_partner_defends_left_side() {
+        // the left paddle is at the top, so only the partner's can reach the bottom row.
+        let bottom = DIMENSIONS.height() - 1;
+        let mut state = game_state(1, bottom, false, false);
+        let mut partner_paddle = DIMENSIONS.max_paddle_position();
+        assert_eq!(
+            state.tick_coop(
+                DIMENSIONS,
+                &mut partner_paddle,
+                MoveDirection::Stop,
+                MoveDirection::Stop,
+                MoveDirection::Stop,
+            ),
+            None
+        );
+        assert_eq!(state.ball, game_state(2, bottom - 1, true, false).ball);
+        // once the partner moves away, the ball gets past both of them.
+        let mut state = game_state(1, bottom, false, false);
+        assert_eq!(
+            state.tick_coop(
+                DIMENSIONS,
+                &mut partner_paddle,
+                MoveDirection::Stop,
+                MoveDirection::Up,
+                MoveDirection::Stop,
+            ),
+            Some(Side::Right)
+        );
+        assert_eq!(partner_paddle, DIMENSIONS.max_paddle_position() - 1);
+    }
+
+    #[test]
+    fn coop_ai_paddle_moves_and_misses() {
+        let mut state = game_state(DIMENSIONS.width() - 2, 2, true, true);
+        let mut partner_paddle = 0;
+        assert_eq!(
+            state.tick_coop(
+                DIMENSIONS,
+                &mut partner_paddle,
+                MoveDirection::Stop,
+                MoveDirection::Stop,
+                MoveDirection::Down,
+            ),
+            None
+        );
+        assert_eq!(state.right_paddle, 1);
+        let mut state = game_state(
+            DIMENSIONS.width() - 2,
+            DIMENSIONS.paddle_height(),
+            true,
+            true,
+        );
+        assert_eq!(
+            state.tick_coop(
+                DIMENSIONS,
+                &mut partner_paddle,
+                MoveDirection::Stop,
+                MoveDirection::Stop,
+                MoveDirection::Stop,
+            ),
+            Some(Side::Left)
+        );
+    }
+
+    #[test]
+    fn ai_speeds_up_with_score() {
+        assert_eq!(ai_speed_level(0), 0);
+        assert_eq!(ai_speed_level(RETURNS_PER_AI_SPEED_UP - 1), 0);
+        assert_eq!(ai_speed_level(RETURNS_PER_AI_SPEED_UP), 1);
+        assert_eq!(ai_speed_level(u16::MAX), MAX_AI_SPEED_LEVEL);
+        // the ball is below the paddle, which only moves on some ticks until it's at top speed.
+        let state = game_state(20, 8, true, true);
+        let moves = |score| {
+            (0..8)
+                .filter(|&tick| {
+                    ai_direction(&state, DIMENSIONS, tick, score) == MoveDirection::Down
+                })
+                .count()
+        };
+        assert_eq!(moves(0), 2);
+        assert_eq!(moves(RETURNS_PER_AI_SPEED_UP), 4);
+        assert_eq!(moves(u16::MAX), 8);
+        // the paddle stays put once the ball is in reach.
+        let state = game_state(20, 3, true, true);
+        assert_eq!(ai_direction(&state, DIMENSIONS, 0, 0), MoveDirection::Stop);
+    }
+
+    #[test]
+    fn dimensions_within_limits() {
+        assert_eq!(
+            GameDimensions::new(51, 11, 5),
+            Some(GameDimensions::DEFAULT)
+        );
+        assert!(GameDimensions::new(126, 126, 112).is_some());
+        assert!(GameDimensions::new(5, 2, 1).is_some());
+        assert!(GameDimensions::new(MAX_U14, MAX_U14, 1).is_some());
+        // too small to play on.
+        assert_eq!(GameDimensions::new(4, 11, 5), None);
+        assert_eq!(GameDimensions::new(51, 1, 1), None);
+        assert_eq!(GameDimensions::new(51, 11, 0), None);
+        assert_eq!(GameDimensions::new(51, 11, 12), None);
+        // too large to serialize.
+        assert_eq!(GameDimensions::new(MAX_U14 + 1, 11, 5), None);
+        assert_eq!(GameDimensions::new(51, MAX_U14 + 1, 5), None);
+    }
+
+    #[test]
+    fn dimensions_requiring_wide_encoding() {
+        assert!(!GameDimensions::DEFAULT.requires_wide_encoding());
+        assert!(!GameDimensions::new(126, 126, 112)
+            .unwrap()
+            .requires_wide_encoding());
+        assert!(GameDimensions::new(127, 11, 5)
+            .unwrap()
+            .requires_wide_encoding());
+        assert!(GameDimensions::new(51, 127, 113)
+            .unwrap()
+            .requires_wide_encoding());
+        assert!(GameDimensions::new(51, 20, 5)
+            .unwrap()
+            .requires_wide_encoding());
+    }
+
+    #[test]
+    fn ball_bounces_off_larger_paddles() {
+        let dimensions = GameDimensions::new(21, 20, 8).unwrap();
+        let mut state = game_state(19, 7, true, true);
+        assert_eq!(
+            state.tick(
+                dimensions,
+                Handicaps::NONE,
+                Modifier::Classic,
+                MoveDirection::Stop,
+                MoveDirection::Stop
+            ),
+            None
+        );
+        assert_eq!(state.ball, game_state(18, 8, false, true).ball);
+        let mut state = game_state(19, 8, true, true);
+        assert_eq!(
+            state.tick(
+                dimensions,
+                Handicaps::NONE,
+                Modifier::Classic,
+                MoveDirection::Stop,
+                MoveDirection::Stop
+            ),
+            Some(Side::Left)
+        );
+        assert_eq!(
+            move_paddle(dimensions, Handicap::NONE, 12, MoveDirection::Down),
+            dimensions.max_paddle_position()
+        );
+    }
+
+    #[test]
+    fn handicapped_paddles() {
+        let handicaps = Handicaps {
+            left: Handicap {
+                paddle_height: Some(2),
+                paddle_speed: None,
+            },
+            right: Handicap {
+                paddle_height: None,
+                paddle_speed: Some(3),
+            },
+        };
+        // the ball gets past the shorter left paddle where it would hit a full size one.
+        let mut state = game_state(1, 2, false, true);
+        assert_eq!(
+            state.tick(
+                DIMENSIONS,
+                handicaps,
+                Modifier::Classic,
+                MoveDirection::Stop,
+                MoveDirection::Stop
+            ),
+            Some(Side::Right)
+        );
+        // the faster right paddle moves three cells a tick, and the shorter left paddle can move
+        // further down.
+        let mut state = game_state(10, 5, true, true);
+        state.tick(
+            DIMENSIONS,
+            handicaps,
+            Modifier::Classic,
+            MoveDirection::Down,
+            MoveDirection::Down,
+        );
+        assert_eq!(state.left_paddle, 1);
+        assert_eq!(state.right_paddle, 3);
+        assert_eq!(
+            move_paddle(DIMENSIONS, handicaps.left, 8, MoveDirection::Down),
+            DIMENSIONS.height() - 2
+        );
+        assert_eq!(
+            move_paddle(DIMENSIONS, handicaps.right, 5, MoveDirection::Down),
+            DIMENSIONS.max_paddle_position()
+        );
+        // paddles taller than the field are cut down to fit it.
+        let tall = Handicap {
+            paddle_height: Some(100),
+            paddle_speed: None,
+        };
+        assert_eq!(tall.paddle_height(DIMENSIONS), DIMENSIONS.height());
+        let fitted = Handicaps {
+            left: tall,
+            right: Handicap::NONE,
+        }
+        .fit(DIMENSIONS);
+        assert_eq!(fitted.left.paddle_height, Some(DIMENSIONS.height()));
+    }
+
+    #[test]
+    fn handicaps_requiring_wide_encoding() {
+        let dimensions = GameDimensions::new(51, 20, 8).unwrap();
+        assert!(!dimensions.requires_wide_encoding());
+        let handicaps = Handicaps {
+            left: Handicap::NONE,
+            right: Handicap {
+                paddle_height: Some(2),
+                paddle_speed: None,
+            },
+        };
+        assert!(dimensions.requires_wide_encoding_with(handicaps));
+        let mut state = game_state(10, 5, true, true);
+        state.right_paddle = 18;
+        assert_eq!(dimensions.validate_with(handicaps, &state), Ok(()));
+        assert_eq!(
+            dimensions.validate(&state),
+            Err(DeserializeMessageError::InvalidPaddlePosition)
+        );
+    }
+
+    #[test]
+    fn speed_level_capped() {
+        assert_eq!(speed_level(0), 0);
+        assert_eq!(speed_level(2), 2);
+        assert_eq!(speed_level(u16::MAX), MAX_SPEED_LEVEL);
+    }
+
+    #[test]
+    fn ticks_shorten_with_speed_level() {
+        assert_eq!(sped_up_tick_interval_ms(NORMAL_TICK_INTERVAL_MS, 0), 100);
+        assert_eq!(sped_up_tick_interval_ms(NORMAL_TICK_INTERVAL_MS, 1), 90);
+        assert_eq!(
+            sped_up_tick_interval_ms(NORMAL_TICK_INTERVAL_MS, MAX_SPEED_LEVEL),
+            50
+        );
+        // the ball stops speeding up once it reaches the highest level.
+        assert_eq!(
+            sped_up_tick_interval_ms(NORMAL_TICK_INTERVAL_MS, MAX_SPEED_LEVEL + 1),
+            50
+        );
+        assert_eq!(
+            sped_up_tick_interval_ms(30, MAX_SPEED_LEVEL),
+            MIN_TICK_INTERVAL_MS
+        );
+        assert_eq!(sped_up_tick_interval_ms(10, MAX_SPEED_LEVEL), 10);
+    }
+
+    #[test]
+    fn validate_game_state() {
+        let dimensions = GameDimensions::new(21, 10, 5).unwrap();
+        assert_eq!(dimensions.validate(&game_state(20, 9, true, true)), Ok(()));
+        assert_eq!(
+            dimensions.validate(&game_state(21, 9, true, true)),
+            Err(DeserializeMessageError::InvalidBallPosition)
+        );
+        assert_eq!(
+            dimensions.validate(&game_state(20, 10, true, true)),
+            Err(DeserializeMessageError::InvalidBallPosition)
+        );
+        let mut state = game_state(10, 5, true, true);
+        state.right_paddle = 6;
+        assert_eq!(
+            dimensions.validate(&state),
+            Err(DeserializeMessageError::InvalidPaddlePosition)
+        );
+    }
+}
@@ -0,0 +1,302 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{self, ErrorKind, Read},
+    net::TcpStream,
+    time::Duration,
+};
+
+use socket2::{SockRef, TcpKeepalive};
+
+/// the number of bytes requested from the underlying reader at a time.
+const READ_CHUNK_SIZE: usize = 64;
+
+/// reads delimiter-terminated messages from a byte stream.
+/// a single read from the stream may contain part of a message, or several messages at once, so
+/// bytes are buffered until a complete message is available.
+pub struct MessageReader<R: Read> {
+    inner: R,
+    delimiter: u8,
+    max_message_size: usize,
+    buffer: Vec<u8>,
+    /// the number of bytes at the start of the buffer belonging to the last returned message.
+    consumed: usize,
+    /// whether the buffered bytes are the tail of a message already known to be too large. kept
+    /// across reads, so that an error partway through skipping it doesn't end the skip.
+    skipping_oversized_message: bool,
+}
+
+#[derive(Debug)]
+pub enum ReadMessageError {
+    /// the stream ended. any incomplete message left in the buffer is discarded.
+    Closed,
+    /// a message was longer than the maximum message size. the message has been skipped, so
+    /// subsequent messages can still be read.
+    OversizedMessage,
+    Io(io::Error),
+}
+
+impl Display for ReadMessageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadMessageError::Closed => Display::fmt("connection closed", f),
+            ReadMessageError::OversizedMessage => Display::fmt("message too large", f),
+            ReadMessageError::Io(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+impl Error for ReadMessageError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReadMessageError::Closed | ReadMessageError::OversizedMessage => None,
+            ReadMessageError::Io(source) => Some(source),
+        }
+    }
+}
+
+impl<R: Read> MessageReader<R> {
+    /// `max_message_size` excludes the delimiter.
+    pub fn new(inner: R, delimiter: u8, max_message_size: usize) -> Self {
+        Self {
+            inner,
+            delimiter,
+            max_message_size,
+            buffer: Vec::with_capacity(max_message_size + READ_CHUNK_SIZE),
+            consumed: 0,
+            skipping_oversized_message: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// blocks until a complete message has been read, returning it without its delimiter.
+    pub fn read_message(&mut self) -> Result<&[u8], ReadMessageError> {
+        self.buffer.drain(..self.consumed);
+        self.consumed = 0;
+        loop {
+            if let Some(pos) = self.buffer.iter().position(|&b| b == self.delimiter) {
+                if self.skipping_oversized_message {
+                    self.skipping_oversized_message = false;
+                    self.buffer.drain(..=pos);
+                    return Err(ReadMessageError::OversizedMessage);
+                }
+                if pos > self.max_message_size {
+                    self.buffer.drain(..=pos);
+                    return Err(ReadMessageError::OversizedMessage);
+                }
+                self.consumed = pos + 1;
+                return Ok(&self.buffer[..pos]);
+            }
+            if self.buffer.len() > self.max_message_size {
+                // the delimiter could no longer arrive in time for this message to fit, so there's
+                // no need to keep buffering it.
+                self.skipping_oversized_message = true;
+                self.buffer.clear();
+            }
+            let len = self.buffer.len();
+            self.buffer.resize(len + READ_CHUNK_SIZE, 0);
+            let read = self.inner.read(&mut self.buffer[len..]);
+            self.buffer.truncate(len + *read.as_ref().unwrap_or(&0));
+            match read {
+                Ok(0) => return Err(ReadMessageError::Closed),
+                Ok(_) => {}
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(ReadMessageError::Io(err)),
+            }
+        }
+    }
+}
+
+/// how the os checks that the other end of an idle connection is still there, so that a connection
+/// that was dropped without being closed, such as by a nat, is noticed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Keepalive {
+    /// how long the connection is idle before the first probe is sent.
+    pub idle: Duration,
+    /// how long to wait between unanswered probes, if not the os' default. only set where the os
+    /// supports it.
+    pub interval: Option<Duration>,
+    /// how many probes go unanswered before the connection is dropped, if not the os' default.
+    /// only set where the os supports it.
+    pub retries: Option<u32>,
+}
+
+impl Keepalive {
+    pub fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        #[allow(unused_mut)]
+        let mut keepalive = TcpKeepalive::new().with_time(self.idle);
+        #[cfg(any(
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "ios",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "netbsd",
+            target_os = "windows",
+        ))]
+        if let Some(interval) = self.interval {
+            keepalive = keepalive.with_interval(interval);
+        }
+        #[cfg(any(
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "ios",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "netbsd",
+        ))]
+        if let Some(retries) = self.retries {
+            keepalive = keepalive.with_retries(retries);
+        }
+        SockRef::from(stream).set_tcp_keepalive(&keepalive)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        io::{self, ErrorKind, Read},
+    };
+
+    use crate::io::{MessageReader, ReadMessageError};
+
+    /// returns at most one byte per read, to simulate a message arriving in several segments.
+    struct TrickleReader<'a>(&'a [u8]);
+
+    impl Read for TrickleReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((&b, rest)) if !buf.is_empty() => {
+                    buf[0] = b;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn partial_reads() {
+        let mut reader = MessageReader::new(TrickleReader(&[1, 2, 3, 0xFF, 4, 0xFF]), 0xFF, 3);
+        assert_eq!(reader.read_message().unwrap(), &[1, 2, 3]);
+        assert_eq!(reader.read_message().unwrap(), &[4]);
+        assert!(matches!(
+            reader.read_message(),
+            Err(ReadMessageError::Closed)
+        ));
+    }
+
+    #[test]
+    fn coalesced_messages() {
+        let bytes: &[u8] = &[1, 0xFF, 2, 3, 0xFF, 0xFF, 4, 0xFF];
+        let mut reader = MessageReader::new(bytes, 0xFF, 3);
+        assert_eq!(reader.read_message().unwrap(), &[1]);
+        assert_eq!(reader.read_message().unwrap(), &[2, 3]);
+        assert_eq!(reader.read_message().unwrap(), &[]);
+        assert_eq!(reader.read_message().unwrap(), &[4]);
+        assert!(matches!(
+            reader.read_message(),
+            Err(ReadMessageError::Closed)
+        ));
+    }
+
+    #[test]
+    fn oversized_messages_are_skipped() {
+        let mut bytes = vec![1; 200];
+        bytes.extend([0xFF, 1, 2, 3, 4, 0xFF, 5, 0xFF]);
+        let mut reader = MessageReader::new(bytes.as_slice(), 0xFF, 3);
+        assert!(matches!(
+            reader.read_message(),
+            Err(ReadMessageError::OversizedMessage)
+        ));
+        assert!(matches!(
+            reader.read_message(),
+            Err(ReadMessageError::OversizedMessage)
+        ));
+        assert_eq!(reader.read_message().unwrap(), &[5]);
+    }
+
+    /// returns each of its reads in turn, as a non-blocking socket might.
+    struct ScriptedReader(VecDeque<io::Result<Vec<u8>>>);
+
+    impl Read for ScriptedReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.pop_front() {
+                Some(Ok(bytes)) => {
+                    buf[..bytes.len()].copy_from_slice(&bytes);
+                    Ok(bytes.len())
+                }
+                Some(Err(err)) => Err(err),
+                None => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn oversized_message_skipped_across_errors() {
+        let reads = [
+            Ok(vec![1; 30]),
+            Err(ErrorKind::WouldBlock.into()),
+            Ok(vec![2, 3, 0xFF]),
+            Ok(vec![4, 0xFF]),
+        ];
+        let mut reader = MessageReader::new(ScriptedReader(reads.into()), 0xFF, 10);
+        assert!(matches!(
+            reader.read_message(),
+            Err(ReadMessageError::Io(err)) if err.kind() == ErrorKind::WouldBlock
+        ));
+        assert!(matches!(
+            reader.read_message(),
+            Err(ReadMessageError::OversizedMessage)
+        ));
+        assert_eq!(reader.read_message().unwrap(), &[4]);
+    }
+
+    #[test]
+    fn incomplete_message_before_close() {
+        let bytes: &[u8] = &[1, 0xFF, 2];
+        let mut reader = MessageReader::new(bytes, 0xFF, 3);
+        assert_eq!(reader.read_message().unwrap(), &[1]);
+        assert!(matches!(
+            reader.read_message(),
+            Err(ReadMessageError::Closed)
+        ));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn applies_keepalive() {
+        use std::{
+            net::{TcpListener, TcpStream},
+            time::Duration,
+        };
+
+        use socket2::SockRef;
+
+        use crate::io::Keepalive;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        Keepalive {
+            idle: Duration::from_secs(30),
+            interval: Some(Duration::from_secs(5)),
+            retries: Some(4),
+        }
+        .apply(&stream)
+        .unwrap();
+        let socket = SockRef::from(&stream);
+        assert!(socket.keepalive().unwrap());
+        assert_eq!(socket.keepalive_time().unwrap(), Duration::from_secs(30));
+        assert_eq!(socket.keepalive_interval().unwrap(), Duration::from_secs(5));
+        assert_eq!(socket.keepalive_retries().unwrap(), 4);
+    }
+}
@@ -1,43 +1,132 @@
-use std::{error::Error, fmt::Display, str::Utf8Error};
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+#[cfg(any(feature = "alloc", test))]
+extern crate alloc;
+
+use core::{error::Error, fmt::Display};
+
+use game_state::{
+    GameDimensions, Handicap, Handicaps, Modifier, MoveDirection, Ruleset, Side,
+    MAX_PADDLE_CELLS_PER_TICK, MAX_TICK_INTERVAL_MS, MIN_TICK_INTERVAL_MS,
+};
+
+#[cfg(any(feature = "proptest", test))]
+pub mod arbitrary;
+pub mod checksum;
 pub mod client_msg;
+pub mod cosmetics;
 pub mod game_state;
+#[cfg(feature = "std")]
+pub mod io;
+pub mod lobby_id;
 pub mod server_msg;
+#[cfg(feature = "std")]
+pub mod session;
+pub mod tournament;
 
-pub const LOBBY_ID_LEN: usize = 4;
-
-pub type LobbyId = String;
+use lobby_id::ParseLobbyIdError;
+pub use lobby_id::{LobbyId, LOBBY_ID_LEN, MAX_LOBBY_ID_LEN};
 
 #[derive(Debug)]
 #[cfg_attr(test, derive(PartialEq))]
 pub enum DeserializeMessageError {
     EmptyMessage,
     InvalidBallPosition,
-    InvalidByteCount,
+    InvalidBracket,
+    InvalidByteCount {
+        state_id: u8,
+        message_id: u8,
+        expected: usize,
+        actual: usize,
+    },
+    InvalidCapabilities,
+    InvalidCosmetics,
+    InvalidCount,
+    InvalidDuration,
+    InvalidGameDimensions,
+    InvalidHandicap,
+    InvalidLobbyId(ParseLobbyIdError),
+    InvalidMatchLength,
+    InvalidModifier,
+    InvalidMoveDirection,
     InvalidPaddlePosition,
-    UnrecognisedMessageVariant,
-    InvalidState,
-    Utf8Error(Utf8Error),
+    InvalidProtocolState,
+    InvalidScore,
+    InvalidSeat,
+    InvalidSide,
+    InvalidSpeedLevel,
+    InvalidStandings,
+    InvalidTickInterval,
+    InvalidTimeout,
+    InvalidTournamentSize,
+    InvalidVersion,
+    UnrecognisedMessageVariant {
+        state_id: u8,
+        message_id: u8,
+    },
+    InvalidState {
+        expected: u8,
+        actual: u8,
+    },
 }
 
 impl Display for DeserializeMessageError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             DeserializeMessageError::EmptyMessage => Display::fmt("empty message", f),
             DeserializeMessageError::InvalidBallPosition => {
                 Display::fmt("invalid ball position", f)
             }
-            DeserializeMessageError::InvalidByteCount => Display::fmt("invalid amount of bytes", f),
+            DeserializeMessageError::InvalidBracket => Display::fmt("invalid bracket", f),
+            DeserializeMessageError::InvalidByteCount {
+                state_id,
+                message_id,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "invalid amount of bytes for message {message_id} in state {state_id}: expected {expected}, got {actual}"
+            ),
+            DeserializeMessageError::InvalidCapabilities => Display::fmt("invalid capabilities", f),
+            DeserializeMessageError::InvalidCosmetics => Display::fmt("invalid cosmetics", f),
+            DeserializeMessageError::InvalidCount => Display::fmt("invalid count", f),
+            DeserializeMessageError::InvalidDuration => Display::fmt("invalid duration", f),
+            DeserializeMessageError::InvalidGameDimensions => {
+                Display::fmt("invalid game dimensions", f)
+            }
+            DeserializeMessageError::InvalidHandicap => Display::fmt("invalid handicap", f),
+            DeserializeMessageError::InvalidLobbyId(err) => Display::fmt(err, f),
+            DeserializeMessageError::InvalidMatchLength => Display::fmt("invalid match length", f),
+            DeserializeMessageError::InvalidModifier => Display::fmt("invalid modifier", f),
+            DeserializeMessageError::InvalidMoveDirection => {
+                Display::fmt("invalid move direction", f)
+            }
             DeserializeMessageError::InvalidPaddlePosition => {
                 Display::fmt("invalid paddle position", f)
             }
-            DeserializeMessageError::InvalidState => {
-                Display::fmt("invalid state", f)
+            DeserializeMessageError::InvalidProtocolState => {
+                Display::fmt("invalid protocol state", f)
+            }
+            DeserializeMessageError::InvalidScore => Display::fmt("invalid score", f),
+            DeserializeMessageError::InvalidSeat => Display::fmt("invalid seat", f),
+            DeserializeMessageError::InvalidSide => Display::fmt("invalid side", f),
+            DeserializeMessageError::InvalidSpeedLevel => Display::fmt("invalid speed level", f),
+            DeserializeMessageError::InvalidStandings => Display::fmt("invalid standings", f),
+            DeserializeMessageError::InvalidState { expected, actual } => {
+                write!(f, "invalid state: expected {expected}, got {actual}")
+            }
+            DeserializeMessageError::InvalidTickInterval => {
+                Display::fmt("invalid tick interval", f)
             }
-            DeserializeMessageError::UnrecognisedMessageVariant => {
-                Display::fmt("unrecognised message", f)
+            DeserializeMessageError::InvalidTimeout => Display::fmt("invalid timeout", f),
+            DeserializeMessageError::InvalidTournamentSize => {
+                Display::fmt("invalid tournament size", f)
             }
-            DeserializeMessageError::Utf8Error(err) => Display::fmt(err, f),
+            DeserializeMessageError::InvalidVersion => Display::fmt("invalid version", f),
+            DeserializeMessageError::UnrecognisedMessageVariant {
+                state_id,
+                message_id,
+            } => write!(f, "unrecognised message {message_id} in state {state_id}"),
         }
     }
 }
@@ -45,13 +134,336 @@ impl Display for DeserializeMessageError {
 impl Error for DeserializeMessageError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
-            DeserializeMessageError::EmptyMessage
-            | DeserializeMessageError::InvalidBallPosition
-            | DeserializeMessageError::InvalidByteCount
-            | DeserializeMessageError::InvalidPaddlePosition
-            | DeserializeMessageError::InvalidState
-            | DeserializeMessageError::UnrecognisedMessageVariant => None,
-            DeserializeMessageError::Utf8Error(source) => Some(source),
+            DeserializeMessageError::InvalidLobbyId(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseLobbyIdError> for DeserializeMessageError {
+    fn from(value: ParseLobbyIdError) -> Self {
+        DeserializeMessageError::InvalidLobbyId(value)
+    }
+}
+
+/// serializes a message into a caller-provided buffer, so that sending it needn't allocate.
+pub trait WriteInto {
+    /// writes the message to the start of `buf`, returning the number of bytes written.
+    /// panics if `buf` is too small, which can't happen if it's at least the maximum message size
+    /// of the side sending it.
+    fn write_into(&self, buf: &mut [u8]) -> usize;
+}
+
+/// copies `bytes` to the start of `buf`, returning the number of bytes copied.
+fn write_bytes(buf: &mut [u8], bytes: &[u8]) -> usize {
+    buf[..bytes.len()].copy_from_slice(bytes);
+    bytes.len()
+}
+
+/// serializes a message into a new vec, for when allocating doesn't matter.
+#[cfg(any(feature = "alloc", test))]
+fn write_to_vec<M: WriteInto, const MAX_SIZE: usize>(message: &M) -> alloc::vec::Vec<u8> {
+    let mut buf = [0; MAX_SIZE];
+    let n = message.write_into(&mut buf);
+    buf[..n].to_vec()
+}
+
+/// how a field of a message is serialized, for messages deriving `PongMessage`.
+/// most encodings of a field take up the same number of bytes, but a field can vary in size as
+/// long as its size can be told from its first byte.
+trait FieldCodec<T> {
+    /// the size of every encoding of the field, or of the largest if they vary.
+    const SIZE: usize;
+
+    /// the size of the encoding of `value`.
+    fn size(_value: &T) -> usize {
+        Self::SIZE
+    }
+
+    /// the size of the encoding at the start of `bytes`, which holds the rest of the message and
+    /// may be empty.
+    fn read_size(_bytes: &[u8]) -> usize {
+        Self::SIZE
+    }
+
+    /// `buf` is exactly [`FieldCodec::size`] bytes long.
+    fn write(value: &T, buf: &mut [u8]);
+
+    /// `bytes` is exactly [`FieldCodec::read_size`] bytes long.
+    fn read(bytes: &[u8]) -> Result<T, DeserializeMessageError>;
+}
+
+/// a tick interval in milliseconds, serialized as a u14.
+struct TickInterval;
+
+impl FieldCodec<u16> for TickInterval {
+    const SIZE: usize = 2;
+
+    fn write(value: &u16, buf: &mut [u8]) {
+        write_bytes(buf, &serialize_tick_interval(*value));
+    }
+
+    fn read(bytes: &[u8]) -> Result<u16, DeserializeMessageError> {
+        deserialize_tick_interval(bytes)
+    }
+}
+
+/// a timeout in seconds, serialized as a u14.
+struct Timeout;
+
+impl FieldCodec<u16> for Timeout {
+    const SIZE: usize = 2;
+
+    fn write(value: &u16, buf: &mut [u8]) {
+        write_bytes(buf, &serialize_timeout(*value));
+    }
+
+    fn read(bytes: &[u8]) -> Result<u16, DeserializeMessageError> {
+        deserialize_timeout(bytes)
+    }
+}
+
+/// a count of things, serialized as a u14. counts too large to serialize are capped at
+/// [`MAX_U14`].
+struct Count;
+
+impl FieldCodec<u16> for Count {
+    const SIZE: usize = 2;
+
+    fn write(value: &u16, buf: &mut [u8]) {
+        write_bytes(buf, &serialize_u14((*value).min(MAX_U14)));
+    }
+
+    fn read(bytes: &[u8]) -> Result<u16, DeserializeMessageError> {
+        deserialize_u14(bytes).ok_or(DeserializeMessageError::InvalidCount)
+    }
+}
+
+/// a long duration in seconds, serialized as a u28. durations too long to serialize are capped at
+/// [`MAX_U28`].
+struct Seconds;
+
+impl FieldCodec<u32> for Seconds {
+    const SIZE: usize = 4;
+
+    fn write(value: &u32, buf: &mut [u8]) {
+        write_bytes(buf, &serialize_u28((*value).min(MAX_U28)));
+    }
+
+    fn read(bytes: &[u8]) -> Result<u32, DeserializeMessageError> {
+        deserialize_u28(bytes).ok_or(DeserializeMessageError::InvalidDuration)
+    }
+}
+
+/// the length prefixed to a lobby id, if there is one. ids of [`LOBBY_ID_LEN`] are sent without a
+/// prefix, as they were before ids could be longer, and a prefix can't be mistaken for the first
+/// character of an id since every character is larger than [`MAX_LOBBY_ID_LEN`].
+fn lobby_id_len_prefix(bytes: &[u8]) -> Option<usize> {
+    match bytes.first() {
+        Some(&len) if len as usize <= MAX_LOBBY_ID_LEN => Some(len as usize),
+        _ => None,
+    }
+}
+
+impl FieldCodec<LobbyId> for LobbyId {
+    const SIZE: usize = 1 + MAX_LOBBY_ID_LEN;
+
+    fn size(value: &LobbyId) -> usize {
+        match value.as_bytes().len() {
+            LOBBY_ID_LEN => LOBBY_ID_LEN,
+            len => 1 + len,
+        }
+    }
+
+    fn read_size(bytes: &[u8]) -> usize {
+        lobby_id_len_prefix(bytes).map_or(LOBBY_ID_LEN, |len| 1 + len)
+    }
+
+    fn write(value: &LobbyId, buf: &mut [u8]) {
+        let bytes = value.as_bytes();
+        if bytes.len() == LOBBY_ID_LEN {
+            write_bytes(buf, bytes);
+        } else {
+            buf[0] = bytes.len() as u8;
+            write_bytes(&mut buf[1..], bytes);
+        }
+    }
+
+    fn read(bytes: &[u8]) -> Result<LobbyId, DeserializeMessageError> {
+        match lobby_id_len_prefix(bytes) {
+            Some(_) => Ok(LobbyId::try_from(&bytes[1..])?),
+            None => Ok(LobbyId::try_from(bytes)?),
+        }
+    }
+}
+
+/// a lobby id that may be left out, in which case it's written as a zero length prefix.
+impl FieldCodec<Option<LobbyId>> for LobbyId {
+    const SIZE: usize = <LobbyId as FieldCodec<LobbyId>>::SIZE;
+
+    fn size(value: &Option<LobbyId>) -> usize {
+        value
+            .as_ref()
+            .map_or(1, <LobbyId as FieldCodec<LobbyId>>::size)
+    }
+
+    fn read_size(bytes: &[u8]) -> usize {
+        <LobbyId as FieldCodec<LobbyId>>::read_size(bytes)
+    }
+
+    fn write(value: &Option<LobbyId>, buf: &mut [u8]) {
+        match value {
+            Some(lobby_id) => <LobbyId as FieldCodec<LobbyId>>::write(lobby_id, buf),
+            None => buf[0] = 0,
+        }
+    }
+
+    fn read(bytes: &[u8]) -> Result<Option<LobbyId>, DeserializeMessageError> {
+        match bytes {
+            [0] => Ok(None),
+            _ => <LobbyId as FieldCodec<LobbyId>>::read(bytes).map(Some),
+        }
+    }
+}
+
+impl FieldCodec<GameDimensions> for GameDimensions {
+    const SIZE: usize = 6;
+
+    fn write(value: &GameDimensions, buf: &mut [u8]) {
+        write_bytes(buf, &serialize_dimensions(*value));
+    }
+
+    fn read(bytes: &[u8]) -> Result<GameDimensions, DeserializeMessageError> {
+        deserialize_dimensions(bytes)
+    }
+}
+
+/// both players' handicaps, as the paddle height and speed of each in turn. each is serialized as
+/// a u14, or zero if the paddle's isn't handicapped.
+impl FieldCodec<Handicaps> for Handicaps {
+    const SIZE: usize = 8;
+
+    fn write(value: &Handicaps, buf: &mut [u8]) {
+        for (i, handicap) in [value.left, value.right].iter().enumerate() {
+            for (j, field) in [handicap.paddle_height, handicap.paddle_speed]
+                .iter()
+                .enumerate()
+            {
+                let n = 4 * i + 2 * j;
+                write_bytes(&mut buf[n..], &serialize_u14(field.unwrap_or(0)));
+            }
+        }
+    }
+
+    fn read(bytes: &[u8]) -> Result<Handicaps, DeserializeMessageError> {
+        let field = |i: usize| match deserialize_u14(&bytes[2 * i..2 * i + 2]) {
+            Some(0) => Ok(None),
+            Some(value) => Ok(Some(value)),
+            None => Err(DeserializeMessageError::InvalidHandicap),
+        };
+        let handicap = |i: usize| {
+            let paddle_speed = field(2 * i + 1)?;
+            if paddle_speed.is_some_and(|speed| speed > MAX_PADDLE_CELLS_PER_TICK) {
+                return Err(DeserializeMessageError::InvalidHandicap);
+            }
+            Ok(Handicap {
+                paddle_height: field(2 * i)?,
+                paddle_speed,
+            })
+        };
+        Ok(Handicaps {
+            left: handicap(0)?,
+            right: handicap(1)?,
+        })
+    }
+}
+
+impl FieldCodec<MoveDirection> for MoveDirection {
+    const SIZE: usize = 1;
+
+    fn write(value: &MoveDirection, buf: &mut [u8]) {
+        buf[0] = match value {
+            MoveDirection::Stop => 0,
+            MoveDirection::Up => 1,
+            MoveDirection::Down => 2,
+        };
+    }
+
+    fn read(bytes: &[u8]) -> Result<MoveDirection, DeserializeMessageError> {
+        match bytes[0] {
+            0 => Ok(MoveDirection::Stop),
+            1 => Ok(MoveDirection::Up),
+            2 => Ok(MoveDirection::Down),
+            _ => Err(DeserializeMessageError::InvalidMoveDirection),
+        }
+    }
+}
+
+impl FieldCodec<Modifier> for Modifier {
+    const SIZE: usize = 1;
+
+    fn write(value: &Modifier, buf: &mut [u8]) {
+        buf[0] = match value {
+            Modifier::Classic => 0,
+            Modifier::Gravity => 1,
+            Modifier::Wobble => 2,
+            Modifier::Wrap => 3,
+        };
+    }
+
+    fn read(bytes: &[u8]) -> Result<Modifier, DeserializeMessageError> {
+        match bytes[0] {
+            0 => Ok(Modifier::Classic),
+            1 => Ok(Modifier::Gravity),
+            2 => Ok(Modifier::Wobble),
+            3 => Ok(Modifier::Wrap),
+            _ => Err(DeserializeMessageError::InvalidModifier),
+        }
+    }
+}
+
+impl FieldCodec<Ruleset> for Ruleset {
+    const SIZE: usize = 13;
+
+    fn write(value: &Ruleset, buf: &mut [u8]) {
+        write_bytes(buf, &serialize_tick_interval(value.tick_interval_ms));
+        write_bytes(
+            &mut buf[2..],
+            &serialize_u14(value.match_length.unwrap_or(0)),
+        );
+        Handicaps::write(&value.handicaps, &mut buf[4..12]);
+        Modifier::write(&value.modifier, &mut buf[12..]);
+    }
+
+    fn read(bytes: &[u8]) -> Result<Ruleset, DeserializeMessageError> {
+        Ok(Ruleset {
+            tick_interval_ms: deserialize_tick_interval(&bytes[..2])?,
+            match_length: match deserialize_u14(&bytes[2..4]) {
+                Some(0) => None,
+                Some(match_length) => Some(match_length),
+                None => return Err(DeserializeMessageError::InvalidMatchLength),
+            },
+            handicaps: Handicaps::read(&bytes[4..12])?,
+            modifier: Modifier::read(&bytes[12..])?,
+        })
+    }
+}
+
+impl FieldCodec<Side> for Side {
+    const SIZE: usize = 1;
+
+    fn write(value: &Side, buf: &mut [u8]) {
+        buf[0] = match value {
+            Side::Left => 0,
+            Side::Right => 1,
+        };
+    }
+
+    fn read(bytes: &[u8]) -> Result<Side, DeserializeMessageError> {
+        match bytes[0] {
+            0 => Ok(Side::Left),
+            1 => Ok(Side::Right),
+            _ => Err(DeserializeMessageError::InvalidSide),
         }
     }
 }
@@ -60,25 +472,125 @@ fn validate_state_and_get_message_id(
     value: &[u8],
     expected_state_id: u8,
 ) -> Result<u8, DeserializeMessageError> {
-    if value.len() == 0 {
+    if value.is_empty() {
         return Err(DeserializeMessageError::EmptyMessage);
     }
     let state_id = value[0] >> 4;
     if state_id != expected_state_id {
-        return Err(DeserializeMessageError::InvalidState);
+        return Err(DeserializeMessageError::InvalidState {
+            expected: expected_state_id,
+            actual: state_id,
+        });
     }
     let message_id = value[0] & 0b1111;
     Ok(message_id)
 }
 
+/// must only be called once the message is known not to be empty.
 fn validate_byte_count(slice: &[u8], exp_len: usize) -> Result<(), DeserializeMessageError> {
     if slice.len() != exp_len {
-        Err(DeserializeMessageError::InvalidByteCount)
+        Err(DeserializeMessageError::InvalidByteCount {
+            state_id: slice[0] >> 4,
+            message_id: slice[0] & 0b1111,
+            expected: exp_len,
+            actual: slice.len(),
+        })
     } else {
         Ok(())
     }
 }
 
+fn unrecognised_message_variant(value: &[u8]) -> DeserializeMessageError {
+    DeserializeMessageError::UnrecognisedMessageVariant {
+        state_id: value[0] >> 4,
+        message_id: value[0] & 0b1111,
+    }
+}
+
+/// the largest value that can be serialized with [`serialize_u14`].
+pub const MAX_U14: u16 = (1 << 14) - 1;
+
+/// serializes a value of up to 14 bits into two bytes.
+/// only 7 bits are used per byte so that neither byte can ever equal the server message delimiter.
+fn serialize_u14(value: u16) -> [u8; 2] {
+    [(value >> 7) as u8 & 0b1111111, value as u8 & 0b1111111]
+}
+
+/// returns [`None`] if either byte uses more than 7 bits.
+fn deserialize_u14(bytes: &[u8]) -> Option<u16> {
+    if bytes.iter().any(|byte| byte >> 7 != 0) {
+        return None;
+    }
+    Some((bytes[0] as u16) << 7 | bytes[1] as u16)
+}
+
+/// the largest value that can be serialized with [`serialize_u28`].
+pub const MAX_U28: u32 = (1 << 28) - 1;
+
+/// serializes a value of up to 28 bits into four bytes, using 7 bits per byte like
+/// [`serialize_u14`].
+fn serialize_u28(value: u32) -> [u8; 4] {
+    [21, 14, 7, 0].map(|shift| (value >> shift) as u8 & 0b1111111)
+}
+
+/// returns [`None`] if any byte uses more than 7 bits.
+fn deserialize_u28(bytes: &[u8]) -> Option<u32> {
+    if bytes.iter().any(|byte| byte >> 7 != 0) {
+        return None;
+    }
+    Some(
+        bytes
+            .iter()
+            .fold(0, |value, &byte| value << 7 | byte as u32),
+    )
+}
+
+fn serialize_tick_interval(tick_interval_ms: u16) -> [u8; 2] {
+    serialize_u14(tick_interval_ms)
+}
+
+fn deserialize_tick_interval(bytes: &[u8]) -> Result<u16, DeserializeMessageError> {
+    match deserialize_u14(bytes) {
+        Some(tick_interval_ms)
+            if (MIN_TICK_INTERVAL_MS..=MAX_TICK_INTERVAL_MS).contains(&tick_interval_ms) =>
+        {
+            Ok(tick_interval_ms)
+        }
+        _ => Err(DeserializeMessageError::InvalidTickInterval),
+    }
+}
+
+fn serialize_timeout(timeout_secs: u16) -> [u8; 2] {
+    serialize_u14(timeout_secs)
+}
+
+fn deserialize_timeout(bytes: &[u8]) -> Result<u16, DeserializeMessageError> {
+    deserialize_u14(bytes).ok_or(DeserializeMessageError::InvalidTimeout)
+}
+
+fn serialize_dimensions(dimensions: GameDimensions) -> [u8; 6] {
+    let [width_high, width_low] = serialize_u14(dimensions.width());
+    let [height_high, height_low] = serialize_u14(dimensions.height());
+    let [paddle_high, paddle_low] = serialize_u14(dimensions.paddle_height());
+    [
+        width_high,
+        width_low,
+        height_high,
+        height_low,
+        paddle_high,
+        paddle_low,
+    ]
+}
+
+fn deserialize_dimensions(bytes: &[u8]) -> Result<GameDimensions, DeserializeMessageError> {
+    let dimension = |i: usize| {
+        deserialize_u14(&bytes[2 * i..2 * i + 2])
+            .ok_or(DeserializeMessageError::InvalidGameDimensions)
+    };
+    GameDimensions::new(dimension(0)?, dimension(1)?, dimension(2)?)
+        .ok_or(DeserializeMessageError::InvalidGameDimensions)
+}
+
 #[cfg(test)]
 #[macro_export]
 macro_rules! assert_serialize {
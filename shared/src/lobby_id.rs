@@ -0,0 +1,205 @@
+use core::{error::Error, fmt::Display, str::FromStr};
+
+/// the length of the ids servers generate by default, and the only length peers from before ids
+/// could be longer know of.
+pub const LOBBY_ID_LEN: usize = 4;
+/// the longest a lobby id can be, for servers with too many lobbies for ids of [`LOBBY_ID_LEN`].
+pub const MAX_LOBBY_ID_LEN: usize = 8;
+
+/// the characters lobby ids are made from. 0 and 1 are left out because they could be mixed up
+/// with O and I.
+pub const LOBBY_ID_ALPHABET: &[u8; 32] = b"23456789ABCDEFGHIJKLMNOPQRSTUVWX";
+
+/// a lobby id, known to be made of `LOBBY_ID_LEN` to `MAX_LOBBY_ID_LEN` characters from
+/// `LOBBY_ID_ALPHABET`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LobbyId {
+    /// the bytes after the id are always zero, so that equal ids have equal bytes.
+    bytes: [u8; MAX_LOBBY_ID_LEN],
+    len: u8,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseLobbyIdError {
+    InvalidLength { actual: usize },
+    InvalidCharacter(char),
+}
+
+impl Display for ParseLobbyIdError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ParseLobbyIdError::InvalidLength { actual } => write!(
+                f,
+                "expected a lobby id of {LOBBY_ID_LEN} to {MAX_LOBBY_ID_LEN} characters, got {actual}"
+            ),
+            ParseLobbyIdError::InvalidCharacter(c) => {
+                write!(f, "invalid character in lobby id: {c:?}")
+            }
+        }
+    }
+}
+
+impl Error for ParseLobbyIdError {}
+
+impl LobbyId {
+    /// builds an id from indices into `LOBBY_ID_ALPHABET`. each index must be less than 32, and
+    /// there must be `LOBBY_ID_LEN` to `MAX_LOBBY_ID_LEN` of them.
+    pub fn from_digits(digits: &[u8]) -> Self {
+        assert!(is_valid_len(digits.len()), "invalid lobby id length");
+        let mut bytes = [0; MAX_LOBBY_ID_LEN];
+        for (byte, &digit) in bytes.iter_mut().zip(digits) {
+            *byte = LOBBY_ID_ALPHABET[digit as usize];
+        }
+        Self {
+            bytes,
+            len: digits.len() as u8,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+
+    pub fn as_str(&self) -> &str {
+        // every character is ascii, so this can't fail.
+        core::str::from_utf8(self.as_bytes()).unwrap()
+    }
+}
+
+impl Display for LobbyId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        Display::fmt(self.as_str(), f)
+    }
+}
+
+/// parses an id exactly as it's sent over the wire.
+impl TryFrom<&[u8]> for LobbyId {
+    type Error = ParseLobbyIdError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        if !is_valid_len(value.len()) {
+            return Err(ParseLobbyIdError::InvalidLength {
+                actual: value.len(),
+            });
+        }
+        if let Some(&b) = value.iter().find(|b| !LOBBY_ID_ALPHABET.contains(b)) {
+            return Err(ParseLobbyIdError::InvalidCharacter(b as char));
+        }
+        let mut bytes = [0; MAX_LOBBY_ID_LEN];
+        bytes[..value.len()].copy_from_slice(value);
+        Ok(Self {
+            bytes,
+            len: value.len() as u8,
+        })
+    }
+}
+
+/// parses an id typed by a player, so it's case insensitive and reads 0 and 1 as O and I.
+impl FromStr for LobbyId {
+    type Err = ParseLobbyIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let actual = s.chars().count();
+        if !is_valid_len(actual) {
+            return Err(ParseLobbyIdError::InvalidLength { actual });
+        }
+        let mut bytes = [0; MAX_LOBBY_ID_LEN];
+        for (byte, c) in bytes.iter_mut().zip(s.chars()) {
+            let normalized = match c.to_ascii_uppercase() {
+                '0' => 'O',
+                '1' => 'I',
+                normalized => normalized,
+            };
+            if !normalized.is_ascii() || !LOBBY_ID_ALPHABET.contains(&(normalized as u8)) {
+                return Err(ParseLobbyIdError::InvalidCharacter(c));
+            }
+            *byte = normalized as u8;
+        }
+        Ok(Self {
+            bytes,
+            len: actual as u8,
+        })
+    }
+}
+
+fn is_valid_len(len: usize) -> bool {
+    (LOBBY_ID_LEN..=MAX_LOBBY_ID_LEN).contains(&len)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lobby_id::{LobbyId, ParseLobbyIdError};
+
+    #[test]
+    fn from_str() {
+        assert_eq!(
+            "A5EZ".parse::<LobbyId>().map(|id| id.to_string()),
+            Err(ParseLobbyIdError::InvalidCharacter('Z'))
+        );
+        assert_eq!(
+            "f7bw".parse::<LobbyId>().map(|id| id.to_string()),
+            Ok("F7BW".to_owned())
+        );
+        assert_eq!(
+            "10KX".parse::<LobbyId>().map(|id| id.to_string()),
+            Ok("IOKX".to_owned())
+        );
+        assert_eq!(
+            "ABC".parse::<LobbyId>(),
+            Err(ParseLobbyIdError::InvalidLength { actual: 3 })
+        );
+        assert_eq!(
+            "abcde7".parse::<LobbyId>().map(|id| id.to_string()),
+            Ok("ABCDE7".to_owned())
+        );
+        assert_eq!(
+            "ABCDEFGHJ".parse::<LobbyId>(),
+            Err(ParseLobbyIdError::InvalidLength { actual: 9 })
+        );
+        assert_eq!(
+            "AB-D".parse::<LobbyId>(),
+            Err(ParseLobbyIdError::InvalidCharacter('-'))
+        );
+        assert_eq!(
+            "ABÉD".parse::<LobbyId>(),
+            Err(ParseLobbyIdError::InvalidCharacter('É'))
+        );
+    }
+
+    #[test]
+    fn try_from_bytes() {
+        assert_eq!(
+            LobbyId::try_from(b"H5MS".as_slice()).map(|id| id.to_string()),
+            Ok("H5MS".to_owned())
+        );
+        assert_eq!(
+            LobbyId::try_from(b"h5ms".as_slice()),
+            Err(ParseLobbyIdError::InvalidCharacter('h'))
+        );
+        assert_eq!(
+            LobbyId::try_from(b"H0MS".as_slice()),
+            Err(ParseLobbyIdError::InvalidCharacter('0'))
+        );
+        assert_eq!(
+            LobbyId::try_from(b"H5M".as_slice()),
+            Err(ParseLobbyIdError::InvalidLength { actual: 3 })
+        );
+        assert_eq!(
+            LobbyId::try_from(b"H5MS2345".as_slice()).map(|id| id.to_string()),
+            Ok("H5MS2345".to_owned())
+        );
+        assert_ne!(
+            LobbyId::try_from(b"H5MS".as_slice()),
+            LobbyId::try_from(b"H5MS2".as_slice())
+        );
+    }
+
+    #[test]
+    fn from_digits() {
+        assert_eq!(LobbyId::from_digits(&[0, 7, 8, 31]).as_str(), "29AX");
+        assert_eq!(
+            LobbyId::from_digits(&[0, 7, 8, 31, 0, 0]).as_str(),
+            "29AX22"
+        );
+    }
+}
@@ -1,221 +1,527 @@
-use crate::game_state::{GAME_HEIGHT, GAME_WIDTH, PADDLE_HEIGHT};
+use core::fmt::Display;
 
-use super::{
-    game_state::{Ball, GameState},
-    validate_byte_count, validate_state_and_get_message_id, DeserializeMessageError, LOBBY_ID_LEN,
-};
+use shared_derive::PongMessage;
 
-const _CHECKS: () = {
-    assert!(
-        GAME_HEIGHT < 2u8.pow(7) - 1,
-        "height of the game window is too large to serialize the ball's vertical position and direction using a single u8"
-    );
-    assert!(
-        GAME_WIDTH < 2u8.pow(7) - 1,
-        "width of the game window is too large to serialize the ball's horizontal position and direction using a single u8"
-    );
-    assert!(
-        GAME_HEIGHT - PADDLE_HEIGHT < 2u8.pow(4) - 1,
-        "height of the game window is too large to serialize both paddle positions using a single u8"
-    );
+use super::{
+    cosmetics::Cosmetics,
+    deserialize_u14,
+    game_state::{
+        Ball, GameDimensions, GameState, Handicaps, Ruleset, Side, MAX_GAME_HEIGHT, MAX_GAME_WIDTH,
+        MAX_SPEED_LEVEL, NARROW_MAX_GAME_HEIGHT, NARROW_MAX_GAME_WIDTH, NARROW_MAX_PADDLE_POSITION,
+    },
+    serialize_u14,
+    tournament::{Bracket, LeagueSize, Seat, Standings},
+    unrecognised_message_variant, validate_byte_count, validate_state_and_get_message_id,
+    write_bytes, Count, DeserializeMessageError, FieldCodec, LobbyId, Seconds, TickInterval,
+    Timeout, WriteInto, MAX_LOBBY_ID_LEN,
 };
+#[cfg(any(feature = "alloc", test))]
+use {super::write_to_vec, alloc::vec::Vec};
 
 /// the largest number of bytes a serialized server message could take up.
-/// [`AwaitingNewLobbyServerMessage::NewLobbyCreated`] is the largest server message when serialized (one byte for the identifier + lobby id length).
-pub const MAX_SERVER_MESSAGE_SIZE: usize = 1 + LOBBY_ID_LEN;
+/// [`AwaitingMatchServerMessage::MatchFound`] and [`AwaitingTournamentServerMessage::MatchReady`] are the largest server messages when serialized with the longest lobby id (one byte for the identifier + one byte for the id's length + the id itself + one byte for the side + two bytes for the timeout + six bytes for the dimensions).
+pub const MAX_SERVER_MESSAGE_SIZE: usize = 1 + 1 + MAX_LOBBY_ID_LEN + 1 + 2 + 6;
+
+/// the most lobbies listed in one page of [`AwaitingLobbyPageServerMessage`]s.
+pub const LOBBY_PAGE_SIZE: usize = 10;
 
 /// this byte is appended to the end of every server message to indicate termination.
 /// we must therefore ensure that no other bytes in a message must serialize to this value.
 pub const SERVER_MESSAGE_DELIMITER: u8 = u8::MAX;
 
-#[cfg_attr(test, derive(Clone, Debug, PartialEq))]
-pub enum AwaitingNewLobbyServerMessage<'a> {
-    NewLobbyCreated { lobby_id: &'a str },
+#[derive(PongMessage)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Clone, Debug, PartialEq))]
+#[pong(state = 0, max_size = MAX_SERVER_MESSAGE_SIZE)]
+pub enum AwaitingNewLobbyServerMessage {
+    #[pong(id = 0)]
+    NewLobbyCreated {
+        lobby_id: LobbyId,
+        dimensions: GameDimensions,
+    },
+    /// the lobby id the host asked for belongs to another lobby.
+    #[pong(id = 1)]
+    IdTaken,
+    /// the host's address has created too many lobbies lately.
+    #[pong(id = 2)]
+    TooManyLobbies,
+    /// the server plays its games in a way the host's client can't, such as in lockstep.
+    #[pong(id = 3)]
+    LobbyIncompatible,
 }
 
-#[cfg_attr(test, derive(Clone, Debug, PartialEq))]
+#[derive(PongMessage)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Clone, Debug, PartialEq))]
+#[pong(state = 1, max_size = MAX_SERVER_MESSAGE_SIZE)]
 pub enum AwaitingJoinLobbyServerMessage {
-    JoinedLobby,
+    #[pong(id = 0)]
+    JoinedLobby {
+        #[pong(codec = Timeout)]
+        ready_timeout_secs: u16,
+        dimensions: GameDimensions,
+    },
+    #[pong(id = 1)]
     LobbyFull,
+    #[pong(id = 2)]
     LobbyNotFound,
+    /// the lobby's field is too large, or its paddles handicapped, for the joining client's
+    /// capabilities.
+    #[pong(id = 3)]
+    LobbyIncompatible,
 }
 
-#[cfg_attr(test, derive(Clone, Debug, PartialEq))]
+#[derive(PongMessage)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Clone, Debug, PartialEq))]
+#[pong(state = 2, max_size = MAX_SERVER_MESSAGE_SIZE)]
 pub enum AwaitingOpponentJoinServerMessage {
-    OpponentJoined,
-}
-
-#[cfg_attr(test, derive(Clone, Debug, PartialEq))]
-pub enum AwaitingReadyServerMessage {
-    OpponentLeft,
-    OpponentReadied,
-    OpponentUnreadied,
-    YouReadied,
-    YouUnreadied,
-    GameStarted,
+    #[pong(id = 0)]
+    OpponentJoined {
+        #[pong(codec = Timeout)]
+        ready_timeout_secs: u16,
+    },
+    /// the lobby has been inactive for so long that it will be closed in the given number of
+    /// seconds, unless the host sends [`AwaitingOpponentJoinClientMessage::StillHere`].
+    ///
+    /// [`AwaitingOpponentJoinClientMessage::StillHere`]: crate::client_msg::AwaitingOpponentJoinClientMessage::StillHere
+    #[pong(id = 1)]
+    LobbyExpiringSoon {
+        #[pong(codec = Timeout)]
+        seconds: u16,
+    },
+    /// the lobby was closed after being inactive for too long.
+    #[pong(id = 2)]
+    LobbyExpired,
 }
 
-#[derive(Clone)]
-#[cfg_attr(test, derive(Debug, PartialEq))]
-pub enum PlayingServerMessage {
-    OpponentLeft,
-    OpponentWon,
-    YouWon,
-    GameStateUpdated { game_state: GameState },
+/// sent in place of whatever the client was waiting for, just before its connection is closed.
+#[derive(PongMessage, Clone, Copy, Debug, PartialEq, Eq)]
+#[pong(state = 9, max_size = MAX_SERVER_MESSAGE_SIZE)]
+pub enum RejectedServerMessage {
+    /// the client's address has opened too many connections lately.
+    #[pong(id = 0)]
+    TooManyConnections,
+    /// the client's address isn't allowed to connect.
+    #[pong(id = 1)]
+    AddressDenied,
 }
 
-impl From<AwaitingNewLobbyServerMessage<'_>> for Vec<u8> {
-    fn from(value: AwaitingNewLobbyServerMessage) -> Self {
-        match value {
-            AwaitingNewLobbyServerMessage::NewLobbyCreated { lobby_id } => {
-                [&[0], lobby_id.as_bytes()].concat()
+impl Display for RejectedServerMessage {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RejectedServerMessage::TooManyConnections => {
+                Display::fmt("too many connections from this address", f)
+            }
+            RejectedServerMessage::AddressDenied => {
+                Display::fmt("this address isn't allowed to connect", f)
             }
         }
     }
 }
 
-impl<'a> TryFrom<&'a [u8]> for AwaitingNewLobbyServerMessage<'a> {
-    type Error = DeserializeMessageError;
+/// the server's reply to a [`ResyncClientMessage::ResyncRequest`], which can be sent in any state.
+///
+/// [`ResyncClientMessage::ResyncRequest`]: crate::client_msg::ResyncClientMessage::ResyncRequest
+#[derive(PongMessage, Clone, Copy, Debug, PartialEq, Eq)]
+#[pong(state = 10, max_size = MAX_SERVER_MESSAGE_SIZE)]
+pub enum ResyncServerMessage {
+    /// the state the server has the client in. every message sent after this one is for that
+    /// state.
+    #[pong(id = 0)]
+    FullState { state: ProtocolState },
+}
 
-    fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
-        match validate_state_and_get_message_id(value, 0)? {
-            0 => {
-                validate_byte_count(value, 1 + LOBBY_ID_LEN)?;
-                let lobby_id = std::str::from_utf8(&value[1..])
-                    .map_err(|err| DeserializeMessageError::Utf8Error(err))?;
-                Ok(AwaitingNewLobbyServerMessage::NewLobbyCreated { lobby_id })
-            }
-            _ => Err(DeserializeMessageError::UnrecognisedMessageVariant),
-        }
-    }
+/// the server's reply to a [`ChecksumClientMessage::EnableChecksums`], which can be sent in any
+/// state.
+///
+/// [`ChecksumClientMessage::EnableChecksums`]: crate::client_msg::ChecksumClientMessage::EnableChecksums
+#[derive(PongMessage, Clone, Copy, Debug, PartialEq, Eq)]
+#[pong(state = 11, max_size = MAX_SERVER_MESSAGE_SIZE)]
+pub enum ChecksumServerMessage {
+    /// the last frame without a [checksum](crate::checksum). every frame sent after this one has
+    /// one.
+    #[pong(id = 0)]
+    ChecksumsEnabled,
 }
 
-impl From<AwaitingJoinLobbyServerMessage> for Vec<u8> {
-    fn from(value: AwaitingJoinLobbyServerMessage) -> Self {
-        let mut bytes = match value {
-            AwaitingJoinLobbyServerMessage::JoinedLobby => vec![0],
-            AwaitingJoinLobbyServerMessage::LobbyFull => vec![1],
-            AwaitingJoinLobbyServerMessage::LobbyNotFound => vec![2],
-        };
-        bytes[0] |= 1 << 4;
-        bytes
-    }
+/// which protocol state the server has a client in, as far as the client needs to know to carry
+/// on from it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProtocolState {
+    Open,
+    AwaitingOpponentJoin,
+    AwaitingMatch,
+    AwaitingTournament,
+    AwaitingReady {
+        you_ready: bool,
+        opponent_ready: bool,
+    },
+    Playing,
 }
 
-impl TryFrom<&[u8]> for AwaitingJoinLobbyServerMessage {
-    type Error = DeserializeMessageError;
+impl FieldCodec<ProtocolState> for ProtocolState {
+    const SIZE: usize = 1;
 
-    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        match validate_state_and_get_message_id(value, 1)? {
-            0 => {
-                validate_byte_count(value, 1)?;
-                Ok(AwaitingJoinLobbyServerMessage::JoinedLobby)
-            }
-            1 => {
-                validate_byte_count(value, 1)?;
-                Ok(AwaitingJoinLobbyServerMessage::LobbyFull)
-            }
-            2 => {
-                validate_byte_count(value, 1)?;
-                Ok(AwaitingJoinLobbyServerMessage::LobbyNotFound)
-            }
-            _ => Err(DeserializeMessageError::UnrecognisedMessageVariant),
+    fn write(value: &ProtocolState, buf: &mut [u8]) {
+        buf[0] = match value {
+            ProtocolState::Open => 0,
+            ProtocolState::AwaitingOpponentJoin => 1,
+            ProtocolState::AwaitingMatch => 2,
+            ProtocolState::AwaitingTournament => 3,
+            // both players' readiness is packed into the two lowest bits.
+            ProtocolState::AwaitingReady {
+                you_ready,
+                opponent_ready,
+            } => 4 | (*opponent_ready as u8) << 1 | *you_ready as u8,
+            ProtocolState::Playing => 8,
+        };
+    }
+
+    fn read(bytes: &[u8]) -> Result<ProtocolState, DeserializeMessageError> {
+        match bytes[0] {
+            0 => Ok(ProtocolState::Open),
+            1 => Ok(ProtocolState::AwaitingOpponentJoin),
+            2 => Ok(ProtocolState::AwaitingMatch),
+            3 => Ok(ProtocolState::AwaitingTournament),
+            byte @ 4..=7 => Ok(ProtocolState::AwaitingReady {
+                you_ready: byte & 1 != 0,
+                opponent_ready: byte & 2 != 0,
+            }),
+            8 => Ok(ProtocolState::Playing),
+            _ => Err(DeserializeMessageError::InvalidProtocolState),
         }
     }
 }
 
-impl From<AwaitingOpponentJoinServerMessage> for Vec<u8> {
-    fn from(value: AwaitingOpponentJoinServerMessage) -> Self {
-        let mut bytes = match value {
-            AwaitingOpponentJoinServerMessage::OpponentJoined => vec![0],
-        };
-        bytes[0] |= 2 << 4;
-        bytes
+/// the version of the server's build, which isn't necessarily the version of the protocol.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ServerVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+}
+
+impl Display for ServerVersion {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
     }
 }
 
-impl TryFrom<&[u8]> for AwaitingOpponentJoinServerMessage {
-    type Error = DeserializeMessageError;
+impl FieldCodec<ServerVersion> for ServerVersion {
+    const SIZE: usize = 6;
 
-    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        match validate_state_and_get_message_id(value, 2)? {
-            0 => {
-                validate_byte_count(value, 1)?;
-                Ok(AwaitingOpponentJoinServerMessage::OpponentJoined)
-            }
-            _ => Err(DeserializeMessageError::UnrecognisedMessageVariant),
+    fn write(value: &ServerVersion, buf: &mut [u8]) {
+        for (i, number) in [value.major, value.minor, value.patch]
+            .into_iter()
+            .enumerate()
+        {
+            write_bytes(&mut buf[2 * i..], &serialize_u14(number));
         }
     }
-}
 
-impl From<AwaitingReadyServerMessage> for Vec<u8> {
-    fn from(value: AwaitingReadyServerMessage) -> Self {
-        let mut bytes = match value {
-            AwaitingReadyServerMessage::OpponentLeft => vec![0],
-            AwaitingReadyServerMessage::OpponentReadied => vec![1],
-            AwaitingReadyServerMessage::OpponentUnreadied => vec![2],
-            AwaitingReadyServerMessage::YouReadied => vec![3],
-            AwaitingReadyServerMessage::YouUnreadied => vec![4],
-            AwaitingReadyServerMessage::GameStarted => vec![5],
+    fn read(bytes: &[u8]) -> Result<ServerVersion, DeserializeMessageError> {
+        let number = |i: usize| {
+            deserialize_u14(&bytes[2 * i..2 * i + 2]).ok_or(DeserializeMessageError::InvalidVersion)
         };
-        bytes[0] |= 3 << 4;
-        bytes
+        Ok(ServerVersion {
+            major: number(0)?,
+            minor: number(1)?,
+            patch: number(2)?,
+        })
     }
 }
 
-impl TryFrom<&[u8]> for AwaitingReadyServerMessage {
-    type Error = DeserializeMessageError;
+#[derive(PongMessage)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Clone, Debug, PartialEq))]
+#[pong(state = 5, max_size = MAX_SERVER_MESSAGE_SIZE)]
+pub enum AwaitingServerStatusServerMessage {
+    #[pong(id = 0)]
+    ServerStatus {
+        /// players in a lobby, whether or not they're playing.
+        #[pong(codec = Count)]
+        players_online: u16,
+        /// lobbies waiting for an opponent to join.
+        #[pong(codec = Count)]
+        open_lobbies: u16,
+        version: ServerVersion,
+        #[pong(codec = Seconds)]
+        uptime_secs: u32,
+    },
+}
 
-    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        match validate_state_and_get_message_id(value, 3)? {
-            0 => {
-                validate_byte_count(value, 1)?;
-                Ok(AwaitingReadyServerMessage::OpponentLeft)
+#[derive(PongMessage)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Clone, Debug, PartialEq))]
+#[pong(state = 6, max_size = MAX_SERVER_MESSAGE_SIZE)]
+pub enum AwaitingMatchServerMessage {
+    /// an opponent was found in the queue, and both players have been seated in a new lobby. also
+    /// sent straight away to a client that asked to practice, whose opponent is a wall.
+    #[pong(id = 0)]
+    MatchFound {
+        lobby_id: LobbyId,
+        /// the side of the field the player's paddle is on.
+        side: Side,
+        #[pong(codec = Timeout)]
+        ready_timeout_secs: u16,
+        dimensions: GameDimensions,
+    },
+}
+
+#[derive(Clone, PongMessage)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Debug, PartialEq))]
+#[pong(state = 7, max_size = MAX_SERVER_MESSAGE_SIZE)]
+pub enum AwaitingTournamentServerMessage {
+    /// the player has taken a seat in the tournament, which starts once every seat is taken.
+    #[pong(id = 0)]
+    JoinedTournament {
+        tournament_id: LobbyId,
+        #[pong(codec = Seat)]
+        seat: u8,
+        /// leagues can have any number of players, so this isn't limited to the sizes a single
+        /// elimination tournament can have.
+        #[pong(codec = LeagueSize)]
+        players: u8,
+    },
+    #[pong(id = 1)]
+    TournamentFull,
+    #[pong(id = 2)]
+    TournamentNotFound,
+    /// the tournament's field is too large for the joining client's capabilities.
+    #[pong(id = 3)]
+    TournamentIncompatible,
+    /// the number of seats taken changed before the tournament started.
+    #[pong(id = 4)]
+    PlayersJoined {
+        #[pong(codec = Count)]
+        players: u16,
+    },
+    /// the tournament has started, or a match in it has finished. players in the middle of a match
+    /// are sent the bracket once their match is over.
+    #[pong(id = 5)]
+    BracketUpdated { bracket: Bracket },
+    /// the player's next match is ready to play, as a lobby with both players already in it. the
+    /// lobby is closed after one game, and the player returns to the tournament.
+    #[pong(id = 6)]
+    MatchReady {
+        lobby_id: LobbyId,
+        side: Side,
+        #[pong(codec = Timeout)]
+        ready_timeout_secs: u16,
+        dimensions: GameDimensions,
+    },
+    /// the tournament or league has finished, won by the given seat.
+    #[pong(id = 7)]
+    TournamentWon {
+        #[pong(codec = Seat)]
+        seat: u8,
+    },
+    /// the league has started, or a match in it has finished. like [`Self::BracketUpdated`],
+    /// players in the middle of a match are sent the standings once their match is over.
+    #[pong(id = 8)]
+    StandingsUpdated { standings: Standings },
+}
+
+/// a page of open lobbies, oldest first, sent as one message per lobby followed by
+/// [`AwaitingLobbyPageServerMessage::LobbyPageEnd`].
+#[derive(PongMessage)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Clone, Debug, PartialEq))]
+#[pong(state = 8, max_size = MAX_SERVER_MESSAGE_SIZE)]
+pub enum AwaitingLobbyPageServerMessage {
+    #[pong(id = 0)]
+    LobbyListed {
+        lobby_id: LobbyId,
+        #[pong(codec = TickInterval)]
+        tick_interval_ms: u16,
+        /// how long the lobby has been waiting for an opponent.
+        #[pong(codec = Seconds)]
+        age_secs: u32,
+    },
+    /// the page is over. pages past the last are sent empty, rather than being an error, since
+    /// lobbies can close while they're being browsed.
+    #[pong(id = 1)]
+    LobbyPageEnd {
+        #[pong(codec = Count)]
+        page: u16,
+        /// at least one, even with no lobbies open.
+        #[pong(codec = Count)]
+        pages: u16,
+    },
+}
+
+#[derive(Clone, PongMessage)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Debug, PartialEq))]
+#[pong(state = 3, max_size = MAX_SERVER_MESSAGE_SIZE)]
+pub enum AwaitingReadyServerMessage {
+    #[pong(id = 0)]
+    OpponentLeft,
+    #[pong(id = 1)]
+    OpponentReadied,
+    #[pong(id = 2)]
+    OpponentUnreadied,
+    #[pong(id = 3)]
+    YouReadied,
+    #[pong(id = 4)]
+    YouUnreadied,
+    #[pong(id = 5)]
+    GameStarted {
+        #[pong(codec = TickInterval)]
+        tick_interval_ms: u16,
+    },
+    #[pong(id = 6)]
+    LobbyTimedOut,
+    /// sent instead of [`AwaitingReadyServerMessage::GameStarted`] when either player is
+    /// handicapped. only clients with
+    /// [`Capabilities::HANDICAPS`](crate::client_msg::Capabilities::HANDICAPS) can play in such
+    /// lobbies.
+    #[pong(id = 7)]
+    HandicappedGameStarted {
+        #[pong(codec = TickInterval)]
+        tick_interval_ms: u16,
+        handicaps: Handicaps,
+    },
+    /// the ruleset the lobby is played under, sent to both players once it has been joined so
+    /// that they agree on it before readying up. only sent to clients with
+    /// [`Capabilities::RULESET`](crate::client_msg::Capabilities::RULESET).
+    #[pong(id = 8)]
+    LobbyRuleset { ruleset: Ruleset },
+    /// the lobby is for co-op survival, so both players defend the left side together against a
+    /// paddle steered by the server. sent to both players once the lobby has been joined.
+    #[pong(id = 9)]
+    CoopLobby,
+    /// the opponent left the lobby, which is open again with the player as its host, waiting for
+    /// someone else to join.
+    #[pong(id = 10)]
+    OpponentLeftLobby,
+    /// the lobby's host left it before the game started, so the player hosts it from now on,
+    /// waiting for someone else to join.
+    #[pong(id = 11)]
+    PromotedToHost { lobby_id: LobbyId },
+    /// how the opponent chose to have their paddle drawn, sent once the lobby has been joined.
+    /// only sent to clients that said how they'd like their own drawn.
+    #[pong(id = 12)]
+    OpponentCosmetics { cosmetics: Cosmetics },
+    /// the server only advances the game once both players have sent their direction for the next
+    /// tick, so the player should send theirs after every game state, even if it hasn't changed.
+    /// sent just before [`AwaitingReadyServerMessage::GameStarted`], by servers in lockstep only.
+    #[pong(id = 13)]
+    LockstepGame,
+}
+
+/// game states have two encodings, under different message ids, so this message is serialized by
+/// hand rather than deriving `PongMessage`.
+#[derive(Clone)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Debug, PartialEq))]
+pub enum PlayingServerMessage {
+    OpponentLeft,
+    OpponentWon,
+    YouWon,
+    GameStateUpdated {
+        game_state: GameState,
+    },
+    OpponentResigned,
+    /// a paddle hit the ball, speeding it up. only sent to clients with
+    /// [`Capabilities::SPEED_LEVEL`](crate::client_msg::Capabilities::SPEED_LEVEL), and only while
+    /// the ball can still get faster.
+    BallSpedUp {
+        speed_level: u8,
+    },
+    /// the game won't start until the serving side sends [`PlayingClientMessage::Serve`], or the
+    /// server's serve delay has passed. only sent to clients with
+    /// [`Capabilities::SERVE`](crate::client_msg::Capabilities::SERVE).
+    ///
+    /// [`PlayingClientMessage::Serve`]: crate::client_msg::PlayingClientMessage::Serve
+    Serving {
+        side: Side,
+    },
+    /// the match is tied with both players a game away from winning it, so it goes on until one
+    /// of them is two games ahead. only sent to clients with
+    /// [`Capabilities::TIEBREAK`](crate::client_msg::Capabilities::TIEBREAK).
+    Deuce,
+    /// the match is tied with both players a game away from winning it, so this game decides it.
+    /// only sent to clients with
+    /// [`Capabilities::TIEBREAK`](crate::client_msg::Capabilities::TIEBREAK).
+    SuddenDeath,
+    /// sent instead of [`PlayingServerMessage::GameStateUpdated`] in a co-op survival game. the
+    /// game state's left paddle is the host's and its right paddle the server's, and the joiner's
+    /// paddle is also on the left. the score is the number of times the players have returned
+    /// the ball. always uses the wide encoding.
+    CoopStateUpdated {
+        game_state: GameState,
+        partner_paddle: u16,
+        score: u16,
+    },
+    /// one player is a game away from winning the match, and the other isn't, so this game could
+    /// decide it. the match point is the side with more wins. only sent to clients with
+    /// [`Capabilities::TIEBREAK`](crate::client_msg::Capabilities::TIEBREAK).
+    MatchPoint {
+        left_wins: u16,
+        right_wins: u16,
+    },
+}
+
+impl WriteInto for PlayingServerMessage {
+    fn write_into(&self, buf: &mut [u8]) -> usize {
+        let n = match self {
+            PlayingServerMessage::OpponentLeft => write_bytes(buf, &[0]),
+            PlayingServerMessage::OpponentWon => write_bytes(buf, &[1]),
+            PlayingServerMessage::YouWon => write_bytes(buf, &[2]),
+            PlayingServerMessage::GameStateUpdated { game_state }
+                if fits_narrow_encoding(game_state) =>
+            {
+                write_bytes(
+                    buf,
+                    &[
+                        3,
+                        // serialize the position of both paddles into a single byte.
+                        (game_state.left_paddle as u8) << 4 | game_state.right_paddle as u8,
+                        // for each axis, serialize the position and direction of the ball into a single byte.
+                        (game_state.ball.x as u8) << 1 | game_state.ball.moving_right as u8,
+                        (game_state.ball.y as u8) << 1 | game_state.ball.moving_down as u8,
+                    ],
+                )
             }
-            1 => {
-                validate_byte_count(value, 1)?;
-                Ok(AwaitingReadyServerMessage::OpponentReadied)
+            // only clients that support the wide encoding are sent states of fields too large for
+            // the narrow encoding.
+            PlayingServerMessage::GameStateUpdated { game_state } => {
+                let n = write_bytes(buf, &[5]);
+                n + write_wide_game_state(&mut buf[n..], game_state)
             }
-            2 => {
-                validate_byte_count(value, 1)?;
-                Ok(AwaitingReadyServerMessage::OpponentUnreadied)
+            PlayingServerMessage::OpponentResigned => write_bytes(buf, &[4]),
+            PlayingServerMessage::BallSpedUp { speed_level } => {
+                write_bytes(buf, &[6, *speed_level])
             }
-            3 => {
-                validate_byte_count(value, 1)?;
-                Ok(AwaitingReadyServerMessage::YouReadied)
+            PlayingServerMessage::Serving { side } => {
+                let n = write_bytes(buf, &[7]);
+                Side::write(side, &mut buf[n..]);
+                n + Side::SIZE
             }
-            4 => {
-                validate_byte_count(value, 1)?;
-                Ok(AwaitingReadyServerMessage::YouUnreadied)
+            PlayingServerMessage::Deuce => write_bytes(buf, &[8]),
+            PlayingServerMessage::SuddenDeath => write_bytes(buf, &[9]),
+            PlayingServerMessage::CoopStateUpdated {
+                game_state,
+                partner_paddle,
+                score,
+            } => {
+                let mut n = write_bytes(buf, &[10]);
+                n += write_wide_game_state(&mut buf[n..], game_state);
+                n += write_bytes(&mut buf[n..], &serialize_u14(*partner_paddle));
+                n + write_bytes(&mut buf[n..], &serialize_u14(*score))
             }
-            5 => {
-                validate_byte_count(value, 1)?;
-                Ok(AwaitingReadyServerMessage::GameStarted)
+            PlayingServerMessage::MatchPoint {
+                left_wins,
+                right_wins,
+            } => {
+                let n = write_bytes(buf, &[11]);
+                let n = n + write_bytes(&mut buf[n..], &serialize_u14(*left_wins));
+                n + write_bytes(&mut buf[n..], &serialize_u14(*right_wins))
             }
-            _ => Err(DeserializeMessageError::UnrecognisedMessageVariant),
-        }
+        };
+        buf[0] |= 4 << 4;
+        n
     }
 }
 
+#[cfg(any(feature = "alloc", test))]
 impl From<PlayingServerMessage> for Vec<u8> {
     fn from(value: PlayingServerMessage) -> Self {
-        let mut bytes = match value {
-            PlayingServerMessage::OpponentLeft => vec![0],
-            PlayingServerMessage::OpponentWon => vec![1],
-            PlayingServerMessage::YouWon => vec![2],
-            PlayingServerMessage::GameStateUpdated { game_state } => vec![
-                3,
-                // serialize the position of both paddles into a single byte.
-                // an assertion is performed at the top of the file to ensure this is possible without loss of information.
-                game_state.left_paddle << 4 | (game_state.right_paddle & 0b1111),
-                // for each axis, serialize the position and direction of the ball into a single byte.
-                // an assertion is performed at the top of the file to ensure this is possible without loss of information.
-                game_state.ball.x << 1 | game_state.ball.moving_right as u8,
-                game_state.ball.y << 1 | game_state.ball.moving_down as u8,
-            ],
-        };
-        bytes[0] |= 4 << 4;
-        bytes
+        write_to_vec::<_, MAX_SERVER_MESSAGE_SIZE>(&value)
     }
 }
 
@@ -238,17 +544,19 @@ impl TryFrom<&[u8]> for PlayingServerMessage {
             }
             3 => {
                 validate_byte_count(value, 4)?;
-                let left_paddle = value[1] >> 4;
-                if left_paddle > GAME_HEIGHT - PADDLE_HEIGHT {
+                // positions are only checked against the limits of any game here, since the
+                // dimensions of this particular game aren't known. see [`GameDimensions::validate`].
+                let left_paddle = (value[1] >> 4) as u16;
+                if left_paddle > NARROW_MAX_PADDLE_POSITION {
                     return Err(DeserializeMessageError::InvalidPaddlePosition);
                 }
-                let right_paddle = value[1] & 0b1111;
-                if right_paddle > GAME_HEIGHT - PADDLE_HEIGHT {
+                let right_paddle = (value[1] & 0b1111) as u16;
+                if right_paddle > NARROW_MAX_PADDLE_POSITION {
                     return Err(DeserializeMessageError::InvalidPaddlePosition);
                 }
-                let x = value[2] >> 1;
-                let y = value[3] >> 1;
-                if x >= GAME_WIDTH || y >= GAME_HEIGHT {
+                let x = (value[2] >> 1) as u16;
+                let y = (value[3] >> 1) as u16;
+                if x >= NARROW_MAX_GAME_WIDTH || y >= NARROW_MAX_GAME_HEIGHT {
                     return Err(DeserializeMessageError::InvalidBallPosition);
                 }
                 Ok(PlayingServerMessage::GameStateUpdated {
@@ -264,39 +572,193 @@ impl TryFrom<&[u8]> for PlayingServerMessage {
                     },
                 })
             }
-            _ => Err(DeserializeMessageError::UnrecognisedMessageVariant),
+            4 => {
+                validate_byte_count(value, 1)?;
+                Ok(PlayingServerMessage::OpponentResigned)
+            }
+            5 => {
+                validate_byte_count(value, 1 + WIDE_GAME_STATE_SIZE)?;
+                Ok(PlayingServerMessage::GameStateUpdated {
+                    game_state: read_wide_game_state(&value[1..])?,
+                })
+            }
+            6 => {
+                validate_byte_count(value, 2)?;
+                match value[1] {
+                    speed_level @ 1..=MAX_SPEED_LEVEL => {
+                        Ok(PlayingServerMessage::BallSpedUp { speed_level })
+                    }
+                    _ => Err(DeserializeMessageError::InvalidSpeedLevel),
+                }
+            }
+            7 => {
+                validate_byte_count(value, 1 + Side::SIZE)?;
+                Ok(PlayingServerMessage::Serving {
+                    side: Side::read(&value[1..])?,
+                })
+            }
+            8 => {
+                validate_byte_count(value, 1)?;
+                Ok(PlayingServerMessage::Deuce)
+            }
+            9 => {
+                validate_byte_count(value, 1)?;
+                Ok(PlayingServerMessage::SuddenDeath)
+            }
+            10 => {
+                validate_byte_count(value, 1 + WIDE_GAME_STATE_SIZE + 4)?;
+                let rest = &value[1 + WIDE_GAME_STATE_SIZE..];
+                Ok(PlayingServerMessage::CoopStateUpdated {
+                    game_state: read_wide_game_state(&value[1..])?,
+                    partner_paddle: deserialize_u14(&rest[..2])
+                        .filter(|&paddle| paddle < MAX_GAME_HEIGHT)
+                        .ok_or(DeserializeMessageError::InvalidPaddlePosition)?,
+                    score: deserialize_u14(&rest[2..])
+                        .ok_or(DeserializeMessageError::InvalidScore)?,
+                })
+            }
+            11 => {
+                validate_byte_count(value, 5)?;
+                match (deserialize_u14(&value[1..3]), deserialize_u14(&value[3..])) {
+                    // a tied match has no match point.
+                    (Some(left_wins), Some(right_wins)) if left_wins != right_wins => {
+                        Ok(PlayingServerMessage::MatchPoint {
+                            left_wins,
+                            right_wins,
+                        })
+                    }
+                    _ => Err(DeserializeMessageError::InvalidScore),
+                }
+            }
+            _ => Err(unrecognised_message_variant(value)),
         }
     }
 }
 
+/// the number of bytes a game state takes up in the wide encoding: a u14 for each paddle's
+/// position and each of the ball's coordinates, then a byte for the ball's directions.
+const WIDE_GAME_STATE_SIZE: usize = 9;
+
+fn write_wide_game_state(buf: &mut [u8], game_state: &GameState) -> usize {
+    let mut n = 0;
+    for value in [
+        game_state.left_paddle,
+        game_state.right_paddle,
+        game_state.ball.x,
+        game_state.ball.y,
+    ] {
+        n += write_bytes(&mut buf[n..], &serialize_u14(value));
+    }
+    let directions = (game_state.ball.moving_right as u8) << 1 | game_state.ball.moving_down as u8;
+    n + write_bytes(&mut buf[n..], &[directions])
+}
+
+/// reads a game state in the wide encoding from the start of `bytes`, which must be long enough.
+fn read_wide_game_state(bytes: &[u8]) -> Result<GameState, DeserializeMessageError> {
+    let left_paddle = deserialize_u14(&bytes[0..2])
+        .filter(|&paddle| paddle < MAX_GAME_HEIGHT)
+        .ok_or(DeserializeMessageError::InvalidPaddlePosition)?;
+    let right_paddle = deserialize_u14(&bytes[2..4])
+        .filter(|&paddle| paddle < MAX_GAME_HEIGHT)
+        .ok_or(DeserializeMessageError::InvalidPaddlePosition)?;
+    let x = deserialize_u14(&bytes[4..6])
+        .filter(|&x| x < MAX_GAME_WIDTH)
+        .ok_or(DeserializeMessageError::InvalidBallPosition)?;
+    let y = deserialize_u14(&bytes[6..8])
+        .filter(|&y| y < MAX_GAME_HEIGHT)
+        .ok_or(DeserializeMessageError::InvalidBallPosition)?;
+    let directions = bytes[8];
+    if directions >> 2 != 0 {
+        return Err(DeserializeMessageError::InvalidBallPosition);
+    }
+    Ok(GameState {
+        left_paddle,
+        right_paddle,
+        ball: Ball {
+            x,
+            y,
+            moving_right: directions & 0b10 != 0,
+            moving_down: directions & 1 != 0,
+        },
+    })
+}
+
+/// whether a game state can be serialized using the narrow encoding, which every client supports.
+fn fits_narrow_encoding(game_state: &GameState) -> bool {
+    game_state.left_paddle <= NARROW_MAX_PADDLE_POSITION
+        && game_state.right_paddle <= NARROW_MAX_PADDLE_POSITION
+        && game_state.ball.x < NARROW_MAX_GAME_WIDTH
+        && game_state.ball.y < NARROW_MAX_GAME_HEIGHT
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
         assert_deserialize, assert_serialize, assert_serialize_and_back,
-        game_state::{Ball, GameState},
+        cosmetics::{Cosmetics, PaddleColour},
+        game_state::{
+            Ball, GameDimensions, GameState, Handicap, Handicaps, Modifier, Ruleset, Side,
+        },
+        lobby_id::ParseLobbyIdError,
         server_msg::{
-            AwaitingJoinLobbyServerMessage, AwaitingNewLobbyServerMessage,
-            AwaitingOpponentJoinServerMessage, AwaitingReadyServerMessage, PlayingServerMessage,
+            AwaitingJoinLobbyServerMessage, AwaitingLobbyPageServerMessage,
+            AwaitingMatchServerMessage, AwaitingNewLobbyServerMessage,
+            AwaitingOpponentJoinServerMessage, AwaitingReadyServerMessage,
+            AwaitingServerStatusServerMessage, AwaitingTournamentServerMessage,
+            ChecksumServerMessage, PlayingServerMessage, ProtocolState, RejectedServerMessage,
+            ResyncServerMessage, ServerVersion,
         },
-        DeserializeMessageError,
+        tournament::{Bracket, BracketSeat, Standing, Standings},
+        DeserializeMessageError, LobbyId, MAX_U14,
     };
 
     #[test]
     fn awaiting_new_lobby_serialize() {
-        let lobby_id = "A5EZ";
+        let lobby_id: LobbyId = "A5EX".parse().unwrap();
+        assert_serialize!(
+            AwaitingNewLobbyServerMessage::NewLobbyCreated {
+                lobby_id,
+                dimensions: GameDimensions::DEFAULT,
+            },
+            [&[0], lobby_id.as_bytes(), &[0, 51, 0, 11, 0, 5]].concat()
+        );
         assert_serialize!(
-            AwaitingNewLobbyServerMessage::NewLobbyCreated { lobby_id },
-            [&[0], lobby_id.as_bytes()].concat()
+            AwaitingNewLobbyServerMessage::NewLobbyCreated {
+                lobby_id: "A5EXA5EX".parse().unwrap(),
+                dimensions: GameDimensions::DEFAULT,
+            },
+            [&[0, 8], b"A5EXA5EX".as_slice(), &[0, 51, 0, 11, 0, 5]].concat()
         );
+        assert_serialize!(AwaitingNewLobbyServerMessage::IdTaken, vec![1]);
+        assert_serialize!(AwaitingNewLobbyServerMessage::TooManyLobbies, vec![2]);
+        assert_serialize!(AwaitingNewLobbyServerMessage::LobbyIncompatible, vec![3]);
     }
 
     #[test]
     fn awaiting_new_lobby_deserialize_ok() {
-        let lobby_id = "F7BW";
+        let lobby_id: LobbyId = "F7BW".parse().unwrap();
+        assert_deserialize!(
+            AwaitingNewLobbyServerMessage,
+            [&[0], lobby_id.as_bytes(), &[0, 21, 0, 20, 0, 8]].concat(),
+            Ok(AwaitingNewLobbyServerMessage::NewLobbyCreated {
+                lobby_id,
+                dimensions: GameDimensions::new(21, 20, 8).unwrap(),
+            }),
+        );
+        assert_deserialize!(
+            AwaitingNewLobbyServerMessage,
+            [1],
+            Ok(AwaitingNewLobbyServerMessage::IdTaken),
+        );
+        assert_deserialize!(
+            AwaitingNewLobbyServerMessage,
+            [2],
+            Ok(AwaitingNewLobbyServerMessage::TooManyLobbies),
+        );
         assert_deserialize!(
             AwaitingNewLobbyServerMessage,
-            [&[0], lobby_id.as_bytes()].concat(),
-            Ok(AwaitingNewLobbyServerMessage::NewLobbyCreated { lobby_id }),
+            [3],
+            Ok(AwaitingNewLobbyServerMessage::LobbyIncompatible),
         );
     }
 
@@ -312,55 +774,181 @@ mod tests {
         assert_deserialize!(
             AwaitingNewLobbyServerMessage,
             [0],
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 0,
+                message_id: 0,
+                expected: 11,
+                actual: 1
+            }),
         );
-        // new lobby created message with not enough bytes.
+        // new lobby created message with no dimension bytes.
         assert_deserialize!(
             AwaitingNewLobbyServerMessage,
-            [&[0], "A5E".as_bytes()].concat(),
-            Err(DeserializeMessageError::InvalidByteCount),
+            [&[0], "A5EX".as_bytes()].concat(),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 0,
+                message_id: 0,
+                expected: 11,
+                actual: 5
+            }),
         );
         // new lobby created message with too many bytes.
         assert_deserialize!(
             AwaitingNewLobbyServerMessage,
-            [&[0], "A5EZ8".as_bytes()].concat(),
-            Err(DeserializeMessageError::InvalidByteCount),
-        );
-        // new lobby created with invalid utf-8.
-        assert!(matches!(
-            AwaitingNewLobbyServerMessage::try_from([0, 255, 255, 255, 255].as_slice()),
-            Err(DeserializeMessageError::Utf8Error(_))
-        ));
+            [&[0], "A5EX".as_bytes(), &[0, 51, 0, 11, 0, 5, 8]].concat(),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 0,
+                message_id: 0,
+                expected: 11,
+                actual: 12
+            }),
+        );
+        // dimensions that can't be played on.
+        assert_deserialize!(
+            AwaitingNewLobbyServerMessage,
+            [&[0], "A5EX".as_bytes(), &[0, 51, 0, 11, 0, 12]].concat(),
+            Err(DeserializeMessageError::InvalidGameDimensions),
+        );
+        // lobby id with a character that isn't used in ids.
+        assert_deserialize!(
+            AwaitingNewLobbyServerMessage,
+            [&[0], "A0EX".as_bytes(), &[0, 51, 0, 11, 0, 5]].concat(),
+            Err(DeserializeMessageError::InvalidLobbyId(
+                ParseLobbyIdError::InvalidCharacter('0')
+            )),
+        );
+        // lobby id that isn't normalized.
+        assert_deserialize!(
+            AwaitingNewLobbyServerMessage,
+            [&[0], "a5ex".as_bytes(), &[0, 51, 0, 11, 0, 5]].concat(),
+            Err(DeserializeMessageError::InvalidLobbyId(
+                ParseLobbyIdError::InvalidCharacter('a')
+            )),
+        );
         // invalid state variant.
         assert_deserialize!(
             AwaitingNewLobbyServerMessage,
             [1 << 4],
-            Err(DeserializeMessageError::InvalidState),
+            Err(DeserializeMessageError::InvalidState {
+                expected: 0,
+                actual: 1
+            }),
         );
         // unrecognised message variant.
         assert_deserialize!(
             AwaitingNewLobbyServerMessage,
-            [1],
-            Err(DeserializeMessageError::UnrecognisedMessageVariant),
+            [4],
+            Err(DeserializeMessageError::UnrecognisedMessageVariant {
+                state_id: 0,
+                message_id: 4
+            }),
+        );
+    }
+
+    #[test]
+    fn rejected_serialize_and_deserialize() {
+        assert_serialize!(RejectedServerMessage::TooManyConnections, vec![9 << 4]);
+        assert_serialize!(RejectedServerMessage::AddressDenied, vec![9 << 4 | 1]);
+        assert_deserialize!(
+            RejectedServerMessage,
+            [9 << 4],
+            Ok(RejectedServerMessage::TooManyConnections),
+        );
+        assert_deserialize!(
+            RejectedServerMessage,
+            [9 << 4 | 1],
+            Ok(RejectedServerMessage::AddressDenied),
+        );
+        // a message the client was waiting for.
+        assert_deserialize!(
+            RejectedServerMessage,
+            [0],
+            Err(DeserializeMessageError::InvalidState {
+                expected: 9,
+                actual: 0
+            }),
+        );
+    }
+
+    #[test]
+    fn resync_serialize_and_deserialize() {
+        assert_serialize!(
+            ResyncServerMessage::FullState {
+                state: ProtocolState::Open
+            },
+            vec![10 << 4, 0],
+        );
+        assert_serialize!(
+            ResyncServerMessage::FullState {
+                state: ProtocolState::AwaitingReady {
+                    you_ready: false,
+                    opponent_ready: true,
+                }
+            },
+            vec![10 << 4, 6],
+        );
+        assert_serialize!(
+            ResyncServerMessage::FullState {
+                state: ProtocolState::Playing
+            },
+            vec![10 << 4, 8],
+        );
+        assert_deserialize!(
+            ResyncServerMessage,
+            [10 << 4, 5],
+            Ok(ResyncServerMessage::FullState {
+                state: ProtocolState::AwaitingReady {
+                    you_ready: true,
+                    opponent_ready: false,
+                }
+            }),
+        );
+        assert_deserialize!(
+            ResyncServerMessage,
+            [10 << 4, 9],
+            Err(DeserializeMessageError::InvalidProtocolState),
+        );
+    }
+
+    #[test]
+    fn checksum_serialize_and_deserialize() {
+        assert_serialize!(ChecksumServerMessage::ChecksumsEnabled, vec![11 << 4]);
+        assert_deserialize!(
+            ChecksumServerMessage,
+            [11 << 4],
+            Ok(ChecksumServerMessage::ChecksumsEnabled),
         );
     }
 
     #[test]
     fn awaiting_join_lobby_serialize() {
-        assert_serialize!(AwaitingJoinLobbyServerMessage::JoinedLobby, vec![1 << 4]);
+        assert_serialize!(
+            AwaitingJoinLobbyServerMessage::JoinedLobby {
+                ready_timeout_secs: 300,
+                dimensions: GameDimensions::DEFAULT,
+            },
+            vec![1 << 4, 0b0000010, 0b0101100, 0, 51, 0, 11, 0, 5]
+        );
         assert_serialize!(AwaitingJoinLobbyServerMessage::LobbyFull, vec![1 << 4 | 1]);
         assert_serialize!(
             AwaitingJoinLobbyServerMessage::LobbyNotFound,
             vec![1 << 4 | 2]
         );
+        assert_serialize!(
+            AwaitingJoinLobbyServerMessage::LobbyIncompatible,
+            vec![1 << 4 | 3]
+        );
     }
 
     #[test]
     fn awaiting_join_lobby_deserialize_ok() {
         assert_deserialize!(
             AwaitingJoinLobbyServerMessage,
-            [1 << 4],
-            Ok(AwaitingJoinLobbyServerMessage::JoinedLobby),
+            [1 << 4, 0, 60, 0, 21, 0, 20, 0, 8],
+            Ok(AwaitingJoinLobbyServerMessage::JoinedLobby {
+                ready_timeout_secs: 60,
+                dimensions: GameDimensions::new(21, 20, 8).unwrap(),
+            }),
         );
         assert_deserialize!(
             AwaitingJoinLobbyServerMessage,
@@ -372,6 +960,11 @@ mod tests {
             [1 << 4 | 2],
             Ok(AwaitingJoinLobbyServerMessage::LobbyNotFound),
         );
+        assert_deserialize!(
+            AwaitingJoinLobbyServerMessage,
+            [1 << 4 | 3],
+            Ok(AwaitingJoinLobbyServerMessage::LobbyIncompatible),
+        );
     }
 
     #[test]
@@ -382,43 +975,563 @@ mod tests {
             [],
             Err(DeserializeMessageError::EmptyMessage),
         );
+        // joined lobby message with missing bytes.
+        assert_deserialize!(
+            AwaitingJoinLobbyServerMessage,
+            [1 << 4],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 1,
+                message_id: 0,
+                expected: 9,
+                actual: 1
+            }),
+        );
         // joined lobby message with extra bytes.
         assert_deserialize!(
             AwaitingJoinLobbyServerMessage,
-            [&[1 << 4 | 0], "A5EZ".as_bytes()].concat(),
-            Err(DeserializeMessageError::InvalidByteCount),
+            [1 << 4, 0, 60, 0, 51, 0, 11, 0, 5, 0],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 1,
+                message_id: 0,
+                expected: 9,
+                actual: 10
+            }),
+        );
+        // joined lobby message with a timeout byte using more than 7 bits.
+        assert_deserialize!(
+            AwaitingJoinLobbyServerMessage,
+            [1 << 4, 0b10000000, 0, 0, 51, 0, 11, 0, 5],
+            Err(DeserializeMessageError::InvalidTimeout),
+        );
+        // joined lobby message with a dimension byte using more than 7 bits.
+        assert_deserialize!(
+            AwaitingJoinLobbyServerMessage,
+            [1 << 4, 0, 60, 0, 0b10110011, 0, 11, 0, 5],
+            Err(DeserializeMessageError::InvalidGameDimensions),
         );
         // lobby full message with extra bytes.
         assert_deserialize!(
             AwaitingJoinLobbyServerMessage,
             [&[1 << 4 | 1], "A5EZ".as_bytes()].concat(),
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 1,
+                message_id: 1,
+                expected: 1,
+                actual: 5
+            }),
         );
         // lobby not found message with extra bytes.
         assert_deserialize!(
             AwaitingJoinLobbyServerMessage,
             [&[1 << 4 | 2], "A5EZ".as_bytes()].concat(),
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 1,
+                message_id: 2,
+                expected: 1,
+                actual: 5
+            }),
         );
         // invalid state variant.
         assert_deserialize!(
             AwaitingJoinLobbyServerMessage,
             [0],
-            Err(DeserializeMessageError::InvalidState),
+            Err(DeserializeMessageError::InvalidState {
+                expected: 1,
+                actual: 0
+            }),
         );
         // unrecognised message variant.
         assert_deserialize!(
             AwaitingJoinLobbyServerMessage,
-            [1 << 4 | 3],
-            Err(DeserializeMessageError::UnrecognisedMessageVariant),
+            [1 << 4 | 4],
+            Err(DeserializeMessageError::UnrecognisedMessageVariant {
+                state_id: 1,
+                message_id: 4
+            }),
+        );
+    }
+
+    #[test]
+    fn awaiting_server_status_serialize() {
+        assert_serialize!(
+            AwaitingServerStatusServerMessage::ServerStatus {
+                players_online: 0b10000001, // 129
+                open_lobbies: 3,
+                version: ServerVersion {
+                    major: 1,
+                    minor: 200,
+                    patch: 0,
+                },
+                uptime_secs: 0b1_0101000_1100000_0000000, // 2764800
+            },
+            vec![
+                5 << 4,
+                1,
+                1,
+                0,
+                3,
+                0,
+                1,
+                1,
+                0b1001000,
+                0,
+                0,
+                1,
+                0b0101000,
+                0b1100000,
+                0
+            ],
+        );
+        // counts and durations too large to serialize are capped.
+        assert_serialize!(
+            AwaitingServerStatusServerMessage::ServerStatus {
+                players_online: u16::MAX,
+                open_lobbies: 0,
+                version: ServerVersion {
+                    major: 0,
+                    minor: 1,
+                    patch: 0,
+                },
+                uptime_secs: u32::MAX,
+            },
+            vec![5 << 4, 127, 127, 0, 0, 0, 0, 0, 1, 0, 0, 127, 127, 127, 127],
+        );
+    }
+
+    #[test]
+    fn awaiting_server_status_deserialize_ok() {
+        assert_deserialize!(
+            AwaitingServerStatusServerMessage,
+            [5 << 4, 0, 12, 0, 5, 0, 0, 0, 3, 0, 14, 0, 0, 1, 0],
+            Ok(AwaitingServerStatusServerMessage::ServerStatus {
+                players_online: 12,
+                open_lobbies: 5,
+                version: ServerVersion {
+                    major: 0,
+                    minor: 3,
+                    patch: 14,
+                },
+                uptime_secs: 128,
+            }),
+        );
+    }
+
+    #[test]
+    fn awaiting_server_status_deserialize_err() {
+        // server status message with missing bytes.
+        assert_deserialize!(
+            AwaitingServerStatusServerMessage,
+            [5 << 4, 0, 12, 0, 5],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 5,
+                message_id: 0,
+                expected: 15,
+                actual: 5
+            }),
+        );
+        // count byte using more than 7 bits.
+        assert_deserialize!(
+            AwaitingServerStatusServerMessage,
+            [5 << 4, 0b10000000, 12, 0, 5, 0, 0, 0, 3, 0, 14, 0, 0, 1, 0],
+            Err(DeserializeMessageError::InvalidCount),
+        );
+        // version byte using more than 7 bits.
+        assert_deserialize!(
+            AwaitingServerStatusServerMessage,
+            [5 << 4, 0, 12, 0, 5, 0, 0, 0, 0b10000011, 0, 14, 0, 0, 1, 0],
+            Err(DeserializeMessageError::InvalidVersion),
+        );
+        // uptime byte using more than 7 bits.
+        assert_deserialize!(
+            AwaitingServerStatusServerMessage,
+            [5 << 4, 0, 12, 0, 5, 0, 0, 0, 3, 0, 14, 0, 0, 1, 0b10000000],
+            Err(DeserializeMessageError::InvalidDuration),
+        );
+        // invalid state variant.
+        assert_deserialize!(
+            AwaitingServerStatusServerMessage,
+            [0],
+            Err(DeserializeMessageError::InvalidState {
+                expected: 5,
+                actual: 0
+            }),
+        );
+        // unrecognised message variant.
+        assert_deserialize!(
+            AwaitingServerStatusServerMessage,
+            [5 << 4 | 1],
+            Err(DeserializeMessageError::UnrecognisedMessageVariant {
+                state_id: 5,
+                message_id: 1
+            }),
+        );
+    }
+
+    #[test]
+    fn awaiting_match_serialize() {
+        let lobby_id: LobbyId = "A5EX".parse().unwrap();
+        assert_serialize!(
+            AwaitingMatchServerMessage::MatchFound {
+                lobby_id,
+                side: Side::Right,
+                ready_timeout_secs: 300,
+                dimensions: GameDimensions::DEFAULT,
+            },
+            [
+                &[6 << 4],
+                lobby_id.as_bytes(),
+                &[1, 0b0000010, 0b0101100, 0, 51, 0, 11, 0, 5]
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn awaiting_match_deserialize_ok() {
+        let lobby_id: LobbyId = "F7BW".parse().unwrap();
+        assert_deserialize!(
+            AwaitingMatchServerMessage,
+            [
+                &[6 << 4],
+                lobby_id.as_bytes(),
+                &[0, 0, 60, 0, 21, 0, 20, 0, 8]
+            ]
+            .concat(),
+            Ok(AwaitingMatchServerMessage::MatchFound {
+                lobby_id,
+                side: Side::Left,
+                ready_timeout_secs: 60,
+                dimensions: GameDimensions::new(21, 20, 8).unwrap(),
+            }),
+        );
+    }
+
+    #[test]
+    fn awaiting_match_deserialize_err() {
+        // missing bytes.
+        assert_deserialize!(
+            AwaitingMatchServerMessage,
+            [&[6 << 4], "A5EX".as_bytes(), &[0, 0, 60]].concat(),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 6,
+                message_id: 0,
+                expected: 14,
+                actual: 8
+            }),
+        );
+        // a side that doesn't exist.
+        assert_deserialize!(
+            AwaitingMatchServerMessage,
+            [
+                &[6 << 4],
+                "A5EX".as_bytes(),
+                &[2, 0, 60, 0, 51, 0, 11, 0, 5]
+            ]
+            .concat(),
+            Err(DeserializeMessageError::InvalidSide),
+        );
+        // invalid state variant.
+        assert_deserialize!(
+            AwaitingMatchServerMessage,
+            [0],
+            Err(DeserializeMessageError::InvalidState {
+                expected: 6,
+                actual: 0
+            }),
+        );
+        // unrecognised message variant.
+        assert_deserialize!(
+            AwaitingMatchServerMessage,
+            [6 << 4 | 1],
+            Err(DeserializeMessageError::UnrecognisedMessageVariant {
+                state_id: 6,
+                message_id: 1
+            }),
+        );
+    }
+
+    #[test]
+    fn awaiting_tournament_serialize() {
+        let tournament_id: LobbyId = "A5EX".parse().unwrap();
+        assert_serialize!(
+            AwaitingTournamentServerMessage::JoinedTournament {
+                tournament_id,
+                seat: 3,
+                players: 4,
+            },
+            [&[7 << 4], tournament_id.as_bytes(), &[3, 4]].concat()
+        );
+        assert_serialize!(
+            AwaitingTournamentServerMessage::TournamentFull,
+            vec![7 << 4 | 1]
+        );
+        assert_serialize!(
+            AwaitingTournamentServerMessage::PlayersJoined { players: 3 },
+            vec![7 << 4 | 4, 0, 3]
+        );
+        let mut bracket = Bracket::default();
+        bracket.seats[0] = BracketSeat {
+            wins: 2,
+            eliminated: false,
+        };
+        bracket.seats[3] = BracketSeat {
+            wins: 1,
+            eliminated: true,
+        };
+        assert_serialize!(
+            AwaitingTournamentServerMessage::BracketUpdated { bracket },
+            vec![7 << 4 | 5, 2, 0, 0, 0b1001, 0, 0, 0, 0]
+        );
+        assert_serialize!(
+            AwaitingTournamentServerMessage::MatchReady {
+                lobby_id: tournament_id,
+                side: Side::Left,
+                ready_timeout_secs: 60,
+                dimensions: GameDimensions::DEFAULT,
+            },
+            [
+                &[7 << 4 | 6],
+                tournament_id.as_bytes(),
+                &[0, 0, 60, 0, 51, 0, 11, 0, 5]
+            ]
+            .concat()
+        );
+        assert_serialize!(
+            AwaitingTournamentServerMessage::TournamentWon { seat: 7 },
+            vec![7 << 4 | 7, 7]
+        );
+        let mut standings = Standings {
+            round: 2,
+            ..Standings::default()
+        };
+        standings.seats[1] = Standing {
+            wins: 2,
+            losses: 0,
+            left: false,
+        };
+        standings.seats[2] = Standing {
+            wins: 1,
+            losses: 1,
+            left: true,
+        };
+        assert_serialize!(
+            AwaitingTournamentServerMessage::StandingsUpdated { standings },
+            vec![7 << 4 | 8, 2, 0, 2, 0b1001001, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn awaiting_tournament_deserialize_ok() {
+        let tournament_id: LobbyId = "F7BW".parse().unwrap();
+        assert_deserialize!(
+            AwaitingTournamentServerMessage,
+            [&[7 << 4], tournament_id.as_bytes(), &[0, 2]].concat(),
+            Ok(AwaitingTournamentServerMessage::JoinedTournament {
+                tournament_id,
+                seat: 0,
+                players: 2,
+            }),
+        );
+        assert_deserialize!(
+            AwaitingTournamentServerMessage,
+            [7 << 4 | 2],
+            Ok(AwaitingTournamentServerMessage::TournamentNotFound),
+        );
+        assert_deserialize!(
+            AwaitingTournamentServerMessage,
+            [7 << 4 | 3],
+            Ok(AwaitingTournamentServerMessage::TournamentIncompatible),
+        );
+        let mut bracket = Bracket::default();
+        for seat in &mut bracket.seats[..7] {
+            seat.eliminated = true;
+        }
+        bracket.seats[7].wins = 3;
+        assert_deserialize!(
+            AwaitingTournamentServerMessage,
+            [
+                7 << 4 | 5,
+                0b1000,
+                0b1000,
+                0b1000,
+                0b1000,
+                0b1000,
+                0b1000,
+                0b1000,
+                3
+            ],
+            Ok(AwaitingTournamentServerMessage::BracketUpdated { bracket }),
+        );
+        let mut standings = Standings {
+            round: 7,
+            ..Standings::default()
+        };
+        standings.seats[0] = Standing {
+            wins: 7,
+            losses: 0,
+            left: false,
+        };
+        standings.seats[7] = Standing {
+            wins: 0,
+            losses: 7,
+            left: true,
+        };
+        assert_deserialize!(
+            AwaitingTournamentServerMessage,
+            [7 << 4 | 8, 7, 7, 0, 0, 0, 0, 0, 0, 0b1111000],
+            Ok(AwaitingTournamentServerMessage::StandingsUpdated { standings }),
+        );
+    }
+
+    #[test]
+    fn awaiting_tournament_deserialize_err() {
+        // a seat beyond the largest tournament.
+        assert_deserialize!(
+            AwaitingTournamentServerMessage,
+            [&[7 << 4], "A5EX".as_bytes(), &[8, 8]].concat(),
+            Err(DeserializeMessageError::InvalidSeat),
+        );
+        // a tournament that can't be played.
+        assert_deserialize!(
+            AwaitingTournamentServerMessage,
+            [&[7 << 4], "A5EX".as_bytes(), &[0, 9]].concat(),
+            Err(DeserializeMessageError::InvalidTournamentSize),
+        );
+        // more wins than the largest tournament has rounds.
+        assert_deserialize!(
+            AwaitingTournamentServerMessage,
+            [7 << 4 | 5, 4, 0, 0, 0, 0, 0, 0, 0],
+            Err(DeserializeMessageError::InvalidBracket),
+        );
+        // a bracket byte with unused bits set.
+        assert_deserialize!(
+            AwaitingTournamentServerMessage,
+            [7 << 4 | 5, 0, 0, 0b10000, 0, 0, 0, 0, 0],
+            Err(DeserializeMessageError::InvalidBracket),
+        );
+        // a bracket missing seats.
+        assert_deserialize!(
+            AwaitingTournamentServerMessage,
+            [7 << 4 | 5, 0, 0, 0, 0],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 7,
+                message_id: 5,
+                expected: 9,
+                actual: 5
+            }),
+        );
+        // more rounds played than the largest league has.
+        assert_deserialize!(
+            AwaitingTournamentServerMessage,
+            [7 << 4 | 8, 8, 0, 0, 0, 0, 0, 0, 0, 0],
+            Err(DeserializeMessageError::InvalidStandings),
+        );
+        // a standing with more matches played than the largest league has rounds.
+        assert_deserialize!(
+            AwaitingTournamentServerMessage,
+            [7 << 4 | 8, 7, 0, 0b0111001, 0, 0, 0, 0, 0, 0],
+            Err(DeserializeMessageError::InvalidStandings),
+        );
+        // invalid state variant.
+        assert_deserialize!(
+            AwaitingTournamentServerMessage,
+            [6 << 4],
+            Err(DeserializeMessageError::InvalidState {
+                expected: 7,
+                actual: 6
+            }),
+        );
+        // unrecognised message variant.
+        assert_deserialize!(
+            AwaitingTournamentServerMessage,
+            [7 << 4 | 9],
+            Err(DeserializeMessageError::UnrecognisedMessageVariant {
+                state_id: 7,
+                message_id: 9
+            }),
         );
     }
 
     #[test]
     fn awaiting_opponent_join_serialize() {
         assert_serialize!(
-            AwaitingOpponentJoinServerMessage::OpponentJoined,
-            vec![2 << 4]
+            AwaitingOpponentJoinServerMessage::OpponentJoined {
+                ready_timeout_secs: 300
+            },
+            vec![2 << 4, 0b0000010, 0b0101100]
+        );
+        assert_serialize!(
+            AwaitingOpponentJoinServerMessage::LobbyExpiringSoon { seconds: 60 },
+            vec![2 << 4 | 1, 0, 60]
+        );
+        assert_serialize!(
+            AwaitingOpponentJoinServerMessage::LobbyExpired,
+            vec![2 << 4 | 2]
+        );
+    }
+
+    #[test]
+    fn awaiting_lobby_page_serialize() {
+        let lobby_id: LobbyId = "A5EX".parse().unwrap();
+        assert_serialize!(
+            AwaitingLobbyPageServerMessage::LobbyListed {
+                lobby_id,
+                tick_interval_ms: 150,
+                age_secs: 200,
+            },
+            [
+                &[8 << 4],
+                lobby_id.as_bytes(),
+                &[1, 0b0010110, 0, 0, 1, 0b1001000]
+            ]
+            .concat()
+        );
+        assert_serialize!(
+            AwaitingLobbyPageServerMessage::LobbyPageEnd { page: 0, pages: 3 },
+            vec![8 << 4 | 1, 0, 0, 0, 3]
+        );
+    }
+
+    #[test]
+    fn awaiting_lobby_page_deserialize_ok() {
+        let lobby_id: LobbyId = "F7BW".parse().unwrap();
+        assert_deserialize!(
+            AwaitingLobbyPageServerMessage,
+            [&[8 << 4], lobby_id.as_bytes(), &[0, 60, 0, 0, 0, 5]].concat(),
+            Ok(AwaitingLobbyPageServerMessage::LobbyListed {
+                lobby_id,
+                tick_interval_ms: 60,
+                age_secs: 5,
+            }),
+        );
+        assert_deserialize!(
+            AwaitingLobbyPageServerMessage,
+            [8 << 4 | 1, 0, 2, 0, 2],
+            Ok(AwaitingLobbyPageServerMessage::LobbyPageEnd { page: 2, pages: 2 }),
+        );
+    }
+
+    #[test]
+    fn awaiting_lobby_page_deserialize_err() {
+        // a lobby with no age.
+        assert_deserialize!(
+            AwaitingLobbyPageServerMessage,
+            [&[8 << 4], "A5EX".as_bytes(), &[0, 60]].concat(),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 8,
+                message_id: 0,
+                expected: 11,
+                actual: 7
+            }),
+        );
+        // unrecognised message variant.
+        assert_deserialize!(
+            AwaitingLobbyPageServerMessage,
+            [8 << 4 | 2],
+            Err(DeserializeMessageError::UnrecognisedMessageVariant {
+                state_id: 8,
+                message_id: 2
+            }),
         );
     }
 
@@ -426,8 +1539,20 @@ mod tests {
     fn awaiting_opponent_join_deserialize_ok() {
         assert_deserialize!(
             AwaitingOpponentJoinServerMessage,
-            [2 << 4],
-            Ok(AwaitingOpponentJoinServerMessage::OpponentJoined),
+            [2 << 4, 0, 60],
+            Ok(AwaitingOpponentJoinServerMessage::OpponentJoined {
+                ready_timeout_secs: 60
+            }),
+        );
+        assert_deserialize!(
+            AwaitingOpponentJoinServerMessage,
+            [2 << 4 | 1, 1, 0],
+            Ok(AwaitingOpponentJoinServerMessage::LobbyExpiringSoon { seconds: 128 }),
+        );
+        assert_deserialize!(
+            AwaitingOpponentJoinServerMessage,
+            [2 << 4 | 2],
+            Ok(AwaitingOpponentJoinServerMessage::LobbyExpired),
         );
     }
 
@@ -439,23 +1564,51 @@ mod tests {
             [],
             Err(DeserializeMessageError::EmptyMessage),
         );
-        // extra bytes.
+        // missing bytes.
         assert_deserialize!(
             AwaitingOpponentJoinServerMessage,
             [2 << 4, 0],
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 2,
+                message_id: 0,
+                expected: 3,
+                actual: 2
+            }),
+        );
+        // extra bytes.
+        assert_deserialize!(
+            AwaitingOpponentJoinServerMessage,
+            [2 << 4, 0, 60, 0],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 2,
+                message_id: 0,
+                expected: 3,
+                actual: 4
+            }),
+        );
+        // timeout byte using more than 7 bits.
+        assert_deserialize!(
+            AwaitingOpponentJoinServerMessage,
+            [2 << 4, 0, 0b11111111],
+            Err(DeserializeMessageError::InvalidTimeout),
         );
         // invalid state variant.
         assert_deserialize!(
             AwaitingOpponentJoinServerMessage,
             [0],
-            Err(DeserializeMessageError::InvalidState),
+            Err(DeserializeMessageError::InvalidState {
+                expected: 2,
+                actual: 0
+            }),
         );
         // unrecognised message variant.
         assert_deserialize!(
             AwaitingOpponentJoinServerMessage,
-            [2 << 4 | 1],
-            Err(DeserializeMessageError::UnrecognisedMessageVariant),
+            [2 << 4 | 3],
+            Err(DeserializeMessageError::UnrecognisedMessageVariant {
+                state_id: 2,
+                message_id: 3
+            }),
         );
     }
 
@@ -472,7 +1625,73 @@ mod tests {
         );
         assert_serialize!(AwaitingReadyServerMessage::YouReadied, vec![3 << 4 | 3]);
         assert_serialize!(AwaitingReadyServerMessage::YouUnreadied, vec![3 << 4 | 4]);
-        assert_serialize!(AwaitingReadyServerMessage::GameStarted, vec![3 << 4 | 5]);
+        assert_serialize!(
+            AwaitingReadyServerMessage::GameStarted {
+                tick_interval_ms: 100
+            },
+            vec![3 << 4 | 5, 0, 100]
+        );
+        assert_serialize!(
+            AwaitingReadyServerMessage::GameStarted {
+                tick_interval_ms: 0b1111101000 // 1000
+            },
+            vec![3 << 4 | 5, 0b0000111, 0b1101000]
+        );
+        assert_serialize!(AwaitingReadyServerMessage::LobbyTimedOut, vec![3 << 4 | 6]);
+        assert_serialize!(
+            AwaitingReadyServerMessage::HandicappedGameStarted {
+                tick_interval_ms: 100,
+                handicaps: Handicaps {
+                    left: Handicap {
+                        paddle_height: Some(2),
+                        paddle_speed: None,
+                    },
+                    right: Handicap {
+                        paddle_height: None,
+                        paddle_speed: Some(3),
+                    },
+                },
+            },
+            vec![3 << 4 | 7, 0, 100, 0, 2, 0, 0, 0, 0, 0, 3]
+        );
+        assert_serialize!(
+            AwaitingReadyServerMessage::LobbyRuleset {
+                ruleset: Ruleset {
+                    tick_interval_ms: 100,
+                    match_length: Some(5),
+                    handicaps: Handicaps::NONE,
+                    modifier: Modifier::Gravity,
+                },
+            },
+            vec![3 << 4 | 8, 0, 100, 0, 5, 0, 0, 0, 0, 0, 0, 0, 0, 1]
+        );
+        assert_serialize!(AwaitingReadyServerMessage::CoopLobby, vec![3 << 4 | 9]);
+        assert_serialize!(
+            AwaitingReadyServerMessage::OpponentLeftLobby,
+            vec![3 << 4 | 10]
+        );
+        assert_serialize!(
+            AwaitingReadyServerMessage::PromotedToHost {
+                lobby_id: "ABCD".parse().unwrap()
+            },
+            vec![3 << 4 | 11, b'A', b'B', b'C', b'D']
+        );
+        assert_serialize!(
+            AwaitingReadyServerMessage::OpponentCosmetics {
+                cosmetics: Cosmetics {
+                    glyph: Some('#'),
+                    colour: Some(PaddleColour::Blue),
+                }
+            },
+            vec![3 << 4 | 12, b'#', 4]
+        );
+        assert_serialize!(
+            AwaitingReadyServerMessage::OpponentCosmetics {
+                cosmetics: Cosmetics::NONE
+            },
+            vec![3 << 4 | 12, 0, 0]
+        );
+        assert_serialize!(AwaitingReadyServerMessage::LockstepGame, vec![3 << 4 | 13]);
     }
 
     #[test]
@@ -504,8 +1723,50 @@ mod tests {
         );
         assert_deserialize!(
             AwaitingReadyServerMessage,
-            [3 << 4 | 5],
-            Ok(AwaitingReadyServerMessage::GameStarted),
+            [3 << 4 | 5, 0b0000001, 0b0010110],
+            Ok(AwaitingReadyServerMessage::GameStarted {
+                tick_interval_ms: 0b10010110 // 150
+            }),
+        );
+        assert_deserialize!(
+            AwaitingReadyServerMessage,
+            [3 << 4 | 6],
+            Ok(AwaitingReadyServerMessage::LobbyTimedOut),
+        );
+        assert_deserialize!(
+            AwaitingReadyServerMessage,
+            [3 << 4 | 7, 0, 60, 0, 0, 0, 4, 0, 1, 0, 0],
+            Ok(AwaitingReadyServerMessage::HandicappedGameStarted {
+                tick_interval_ms: 60,
+                handicaps: Handicaps {
+                    left: Handicap {
+                        paddle_height: None,
+                        paddle_speed: Some(4),
+                    },
+                    right: Handicap {
+                        paddle_height: Some(1),
+                        paddle_speed: None,
+                    },
+                },
+            }),
+        );
+        assert_deserialize!(
+            AwaitingReadyServerMessage,
+            [3 << 4 | 8, 0, 60, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 3],
+            Ok(AwaitingReadyServerMessage::LobbyRuleset {
+                ruleset: Ruleset {
+                    tick_interval_ms: 60,
+                    match_length: None,
+                    handicaps: Handicaps {
+                        left: Handicap::NONE,
+                        right: Handicap {
+                            paddle_height: Some(3),
+                            paddle_speed: None,
+                        },
+                    },
+                    modifier: Modifier::Wrap,
+                },
+            }),
         );
     }
 
@@ -521,49 +1782,159 @@ mod tests {
         assert_deserialize!(
             AwaitingReadyServerMessage,
             [3 << 4, 0],
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 3,
+                message_id: 0,
+                expected: 1,
+                actual: 2
+            }),
         );
         // extra bytes.
         assert_deserialize!(
             AwaitingReadyServerMessage,
             [3 << 4 | 1, 0],
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 3,
+                message_id: 1,
+                expected: 1,
+                actual: 2
+            }),
         );
         // extra bytes.
         assert_deserialize!(
             AwaitingReadyServerMessage,
             [3 << 4 | 2, 0],
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 3,
+                message_id: 2,
+                expected: 1,
+                actual: 2
+            }),
         );
         // extra bytes.
         assert_deserialize!(
             AwaitingReadyServerMessage,
             [3 << 4 | 3, 0],
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 3,
+                message_id: 3,
+                expected: 1,
+                actual: 2
+            }),
         );
         // extra bytes.
         assert_deserialize!(
             AwaitingReadyServerMessage,
             [3 << 4 | 4, 0],
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 3,
+                message_id: 4,
+                expected: 1,
+                actual: 2
+            }),
         );
-        // extra bytes.
+        // missing bytes.
         assert_deserialize!(
             AwaitingReadyServerMessage,
             [3 << 4 | 5, 0],
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 3,
+                message_id: 5,
+                expected: 3,
+                actual: 2
+            }),
+        );
+        // extra bytes.
+        assert_deserialize!(
+            AwaitingReadyServerMessage,
+            [3 << 4 | 5, 0, 100, 0],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 3,
+                message_id: 5,
+                expected: 3,
+                actual: 4
+            }),
+        );
+        // tick interval too short.
+        assert_deserialize!(
+            AwaitingReadyServerMessage,
+            [3 << 4 | 5, 0, 0],
+            Err(DeserializeMessageError::InvalidTickInterval),
+        );
+        // extra bytes.
+        assert_deserialize!(
+            AwaitingReadyServerMessage,
+            [3 << 4 | 6, 0],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 3,
+                message_id: 6,
+                expected: 1,
+                actual: 2
+            }),
         );
         // invalid state variant.
         assert_deserialize!(
             AwaitingReadyServerMessage,
             [0],
-            Err(DeserializeMessageError::InvalidState),
+            Err(DeserializeMessageError::InvalidState {
+                expected: 3,
+                actual: 0
+            }),
+        );
+        // handicapped game started with missing handicaps.
+        assert_deserialize!(
+            AwaitingReadyServerMessage,
+            [3 << 4 | 7, 0, 100],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 3,
+                message_id: 7,
+                expected: 11,
+                actual: 3
+            }),
+        );
+        // paddle speed too fast.
+        assert_deserialize!(
+            AwaitingReadyServerMessage,
+            [3 << 4 | 7, 0, 100, 0, 0, 0, 5, 0, 0, 0, 0],
+            Err(DeserializeMessageError::InvalidHandicap),
+        );
+        // ruleset with an unknown modifier.
+        assert_deserialize!(
+            AwaitingReadyServerMessage,
+            [3 << 4 | 8, 0, 100, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4],
+            Err(DeserializeMessageError::InvalidModifier),
+        );
+        // co-op lobby with an extra byte.
+        assert_deserialize!(
+            AwaitingReadyServerMessage,
+            [3 << 4 | 9, 0],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 3,
+                message_id: 9,
+                expected: 1,
+                actual: 2
+            }),
+        );
+        // opponent cosmetics with a glyph that isn't printable.
+        assert_deserialize!(
+            AwaitingReadyServerMessage,
+            [3 << 4 | 12, b'\n', 0],
+            Err(DeserializeMessageError::InvalidCosmetics),
+        );
+        // opponent cosmetics with an unknown colour.
+        assert_deserialize!(
+            AwaitingReadyServerMessage,
+            [3 << 4 | 12, 0, 8],
+            Err(DeserializeMessageError::InvalidCosmetics),
         );
         // unrecognised message variant.
         assert_deserialize!(
             AwaitingReadyServerMessage,
-            [3 << 4 | 6],
-            Err(DeserializeMessageError::UnrecognisedMessageVariant),
+            [3 << 4 | 14],
+            Err(DeserializeMessageError::UnrecognisedMessageVariant {
+                state_id: 3,
+                message_id: 14
+            }),
         );
     }
 
@@ -572,6 +1943,17 @@ mod tests {
         assert_serialize!(PlayingServerMessage::OpponentLeft, vec![4 << 4]);
         assert_serialize!(PlayingServerMessage::OpponentWon, vec![4 << 4 | 1]);
         assert_serialize!(PlayingServerMessage::YouWon, vec![4 << 4 | 2]);
+        assert_serialize!(PlayingServerMessage::OpponentResigned, vec![4 << 4 | 4]);
+        assert_serialize!(
+            PlayingServerMessage::BallSpedUp { speed_level: 3 },
+            vec![4 << 4 | 6, 3]
+        );
+        assert_serialize!(
+            PlayingServerMessage::Serving { side: Side::Right },
+            vec![4 << 4 | 7, 1]
+        );
+        assert_serialize!(PlayingServerMessage::Deuce, vec![4 << 4 | 8]);
+        assert_serialize!(PlayingServerMessage::SuddenDeath, vec![4 << 4 | 9]);
         assert_serialize!(
             PlayingServerMessage::GameStateUpdated {
                 game_state: GameState {
@@ -587,7 +1969,7 @@ mod tests {
             },
             vec![4 << 4 | 3, 0b00110111, 0b00011101, 0b00001010],
         );
-        // these positions are technically impossible given the size of the game window. bits will be truncated during serialized.
+        // positions too large for the narrow encoding use the wide one.
         assert_serialize!(
             PlayingServerMessage::GameStateUpdated {
                 game_state: GameState {
@@ -601,7 +1983,42 @@ mod tests {
                     }
                 }
             },
-            vec![4 << 4 | 3, 0b01110101, 0b11000100, 0b01011101],
+            vec![
+                4 << 4 | 5,
+                1,
+                0b0110111,
+                0,
+                0b1110101,
+                1,
+                0b1100010,
+                1,
+                0b0101110,
+                0b01
+            ],
+        );
+        assert_serialize!(
+            PlayingServerMessage::CoopStateUpdated {
+                game_state: GameState {
+                    left_paddle: 2,
+                    right_paddle: 5,
+                    ball: Ball {
+                        x: 200,
+                        y: 4,
+                        moving_right: true,
+                        moving_down: true,
+                    }
+                },
+                partner_paddle: 6,
+                score: 130,
+            },
+            vec![4 << 4 | 10, 0, 2, 0, 5, 1, 72, 0, 4, 0b11, 0, 6, 1, 2],
+        );
+        assert_serialize!(
+            PlayingServerMessage::MatchPoint {
+                left_wins: 4,
+                right_wins: 130,
+            },
+            vec![4 << 4 | 11, 0, 4, 1, 2],
         );
     }
 
@@ -622,6 +2039,39 @@ mod tests {
             [4 << 4 | 2],
             Ok(PlayingServerMessage::YouWon)
         );
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 4],
+            Ok(PlayingServerMessage::OpponentResigned)
+        );
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 6, 5],
+            Ok(PlayingServerMessage::BallSpedUp { speed_level: 5 })
+        );
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 7, 0],
+            Ok(PlayingServerMessage::Serving { side: Side::Left })
+        );
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 8],
+            Ok(PlayingServerMessage::Deuce)
+        );
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 9],
+            Ok(PlayingServerMessage::SuddenDeath)
+        );
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 11, 0, 2, 0, 1],
+            Ok(PlayingServerMessage::MatchPoint {
+                left_wins: 2,
+                right_wins: 1
+            })
+        );
         assert_deserialize!(
             PlayingServerMessage,
             [4 << 4 | 3, 0b01010000, 0b01001111, 0b00010000],
@@ -638,6 +2088,33 @@ mod tests {
                 }
             }),
         );
+        assert_deserialize!(
+            PlayingServerMessage,
+            [
+                4 << 4 | 5,
+                0,
+                3,
+                0b0000001,
+                0b0000000,
+                0b0000010,
+                0b0101100,
+                0,
+                40,
+                0b11
+            ],
+            Ok(PlayingServerMessage::GameStateUpdated {
+                game_state: GameState {
+                    left_paddle: 3,
+                    right_paddle: 0b10000000, // 128
+                    ball: Ball {
+                        x: 0b100101100, // 300
+                        y: 40,
+                        moving_right: true,
+                        moving_down: true,
+                    }
+                }
+            }),
+        );
     }
 
     #[test]
@@ -652,81 +2129,313 @@ mod tests {
         assert_deserialize!(
             PlayingServerMessage,
             [4 << 4, 0],
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 4,
+                message_id: 0,
+                expected: 1,
+                actual: 2
+            }),
         );
         // extra bytes.
         assert_deserialize!(
             PlayingServerMessage,
             [4 << 4 | 1, 0],
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 4,
+                message_id: 1,
+                expected: 1,
+                actual: 2
+            }),
         );
         // extra bytes.
         assert_deserialize!(
             PlayingServerMessage,
             [4 << 4 | 2, 0],
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 4,
+                message_id: 2,
+                expected: 1,
+                actual: 2
+            }),
         );
         // extra bytes.
         assert_deserialize!(
             PlayingServerMessage,
             [4 << 4 | 3, 0b11110000, 0b01101111, 0b00011000, 0],
-            Err(DeserializeMessageError::InvalidByteCount),
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 4,
+                message_id: 3,
+                expected: 4,
+                actual: 5
+            }),
+        );
+        // extra bytes.
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 4, 0],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 4,
+                message_id: 4,
+                expected: 1,
+                actual: 2
+            }),
+        );
+        // wide game state with missing bytes.
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 5, 0, 3, 0, 3, 0, 40, 0, 40],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 4,
+                message_id: 5,
+                expected: 10,
+                actual: 9
+            }),
+        );
+        // speed level with missing byte.
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 6],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 4,
+                message_id: 6,
+                expected: 2,
+                actual: 1
+            }),
+        );
+        // the ball is never sped up to level 0, nor beyond the highest level.
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 6, 0],
+            Err(DeserializeMessageError::InvalidSpeedLevel),
+        );
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 6, 6],
+            Err(DeserializeMessageError::InvalidSpeedLevel),
+        );
+        // serving side with missing byte.
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 7],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 4,
+                message_id: 7,
+                expected: 2,
+                actual: 1
+            }),
+        );
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 7, 2],
+            Err(DeserializeMessageError::InvalidSide),
+        );
+        // sudden death with an extra byte.
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 9, 0],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 4,
+                message_id: 9,
+                expected: 1,
+                actual: 2
+            }),
         );
-        // invalid left paddle position.
+        // left paddle position outside of any game.
         assert_deserialize!(
             PlayingServerMessage,
             [4 << 4 | 3, 0b11110000, 0b01001111, 0b00010000],
             Err(DeserializeMessageError::InvalidPaddlePosition),
         );
-        // invalid right paddle position.
+        // right paddle position outside of any game.
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 3, 0b01011111, 0b01001111, 0b00010000],
+            Err(DeserializeMessageError::InvalidPaddlePosition),
+        );
+        // ball x position outside of any game.
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 3, 0b01010000, 0b11111100, 0b00010000],
+            Err(DeserializeMessageError::InvalidBallPosition),
+        );
+        // ball y position outside of any game.
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 3, 0b01010000, 0b01001111, 0b11111101],
+            Err(DeserializeMessageError::InvalidBallPosition),
+        );
+        // wide paddle position byte using more than 7 bits.
         assert_deserialize!(
             PlayingServerMessage,
-            [4 << 4 | 3, 0b01011000, 0b01001111, 0b00010000],
+            [4 << 4 | 5, 0b10000000, 3, 0, 3, 0, 40, 0, 40, 0],
             Err(DeserializeMessageError::InvalidPaddlePosition),
         );
-        // invalid ball x position.
+        // wide ball position byte using more than 7 bits.
         assert_deserialize!(
             PlayingServerMessage,
-            [4 << 4 | 3, 0b01010000, 0b01101111, 0b00010000],
+            [4 << 4 | 5, 0, 3, 0, 3, 0, 0b11111111, 0, 40, 0],
             Err(DeserializeMessageError::InvalidBallPosition),
         );
-        // invalid ball y position.
+        // wide ball directions using more than 2 bits.
         assert_deserialize!(
             PlayingServerMessage,
-            [4 << 4 | 3, 0b01010000, 0b01001111, 0b00011000],
+            [4 << 4 | 5, 0, 3, 0, 3, 0, 40, 0, 40, 0b100],
             Err(DeserializeMessageError::InvalidBallPosition),
         );
         // invalid state variant.
         assert_deserialize!(
             PlayingServerMessage,
             [0],
-            Err(DeserializeMessageError::InvalidState),
+            Err(DeserializeMessageError::InvalidState {
+                expected: 4,
+                actual: 0
+            }),
+        );
+        // co-op state with a partner paddle byte using more than 7 bits.
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 10, 0, 3, 0, 3, 0, 40, 0, 4, 0, 0b10000000, 1, 0, 0],
+            Err(DeserializeMessageError::InvalidPaddlePosition),
+        );
+        // co-op state with a score byte using more than 7 bits.
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 10, 0, 3, 0, 3, 0, 40, 0, 4, 0, 0, 1, 0, 0b10000000],
+            Err(DeserializeMessageError::InvalidScore),
+        );
+        // co-op state without its score.
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 10, 0, 3, 0, 3, 0, 40, 0, 4, 0, 0, 1],
+            Err(DeserializeMessageError::InvalidByteCount {
+                state_id: 4,
+                message_id: 10,
+                expected: 14,
+                actual: 12
+            }),
+        );
+        // match point in a tied match.
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 11, 0, 2, 0, 2],
+            Err(DeserializeMessageError::InvalidScore),
+        );
+        // match point with a score byte using more than 7 bits.
+        assert_deserialize!(
+            PlayingServerMessage,
+            [4 << 4 | 11, 0, 2, 0b10000000, 0],
+            Err(DeserializeMessageError::InvalidScore),
         );
         // unrecognised message variant.
         assert_deserialize!(
             PlayingServerMessage,
-            [4 << 4 | 4],
-            Err(DeserializeMessageError::UnrecognisedMessageVariant)
+            [4 << 4 | 12],
+            Err(DeserializeMessageError::UnrecognisedMessageVariant {
+                state_id: 4,
+                message_id: 12
+            })
         );
     }
 
     #[test]
     fn serialize_and_back() {
         assert_serialize_and_back!(AwaitingNewLobbyServerMessage::NewLobbyCreated {
-            lobby_id: "G16P"
+            lobby_id: "G26P".parse().unwrap(),
+            dimensions: GameDimensions::new(126, 126, 112).unwrap(),
+        });
+        assert_serialize_and_back!(AwaitingJoinLobbyServerMessage::JoinedLobby {
+            ready_timeout_secs: 120,
+            dimensions: GameDimensions::DEFAULT,
         });
-        assert_serialize_and_back!(AwaitingJoinLobbyServerMessage::JoinedLobby);
         assert_serialize_and_back!(AwaitingJoinLobbyServerMessage::LobbyFull);
         assert_serialize_and_back!(AwaitingJoinLobbyServerMessage::LobbyNotFound);
-        assert_serialize_and_back!(AwaitingOpponentJoinServerMessage::OpponentJoined);
+        assert_serialize_and_back!(AwaitingJoinLobbyServerMessage::LobbyIncompatible);
+        assert_serialize_and_back!(AwaitingServerStatusServerMessage::ServerStatus {
+            players_online: 40,
+            open_lobbies: 7,
+            version: ServerVersion {
+                major: 2,
+                minor: 0,
+                patch: 11,
+            },
+            uptime_secs: 31_536_000,
+        });
+        assert_serialize_and_back!(AwaitingOpponentJoinServerMessage::OpponentJoined {
+            ready_timeout_secs: 120
+        });
+        assert_serialize_and_back!(AwaitingMatchServerMessage::MatchFound {
+            lobby_id: "G26P".parse().unwrap(),
+            side: Side::Left,
+            ready_timeout_secs: 120,
+            dimensions: GameDimensions::DEFAULT,
+        });
+        assert_serialize_and_back!(AwaitingTournamentServerMessage::PlayersJoined { players: 7 });
+        assert_serialize_and_back!(AwaitingTournamentServerMessage::TournamentWon { seat: 2 });
         assert_serialize_and_back!(AwaitingReadyServerMessage::OpponentLeft);
         assert_serialize_and_back!(AwaitingReadyServerMessage::OpponentReadied);
         assert_serialize_and_back!(AwaitingReadyServerMessage::OpponentUnreadied);
         assert_serialize_and_back!(AwaitingReadyServerMessage::YouReadied);
         assert_serialize_and_back!(AwaitingReadyServerMessage::YouUnreadied);
+        assert_serialize_and_back!(AwaitingReadyServerMessage::GameStarted {
+            tick_interval_ms: 60
+        });
+        assert_serialize_and_back!(AwaitingReadyServerMessage::LobbyTimedOut);
+        assert_serialize_and_back!(AwaitingReadyServerMessage::HandicappedGameStarted {
+            tick_interval_ms: 60,
+            handicaps: Handicaps {
+                left: Handicap {
+                    paddle_height: Some(MAX_U14),
+                    paddle_speed: Some(1),
+                },
+                right: Handicap::NONE,
+            },
+        });
+        assert_serialize_and_back!(AwaitingReadyServerMessage::LobbyRuleset {
+            ruleset: Ruleset {
+                tick_interval_ms: 60,
+                match_length: Some(MAX_U14),
+                handicaps: Handicaps::NONE,
+                modifier: Modifier::Classic,
+            },
+        });
         assert_serialize_and_back!(PlayingServerMessage::OpponentLeft);
         assert_serialize_and_back!(PlayingServerMessage::OpponentWon);
         assert_serialize_and_back!(PlayingServerMessage::YouWon);
+        assert_serialize_and_back!(PlayingServerMessage::OpponentResigned);
+        assert_serialize_and_back!(PlayingServerMessage::BallSpedUp { speed_level: 1 });
+        assert_serialize_and_back!(PlayingServerMessage::Serving { side: Side::Left });
+        assert_serialize_and_back!(PlayingServerMessage::Deuce);
+        assert_serialize_and_back!(PlayingServerMessage::SuddenDeath);
+        assert_serialize_and_back!(PlayingServerMessage::MatchPoint {
+            left_wins: 0,
+            right_wins: MAX_U14,
+        });
+        assert_serialize_and_back!(AwaitingReadyServerMessage::CoopLobby);
+        assert_serialize_and_back!(AwaitingReadyServerMessage::OpponentLeftLobby);
+        assert_serialize_and_back!(AwaitingReadyServerMessage::PromotedToHost {
+            lobby_id: "ABCDEF".parse().unwrap()
+        });
+        assert_serialize_and_back!(AwaitingReadyServerMessage::OpponentCosmetics {
+            cosmetics: Cosmetics {
+                glyph: Some('~'),
+                colour: Some(PaddleColour::Green),
+            }
+        });
+        assert_serialize_and_back!(AwaitingReadyServerMessage::LockstepGame);
+        assert_serialize_and_back!(PlayingServerMessage::CoopStateUpdated {
+            game_state: GameState {
+                left_paddle: 0,
+                right_paddle: MAX_U14 - 1,
+                ball: Ball {
+                    x: MAX_U14 - 1,
+                    y: 0,
+                    moving_right: false,
+                    moving_down: false,
+                },
+            },
+            partner_paddle: 12,
+            score: MAX_U14,
+        });
         assert_serialize_and_back!(PlayingServerMessage::GameStateUpdated {
             game_state: GameState {
                 left_paddle: 6,
@@ -739,5 +2448,17 @@ mod tests {
                 },
             },
         });
+        assert_serialize_and_back!(PlayingServerMessage::GameStateUpdated {
+            game_state: GameState {
+                left_paddle: 900,
+                right_paddle: 14,
+                ball: Ball {
+                    x: 1000,
+                    y: 127,
+                    moving_right: false,
+                    moving_down: true,
+                },
+            },
+        });
     }
 }
@@ -0,0 +1,1327 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{self, ErrorKind, Read, Write},
+    marker::PhantomData,
+    net::TcpStream,
+    time::Duration,
+};
+
+use crate::{
+    checksum::{self, checksum, CHECKSUM_SIZE},
+    client_msg::{
+        AwaitingOpenClientMessage, AwaitingOpponentJoinClientMessage, AwaitingReadyClientMessage,
+        Capabilities, ChecksumClientMessage, PlayingClientMessage, ResyncClientMessage,
+        CLIENT_MESSAGE_DELIMITER, MAX_CLIENT_MESSAGE_SIZE,
+    },
+    cosmetics::Cosmetics,
+    game_state::{GameDimensions, Ruleset, Side},
+    io::{MessageReader, ReadMessageError},
+    server_msg::{
+        AwaitingJoinLobbyServerMessage, AwaitingLobbyPageServerMessage, AwaitingMatchServerMessage,
+        AwaitingNewLobbyServerMessage, AwaitingOpponentJoinServerMessage,
+        AwaitingReadyServerMessage, AwaitingServerStatusServerMessage,
+        AwaitingTournamentServerMessage, ChecksumServerMessage, PlayingServerMessage,
+        ProtocolState, RejectedServerMessage, ResyncServerMessage, ServerVersion,
+        MAX_SERVER_MESSAGE_SIZE, SERVER_MESSAGE_DELIMITER,
+    },
+    DeserializeMessageError, LobbyId, WriteInto,
+};
+
+/// a protocol state in which the client may send messages.
+pub trait Sends {
+    type Message<'a>: WriteInto;
+}
+
+/// a protocol state in which the client may receive messages.
+pub trait Receives {
+    type Message<'a>: TryFrom<&'a [u8], Error = DeserializeMessageError>;
+}
+
+/// the client hasn't yet created or joined a lobby.
+pub struct AwaitingOpen;
+/// the client has asked about the server and is waiting for its status.
+pub struct AwaitingServerStatus;
+/// the client has asked for a page of open lobbies and is waiting for it.
+pub struct AwaitingLobbyPage;
+/// the client has asked for a new lobby and is waiting for its id.
+pub struct AwaitingNewLobby;
+/// the client has asked to join a lobby and is waiting to hear whether it could.
+pub struct AwaitingJoinLobby;
+/// the client is hosting a lobby and waiting for an opponent to join it.
+pub struct AwaitingOpponentJoin;
+/// the client is queued for a match, and waiting for an opponent to be found.
+pub struct AwaitingMatch;
+/// the client has a seat in a tournament, and is waiting for it to start or for their next match.
+pub struct AwaitingTournament;
+/// both players are in the lobby, and the game starts once both are ready.
+pub struct AwaitingReady;
+/// a game is being played.
+pub struct Playing;
+
+impl Sends for AwaitingOpen {
+    type Message<'a> = AwaitingOpenClientMessage;
+}
+
+impl Receives for AwaitingServerStatus {
+    type Message<'a> = AwaitingServerStatusServerMessage;
+}
+
+impl Receives for AwaitingLobbyPage {
+    type Message<'a> = AwaitingLobbyPageServerMessage;
+}
+
+impl Receives for AwaitingNewLobby {
+    type Message<'a> = AwaitingNewLobbyServerMessage;
+}
+
+impl Receives for AwaitingJoinLobby {
+    type Message<'a> = AwaitingJoinLobbyServerMessage;
+}
+
+impl Sends for AwaitingOpponentJoin {
+    type Message<'a> = AwaitingOpponentJoinClientMessage;
+}
+
+impl Receives for AwaitingOpponentJoin {
+    type Message<'a> = AwaitingOpponentJoinServerMessage;
+}
+
+impl Receives for AwaitingMatch {
+    type Message<'a> = AwaitingMatchServerMessage;
+}
+
+impl Receives for AwaitingTournament {
+    type Message<'a> = AwaitingTournamentServerMessage;
+}
+
+impl Sends for AwaitingReady {
+    type Message<'a> = AwaitingReadyClientMessage;
+}
+
+impl Receives for AwaitingReady {
+    type Message<'a> = AwaitingReadyServerMessage;
+}
+
+impl Sends for Playing {
+    type Message<'a> = PlayingClientMessage;
+}
+
+impl Receives for Playing {
+    type Message<'a> = PlayingServerMessage;
+}
+
+/// a client's connection to the server, in protocol state `S`.
+/// only the messages valid in the current state can be sent or received, and the state can only
+/// be moved along the transitions the protocol allows.
+pub struct ClientSession<S, R: Read, W: Write> {
+    reader: MessageReader<R>,
+    writer: W,
+    /// whether every frame either way ends with a checksum.
+    checksums: bool,
+    /// how the player would like their paddle drawn, sent whenever the session creates or joins
+    /// something to play in.
+    cosmetics: Option<Cosmetics>,
+    state: PhantomData<S>,
+}
+
+/// sends messages on behalf of a [`ClientSession`] in state `S`, e.g. from another thread.
+pub struct MessageSender<S, W: Write> {
+    writer: W,
+    checksums: bool,
+    state: PhantomData<S>,
+}
+
+pub enum OpponentJoinOutcome<R: Read, W: Write> {
+    Joined {
+        ready_timeout_secs: u16,
+        session: ClientSession<AwaitingReady, R, W>,
+    },
+    /// the lobby will be closed in `seconds` unless the host sends
+    /// [`AwaitingOpponentJoinClientMessage::StillHere`]. either way, the lobby is still open.
+    ExpiringSoon {
+        seconds: u16,
+        session: ClientSession<AwaitingOpponentJoin, R, W>,
+    },
+    Expired,
+}
+
+pub enum NewLobbyOutcome<R: Read, W: Write> {
+    /// the lobby was created, and the session is waiting for an opponent to join it.
+    Created {
+        lobby_id: LobbyId,
+        dimensions: GameDimensions,
+        session: ClientSession<AwaitingOpponentJoin, R, W>,
+    },
+    /// the lobby id the host asked for belongs to another lobby.
+    IdTaken,
+    /// the host's address has created too many lobbies lately.
+    TooManyLobbies,
+    /// the server plays its games in a way the host's client can't.
+    LobbyIncompatible,
+}
+
+/// what the server reported about itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ServerStatus {
+    pub players_online: u16,
+    pub open_lobbies: u16,
+    pub version: ServerVersion,
+    pub uptime_secs: u32,
+}
+
+/// a lobby listed while browsing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ListedLobby {
+    pub lobby_id: LobbyId,
+    pub tick_interval_ms: u16,
+    pub age_secs: u32,
+}
+
+/// one page of the open lobbies, oldest first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LobbyPage {
+    pub lobbies: Vec<ListedLobby>,
+    pub page: u16,
+    pub pages: u16,
+}
+
+/// the lobby the client was seated in with an opponent from the queue.
+pub struct FoundMatch<R: Read, W: Write> {
+    pub lobby_id: LobbyId,
+    pub side: Side,
+    pub ready_timeout_secs: u16,
+    pub dimensions: GameDimensions,
+    pub session: ClientSession<AwaitingReady, R, W>,
+}
+
+pub enum JoinLobbyOutcome<R: Read, W: Write> {
+    Joined {
+        ready_timeout_secs: u16,
+        dimensions: GameDimensions,
+        session: ClientSession<AwaitingReady, R, W>,
+    },
+    LobbyFull,
+    LobbyNotFound,
+    /// the lobby's games need protocol features the client doesn't support.
+    LobbyIncompatible,
+}
+
+/// the state the server reported having the client in, with the session moved to it.
+pub enum Resynced<R: Read, W: Write> {
+    Open(ClientSession<AwaitingOpen, R, W>),
+    AwaitingOpponentJoin(ClientSession<AwaitingOpponentJoin, R, W>),
+    AwaitingMatch(ClientSession<AwaitingMatch, R, W>),
+    AwaitingTournament(ClientSession<AwaitingTournament, R, W>),
+    AwaitingReady {
+        you_ready: bool,
+        opponent_ready: bool,
+        session: ClientSession<AwaitingReady, R, W>,
+    },
+    Playing(ClientSession<Playing, R, W>),
+}
+
+#[derive(Debug)]
+pub enum ReceiveError {
+    Read(ReadMessageError),
+    Deserialize(DeserializeMessageError),
+    /// the server turned the client away, and is closing the connection.
+    Rejected(RejectedServerMessage),
+    /// a request the reply was to answer couldn't be sent.
+    Send(io::Error),
+    /// a frame's checksum didn't match its message, which was corrupted on its way.
+    ChecksumMismatch,
+}
+
+impl Display for ReceiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReceiveError::Read(err) => Display::fmt(err, f),
+            ReceiveError::Deserialize(err) => Display::fmt(err, f),
+            ReceiveError::Rejected(rejection) => Display::fmt(rejection, f),
+            ReceiveError::Send(err) => Display::fmt(err, f),
+            ReceiveError::ChecksumMismatch => Display::fmt("message failed its checksum", f),
+        }
+    }
+}
+
+impl Error for ReceiveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReceiveError::Read(source) => Some(source),
+            ReceiveError::Deserialize(source) => Some(source),
+            ReceiveError::Rejected(_) => None,
+            ReceiveError::Send(source) => Some(source),
+            ReceiveError::ChecksumMismatch => None,
+        }
+    }
+}
+
+impl ReceiveError {
+    /// whether no message arrived before the session's receive timeout. the session can carry on
+    /// receiving, since any part of a message that did arrive is kept for the next receive.
+    pub fn is_timeout(&self) -> bool {
+        matches!(
+            self,
+            ReceiveError::Read(ReadMessageError::Io(err))
+                if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+        )
+    }
+}
+
+impl From<ReadMessageError> for ReceiveError {
+    fn from(value: ReadMessageError) -> Self {
+        ReceiveError::Read(value)
+    }
+}
+
+impl From<DeserializeMessageError> for ReceiveError {
+    fn from(value: DeserializeMessageError) -> Self {
+        ReceiveError::Deserialize(value)
+    }
+}
+
+impl<S, R: Read, W: Write> ClientSession<S, R, W> {
+    fn into_state<T>(self) -> ClientSession<T, R, W> {
+        ClientSession {
+            reader: self.reader,
+            writer: self.writer,
+            checksums: self.checksums,
+            cosmetics: self.cosmetics,
+            state: PhantomData,
+        }
+    }
+
+    /// blocks until the next frame arrives from the server, returning its message.
+    fn read_message(&mut self) -> Result<&[u8], ReceiveError> {
+        let frame = self.reader.read_message()?;
+        if !self.checksums {
+            return Ok(frame);
+        }
+        checksum::verify(frame).ok_or(ReceiveError::ChecksumMismatch)
+    }
+
+    /// asks the server which state it has the client in, for when a message arrived that isn't
+    /// valid in the session's own. whatever the server sent before its reply is skipped, and the
+    /// session carries on in the state it reported. blocks until the reply arrives, however long
+    /// the session's receive timeout is.
+    pub fn resync(mut self) -> Result<Resynced<R, W>, ReceiveError> {
+        write_client_message(
+            &mut self.writer,
+            ResyncClientMessage::ResyncRequest,
+            self.checksums,
+        )
+        .map_err(ReceiveError::Send)?;
+        let state = loop {
+            let message = match self.read_message() {
+                Ok(message) => message,
+                Err(err) => {
+                    if err.is_timeout() {
+                        continue;
+                    }
+                    return Err(err);
+                }
+            };
+            if let Ok(rejection) = RejectedServerMessage::try_from(message) {
+                return Err(ReceiveError::Rejected(rejection));
+            }
+            if let Ok(ResyncServerMessage::FullState { state }) =
+                ResyncServerMessage::try_from(message)
+            {
+                break state;
+            }
+        };
+        Ok(match state {
+            ProtocolState::Open => Resynced::Open(self.into_state()),
+            ProtocolState::AwaitingOpponentJoin => {
+                Resynced::AwaitingOpponentJoin(self.into_state())
+            }
+            ProtocolState::AwaitingMatch => Resynced::AwaitingMatch(self.into_state()),
+            ProtocolState::AwaitingTournament => Resynced::AwaitingTournament(self.into_state()),
+            ProtocolState::AwaitingReady {
+                you_ready,
+                opponent_ready,
+            } => Resynced::AwaitingReady {
+                you_ready,
+                opponent_ready,
+                session: self.into_state(),
+            },
+            ProtocolState::Playing => Resynced::Playing(self.into_state()),
+        })
+    }
+}
+
+impl<S: Sends, R: Read, W: Write> ClientSession<S, R, W> {
+    pub fn send(&mut self, message: S::Message<'_>) -> io::Result<()> {
+        write_client_message(&mut self.writer, message, self.checksums)
+    }
+}
+
+impl<S: Receives, R: Read, W: Write> ClientSession<S, R, W> {
+    /// blocks until the next message arrives from the server.
+    pub fn receive(&mut self) -> Result<S::Message<'_>, ReceiveError> {
+        let message = self.read_message()?;
+        // the server can turn the client away whatever it was waiting for.
+        if let Ok(rejection) = RejectedServerMessage::try_from(message) {
+            return Err(ReceiveError::Rejected(rejection));
+        }
+        Ok(S::Message::try_from(message)?)
+    }
+}
+
+impl<S, W: Write> ClientSession<S, TcpStream, W> {
+    /// makes receiving give up once no message has arrived for the given time, or wait for as
+    /// long as it takes again if there's no timeout.
+    pub fn set_receive_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.reader.get_ref().set_read_timeout(timeout)
+    }
+}
+
+impl<S, R: Read> ClientSession<S, R, TcpStream> {
+    pub fn try_clone_sender(&self) -> io::Result<MessageSender<S, TcpStream>> {
+        Ok(MessageSender {
+            writer: self.writer.try_clone()?,
+            checksums: self.checksums,
+            state: PhantomData,
+        })
+    }
+}
+
+impl<S: Sends, W: Write> MessageSender<S, W> {
+    pub fn send(&mut self, message: S::Message<'_>) -> io::Result<()> {
+        write_client_message(&mut self.writer, message, self.checksums)
+    }
+}
+
+impl<R: Read, W: Write> ClientSession<AwaitingOpen, R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self {
+            // frames can be a checksum longer than their message.
+            reader: MessageReader::new(
+                reader,
+                SERVER_MESSAGE_DELIMITER,
+                MAX_SERVER_MESSAGE_SIZE + CHECKSUM_SIZE,
+            ),
+            writer,
+            checksums: false,
+            cosmetics: None,
+            state: PhantomData,
+        }
+    }
+
+    /// has the server tell the player's opponents how they'd like their paddle drawn, and tell
+    /// the player how their opponents would like theirs drawn in turn, with
+    /// [`AwaitingReadyServerMessage::OpponentCosmetics`]. servers from before cosmetics can't be
+    /// played on with them set.
+    pub fn set_cosmetics(&mut self, cosmetics: Cosmetics) {
+        self.cosmetics = Some(cosmetics);
+    }
+
+    /// has every frame sent either way end with a checksum from now on, so that a message
+    /// corrupted on its way is received as [`ReceiveError::ChecksumMismatch`] rather than as some
+    /// other message. blocks until the server agrees, within the session's receive timeout.
+    pub fn enable_checksums(mut self) -> Result<Self, ReceiveError> {
+        write_client_message(
+            &mut self.writer,
+            ChecksumClientMessage::EnableChecksums,
+            false,
+        )
+        .map_err(ReceiveError::Send)?;
+        loop {
+            let message = self.reader.read_message()?;
+            if let Ok(rejection) = RejectedServerMessage::try_from(message) {
+                return Err(ReceiveError::Rejected(rejection));
+            }
+            if let Ok(ChecksumServerMessage::ChecksumsEnabled) =
+                ChecksumServerMessage::try_from(message)
+            {
+                self.checksums = true;
+                return Ok(self);
+            }
+        }
+    }
+
+    /// creates a lobby played under the given ruleset, with the given id if there is one, or one
+    /// the server generates otherwise.
+    pub fn new_lobby(
+        mut self,
+        ruleset: Ruleset,
+        custom_id: Option<LobbyId>,
+    ) -> io::Result<ClientSession<AwaitingNewLobby, R, W>> {
+        self.send(AwaitingOpenClientMessage::NewLobbyWithRuleset {
+            ruleset,
+            capabilities: Capabilities::ALL,
+            custom_id,
+            cosmetics: self.cosmetics,
+        })?;
+        Ok(self.into_state())
+    }
+
+    /// creates a co-op survival lobby running at the given tick interval, with the given id if
+    /// there is one, or one the server generates otherwise.
+    pub fn new_coop_lobby(
+        mut self,
+        tick_interval_ms: u16,
+        custom_id: Option<LobbyId>,
+    ) -> io::Result<ClientSession<AwaitingNewLobby, R, W>> {
+        self.send(AwaitingOpenClientMessage::NewCoopLobby {
+            tick_interval_ms,
+            capabilities: Capabilities::ALL,
+            custom_id,
+            cosmetics: self.cosmetics,
+        })?;
+        Ok(self.into_state())
+    }
+
+    /// asks the server about itself. the session can still create or join a lobby once the
+    /// status has arrived.
+    pub fn server_status(mut self) -> io::Result<ClientSession<AwaitingServerStatus, R, W>> {
+        self.send(AwaitingOpenClientMessage::GetServerStatus)?;
+        Ok(self.into_state())
+    }
+
+    /// asks for a page of the lobbies waiting for an opponent. like the server's status, the
+    /// session can still create or join a lobby once the page has arrived.
+    pub fn browse_lobbies(
+        mut self,
+        page: u16,
+    ) -> io::Result<ClientSession<AwaitingLobbyPage, R, W>> {
+        self.send(AwaitingOpenClientMessage::BrowseLobbies {
+            page,
+            capabilities: Capabilities::ALL,
+        })?;
+        Ok(self.into_state())
+    }
+
+    pub fn join_lobby(
+        mut self,
+        lobby_id: LobbyId,
+    ) -> io::Result<ClientSession<AwaitingJoinLobby, R, W>> {
+        self.send(AwaitingOpenClientMessage::JoinLobby {
+            lobby_id,
+            capabilities: Capabilities::ALL,
+            cosmetics: self.cosmetics,
+        })?;
+        Ok(self.into_state())
+    }
+
+    /// creates a tournament for the given number of players, taking the first seat in it.
+    pub fn new_tournament(
+        mut self,
+        players: u8,
+    ) -> io::Result<ClientSession<AwaitingTournament, R, W>> {
+        self.send(AwaitingOpenClientMessage::NewTournament {
+            players,
+            capabilities: Capabilities::ALL,
+            cosmetics: self.cosmetics,
+        })?;
+        Ok(self.into_state())
+    }
+
+    /// creates a league for the given number of players, taking the first seat in it. leagues are
+    /// joined with [`Self::join_tournament`].
+    pub fn new_league(
+        mut self,
+        players: u8,
+    ) -> io::Result<ClientSession<AwaitingTournament, R, W>> {
+        self.send(AwaitingOpenClientMessage::NewLeague {
+            players,
+            capabilities: Capabilities::ALL,
+            cosmetics: self.cosmetics,
+        })?;
+        Ok(self.into_state())
+    }
+
+    pub fn join_tournament(
+        mut self,
+        tournament_id: LobbyId,
+    ) -> io::Result<ClientSession<AwaitingTournament, R, W>> {
+        self.send(AwaitingOpenClientMessage::JoinTournament {
+            tournament_id,
+            capabilities: Capabilities::ALL,
+            cosmetics: self.cosmetics,
+        })?;
+        Ok(self.into_state())
+    }
+
+    /// queues for a match against whoever else is looking for one.
+    pub fn find_match(mut self) -> io::Result<ClientSession<AwaitingMatch, R, W>> {
+        self.send(AwaitingOpenClientMessage::FindMatch {
+            capabilities: Capabilities::ALL,
+            cosmetics: self.cosmetics,
+        })?;
+        Ok(self.into_state())
+    }
+
+    /// starts a practice lobby against a wall, which is seated like a match found in the queue.
+    pub fn practice(
+        mut self,
+        tick_interval_ms: u16,
+    ) -> io::Result<ClientSession<AwaitingMatch, R, W>> {
+        self.send(AwaitingOpenClientMessage::Practice {
+            tick_interval_ms,
+            capabilities: Capabilities::ALL,
+        })?;
+        Ok(self.into_state())
+    }
+}
+
+impl<R: Read, W: Write> ClientSession<AwaitingServerStatus, R, W> {
+    pub fn await_status(
+        mut self,
+    ) -> Result<(ServerStatus, ClientSession<AwaitingOpen, R, W>), ReceiveError> {
+        let status = match self.receive()? {
+            AwaitingServerStatusServerMessage::ServerStatus {
+                players_online,
+                open_lobbies,
+                version,
+                uptime_secs,
+            } => ServerStatus {
+                players_online,
+                open_lobbies,
+                version,
+                uptime_secs,
+            },
+        };
+        Ok((status, self.into_state()))
+    }
+}
+
+impl<R: Read, W: Write> ClientSession<AwaitingLobbyPage, R, W> {
+    pub fn await_page(
+        mut self,
+    ) -> Result<(LobbyPage, ClientSession<AwaitingOpen, R, W>), ReceiveError> {
+        let mut lobbies = Vec::new();
+        loop {
+            match self.receive()? {
+                AwaitingLobbyPageServerMessage::LobbyListed {
+                    lobby_id,
+                    tick_interval_ms,
+                    age_secs,
+                } => lobbies.push(ListedLobby {
+                    lobby_id,
+                    tick_interval_ms,
+                    age_secs,
+                }),
+                AwaitingLobbyPageServerMessage::LobbyPageEnd { page, pages } => {
+                    let page = LobbyPage {
+                        lobbies,
+                        page,
+                        pages,
+                    };
+                    return Ok((page, self.into_state()));
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read, W: Write> ClientSession<AwaitingNewLobby, R, W> {
+    /// waits for the server to create the lobby, returning its id and the dimensions of its
+    /// games.
+    pub fn await_lobby(mut self) -> Result<NewLobbyOutcome<R, W>, ReceiveError> {
+        Ok(match self.receive()? {
+            AwaitingNewLobbyServerMessage::NewLobbyCreated {
+                lobby_id,
+                dimensions,
+            } => NewLobbyOutcome::Created {
+                lobby_id,
+                dimensions,
+                session: self.into_state(),
+            },
+            AwaitingNewLobbyServerMessage::IdTaken => NewLobbyOutcome::IdTaken,
+            AwaitingNewLobbyServerMessage::TooManyLobbies => NewLobbyOutcome::TooManyLobbies,
+            AwaitingNewLobbyServerMessage::LobbyIncompatible => NewLobbyOutcome::LobbyIncompatible,
+        })
+    }
+}
+
+impl<R: Read, W: Write> ClientSession<AwaitingOpponentJoin, R, W> {
+    /// waits for an opponent to join, or for the lobby to come close to expiring.
+    pub fn await_opponent(mut self) -> Result<OpponentJoinOutcome<R, W>, ReceiveError> {
+        Ok(match self.receive()? {
+            AwaitingOpponentJoinServerMessage::OpponentJoined { ready_timeout_secs } => {
+                OpponentJoinOutcome::Joined {
+                    ready_timeout_secs,
+                    session: self.into_state(),
+                }
+            }
+            AwaitingOpponentJoinServerMessage::LobbyExpiringSoon { seconds } => {
+                OpponentJoinOutcome::ExpiringSoon {
+                    seconds,
+                    session: self,
+                }
+            }
+            AwaitingOpponentJoinServerMessage::LobbyExpired => OpponentJoinOutcome::Expired,
+        })
+    }
+
+    /// closes the lobby. whatever the server sent before it heard is skipped, and the session is
+    /// resynced to the state the server put the client in, which is open.
+    pub fn leave_lobby(mut self) -> Result<Resynced<R, W>, ReceiveError> {
+        self.send(AwaitingOpponentJoinClientMessage::LeaveLobby)
+            .map_err(ReceiveError::Send)?;
+        self.resync()
+    }
+}
+
+impl<R: Read, W: Write> ClientSession<AwaitingMatch, R, W> {
+    /// waits for an opponent to be found, which may take as long as nobody else is queued.
+    pub fn await_match(mut self) -> Result<FoundMatch<R, W>, ReceiveError> {
+        Ok(match self.receive()? {
+            AwaitingMatchServerMessage::MatchFound {
+                lobby_id,
+                side,
+                ready_timeout_secs,
+                dimensions,
+            } => FoundMatch {
+                lobby_id,
+                side,
+                ready_timeout_secs,
+                dimensions,
+                session: self.into_state(),
+            },
+        })
+    }
+}
+
+impl<R: Read, W: Write> ClientSession<AwaitingJoinLobby, R, W> {
+    pub fn await_join(mut self) -> Result<JoinLobbyOutcome<R, W>, ReceiveError> {
+        Ok(match self.receive()? {
+            AwaitingJoinLobbyServerMessage::JoinedLobby {
+                ready_timeout_secs,
+                dimensions,
+            } => JoinLobbyOutcome::Joined {
+                ready_timeout_secs,
+                dimensions,
+                session: self.into_state(),
+            },
+            AwaitingJoinLobbyServerMessage::LobbyFull => JoinLobbyOutcome::LobbyFull,
+            AwaitingJoinLobbyServerMessage::LobbyNotFound => JoinLobbyOutcome::LobbyNotFound,
+            AwaitingJoinLobbyServerMessage::LobbyIncompatible => {
+                JoinLobbyOutcome::LobbyIncompatible
+            }
+        })
+    }
+}
+
+impl<R: Read, W: Write> ClientSession<AwaitingTournament, R, W> {
+    /// moves to the ready state of the player's next match, once
+    /// [`AwaitingTournamentServerMessage::MatchReady`] has been received.
+    pub fn start_match(self) -> ClientSession<AwaitingReady, R, W> {
+        self.into_state()
+    }
+}
+
+impl<R: Read, W: Write> ClientSession<AwaitingReady, R, W> {
+    /// moves to the playing state, once [`AwaitingReadyServerMessage::GameStarted`] or
+    /// [`AwaitingReadyServerMessage::HandicappedGameStarted`] has been received.
+    pub fn start_game(self) -> ClientSession<Playing, R, W> {
+        self.into_state()
+    }
+
+    /// returns to the tournament once one of its matches is over: its game has been played, the
+    /// opponent left, or the lobby timed out.
+    pub fn end_match(self) -> ClientSession<AwaitingTournament, R, W> {
+        self.into_state()
+    }
+
+    /// moves back to waiting for an opponent, as the lobby's host, once
+    /// [`AwaitingReadyServerMessage::OpponentLeftLobby`] or
+    /// [`AwaitingReadyServerMessage::PromotedToHost`] has been received.
+    pub fn reopen_lobby(self) -> ClientSession<AwaitingOpponentJoin, R, W> {
+        self.into_state()
+    }
+
+    /// leaves the lobby before its game has started. like [`Self::resync`], whatever the server
+    /// sent before it heard is skipped. the server puts the client back in the open state, unless
+    /// the lobby is a tournament match, which can't be left.
+    pub fn leave_lobby(mut self) -> Result<Resynced<R, W>, ReceiveError> {
+        self.send(AwaitingReadyClientMessage::LeaveLobby)
+            .map_err(ReceiveError::Send)?;
+        self.resync()
+    }
+}
+
+impl<R: Read, W: Write> ClientSession<Playing, R, W> {
+    /// returns to the ready state, once a message ending the game has been received.
+    pub fn end_game(self) -> ClientSession<AwaitingReady, R, W> {
+        self.into_state()
+    }
+}
+
+fn write_client_message<W: Write, M: WriteInto>(
+    writer: &mut W,
+    message: M,
+    checksums: bool,
+) -> io::Result<()> {
+    let mut frame = [0; MAX_CLIENT_MESSAGE_SIZE + CHECKSUM_SIZE + 1];
+    let mut n = message.write_into(&mut frame);
+    if checksums {
+        let checksum = checksum(&frame[..n]);
+        frame[n..n + CHECKSUM_SIZE].copy_from_slice(&checksum);
+        n += CHECKSUM_SIZE;
+    }
+    frame[n] = CLIENT_MESSAGE_DELIMITER;
+    writer.write_all(&frame[..=n])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        checksum::checksum,
+        client_msg::{
+            AwaitingOpponentJoinClientMessage, AwaitingReadyClientMessage, PlayingClientMessage,
+        },
+        game_state::{GameDimensions, Handicaps, Modifier, MoveDirection, Ruleset, Side},
+        server_msg::{
+            AwaitingJoinLobbyServerMessage, AwaitingLobbyPageServerMessage,
+            AwaitingMatchServerMessage, AwaitingNewLobbyServerMessage,
+            AwaitingOpponentJoinServerMessage, AwaitingReadyServerMessage,
+            AwaitingServerStatusServerMessage, AwaitingTournamentServerMessage,
+            ChecksumServerMessage, PlayingServerMessage, ProtocolState, RejectedServerMessage,
+            ResyncServerMessage, ServerVersion, SERVER_MESSAGE_DELIMITER,
+        },
+        session::{
+            AwaitingReady, ClientSession, JoinLobbyOutcome, ListedLobby, LobbyPage,
+            NewLobbyOutcome, OpponentJoinOutcome, Playing, ReceiveError, Resynced, ServerStatus,
+        },
+        DeserializeMessageError,
+    };
+    use std::io::{self, ErrorKind, Read};
+
+    const RULESET: Ruleset = Ruleset {
+        tick_interval_ms: 100,
+        match_length: None,
+        handicaps: Handicaps::NONE,
+        modifier: Modifier::Classic,
+    };
+
+    /// frames server messages as they would arrive over the wire.
+    fn server_bytes(messages: Vec<Vec<u8>>) -> Vec<u8> {
+        messages
+            .into_iter()
+            .flat_map(|mut message| {
+                message.push(SERVER_MESSAGE_DELIMITER);
+                message
+            })
+            .collect()
+    }
+
+    #[test]
+    fn host_session() {
+        let incoming = server_bytes(vec![
+            AwaitingNewLobbyServerMessage::NewLobbyCreated {
+                lobby_id: "ABCD".parse().unwrap(),
+                dimensions: GameDimensions::DEFAULT,
+            }
+            .into(),
+            AwaitingOpponentJoinServerMessage::LobbyExpiringSoon { seconds: 60 }.into(),
+            AwaitingOpponentJoinServerMessage::OpponentJoined {
+                ready_timeout_secs: 300,
+            }
+            .into(),
+            AwaitingReadyServerMessage::LobbyRuleset { ruleset: RULESET }.into(),
+            AwaitingReadyServerMessage::YouReadied.into(),
+            AwaitingReadyServerMessage::GameStarted {
+                tick_interval_ms: 100,
+            }
+            .into(),
+            PlayingServerMessage::YouWon.into(),
+        ]);
+        let session = ClientSession::new(incoming.as_slice(), Vec::new());
+        let session = session.new_lobby(RULESET, None).unwrap();
+        let (lobby_id, dimensions, session) = match session.await_lobby().unwrap() {
+            NewLobbyOutcome::Created {
+                lobby_id,
+                dimensions,
+                session,
+            } => (lobby_id, dimensions, session),
+            NewLobbyOutcome::IdTaken
+            | NewLobbyOutcome::TooManyLobbies
+            | NewLobbyOutcome::LobbyIncompatible => panic!("expected the lobby to be created"),
+        };
+        assert_eq!(lobby_id.as_str(), "ABCD");
+        assert_eq!(dimensions, GameDimensions::DEFAULT);
+        let mut session = match session.await_opponent().unwrap() {
+            OpponentJoinOutcome::ExpiringSoon { seconds, session } => {
+                assert_eq!(seconds, 60);
+                session
+            }
+            _ => panic!("expected an expiry warning"),
+        };
+        session
+            .send(AwaitingOpponentJoinClientMessage::StillHere)
+            .unwrap();
+        let (ready_timeout_secs, mut session) = match session.await_opponent().unwrap() {
+            OpponentJoinOutcome::Joined {
+                ready_timeout_secs,
+                session,
+            } => (ready_timeout_secs, session),
+            _ => panic!("expected an opponent to join"),
+        };
+        assert_eq!(ready_timeout_secs, 300);
+        assert_eq!(
+            session.receive().unwrap(),
+            AwaitingReadyServerMessage::LobbyRuleset { ruleset: RULESET }
+        );
+        session.send(AwaitingReadyClientMessage::Ready).unwrap();
+        assert_eq!(
+            session.receive().unwrap(),
+            AwaitingReadyServerMessage::YouReadied
+        );
+        assert_eq!(
+            session.receive().unwrap(),
+            AwaitingReadyServerMessage::GameStarted {
+                tick_interval_ms: 100
+            }
+        );
+        let mut session = session.start_game();
+        session
+            .send(PlayingClientMessage::MoveIntent {
+                direction: MoveDirection::Up,
+            })
+            .unwrap();
+        assert_eq!(session.receive().unwrap(), PlayingServerMessage::YouWon);
+        let session = session.end_game();
+        assert_eq!(
+            session.writer,
+            [
+                vec![8, 0, 100, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 129, 127, 0, 0xFF],
+                vec![3 << 4, 0xFF],
+                vec![1 << 4, 0xFF],
+                vec![2 << 4, 1, 0xFF],
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn join_session() {
+        let incoming = server_bytes(vec![AwaitingJoinLobbyServerMessage::JoinedLobby {
+            ready_timeout_secs: 60,
+            dimensions: GameDimensions::DEFAULT,
+        }
+        .into()]);
+        let session = ClientSession::new(incoming.as_slice(), Vec::new());
+        let session = session.join_lobby("ABCD".parse().unwrap()).unwrap();
+        match session.await_join().unwrap() {
+            JoinLobbyOutcome::Joined {
+                ready_timeout_secs,
+                dimensions,
+                session,
+            } => {
+                assert_eq!(ready_timeout_secs, 60);
+                assert_eq!(dimensions, GameDimensions::DEFAULT);
+                assert_eq!(session.writer, [1, b'A', b'B', b'C', b'D', 129, 127, 0xFF]);
+            }
+            _ => panic!("expected to join the lobby"),
+        }
+    }
+
+    #[test]
+    fn quick_match_session() {
+        let incoming = server_bytes(vec![AwaitingMatchServerMessage::MatchFound {
+            lobby_id: "ABCD".parse().unwrap(),
+            side: Side::Right,
+            ready_timeout_secs: 60,
+            dimensions: GameDimensions::DEFAULT,
+        }
+        .into()]);
+        let session = ClientSession::new(incoming.as_slice(), Vec::new());
+        let found_match = session.find_match().unwrap().await_match().unwrap();
+        assert_eq!(found_match.lobby_id.as_str(), "ABCD");
+        assert!(found_match.side == Side::Right);
+        assert_eq!(found_match.ready_timeout_secs, 60);
+        assert_eq!(found_match.dimensions, GameDimensions::DEFAULT);
+        assert_eq!(found_match.session.writer, [3, 129, 127, 0xFF]);
+    }
+
+    #[test]
+    fn practice_session() {
+        let incoming = server_bytes(vec![AwaitingMatchServerMessage::MatchFound {
+            lobby_id: "WALL".parse().unwrap(),
+            side: Side::Left,
+            ready_timeout_secs: 60,
+            dimensions: GameDimensions::DEFAULT,
+        }
+        .into()]);
+        let session = ClientSession::new(incoming.as_slice(), Vec::new());
+        let found_match = session.practice(100).unwrap().await_match().unwrap();
+        assert_eq!(found_match.lobby_id.as_str(), "WALL");
+        assert!(found_match.side == Side::Left);
+        assert_eq!(found_match.session.writer, [9, 0, 100, 129, 127, 0xFF]);
+    }
+
+    #[test]
+    fn tournament_session() {
+        let incoming = server_bytes(vec![
+            AwaitingTournamentServerMessage::JoinedTournament {
+                tournament_id: "ABCD".parse().unwrap(),
+                seat: 1,
+                players: 2,
+            }
+            .into(),
+            AwaitingTournamentServerMessage::MatchReady {
+                lobby_id: "EFGH".parse().unwrap(),
+                side: Side::Right,
+                ready_timeout_secs: 60,
+                dimensions: GameDimensions::DEFAULT,
+            }
+            .into(),
+            AwaitingReadyServerMessage::OpponentLeft.into(),
+            AwaitingTournamentServerMessage::TournamentWon { seat: 1 }.into(),
+        ]);
+        let session = ClientSession::new(incoming.as_slice(), Vec::new());
+        let mut session = session.join_tournament("ABCD".parse().unwrap()).unwrap();
+        assert!(matches!(
+            session.receive().unwrap(),
+            AwaitingTournamentServerMessage::JoinedTournament { seat: 1, .. }
+        ));
+        assert!(matches!(
+            session.receive().unwrap(),
+            AwaitingTournamentServerMessage::MatchReady { .. }
+        ));
+        let mut session = session.start_match();
+        assert_eq!(
+            session.receive().unwrap(),
+            AwaitingReadyServerMessage::OpponentLeft
+        );
+        let mut session = session.end_match();
+        assert_eq!(
+            session.receive().unwrap(),
+            AwaitingTournamentServerMessage::TournamentWon { seat: 1 }
+        );
+        assert_eq!(session.writer, [5, b'A', b'B', b'C', b'D', 129, 127, 0xFF]);
+    }
+
+    #[test]
+    fn status_then_new_lobby() {
+        let version = ServerVersion {
+            major: 0,
+            minor: 1,
+            patch: 0,
+        };
+        let incoming = server_bytes(vec![
+            AwaitingServerStatusServerMessage::ServerStatus {
+                players_online: 3,
+                open_lobbies: 1,
+                version,
+                uptime_secs: 86400,
+            }
+            .into(),
+            AwaitingNewLobbyServerMessage::NewLobbyCreated {
+                lobby_id: "ABCD".parse().unwrap(),
+                dimensions: GameDimensions::DEFAULT,
+            }
+            .into(),
+        ]);
+        let session = ClientSession::new(incoming.as_slice(), Vec::new());
+        let (status, session) = session.server_status().unwrap().await_status().unwrap();
+        assert_eq!(
+            status,
+            ServerStatus {
+                players_online: 3,
+                open_lobbies: 1,
+                version,
+                uptime_secs: 86400,
+            }
+        );
+        let session = session.new_lobby(RULESET, None).unwrap();
+        let (lobby_id, session) = match session.await_lobby().unwrap() {
+            NewLobbyOutcome::Created {
+                lobby_id, session, ..
+            } => (lobby_id, session),
+            NewLobbyOutcome::IdTaken
+            | NewLobbyOutcome::TooManyLobbies
+            | NewLobbyOutcome::LobbyIncompatible => panic!("expected the lobby to be created"),
+        };
+        assert_eq!(lobby_id.as_str(), "ABCD");
+        assert_eq!(
+            session.writer,
+            [2, 0xFF, 8, 0, 100, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 129, 127, 0, 0xFF]
+        );
+    }
+
+    #[test]
+    fn custom_id_taken() {
+        let incoming = server_bytes(vec![AwaitingNewLobbyServerMessage::IdTaken.into()]);
+        let session = ClientSession::new(incoming.as_slice(), Vec::new());
+        let session = session
+            .new_lobby(RULESET, Some("GAME".parse().unwrap()))
+            .unwrap();
+        assert_eq!(
+            session.writer,
+            [8, 0, 100, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 129, 127, b'G', b'A', b'M', b'E', 0xFF]
+        );
+        assert!(matches!(
+            session.await_lobby().unwrap(),
+            NewLobbyOutcome::IdTaken
+        ));
+    }
+
+    #[test]
+    fn too_many_lobbies() {
+        let incoming = server_bytes(vec![AwaitingNewLobbyServerMessage::TooManyLobbies.into()]);
+        let session = ClientSession::new(incoming.as_slice(), Vec::new());
+        let session = session.new_lobby(RULESET, None).unwrap();
+        assert!(matches!(
+            session.await_lobby().unwrap(),
+            NewLobbyOutcome::TooManyLobbies
+        ));
+    }
+
+    #[test]
+    fn new_lobby_incompatible() {
+        let incoming = server_bytes(vec![AwaitingNewLobbyServerMessage::LobbyIncompatible.into()]);
+        let session = ClientSession::new(incoming.as_slice(), Vec::new());
+        let session = session.new_lobby(RULESET, None).unwrap();
+        assert!(matches!(
+            session.await_lobby().unwrap(),
+            NewLobbyOutcome::LobbyIncompatible
+        ));
+    }
+
+    #[test]
+    fn rejected() {
+        let incoming = server_bytes(vec![RejectedServerMessage::TooManyConnections.into()]);
+        let session = ClientSession::new(incoming.as_slice(), Vec::new());
+        let session = session.new_lobby(RULESET, None).unwrap();
+        assert!(matches!(
+            session.await_lobby(),
+            Err(ReceiveError::Rejected(
+                RejectedServerMessage::TooManyConnections
+            ))
+        ));
+    }
+
+    /// returns each of its chunks from a separate read, timing out on the gaps between them.
+    struct SlowReader(Vec<Option<Vec<u8>>>);
+
+    impl Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Ok(0);
+            }
+            match self.0.remove(0) {
+                Some(chunk) => {
+                    buf[..chunk.len()].copy_from_slice(&chunk);
+                    Ok(chunk.len())
+                }
+                None => Err(ErrorKind::WouldBlock.into()),
+            }
+        }
+    }
+
+    #[test]
+    fn receive_after_timeout() {
+        let incoming = server_bytes(vec![
+            PlayingServerMessage::BallSpedUp { speed_level: 2 }.into(),
+            PlayingServerMessage::YouWon.into(),
+        ]);
+        let (first, rest) = incoming.split_at(1);
+        let reader = SlowReader(vec![None, Some(first.to_vec()), None, Some(rest.to_vec())]);
+        let mut session: ClientSession<Playing, _, _> =
+            ClientSession::new(reader, Vec::new()).into_state();
+        assert!(session.receive().unwrap_err().is_timeout());
+        // the part of the message that arrived before the timeout isn't lost.
+        assert!(session.receive().unwrap_err().is_timeout());
+        assert_eq!(
+            session.receive().unwrap(),
+            PlayingServerMessage::BallSpedUp { speed_level: 2 }
+        );
+        assert_eq!(session.receive().unwrap(), PlayingServerMessage::YouWon);
+        assert!(!session.receive().unwrap_err().is_timeout());
+    }
+
+    #[test]
+    fn coop_session() {
+        let incoming = server_bytes(vec![AwaitingNewLobbyServerMessage::NewLobbyCreated {
+            lobby_id: "COOP".parse().unwrap(),
+            dimensions: GameDimensions::DEFAULT,
+        }
+        .into()]);
+        let session = ClientSession::new(incoming.as_slice(), Vec::new());
+        let session = session.new_coop_lobby(60, None).unwrap();
+        assert_eq!(session.writer, [10, 0, 60, 129, 127, 0, 0xFF]);
+        assert!(matches!(
+            session.await_lobby().unwrap(),
+            NewLobbyOutcome::Created { lobby_id, .. } if lobby_id.as_str() == "COOP"
+        ));
+    }
+
+    #[test]
+    fn browse_then_join() {
+        let lobby_id = "ABCD".parse().unwrap();
+        let incoming = server_bytes(vec![
+            AwaitingLobbyPageServerMessage::LobbyListed {
+                lobby_id,
+                tick_interval_ms: 100,
+                age_secs: 42,
+            }
+            .into(),
+            AwaitingLobbyPageServerMessage::LobbyPageEnd { page: 1, pages: 2 }.into(),
+            AwaitingJoinLobbyServerMessage::LobbyFull.into(),
+        ]);
+        let session = ClientSession::new(incoming.as_slice(), Vec::new());
+        let (page, session) = session.browse_lobbies(1).unwrap().await_page().unwrap();
+        assert_eq!(
+            page,
+            LobbyPage {
+                lobbies: vec![ListedLobby {
+                    lobby_id,
+                    tick_interval_ms: 100,
+                    age_secs: 42,
+                }],
+                page: 1,
+                pages: 2,
+            }
+        );
+        let session = session.join_lobby(lobby_id).unwrap();
+        assert_eq!(
+            session.writer,
+            [7, 0, 1, 129, 127, 0xFF, 1, b'A', b'B', b'C', b'D', 129, 127, 0xFF]
+        );
+        assert!(matches!(
+            session.await_join().unwrap(),
+            JoinLobbyOutcome::LobbyFull
+        ));
+    }
+
+    #[test]
+    fn join_full_lobby() {
+        let incoming = server_bytes(vec![AwaitingJoinLobbyServerMessage::LobbyFull.into()]);
+        let session = ClientSession::new(incoming.as_slice(), Vec::new());
+        let session = session.join_lobby("ABCD".parse().unwrap()).unwrap();
+        assert!(matches!(
+            session.await_join().unwrap(),
+            JoinLobbyOutcome::LobbyFull
+        ));
+    }
+
+    #[test]
+    fn message_from_wrong_state() {
+        let incoming = server_bytes(vec![PlayingServerMessage::YouWon.into()]);
+        let session = ClientSession::new(incoming.as_slice(), Vec::new());
+        let session = session.join_lobby("ABCD".parse().unwrap()).unwrap();
+        assert!(matches!(
+            session.await_join(),
+            Err(ReceiveError::Deserialize(
+                DeserializeMessageError::InvalidState {
+                    expected: 1,
+                    actual: 4
+                }
+            ))
+        ));
+    }
+
+    #[test]
+    fn resync_after_message_from_wrong_state() {
+        let incoming = server_bytes(vec![
+            AwaitingReadyServerMessage::OpponentReadied.into(),
+            // still in flight when the resync was asked for.
+            AwaitingReadyServerMessage::YouReadied.into(),
+            ResyncServerMessage::FullState {
+                state: ProtocolState::AwaitingReady {
+                    you_ready: true,
+                    opponent_ready: true,
+                },
+            }
+            .into(),
+            AwaitingReadyServerMessage::GameStarted {
+                tick_interval_ms: 100,
+            }
+            .into(),
+        ]);
+        let mut session =
+            ClientSession::new(incoming.as_slice(), Vec::new()).into_state::<Playing>();
+        assert!(matches!(
+            session.receive(),
+            Err(ReceiveError::Deserialize(
+                DeserializeMessageError::InvalidState {
+                    expected: 4,
+                    actual: 3
+                }
+            ))
+        ));
+        let mut session = match session.resync().unwrap() {
+            Resynced::AwaitingReady {
+                you_ready,
+                opponent_ready,
+                session,
+            } => {
+                assert!(you_ready);
+                assert!(opponent_ready);
+                session
+            }
+            _ => panic!("expected to be resynced to the ready state"),
+        };
+        assert_eq!(
+            session.receive().unwrap(),
+            AwaitingReadyServerMessage::GameStarted {
+                tick_interval_ms: 100
+            }
+        );
+        assert_eq!(session.writer, [4 << 4, 0xFF]);
+    }
+
+    #[test]
+    fn leave_lobby() {
+        let incoming = server_bytes(vec![
+            AwaitingReadyServerMessage::OpponentLeftLobby.into(),
+            // sent before the server heard the host had left.
+            AwaitingOpponentJoinServerMessage::OpponentJoined {
+                ready_timeout_secs: 60,
+            }
+            .into(),
+            ResyncServerMessage::FullState {
+                state: ProtocolState::Open,
+            }
+            .into(),
+        ]);
+        let mut session =
+            ClientSession::new(incoming.as_slice(), Vec::new()).into_state::<AwaitingReady>();
+        assert_eq!(
+            session.receive().unwrap(),
+            AwaitingReadyServerMessage::OpponentLeftLobby
+        );
+        let session = session.reopen_lobby();
+        match session.leave_lobby().unwrap() {
+            Resynced::Open(session) => {
+                assert_eq!(session.writer, [3 << 4 | 1, 0xFF, 4 << 4, 0xFF]);
+            }
+            _ => panic!("expected to be back in the open state"),
+        }
+    }
+
+    #[test]
+    fn checksums() {
+        let message: Vec<u8> = AwaitingReadyServerMessage::YouReadied.into();
+        let checksummed = [&message[..], &checksum(&message)].concat();
+        // a different message arriving with the checksum of the one sent.
+        let mut corrupted = checksummed.clone();
+        corrupted[0] = Vec::<u8>::from(AwaitingReadyServerMessage::OpponentReadied)[0];
+        let incoming = server_bytes(vec![
+            ChecksumServerMessage::ChecksumsEnabled.into(),
+            checksummed,
+            corrupted,
+        ]);
+        let mut session = ClientSession::new(incoming.as_slice(), Vec::new())
+            .enable_checksums()
+            .unwrap()
+            .into_state::<AwaitingReady>();
+        assert_eq!(
+            session.receive().unwrap(),
+            AwaitingReadyServerMessage::YouReadied
+        );
+        assert!(matches!(
+            session.receive(),
+            Err(ReceiveError::ChecksumMismatch)
+        ));
+        session.send(AwaitingReadyClientMessage::Ready).unwrap();
+        assert_eq!(
+            session.writer,
+            [&[5 << 4, 0xFF, 1 << 4][..], &checksum(&[1 << 4]), &[0xFF]].concat()
+        );
+    }
+}
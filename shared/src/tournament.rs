@@ -0,0 +1,327 @@
+use core::ops::Range;
+
+use crate::{DeserializeMessageError, FieldCodec};
+
+/// the most players a tournament can have, so that its whole bracket fits in one message.
+pub const MAX_TOURNAMENT_PLAYERS: u8 = 8;
+
+/// tournaments are single elimination, so every round must halve the players evenly.
+pub fn is_valid_tournament_size(players: u8) -> bool {
+    (2..=MAX_TOURNAMENT_PLAYERS).contains(&players) && players.is_power_of_two()
+}
+
+/// the number of rounds a tournament of the given size is played over.
+pub fn rounds(players: u8) -> u8 {
+    players.trailing_zeros() as u8
+}
+
+/// the seats whose winner the given seat plays in the round after it has won `wins` matches.
+/// seats are paired in order, so the bracket is fixed once every seat is taken.
+pub fn opponent_seats(seat: u8, wins: u8) -> Range<u8> {
+    let start = (seat >> wins ^ 1) << wins;
+    start..start + (1 << wins)
+}
+
+/// leagues are round robin, so can be played with any number of players.
+pub fn is_valid_league_size(players: u8) -> bool {
+    (2..=MAX_TOURNAMENT_PLAYERS).contains(&players)
+}
+
+/// the number of rounds a league of the given size is played over. with an odd number of players,
+/// one player sits out each round.
+pub fn league_rounds(players: u8) -> u8 {
+    players - 1 + players % 2
+}
+
+/// the seat the given seat plays in the given round of a league, or `None` if it sits out that
+/// round. seats are paired by the circle method, so everyone plays everyone once.
+pub fn league_opponent(seat: u8, round: u8, players: u8) -> Option<u8> {
+    // with an odd number of players, whoever is paired with the extra seat sits out.
+    let seats = players + players % 2;
+    let last = seats - 1;
+    let opponent = if seat == last {
+        round
+    } else if seat == round {
+        last
+    } else {
+        (2 * round + last - seat) % last
+    };
+    Some(opponent).filter(|&opponent| opponent < players)
+}
+
+/// how far a seat's player has got in a tournament.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BracketSeat {
+    pub wins: u8,
+    /// the player lost a match, or left.
+    pub eliminated: bool,
+}
+
+/// the progress of every seat in a tournament. seats beyond the tournament's size are unused.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Bracket {
+    pub seats: [BracketSeat; MAX_TOURNAMENT_PLAYERS as usize],
+}
+
+impl Bracket {
+    /// the seat that won the given seats' part of the bracket, having won `wins` matches to do so,
+    /// if it has been decided yet.
+    pub fn winner(&self, seats: Range<u8>, wins: u8) -> Option<u8> {
+        seats
+            .into_iter()
+            .find(|&seat| self.seats[seat as usize].wins >= wins)
+    }
+}
+
+impl FieldCodec<Bracket> for Bracket {
+    const SIZE: usize = MAX_TOURNAMENT_PLAYERS as usize;
+
+    fn write(value: &Bracket, buf: &mut [u8]) {
+        for (byte, seat) in buf.iter_mut().zip(value.seats) {
+            *byte = seat.wins | (seat.eliminated as u8) << 3;
+        }
+    }
+
+    fn read(bytes: &[u8]) -> Result<Bracket, DeserializeMessageError> {
+        let mut bracket = Bracket::default();
+        for (seat, &byte) in bracket.seats.iter_mut().zip(bytes) {
+            let wins = byte & 0b111;
+            if byte >> 4 != 0 || wins > rounds(MAX_TOURNAMENT_PLAYERS) {
+                return Err(DeserializeMessageError::InvalidBracket);
+            }
+            *seat = BracketSeat {
+                wins,
+                eliminated: byte & 0b1000 != 0,
+            };
+        }
+        Ok(bracket)
+    }
+}
+
+/// a seat's record in a league.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Standing {
+    pub wins: u8,
+    pub losses: u8,
+    /// the player left, forfeiting the rest of their matches.
+    pub left: bool,
+}
+
+/// the record of every seat in a league, and how many of its rounds have been played. seats
+/// beyond the league's size are unused.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Standings {
+    pub round: u8,
+    pub seats: [Standing; MAX_TOURNAMENT_PLAYERS as usize],
+}
+
+impl Standings {
+    /// the league's seats from first to last: by most wins, then fewest losses, then earliest
+    /// seat. only the first `players` seats are ordered, the rest are left as they are.
+    pub fn table(&self, players: u8) -> [u8; MAX_TOURNAMENT_PLAYERS as usize] {
+        let mut table = [0, 1, 2, 3, 4, 5, 6, 7];
+        table[..players as usize].sort_unstable_by_key(|&seat| {
+            let standing = self.seats[seat as usize];
+            (u8::MAX - standing.wins, standing.losses, seat)
+        });
+        table
+    }
+}
+
+impl FieldCodec<Standings> for Standings {
+    const SIZE: usize = 1 + MAX_TOURNAMENT_PLAYERS as usize;
+
+    fn write(value: &Standings, buf: &mut [u8]) {
+        buf[0] = value.round;
+        for (byte, seat) in buf[1..].iter_mut().zip(value.seats) {
+            *byte = seat.wins | seat.losses << 3 | (seat.left as u8) << 6;
+        }
+    }
+
+    fn read(bytes: &[u8]) -> Result<Standings, DeserializeMessageError> {
+        let max_rounds = league_rounds(MAX_TOURNAMENT_PLAYERS);
+        if bytes[0] > max_rounds {
+            return Err(DeserializeMessageError::InvalidStandings);
+        }
+        let mut standings = Standings {
+            round: bytes[0],
+            ..Standings::default()
+        };
+        for (seat, &byte) in standings.seats.iter_mut().zip(&bytes[1..]) {
+            let wins = byte & 0b111;
+            let losses = byte >> 3 & 0b111;
+            if byte >> 7 != 0 || wins + losses > max_rounds {
+                return Err(DeserializeMessageError::InvalidStandings);
+            }
+            *seat = Standing {
+                wins,
+                losses,
+                left: byte & 0b100_0000 != 0,
+            };
+        }
+        Ok(standings)
+    }
+}
+
+/// a number of players that a tournament can be played with, serialized as a single byte.
+pub(crate) struct TournamentSize;
+
+impl FieldCodec<u8> for TournamentSize {
+    const SIZE: usize = 1;
+
+    fn write(value: &u8, buf: &mut [u8]) {
+        buf[0] = *value;
+    }
+
+    fn read(bytes: &[u8]) -> Result<u8, DeserializeMessageError> {
+        match bytes[0] {
+            players if is_valid_tournament_size(players) => Ok(players),
+            _ => Err(DeserializeMessageError::InvalidTournamentSize),
+        }
+    }
+}
+
+/// a number of players that a league can be played with, serialized as a single byte.
+pub(crate) struct LeagueSize;
+
+impl FieldCodec<u8> for LeagueSize {
+    const SIZE: usize = 1;
+
+    fn write(value: &u8, buf: &mut [u8]) {
+        buf[0] = *value;
+    }
+
+    fn read(bytes: &[u8]) -> Result<u8, DeserializeMessageError> {
+        match bytes[0] {
+            players if is_valid_league_size(players) => Ok(players),
+            _ => Err(DeserializeMessageError::InvalidTournamentSize),
+        }
+    }
+}
+
+/// a player's seat in a tournament, serialized as a single byte.
+pub(crate) struct Seat;
+
+impl FieldCodec<u8> for Seat {
+    const SIZE: usize = 1;
+
+    fn write(value: &u8, buf: &mut [u8]) {
+        buf[0] = *value;
+    }
+
+    fn read(bytes: &[u8]) -> Result<u8, DeserializeMessageError> {
+        match bytes[0] {
+            seat if seat < MAX_TOURNAMENT_PLAYERS => Ok(seat),
+            _ => Err(DeserializeMessageError::InvalidSeat),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tournament::{
+        is_valid_league_size, is_valid_tournament_size, league_opponent, league_rounds,
+        opponent_seats, rounds, Bracket, BracketSeat, Standing, Standings,
+    };
+
+    #[test]
+    fn tournament_sizes() {
+        let valid: Vec<_> = (0..=u8::MAX)
+            .filter(|&players| is_valid_tournament_size(players))
+            .collect();
+        assert_eq!(valid, [2, 4, 8]);
+        assert_eq!(rounds(2), 1);
+        assert_eq!(rounds(8), 3);
+    }
+
+    #[test]
+    fn opponents() {
+        assert_eq!(opponent_seats(0, 0), 1..2);
+        assert_eq!(opponent_seats(1, 0), 0..1);
+        assert_eq!(opponent_seats(5, 0), 4..5);
+        assert_eq!(opponent_seats(1, 1), 2..4);
+        assert_eq!(opponent_seats(6, 1), 4..6);
+        assert_eq!(opponent_seats(2, 2), 4..8);
+        assert_eq!(opponent_seats(7, 2), 0..4);
+    }
+
+    #[test]
+    fn winners() {
+        let mut bracket = Bracket::default();
+        assert_eq!(bracket.winner(0..1, 0), Some(0));
+        assert_eq!(bracket.winner(0..2, 1), None);
+        bracket.seats[1] = BracketSeat {
+            wins: 2,
+            eliminated: true,
+        };
+        bracket.seats[0].eliminated = true;
+        assert_eq!(bracket.winner(0..2, 1), Some(1));
+        assert_eq!(bracket.winner(0..4, 2), Some(1));
+        assert_eq!(bracket.winner(0..8, 3), None);
+    }
+
+    #[test]
+    fn league_sizes() {
+        let valid: Vec<_> = (0..=u8::MAX)
+            .filter(|&players| is_valid_league_size(players))
+            .collect();
+        assert_eq!(valid, [2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(league_rounds(2), 1);
+        assert_eq!(league_rounds(3), 3);
+        assert_eq!(league_rounds(8), 7);
+    }
+
+    #[test]
+    fn everyone_plays_everyone() {
+        for players in 2..=8 {
+            let mut played = vec![vec![0; players as usize]; players as usize];
+            for round in 0..league_rounds(players) {
+                let mut byes = 0;
+                for seat in 0..players {
+                    match league_opponent(seat, round, players) {
+                        Some(opponent) => {
+                            assert_ne!(opponent, seat);
+                            assert_eq!(league_opponent(opponent, round, players), Some(seat));
+                            played[seat as usize][opponent as usize] += 1;
+                        }
+                        None => byes += 1,
+                    }
+                }
+                assert_eq!(byes, players % 2);
+            }
+            for seat in 0..players {
+                for opponent in 0..players {
+                    let expected = if seat == opponent { 0 } else { 1 };
+                    assert_eq!(played[seat as usize][opponent as usize], expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn standings_table() {
+        let mut standings = Standings::default();
+        standings.seats[0] = Standing {
+            wins: 1,
+            losses: 2,
+            left: false,
+        };
+        standings.seats[1] = Standing {
+            wins: 2,
+            losses: 1,
+            left: false,
+        };
+        standings.seats[2] = Standing {
+            wins: 1,
+            losses: 1,
+            left: true,
+        };
+        standings.seats[4] = Standing {
+            wins: 3,
+            losses: 0,
+            left: false,
+        };
+        assert_eq!(standings.table(4)[..4], [1, 2, 0, 3]);
+        assert_eq!(standings.table(5)[..5], [4, 1, 2, 0, 3]);
+    }
+}
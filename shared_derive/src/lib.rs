@@ -0,0 +1,275 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse_macro_input, spanned::Spanned, Attribute, Data, DeriveInput, Expr, Fields, Ident, LitInt,
+    Type, Variant,
+};
+
+/// generates `WriteInto`, `From<_> for Vec<u8>` and `TryFrom<&[u8]>` impls for a protocol message
+/// enum, for use within the `shared` crate only.
+///
+/// the enum is given its state id and the largest size of any message sent from its side with
+/// `#[pong(state = 1, max_size = MAX_CLIENT_MESSAGE_SIZE)]`, and each variant its message id with
+/// `#[pong(id = 0)]`. the fields of a variant are serialized in order, each by the `FieldCodec`
+/// implemented by its type, or by the one named with `#[pong(codec = TickInterval)]`. trailing
+/// fields may be marked `#[pong(optional)]`, in which case they are left out by older peers and
+/// defaulted when missing. each optional field is only read if all the ones before it are present.
+#[proc_macro_derive(PongMessage, attributes(pong))]
+pub fn derive_pong_message(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct MessageAttrs {
+    state: LitInt,
+    max_size: Expr,
+}
+
+struct MessageField {
+    ident: Ident,
+    codec: TokenStream,
+    optional: bool,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new(
+            input.span(),
+            "PongMessage can only be derived for enums",
+        ));
+    };
+    let MessageAttrs { state, max_size } = parse_message_attrs(&input)?;
+    let name = &input.ident;
+    let mut write_arms = Vec::new();
+    let mut read_arms = Vec::new();
+    for variant in &data.variants {
+        let id = parse_variant_id(variant)?;
+        let fields = parse_fields(variant)?;
+        write_arms.push(expand_write_arm(variant, &state, &id, &fields));
+        read_arms.push(expand_read_arm(variant, &id, &fields));
+    }
+    Ok(quote! {
+        impl crate::WriteInto for #name {
+            fn write_into(&self, buf: &mut [u8]) -> usize {
+                match self {
+                    #(#write_arms)*
+                }
+            }
+        }
+
+        #[cfg(any(feature = "alloc", test))]
+        impl From<#name> for alloc::vec::Vec<u8> {
+            fn from(value: #name) -> Self {
+                crate::write_to_vec::<_, { #max_size }>(&value)
+            }
+        }
+
+        impl TryFrom<&[u8]> for #name {
+            type Error = crate::DeserializeMessageError;
+
+            fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+                match crate::validate_state_and_get_message_id(value, #state)? {
+                    #(#read_arms)*
+                    _ => Err(crate::unrecognised_message_variant(value)),
+                }
+            }
+        }
+    })
+}
+
+fn expand_write_arm(
+    variant: &Variant,
+    state: &LitInt,
+    id: &LitInt,
+    fields: &[MessageField],
+) -> TokenStream {
+    let variant_ident = &variant.ident;
+    let idents: Vec<_> = fields.iter().map(|field| &field.ident).collect();
+    let writes = fields.iter().map(|field| {
+        let MessageField { ident, codec, .. } = field;
+        quote! {
+            let size = #codec::size(#ident);
+            #codec::write(#ident, &mut buf[n..n + size]);
+            n += size;
+        }
+    });
+    quote! {
+        Self::#variant_ident { #(#idents),* } => {
+            buf[0] = (#state << 4) | #id;
+            #[allow(unused_mut)]
+            let mut n = 1;
+            #(#writes)*
+            n
+        }
+    }
+}
+
+fn expand_read_arm(variant: &Variant, id: &LitInt, fields: &[MessageField]) -> TokenStream {
+    let variant_ident = &variant.ident;
+    let idents: Vec<_> = fields.iter().map(|field| &field.ident).collect();
+    // the size of a field can depend on its first byte, so the expected length of the message is
+    // found by stepping through it.
+    let required_sizes = fields.iter().filter(|field| !field.optional).map(|field| {
+        let codec = &field.codec;
+        quote! {
+            len += #codec::read_size(value.get(len..).unwrap_or_default());
+        }
+    });
+    let optional_sizes = fields
+        .iter()
+        .filter(|field| field.optional)
+        .enumerate()
+        .map(|(i, field)| {
+            let codec = &field.codec;
+            let present = i + 1;
+            quote! {
+                full_len += #codec::read_size(value.get(full_len..).unwrap_or_default());
+                if value.len() == full_len {
+                    len = full_len;
+                    present = #present;
+                }
+            }
+        });
+    let validate = quote! {
+        #[allow(unused_mut)]
+        let mut len = 1;
+        #(#required_sizes)*
+        // peers that don't know of the trailing optional fields leave them out.
+        #[allow(unused_mut, unused_variables, unused_assignments)]
+        let mut present = 0;
+        #[allow(unused_mut, unused_variables)]
+        let mut full_len = len;
+        #(#optional_sizes)*
+        crate::validate_byte_count(value, len)?;
+    };
+    let mut optional_index = 0usize;
+    let reads: Vec<_> = fields
+        .iter()
+        .map(|field| {
+            let MessageField {
+                ident,
+                codec,
+                optional,
+            } = field;
+            let read = quote! {
+                let size = #codec::read_size(&value[n..]);
+                let #ident = #codec::read(&value[n..n + size])?;
+                n += size;
+            };
+            if *optional {
+                let index = optional_index;
+                optional_index += 1;
+                quote! {
+                    let #ident = if present > #index {
+                        #read
+                        #ident
+                    } else {
+                        Default::default()
+                    };
+                }
+            } else {
+                read
+            }
+        })
+        .collect();
+    quote! {
+        #id => {
+            #validate
+            #[allow(unused_mut, unused_variables, unused_assignments)]
+            let mut n = 1;
+            #(#reads)*
+            Ok(Self::#variant_ident { #(#idents),* })
+        }
+    }
+}
+
+fn parse_message_attrs(input: &DeriveInput) -> syn::Result<MessageAttrs> {
+    let mut state = None;
+    let mut max_size = None;
+    for attr in pong_attrs(&input.attrs) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("state") {
+                state = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("max_size") {
+                max_size = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `state` or `max_size`"))
+            }
+        })?;
+    }
+    match (state, max_size) {
+        (Some(state), Some(max_size)) => Ok(MessageAttrs { state, max_size }),
+        _ => Err(syn::Error::new(
+            input.ident.span(),
+            "expected `#[pong(state = .., max_size = ..)]`",
+        )),
+    }
+}
+
+fn parse_variant_id(variant: &Variant) -> syn::Result<LitInt> {
+    let mut id = None;
+    for attr in pong_attrs(&variant.attrs) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("id") {
+                id = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `id`"))
+            }
+        })?;
+    }
+    id.ok_or_else(|| syn::Error::new(variant.ident.span(), "expected `#[pong(id = ..)]`"))
+}
+
+fn parse_fields(variant: &Variant) -> syn::Result<Vec<MessageField>> {
+    let named = match &variant.fields {
+        Fields::Named(named) => named.named.iter().collect(),
+        Fields::Unit => Vec::new(),
+        Fields::Unnamed(_) => {
+            return Err(syn::Error::new(
+                variant.fields.span(),
+                "message fields must be named",
+            ))
+        }
+    };
+    let mut fields = Vec::new();
+    for field in named {
+        let mut codec: Option<Type> = None;
+        let mut optional = false;
+        for attr in pong_attrs(&field.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("codec") {
+                    codec = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("optional") {
+                    optional = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `codec` or `optional`"))
+                }
+            })?;
+        }
+        if !optional && fields.iter().any(|field: &MessageField| field.optional) {
+            return Err(syn::Error::new(
+                field.span(),
+                "optional fields must come after all the others",
+            ));
+        }
+        let ty = &field.ty;
+        let codec = codec.as_ref().unwrap_or(ty);
+        fields.push(MessageField {
+            ident: field.ident.clone().unwrap(),
+            codec: quote! { <#codec as crate::FieldCodec<#ty>> },
+            optional,
+        });
+    }
+    Ok(fields)
+}
+
+fn pong_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("pong"))
+}